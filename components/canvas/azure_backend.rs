@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::canvas_data::{default_font_style, ellipse_to_bezier_curves};
 use crate::canvas_data::{
     Backend, CanvasPaintState, Color, CompositionOp, DrawOptions, ExtendMode, Filter,
     GenericDrawTarget, GenericPathBuilder, GradientStop, GradientStops, Path, Pattern,
@@ -18,6 +19,7 @@ use canvas_traits::canvas::*;
 use cssparser::RGBA;
 use euclid::default::{Point2D, Rect, Size2D, Transform2D, Vector2D};
 
+use std::borrow::Cow;
 use std::marker::PhantomData;
 
 pub struct AzureBackend;
@@ -95,6 +97,12 @@ impl Backend for AzureBackend {
         ))
     }
 
+    /// Azure has no dash offset, so the dash list is rotated to start at the
+    /// offset instead.
+    fn line_dash(&self, dash: &[f32], offset: f32) -> (Vec<f32>, f32) {
+        (offset_line_dash(dash, offset), 0.)
+    }
+
     fn recreate_paint_state<'a>(&self, state: &CanvasPaintState<'a>) -> CanvasPaintState<'a> {
         CanvasPaintState::new(AntialiasMode::from_azure(
             state.draw_options.as_azure().antialias,
@@ -125,16 +133,259 @@ impl<'a> CanvasPaintState<'a> {
                 10.0,
                 &[],
             )),
+            line_dash: vec![],
+            line_dash_offset: 0.0,
             transform: Transform2D::identity(),
             shadow_offset_x: 0.0,
             shadow_offset_y: 0.0,
             shadow_blur: 0.0,
             shadow_color: Color::Azure(azure_hl::Color::transparent()),
+            font_style: servo_arc::Arc::new(default_font_style()),
+            filters: vec![],
+        }
+    }
+}
+
+/// Returns a dash list that draws, from its start, the dashes that `dash`
+/// draws from `offset`. When the offset falls in a gap, the list starts with
+/// an empty dash, which round and square caps still draw as a dot.
+fn offset_line_dash(dash: &[f32], offset: f32) -> Vec<f32> {
+    // An odd dash list is repeated to make it even.
+    let dash: Vec<f32> = if dash.len() % 2 == 1 {
+        dash.iter().chain(dash.iter()).cloned().collect()
+    } else {
+        dash.to_vec()
+    };
+    let length: f32 = dash.iter().sum();
+    if offset == 0. || !(length > 0.) {
+        return dash;
+    }
+    let mut phase = offset.rem_euclid(length);
+    let mut index = 0;
+    while index + 1 < dash.len() && phase >= dash[index] {
+        phase -= dash[index];
+        index += 1;
+    }
+    let mut rotated = Vec::with_capacity(dash.len() + 2);
+    if index % 2 == 1 {
+        rotated.push(0.);
+    }
+    rotated.push(dash[index] - phase);
+    rotated.extend_from_slice(&dash[index + 1..]);
+    rotated.extend_from_slice(&dash[..index]);
+    rotated.push(phase);
+    if rotated.len() % 2 == 1 {
+        rotated.push(0.);
+    }
+    rotated
+}
+
+/// An Azure path, along with the subpaths it was built from.
+///
+/// Azure always fills paths with the nonzero winding rule, so the subpaths
+/// are used to build an equivalent path when the even-odd rule is asked for.
+#[derive(Clone)]
+pub struct AzurePath {
+    path: azure_hl::Path,
+    subpaths: Vec<Subpath>,
+    fill_rule: FillRule,
+}
+
+impl AzurePath {
+    /// Returns the path Azure should fill to fill this one with its fill rule.
+    fn to_fill(&self, draw_target: &DrawTarget) -> Cow<azure_hl::Path> {
+        match self.fill_rule {
+            FillRule::Nonzero => Cow::Borrowed(&self.path),
+            FillRule::Evenodd => Cow::Owned(self.to_nonzero_even_odd(draw_target)),
+        }
+    }
+
+    /// Builds a path whose nonzero fill is the even-odd fill of this one, by
+    /// orienting each subpath the other way from the subpaths it's nested in.
+    /// This is exact as long as the subpaths don't intersect each other.
+    fn to_nonzero_even_odd(&self, draw_target: &DrawTarget) -> azure_hl::Path {
+        let polygons: Vec<Vec<Point2D<f32>>> = self.subpaths.iter().map(Subpath::flatten).collect();
+        let builder = draw_target.create_path_builder();
+        for (i, subpath) in self.subpaths.iter().enumerate() {
+            if subpath.curves.is_empty() {
+                continue;
+            }
+            let depth = polygons
+                .iter()
+                .enumerate()
+                .filter(|&(j, polygon)| j != i && crossings(polygon, &subpath.start) % 2 == 1)
+                .count();
+            let reversed = (signed_area(&polygons[i]) > 0.) != (depth % 2 == 0);
+            subpath.replay(&builder, reversed);
+        }
+        builder.finish()
+    }
+
+    fn contains_point_even_odd(&self, x: f64, y: f64, path_transform: &Transform2D<f32>) -> bool {
+        let point = Point2D::new(x as f32, y as f32);
+        let crossings: usize = self
+            .subpaths
+            .iter()
+            .map(|subpath| {
+                let polygon: Vec<_> = subpath
+                    .flatten()
+                    .iter()
+                    .map(|vertex| path_transform.transform_point(*vertex))
+                    .collect();
+                crossings(&polygon, &point)
+            })
+            .sum();
+        crossings % 2 == 1
+    }
+}
+
+/// A subpath, with all of its segments as cubic bezier curves.
+#[derive(Clone)]
+struct Subpath {
+    start: Point2D<f32>,
+    curves: Vec<[Point2D<f32>; 3]>,
+}
+
+impl Subpath {
+    fn new(start: Point2D<f32>) -> Subpath {
+        Subpath {
+            start,
+            curves: vec![],
+        }
+    }
+
+    fn end(&self) -> Point2D<f32> {
+        self.curves.last().map_or(self.start, |curve| curve[2])
+    }
+
+    fn transformed(&self, transform: &Transform2D<f32>) -> Subpath {
+        Subpath {
+            start: transform.transform_point(self.start),
+            curves: self
+                .curves
+                .iter()
+                .map(|curve| {
+                    [
+                        transform.transform_point(curve[0]),
+                        transform.transform_point(curve[1]),
+                        transform.transform_point(curve[2]),
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// Approximates the subpath, closed, with a polygon.
+    fn flatten(&self) -> Vec<Point2D<f32>> {
+        const STEPS: usize = 16;
+        let mut polygon = vec![self.start];
+        let mut current = self.start;
+        for curve in &self.curves {
+            if curve[0] == current && curve[1] == curve[2] {
+                polygon.push(curve[2]);
+            } else {
+                for step in 1..=STEPS {
+                    let t = step as f32 / STEPS as f32;
+                    let u = 1. - t;
+                    let point = current.to_vector() * (u * u * u) +
+                        curve[0].to_vector() * (3. * u * u * t) +
+                        curve[1].to_vector() * (3. * u * t * t) +
+                        curve[2].to_vector() * (t * t * t);
+                    polygon.push(point.to_point());
+                }
+            }
+            current = curve[2];
+        }
+        polygon
+    }
+
+    /// Adds the subpath, closed, to the builder, reversing its direction if
+    /// asked to.
+    fn replay(&self, builder: &azure_hl::PathBuilder, reversed: bool) {
+        if !reversed {
+            builder.move_to(self.start);
+            for curve in &self.curves {
+                builder.bezier_curve_to(&curve[0], &curve[1], &curve[2]);
+            }
+        } else {
+            builder.move_to(self.start);
+            builder.line_to(self.end());
+            for (i, curve) in self.curves.iter().enumerate().rev() {
+                let end = if i == 0 {
+                    self.start
+                } else {
+                    self.curves[i - 1][2]
+                };
+                builder.bezier_curve_to(&curve[1], &curve[0], &end);
+            }
+        }
+        builder.close();
+    }
+}
+
+/// Twice the signed area of a polygon, positive when it's clockwise in the
+/// canvas coordinate space.
+fn signed_area(polygon: &[Point2D<f32>]) -> f32 {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum()
+}
+
+/// The number of edges of a closed polygon that a ray going right from the
+/// point crosses.
+fn crossings(polygon: &[Point2D<f32>], point: &Point2D<f32>) -> usize {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .filter(|&(a, b)| {
+            (a.y > point.y) != (b.y > point.y) &&
+                point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        })
+        .count()
+}
+
+/// An Azure path builder that also records the subpaths it builds.
+pub struct PathBuilder {
+    builder: azure_hl::PathBuilder,
+    subpaths: Vec<Subpath>,
+}
+
+impl PathBuilder {
+    fn new(builder: azure_hl::PathBuilder, subpaths: Vec<Subpath>) -> PathBuilder {
+        PathBuilder { builder, subpaths }
+    }
+
+    /// Records a line to the point, which starts a subpath if there is none.
+    fn record_line_to(&mut self, point: Point2D<f32>) {
+        match self.subpaths.last_mut() {
+            Some(subpath) => {
+                let end = subpath.end();
+                subpath.curves.push([end, point, point]);
+            },
+            None => self.subpaths.push(Subpath::new(point)),
+        }
+    }
+
+    fn record_curve(&mut self, curve: [Point2D<f32>; 3]) {
+        if self.subpaths.is_empty() {
+            self.subpaths.push(Subpath::new(curve[0]));
+        }
+        if let Some(subpath) = self.subpaths.last_mut() {
+            subpath.curves.push(curve);
+        }
+    }
+
+    fn record_ellipse(&mut self, start: Point2D<f32>, curves: Vec<[Point2D<f32>; 3]>) {
+        self.record_line_to(start);
+        for curve in curves {
+            self.record_curve(curve);
         }
     }
 }
 
-impl GenericPathBuilder for azure_hl::PathBuilder {
+impl GenericPathBuilder for PathBuilder {
     fn arc(
         &mut self,
         origin: Point2D<f32>,
@@ -144,13 +395,23 @@ impl GenericPathBuilder for azure_hl::PathBuilder {
         anticlockwise: bool,
     ) {
         azure_hl::PathBuilder::arc(
-            self,
+            &self.builder,
             origin as Point2D<AzFloat>,
             radius as AzFloat,
             start_angle as AzFloat,
             end_angle as AzFloat,
             anticlockwise,
         );
+        let (start, curves) = ellipse_to_bezier_curves(
+            origin,
+            radius,
+            radius,
+            0.,
+            start_angle,
+            end_angle,
+            anticlockwise,
+        );
+        self.record_ellipse(start, curves);
     }
     fn bezier_curve_to(
         &mut self,
@@ -159,14 +420,18 @@ impl GenericPathBuilder for azure_hl::PathBuilder {
         control_point3: &Point2D<f32>,
     ) {
         azure_hl::PathBuilder::bezier_curve_to(
-            self,
+            &self.builder,
             control_point1 as &Point2D<AzFloat>,
             control_point2 as &Point2D<AzFloat>,
             control_point3 as &Point2D<AzFloat>,
         );
+        self.record_curve([*control_point1, *control_point2, *control_point3]);
     }
     fn close(&mut self) {
-        azure_hl::PathBuilder::close(self);
+        azure_hl::PathBuilder::close(&self.builder);
+        if let Some(start) = self.subpaths.last().map(|subpath| subpath.start) {
+            self.subpaths.push(Subpath::new(start));
+        }
     }
     fn ellipse(
         &mut self,
@@ -179,7 +444,7 @@ impl GenericPathBuilder for azure_hl::PathBuilder {
         anticlockwise: bool,
     ) {
         azure_hl::PathBuilder::ellipse(
-            self,
+            &self.builder,
             origin as Point2D<AzFloat>,
             radius_x as AzFloat,
             radius_y as AzFloat,
@@ -188,26 +453,58 @@ impl GenericPathBuilder for azure_hl::PathBuilder {
             end_angle as AzFloat,
             anticlockwise,
         );
+        let (start, curves) = ellipse_to_bezier_curves(
+            origin,
+            radius_x,
+            radius_y,
+            rotation_angle,
+            start_angle,
+            end_angle,
+            anticlockwise,
+        );
+        self.record_ellipse(start, curves);
     }
     fn get_current_point(&mut self) -> Point2D<f32> {
-        let AzPoint { x, y } = azure_hl::PathBuilder::get_current_point(self);
+        let AzPoint { x, y } = azure_hl::PathBuilder::get_current_point(&self.builder);
         Point2D::new(x as f32, y as f32)
     }
     fn line_to(&mut self, point: Point2D<f32>) {
-        azure_hl::PathBuilder::line_to(self, point as Point2D<AzFloat>);
+        azure_hl::PathBuilder::line_to(&self.builder, point as Point2D<AzFloat>);
+        self.record_line_to(point);
     }
     fn move_to(&mut self, point: Point2D<f32>) {
-        azure_hl::PathBuilder::move_to(self, point as Point2D<AzFloat>);
+        azure_hl::PathBuilder::move_to(&self.builder, point as Point2D<AzFloat>);
+        if self
+            .subpaths
+            .last()
+            .map_or(false, |subpath| subpath.curves.is_empty())
+        {
+            self.subpaths.pop();
+        }
+        self.subpaths.push(Subpath::new(point));
     }
     fn quadratic_curve_to(&mut self, control_point: &Point2D<f32>, end_point: &Point2D<f32>) {
         azure_hl::PathBuilder::quadratic_curve_to(
-            self,
+            &self.builder,
             control_point as &Point2D<AzFloat>,
             end_point as &Point2D<AzFloat>,
         );
+        let start = self
+            .subpaths
+            .last()
+            .map_or(*control_point, |subpath| subpath.end());
+        self.record_curve([
+            start.lerp(*control_point, 2. / 3.),
+            end_point.lerp(*control_point, 2. / 3.),
+            *end_point,
+        ]);
     }
     fn finish(&mut self) -> Path {
-        Path::Azure(azure_hl::PathBuilder::finish(self))
+        Path::Azure(AzurePath {
+            path: azure_hl::PathBuilder::finish(&self.builder),
+            subpaths: self.subpaths.clone(),
+            fill_rule: FillRule::Nonzero,
+        })
     }
 }
 
@@ -236,7 +533,7 @@ impl GenericDrawTarget for azure_hl::DrawTarget {
     }
 
     fn create_path_builder(&self) -> Box<dyn GenericPathBuilder> {
-        Box::new(self.create_path_builder())
+        Box::new(PathBuilder::new(self.create_path_builder(), vec![]))
     }
 
     fn create_similar_draw_target(
@@ -279,7 +576,7 @@ impl GenericDrawTarget for azure_hl::DrawTarget {
         );
     }
     fn draw_surface_with_shadow(
-        &self,
+        &mut self,
         surface: SourceSurface,
         dest: &Point2D<f32>,
         color: &Color,
@@ -287,7 +584,8 @@ impl GenericDrawTarget for azure_hl::DrawTarget {
         sigma: f32,
         operator: CompositionOp,
     ) {
-        self.draw_surface_with_shadow(
+        azure_hl::DrawTarget::draw_surface_with_shadow(
+            self,
             surface.into_azure(),
             dest as &Point2D<AzFloat>,
             color.as_azure(),
@@ -297,9 +595,10 @@ impl GenericDrawTarget for azure_hl::DrawTarget {
         );
     }
     fn fill(&mut self, path: &Path, pattern: Pattern, draw_options: &DrawOptions) {
+        let path = path.as_azure().to_fill(self);
         azure_hl::DrawTarget::fill(
             self,
-            path.as_azure(),
+            &path,
             pattern.as_azure().to_pattern_ref(),
             draw_options.as_azure(),
         );
@@ -331,7 +630,8 @@ impl GenericDrawTarget for azure_hl::DrawTarget {
         azure_hl::DrawTarget::pop_clip(self);
     }
     fn push_clip(&mut self, path: &Path) {
-        azure_hl::DrawTarget::push_clip(self, path.as_azure());
+        let path = path.as_azure().to_fill(self);
+        azure_hl::DrawTarget::push_clip(self, &path);
     }
     fn set_transform(&mut self, matrix: &Transform2D<f32>) {
         azure_hl::DrawTarget::set_transform(self, matrix as &Transform2D<AzFloat>);
@@ -348,7 +648,7 @@ impl GenericDrawTarget for azure_hl::DrawTarget {
     ) {
         azure_hl::DrawTarget::stroke(
             self,
-            path.as_azure(),
+            &path.as_azure().path,
             pattern.as_azure().to_pattern_ref(),
             stroke_options.as_azure(),
             draw_options.as_azure(),
@@ -486,7 +786,7 @@ impl SourceSurface {
 }
 
 impl Path {
-    fn as_azure(&self) -> &azure_hl::Path {
+    fn as_azure(&self) -> &AzurePath {
         match self {
             Path::Azure(p) => p,
         }
@@ -545,6 +845,19 @@ impl<'a> StrokeOptions<'a> {
             StrokeOptions::Azure(options) => options.line_cap = val.to_azure_style(),
         }
     }
+    /// Azure has no support for a dash offset, so the dash list must already
+    /// have been offset with `Backend::line_dash`.
+    pub fn with_line_dash<'b>(&self, dash: &'b [f32], _offset: f32) -> StrokeOptions<'b> {
+        match self {
+            StrokeOptions::Azure(options) => StrokeOptions::Azure(azure_hl::StrokeOptions::new(
+                options.line_width,
+                options.line_join,
+                options.line_cap,
+                options.miter_limit,
+                dash,
+            )),
+        }
+    }
 }
 
 pub trait ToAzureStyle {
@@ -772,14 +1085,35 @@ impl Path {
         &self,
         transform: &Transform2D<f32>,
     ) -> Box<dyn GenericPathBuilder> {
-        Box::new(self.as_azure().transformed_copy_to_builder(transform))
+        let path = self.as_azure();
+        Box::new(PathBuilder::new(
+            path.path.transformed_copy_to_builder(transform),
+            path.subpaths
+                .iter()
+                .map(|subpath| subpath.transformed(transform))
+                .collect(),
+        ))
     }
 
     pub fn contains_point(&self, x: f64, y: f64, path_transform: &Transform2D<f32>) -> bool {
-        self.as_azure().contains_point(x, y, path_transform)
+        let path = self.as_azure();
+        match path.fill_rule {
+            FillRule::Nonzero => path.path.contains_point(x, y, path_transform),
+            FillRule::Evenodd => path.contains_point_even_odd(x, y, path_transform),
+        }
     }
 
     pub fn copy_to_builder(&self) -> Box<dyn GenericPathBuilder> {
-        Box::new(self.as_azure().copy_to_builder())
+        let path = self.as_azure();
+        Box::new(PathBuilder::new(
+            path.path.copy_to_builder(),
+            path.subpaths.clone(),
+        ))
+    }
+
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) {
+        match self {
+            Path::Azure(path) => path.fill_rule = fill_rule,
+        }
     }
 }
@@ -3,15 +3,38 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::canvas_paint_thread::AntialiasMode;
+use crate::filters::{apply_filters, FilterOperation};
+use app_units::Au;
 use canvas_traits::canvas::*;
-use cssparser::RGBA;
+use cssparser::{Parser, ParserInput, RGBA};
 use euclid::default::{Point2D, Rect, Size2D, Transform2D, Vector2D};
+use euclid::Scale;
+use gfx::font::FontRef;
+use gfx::font_cache_thread::FontCacheThread;
+use gfx::font_context::FontContext;
+use gfx::text::glyph::GlyphId;
 use ipc_channel::ipc::{IpcSender, IpcSharedMemory};
 use num_traits::ToPrimitive;
+use std::f32::consts::{FRAC_PI_2, PI};
 #[allow(unused_imports)]
 use std::marker::PhantomData;
 use std::mem;
+use std::rc::Rc;
 use std::sync::Arc;
+use style::context::QuirksMode;
+use style::media_queries::{Device, MediaType};
+use style::parser::ParserContext;
+use style::properties::longhands::filter;
+use style::properties::longhands::font_variant_caps::computed_value::T as FontVariantCaps;
+use style::properties::shorthands::font;
+use style::properties::style_structs::Font as FontStyleStruct;
+use style::properties::ComputedValues;
+use style::stylesheets::{CssRuleType, Origin, UrlExtraData};
+use style::values::computed::font::{FontFamily, FontFamilyList, FontSize, FontStretch};
+use style::values::computed::font::{FontWeight, GenericFontFamily, SingleFontFamily};
+use style::values::computed::{Context, ToComputedValue};
+use style::values::generics::font::FontStyle;
+use style_traits::{ParseError, ParsingMode};
 use webrender::api::DirtyRect;
 use webrender_api::units::RectExt;
 
@@ -76,6 +99,8 @@ pub trait Backend {
         state: &mut CanvasPaintState<'a>,
     );
     fn create_drawtarget(&self, size: Size2D<u64>) -> Box<dyn GenericDrawTarget>;
+    /// Returns the dash list and offset to stroke with for the given ones.
+    fn line_dash(&self, dash: &[f32], offset: f32) -> (Vec<f32>, f32);
     fn recreate_paint_state<'a>(&self, state: &CanvasPaintState<'a>) -> CanvasPaintState<'a>;
     fn size_from_pattern(&self, rect: &Rect<f32>, pattern: &Pattern) -> Option<Size2D<f32>>;
 }
@@ -200,6 +225,106 @@ impl<'a> PathBuilderRef<'a> {
         );
     }
 
+    fn arc_to(&mut self, cp1: &Point2D<f32>, cp2: &Point2D<f32>, radius: f32) {
+        let cp0 = match self.current_point() {
+            Some(p) => p,
+            None => return,
+        };
+        let cp1 = *cp1;
+        let cp2 = *cp2;
+
+        if (cp0.x == cp1.x && cp0.y == cp1.y) || cp1 == cp2 || radius == 0.0 {
+            self.line_to(&cp1);
+            return;
+        }
+
+        // if all three control points lie on a single straight line,
+        // connect the first two by a straight line
+        let direction = (cp2.x - cp1.x) * (cp0.y - cp1.y) + (cp2.y - cp1.y) * (cp1.x - cp0.x);
+        if direction == 0.0 {
+            self.line_to(&cp1);
+            return;
+        }
+
+        // otherwise, draw the Arc
+        let a2 = (cp0.x - cp1.x).powi(2) + (cp0.y - cp1.y).powi(2);
+        let b2 = (cp1.x - cp2.x).powi(2) + (cp1.y - cp2.y).powi(2);
+        let d = {
+            let c2 = (cp0.x - cp2.x).powi(2) + (cp0.y - cp2.y).powi(2);
+            let cosx = (a2 + b2 - c2) / (2.0 * (a2 * b2).sqrt());
+            let sinx = (1.0 - cosx.powi(2)).sqrt();
+            radius / ((1.0 - cosx) / sinx)
+        };
+
+        // first tangent point
+        let anx = (cp1.x - cp0.x) / a2.sqrt();
+        let any = (cp1.y - cp0.y) / a2.sqrt();
+        let tp1 = Point2D::new(cp1.x - anx * d, cp1.y - any * d);
+
+        // second tangent point
+        let bnx = (cp1.x - cp2.x) / b2.sqrt();
+        let bny = (cp1.y - cp2.y) / b2.sqrt();
+        let tp2 = Point2D::new(cp1.x - bnx * d, cp1.y - bny * d);
+
+        // arc center and angles
+        let anticlockwise = direction < 0.0;
+        let cx = tp1.x + any * radius * if anticlockwise { 1.0 } else { -1.0 };
+        let cy = tp1.y - anx * radius * if anticlockwise { 1.0 } else { -1.0 };
+        let angle_start = (tp1.y - cy).atan2(tp1.x - cx);
+        let angle_end = (tp2.y - cy).atan2(tp2.x - cx);
+
+        self.line_to(&tp1);
+        if [cx, cy, angle_start, angle_end]
+            .iter()
+            .all(|x| x.is_finite())
+        {
+            self.arc(
+                &Point2D::new(cx, cy),
+                radius,
+                angle_start,
+                angle_end,
+                anticlockwise,
+            );
+        }
+    }
+
+    /// Appends a segment of a `Path2D` object to this path.
+    fn add_segment(&mut self, segment: &PathSegment) {
+        match *segment {
+            PathSegment::ClosePath => self.builder.close(),
+            PathSegment::MoveTo(ref point) => self.move_to(point),
+            PathSegment::LineTo(ref point) => self.line_to(point),
+            PathSegment::QuadraticCurveTo(ref cp, ref endpoint) => {
+                self.quadratic_curve_to(cp, endpoint)
+            },
+            PathSegment::BezierCurveTo(ref cp1, ref cp2, ref endpoint) => {
+                self.bezier_curve_to(cp1, cp2, endpoint)
+            },
+            PathSegment::ArcTo(ref cp1, ref cp2, radius) => self.arc_to(cp1, cp2, radius),
+            PathSegment::Arc(ref center, radius, start_angle, end_angle, ccw) => {
+                self.arc(center, radius, start_angle, end_angle, ccw)
+            },
+            PathSegment::Ellipse(
+                ref center,
+                radius_x,
+                radius_y,
+                rotation_angle,
+                start_angle,
+                end_angle,
+                ccw,
+            ) => self.ellipse(
+                center,
+                radius_x,
+                radius_y,
+                rotation_angle,
+                start_angle,
+                end_angle,
+                ccw,
+            ),
+            PathSegment::Rect(ref rect) => self.rect(rect),
+        }
+    }
+
     fn current_point(&mut self) -> Option<Point2D<f32>> {
         let inverse = match self.transform.inverse() {
             Some(i) => i,
@@ -247,7 +372,7 @@ pub trait GenericDrawTarget {
         draw_options: &DrawOptions,
     );
     fn draw_surface_with_shadow(
-        &self,
+        &mut self,
         surface: SourceSurface,
         dest: &Point2D<f32>,
         color: &Color,
@@ -340,13 +465,13 @@ pub enum SourceSurface {
     #[cfg(feature = "canvas2d-azure")]
     Azure(azure::azure_hl::SourceSurface),
     #[cfg(feature = "canvas2d-raqote")]
-    Raqote(Vec<u8>), // TODO: See if we can avoid the alloc (probably?)
+    Raqote(Vec<u32>, Size2D<i32>), // TODO: See if we can avoid the alloc (probably?)
 }
 
 #[derive(Clone)]
 pub enum Path {
     #[cfg(feature = "canvas2d-azure")]
-    Azure(azure::azure_hl::Path),
+    Azure(crate::azure_backend::AzurePath),
     #[cfg(feature = "canvas2d-raqote")]
     Raqote(raqote::Path),
 }
@@ -356,7 +481,7 @@ pub enum Pattern<'a> {
     #[cfg(feature = "canvas2d-azure")]
     Azure(azure::azure_hl::Pattern, PhantomData<&'a ()>),
     #[cfg(feature = "canvas2d-raqote")]
-    Raqote(raqote::Source<'a>),
+    Raqote(crate::raqote_backend::Pattern, PhantomData<&'a ()>),
 }
 
 pub enum DrawSurfaceOptions {
@@ -454,28 +579,29 @@ impl<'a> CanvasData<'a> {
             image_data.into()
         };
 
-        let draw_options = self.state.draw_options.clone();
-        let writer = |draw_target: &mut dyn GenericDrawTarget| {
+        let writer = |draw_target: &mut dyn GenericDrawTarget, draw_options: &DrawOptions| {
             write_image(
                 draw_target,
                 image_data,
                 source_rect.size,
                 dest_rect,
                 smoothing_enabled,
-                &draw_options,
+                draw_options,
             );
         };
 
-        if self.need_to_draw_shadow() {
+        if !self.state.filters.is_empty() {
+            self.draw_with_filter(writer);
+        } else if self.need_to_draw_shadow() {
             let rect = Rect::new(
                 Point2D::new(dest_rect.origin.x as f32, dest_rect.origin.y as f32),
                 Size2D::new(dest_rect.size.width as f32, dest_rect.size.height as f32),
             );
 
-            // TODO(pylbrecht) pass another closure for raqote
-            self.draw_with_shadow(&rect, writer);
+            let draw_options = self.state.draw_options.clone();
+            self.draw_with_shadow(&rect, |draw_target| writer(draw_target, &draw_options));
         } else {
-            writer(&mut *self.drawtarget);
+            writer(&mut *self.drawtarget, &self.state.draw_options);
         }
     }
 
@@ -498,6 +624,83 @@ impl<'a> CanvasData<'a> {
         );
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-measuretext
+    pub fn measure_text(
+        &self,
+        font_context: &mut FontContext<FontCacheThread>,
+        text: &str,
+    ) -> TextMetrics {
+        let font_group = font_context.font_group(self.state.font_style.clone());
+        let mut font_group = font_group.borrow_mut();
+        let first_font = match font_group.first(font_context) {
+            Some(font) => font,
+            None => {
+                warn!("No font available to measure canvas text.");
+                return TextMetrics::default();
+            },
+        };
+
+        let mut width = 0.;
+        let (mut ascent, mut descent) = {
+            let font = first_font.borrow();
+            (font.metrics.ascent, font.metrics.descent)
+        };
+        let mut previous_glyph: Option<(FontRef, GlyphId)> = None;
+        for codepoint in text.chars() {
+            // Step 2: ASCII whitespace is replaced by spaces.
+            let codepoint = if codepoint.is_ascii_whitespace() {
+                ' '
+            } else {
+                codepoint
+            };
+            let font_ref = match font_group.find_by_codepoint(font_context, codepoint) {
+                Some(font) => font,
+                None => continue,
+            };
+            let font = font_ref.borrow();
+            let glyph = match font.glyph_index(codepoint) {
+                Some(glyph) => glyph,
+                None => continue,
+            };
+            if let Some((ref previous_font, previous_glyph)) = previous_glyph {
+                if Rc::ptr_eq(previous_font, &font_ref) {
+                    width += font.glyph_h_kerning(previous_glyph, glyph);
+                }
+            }
+            width += font.glyph_h_advance(glyph);
+            ascent = ascent.max(font.metrics.ascent);
+            descent = descent.max(font.metrics.descent);
+            drop(font);
+            previous_glyph = Some((font_ref, glyph));
+        }
+
+        // The glyph outlines aren't available here, so the actual bounding box is
+        // approximated by the advance and the metrics of the fonts that were used.
+        let font = first_font.borrow();
+        let font_ascent = font.metrics.ascent.to_f64_px();
+        let font_descent = font.metrics.descent.to_f64_px();
+        let em_size = font.metrics.em_size.to_f64_px();
+        let em_height_ascent = if font_ascent + font_descent > 0. {
+            em_size * font_ascent / (font_ascent + font_descent)
+        } else {
+            em_size
+        };
+        TextMetrics {
+            width,
+            actual_bounding_box_left: 0.,
+            actual_bounding_box_right: width,
+            font_bounding_box_ascent: font_ascent,
+            font_bounding_box_descent: font_descent,
+            actual_bounding_box_ascent: ascent.to_f64_px(),
+            actual_bounding_box_descent: descent.to_f64_px(),
+            em_height_ascent,
+            em_height_descent: em_size - em_height_ascent,
+            hanging_baseline: font_ascent,
+            alphabetic_baseline: 0.,
+            ideographic_baseline: -font_descent,
+        }
+    }
+
     pub fn fill_rect(&mut self, rect: &Rect<f32>) {
         if self.state.fill_style.is_zero_size_gradient() {
             return; // Paint nothing if gradient size is zero.
@@ -510,13 +713,16 @@ impl<'a> CanvasData<'a> {
                 .unwrap_or(rect.size),
         );
 
-        if self.need_to_draw_shadow() {
+        if !self.state.filters.is_empty() {
+            let fill_style = self.state.fill_style.clone();
+            self.draw_with_filter(|draw_target, draw_options| {
+                draw_target.fill_rect(&draw_rect, fill_style, Some(draw_options));
+            });
+        } else if self.need_to_draw_shadow() {
+            let fill_style = self.state.fill_style.clone();
+            let draw_options = self.state.draw_options.clone();
             self.draw_with_shadow(&draw_rect, |new_draw_target: &mut dyn GenericDrawTarget| {
-                new_draw_target.fill_rect(
-                    &draw_rect,
-                    self.state.fill_style.clone(),
-                    Some(&self.state.draw_options),
-                );
+                new_draw_target.fill_rect(&draw_rect, fill_style, Some(&draw_options));
             });
         } else {
             self.drawtarget.fill_rect(
@@ -536,17 +742,26 @@ impl<'a> CanvasData<'a> {
             return; // Paint nothing if gradient size is zero.
         }
 
-        if self.need_to_draw_shadow() {
+        let (line_dash, line_dash_offset) = self
+            .backend
+            .line_dash(&self.state.line_dash, self.state.line_dash_offset);
+        let stroke_opts = self
+            .state
+            .stroke_opts
+            .with_line_dash(&line_dash, line_dash_offset);
+        if !self.state.filters.is_empty() {
+            let stroke_style = self.state.stroke_style.clone();
+            self.draw_with_filter(|draw_target, draw_options| {
+                draw_target.stroke_rect(rect, stroke_style, &stroke_opts, draw_options);
+            });
+        } else if self.need_to_draw_shadow() {
+            let stroke_style = self.state.stroke_style.clone();
+            let draw_options = self.state.draw_options.clone();
             self.draw_with_shadow(&rect, |new_draw_target: &mut dyn GenericDrawTarget| {
-                new_draw_target.stroke_rect(
-                    rect,
-                    self.state.stroke_style.clone(),
-                    &self.state.stroke_opts,
-                    &self.state.draw_options,
-                );
+                new_draw_target.stroke_rect(rect, stroke_style, &stroke_opts, &draw_options);
             });
         } else if rect.size.width == 0. || rect.size.height == 0. {
-            let mut stroke_opts = self.state.stroke_opts.clone();
+            let mut stroke_opts = stroke_opts.clone();
             stroke_opts.set_line_cap(LineCapStyle::Butt);
             self.drawtarget.stroke_line(
                 rect.origin,
@@ -559,7 +774,7 @@ impl<'a> CanvasData<'a> {
             self.drawtarget.stroke_rect(
                 rect,
                 self.state.stroke_style.clone(),
-                &self.state.stroke_opts,
+                &stroke_opts,
                 &self.state.draw_options,
             );
         }
@@ -639,14 +854,55 @@ impl<'a> CanvasData<'a> {
             .path()
     }
 
-    pub fn fill(&mut self) {
+    /// Builds a user-space path out of the segments of a `Path2D` object,
+    /// leaving the current default path untouched.
+    fn path_from_segments(&self, segments: &[PathSegment], fill_rule: FillRule) -> Path {
+        let mut builder = self.drawtarget.create_path_builder();
+        {
+            let mut path_builder = PathBuilderRef {
+                builder: &mut builder,
+                transform: Transform2D::identity(),
+            };
+            for segment in segments {
+                path_builder.add_segment(segment);
+            }
+        }
+        let mut path = builder.finish();
+        path.set_fill_rule(fill_rule);
+        path
+    }
+
+    pub fn fill(&mut self, fill_rule: FillRule) {
         if self.state.fill_style.is_zero_size_gradient() {
             return; // Paint nothing if gradient size is zero.
         }
 
         self.ensure_path();
+        let mut path = self.path().clone();
+        path.set_fill_rule(fill_rule);
+        self.fill_path(&path);
+    }
+
+    pub fn fill_path_segments(&mut self, segments: &[PathSegment], fill_rule: FillRule) {
+        if self.state.fill_style.is_zero_size_gradient() {
+            return; // Paint nothing if gradient size is zero.
+        }
+
+        let path = self.path_from_segments(segments, fill_rule);
+        self.fill_path(&path);
+    }
+
+    fn fill_path(&mut self, path: &Path) {
+        if !self.state.filters.is_empty() {
+            let fill_style = self.state.fill_style.clone();
+            self.draw_with_filter(|draw_target, draw_options| {
+                draw_target.fill(path, fill_style, draw_options);
+            });
+            return;
+        }
+
         self.drawtarget.fill(
-            &self.path().clone(),
+            path,
             self.state.fill_style.clone(),
             &self.state.draw_options,
         );
@@ -658,32 +914,63 @@ impl<'a> CanvasData<'a> {
         }
 
         self.ensure_path();
+        let path = self.path().clone();
+        self.stroke_path(&path);
+    }
+
+    pub fn stroke_path_segments(&mut self, segments: &[PathSegment]) {
+        if self.state.stroke_style.is_zero_size_gradient() {
+            return; // Paint nothing if gradient size is zero.
+        }
+
+        let path = self.path_from_segments(segments, FillRule::Nonzero);
+        self.stroke_path(&path);
+    }
+
+    fn stroke_path(&mut self, path: &Path) {
+        let (line_dash, line_dash_offset) = self
+            .backend
+            .line_dash(&self.state.line_dash, self.state.line_dash_offset);
+        let stroke_opts = self
+            .state
+            .stroke_opts
+            .with_line_dash(&line_dash, line_dash_offset);
+        if !self.state.filters.is_empty() {
+            let stroke_style = self.state.stroke_style.clone();
+            self.draw_with_filter(|draw_target, draw_options| {
+                draw_target.stroke(path, stroke_style, &stroke_opts, draw_options);
+            });
+            return;
+        }
+
         self.drawtarget.stroke(
-            &self.path().clone(),
+            path,
             self.state.stroke_style.clone(),
-            &self.state.stroke_opts,
+            &stroke_opts,
             &self.state.draw_options,
         );
     }
 
-    pub fn clip(&mut self) {
+    pub fn clip(&mut self, fill_rule: FillRule) {
         self.ensure_path();
-        let path = self.path().clone();
+        let mut path = self.path().clone();
+        path.set_fill_rule(fill_rule);
         self.drawtarget.push_clip(&path);
     }
 
-    pub fn is_point_in_path(
-        &mut self,
-        x: f64,
-        y: f64,
-        _fill_rule: FillRule,
-        chan: IpcSender<bool>,
-    ) {
+    pub fn clip_path_segments(&mut self, segments: &[PathSegment], fill_rule: FillRule) {
+        let path = self.path_from_segments(segments, fill_rule);
+        self.drawtarget.push_clip(&path);
+    }
+
+    pub fn is_point_in_path(&mut self, x: f64, y: f64, fill_rule: FillRule, chan: IpcSender<bool>) {
         self.ensure_path();
         let result = match self.path_state.as_ref() {
             Some(PathState::UserSpacePath(ref path, ref transform)) => {
                 let target_transform = self.drawtarget.get_transform();
                 let path_transform = transform.as_ref().unwrap_or(&target_transform);
+                let mut path = path.clone();
+                path.set_fill_rule(fill_rule);
                 path.contains_point(x, y, path_transform)
             },
             Some(_) | None => false,
@@ -691,6 +978,19 @@ impl<'a> CanvasData<'a> {
         chan.send(result).unwrap();
     }
 
+    pub fn is_point_in_path_segments(
+        &mut self,
+        segments: &[PathSegment],
+        x: f64,
+        y: f64,
+        fill_rule: FillRule,
+        chan: IpcSender<bool>,
+    ) {
+        let path = self.path_from_segments(segments, fill_rule);
+        let result = path.contains_point(x, y, &self.drawtarget.get_transform());
+        chan.send(result).unwrap();
+    }
+
     pub fn move_to(&mut self, point: &Point2D<f32>) {
         self.path_builder().move_to(point);
     }
@@ -794,66 +1094,7 @@ impl<'a> CanvasData<'a> {
     }
 
     pub fn arc_to(&mut self, cp1: &Point2D<f32>, cp2: &Point2D<f32>, radius: f32) {
-        let cp0 = match self.path_builder().current_point() {
-            Some(p) => p,
-            None => return,
-        };
-        let cp1 = *cp1;
-        let cp2 = *cp2;
-
-        if (cp0.x == cp1.x && cp0.y == cp1.y) || cp1 == cp2 || radius == 0.0 {
-            self.line_to(&cp1);
-            return;
-        }
-
-        // if all three control points lie on a single straight line,
-        // connect the first two by a straight line
-        let direction = (cp2.x - cp1.x) * (cp0.y - cp1.y) + (cp2.y - cp1.y) * (cp1.x - cp0.x);
-        if direction == 0.0 {
-            self.line_to(&cp1);
-            return;
-        }
-
-        // otherwise, draw the Arc
-        let a2 = (cp0.x - cp1.x).powi(2) + (cp0.y - cp1.y).powi(2);
-        let b2 = (cp1.x - cp2.x).powi(2) + (cp1.y - cp2.y).powi(2);
-        let d = {
-            let c2 = (cp0.x - cp2.x).powi(2) + (cp0.y - cp2.y).powi(2);
-            let cosx = (a2 + b2 - c2) / (2.0 * (a2 * b2).sqrt());
-            let sinx = (1.0 - cosx.powi(2)).sqrt();
-            radius / ((1.0 - cosx) / sinx)
-        };
-
-        // first tangent point
-        let anx = (cp1.x - cp0.x) / a2.sqrt();
-        let any = (cp1.y - cp0.y) / a2.sqrt();
-        let tp1 = Point2D::new(cp1.x - anx * d, cp1.y - any * d);
-
-        // second tangent point
-        let bnx = (cp1.x - cp2.x) / b2.sqrt();
-        let bny = (cp1.y - cp2.y) / b2.sqrt();
-        let tp2 = Point2D::new(cp1.x - bnx * d, cp1.y - bny * d);
-
-        // arc center and angles
-        let anticlockwise = direction < 0.0;
-        let cx = tp1.x + any * radius * if anticlockwise { 1.0 } else { -1.0 };
-        let cy = tp1.y - anx * radius * if anticlockwise { 1.0 } else { -1.0 };
-        let angle_start = (tp1.y - cy).atan2(tp1.x - cx);
-        let angle_end = (tp2.y - cy).atan2(tp2.x - cx);
-
-        self.line_to(&tp1);
-        if [cx, cy, angle_start, angle_end]
-            .iter()
-            .all(|x| x.is_finite())
-        {
-            self.arc(
-                &Point2D::new(cx, cy),
-                radius,
-                angle_start,
-                angle_end,
-                anticlockwise,
-            );
-        }
+        self.path_builder().arc_to(cp1, cp2, radius);
     }
 
     pub fn ellipse(
@@ -903,6 +1144,14 @@ impl<'a> CanvasData<'a> {
         self.state.stroke_opts.set_miter_limit(limit);
     }
 
+    pub fn set_line_dash(&mut self, segments: Vec<f32>) {
+        self.state.line_dash = segments;
+    }
+
+    pub fn set_line_dash_offset(&mut self, offset: f32) {
+        self.state.line_dash_offset = offset;
+    }
+
     pub fn set_transform(&mut self, transform: &Transform2D<f32>) {
         // If there is an in-progress path, store the existing transformation required
         // to move between device and user space.
@@ -1032,6 +1281,84 @@ impl<'a> CanvasData<'a> {
         self.backend.set_shadow_color(value, &mut self.state);
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-font
+    pub fn set_font(&mut self, value: &str) {
+        let font_style = self.compute_css_value(value, font::parse_value, |longhands, context| {
+            let mut font_style = FontStyleStruct {
+                font_family: longhands.font_family.to_computed_value(context),
+                font_style: longhands.font_style.to_computed_value(context),
+                font_variant_caps: longhands.font_variant_caps.to_computed_value(context),
+                font_weight: longhands.font_weight.to_computed_value(context),
+                font_size: longhands.font_size.to_computed_value(context),
+                font_stretch: longhands.font_stretch.to_computed_value(context),
+                hash: 0,
+                ..ComputedValues::initial_values().get_font().clone()
+            };
+            font_style.compute_font_hash();
+            font_style
+        });
+        match font_style {
+            Some(font_style) => self.state.font_style = servo_arc::Arc::new(font_style),
+            None => warn!("Couldn't compute canvas font {:?}.", value),
+        }
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-filter
+    pub fn set_filter(&mut self, value: &str) {
+        let filters = self.compute_css_value(value, filter::parse, |filters, context| {
+            filters
+                .0
+                .iter()
+                .map(|filter| FilterOperation::from_computed(&filter.to_computed_value(context)))
+                .collect()
+        });
+        match filters {
+            Some(filters) => self.state.filters = filters,
+            None => warn!("Couldn't compute canvas filter {:?}.", value),
+        }
+    }
+
+    /// Parses and computes the value of a CSS property. Script only sends
+    /// valid values, but the relative ones are resolved against the initial
+    /// style here, as the style of the canvas element isn't known.
+    fn compute_css_value<S, T, C>(
+        &self,
+        value: &str,
+        parse: for<'i, 't> fn(&ParserContext, &mut Parser<'i, 't>) -> Result<S, ParseError<'i>>,
+        compute: C,
+    ) -> Option<T>
+    where
+        C: FnOnce(&S, &Context) -> T,
+    {
+        let url_data = UrlExtraData::parse("about:blank").unwrap();
+        let parser_context = ParserContext::new(
+            Origin::Author,
+            &url_data,
+            Some(CssRuleType::Style),
+            ParsingMode::DEFAULT,
+            QuirksMode::NoQuirks,
+            None,
+            None,
+        );
+        let mut input = ParserInput::new(value);
+        let mut parser = Parser::new(&mut input);
+        let specified = parser
+            .parse_entirely(|input| parse(&parser_context, input))
+            .ok()?;
+
+        let device = Device::new(
+            MediaType::screen(),
+            euclid::Size2D::from_untyped(self.drawtarget.get_size().to_f32()),
+            Scale::new(1.0),
+            Default::default(),
+        );
+        Some(Context::for_media_query_evaluation(
+            &device,
+            QuirksMode::NoQuirks,
+            |context| compute(&specified, context),
+        ))
+    }
+
     // https://html.spec.whatwg.org/multipage/#when-shadows-are-drawn
     fn need_to_draw_shadow(&self) -> bool {
         self.backend.need_to_draw_shadow(&self.state.shadow_color) &&
@@ -1055,7 +1382,7 @@ impl<'a> CanvasData<'a> {
         draw_target
     }
 
-    fn draw_with_shadow<F>(&self, rect: &Rect<f32>, draw_shadow_source: F)
+    fn draw_with_shadow<F>(&mut self, rect: &Rect<f32>, draw_shadow_source: F)
     where
         F: FnOnce(&mut dyn GenericDrawTarget),
     {
@@ -1078,6 +1405,62 @@ impl<'a> CanvasData<'a> {
         );
     }
 
+    /// Draws into a layer the size of the canvas, which is filtered and then
+    /// composited onto the canvas with the shadow of the context.
+    ///
+    /// <https://html.spec.whatwg.org/multipage/#drawing-model>
+    fn draw_with_filter<F>(&mut self, draw: F)
+    where
+        F: FnOnce(&mut dyn GenericDrawTarget, &DrawOptions),
+    {
+        let size = self.drawtarget.get_size();
+        let mut layer = self
+            .drawtarget
+            .create_similar_draw_target(&size, self.drawtarget.get_format());
+        layer.set_transform(&self.state.transform);
+
+        // None of the filters change the result of scaling the alpha of the
+        // pixels first, so the global alpha is applied while drawing the layer
+        // and only the compositing operator is left for compositing it.
+        let mut layer_state = self.state.clone();
+        self.backend
+            .set_global_composition(CompositionOrBlending::default(), &mut layer_state);
+        draw(&mut *layer, &layer_state.draw_options);
+
+        let mut pixels = layer.snapshot_data_owned();
+        apply_filters(&self.state.filters, &mut pixels, size);
+        let surface =
+            match self
+                .drawtarget
+                .create_source_surface_from_data(&pixels, size, size.width * 4)
+            {
+                Some(surface) => surface,
+                None => return,
+            };
+
+        self.drawtarget.set_transform(&Transform2D::identity());
+        if self.need_to_draw_shadow() {
+            self.drawtarget.draw_surface_with_shadow(
+                surface,
+                &Point2D::zero(),
+                &self.state.shadow_color,
+                &Vector2D::new(
+                    self.state.shadow_offset_x as f32,
+                    self.state.shadow_offset_y as f32,
+                ),
+                (self.state.shadow_blur / 2.0f64) as f32,
+                self.backend.get_composition_op(&self.state.draw_options),
+            );
+        } else {
+            let mut draw_options = self.state.draw_options.clone();
+            draw_options.set_alpha(1.0);
+            let rect = Rect::from_size(size.to_f64());
+            self.drawtarget
+                .draw_surface(surface, rect, rect, Filter::Point, &draw_options);
+        }
+        self.drawtarget.set_transform(&self.state.transform);
+    }
+
     /// It reads image data from the canvas
     /// canvas_size: The size of the canvas we're reading from
     /// read_rect: The area of the canvas we want to read from
@@ -1118,12 +1501,97 @@ pub struct CanvasPaintState<'a> {
     pub fill_style: Pattern<'a>,
     pub stroke_style: Pattern<'a>,
     pub stroke_opts: StrokeOptions<'a>,
+    /// The dash list of the stroke, kept apart from `stroke_opts` as some
+    /// backends only borrow it.
+    pub line_dash: Vec<f32>,
+    pub line_dash_offset: f32,
     /// The current 2D transform matrix.
     pub transform: Transform2D<f32>,
     pub shadow_offset_x: f64,
     pub shadow_offset_y: f64,
     pub shadow_blur: f64,
     pub shadow_color: Color,
+    /// The computed style of the font of the context.
+    pub font_style: servo_arc::Arc<FontStyleStruct>,
+    /// The filters of the context, applied in order.
+    pub filters: Vec<FilterOperation>,
+}
+
+/// The style of the default `10px sans-serif` canvas font.
+pub(crate) fn default_font_style() -> FontStyleStruct {
+    let mut style = FontStyleStruct {
+        font_family: FontFamily {
+            families: FontFamilyList::new(Box::new([SingleFontFamily::Generic(
+                GenericFontFamily::SansSerif,
+            )])),
+            is_system_font: false,
+        },
+        font_style: FontStyle::Normal,
+        font_variant_caps: FontVariantCaps::Normal,
+        font_weight: FontWeight::normal(),
+        font_size: FontSize {
+            size: Au::from_px(10).into(),
+            keyword_info: None,
+        },
+        font_stretch: FontStretch::hundred(),
+        hash: 0,
+        ..ComputedValues::initial_values().get_font().clone()
+    };
+    style.compute_font_hash();
+    style
+}
+
+/// Approximates an arc of an ellipse with one cubic bezier curve per quarter
+/// turn, returning the start point of the arc and the control points and end
+/// point of each curve.
+///
+/// <https://html.spec.whatwg.org/multipage/#dom-context-2d-ellipse>
+pub(crate) fn ellipse_to_bezier_curves(
+    origin: Point2D<f32>,
+    radius_x: f32,
+    radius_y: f32,
+    rotation_angle: f32,
+    start_angle: f32,
+    end_angle: f32,
+    anticlockwise: bool,
+) -> (Point2D<f32>, Vec<[Point2D<f32>; 3]>) {
+    let sweep = if !anticlockwise && end_angle - start_angle >= 2. * PI {
+        2. * PI
+    } else if !anticlockwise {
+        (end_angle - start_angle).rem_euclid(2. * PI)
+    } else if start_angle - end_angle >= 2. * PI {
+        -2. * PI
+    } else {
+        -(start_angle - end_angle).rem_euclid(2. * PI)
+    };
+
+    // Maps a point of the unit circle onto the ellipse.
+    let (sin_rotation, cos_rotation) = rotation_angle.sin_cos();
+    let point = |x: f32, y: f32| {
+        let (x, y) = (x * radius_x, y * radius_y);
+        Point2D::new(
+            origin.x + x * cos_rotation - y * sin_rotation,
+            origin.y + x * sin_rotation + y * cos_rotation,
+        )
+    };
+
+    let segments = (sweep.abs() / FRAC_PI_2).ceil().max(1.);
+    let delta = sweep / segments;
+    let k = 4. / 3. * (delta / 4.).tan();
+    let mut angle = start_angle;
+    let curves = (0..segments as usize)
+        .map(|_| {
+            let (sin0, cos0) = angle.sin_cos();
+            angle += delta;
+            let (sin1, cos1) = angle.sin_cos();
+            [
+                point(cos0 - k * sin0, sin0 + k * cos0),
+                point(cos1 + k * sin1, sin1 - k * cos1),
+                point(cos1, sin1),
+            ]
+        })
+        .collect();
+    (point(start_angle.cos(), start_angle.sin()), curves)
 }
 
 /// It writes an image to the destination target
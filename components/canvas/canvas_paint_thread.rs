@@ -5,6 +5,8 @@
 use crate::canvas_data::*;
 use canvas_traits::canvas::*;
 use euclid::default::Size2D;
+use gfx::font_cache_thread::FontCacheThread;
+use gfx::font_context::FontContext;
 use ipc_channel::ipc::{self, IpcSender};
 use std::borrow::ToOwned;
 use std::collections::HashMap;
@@ -18,24 +20,27 @@ pub enum AntialiasMode {
 pub struct CanvasPaintThread<'a> {
     canvases: HashMap<CanvasId, CanvasData<'a>>,
     next_canvas_id: CanvasId,
+    /// The font context used to measure text drawn by any of the canvases.
+    font_context: FontContext<FontCacheThread>,
 }
 
 impl<'a> CanvasPaintThread<'a> {
-    fn new() -> CanvasPaintThread<'a> {
+    fn new(font_cache_thread: FontCacheThread) -> CanvasPaintThread<'a> {
         CanvasPaintThread {
             canvases: HashMap::new(),
             next_canvas_id: CanvasId(0),
+            font_context: FontContext::new(font_cache_thread),
         }
     }
 
     /// Creates a new `CanvasPaintThread` and returns an `IpcSender` to
     /// communicate with it.
-    pub fn start() -> IpcSender<CanvasMsg> {
+    pub fn start(font_cache_thread: FontCacheThread) -> IpcSender<CanvasMsg> {
         let (sender, receiver) = ipc::channel::<CanvasMsg>().unwrap();
         thread::Builder::new()
             .name("CanvasThread".to_owned())
             .spawn(move || {
-                let mut canvas_paint_thread = CanvasPaintThread::new();
+                let mut canvas_paint_thread = CanvasPaintThread::new(font_cache_thread);
                 loop {
                     match receiver.recv() {
                         Ok(msg) => match msg {
@@ -110,12 +115,29 @@ impl<'a> CanvasPaintThread<'a> {
             Canvas2dMsg::ClearRect(ref rect) => self.canvas(canvas_id).clear_rect(rect),
             Canvas2dMsg::BeginPath => self.canvas(canvas_id).begin_path(),
             Canvas2dMsg::ClosePath => self.canvas(canvas_id).close_path(),
-            Canvas2dMsg::Fill => self.canvas(canvas_id).fill(),
+            Canvas2dMsg::Fill(fill_rule) => self.canvas(canvas_id).fill(fill_rule),
+            Canvas2dMsg::FillPath(ref segments, fill_rule) => self
+                .canvas(canvas_id)
+                .fill_path_segments(segments, fill_rule),
             Canvas2dMsg::Stroke => self.canvas(canvas_id).stroke(),
-            Canvas2dMsg::Clip => self.canvas(canvas_id).clip(),
+            Canvas2dMsg::StrokePath(ref segments) => {
+                self.canvas(canvas_id).stroke_path_segments(segments)
+            },
+            Canvas2dMsg::Clip(fill_rule) => self.canvas(canvas_id).clip(fill_rule),
+            Canvas2dMsg::ClipPath(ref segments, fill_rule) => self
+                .canvas(canvas_id)
+                .clip_path_segments(segments, fill_rule),
             Canvas2dMsg::IsPointInPath(x, y, fill_rule, chan) => self
                 .canvas(canvas_id)
                 .is_point_in_path(x, y, fill_rule, chan),
+            Canvas2dMsg::IsPointInPathSegments(ref segments, x, y, fill_rule, chan) => self
+                .canvas(canvas_id)
+                .is_point_in_path_segments(segments, x, y, fill_rule, chan),
+            Canvas2dMsg::MeasureText(text, chan) => {
+                let canvas = self.canvases.get(&canvas_id).expect("Bogus canvas id");
+                let metrics = canvas.measure_text(&mut self.font_context, &text);
+                chan.send(metrics).unwrap();
+            },
             Canvas2dMsg::DrawImage(
                 imagedata,
                 image_size,
@@ -179,6 +201,10 @@ impl<'a> CanvasPaintThread<'a> {
             Canvas2dMsg::SetLineCap(cap) => self.canvas(canvas_id).set_line_cap(cap),
             Canvas2dMsg::SetLineJoin(join) => self.canvas(canvas_id).set_line_join(join),
             Canvas2dMsg::SetMiterLimit(limit) => self.canvas(canvas_id).set_miter_limit(limit),
+            Canvas2dMsg::SetLineDash(segments) => self.canvas(canvas_id).set_line_dash(segments),
+            Canvas2dMsg::SetLineDashOffset(offset) => {
+                self.canvas(canvas_id).set_line_dash_offset(offset)
+            },
             Canvas2dMsg::SetTransform(ref matrix) => self.canvas(canvas_id).set_transform(matrix),
             Canvas2dMsg::SetGlobalAlpha(alpha) => self.canvas(canvas_id).set_global_alpha(alpha),
             Canvas2dMsg::SetGlobalComposition(op) => {
@@ -200,6 +226,8 @@ impl<'a> CanvasPaintThread<'a> {
             },
             Canvas2dMsg::SetShadowBlur(value) => self.canvas(canvas_id).set_shadow_blur(value),
             Canvas2dMsg::SetShadowColor(color) => self.canvas(canvas_id).set_shadow_color(color),
+            Canvas2dMsg::SetFont(font) => self.canvas(canvas_id).set_font(&font),
+            Canvas2dMsg::SetFilter(filter) => self.canvas(canvas_id).set_filter(&filter),
         }
     }
 
@@ -0,0 +1,249 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The filter functions of the `filter` attribute of 2D canvas contexts.
+//!
+//! None of the backends can apply them, so they are applied in software to
+//! the premultiplied BGRA pixels of a layer before it's composited.
+
+use euclid::default::Size2D;
+use std::f32::consts::PI;
+use style::values::computed::effects::Filter as ComputedFilter;
+use style::values::generics::effects::Filter as GenericFilter;
+
+/// <https://drafts.fxtf.org/filter-effects/#filter-functions>
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterOperation {
+    /// A gaussian blur with the given standard deviation, in pixels.
+    Blur(f32),
+    /// A linear transformation of the unpremultiplied components of each
+    /// pixel. Each row computes one of the red, green, blue and alpha
+    /// components from the red, green, blue and alpha components and a
+    /// constant offset.
+    ColorMatrix([[f32; 5]; 4]),
+}
+
+impl FilterOperation {
+    pub fn from_computed(filter: &ComputedFilter) -> FilterOperation {
+        match *filter {
+            GenericFilter::Blur(ref length) => FilterOperation::Blur(length.0.px()),
+            GenericFilter::Brightness(ref amount) => linear_transfer(amount.0, 0.),
+            GenericFilter::Contrast(ref amount) => linear_transfer(amount.0, 0.5 - 0.5 * amount.0),
+            GenericFilter::Grayscale(ref amount) => {
+                let a = 1. - amount.0;
+                color_matrix([
+                    [
+                        0.2126 + 0.7874 * a,
+                        0.7152 - 0.7152 * a,
+                        0.0722 - 0.0722 * a,
+                    ],
+                    [
+                        0.2126 - 0.2126 * a,
+                        0.7152 + 0.2848 * a,
+                        0.0722 - 0.0722 * a,
+                    ],
+                    [
+                        0.2126 - 0.2126 * a,
+                        0.7152 - 0.7152 * a,
+                        0.0722 + 0.9278 * a,
+                    ],
+                ])
+            },
+            GenericFilter::HueRotate(ref angle) => {
+                let (sin, cos) = angle.radians().sin_cos();
+                color_matrix([
+                    [
+                        0.213 + cos * 0.787 - sin * 0.213,
+                        0.715 - cos * 0.715 - sin * 0.715,
+                        0.072 - cos * 0.072 + sin * 0.928,
+                    ],
+                    [
+                        0.213 - cos * 0.213 + sin * 0.143,
+                        0.715 + cos * 0.285 + sin * 0.140,
+                        0.072 - cos * 0.072 - sin * 0.283,
+                    ],
+                    [
+                        0.213 - cos * 0.213 - sin * 0.787,
+                        0.715 - cos * 0.715 + sin * 0.715,
+                        0.072 + cos * 0.928 + sin * 0.072,
+                    ],
+                ])
+            },
+            GenericFilter::Invert(ref amount) => linear_transfer(1. - 2. * amount.0, amount.0),
+            GenericFilter::Opacity(ref amount) => {
+                let mut matrix = identity_matrix();
+                matrix[3][3] = amount.0;
+                FilterOperation::ColorMatrix(matrix)
+            },
+            GenericFilter::Saturate(ref amount) => {
+                let s = amount.0;
+                color_matrix([
+                    [0.213 + 0.787 * s, 0.715 - 0.715 * s, 0.072 - 0.072 * s],
+                    [0.213 - 0.213 * s, 0.715 + 0.285 * s, 0.072 - 0.072 * s],
+                    [0.213 - 0.213 * s, 0.715 - 0.715 * s, 0.072 + 0.928 * s],
+                ])
+            },
+            GenericFilter::Sepia(ref amount) => {
+                let a = 1. - amount.0;
+                color_matrix([
+                    [0.393 + 0.607 * a, 0.769 - 0.769 * a, 0.189 - 0.189 * a],
+                    [0.349 - 0.349 * a, 0.686 + 0.314 * a, 0.168 - 0.168 * a],
+                    [0.272 - 0.272 * a, 0.534 - 0.534 * a, 0.131 + 0.869 * a],
+                ])
+            },
+            GenericFilter::DropShadow(ref shadow) => match *shadow {},
+            GenericFilter::Url(ref url) => match *url {},
+        }
+    }
+}
+
+fn identity_matrix() -> [[f32; 5]; 4] {
+    let mut matrix = [[0.; 5]; 4];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[i] = 1.;
+    }
+    matrix
+}
+
+/// A matrix transforming the color components with the given 3x3 matrix and
+/// leaving the alpha component alone.
+fn color_matrix(rgb: [[f32; 3]; 3]) -> FilterOperation {
+    let mut matrix = identity_matrix();
+    for (row, rgb_row) in matrix.iter_mut().zip(rgb.iter()) {
+        row[..3].copy_from_slice(rgb_row);
+    }
+    FilterOperation::ColorMatrix(matrix)
+}
+
+/// A matrix mapping each color component `c` to `slope * c + intercept`.
+fn linear_transfer(slope: f32, intercept: f32) -> FilterOperation {
+    let mut matrix = identity_matrix();
+    for (i, row) in matrix.iter_mut().take(3).enumerate() {
+        row[i] = slope;
+        row[4] = intercept;
+    }
+    FilterOperation::ColorMatrix(matrix)
+}
+
+/// Applies the filters, in order, to premultiplied BGRA pixels.
+pub fn apply_filters(filters: &[FilterOperation], pixels: &mut [u8], size: Size2D<i32>) {
+    let (width, height) = (size.width as usize, size.height as usize);
+    for filter in filters {
+        match *filter {
+            FilterOperation::Blur(sigma) => blur_pixels(pixels, width, height, sigma),
+            FilterOperation::ColorMatrix(ref matrix) => {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    transform_pixel(matrix, pixel);
+                }
+            },
+        }
+    }
+}
+
+fn transform_pixel(matrix: &[[f32; 5]; 4], pixel: &mut [u8]) {
+    let alpha = pixel[3] as f32 / 255.;
+    let unpremultiply = |component: u8| {
+        if alpha > 0. {
+            (component as f32 / 255. / alpha).min(1.)
+        } else {
+            0.
+        }
+    };
+    let input = [
+        unpremultiply(pixel[2]),
+        unpremultiply(pixel[1]),
+        unpremultiply(pixel[0]),
+        alpha,
+        1.,
+    ];
+    let mut output = [0.; 4];
+    for (component, row) in output.iter_mut().zip(matrix.iter()) {
+        let value: f32 = row.iter().zip(input.iter()).map(|(m, c)| m * c).sum();
+        *component = value.max(0.).min(1.);
+    }
+    let [red, green, blue, alpha] = output;
+    let premultiply = |component: f32| (component * alpha * 255. + 0.5) as u8;
+    pixel[0] = premultiply(blue);
+    pixel[1] = premultiply(green);
+    pixel[2] = premultiply(red);
+    pixel[3] = (alpha * 255. + 0.5) as u8;
+}
+
+/// Blurs each channel of premultiplied pixels, which keeps the colors of
+/// partially transparent pixels right.
+fn blur_pixels(pixels: &mut [u8], width: usize, height: usize, sigma: f32) {
+    let radius = box_blur_radius(sigma);
+    if radius == 0 {
+        return;
+    }
+    let mut channel = vec![0u8; width * height];
+    for offset in 0..4 {
+        for (value, pixel) in channel.iter_mut().zip(pixels.chunks_exact(4)) {
+            *value = pixel[offset];
+        }
+        blur_alpha_mask(&mut channel, width, height, radius);
+        for (value, pixel) in channel.iter().zip(pixels.chunks_exact_mut(4)) {
+            pixel[offset] = *value;
+        }
+    }
+}
+
+/// The radius of the three box blurs approximating a gaussian blur with
+/// standard deviation `sigma`, as described in the filter effects spec.
+pub(crate) fn box_blur_radius(sigma: f32) -> usize {
+    let d = (sigma * 3. * (2. * PI).sqrt() / 4. + 0.5).floor();
+    if d > 0. {
+        d as usize / 2
+    } else {
+        0
+    }
+}
+
+pub(crate) fn blur_alpha_mask(mask: &mut [u8], width: usize, height: usize, radius: usize) {
+    if radius == 0 {
+        return;
+    }
+    let mut scratch = vec![0u8; mask.len()];
+    for _ in 0..3 {
+        box_blur(mask, &mut scratch, width, height, radius, true);
+        box_blur(&scratch, mask, width, height, radius, false);
+    }
+}
+
+fn box_blur(
+    src: &[u8],
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    radius: usize,
+    horizontal: bool,
+) {
+    let (lines, len) = if horizontal {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let index = |line: usize, i: usize| {
+        if horizontal {
+            line * width + i
+        } else {
+            i * width + line
+        }
+    };
+    let window = (2 * radius + 1) as u32;
+    for line in 0..lines {
+        let mut sum: u32 = (0..radius.min(len))
+            .map(|i| src[index(line, i)] as u32)
+            .sum();
+        for i in 0..len {
+            if i + radius < len {
+                sum += src[index(line, i + radius)] as u32;
+            }
+            dst[index(line, i)] = (sum / window) as u8;
+            if i >= radius {
+                sum -= src[index(line, i - radius)] as u32;
+            }
+        }
+    }
+}
@@ -15,6 +15,7 @@ mod raqote_backend;
 
 pub mod canvas_data;
 pub mod canvas_paint_thread;
+mod filters;
 pub mod gl_context;
 mod webgl_mode;
 pub mod webgl_thread;
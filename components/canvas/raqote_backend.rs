@@ -2,17 +2,21 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::canvas_data;
+use crate::canvas_data::{default_font_style, ellipse_to_bezier_curves};
 use crate::canvas_data::{
     Backend, CanvasPaintState, Color, CompositionOp, DrawOptions, ExtendMode, Filter,
-    GenericDrawTarget, GenericPathBuilder, GradientStop, GradientStops, Path, Pattern,
-    SourceSurface, StrokeOptions, SurfaceFormat,
+    GenericDrawTarget, GenericPathBuilder, GradientStop, GradientStops, Path, SourceSurface,
+    StrokeOptions, SurfaceFormat,
 };
 use crate::canvas_paint_thread::AntialiasMode;
+use crate::filters::{blur_alpha_mask, box_blur_radius};
 use canvas_traits::canvas::*;
 use cssparser::RGBA;
 use euclid::default::{Point2D, Rect, Size2D, Transform2D, Vector2D};
 use raqote::PathOp;
 use std::marker::PhantomData;
+use std::sync::Arc;
 
 pub struct RaqoteBackend;
 
@@ -25,11 +29,21 @@ impl Backend for RaqoteBackend {
         color.as_raqote().a != 0
     }
 
-    fn size_from_pattern(&self, rect: &Rect<f32>, pattern: &Pattern) -> Option<Size2D<f32>> {
-        match pattern {
-            Pattern::Raqote(raqote::Source::Image(image, extend, ..)) => match extend {
-                raqote::ExtendMode::Repeat => Some(rect.size),
-                _ => Some(Size2D::new(image.width as f32, image.height as f32)),
+    fn size_from_pattern(
+        &self,
+        rect: &Rect<f32>,
+        pattern: &canvas_data::Pattern,
+    ) -> Option<Size2D<f32>> {
+        match pattern.as_raqote() {
+            Pattern::Surface(surface) => {
+                let surface_size = surface.size.to_f32();
+                let size = match (surface.repeat_x, surface.repeat_y) {
+                    (true, true) => rect.size,
+                    (true, false) => Size2D::new(rect.size.width, surface_size.height),
+                    (false, true) => Size2D::new(surface_size.width, rect.size.height),
+                    (false, false) => surface_size,
+                };
+                Some(size)
             },
             _ => None,
         }
@@ -45,8 +59,8 @@ impl Backend for RaqoteBackend {
         state: &mut CanvasPaintState<'a>,
         _drawtarget: &dyn GenericDrawTarget,
     ) {
-        if let Some(source) = style.to_raqote_source() {
-            state.fill_style = Pattern::Raqote(source);
+        if let Some(pattern) = style.to_raqote_pattern() {
+            state.fill_style = canvas_data::Pattern::Raqote(pattern, PhantomData);
         }
     }
 
@@ -56,8 +70,8 @@ impl Backend for RaqoteBackend {
         state: &mut CanvasPaintState<'a>,
         _drawtarget: &dyn GenericDrawTarget,
     ) {
-        if let Some(pattern) = style.to_raqote_source() {
-            state.stroke_style = Pattern::Raqote(pattern)
+        if let Some(pattern) = style.to_raqote_pattern() {
+            state.stroke_style = canvas_data::Pattern::Raqote(pattern, PhantomData);
         }
     }
 
@@ -76,6 +90,10 @@ impl Backend for RaqoteBackend {
         ))
     }
 
+    fn line_dash(&self, dash: &[f32], offset: f32) -> (Vec<f32>, f32) {
+        (dash.to_vec(), offset)
+    }
+
     fn recreate_paint_state<'a>(&self, _state: &CanvasPaintState<'a>) -> CanvasPaintState<'a> {
         CanvasPaintState::new(AntialiasMode::Default)
     }
@@ -91,9 +109,11 @@ impl<'a> CanvasPaintState<'a> {
         };
         CanvasPaintState {
             draw_options: DrawOptions::Raqote(raqote::DrawOptions::new()),
-            fill_style: Pattern::Raqote(raqote::Source::Solid(solid_src)),
-            stroke_style: Pattern::Raqote(raqote::Source::Solid(solid_src)),
+            fill_style: canvas_data::Pattern::Raqote(Pattern::Color(solid_src), PhantomData),
+            stroke_style: canvas_data::Pattern::Raqote(Pattern::Color(solid_src), PhantomData),
             stroke_opts: StrokeOptions::Raqote(Default::default(), PhantomData),
+            line_dash: vec![],
+            line_dash_offset: 0.0,
             transform: Transform2D::identity(),
             shadow_offset_x: 0.0,
             shadow_offset_y: 0.0,
@@ -104,28 +124,125 @@ impl<'a> CanvasPaintState<'a> {
                 b: 0,
                 a: 0,
             }),
+            font_style: servo_arc::Arc::new(default_font_style()),
+            filters: vec![],
         }
     }
 }
 
-impl Pattern<'_> {
-    pub fn is_zero_size_gradient(&self) -> bool {
+/// A fill or stroke style. Unlike `raqote::Source`, this owns the pixels of
+/// surface patterns and remembers the axes they repeat on.
+#[derive(Clone)]
+pub enum Pattern {
+    Color(raqote::SolidSource),
+    LinearGradient(raqote::Gradient, Point2D<f32>, Point2D<f32>),
+    RadialGradient(raqote::Gradient, Point2D<f32>, f32, Point2D<f32>, f32),
+    Surface(SurfacePattern),
+}
+
+#[derive(Clone)]
+pub struct SurfacePattern {
+    data: Arc<Vec<u32>>,
+    size: Size2D<i32>,
+    repeat_x: bool,
+    repeat_y: bool,
+}
+
+impl Pattern {
+    fn source(&self) -> raqote::Source {
         match self {
-            Pattern::Raqote(p) => {
-                use raqote::Source::*;
-
-                match p {
-                    LinearGradient(g, ..) |
-                    RadialGradient(g, ..) |
-                    TwoCircleRadialGradient(g, ..) => g.stops.is_empty(),
-                    _ => false,
-                }
+            Pattern::Color(color) => raqote::Source::Solid(*color),
+            // A gradient without any color stops paints transparent black.
+            Pattern::LinearGradient(gradient, ..) | Pattern::RadialGradient(gradient, ..)
+                if gradient.stops.is_empty() =>
+            {
+                raqote::Source::Solid(raqote::SolidSource {
+                    r: 0,
+                    g: 0,
+                    b: 0,
+                    a: 0,
+                })
+            },
+            Pattern::LinearGradient(gradient, start, end) => raqote::Source::new_linear_gradient(
+                gradient.clone(),
+                *start,
+                *end,
+                raqote::Spread::Pad,
+            ),
+            Pattern::RadialGradient(gradient, center1, radius1, center2, radius2) => {
+                raqote::Source::new_two_circle_radial_gradient(
+                    gradient.clone(),
+                    *center1,
+                    *radius1,
+                    *center2,
+                    *radius2,
+                    raqote::Spread::Pad,
+                )
+            },
+            Pattern::Surface(surface) => {
+                let extend = if surface.repeat_x || surface.repeat_y {
+                    raqote::ExtendMode::Repeat
+                } else {
+                    raqote::ExtendMode::Pad
+                };
+                raqote::Source::Image(
+                    raqote::Image {
+                        width: surface.size.width,
+                        height: surface.size.height,
+                        data: &surface.data,
+                    },
+                    extend,
+                    raqote::FilterMode::Bilinear,
+                    raqote::Transform::identity(),
+                )
             },
         }
     }
-    pub fn as_raqote(&self) -> &raqote::Source {
+
+    /// The user-space area this pattern may paint on a draw target of the given
+    /// size and transform, or `None` if it covers the whole plane.
+    ///
+    /// raqote can only repeat an image in both directions or in none, so the
+    /// image is clipped to its own extent along the axes it doesn't repeat on.
+    fn paint_area(&self, size: Size2D<i32>, transform: &Transform2D<f32>) -> Option<Rect<f32>> {
+        let surface = match self {
+            Pattern::Surface(surface) if !(surface.repeat_x && surface.repeat_y) => surface,
+            _ => return None,
+        };
+        let visible = transform
+            .inverse()
+            .map(|inverse| inverse.transform_rect(&Rect::from_size(size.to_f32())))
+            .unwrap_or_else(Rect::zero);
+        let (x0, x1) = if surface.repeat_x {
+            (visible.min_x(), visible.max_x())
+        } else {
+            (0., surface.size.width as f32)
+        };
+        let (y0, y1) = if surface.repeat_y {
+            (visible.min_y(), visible.max_y())
+        } else {
+            (0., surface.size.height as f32)
+        };
+        Some(Rect::new(
+            Point2D::new(x0, y0),
+            Size2D::new(x1 - x0, y1 - y0),
+        ))
+    }
+}
+
+impl canvas_data::Pattern<'_> {
+    pub fn is_zero_size_gradient(&self) -> bool {
+        match self.as_raqote() {
+            Pattern::LinearGradient(_, start, end) => start == end,
+            Pattern::RadialGradient(_, center1, radius1, center2, radius2) => {
+                center1 == center2 && radius1 == radius2
+            },
+            _ => false,
+        }
+    }
+    pub fn as_raqote(&self) -> &Pattern {
         match self {
-            Pattern::Raqote(p) => p,
+            canvas_data::Pattern::Raqote(p, _) => p,
         }
     }
 }
@@ -151,6 +268,16 @@ impl<'a> StrokeOptions<'a> {
             StrokeOptions::Raqote(options, _) => options.cap = val.to_raqote_style(),
         }
     }
+    pub fn with_line_dash<'b>(&self, dash: &'b [f32], offset: f32) -> StrokeOptions<'b> {
+        match self {
+            StrokeOptions::Raqote(options, _) => {
+                let mut options = options.clone();
+                options.dash_array = dash.to_vec();
+                options.dash_offset = offset;
+                StrokeOptions::Raqote(options, PhantomData)
+            },
+        }
+    }
     pub fn as_raqote(&self) -> &raqote::StrokeStyle {
         match self {
             StrokeOptions::Raqote(options, _) => options,
@@ -179,13 +306,15 @@ impl DrawOptions {
 impl Path {
     pub fn transformed_copy_to_builder(
         &self,
-        _transform: &Transform2D<f32>,
+        transform: &Transform2D<f32>,
     ) -> Box<dyn GenericPathBuilder> {
-        unimplemented!()
+        Box::new(PathBuilder(Some(raqote::PathBuilder::from(
+            self.as_raqote().clone().transform(transform),
+        ))))
     }
 
-    pub fn contains_point(&self, x: f64, y: f64, _path_transform: &Transform2D<f32>) -> bool {
-        let path = self.as_raqote();
+    pub fn contains_point(&self, x: f64, y: f64, path_transform: &Transform2D<f32>) -> bool {
+        let path = self.as_raqote().clone().transform(path_transform);
         path.contains_point(0.1, path.winding, x as f32, y as f32)
     }
 
@@ -195,6 +324,17 @@ impl Path {
         ))))
     }
 
+    pub fn set_fill_rule(&mut self, fill_rule: FillRule) {
+        match self {
+            Path::Raqote(p) => {
+                p.winding = match fill_rule {
+                    FillRule::Nonzero => raqote::Winding::NonZero,
+                    FillRule::Evenodd => raqote::Winding::EvenOdd,
+                }
+            },
+        }
+    }
+
     pub fn as_raqote(&self) -> &raqote::Path {
         match self {
             Path::Raqote(p) => p,
@@ -204,18 +344,11 @@ impl Path {
 
 impl GenericDrawTarget for raqote::DrawTarget {
     fn clear_rect(&mut self, rect: &Rect<f32>) {
-        let mut pb = raqote::PathBuilder::new();
-        pb.rect(
-            rect.origin.x,
-            rect.origin.y,
-            rect.size.width,
-            rect.size.height,
-        );
         let mut options = raqote::DrawOptions::new();
         options.blend_mode = raqote::BlendMode::Clear;
         raqote::DrawTarget::fill(
             self,
-            &pb.finish(),
+            &rect_path(rect),
             &raqote::Source::Solid(raqote::SolidSource {
                 r: 0,
                 g: 0,
@@ -225,17 +358,15 @@ impl GenericDrawTarget for raqote::DrawTarget {
             &options,
         );
     }
-    #[allow(unsafe_code)]
     fn copy_surface(
         &mut self,
         surface: SourceSurface,
         source: Rect<i32>,
         destination: Point2D<i32>,
     ) {
-        let mut dt = raqote::DrawTarget::new(source.size.width, source.size.height);
-        let data = surface.as_raqote();
-        let s = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const u32, data.len() / 4) };
-        dt.get_data_mut().copy_from_slice(s);
+        let (data, size) = surface.as_raqote();
+        let mut dt = raqote::DrawTarget::new(size.width, size.height);
+        dt.get_data_mut().copy_from_slice(data);
         raqote::DrawTarget::copy_surface(self, &dt, source.to_box2d(), destination);
     }
     fn create_gradient_stops(
@@ -262,79 +393,138 @@ impl GenericDrawTarget for raqote::DrawTarget {
     fn create_source_surface_from_data(
         &self,
         data: &[u8],
-        _size: Size2D<i32>,
+        size: Size2D<i32>,
         _stride: i32,
     ) -> Option<SourceSurface> {
-        Some(SourceSurface::Raqote(data.to_vec()))
+        Some(SourceSurface::Raqote(to_pixels(data), size))
     }
-    #[allow(unsafe_code)]
     fn draw_surface(
         &mut self,
         surface: SourceSurface,
         dest: Rect<f64>,
         source: Rect<f64>,
-        _filter: Filter,
+        filter: Filter,
         draw_options: &DrawOptions,
     ) {
-        let v = surface.as_raqote();
+        if dest.is_empty() {
+            return;
+        }
+        let (data, size) = surface.as_raqote();
         let image = raqote::Image {
-            width: source.size.width as i32,
-            height: source.size.height as i32,
-            data: unsafe {
-                std::slice::from_raw_parts(
-                    v.as_ptr() as *const u32,
-                    v.len() * std::mem::size_of::<u8>(),
-                )
-            },
+            width: size.width,
+            height: size.height,
+            data,
         };
-        raqote::DrawTarget::draw_image_with_size_at(
-            self,
-            dest.size.width as f32,
-            dest.size.height as f32,
-            dest.origin.x as f32,
-            dest.origin.y as f32,
-            &image,
-            draw_options.as_raqote(),
+        // Maps the destination rectangle onto the source rectangle of the image.
+        let transform =
+            raqote::Transform::create_translation(-dest.origin.x as f32, -dest.origin.y as f32)
+                .post_scale(
+                    (source.size.width / dest.size.width) as f32,
+                    (source.size.height / dest.size.height) as f32,
+                )
+                .post_translate(Vector2D::new(
+                    source.origin.x as f32,
+                    source.origin.y as f32,
+                ));
+        let source = raqote::Source::Image(
+            image,
+            raqote::ExtendMode::Pad,
+            filter.to_raqote_style(),
+            transform,
         );
+        let path = rect_path(&dest.to_f32());
+        draw_unbounded(self, draw_options.as_raqote(), |dt, draw_options| {
+            dt.fill(&path, &source, draw_options);
+        });
     }
     fn draw_surface_with_shadow(
-        &self,
-        _surface: SourceSurface,
-        _dest: &Point2D<f32>,
-        _color: &Color,
-        _offset: &Vector2D<f32>,
-        _sigma: f32,
-        _operator: CompositionOp,
+        &mut self,
+        surface: SourceSurface,
+        dest: &Point2D<f32>,
+        color: &Color,
+        offset: &Vector2D<f32>,
+        sigma: f32,
+        operator: CompositionOp,
     ) {
-        unimplemented!();
-    }
-    fn fill(&mut self, path: &Path, pattern: Pattern, draw_options: &DrawOptions) {
-        self.fill(
-            path.as_raqote(),
+        let (data, size) = surface.as_raqote();
+        let image = raqote::Image {
+            width: size.width,
+            height: size.height,
+            data,
+        };
+
+        // The shadow is the alpha channel of the surface, blurred and painted
+        // with the shadow color. The blur spreads it beyond the surface bounds.
+        let radius = box_blur_radius(sigma);
+        let padding = 3 * radius;
+        let (width, height) = (size.width as usize, size.height as usize);
+        let (mask_width, mask_height) = (width + 2 * padding, height + 2 * padding);
+        let mut mask = vec![0u8; mask_width * mask_height];
+        for y in 0..height {
+            for x in 0..width {
+                mask[(y + padding) * mask_width + x + padding] = (data[y * width + x] >> 24) as u8;
+            }
+        }
+        blur_alpha_mask(&mut mask, mask_width, mask_height, radius);
+
+        let color = color.as_raqote();
+        let shadow_data: Vec<u32> = mask
+            .iter()
+            .map(|&alpha| {
+                let scale = |component: u8| (component as u32 * alpha as u32 + 127) / 255;
+                scale(color.a) << 24 | scale(color.r) << 16 | scale(color.g) << 8 | scale(color.b)
+            })
+            .collect();
+        let shadow = raqote::Image {
+            width: mask_width as i32,
+            height: mask_height as i32,
+            data: &shadow_data,
+        };
+
+        // Both the surface and its shadow are already in device space.
+        let transform = *self.get_transform();
+        self.set_transform(&Transform2D::identity());
+        let mut options = raqote::DrawOptions::new();
+        options.blend_mode = operator.as_raqote();
+        draw_unbounded(self, &options, |dt, options| {
+            dt.draw_image_at(
+                dest.x + offset.x - padding as f32,
+                dest.y + offset.y - padding as f32,
+                &shadow,
+                options,
+            );
+            dt.draw_image_at(dest.x, dest.y, &image, options);
+        });
+        self.set_transform(&transform);
+    }
+    fn fill(&mut self, path: &Path, pattern: canvas_data::Pattern, draw_options: &DrawOptions) {
+        let path = path.as_raqote();
+        draw_with_pattern(
+            self,
             pattern.as_raqote(),
             draw_options.as_raqote(),
+            |dt, source, draw_options| dt.fill(path, source, draw_options),
         );
     }
     fn fill_rect(
         &mut self,
         rect: &Rect<f32>,
-        pattern: Pattern,
+        pattern: canvas_data::Pattern,
         draw_options: Option<&DrawOptions>,
     ) {
-        let mut pb = raqote::PathBuilder::new();
-        pb.rect(
-            rect.origin.x,
-            rect.origin.y,
-            rect.size.width,
-            rect.size.height,
-        );
+        let path = rect_path(rect);
         let draw_options = if let Some(options) = draw_options {
             *options.as_raqote()
         } else {
             raqote::DrawOptions::new()
         };
 
-        raqote::DrawTarget::fill(self, &pb.finish(), pattern.as_raqote(), &draw_options);
+        draw_with_pattern(
+            self,
+            pattern.as_raqote(),
+            &draw_options,
+            |dt, source, draw_options| dt.fill(&path, source, draw_options),
+        );
     }
     fn get_format(&self) -> SurfaceFormat {
         SurfaceFormat::Raqote(())
@@ -355,33 +545,39 @@ impl GenericDrawTarget for raqote::DrawTarget {
         self.set_transform(matrix);
     }
     fn snapshot(&self) -> SourceSurface {
-        unimplemented!();
+        SourceSurface::Raqote(
+            self.get_data().to_vec(),
+            Size2D::new(self.width(), self.height()),
+        )
     }
     fn stroke(
         &mut self,
         path: &Path,
-        pattern: Pattern,
+        pattern: canvas_data::Pattern,
         stroke_options: &StrokeOptions,
         draw_options: &DrawOptions,
     ) {
-        self.stroke(
-            path.as_raqote(),
+        let path = path.as_raqote();
+        let stroke_options = stroke_options.as_raqote();
+        draw_with_pattern(
+            self,
             pattern.as_raqote(),
-            stroke_options.as_raqote(),
             draw_options.as_raqote(),
+            |dt, source, draw_options| dt.stroke(path, source, stroke_options, draw_options),
         );
     }
     fn stroke_line(
         &mut self,
         start: Point2D<f32>,
         end: Point2D<f32>,
-        pattern: Pattern,
+        pattern: canvas_data::Pattern,
         stroke_options: &StrokeOptions,
         draw_options: &DrawOptions,
     ) {
         let mut pb = raqote::PathBuilder::new();
         pb.move_to(start.x, start.y);
         pb.line_to(end.x, end.y);
+        let path = pb.finish();
         let mut stroke_options = stroke_options.as_raqote().clone();
         let cap = match stroke_options.join {
             raqote::LineJoin::Round => raqote::LineCap::Round,
@@ -389,33 +585,27 @@ impl GenericDrawTarget for raqote::DrawTarget {
         };
         stroke_options.cap = cap;
 
-        self.stroke(
-            &pb.finish(),
+        draw_with_pattern(
+            self,
             pattern.as_raqote(),
-            &stroke_options,
             draw_options.as_raqote(),
+            |dt, source, draw_options| dt.stroke(&path, source, &stroke_options, draw_options),
         );
     }
     fn stroke_rect(
         &mut self,
         rect: &Rect<f32>,
-        pattern: Pattern,
+        pattern: canvas_data::Pattern,
         stroke_options: &StrokeOptions,
         draw_options: &DrawOptions,
     ) {
-        let mut pb = raqote::PathBuilder::new();
-        pb.rect(
-            rect.origin.x,
-            rect.origin.y,
-            rect.size.width,
-            rect.size.height,
-        );
-
-        self.stroke(
-            &pb.finish(),
+        let path = rect_path(rect);
+        let stroke_options = stroke_options.as_raqote();
+        draw_with_pattern(
+            self,
             pattern.as_raqote(),
-            stroke_options.as_raqote(),
             draw_options.as_raqote(),
+            |dt, source, draw_options| dt.stroke(&path, source, stroke_options, draw_options),
         );
     }
     #[allow(unsafe_code)]
@@ -441,12 +631,113 @@ impl GenericDrawTarget for raqote::DrawTarget {
     }
 }
 
+fn rect_path(rect: &Rect<f32>) -> raqote::Path {
+    let mut pb = raqote::PathBuilder::new();
+    pb.rect(
+        rect.origin.x,
+        rect.origin.y,
+        rect.size.width,
+        rect.size.height,
+    );
+    pb.finish()
+}
+
+/// Draws with `draw`, honouring the composition operators that also affect
+/// the pixels outside of the drawn shape: the shape is then drawn on a
+/// transparent layer, which gets composited onto the whole draw target.
+fn draw_unbounded<F>(dt: &mut raqote::DrawTarget, draw_options: &raqote::DrawOptions, draw: F)
+where
+    F: FnOnce(&mut raqote::DrawTarget, &raqote::DrawOptions),
+{
+    match draw_options.blend_mode {
+        raqote::BlendMode::Src |
+        raqote::BlendMode::SrcIn |
+        raqote::BlendMode::SrcOut |
+        raqote::BlendMode::DstIn |
+        raqote::BlendMode::DstAtop => {},
+        _ => return draw(dt, draw_options),
+    }
+
+    let mut layer = raqote::DrawTarget::new(dt.width(), dt.height());
+    layer.set_transform(dt.get_transform());
+    let mut layer_options = *draw_options;
+    layer_options.blend_mode = raqote::BlendMode::SrcOver;
+    draw(&mut layer, &layer_options);
+
+    let transform = *dt.get_transform();
+    dt.set_transform(&Transform2D::identity());
+    let mut options = raqote::DrawOptions::new();
+    options.blend_mode = draw_options.blend_mode;
+    let image = raqote::Image {
+        width: layer.width(),
+        height: layer.height(),
+        data: layer.get_data(),
+    };
+    dt.draw_image_at(0., 0., &image, &options);
+    dt.set_transform(&transform);
+}
+
+/// Draws with `draw` using the source of `pattern`, restricted to the area
+/// the pattern paints.
+fn draw_with_pattern<F>(
+    dt: &mut raqote::DrawTarget,
+    pattern: &Pattern,
+    draw_options: &raqote::DrawOptions,
+    draw: F,
+) where
+    F: FnOnce(&mut raqote::DrawTarget, &raqote::Source, &raqote::DrawOptions),
+{
+    let source = pattern.source();
+    draw_unbounded(dt, draw_options, |dt, draw_options| {
+        let area = pattern.paint_area(Size2D::new(dt.width(), dt.height()), dt.get_transform());
+        if let Some(ref area) = area {
+            dt.push_clip(&rect_path(area));
+        }
+        draw(dt, &source, draw_options);
+        if area.is_some() {
+            dt.pop_clip();
+        }
+    });
+}
+
+fn to_pixels(data: &[u8]) -> Vec<u32> {
+    data.chunks_exact(4)
+        .map(|p| u32::from_ne_bytes([p[0], p[1], p[2], p[3]]))
+        .collect()
+}
+
 struct PathBuilder(Option<raqote::PathBuilder>);
 
 impl PathBuilder {
     fn new() -> PathBuilder {
         PathBuilder(Some(raqote::PathBuilder::new()))
     }
+
+    fn current_point(&mut self) -> Option<Point2D<f32>> {
+        let path = self.0.take().unwrap().finish();
+        let mut current_point = None;
+        let mut closed = false;
+        for op in path.ops.iter().rev() {
+            let point = match op {
+                PathOp::MoveTo(point) => point,
+                // A closed subpath continues from the point it started at.
+                PathOp::LineTo(point) | PathOp::CubicTo(_, _, point) | PathOp::QuadTo(_, point)
+                    if !closed =>
+                {
+                    point
+                },
+                PathOp::LineTo(_) | PathOp::CubicTo(..) | PathOp::QuadTo(..) => continue,
+                PathOp::Close => {
+                    closed = true;
+                    continue;
+                },
+            };
+            current_point = Some(Point2D::new(point.x, point.y));
+            break;
+        }
+        self.0 = Some(raqote::PathBuilder::from(path));
+        current_point
+    }
 }
 
 impl GenericPathBuilder for PathBuilder {
@@ -456,12 +747,17 @@ impl GenericPathBuilder for PathBuilder {
         radius: f32,
         start_angle: f32,
         end_angle: f32,
-        _anticlockwise: bool,
+        anticlockwise: bool,
     ) {
-        self.0
-            .as_mut()
-            .unwrap()
-            .arc(origin.x, origin.y, radius, start_angle, end_angle);
+        self.ellipse(
+            origin,
+            radius,
+            radius,
+            0.,
+            start_angle,
+            end_angle,
+            anticlockwise,
+        );
     }
     fn bezier_curve_to(
         &mut self,
@@ -481,32 +777,37 @@ impl GenericPathBuilder for PathBuilder {
     fn close(&mut self) {
         self.0.as_mut().unwrap().close();
     }
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-ellipse
     fn ellipse(
         &mut self,
-        _origin: Point2D<f32>,
-        _radius_x: f32,
-        _radius_y: f32,
-        _rotation_angle: f32,
-        _start_angle: f32,
-        _end_angle: f32,
-        _anticlockwise: bool,
+        origin: Point2D<f32>,
+        radius_x: f32,
+        radius_y: f32,
+        rotation_angle: f32,
+        start_angle: f32,
+        end_angle: f32,
+        anticlockwise: bool,
     ) {
-        unimplemented!();
+        let (start, curves) = ellipse_to_bezier_curves(
+            origin,
+            radius_x,
+            radius_y,
+            rotation_angle,
+            start_angle,
+            end_angle,
+            anticlockwise,
+        );
+        if self.current_point().is_some() {
+            self.line_to(start);
+        } else {
+            self.move_to(start);
+        }
+        for [control_point1, control_point2, end_point] in curves {
+            self.bezier_curve_to(&control_point1, &control_point2, &end_point);
+        }
     }
     fn get_current_point(&mut self) -> Point2D<f32> {
-        let path = self.finish();
-
-        for op in path.as_raqote().ops.iter().rev() {
-            match op {
-                PathOp::MoveTo(point) | PathOp::LineTo(point) => {
-                    return Point2D::new(point.x, point.y)
-                },
-                PathOp::CubicTo(_, _, point) => return Point2D::new(point.x, point.y),
-                PathOp::QuadTo(_, point) => return Point2D::new(point.x, point.y),
-                PathOp::Close => {},
-            };
-        }
-        panic!("dead end");
+        self.current_point().expect("no current point")
     }
     fn line_to(&mut self, point: Point2D<f32>) {
         self.0.as_mut().unwrap().line_to(point.x, point.y);
@@ -557,39 +858,44 @@ impl ToRaqoteStyle for LineCapStyle {
     }
 }
 
-pub trait ToRaqoteSource<'a> {
-    fn to_raqote_source(self) -> Option<raqote::Source<'a>>;
+pub trait ToRaqotePattern {
+    fn to_raqote_pattern(self) -> Option<Pattern>;
 }
 
-impl<'a> ToRaqoteSource<'a> for FillOrStrokeStyle {
-    #[allow(unsafe_code)]
-    fn to_raqote_source(self) -> Option<raqote::Source<'a>> {
+impl ToRaqotePattern for FillOrStrokeStyle {
+    fn to_raqote_pattern(self) -> Option<Pattern> {
         use canvas_traits::canvas::FillOrStrokeStyle::*;
 
         match self {
-            Color(rgba) => Some(raqote::Source::Solid(raqote::SolidSource {
-                r: rgba.red,
-                g: rgba.green,
-                b: rgba.blue,
-                a: rgba.alpha,
-            })),
-            LinearGradient(_) => unimplemented!(),
-            RadialGradient(_) => unimplemented!(),
-            Surface(ref surface) => {
-                let data = &surface.surface_data[..];
-                Some(raqote::Source::Image(
-                    raqote::Image {
-                        data: unsafe {
-                            std::slice::from_raw_parts(data.as_ptr() as *const u32, data.len() / 4)
-                        },
-                        width: surface.surface_size.width as i32,
-                        height: surface.surface_size.height as i32,
+            Color(rgba) => Some(Pattern::Color(rgba.to_raqote_style())),
+            LinearGradient(style) => {
+                let stops = style.stops.into_iter().map(|s| s.to_raqote_style());
+                Some(Pattern::LinearGradient(
+                    raqote::Gradient {
+                        stops: stops.collect(),
                     },
-                    raqote::ExtendMode::Repeat, // TODO: repeat-x, repeat-y ?
-                    raqote::FilterMode::Bilinear,
-                    raqote::Transform::identity(),
+                    Point2D::new(style.x0 as f32, style.y0 as f32),
+                    Point2D::new(style.x1 as f32, style.y1 as f32),
+                ))
+            },
+            RadialGradient(style) => {
+                let stops = style.stops.into_iter().map(|s| s.to_raqote_style());
+                Some(Pattern::RadialGradient(
+                    raqote::Gradient {
+                        stops: stops.collect(),
+                    },
+                    Point2D::new(style.x0 as f32, style.y0 as f32),
+                    style.r0 as f32,
+                    Point2D::new(style.x1 as f32, style.y1 as f32),
+                    style.r1 as f32,
                 ))
             },
+            Surface(surface) => Some(Pattern::Surface(SurfacePattern {
+                data: Arc::new(to_pixels(&surface.surface_data)),
+                size: surface.surface_size.to_i32(),
+                repeat_x: surface.repeat_x,
+                repeat_y: surface.repeat_y,
+            })),
         }
     }
 }
@@ -605,16 +911,45 @@ impl Color {
 impl ToRaqoteStyle for RGBA {
     type Target = raqote::SolidSource;
 
+    /// raqote expects premultiplied colors.
     fn to_raqote_style(self) -> Self::Target {
+        let premultiply = |component: u8| (component as u32 * self.alpha as u32 / 255) as u8;
         raqote::SolidSource {
-            r: self.red,
-            g: self.green,
-            b: self.blue,
+            r: premultiply(self.red),
+            g: premultiply(self.green),
+            b: premultiply(self.blue),
             a: self.alpha,
         }
     }
 }
 
+impl ToRaqoteStyle for CanvasGradientStop {
+    type Target = raqote::GradientStop;
+
+    fn to_raqote_style(self) -> Self::Target {
+        raqote::GradientStop {
+            position: self.offset as f32,
+            color: raqote::Color::new(
+                self.color.alpha,
+                self.color.red,
+                self.color.green,
+                self.color.blue,
+            ),
+        }
+    }
+}
+
+impl ToRaqoteStyle for Filter {
+    type Target = raqote::FilterMode;
+
+    fn to_raqote_style(self) -> Self::Target {
+        match self {
+            Filter::Linear => raqote::FilterMode::Bilinear,
+            Filter::Point => raqote::FilterMode::Nearest,
+        }
+    }
+}
+
 impl ToRaqoteStyle for CompositionOrBlending {
     type Target = raqote::BlendMode;
 
@@ -645,7 +980,7 @@ impl ToRaqoteStyle for BlendingStyle {
             BlendingStyle::Saturation => raqote::BlendMode::Saturation,
             BlendingStyle::Color => raqote::BlendMode::Color,
             BlendingStyle::Luminosity => raqote::BlendMode::Luminosity,
-            BlendingStyle::ColorBurn => unimplemented!("raqote doesn't support colorburn"),
+            BlendingStyle::ColorBurn => raqote::BlendMode::ColorBurn,
         }
     }
 }
@@ -671,9 +1006,17 @@ impl ToRaqoteStyle for CompositionStyle {
 }
 
 impl SourceSurface {
-    fn as_raqote(&self) -> &Vec<u8> {
+    fn as_raqote(&self) -> (&Vec<u32>, Size2D<i32>) {
+        match self {
+            SourceSurface::Raqote(s, size) => (s, *size),
+        }
+    }
+}
+
+impl CompositionOp {
+    fn as_raqote(&self) -> raqote::BlendMode {
         match self {
-            SourceSurface::Raqote(s) => s,
+            CompositionOp::Raqote(op) => *op,
         }
     }
 }
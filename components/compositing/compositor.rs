@@ -21,7 +21,7 @@ use image::{DynamicImage, ImageFormat};
 use ipc_channel::ipc;
 use libc::c_void;
 use msg::constellation_msg::{PipelineId, PipelineIndex, PipelineNamespaceId};
-use net_traits::image::base::Image;
+use net_traits::image::base::{Image, ImageFrame};
 use num_traits::FromPrimitive;
 #[cfg(feature = "gl")]
 use pixels::PixelFormat;
@@ -39,6 +39,7 @@ use std::fs::{create_dir_all, File};
 use std::io::Write;
 use std::num::NonZeroU32;
 use std::rc::Rc;
+use style_traits::media_features::DeviceFeatures;
 use style_traits::viewport::ViewportConstraints;
 use style_traits::{CSSPixel, DevicePixel, PinchZoomFactor};
 use time::{now, precise_time_ns, precise_time_s};
@@ -193,6 +194,9 @@ pub struct IOCompositor<Window: WindowMethods + ?Sized> {
     /// The coordinates of the native window, its view and the screen.
     embedder_coordinates: EmbedderCoordinates,
 
+    /// The media features of the native window, as last reported by the embedder.
+    device_features: DeviceFeatures,
+
     /// Current mouse cursor.
     cursor: Cursor,
 
@@ -292,6 +296,7 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
 
         IOCompositor {
             embedder_coordinates: window.get_coordinates(),
+            device_features: window.get_device_features(),
             window,
             port: state.receiver,
             root_pipeline: None,
@@ -675,6 +680,7 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
         let data = WindowSizeData {
             device_pixel_ratio: dppx,
             initial_viewport: initial_viewport,
+            device_features: self.device_features,
         };
 
         let top_level_browsing_context_id = self
@@ -693,14 +699,18 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
         debug!("compositor resize requested");
 
         let old_coords = self.embedder_coordinates;
+        let old_device_features = self.device_features;
         self.embedder_coordinates = self.window.get_coordinates();
+        self.device_features = self.window.get_device_features();
 
         // A size change could also mean a resolution change.
         if self.embedder_coordinates.hidpi_factor != old_coords.hidpi_factor {
             self.update_zoom_transform();
         }
 
-        if self.embedder_coordinates.viewport == old_coords.viewport {
+        if self.embedder_coordinates.viewport == old_coords.viewport &&
+            self.device_features == old_device_features
+        {
             return;
         }
 
@@ -1384,6 +1394,11 @@ impl<Window: WindowMethods + ?Sized> IOCompositor<Window> {
                     format: PixelFormat::RGB8,
                     bytes: ipc::IpcSharedMemory::from_bytes(&*img),
                     id: None,
+                    density: 1.,
+                    frames: vec![ImageFrame {
+                        delay: None,
+                        byte_range: 0..img.len(),
+                    }],
                 })
             },
             #[cfg(feature = "gl")]
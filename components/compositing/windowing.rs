@@ -18,6 +18,7 @@ use std::fmt::{Debug, Error, Formatter};
 #[cfg(feature = "gl")]
 use std::rc::Rc;
 use std::time::Duration;
+use style_traits::media_features::DeviceFeatures;
 use style_traits::DevicePixel;
 use webrender_api::units::DevicePoint;
 use webrender_api::units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize};
@@ -101,6 +102,8 @@ pub enum WindowEvent {
     CaptureWebRender,
     /// Toggle sampling profiler with the given sampling rate and max duration.
     ToggleSamplingProfiler(Duration, Duration),
+    /// Remove the storage data, such as localStorage, of the origin of the given URL.
+    ClearStorageData(ServoUrl),
 }
 
 impl Debug for WindowEvent {
@@ -131,6 +134,7 @@ impl Debug for WindowEvent {
             WindowEvent::CaptureWebRender => write!(f, "CaptureWebRender"),
             WindowEvent::ToggleSamplingProfiler(..) => write!(f, "ToggleSamplingProfiler"),
             WindowEvent::ExitFullScreen(..) => write!(f, "ExitFullScreen"),
+            WindowEvent::ClearStorageData(..) => write!(f, "ClearStorageData"),
         }
     }
 }
@@ -163,6 +167,12 @@ pub trait WindowMethods {
     fn get_native_display(&self) -> NativeDisplay;
     /// Get the GL api
     fn get_gl_api(&self) -> GlApi;
+    /// Get the characteristics of the native window and user preferences that
+    /// media queries can test for. Embedders should trigger a resize event
+    /// when these change.
+    fn get_device_features(&self) -> DeviceFeatures {
+        DeviceFeatures::default()
+    }
 }
 
 pub trait EmbedderMethods {
@@ -47,7 +47,8 @@ pub struct Opts {
     pub time_profiling: Option<OutputOptions>,
 
     /// When the profiler is enabled, this is an optional path to dump a self-contained HTML file
-    /// visualizing the traces as a timeline.
+    /// visualizing the traces as a timeline, or a Chrome Trace Event JSON file if the path ends
+    /// in `.json`.
     pub time_profiler_trace_path: Option<String>,
 
     /// `None` to disable the memory profiler or `Some` with an interval in seconds to enable it
@@ -633,7 +634,8 @@ pub fn from_cmdline_args(args: &[String]) -> ArgumentParsingResult {
     opts.optflagopt(
         "",
         "profiler-trace-path",
-        "Path to dump a self-contained HTML timeline of profiler traces",
+        "Path to dump a self-contained HTML timeline of profiler traces, \
+         or a Chrome Trace Event file if the path ends in .json",
         "",
     );
     opts.optflagopt(
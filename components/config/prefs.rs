@@ -190,6 +190,9 @@ mod gen {
                 gamepad: {
                     enabled: bool,
                 },
+                indexeddb: {
+                    enabled: bool,
+                },
                 microdata: {
                     testing: {
                         enabled: bool,
@@ -437,6 +440,9 @@ mod gen {
                 columns: {
                     enabled: bool,
                 },
+                grid: {
+                    enabled: bool,
+                },
                 #[serde(default = "default_layout_threads")]
                 threads: i64,
                 viewport: {
@@ -459,9 +465,21 @@ mod gen {
                 http_cache: {
                     #[serde(rename = "network.http-cache.disabled")]
                     disabled: bool,
+                    disk: {
+                        #[serde(rename = "network.http-cache.disk.enabled")]
+                        enabled: bool,
+                        #[serde(rename = "network.http-cache.disk.max-size")]
+                        max_size: i64,
+                    },
                 },
                 mime: {
                     sniff: bool,
+                },
+                proxy: {
+                    http: String,
+                    https: String,
+                    #[serde(rename = "network.proxy.no-proxy")]
+                    no_proxy: String,
                 }
             },
             session_history: {
@@ -479,6 +497,10 @@ mod gen {
                     #[serde(rename = "shell.native-titlebar.enabled")]
                     enabled: bool,
                 },
+                #[serde(rename = "shell.prefers-color-scheme")]
+                prefers_color_scheme: String,
+                #[serde(rename = "shell.prefers-reduced-motion")]
+                prefers_reduced_motion: bool,
                 searchpage: String,
             },
             webgl: {
@@ -129,15 +129,20 @@ use msg::constellation_msg::{
     BrowsingContextGroupId, BrowsingContextId, HistoryStateId, PipelineId,
     TopLevelBrowsingContextId,
 };
+use msg::constellation_msg::{MessagePortId, MessagePortRouterId};
 use msg::constellation_msg::{
     PipelineNamespace, PipelineNamespaceId, PipelineNamespaceRequest, TraversalDirection,
 };
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
 use net_traits::pub_domains::reg_host;
 use net_traits::request::RequestBuilder;
 use net_traits::storage_thread::{StorageThreadMsg, StorageType};
 use net_traits::{self, FetchResponseMsg, IpcSend, ResourceThreads};
 use profile_traits::mem;
 use profile_traits::time;
+use script_traits::transferable::{MessagePortMsg, PortMessageTask, StructuredSerializedData};
+use script_traits::webdriver_msg::WebDriverWindowState;
+use script_traits::webdriver_msg::{WebDriverUserPromptAction, WebDriverUserPromptError};
 use script_traits::CompositorEvent::{MouseButtonEvent, MouseMoveEvent};
 use script_traits::MouseEventType;
 use script_traits::{webdriver_msg, LogEntry, ScriptToConstellationChan, ServiceWorkerMsg};
@@ -169,6 +174,7 @@ use std::process;
 use std::rc::{Rc, Weak};
 use std::sync::Arc;
 use std::thread;
+use style_traits::media_features::DeviceFeatures;
 use style_traits::viewport::ViewportConstraints;
 use style_traits::CSSPixel;
 use webvr_traits::{WebVREvent, WebVRMsg};
@@ -185,6 +191,48 @@ struct Browser {
 
     /// The joint session history for this browser.
     session_history: JointSessionHistory,
+
+    /// The user prompt that script is blocked on in this browser, if it is being handled by
+    /// WebDriver.
+    user_prompt: Option<UserPrompt>,
+}
+
+/// A user prompt opened by `alert()`, `confirm()` or `prompt()`, and the channel that the
+/// blocked script thread waits on for the answer.
+///
+/// https://w3c.github.io/webdriver/#user-prompts
+enum UserPrompt {
+    Alert(String, IpcSender<()>),
+    Confirm(String, IpcSender<bool>),
+    /// The message of the prompt, the current value of its text field, and the channel.
+    Prompt(String, String, IpcSender<Option<String>>),
+}
+
+impl UserPrompt {
+    fn message(&self) -> &str {
+        match *self {
+            UserPrompt::Alert(ref message, _) |
+            UserPrompt::Confirm(ref message, _) |
+            UserPrompt::Prompt(ref message, _, _) => message,
+        }
+    }
+
+    /// Closes this prompt, unblocking the script thread, and returns its message.
+    fn close(self, action: WebDriverUserPromptAction) -> String {
+        let accept = action == WebDriverUserPromptAction::Accept;
+        let (message, result) = match self {
+            UserPrompt::Alert(message, sender) => (message, sender.send(())),
+            UserPrompt::Confirm(message, sender) => (message, sender.send(accept)),
+            UserPrompt::Prompt(message, value, sender) => (
+                message,
+                sender.send(if accept { Some(value) } else { None }),
+            ),
+        };
+        if let Err(e) = result {
+            warn!("Failed to close user prompt ({:?}).", e);
+        }
+        message
+    }
 }
 
 /// A browsing context group.
@@ -205,6 +253,24 @@ struct BrowsingContextGroup {
     event_loops: HashMap<Host, Weak<EventLoop>>,
 }
 
+/// Where the tasks sent to a message port go.
+enum TransferState {
+    /// The port is managed by the global with the given message-port router.
+    Managed(MessagePortRouterId),
+    /// The port is being transferred to another global, and the tasks sent to it
+    /// are buffered until the transfer completes.
+    TransferInProgress(VecDeque<PortMessageTask>),
+}
+
+/// The information the constellation keeps about a message port.
+struct MessagePortInfo {
+    /// Where the tasks for the port go.
+    state: TransferState,
+
+    /// The port this one is entangled with, if any.
+    entangled_with: Option<MessagePortId>,
+}
+
 /// The `Constellation` itself. In the servo browser, there is one
 /// constellation, which maintains all of the browser global data.
 /// In embedded applications, there may be more than one constellation,
@@ -347,6 +413,12 @@ pub struct Constellation<Message, LTF, STF> {
     /// The set of all the browsing contexts in the browser.
     browsing_contexts: HashMap<BrowsingContextId, BrowsingContext>,
 
+    /// The message ports of all the globals, and where to route their tasks.
+    message_ports: HashMap<MessagePortId, MessagePortInfo>,
+
+    /// The message-port routers of the globals managing message ports.
+    message_port_routers: HashMap<MessagePortRouterId, IpcSender<MessagePortMsg>>,
+
     /// A user agent holds a a set of browsing context groups.
     ///
     /// https://html.spec.whatwg.org/multipage/#browsing-context-group-set
@@ -660,6 +732,7 @@ where
         state: InitialConstellationState,
         initial_window_size: Size2D<u32, DeviceIndependentPixel>,
         device_pixels_per_px: Option<f32>,
+        device_features: DeviceFeatures,
         random_pipeline_closure_probability: Option<f32>,
         random_pipeline_closure_seed: Option<usize>,
         is_running_problem_test: bool,
@@ -722,6 +795,8 @@ where
                 // Zero is reserved for the embedder.
                 PipelineNamespace::install(PipelineNamespaceId(1));
 
+                let canvas_chan = CanvasPaintThread::start(state.font_cache_thread.clone());
+
                 let mut constellation: Constellation<Message, LTF, STF> = Constellation {
                     namespace_receiver,
                     namespace_sender,
@@ -753,6 +828,8 @@ where
                     browsing_context_group_next_id: Default::default(),
                     pipelines: HashMap::new(),
                     browsing_contexts: HashMap::new(),
+                    message_ports: HashMap::new(),
+                    message_port_routers: HashMap::new(),
                     pending_changes: vec![],
                     // We initialize the namespace at 2, since we reserved
                     // namespace 0 for the embedder, and 0 for the constellation
@@ -762,6 +839,7 @@ where
                     window_size: WindowSizeData {
                         initial_viewport: initial_window_size.to_f32() * Scale::new(1.0),
                         device_pixel_ratio: Scale::new(device_pixels_per_px.unwrap_or(1.0)),
+                        device_features,
                     },
                     phantom: PhantomData,
                     webdriver: WebDriverData::new(),
@@ -781,7 +859,7 @@ where
                     webgl_threads: state.webgl_threads,
                     webvr_chan: state.webvr_chan,
                     webxr_registry: state.webxr_registry,
-                    canvas_chan: CanvasPaintThread::start(),
+                    canvas_chan,
                     pending_approval_navigations: HashMap::new(),
                     pressed_mouse_buttons: 0,
                     is_running_problem_test,
@@ -1023,6 +1101,7 @@ where
             event_loop,
             load_data,
             device_pixel_ratio: self.window_size.device_pixel_ratio,
+            device_features: self.window_size.device_features,
             prev_visibility: is_visible,
             webrender_api_sender: self.webrender_api_sender.clone(),
             webrender_document: self.webrender_document,
@@ -1467,6 +1546,13 @@ where
             FromCompositorMsg::ExitFullScreen(top_level_browsing_context_id) => {
                 self.handle_exit_fullscreen_msg(top_level_browsing_context_id);
             },
+            FromCompositorMsg::ClearStorageData(url) => {
+                // Both resource threads share the same storage thread.
+                let msg = StorageThreadMsg::ClearOrigin(url);
+                if let Err(e) = self.public_resource_threads.send(msg) {
+                    warn!("Clearing storage data failed ({})", e);
+                }
+            },
         }
     }
 
@@ -1488,8 +1574,31 @@ where
 
         match content {
             FromScriptMsg::ForwardToEmbedder(embedder_msg) => {
-                self.embedder_proxy
-                    .send((Some(source_top_ctx_id), embedder_msg));
+                self.handle_forward_to_embedder(source_top_ctx_id, embedder_msg);
+            },
+            FromScriptMsg::NewMessagePortRouter(router_id, router) => {
+                self.handle_new_messageport_router(router_id, router);
+            },
+            FromScriptMsg::RemoveMessagePortRouter(router_id) => {
+                self.handle_remove_messageport_router(router_id);
+            },
+            FromScriptMsg::NewMessagePort(router_id, port_id) => {
+                self.handle_new_messageport(router_id, port_id);
+            },
+            FromScriptMsg::EntanglePorts(port1, port2) => {
+                self.handle_entangle_messageports(port1, port2);
+            },
+            FromScriptMsg::MessagePortShipped(port_id) => {
+                self.handle_messageport_shipped(port_id);
+            },
+            FromScriptMsg::CompleteMessagePortTransfer(router_id, port_id) => {
+                self.handle_complete_messageport_transfer(router_id, port_id);
+            },
+            FromScriptMsg::RerouteMessagePort(port_id, task) => {
+                self.handle_reroute_messageport(port_id, task);
+            },
+            FromScriptMsg::RemoveMessagePort(port_id) => {
+                self.handle_remove_messageport(port_id);
             },
             FromScriptMsg::PipelineExited => {
                 self.handle_pipeline_exited(source_pipeline_id);
@@ -1685,6 +1794,130 @@ where
         }
     }
 
+    fn handle_new_messageport_router(
+        &mut self,
+        router_id: MessagePortRouterId,
+        router: IpcSender<MessagePortMsg>,
+    ) {
+        self.message_port_routers.insert(router_id, router);
+    }
+
+    fn handle_remove_messageport_router(&mut self, router_id: MessagePortRouterId) {
+        self.message_port_routers.remove(&router_id);
+        let removed_ports: Vec<MessagePortId> = self
+            .message_ports
+            .iter()
+            .filter_map(|(id, info)| match info.state {
+                TransferState::Managed(managed_by) if managed_by == router_id => Some(*id),
+                _ => None,
+            })
+            .collect();
+        for port_id in removed_ports {
+            self.handle_remove_messageport(port_id);
+        }
+    }
+
+    fn handle_new_messageport(&mut self, router_id: MessagePortRouterId, port_id: MessagePortId) {
+        let info = MessagePortInfo {
+            state: TransferState::Managed(router_id),
+            entangled_with: None,
+        };
+        if self.message_ports.insert(port_id, info).is_some() {
+            warn!(
+                "Constellation asked to track an existing message port {}.",
+                port_id
+            );
+        }
+    }
+
+    fn handle_entangle_messageports(&mut self, port1: MessagePortId, port2: MessagePortId) {
+        for &(port, other) in &[(port1, port2), (port2, port1)] {
+            match self.message_ports.get_mut(&port) {
+                Some(info) => info.entangled_with = Some(other),
+                None => warn!(
+                    "Constellation asked to entangle unknown message port {}.",
+                    port
+                ),
+            }
+        }
+    }
+
+    fn handle_messageport_shipped(&mut self, port_id: MessagePortId) {
+        match self.message_ports.get_mut(&port_id) {
+            Some(info) => {
+                if let TransferState::Managed(_) = info.state {
+                    info.state = TransferState::TransferInProgress(VecDeque::new());
+                }
+            },
+            None => warn!(
+                "Constellation asked to ship unknown message port {}.",
+                port_id
+            ),
+        }
+    }
+
+    fn handle_complete_messageport_transfer(
+        &mut self,
+        router_id: MessagePortRouterId,
+        port_id: MessagePortId,
+    ) {
+        let router = match self.message_port_routers.get(&router_id) {
+            Some(router) => router,
+            None => return warn!("Message port transferred to unknown router {}.", router_id),
+        };
+        let info = match self.message_ports.get_mut(&port_id) {
+            Some(info) => info,
+            None => return warn!("Unknown message port {} transferred.", port_id),
+        };
+        let buffered = match replace(&mut info.state, TransferState::Managed(router_id)) {
+            TransferState::TransferInProgress(buffered) => buffered,
+            TransferState::Managed(_) => {
+                warn!(
+                    "Message port {} transferred without being shipped.",
+                    port_id
+                );
+                VecDeque::new()
+            },
+        };
+        if let Err(e) = router.send(MessagePortMsg::CompleteTransfer(port_id, buffered)) {
+            warn!("Failed to complete the transfer of message port ({:?}).", e);
+        }
+    }
+
+    fn handle_reroute_messageport(&mut self, port_id: MessagePortId, task: PortMessageTask) {
+        let info = match self.message_ports.get_mut(&port_id) {
+            Some(info) => info,
+            None => return warn!("Task sent to closed message port {}.", port_id),
+        };
+        match info.state {
+            TransferState::Managed(router_id) => {
+                let result = match self.message_port_routers.get(&router_id) {
+                    Some(router) => router.send(MessagePortMsg::NewTask(port_id, task)),
+                    None => return warn!("Message port {} has no router.", port_id),
+                };
+                if let Err(e) = result {
+                    warn!("Failed to send a task to message port ({:?}).", e);
+                }
+            },
+            TransferState::TransferInProgress(ref mut buffered) => buffered.push_back(task),
+        }
+    }
+
+    fn handle_remove_messageport(&mut self, port_id: MessagePortId) {
+        let entangled_with = match self.message_ports.remove(&port_id) {
+            Some(info) => info.entangled_with,
+            None => {
+                return warn!(
+                    "Constellation asked to remove unknown message port {}.",
+                    port_id
+                )
+            },
+        };
+        if let Some(info) = entangled_with.and_then(|id| self.message_ports.get_mut(&id)) {
+            info.entangled_with = None;
+        }
+    }
+
     fn handle_register_serviceworker(&self, scope_things: ScopeThings, scope: ServoUrl) {
         if let Some(ref mgr) = self.swmanager_chan {
             let _ = mgr.send(ServiceWorkerMsg::RegisterServiceWorker(scope_things, scope));
@@ -1792,6 +2025,7 @@ where
         let (core_sender, core_receiver) = ipc::channel().expect("Failed to create IPC channel!");
         let (storage_sender, storage_receiver) =
             ipc::channel().expect("Failed to create IPC channel!");
+        let (idb_sender, idb_receiver) = ipc::channel().expect("Failed to create IPC channel!");
 
         debug!("Exiting core resource threads.");
         if let Err(e) = self
@@ -1821,6 +2055,14 @@ where
             warn!("Exit storage thread failed ({})", e);
         }
 
+        debug!("Exiting IndexedDB thread.");
+        if let Err(e) = self
+            .public_resource_threads
+            .send(IndexedDBThreadMsg::Exit(idb_sender))
+        {
+            warn!("Exit IndexedDB thread failed ({})", e);
+        }
+
         debug!("Exiting bluetooth thread.");
         if let Err(e) = self.bluetooth_thread.send(BluetoothRequest::Exit) {
             warn!("Exit bluetooth thread failed ({})", e);
@@ -1874,6 +2116,9 @@ where
         if let Err(e) = storage_receiver.recv() {
             warn!("Exit storage thread failed ({})", e);
         }
+        if let Err(e) = idb_receiver.recv() {
+            warn!("Exit IndexedDB thread failed ({})", e);
+        }
 
         debug!("Asking compositor to complete shutdown.");
         self.compositor_proxy
@@ -2084,6 +2329,7 @@ where
             Browser {
                 focused_browsing_context_id: browsing_context_id,
                 session_history: JointSessionHistory::new(),
+                user_prompt: None,
             },
         );
 
@@ -2148,6 +2394,7 @@ where
             let window_size = WindowSizeData {
                 initial_viewport: data.size,
                 device_pixel_ratio: self.window_size.device_pixel_ratio,
+                device_features: self.window_size.device_features,
             };
 
             self.resize_browsing_context(window_size, type_, data.id);
@@ -2423,6 +2670,7 @@ where
             Browser {
                 focused_browsing_context_id: new_browsing_context_id,
                 session_history: JointSessionHistory::new(),
+                user_prompt: None,
             },
         );
 
@@ -3203,7 +3451,7 @@ where
         browsing_context_id: BrowsingContextId,
         source_pipeline: PipelineId,
         origin: Option<ImmutableOrigin>,
-        data: Vec<u8>,
+        data: StructuredSerializedData,
     ) {
         let pipeline_id = match self.browsing_contexts.get(&browsing_context_id) {
             None => {
@@ -3403,6 +3651,39 @@ where
         }
     }
 
+    fn handle_forward_to_embedder(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        msg: EmbedderMsg,
+    ) {
+        // When WebDriver is enabled, user prompts are left open until a WebDriver command
+        // closes them, instead of being shown by the embedder.
+        if opts::get().webdriver_port.is_none() {
+            return self
+                .embedder_proxy
+                .send((Some(top_level_browsing_context_id), msg));
+        }
+        let prompt = match msg {
+            EmbedderMsg::Alert(message, sender) => UserPrompt::Alert(message, sender),
+            EmbedderMsg::Confirm(message, sender) => UserPrompt::Confirm(message, sender),
+            EmbedderMsg::Prompt(message, default, sender) => {
+                UserPrompt::Prompt(message, default, sender)
+            },
+            msg => {
+                return self
+                    .embedder_proxy
+                    .send((Some(top_level_browsing_context_id), msg));
+            },
+        };
+        match self.browsers.get_mut(&top_level_browsing_context_id) {
+            Some(browser) => browser.user_prompt = Some(prompt),
+            None => warn!(
+                "Browser {} opened a user prompt after closure.",
+                top_level_browsing_context_id
+            ),
+        }
+    }
+
     fn handle_webdriver_msg(&mut self, msg: WebDriverCommandMsg) {
         // Find the script channel for the given parent pipeline,
         // and pass the event to that script thread.
@@ -3536,6 +3817,70 @@ where
                 self.compositor_proxy
                     .send(ToCompositorMsg::CreatePng(rect, reply));
             },
+            WebDriverCommandMsg::GetAlertText(top_level_browsing_context_id, reply) => {
+                let result = match self
+                    .browsers
+                    .get(&top_level_browsing_context_id)
+                    .and_then(|browser| browser.user_prompt.as_ref())
+                {
+                    Some(prompt) => Ok(prompt.message().to_owned()),
+                    None => Err(WebDriverUserPromptError::NoSuchAlert),
+                };
+                let _ = reply.send(result);
+            },
+            WebDriverCommandMsg::SendAlertText(top_level_browsing_context_id, text, reply) => {
+                let result = match self
+                    .browsers
+                    .get_mut(&top_level_browsing_context_id)
+                    .and_then(|browser| browser.user_prompt.as_mut())
+                {
+                    Some(UserPrompt::Prompt(_, ref mut value, _)) => {
+                        *value = text;
+                        Ok(())
+                    },
+                    Some(_) => Err(WebDriverUserPromptError::ElementNotInteractable),
+                    None => Err(WebDriverUserPromptError::NoSuchAlert),
+                };
+                let _ = reply.send(result);
+            },
+            WebDriverCommandMsg::HandleUserPrompt(top_level_browsing_context_id, action, reply) => {
+                let result = match self
+                    .browsers
+                    .get_mut(&top_level_browsing_context_id)
+                    .and_then(|browser| browser.user_prompt.take())
+                {
+                    Some(prompt) => Ok(prompt.close(action)),
+                    None => Err(WebDriverUserPromptError::NoSuchAlert),
+                };
+                let _ = reply.send(result);
+            },
+            WebDriverCommandMsg::GetTopLevelBrowsingContexts(reply) => {
+                let _ = reply.send(self.browsers.keys().cloned().collect());
+            },
+            WebDriverCommandMsg::NewWindow(reply) => {
+                let top_level_browsing_context_id = TopLevelBrowsingContextId::new();
+                let url = ServoUrl::parse("about:blank").expect("infallible");
+                self.handle_new_top_level_browsing_context(url, top_level_browsing_context_id);
+                let _ = reply.send(top_level_browsing_context_id);
+            },
+            WebDriverCommandMsg::CloseWindow(top_level_browsing_context_id, reply) => {
+                self.embedder_proxy.send((
+                    Some(top_level_browsing_context_id),
+                    EmbedderMsg::CloseBrowser,
+                ));
+                self.handle_close_top_level_browsing_context(top_level_browsing_context_id);
+                let _ = reply.send(());
+            },
+            WebDriverCommandMsg::SetWindowState(top_level_browsing_context_id, state, reply) => {
+                self.webdriver.resize_channel = Some(reply);
+                let msg = match state {
+                    WebDriverWindowState::Maximized => EmbedderMsg::SetMaximizedState(true),
+                    WebDriverWindowState::Minimized => EmbedderMsg::SetMinimizedState(true),
+                    WebDriverWindowState::Fullscreen => EmbedderMsg::SetFullscreenState(true),
+                };
+                self.embedder_proxy
+                    .send((Some(top_level_browsing_context_id), msg));
+            },
         }
     }
 
@@ -4470,6 +4815,7 @@ where
             .or_insert_with(|| Browser {
                 focused_browsing_context_id: BrowsingContextId::from(top_level_id),
                 session_history: JointSessionHistory::new(),
+                user_prompt: None,
             })
             .session_history
     }
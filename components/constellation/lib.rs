@@ -24,6 +24,8 @@ mod pipeline;
     not(target_arch = "aarch64")
 ))]
 mod sandboxing;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod seccomp_linux;
 mod session_history;
 mod timer_scheduler;
 
@@ -39,3 +41,5 @@ pub use crate::pipeline::UnprivilegedPipelineContent;
     not(target_arch = "aarch64")
 ))]
 pub use crate::sandboxing::content_process_sandbox_profile;
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+pub use crate::seccomp_linux::{content_process_seccomp_filter, SeccompFilter};
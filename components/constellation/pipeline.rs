@@ -47,6 +47,7 @@ use std::process;
 use std::rc::Rc;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use style_traits::media_features::DeviceFeatures;
 use style_traits::CSSPixel;
 use style_traits::DevicePixel;
 use webvr_traits::WebVRMsg;
@@ -170,6 +171,9 @@ pub struct InitialPipelineState {
     /// Information about the device pixel ratio.
     pub device_pixel_ratio: Scale<f32, CSSPixel, DevicePixel>,
 
+    /// The media features of the device the pipeline is displayed on.
+    pub device_features: DeviceFeatures,
+
     /// The ID of the pipeline namespace for this script thread.
     pub pipeline_namespace_id: PipelineNamespaceId,
 
@@ -227,6 +231,7 @@ impl Pipeline {
         let window_size = WindowSizeData {
             initial_viewport: state.window_size,
             device_pixel_ratio: state.device_pixel_ratio,
+            device_features: state.device_features,
         };
 
         let (script_chan, sampler_chan) = match state.event_loop {
@@ -534,7 +539,10 @@ impl UnprivilegedPipelineContent {
         // Idempotent in single-process mode.
         PipelineNamespace::set_installer_sender(self.namespace_request_sender);
 
-        let image_cache = Arc::new(ImageCacheImpl::new(self.webrender_api_sender.create_api()));
+        let image_cache = Arc::new(ImageCacheImpl::new(
+            self.webrender_api_sender.create_api(),
+            self.window_size.device_pixel_ratio.get(),
+        ));
         let paint_time_metrics = PaintTimeMetrics::new(
             self.id,
             self.time_profiler_chan.clone(),
@@ -610,6 +618,7 @@ impl UnprivilegedPipelineContent {
             self.opts.load_webfonts_synchronously,
             self.opts.initial_window_size,
             self.opts.device_pixels_per_px,
+            self.window_size.device_features,
             self.opts.dump_display_list,
             self.opts.dump_display_list_json,
             self.opts.dump_style_tree,
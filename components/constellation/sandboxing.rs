@@ -46,6 +46,7 @@ pub fn content_process_sandbox_profile() -> Profile {
 }
 
 /// Our content process sandbox profile on Linux. As restrictive as possible.
+/// On x86_64, syscalls are further restricted by `content_process_seccomp_filter`.
 #[cfg(not(target_os = "macos"))]
 pub fn content_process_sandbox_profile() -> Profile {
     let mut operations = vec![Operation::FileReadAll(PathPattern::Literal(PathBuf::from(
@@ -0,0 +1,391 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A seccomp-bpf syscall filter for sandboxed content processes on Linux.
+//!
+//! `gaol` starts content processes in new namespaces, restricts the file system to the
+//! paths of the sandbox profile and installs a generic syscall filter. This filter is
+//! stacked on top of it and only allows what script and layout threads need: memory
+//! management, threads, IPC over Unix sockets and read-only file access. Everything else,
+//! including process creation, network sockets, file writes and namespace manipulation,
+//! fails with `EPERM`.
+
+#![allow(unsafe_code)]
+
+use libc;
+use std::io;
+
+// Offsets into `struct seccomp_data`.
+const SECCOMP_DATA_NR: u32 = 0;
+const SECCOMP_DATA_ARCH: u32 = 4;
+const SECCOMP_DATA_ARGS: u32 = 16;
+
+const BPF_LD_W_ABS: u16 = 0x20;
+const BPF_ALU_AND_K: u16 = 0x54;
+const BPF_JMP_JEQ_K: u16 = 0x15;
+const BPF_JMP_JSET_K: u16 = 0x45;
+const BPF_RET_K: u16 = 0x06;
+
+const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+
+const SECCOMP_SET_MODE_FILTER: libc::c_long = 1;
+const SECCOMP_FILTER_FLAG_TSYNC: libc::c_long = 1;
+
+const AUDIT_ARCH_X86_64: u32 = 0xc000_003e;
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+// Not all of these are known to our version of libc.
+const SYS_MEMBARRIER: libc::c_long = 324;
+const SYS_STATX: libc::c_long = 332;
+const SYS_RSEQ: libc::c_long = 334;
+const SYS_CLONE3: libc::c_long = 435;
+
+const NAMESPACE_FLAGS: u32 = (libc::CLONE_NEWNS |
+    libc::CLONE_NEWCGROUP |
+    libc::CLONE_NEWUTS |
+    libc::CLONE_NEWIPC |
+    libc::CLONE_NEWUSER |
+    libc::CLONE_NEWPID |
+    libc::CLONE_NEWNET) as u32;
+
+const WRITE_FLAGS: u32 =
+    (libc::O_WRONLY | libc::O_RDWR | libc::O_CREAT | libc::O_TRUNC | libc::O_APPEND) as u32;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+struct SockFilter {
+    code: u16,
+    jt: u8,
+    jf: u8,
+    k: u32,
+}
+
+#[repr(C)]
+struct SockFprog {
+    len: libc::c_ushort,
+    filter: *const SockFilter,
+}
+
+fn statement(code: u16, k: u32) -> SockFilter {
+    SockFilter {
+        code,
+        jt: 0,
+        jf: 0,
+        k,
+    }
+}
+
+fn jump(code: u16, k: u32, jt: u8, jf: u8) -> SockFilter {
+    SockFilter { code, jt, jf, k }
+}
+
+fn arg_low(arg: u8) -> u32 {
+    SECCOMP_DATA_ARGS + 8 * arg as u32
+}
+
+fn arg_high(arg: u8) -> u32 {
+    arg_low(arg) + 4
+}
+
+fn errno(errno: i32) -> u32 {
+    SECCOMP_RET_ERRNO | (errno as u32 & 0xffff)
+}
+
+/// What to do with a syscall.
+#[derive(Clone, Debug)]
+enum Rule {
+    /// Let it through.
+    Allow,
+    /// Fail it with the given errno.
+    Errno(i32),
+    /// Let it through if the given argument is one of the values.
+    ArgIn(u8, Vec<u32>),
+    /// Let it through if the given argument has all the `required` bits and none of the
+    /// `forbidden` ones.
+    ArgFlags {
+        arg: u8,
+        required: u32,
+        forbidden: u32,
+    },
+}
+
+impl Rule {
+    /// The instructions checking this rule. They always return.
+    fn compile(&self) -> Vec<SockFilter> {
+        let deny = errno(libc::EPERM);
+        match *self {
+            Rule::Allow => vec![statement(BPF_RET_K, SECCOMP_RET_ALLOW)],
+            Rule::Errno(e) => vec![statement(BPF_RET_K, errno(e))],
+            Rule::ArgIn(arg, ref values) => {
+                let n = values.len();
+                let mut program = vec![
+                    statement(BPF_LD_W_ABS, arg_high(arg)),
+                    jump(BPF_JMP_JEQ_K, 0, 0, n as u8 + 1),
+                    statement(BPF_LD_W_ABS, arg_low(arg)),
+                ];
+                program.extend(
+                    values
+                        .iter()
+                        .enumerate()
+                        .map(|(i, value)| jump(BPF_JMP_JEQ_K, *value, (n - i) as u8, 0)),
+                );
+                program.push(statement(BPF_RET_K, deny));
+                program.push(statement(BPF_RET_K, SECCOMP_RET_ALLOW));
+                program
+            },
+            Rule::ArgFlags {
+                arg,
+                required,
+                forbidden,
+            } => vec![
+                statement(BPF_LD_W_ABS, arg_low(arg)),
+                jump(BPF_JMP_JSET_K, forbidden, 3, 0),
+                statement(BPF_ALU_AND_K, required),
+                jump(BPF_JMP_JEQ_K, required, 0, 1),
+                statement(BPF_RET_K, SECCOMP_RET_ALLOW),
+                statement(BPF_RET_K, deny),
+            ],
+        }
+    }
+}
+
+/// A syscall allowlist. Syscalls without a rule fail with `EPERM`.
+#[derive(Clone, Debug, Default)]
+pub struct SeccompFilter {
+    rules: Vec<(libc::c_long, Rule)>,
+}
+
+impl SeccompFilter {
+    pub fn new() -> SeccompFilter {
+        SeccompFilter::default()
+    }
+
+    fn rule(&mut self, syscall: libc::c_long, rule: Rule) -> &mut SeccompFilter {
+        self.rules.push((syscall, rule));
+        self
+    }
+
+    /// Allow these syscalls, whatever their arguments.
+    pub fn allow(&mut self, syscalls: &[libc::c_long]) -> &mut SeccompFilter {
+        for syscall in syscalls {
+            self.rule(*syscall, Rule::Allow);
+        }
+        self
+    }
+
+    /// Fail this syscall with the given errno instead of `EPERM`.
+    pub fn fail_with(&mut self, syscall: libc::c_long, errno: i32) -> &mut SeccompFilter {
+        self.rule(syscall, Rule::Errno(errno))
+    }
+
+    /// Allow this syscall if its argument `arg` is one of `values`.
+    pub fn allow_if_arg_in(
+        &mut self,
+        syscall: libc::c_long,
+        arg: u8,
+        values: &[u32],
+    ) -> &mut SeccompFilter {
+        self.rule(syscall, Rule::ArgIn(arg, values.to_vec()))
+    }
+
+    /// Allow this syscall if its argument `arg` has all the `required` bits set and
+    /// none of the `forbidden` ones.
+    pub fn allow_if_arg_flags(
+        &mut self,
+        syscall: libc::c_long,
+        arg: u8,
+        required: u32,
+        forbidden: u32,
+    ) -> &mut SeccompFilter {
+        self.rule(
+            syscall,
+            Rule::ArgFlags {
+                arg,
+                required,
+                forbidden,
+            },
+        )
+    }
+
+    fn compile(&self) -> Vec<SockFilter> {
+        let mut program = vec![
+            statement(BPF_LD_W_ABS, SECCOMP_DATA_ARCH),
+            jump(BPF_JMP_JEQ_K, AUDIT_ARCH_X86_64, 1, 0),
+            statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+            statement(BPF_LD_W_ABS, SECCOMP_DATA_NR),
+            // The x32 ABI would let the same syscalls through with other numbers.
+            jump(BPF_JMP_JSET_K, X32_SYSCALL_BIT, 0, 1),
+            statement(BPF_RET_K, SECCOMP_RET_KILL_PROCESS),
+        ];
+        for &(syscall, ref rule) in &self.rules {
+            let body = rule.compile();
+            program.push(jump(BPF_JMP_JEQ_K, syscall as u32, 0, body.len() as u8));
+            program.extend(body);
+        }
+        program.push(statement(BPF_RET_K, errno(libc::EPERM)));
+        program
+    }
+
+    /// Install the filter for all the threads of this process. This cannot be undone.
+    pub fn activate(&self) -> io::Result<()> {
+        let program = self.compile();
+        let fprog = SockFprog {
+            len: program.len() as libc::c_ushort,
+            filter: program.as_ptr(),
+        };
+        unsafe {
+            // Required to install a filter without CAP_SYS_ADMIN. Already set when the
+            // `gaol` filter is installed.
+            libc::prctl(
+                libc::PR_SET_NO_NEW_PRIVS,
+                1 as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+                0 as libc::c_ulong,
+            );
+            let result = libc::syscall(
+                libc::SYS_seccomp,
+                SECCOMP_SET_MODE_FILTER,
+                SECCOMP_FILTER_FLAG_TSYNC,
+                &fprog as *const SockFprog,
+            );
+            match result {
+                0 => Ok(()),
+                // Another thread could not be moved to the new filter, so installing the filter
+                // only on this thread would leave the others unconfined.
+                tid if tid > 0 => Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("Thread {} could not be synchronized", tid),
+                )),
+                _ => Err(io::Error::last_os_error()),
+            }
+        }
+    }
+}
+
+/// The syscalls needed by the script and layout threads of a content process.
+pub fn content_process_seccomp_filter() -> SeccompFilter {
+    let mut filter = SeccompFilter::new();
+    filter
+        .allow(&[
+            // Memory.
+            libc::SYS_brk,
+            libc::SYS_mmap,
+            libc::SYS_munmap,
+            libc::SYS_mremap,
+            libc::SYS_mprotect,
+            libc::SYS_madvise,
+            SYS_MEMBARRIER,
+            // Threads and synchronization.
+            libc::SYS_futex,
+            libc::SYS_set_robust_list,
+            libc::SYS_get_robust_list,
+            libc::SYS_set_tid_address,
+            libc::SYS_sched_yield,
+            libc::SYS_sched_getaffinity,
+            libc::SYS_nanosleep,
+            libc::SYS_clock_nanosleep,
+            libc::SYS_clock_gettime,
+            libc::SYS_clock_getres,
+            libc::SYS_gettimeofday,
+            SYS_RSEQ,
+            // Signals.
+            libc::SYS_rt_sigaction,
+            libc::SYS_rt_sigprocmask,
+            libc::SYS_rt_sigreturn,
+            libc::SYS_sigaltstack,
+            libc::SYS_tgkill,
+            libc::SYS_restart_syscall,
+            // File descriptors.
+            libc::SYS_read,
+            libc::SYS_readv,
+            libc::SYS_pread64,
+            libc::SYS_write,
+            libc::SYS_writev,
+            libc::SYS_close,
+            libc::SYS_lseek,
+            libc::SYS_fcntl,
+            libc::SYS_dup,
+            libc::SYS_dup3,
+            libc::SYS_pipe2,
+            libc::SYS_eventfd2,
+            libc::SYS_poll,
+            libc::SYS_ppoll,
+            libc::SYS_epoll_create1,
+            libc::SYS_epoll_ctl,
+            libc::SYS_epoll_wait,
+            libc::SYS_epoll_pwait,
+            // Shared memory for IPC, which ipc-channel creates with memfd_create
+            // as it's built with its `memfd` feature.
+            libc::SYS_memfd_create,
+            libc::SYS_ftruncate,
+            // IPC over already connected sockets.
+            libc::SYS_sendmsg,
+            libc::SYS_recvmsg,
+            libc::SYS_sendto,
+            libc::SYS_recvfrom,
+            libc::SYS_shutdown,
+            libc::SYS_getsockopt,
+            libc::SYS_setsockopt,
+            // Read-only file system access, limited to the paths of the sandbox profile.
+            libc::SYS_stat,
+            libc::SYS_fstat,
+            libc::SYS_lstat,
+            libc::SYS_newfstatat,
+            SYS_STATX,
+            libc::SYS_access,
+            libc::SYS_faccessat,
+            libc::SYS_readlink,
+            libc::SYS_getdents64,
+            libc::SYS_getcwd,
+            // Process information.
+            libc::SYS_getpid,
+            libc::SYS_gettid,
+            libc::SYS_getuid,
+            libc::SYS_geteuid,
+            libc::SYS_getgid,
+            libc::SYS_getegid,
+            libc::SYS_getrandom,
+            libc::SYS_getrlimit,
+            libc::SYS_uname,
+            libc::SYS_sysinfo,
+            libc::SYS_exit,
+            libc::SYS_exit_group,
+        ])
+        // Only new threads, in the same namespaces.
+        .allow_if_arg_flags(
+            libc::SYS_clone,
+            0,
+            libc::CLONE_THREAD as u32,
+            NAMESPACE_FLAGS,
+        )
+        // The arguments of clone3 are behind a pointer, so they can't be checked. glibc
+        // falls back to clone when it's not implemented.
+        .fail_with(SYS_CLONE3, libc::ENOSYS)
+        .allow_if_arg_flags(libc::SYS_open, 1, 0, WRITE_FLAGS)
+        .allow_if_arg_flags(libc::SYS_openat, 2, 0, WRITE_FLAGS)
+        .allow_if_arg_in(libc::SYS_socket, 0, &[libc::AF_UNIX as u32])
+        .allow_if_arg_in(libc::SYS_socketpair, 0, &[libc::AF_UNIX as u32])
+        // Only to read the resource limits.
+        .allow_if_arg_in(libc::SYS_prlimit64, 2, &[0])
+        // Thread names.
+        .allow_if_arg_in(
+            libc::SYS_prctl,
+            0,
+            &[libc::PR_SET_NAME as u32, libc::PR_GET_NAME as u32],
+        )
+        .allow_if_arg_in(
+            libc::SYS_ioctl,
+            1,
+            &[
+                libc::FIONBIO as u32,
+                libc::FIOCLEX as u32,
+                libc::FIONREAD as u32,
+                libc::TCGETS as u32,
+            ],
+        );
+    filter
+}
@@ -0,0 +1,116 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use constellation::content_process_sandbox_profile;
+use gaol::sandbox::{ChildSandbox, ChildSandboxMethods};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Read;
+use std::net::{TcpStream, UdpSocket};
+use std::os::unix::net::UnixDatagram;
+use std::process::Command;
+use std::thread;
+
+/// Set in the child process running the sandboxed half of the test.
+const SANDBOXED_CHILD: &str = "SERVO_SECCOMP_TEST_CHILD";
+
+/// The filters can't be removed once activated, so they're tested in a child process
+/// running this same test.
+fn run_in_child(test: &str, child: impl FnOnce()) {
+    if env::var_os(SANDBOXED_CHILD).is_some() {
+        child();
+        return;
+    }
+    let status = Command::new(env::current_exe().unwrap())
+        .args(&[test, "--exact", "--test-threads=1", "--nocapture"])
+        .env(SANDBOXED_CHILD, "1")
+        .status()
+        .expect("Failed to start child process");
+    assert!(status.success(), "Sandboxed child process failed");
+}
+
+fn run_sandboxed(test: &str, checks: fn()) {
+    run_in_child(test, || {
+        constellation::content_process_seccomp_filter()
+            .activate()
+            .expect("Failed to activate seccomp filter!");
+        checks();
+    });
+}
+
+fn send_from_thread() {
+    let (a, b) = UnixDatagram::pair().unwrap();
+    let thread = thread::Builder::new()
+        .name("SandboxedThread".to_owned())
+        .spawn(move || a.send(b"ping").unwrap())
+        .unwrap();
+    let mut buf = [0; 4];
+    assert_eq!(b.recv(&mut buf).unwrap(), 4);
+    assert_eq!(&buf, b"ping");
+    thread.join().unwrap();
+}
+
+fn assert_not_permitted<T>(result: std::io::Result<T>) {
+    match result {
+        Ok(_) => panic!("Operation should have been forbidden"),
+        Err(error) => assert_eq!(error.raw_os_error(), Some(libc::EPERM)),
+    }
+}
+
+#[test]
+fn test_seccomp_forbids_file_writes() {
+    let path = env::temp_dir().join("servo-seccomp-test");
+    let _ = fs::remove_file(&path);
+    run_sandboxed("test_seccomp_forbids_file_writes", || {
+        let path = env::temp_dir().join("servo-seccomp-test");
+        assert_not_permitted(File::create(&path));
+        assert_not_permitted(OpenOptions::new().append(true).open("/dev/null"));
+        assert_not_permitted(OpenOptions::new().read(true).write(true).open("/dev/null"));
+
+        // Reading is still allowed.
+        let mut exe = File::open(env::current_exe().unwrap()).unwrap();
+        let mut buf = [0; 4];
+        exe.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"\x7fELF");
+    });
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_seccomp_forbids_network_and_processes() {
+    run_sandboxed("test_seccomp_forbids_network_and_processes", || {
+        assert_not_permitted(TcpStream::connect("127.0.0.1:80"));
+        assert_not_permitted(UdpSocket::bind("127.0.0.1:0"));
+        assert_not_permitted(Command::new("/bin/true").status());
+
+        let result = unsafe { libc::unshare(libc::CLONE_NEWUSER) };
+        assert_eq!(result, -1);
+        assert_eq!(
+            std::io::Error::last_os_error().raw_os_error(),
+            Some(libc::EPERM)
+        );
+    });
+}
+
+#[test]
+fn test_seccomp_allows_threads_and_ipc() {
+    run_sandboxed("test_seccomp_allows_threads_and_ipc", send_from_thread);
+}
+
+/// `create_sandbox` installs the filter on top of the `gaol` sandbox, so the `gaol` profile
+/// has to let the filter be installed.
+#[test]
+fn test_seccomp_activates_inside_gaol_sandbox() {
+    run_in_child("test_seccomp_activates_inside_gaol_sandbox", || {
+        ChildSandbox::new(content_process_sandbox_profile())
+            .activate()
+            .expect("Failed to activate sandbox!");
+        constellation::content_process_seccomp_filter()
+            .activate()
+            .expect("Failed to activate seccomp filter!");
+        send_from_thread();
+    });
+}
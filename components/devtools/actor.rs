@@ -175,10 +175,7 @@ impl ActorRegistry {
                 }
             },
         }
-        let new_actors = replace(&mut *self.new_actors.borrow_mut(), vec![]);
-        for actor in new_actors.into_iter() {
-            self.actors.insert(actor.name().to_owned(), actor);
-        }
+        self.register_pending();
 
         let old_actors = replace(&mut *self.old_actors.borrow_mut(), vec![]);
         for name in old_actors {
@@ -187,6 +184,14 @@ impl ActorRegistry {
         Ok(())
     }
 
+    /// Register the actors created with `register_later`.
+    pub fn register_pending(&mut self) {
+        let new_actors = replace(&mut *self.new_actors.borrow_mut(), vec![]);
+        for actor in new_actors.into_iter() {
+            self.actors.insert(actor.name().to_owned(), actor);
+        }
+    }
+
     pub fn drop_actor(&mut self, name: String) {
         self.actors.remove(&name);
     }
@@ -3,7 +3,10 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
-use serde_json::{Map, Value};
+use devtools_traits::EvaluateJSReply;
+use devtools_traits::EvaluateJSReply::{ActorValue, BooleanValue, StringValue};
+use devtools_traits::EvaluateJSReply::{NullValue, NumberValue, VoidValue};
+use serde_json::{Map, Number, Value};
 use std::net::TcpStream;
 
 pub struct ObjectActor {
@@ -44,3 +47,56 @@ impl ObjectActor {
         }
     }
 }
+
+/// Describe a value from script the way the remote debugging protocol expects it,
+/// creating an actor for it if it is an object.
+pub fn grip(registry: &ActorRegistry, value: EvaluateJSReply) -> Value {
+    match value {
+        VoidValue => {
+            let mut m = Map::new();
+            m.insert("type".to_owned(), Value::String("undefined".to_owned()));
+            Value::Object(m)
+        },
+        NullValue => {
+            let mut m = Map::new();
+            m.insert("type".to_owned(), Value::String("null".to_owned()));
+            Value::Object(m)
+        },
+        BooleanValue(val) => Value::Bool(val),
+        NumberValue(val) => {
+            if val.is_nan() {
+                let mut m = Map::new();
+                m.insert("type".to_owned(), Value::String("NaN".to_owned()));
+                Value::Object(m)
+            } else if val.is_infinite() {
+                let mut m = Map::new();
+                if val < 0. {
+                    m.insert("type".to_owned(), Value::String("-Infinity".to_owned()));
+                } else {
+                    m.insert("type".to_owned(), Value::String("Infinity".to_owned()));
+                }
+                Value::Object(m)
+            } else if val == 0. && val.is_sign_negative() {
+                let mut m = Map::new();
+                m.insert("type".to_owned(), Value::String("-0".to_owned()));
+                Value::Object(m)
+            } else {
+                Value::Number(Number::from_f64(val).unwrap())
+            }
+        },
+        StringValue(s) => Value::String(s),
+        ActorValue { class, uuid } => {
+            //TODO: make initial ActorValue message include these properties?
+            let mut m = Map::new();
+            let actor = ObjectActor::new(registry, uuid);
+
+            m.insert("type".to_owned(), Value::String("object".to_owned()));
+            m.insert("class".to_owned(), Value::String(class));
+            m.insert("actor".to_owned(), Value::String(actor));
+            m.insert("extensible".to_owned(), Value::Bool(true));
+            m.insert("frozen".to_owned(), Value::Bool(false));
+            m.insert("sealed".to_owned(), Value::Bool(false));
+            Value::Object(m)
+        },
+    }
+}
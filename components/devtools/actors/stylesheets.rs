@@ -2,31 +2,207 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/stylesheets.js).
+//! Lists the stylesheets of a document and lets the style editor view and edit them.
+
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::protocol::JsonPacketStream;
+use devtools_traits::DevtoolScriptControlMsg::{
+    GetStyleSheetText, GetStyleSheets, UpdateStyleSheet,
+};
+use devtools_traits::{DevtoolScriptControlMsg, StyleSheetInfo};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
 use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::net::TcpStream;
 
+/// The kind of a `styleApplied` event sent after replacing the whole text of a stylesheet.
+const UPDATE_GENERAL: u32 = 1;
+
+#[derive(Serialize)]
+struct GetStyleSheetsReply {
+    from: String,
+    styleSheets: Vec<StyleSheetActorMsg>,
+}
+
+#[derive(Serialize)]
+struct StyleSheetActorMsg {
+    actor: String,
+    href: Option<String>,
+    nodeHref: Option<String>,
+    disabled: bool,
+    title: String,
+    system: bool,
+    styleSheetIndex: usize,
+    ruleCount: usize,
+}
+
+#[derive(Serialize)]
+struct GetTextReply {
+    from: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct UpdateReply {
+    from: String,
+}
+
+#[derive(Serialize)]
+struct StyleAppliedEvent {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+    kind: u32,
+    styleSheet: String,
+}
+
 pub struct StyleSheetsActor {
     pub name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    /// The actors created so far for the stylesheets of the document, by index.
+    stylesheets: RefCell<HashMap<usize, String>>,
 }
 
 impl Actor for StyleSheetsActor {
     fn name(&self) -> String {
         self.name.clone()
     }
+
     fn handle_message(
         &self,
-        _: &ActorRegistry,
-        _: &str,
-        _: &Map<String, Value>,
-        _: &mut TcpStream,
+        registry: &ActorRegistry,
+        msg_type: &str,
+        _msg: &Map<String, Value>,
+        stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
-        Ok(ActorMessageStatus::Ignored)
+        Ok(match msg_type {
+            "getStyleSheets" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(GetStyleSheets(self.pipeline, tx))
+                    .unwrap();
+                let infos = rx.recv().unwrap();
+
+                let msg = GetStyleSheetsReply {
+                    from: self.name(),
+                    styleSheets: infos
+                        .into_iter()
+                        .map(|info| self.encodable(registry, info))
+                        .collect(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
     }
 }
 
 impl StyleSheetsActor {
-    pub fn new(name: String) -> StyleSheetsActor {
-        StyleSheetsActor { name: name }
+    pub fn new(
+        name: String,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        pipeline: PipelineId,
+    ) -> StyleSheetsActor {
+        StyleSheetsActor {
+            name: name,
+            script_chan: script_chan,
+            pipeline: pipeline,
+            stylesheets: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// Describe a stylesheet, creating an actor for it if it doesn't have one yet.
+    fn encodable(&self, registry: &ActorRegistry, info: StyleSheetInfo) -> StyleSheetActorMsg {
+        let mut stylesheets = self.stylesheets.borrow_mut();
+        let actor = stylesheets
+            .entry(info.index)
+            .or_insert_with(|| {
+                let actor = StyleSheetActor {
+                    name: registry.new_name("stylesheet"),
+                    script_chan: self.script_chan.clone(),
+                    pipeline: self.pipeline,
+                    index: info.index,
+                };
+                let name = actor.name();
+                registry.register_later(Box::new(actor));
+                name
+            })
+            .clone();
+
+        StyleSheetActorMsg {
+            actor: actor,
+            href: info.href,
+            nodeHref: None,
+            disabled: info.disabled,
+            title: info.title.unwrap_or_default(),
+            system: false,
+            styleSheetIndex: info.index,
+            ruleCount: info.ruleCount,
+        }
+    }
+}
+
+/// A single stylesheet of a document, identified by its index in the document's stylesheet list.
+struct StyleSheetActor {
+    name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    index: usize,
+}
+
+impl Actor for StyleSheetActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        _registry: &ActorRegistry,
+        msg_type: &str,
+        msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "getText" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(GetStyleSheetText(self.pipeline, self.index, tx))
+                    .unwrap();
+                let msg = GetTextReply {
+                    from: self.name(),
+                    text: rx.recv().unwrap().unwrap_or_default(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "update" => {
+                let text = match msg.get("text").and_then(|text| text.as_str()) {
+                    Some(text) => text.to_owned(),
+                    None => return Err(()),
+                };
+                self.script_chan
+                    .send(UpdateStyleSheet(self.pipeline, self.index, text))
+                    .unwrap();
+                stream.write_json_packet(&UpdateReply { from: self.name() });
+                let event = StyleAppliedEvent {
+                    from: self.name(),
+                    type_: "styleApplied".to_owned(),
+                    kind: UPDATE_GENERAL,
+                    styleSheet: self.name(),
+                };
+                stream.write_json_packet(&event);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
     }
 }
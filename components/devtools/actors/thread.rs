@@ -2,17 +2,33 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+//! Liberally derived from the [Firefox JS implementation]
+//! (https://searchfox.org/mozilla-central/source/devtools/server/actors/thread.js).
+//! Drives the JavaScript debugger of a pipeline: lists its sources, sets breakpoints,
+//! pauses, steps and inspects the stack of paused script.
+
 use crate::actor::{Actor, ActorMessageStatus, ActorRegistry};
+use crate::actors::object::grip;
 use crate::protocol::JsonPacketStream;
+use devtools_traits::DebuggerRequest::{self, Attach, Detach, Evaluate, GetFrames};
+use devtools_traits::DebuggerRequest::{GetSourceText, GetSources, Interrupt};
+use devtools_traits::DebuggerRequest::{RemoveBreakpoint, Resume, SetBreakpoint};
+use devtools_traits::{DevtoolScriptControlMsg, FrameInfo, PauseInfo, ResumeLimit, SourceInfo};
+use ipc_channel::ipc::{self, IpcSender};
+use msg::constellation_msg::PipelineId;
 use serde_json::{Map, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::net::TcpStream;
 
 #[derive(Serialize)]
-struct ThreadAttachedReply {
+struct ThreadPausedReply {
     from: String,
     #[serde(rename = "type")]
     type_: String,
     actor: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame: Option<FrameMsg>,
     poppedFrames: Vec<PoppedFrameMsg>,
     why: WhyMsg,
 }
@@ -24,6 +40,10 @@ enum PoppedFrameMsg {}
 struct WhyMsg {
     #[serde(rename = "type")]
     type_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actors: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frameFinished: Option<Map<String, Value>>,
 }
 
 #[derive(Serialize)]
@@ -40,6 +60,13 @@ struct ThreadInterruptedReply {
     type_: String,
 }
 
+#[derive(Serialize)]
+struct ThreadDetachedReply {
+    from: String,
+    #[serde(rename = "type")]
+    type_: String,
+}
+
 #[derive(Serialize)]
 struct ReconfigureReply {
     from: String,
@@ -48,19 +75,192 @@ struct ReconfigureReply {
 #[derive(Serialize)]
 struct SourcesReply {
     from: String,
-    sources: Vec<Source>,
+    sources: Vec<SourceMsg>,
 }
 
 #[derive(Serialize)]
-enum Source {}
+struct SourceMsg {
+    actor: String,
+    url: Option<String>,
+    isBlackBoxed: bool,
+    introductionType: Option<String>,
+}
+
+#[derive(Serialize)]
+struct FramesReply {
+    from: String,
+    frames: Vec<FrameMsg>,
+}
+
+#[derive(Serialize)]
+struct FrameMsg {
+    actor: String,
+    depth: u32,
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    displayName: Option<String>,
+    #[serde(rename = "where")]
+    where_: LocationMsg,
+    arguments: Vec<Value>,
+}
+
+#[derive(Serialize)]
+struct LocationMsg {
+    actor: Option<String>,
+    line: u32,
+    column: u32,
+}
+
+#[derive(Serialize)]
+struct SourceReply {
+    from: String,
+    source: String,
+    contentType: String,
+}
+
+#[derive(Serialize)]
+struct SetBreakpointReply {
+    from: String,
+    actor: String,
+    isPending: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actualLocation: Option<LocationMsg>,
+}
+
+#[derive(Serialize)]
+struct ErrorReply {
+    from: String,
+    error: String,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct DeleteReply {
+    from: String,
+}
 
 pub struct ThreadActor {
     name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    /// The clients attached to this thread, which are notified when script pauses.
+    streams: RefCell<Vec<TcpStream>>,
+    /// The actors created so far for the sources of the pipeline, by source id.
+    sources: RefCell<HashMap<u32, String>>,
+    /// The actors of the breakpoints set by the client, by source id and line.
+    breakpoints: RefCell<HashMap<(u32, u32), String>>,
+    /// The actors of the frames of paused script, by depth.
+    frames: RefCell<Vec<String>>,
 }
 
 impl ThreadActor {
-    pub fn new(name: String) -> ThreadActor {
-        ThreadActor { name: name }
+    pub fn new(
+        name: String,
+        script_chan: IpcSender<DevtoolScriptControlMsg>,
+        pipeline: PipelineId,
+    ) -> ThreadActor {
+        ThreadActor {
+            name: name,
+            script_chan: script_chan,
+            pipeline: pipeline,
+            streams: RefCell::new(vec![]),
+            sources: RefCell::new(HashMap::new()),
+            breakpoints: RefCell::new(HashMap::new()),
+            frames: RefCell::new(vec![]),
+        }
+    }
+
+    fn send(&self, request: DebuggerRequest) {
+        self.script_chan
+            .send(DevtoolScriptControlMsg::Debugger(self.pipeline, request))
+            .unwrap();
+    }
+
+    /// Notify the attached clients that script was paused.
+    pub fn paused(&self, registry: &ActorRegistry, info: PauseInfo) {
+        let breakpoint = if info.why == "breakpoint" {
+            info.frame.source.and_then(|source| {
+                self.breakpoints
+                    .borrow()
+                    .get(&(source, info.frame.line))
+                    .cloned()
+            })
+        } else {
+            None
+        };
+        let msg = ThreadPausedReply {
+            from: self.name(),
+            type_: "paused".to_owned(),
+            actor: registry.new_name("pause"),
+            frame: Some(self.encodable_frame(registry, info.frame)),
+            poppedFrames: vec![],
+            why: WhyMsg {
+                type_: info.why,
+                actors: breakpoint.map(|actor| vec![actor]),
+                frameFinished: None,
+            },
+        };
+        for stream in &mut *self.streams.borrow_mut() {
+            stream.write_json_packet(&msg);
+        }
+    }
+
+    /// Find the actor of a source, creating it if it doesn't exist yet.
+    fn source_actor(&self, registry: &ActorRegistry, id: u32) -> String {
+        self.sources
+            .borrow_mut()
+            .entry(id)
+            .or_insert_with(|| {
+                let actor = SourceActor {
+                    name: registry.new_name("source"),
+                    script_chan: self.script_chan.clone(),
+                    pipeline: self.pipeline,
+                    thread: self.name(),
+                    id: id,
+                };
+                let name = actor.name();
+                registry.register_later(Box::new(actor));
+                name
+            })
+            .clone()
+    }
+
+    fn encodable_source(&self, registry: &ActorRegistry, info: SourceInfo) -> SourceMsg {
+        SourceMsg {
+            actor: self.source_actor(registry, info.id),
+            url: info.url,
+            isBlackBoxed: false,
+            introductionType: info.introduction_type,
+        }
+    }
+
+    fn encodable_frame(&self, registry: &ActorRegistry, info: FrameInfo) -> FrameMsg {
+        let actor = {
+            let mut frames = self.frames.borrow_mut();
+            while frames.len() <= info.depth as usize {
+                frames.push(registry.new_name("frame"));
+            }
+            frames[info.depth as usize].clone()
+        };
+        FrameMsg {
+            actor: actor,
+            depth: info.depth,
+            type_: info.kind,
+            displayName: info.callee,
+            where_: LocationMsg {
+                actor: info.source.map(|id| self.source_actor(registry, id)),
+                line: info.line,
+                column: info.column,
+            },
+            arguments: vec![],
+        }
+    }
+
+    fn get_frames(&self) -> Vec<FrameInfo> {
+        let (tx, rx) = ipc::channel().unwrap();
+        self.send(GetFrames(tx));
+        rx.recv().unwrap_or_default()
     }
 }
 
@@ -73,25 +273,54 @@ impl Actor for ThreadActor {
         &self,
         registry: &ActorRegistry,
         msg_type: &str,
-        _msg: &Map<String, Value>,
+        msg: &Map<String, Value>,
         stream: &mut TcpStream,
     ) -> Result<ActorMessageStatus, ()> {
         Ok(match msg_type {
             "attach" => {
-                let msg = ThreadAttachedReply {
+                self.send(Attach);
+                self.streams.borrow_mut().push(stream.try_clone().unwrap());
+                let msg = ThreadPausedReply {
                     from: self.name(),
                     type_: "paused".to_owned(),
                     actor: registry.new_name("pause"),
+                    frame: None,
                     poppedFrames: vec![],
                     why: WhyMsg {
                         type_: "attached".to_owned(),
+                        actors: None,
+                        frameFinished: None,
                     },
                 };
                 stream.write_json_packet(&msg);
                 ActorMessageStatus::Processed
             },
 
+            "detach" => {
+                self.send(Detach);
+                self.streams.borrow_mut().clear();
+                self.frames.borrow_mut().clear();
+                let msg = ThreadDetachedReply {
+                    from: self.name(),
+                    type_: "detached".to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
             "resume" => {
+                let limit = msg
+                    .get("resumeLimit")
+                    .and_then(|limit| limit.get("type"))
+                    .and_then(|limit| limit.as_str())
+                    .and_then(|limit| match limit {
+                        "next" => Some(ResumeLimit::Next),
+                        "step" => Some(ResumeLimit::Step),
+                        "finish" => Some(ResumeLimit::Finish),
+                        _ => None,
+                    });
+                self.frames.borrow_mut().clear();
+                self.send(Resume(limit));
                 let msg = ThreadResumedReply {
                     from: self.name(),
                     type_: "resumed".to_owned(),
@@ -101,6 +330,7 @@ impl Actor for ThreadActor {
             },
 
             "interrupt" => {
+                self.send(Interrupt);
                 let msg = ThreadInterruptedReply {
                     from: self.name(),
                     type_: "interrupted".to_owned(),
@@ -115,11 +345,237 @@ impl Actor for ThreadActor {
             },
 
             "sources" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.send(GetSources(tx));
                 let msg = SourcesReply {
                     from: self.name(),
-                    sources: vec![],
+                    sources: rx
+                        .recv()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|source| self.encodable_source(registry, source))
+                        .collect(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "frames" => {
+                let start = msg
+                    .get("start")
+                    .and_then(|start| start.as_u64())
+                    .unwrap_or(0);
+                let count = msg.get("count").and_then(|count| count.as_u64());
+                let frames = self
+                    .get_frames()
+                    .into_iter()
+                    .skip(start as usize)
+                    .take(count.map_or(usize::max_value(), |count| count as usize))
+                    .map(|frame| self.encodable_frame(registry, frame))
+                    .collect();
+                let msg = FramesReply {
+                    from: self.name(),
+                    frames: frames,
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "clientEvaluate" => {
+                let expression = match msg.get("expression").and_then(|e| e.as_str()) {
+                    Some(expression) => expression.to_owned(),
+                    None => return Err(()),
+                };
+                let depth = msg
+                    .get("frame")
+                    .and_then(|frame| frame.as_str())
+                    .and_then(|frame| self.frames.borrow().iter().position(|f| f == frame))
+                    .unwrap_or(0);
+                let (tx, rx) = ipc::channel().unwrap();
+                self.send(Evaluate(depth as u32, expression, tx));
+                let evaluation = match rx.recv() {
+                    Ok(Some(evaluation)) => evaluation,
+                    _ => return Err(()),
+                };
+
+                let msg = ThreadResumedReply {
+                    from: self.name(),
+                    type_: "resumed".to_owned(),
                 };
                 stream.write_json_packet(&msg);
+
+                let mut completion = Map::new();
+                let key = if evaluation.threw { "throw" } else { "return" };
+                completion.insert(key.to_owned(), grip(registry, evaluation.value));
+                let frame = self.get_frames().into_iter().next();
+                let msg = ThreadPausedReply {
+                    from: self.name(),
+                    type_: "paused".to_owned(),
+                    actor: registry.new_name("pause"),
+                    frame: frame.map(|frame| self.encodable_frame(registry, frame)),
+                    poppedFrames: vec![],
+                    why: WhyMsg {
+                        type_: "clientEvaluated".to_owned(),
+                        actors: None,
+                        frameFinished: Some(completion),
+                    },
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+/// A script source of a pipeline, identified by the id the debugger gave it.
+struct SourceActor {
+    name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    thread: String,
+    id: u32,
+}
+
+impl Actor for SourceActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        registry: &ActorRegistry,
+        msg_type: &str,
+        msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "source" => {
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(DevtoolScriptControlMsg::Debugger(
+                        self.pipeline,
+                        GetSourceText(self.id, tx),
+                    ))
+                    .unwrap();
+                let msg = SourceReply {
+                    from: self.name(),
+                    source: rx.recv().ok().and_then(|text| text).unwrap_or_default(),
+                    contentType: "text/javascript".to_owned(),
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            "setBreakpoint" => {
+                let location = msg.get("location");
+                let line = match location
+                    .and_then(|location| location.get("line"))
+                    .and_then(|line| line.as_u64())
+                {
+                    Some(line) => line as u32,
+                    None => return Err(()),
+                };
+                let (tx, rx) = ipc::channel().unwrap();
+                self.script_chan
+                    .send(DevtoolScriptControlMsg::Debugger(
+                        self.pipeline,
+                        SetBreakpoint(self.id, line, tx),
+                    ))
+                    .unwrap();
+                let actual_line = match rx.recv().ok().and_then(|line| line) {
+                    Some(actual_line) => actual_line,
+                    None => {
+                        let msg = ErrorReply {
+                            from: self.name(),
+                            error: "noCodeAtLineColumn".to_owned(),
+                            message: format!("Could not find any code at line {}", line),
+                        };
+                        stream.write_json_packet(&msg);
+                        return Ok(ActorMessageStatus::Processed);
+                    },
+                };
+
+                let thread = registry.find::<ThreadActor>(&self.thread);
+                let actor = thread
+                    .breakpoints
+                    .borrow_mut()
+                    .entry((self.id, actual_line))
+                    .or_insert_with(|| {
+                        let actor = BreakpointActor {
+                            name: registry.new_name("breakpoint"),
+                            script_chan: self.script_chan.clone(),
+                            pipeline: self.pipeline,
+                            thread: self.thread.clone(),
+                            source: self.id,
+                            line: actual_line,
+                        };
+                        let name = actor.name();
+                        registry.register_later(Box::new(actor));
+                        name
+                    })
+                    .clone();
+                let msg = SetBreakpointReply {
+                    from: self.name(),
+                    actor: actor,
+                    isPending: false,
+                    actualLocation: if actual_line != line {
+                        Some(LocationMsg {
+                            actor: Some(self.name()),
+                            line: actual_line,
+                            column: 0,
+                        })
+                    } else {
+                        None
+                    },
+                };
+                stream.write_json_packet(&msg);
+                ActorMessageStatus::Processed
+            },
+
+            _ => ActorMessageStatus::Ignored,
+        })
+    }
+}
+
+/// A breakpoint set by the client at a line of a source.
+struct BreakpointActor {
+    name: String,
+    script_chan: IpcSender<DevtoolScriptControlMsg>,
+    pipeline: PipelineId,
+    thread: String,
+    source: u32,
+    line: u32,
+}
+
+impl Actor for BreakpointActor {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn handle_message(
+        &self,
+        registry: &ActorRegistry,
+        msg_type: &str,
+        _msg: &Map<String, Value>,
+        stream: &mut TcpStream,
+    ) -> Result<ActorMessageStatus, ()> {
+        Ok(match msg_type {
+            "delete" => {
+                self.script_chan
+                    .send(DevtoolScriptControlMsg::Debugger(
+                        self.pipeline,
+                        RemoveBreakpoint(self.source, self.line),
+                    ))
+                    .unwrap();
+                let thread = registry.find::<ThreadActor>(&self.thread);
+                thread
+                    .breakpoints
+                    .borrow_mut()
+                    .remove(&(self.source, self.line));
+                registry.drop_actor_later(self.name());
+                stream.write_json_packet(&DeleteReply { from: self.name() });
                 ActorMessageStatus::Processed
             },
 
@@ -36,7 +36,7 @@ use crate::protocol::JsonPacketStream;
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use devtools_traits::{ChromeToDevtoolsControlMsg, ConsoleMessage, DevtoolsControlMsg};
 use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo, LogLevel, NetworkEvent};
-use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
+use devtools_traits::{PauseInfo, ScriptToDevtoolsControlMsg, WorkerId};
 use ipc_channel::ipc::IpcSender;
 use msg::constellation_msg::PipelineId;
 use std::borrow::ToOwned;
@@ -254,15 +254,20 @@ fn run_server(
                 pipeline: pipeline,
             };
 
-            let timeline = TimelineActor::new(actors.new_name("timeline"), pipeline, script_sender);
+            let timeline =
+                TimelineActor::new(actors.new_name("timeline"), pipeline, script_sender.clone());
 
             let profiler = ProfilerActor::new(actors.new_name("profiler"));
             let performance = PerformanceActor::new(actors.new_name("performance"));
 
             // the strange switch between styleSheets and stylesheets is due
             // to an inconsistency in devtools. See Bug #1498893 in bugzilla
-            let styleSheets = StyleSheetsActor::new(actors.new_name("stylesheets"));
-            let thread = ThreadActor::new(actors.new_name("context"));
+            let styleSheets = StyleSheetsActor::new(
+                actors.new_name("stylesheets"),
+                script_sender.clone(),
+                pipeline,
+            );
+            let thread = ThreadActor::new(actors.new_name("context"), script_sender, pipeline);
 
             let DevtoolsPageInfo { title, url } = page_info;
             let target = BrowsingContextActor {
@@ -361,6 +366,27 @@ fn run_server(
         }
     }
 
+    fn handle_debugger_paused(
+        actors: Arc<Mutex<ActorRegistry>>,
+        id: PipelineId,
+        info: PauseInfo,
+        actor_pipelines: &HashMap<PipelineId, String>,
+    ) {
+        let mut actors = actors.lock().unwrap();
+        let actor_name = match actor_pipelines.get(&id) {
+            Some(name) => name,
+            None => return,
+        };
+        let thread_actor_name = actors
+            .find::<BrowsingContextActor>(actor_name)
+            .thread
+            .clone();
+        actors
+            .find::<ThreadActor>(&thread_actor_name)
+            .paused(&actors, info);
+        actors.register_pending();
+    }
+
     fn find_console_actor(
         actors: Arc<Mutex<ActorRegistry>>,
         id: PipelineId,
@@ -606,6 +632,10 @@ fn run_server(
                     &actor_workers,
                 )
             },
+            DevtoolsControlMsg::FromScript(ScriptToDevtoolsControlMsg::DebuggerPaused(
+                id,
+                info,
+            )) => handle_debugger_paused(actors.clone(), id, info, &actor_pipelines),
             DevtoolsControlMsg::FromChrome(ChromeToDevtoolsControlMsg::NetworkEvent(
                 request_id,
                 network_event,
@@ -82,6 +82,9 @@ pub enum ScriptToDevtoolsControlMsg {
 
     /// Report a CSS parse error for the given pipeline
     ReportCSSError(PipelineId, CSSError),
+
+    /// Script in the given pipeline was paused by the debugger.
+    DebuggerPaused(PipelineId, PauseInfo),
 }
 
 /// Serialized JS return values
@@ -219,6 +222,101 @@ pub enum DevtoolScriptControlMsg {
     RequestAnimationFrame(PipelineId, String),
     /// Direct the given pipeline to reload the current page.
     Reload(PipelineId),
+    /// Retrieve the details of the author stylesheets of the given pipeline.
+    GetStyleSheets(PipelineId, IpcSender<Vec<StyleSheetInfo>>),
+    /// Retrieve the text of the stylesheet at the given index in the given pipeline.
+    GetStyleSheetText(PipelineId, usize, IpcSender<Option<String>>),
+    /// Replace the contents of the stylesheet at the given index in the given pipeline.
+    UpdateStyleSheet(PipelineId, usize, String),
+    /// Control the JavaScript debugger of the given pipeline.
+    Debugger(PipelineId, DebuggerRequest),
+}
+
+/// Requests to the JavaScript debugger of a pipeline.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum DebuggerRequest {
+    /// Start debugging the scripts of the pipeline.
+    Attach,
+    /// Stop debugging the scripts of the pipeline, resuming it if it is paused.
+    Detach,
+    /// Retrieve the scripts sources known to the debugger.
+    GetSources(IpcSender<Vec<SourceInfo>>),
+    /// Retrieve the text of the source with the given id.
+    GetSourceText(u32, IpcSender<Option<String>>),
+    /// Set a breakpoint at the given line of a source, or the closest following line
+    /// that has code. Replies with the line the breakpoint was actually set on.
+    SetBreakpoint(u32, u32, IpcSender<Option<u32>>),
+    /// Remove the breakpoint at the given line of a source.
+    RemoveBreakpoint(u32, u32),
+    /// Pause as soon as script starts running.
+    Interrupt,
+    /// Resume paused script, optionally pausing again once the given limit is reached.
+    Resume(Option<ResumeLimit>),
+    /// Retrieve the stack of paused script, youngest frame first.
+    GetFrames(IpcSender<Vec<FrameInfo>>),
+    /// Evaluate an expression in the frame of paused script at the given depth.
+    Evaluate(u32, String, IpcSender<Option<FrameEvaluation>>),
+}
+
+/// Where paused script should stop again after resuming.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ResumeLimit {
+    /// At the next line of the current frame, stepping over calls.
+    Next,
+    /// At the next line of script, stepping into calls.
+    Step,
+    /// After returning from the current frame.
+    Finish,
+}
+
+/// A script source known to the debugger.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SourceInfo {
+    pub id: u32,
+    pub url: Option<String>,
+    pub introduction_type: Option<String>,
+}
+
+/// A frame of paused script.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FrameInfo {
+    pub depth: u32,
+    /// The kind of frame: "call", "eval", "global" or "module".
+    pub kind: String,
+    /// The name of the function being called, for call frames.
+    pub callee: Option<String>,
+    /// The id of the source being run.
+    pub source: Option<u32>,
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Why and where script was paused by the debugger.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PauseInfo {
+    /// The reason of the pause: "breakpoint", "resumeLimit", "interrupted" or
+    /// "debuggerStatement".
+    pub why: String,
+    pub frame: FrameInfo,
+}
+
+/// The completion of an expression evaluated in a frame of paused script.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FrameEvaluation {
+    /// Whether the evaluation threw instead of returning.
+    pub threw: bool,
+    pub value: EvaluateJSReply,
+}
+
+/// Details of a stylesheet of a document, as shown in the style editor.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StyleSheetInfo {
+    /// The index of the stylesheet in the document's stylesheet list.
+    pub index: usize,
+    pub href: Option<String>,
+    pub title: Option<String>,
+    pub disabled: bool,
+    pub ruleCount: usize,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -118,6 +118,11 @@ pub enum EmbedderMsg {
     ResizeTo(DeviceIntSize),
     // Show an alert message.
     Alert(String, IpcSender<()>),
+    /// Show a confirm dialog, and report whether the user accepted it.
+    Confirm(String, IpcSender<bool>),
+    /// Show a prompt dialog with a default value, and report the text entered by the user,
+    /// or `None` if the dialog was cancelled.
+    Prompt(String, String, IpcSender<Option<String>>),
     /// Wether or not to allow a pipeline to load a url.
     AllowNavigationRequest(PipelineId, ServoUrl),
     /// Whether or not to allow script to open a new tab/browser
@@ -142,6 +147,10 @@ pub enum EmbedderMsg {
     HistoryChanged(Vec<ServoUrl>, usize),
     /// Enter or exit fullscreen
     SetFullscreenState(bool),
+    /// Maximize or restore the window
+    SetMaximizedState(bool),
+    /// Minimize or restore the window
+    SetMinimizedState(bool),
     /// The load of a page has begun
     LoadStart,
     /// The load of a page has completed
@@ -172,6 +181,8 @@ impl Debug for EmbedderMsg {
             EmbedderMsg::MoveTo(..) => write!(f, "MoveTo"),
             EmbedderMsg::ResizeTo(..) => write!(f, "ResizeTo"),
             EmbedderMsg::Alert(..) => write!(f, "Alert"),
+            EmbedderMsg::Confirm(..) => write!(f, "Confirm"),
+            EmbedderMsg::Prompt(..) => write!(f, "Prompt"),
             EmbedderMsg::AllowUnload(..) => write!(f, "AllowUnload"),
             EmbedderMsg::AllowNavigationRequest(..) => write!(f, "AllowNavigationRequest"),
             EmbedderMsg::Keyboard(..) => write!(f, "Keyboard"),
@@ -183,6 +194,8 @@ impl Debug for EmbedderMsg {
             EmbedderMsg::CloseBrowser => write!(f, "CloseBrowser"),
             EmbedderMsg::HistoryChanged(..) => write!(f, "HistoryChanged"),
             EmbedderMsg::SetFullscreenState(..) => write!(f, "SetFullscreenState"),
+            EmbedderMsg::SetMaximizedState(..) => write!(f, "SetMaximizedState"),
+            EmbedderMsg::SetMinimizedState(..) => write!(f, "SetMinimizedState"),
             EmbedderMsg::LoadStart => write!(f, "LoadStart"),
             EmbedderMsg::LoadComplete => write!(f, "LoadComplete"),
             EmbedderMsg::Panic(..) => write!(f, "Panic"),
@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::font_context::{FontContext, FontSource};
-use crate::font_template::FontTemplateDescriptor;
+use crate::font_template::{FontTemplateDescriptor, UnicodeRanges};
 use crate::platform::font::{FontHandle, FontTable};
 use crate::platform::font_context::FontContextHandle;
 pub use crate::platform::font_list::fallback_font_families;
@@ -53,6 +53,10 @@ pub trait FontHandleMethods: Sized {
         pt_size: Option<Au>,
     ) -> Result<Self, ()>;
 
+    /// Sets the design axis coordinates of a variable font. Axes that the font does not have
+    /// are ignored, and platforms without support for variable fonts ignore all of them.
+    fn set_variations(&mut self, _variations: &[FontVariation]) {}
+
     fn template(&self) -> Arc<FontTemplateData>;
     fn family_name(&self) -> Option<String>;
     fn face_name(&self) -> Option<String>;
@@ -124,6 +128,8 @@ pub struct FontDescriptor {
     pub template_descriptor: FontTemplateDescriptor,
     pub variant: font_variant_caps::T,
     pub pt_size: Au,
+    /// The variation axis coordinates, from `font-variation-settings`.
+    pub variation_settings: Vec<FontVariation>,
 }
 
 impl<'a> From<&'a FontStyleStruct> for FontDescriptor {
@@ -132,7 +138,59 @@ impl<'a> From<&'a FontStyleStruct> for FontDescriptor {
             template_descriptor: FontTemplateDescriptor::from(style),
             variant: style.font_variant_caps,
             pt_size: style.font_size.size(),
+            variation_settings: FontVariation::from_style(style),
+        }
+    }
+}
+
+/// A coordinate on one of the design axes of a variable font.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FontVariation {
+    pub tag: FontTableTag,
+    pub value: NotNan<f32>,
+}
+
+impl FontVariation {
+    /// Returns the axis coordinates given by `font-variation-settings`. When an axis appears
+    /// more than once the last value wins.
+    fn from_style(style: &FontStyleStruct) -> Vec<FontVariation> {
+        let mut variations: Vec<FontVariation> = Vec::new();
+        for setting in style.font_variation_settings.0.iter().rev() {
+            if variations.iter().any(|v| v.tag == setting.tag.0) {
+                continue;
+            }
+            if let Ok(value) = NotNan::new(setting.value) {
+                variations.push(FontVariation {
+                    tag: setting.tag.0,
+                    value,
+                });
+            }
         }
+        variations.reverse();
+        variations
+    }
+}
+
+impl From<FontVariation> for webrender_api::FontVariation {
+    fn from(variation: FontVariation) -> Self {
+        webrender_api::FontVariation {
+            tag: variation.tag,
+            value: variation.value.into_inner(),
+        }
+    }
+}
+
+/// An OpenType feature to apply while shaping. A `value` of zero turns the feature off, and
+/// larger values select an alternate glyph for features that have several of them.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct FontFeature {
+    pub tag: FontTableTag,
+    pub value: u32,
+}
+
+impl FontFeature {
+    pub fn new(tag: FontTableTag, value: u32) -> FontFeature {
+        FontFeature { tag, value }
     }
 }
 
@@ -146,6 +204,8 @@ pub struct Font {
     shape_cache: RefCell<HashMap<ShapeCacheEntry, Arc<GlyphStore>>>,
     glyph_advance_cache: RefCell<HashMap<u32, FractionalPixel>>,
     pub font_key: webrender_api::FontInstanceKey,
+    /// The code points this font may be used for, if it was restricted by a `unicode-range`.
+    pub unicode_range: Option<UnicodeRanges>,
 }
 
 impl Font {
@@ -154,6 +214,7 @@ impl Font {
         descriptor: FontDescriptor,
         actual_pt_size: Au,
         font_key: webrender_api::FontInstanceKey,
+        unicode_range: Option<UnicodeRanges>,
     ) -> Font {
         let metrics = handle.metrics();
 
@@ -166,6 +227,7 @@ impl Font {
             shape_cache: RefCell::new(HashMap::new()),
             glyph_advance_cache: RefCell::new(HashMap::new()),
             font_key,
+            unicode_range,
         }
     }
 
@@ -191,7 +253,7 @@ bitflags! {
 }
 
 /// Various options that control text shaping.
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct ShapingOptions {
     /// Spacing to add between each letter. Corresponds to the CSS 2.1 `letter-spacing` property.
     /// NB: You will probably want to set the `IGNORE_LIGATURES_SHAPING_FLAG` if this is non-null.
@@ -202,6 +264,9 @@ pub struct ShapingOptions {
     pub script: Script,
     /// Various flags.
     pub flags: ShapingFlags,
+    /// OpenType features to turn on or off, from `font-feature-settings` and the
+    /// `font-variant-*` properties. Later entries override earlier ones with the same tag.
+    pub features: Vec<FontFeature>,
 }
 
 /// An entry in the shape cache.
@@ -218,7 +283,7 @@ impl Font {
 
         let lookup_key = ShapeCacheEntry {
             text: text.to_owned(),
-            options: *options,
+            options: options.clone(),
         };
         let result = self
             .shape_cache
@@ -261,6 +326,7 @@ impl Font {
     fn can_do_fast_shaping(&self, text: &str, options: &ShapingOptions) -> bool {
         options.script == Script::Latin &&
             !options.flags.contains(ShapingFlags::RTL_FLAG) &&
+            options.features.is_empty() &&
             self.handle.can_do_fast_shaping() &&
             text.is_ascii()
     }
@@ -328,7 +394,16 @@ impl Font {
     }
 
     pub fn has_glyph_for(&self, codepoint: char) -> bool {
-        self.glyph_index(codepoint).is_some()
+        self.covers(Some(codepoint)) && self.glyph_index(codepoint).is_some()
+    }
+
+    /// Returns true if this font may be used for `codepoint`, or for any code point at all when
+    /// `codepoint` is `None`.
+    pub fn covers(&self, codepoint: Option<char>) -> bool {
+        match (&self.unicode_range, codepoint) {
+            (Some(unicode_range), Some(codepoint)) => unicode_range.contains(codepoint),
+            _ => true,
+        }
     }
 
     pub fn glyph_h_kerning(&self, first_glyph: GlyphId, second_glyph: GlyphId) -> FractionalPixel {
@@ -390,7 +465,7 @@ impl FontGroup {
     ) -> Option<FontRef> {
         let has_glyph = |font: &FontRef| font.borrow().has_glyph_for(codepoint);
 
-        let font = self.find(&mut font_context, |font| has_glyph(font));
+        let font = self.find(&mut font_context, Some(codepoint), |font| has_glyph(font));
         if font.is_some() {
             return font;
         }
@@ -415,20 +490,26 @@ impl FontGroup {
         &mut self,
         mut font_context: &mut FontContext<S>,
     ) -> Option<FontRef> {
-        self.find(&mut font_context, |_| true)
+        self.find(&mut font_context, None, |_| true)
             .or_else(|| self.find_fallback(&mut font_context, None, |_| true))
     }
 
-    /// Find a font which returns true for `predicate`. This method mutates because we may need to
-    /// load new font data in the process of finding a suitable font.
-    fn find<S, P>(&mut self, mut font_context: &mut FontContext<S>, predicate: P) -> Option<FontRef>
+    /// Find a font which returns true for `predicate`, preferring the fonts of each family which
+    /// may be used for `codepoint`. This method mutates because we may need to load new font data
+    /// in the process of finding a suitable font.
+    fn find<S, P>(
+        &mut self,
+        mut font_context: &mut FontContext<S>,
+        codepoint: Option<char>,
+        predicate: P,
+    ) -> Option<FontRef>
     where
         S: FontSource,
         P: FnMut(&FontRef) -> bool,
     {
         self.families
             .iter_mut()
-            .filter_map(|family| family.font(&mut font_context))
+            .filter_map(|family| family.font(&mut font_context, codepoint))
             .find(predicate)
     }
 
@@ -450,7 +531,7 @@ impl FontGroup {
             .chain(fallback_font_families(codepoint).into_iter().map(|family| {
                 FontFamilyDescriptor::new(FontFamilyName::from(family), FontSearchScope::Local)
             }))
-            .filter_map(|family| font_context.font(&self.descriptor, &family))
+            .filter_map(|family| font_context.font(&self.descriptor, &family, None))
             .find(predicate)
     }
 }
@@ -458,12 +539,19 @@ impl FontGroup {
 /// A `FontGroupFamily` is a single font family in a `FontGroup`. It corresponds to one of the
 /// families listed in the `font-family` CSS property. The corresponding font data is lazy-loaded,
 /// only if actually needed.
+///
+/// A family defined by `@font-face` rules with a `unicode-range` may consist of several fonts,
+/// each of which can only be used for part of the code points.
 #[derive(Debug)]
 struct FontGroupFamily {
     font_descriptor: FontDescriptor,
     family_descriptor: FontFamilyDescriptor,
+    /// Whether we have looked for a font without asking for a specific code point.
     loaded: bool,
-    font: Option<FontRef>,
+    /// Whether looking for a font for a specific code point found nothing, meaning that there
+    /// are no further fonts to load in this family.
+    exhausted: bool,
+    fonts: SmallVec<[FontRef; 1]>,
 }
 
 impl FontGroupFamily {
@@ -475,20 +563,46 @@ impl FontGroupFamily {
             font_descriptor,
             family_descriptor,
             loaded: false,
-            font: None,
+            exhausted: false,
+            fonts: SmallVec::new(),
         }
     }
 
-    /// Returns the font within this family which matches the style. We'll fetch the data from the
-    /// `FontContext` the first time this method is called, and return a cached reference on
+    /// Returns the font within this family which matches the style and may be used for
+    /// `codepoint`, or else the first font loaded for this family. We'll fetch the data from the
+    /// `FontContext` the first time a font is needed, and return a cached reference on
     /// subsequent calls.
-    fn font<S: FontSource>(&mut self, font_context: &mut FontContext<S>) -> Option<FontRef> {
-        if !self.loaded {
-            self.font = font_context.font(&self.font_descriptor, &self.family_descriptor);
-            self.loaded = true;
+    fn font<S: FontSource>(
+        &mut self,
+        font_context: &mut FontContext<S>,
+        codepoint: Option<char>,
+    ) -> Option<FontRef> {
+        if let Some(font) = self
+            .fonts
+            .iter()
+            .find(|font| font.borrow().covers(codepoint))
+        {
+            return Some(font.clone());
+        }
+
+        let searched = match codepoint {
+            Some(_) => self.exhausted,
+            None => self.loaded,
+        };
+        if !searched {
+            match font_context.font(&self.font_descriptor, &self.family_descriptor, codepoint) {
+                Some(font) => {
+                    if !self.fonts.iter().any(|f| Rc::ptr_eq(f, &font)) {
+                        self.fonts.push(font.clone());
+                    }
+                    return Some(font);
+                },
+                None if codepoint.is_some() => self.exhausted = true,
+                None => self.loaded = true,
+            }
         }
 
-        self.font.clone()
+        self.fonts.first().cloned()
     }
 }
 
@@ -4,7 +4,7 @@
 
 use crate::font::{FontFamilyDescriptor, FontFamilyName, FontSearchScope};
 use crate::font_context::FontSource;
-use crate::font_template::{FontTemplate, FontTemplateDescriptor};
+use crate::font_template::{FontTemplate, FontTemplateDescriptor, UnicodeRanges};
 use crate::platform::font_context::FontContextHandle;
 use crate::platform::font_list::for_each_available_family;
 use crate::platform::font_list::for_each_variation;
@@ -34,6 +34,8 @@ pub struct FontTemplates {
 pub struct FontTemplateInfo {
     pub font_template: Arc<FontTemplateData>,
     pub font_key: webrender_api::FontKey,
+    /// The code points this template may be used for, if it was restricted by a `unicode-range`.
+    pub unicode_range: Option<UnicodeRanges>,
 }
 
 impl FontTemplates {
@@ -41,16 +43,18 @@ impl FontTemplates {
         FontTemplates { templates: vec![] }
     }
 
-    /// Find a font in this family that matches a given descriptor.
+    /// Find a font in this family that matches a given descriptor. If a `codepoint` is given,
+    /// only fonts whose `unicode-range` includes it are considered.
     pub fn find_font_for_style(
         &mut self,
         desc: &FontTemplateDescriptor,
         fctx: &FontContextHandle,
+        codepoint: Option<char>,
     ) -> Option<Arc<FontTemplateData>> {
         // TODO(Issue #189): optimize lookup for
         // regular/bold/italic/bolditalic with fixed offsets and a
         // static decision table for fallback between these values.
-        for template in &mut self.templates {
+        for template in self.templates.iter_mut().filter(|t| t.covers(codepoint)) {
             let maybe_template = template.data_for_descriptor(fctx, desc);
             if maybe_template.is_some() {
                 return maybe_template;
@@ -60,7 +64,7 @@ impl FontTemplates {
         // We didn't find an exact match. Do more expensive fuzzy matching.
         // TODO(#190): Do a better job.
         let (mut best_template_data, mut best_distance) = (None, f32::MAX);
-        for template in &mut self.templates {
+        for template in self.templates.iter_mut().filter(|t| t.covers(codepoint)) {
             if let Some((template_data, distance)) =
                 template.data_for_approximate_descriptor(fctx, desc)
             {
@@ -77,7 +81,7 @@ impl FontTemplates {
         // If a request is made for a font family that exists,
         // pick the first valid font in the family if we failed
         // to find an exact match for the descriptor.
-        for template in &mut self.templates {
+        for template in self.templates.iter_mut().filter(|t| t.covers(codepoint)) {
             let maybe_template = template.get();
             if maybe_template.is_some() {
                 return maybe_template;
@@ -87,17 +91,41 @@ impl FontTemplates {
         None
     }
 
-    pub fn add_template(&mut self, identifier: Atom, maybe_data: Option<Vec<u8>>) {
+    pub fn add_template(
+        &mut self,
+        identifier: Atom,
+        maybe_data: Option<Vec<u8>>,
+        unicode_range: Option<UnicodeRanges>,
+    ) {
         for template in &self.templates {
             if *template.identifier() == identifier {
                 return;
             }
         }
 
-        if let Ok(template) = FontTemplate::new(identifier, maybe_data) {
+        if let Ok(template) = FontTemplate::new(identifier, maybe_data, unicode_range) {
             self.templates.push(template);
         }
     }
+
+    /// Returns the `unicode-range` of the template with the given identifier, if any.
+    pub fn unicode_range(&self, identifier: &Atom) -> Option<UnicodeRanges> {
+        self.templates
+            .iter()
+            .find(|template| template.identifier() == identifier)
+            .and_then(|template| template.unicode_range().cloned())
+    }
+}
+
+/// Notifications sent to layout as the font faces added with `FontCacheThread::add_web_font`
+/// and `FontCacheThread::add_deferred_web_font` finish loading.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum WebFontLoadEvent {
+    /// A font face has been loaded, or all of its sources failed to load.
+    Loaded,
+    /// A font face whose loading was deferred until text needed one of the code points in its
+    /// `unicode-range` has been loaded, or all of its sources failed to load.
+    DeferredLoaded,
 }
 
 /// Commands that the FontContext sends to the font cache thread.
@@ -106,15 +134,36 @@ pub enum Command {
     GetFontTemplate(
         FontTemplateDescriptor,
         FontFamilyDescriptor,
+        Option<char>,
         IpcSender<Reply>,
     ),
     GetFontInstance(
         webrender_api::FontKey,
         Au,
+        Vec<webrender_api::FontVariation>,
         IpcSender<webrender_api::FontInstanceKey>,
     ),
-    AddWebFont(LowercaseString, EffectiveSources, IpcSender<()>),
-    AddDownloadedWebFont(LowercaseString, ServoUrl, Vec<u8>, IpcSender<()>),
+    AddWebFont(
+        LowercaseString,
+        EffectiveSources,
+        Option<UnicodeRanges>,
+        WebFontLoadEvent,
+        IpcSender<WebFontLoadEvent>,
+    ),
+    AddDeferredWebFont(
+        LowercaseString,
+        EffectiveSources,
+        UnicodeRanges,
+        IpcSender<WebFontLoadEvent>,
+    ),
+    AddDownloadedWebFont(
+        LowercaseString,
+        ServoUrl,
+        Vec<u8>,
+        Option<UnicodeRanges>,
+        WebFontLoadEvent,
+        IpcSender<WebFontLoadEvent>,
+    ),
     Exit(IpcSender<()>),
     Ping,
 }
@@ -125,6 +174,14 @@ pub enum Reply {
     GetFontTemplateReply(Option<FontTemplateInfo>),
 }
 
+/// A font face whose sources are only fetched once text needs one of the code points in its
+/// `unicode-range`.
+struct DeferredWebFont {
+    sources: EffectiveSources,
+    unicode_range: UnicodeRanges,
+    sender: IpcSender<WebFontLoadEvent>,
+}
+
 /// The font cache thread itself. It maintains a list of reference counted
 /// font templates that are currently in use.
 struct FontCache {
@@ -133,11 +190,19 @@ struct FontCache {
     generic_fonts: HashMap<FontFamilyName, LowercaseString>,
     local_families: HashMap<LowercaseString, FontTemplates>,
     web_families: HashMap<LowercaseString, FontTemplates>,
+    deferred_web_fonts: HashMap<LowercaseString, Vec<DeferredWebFont>>,
     font_context: FontContextHandle,
     core_resource_thread: CoreResourceThread,
     webrender_api: webrender_api::RenderApi,
     webrender_fonts: HashMap<Atom, webrender_api::FontKey>,
-    font_instances: HashMap<(webrender_api::FontKey, Au), webrender_api::FontInstanceKey>,
+    font_instances: HashMap<
+        (
+            webrender_api::FontKey,
+            Au,
+            Vec<webrender_api::FontVariation>,
+        ),
+        webrender_api::FontInstanceKey,
+    >,
 }
 
 fn populate_generic_fonts() -> HashMap<FontFamilyName, LowercaseString> {
@@ -173,35 +238,59 @@ impl FontCache {
             let msg = self.port.recv().unwrap();
 
             match msg {
-                Command::GetFontTemplate(template_descriptor, family_descriptor, result) => {
-                    let maybe_font_template =
-                        self.find_font_template(&template_descriptor, &family_descriptor);
+                Command::GetFontTemplate(
+                    template_descriptor,
+                    family_descriptor,
+                    codepoint,
+                    result,
+                ) => {
+                    let maybe_font_template = self.find_font_template(
+                        &template_descriptor,
+                        &family_descriptor,
+                        codepoint,
+                    );
                     let _ = result.send(Reply::GetFontTemplateReply(maybe_font_template));
                 },
-                Command::GetFontInstance(font_key, size, result) => {
+                Command::GetFontInstance(font_key, size, variations, result) => {
                     let webrender_api = &self.webrender_api;
 
-                    let instance_key =
-                        *self
-                            .font_instances
-                            .entry((font_key, size))
-                            .or_insert_with(|| {
-                                let key = webrender_api.generate_font_instance_key();
-                                let mut txn = webrender_api::Transaction::new();
-                                txn.add_font_instance(key, font_key, size, None, None, Vec::new());
-                                webrender_api.update_resources(txn.resource_updates);
-                                key
-                            });
+                    let instance_key = *self
+                        .font_instances
+                        .entry((font_key, size, variations.clone()))
+                        .or_insert_with(|| {
+                            let key = webrender_api.generate_font_instance_key();
+                            let mut txn = webrender_api::Transaction::new();
+                            txn.add_font_instance(key, font_key, size, None, None, variations);
+                            webrender_api.update_resources(txn.resource_updates);
+                            key
+                        });
 
                     let _ = result.send(instance_key);
                 },
-                Command::AddWebFont(family_name, sources, result) => {
-                    self.handle_add_web_font(family_name, sources, result);
+                Command::AddWebFont(family_name, sources, unicode_range, event, result) => {
+                    self.handle_add_web_font(family_name, sources, unicode_range, event, result);
+                },
+                Command::AddDeferredWebFont(family_name, sources, unicode_range, result) => {
+                    self.deferred_web_fonts
+                        .entry(family_name)
+                        .or_insert_with(Vec::new)
+                        .push(DeferredWebFont {
+                            sources,
+                            unicode_range,
+                            sender: result,
+                        });
                 },
-                Command::AddDownloadedWebFont(family_name, url, bytes, result) => {
+                Command::AddDownloadedWebFont(
+                    family_name,
+                    url,
+                    bytes,
+                    unicode_range,
+                    event,
+                    result,
+                ) => {
                     let templates = &mut self.web_families.get_mut(&family_name).unwrap();
-                    templates.add_template(Atom::from(url.to_string()), Some(bytes));
-                    drop(result.send(()));
+                    templates.add_template(Atom::from(url.to_string()), Some(bytes), unicode_range);
+                    drop(result.send(event));
                 },
                 Command::Ping => (),
                 Command::Exit(result) => {
@@ -216,12 +305,14 @@ impl FontCache {
         &mut self,
         family_name: LowercaseString,
         mut sources: EffectiveSources,
-        sender: IpcSender<()>,
+        unicode_range: Option<UnicodeRanges>,
+        event: WebFontLoadEvent,
+        sender: IpcSender<WebFontLoadEvent>,
     ) {
         let src = if let Some(src) = sources.next() {
             src
         } else {
-            sender.send(()).unwrap();
+            sender.send(event).unwrap();
             return;
         };
 
@@ -238,6 +329,8 @@ impl FontCache {
                     None => return,
                 };
 
+                // The font cache doesn't know which document a font is for, so
+                // the request is left cross-site for `SameSite` cookies.
                 let request = RequestBuilder::new(url.clone()).destination(Destination::Font);
 
                 let channel_to_self = self.channel_to_self.clone();
@@ -247,7 +340,8 @@ impl FontCache {
                 fetch_async(request, &self.core_resource_thread, move |response| {
                     match response {
                         FetchResponseMsg::ProcessRequestBody |
-                        FetchResponseMsg::ProcessRequestEOF => (),
+                        FetchResponseMsg::ProcessRequestEOF |
+                        FetchResponseMsg::ProcessCspViolations(_) => (),
                         FetchResponseMsg::ProcessResponse(meta_result) => {
                             trace!(
                                 "@font-face {} metadata ok={:?}",
@@ -268,6 +362,8 @@ impl FontCache {
                                 let msg = Command::AddWebFont(
                                     family_name.clone(),
                                     sources.clone(),
+                                    unicode_range.clone(),
+                                    event,
                                     sender.clone(),
                                 );
                                 channel_to_self.send(msg).unwrap();
@@ -275,6 +371,9 @@ impl FontCache {
                             }
                             let bytes = mem::replace(&mut *bytes.lock().unwrap(), vec![]);
                             trace!("@font-face {} data={:?}", family_name, bytes);
+                            // The sanitiser decodes WOFF and WOFF2 data to sfnt and validates
+                            // the font tables, so that untrusted data never reaches the platform
+                            // font libraries.
                             let bytes = match fontsan::process(&bytes) {
                                 Ok(san) => san,
                                 Err(_) => {
@@ -287,6 +386,8 @@ impl FontCache {
                                     let msg = Command::AddWebFont(
                                         family_name.clone(),
                                         sources.clone(),
+                                        unicode_range.clone(),
+                                        event,
                                         sender.clone(),
                                     );
                                     channel_to_self.send(msg).unwrap();
@@ -297,6 +398,8 @@ impl FontCache {
                                 family_name.clone(),
                                 url.clone(),
                                 bytes,
+                                unicode_range.clone(),
+                                event,
                                 sender.clone(),
                             );
                             channel_to_self.send(command).unwrap();
@@ -310,12 +413,13 @@ impl FontCache {
                 let mut found = false;
                 for_each_variation(&font_face_name, |path| {
                     found = true;
-                    templates.add_template(Atom::from(&*path), None);
+                    templates.add_template(Atom::from(&*path), None, unicode_range.clone());
                 });
                 if found {
-                    sender.send(()).unwrap();
+                    sender.send(event).unwrap();
                 } else {
-                    let msg = Command::AddWebFont(family_name, sources, sender);
+                    let msg =
+                        Command::AddWebFont(family_name, sources, unicode_range, event, sender);
                     self.channel_to_self.send(msg).unwrap();
                 }
             },
@@ -355,14 +459,14 @@ impl FontCache {
 
             if s.templates.is_empty() {
                 for_each_variation(&family_name, |path| {
-                    s.add_template(Atom::from(&*path), None);
+                    s.add_template(Atom::from(&*path), None, None);
                 });
             }
 
             // TODO(Issue #192: handle generic font families, like 'serif' and 'sans-serif'.
             // if such family exists, try to match style to a font
 
-            s.find_font_for_style(template_descriptor, &self.font_context)
+            s.find_font_for_style(template_descriptor, &self.font_context, None)
         } else {
             debug!(
                 "FontList: Couldn't find font family with name={}",
@@ -376,18 +480,54 @@ impl FontCache {
         &mut self,
         template_descriptor: &FontTemplateDescriptor,
         family_name: &FontFamilyName,
-    ) -> Option<Arc<FontTemplateData>> {
+        codepoint: Option<char>,
+    ) -> Option<FontTemplateInfo> {
         let family_name = LowercaseString::from(family_name);
 
-        if self.web_families.contains_key(&family_name) {
-            let templates = self.web_families.get_mut(&family_name).unwrap();
-            templates.find_font_for_style(template_descriptor, &self.font_context)
-        } else {
-            None
+        if let Some(codepoint) = codepoint {
+            self.load_deferred_web_fonts(&family_name, codepoint);
         }
+
+        let templates = self.web_families.get_mut(&family_name)?;
+        let template =
+            templates.find_font_for_style(template_descriptor, &self.font_context, codepoint)?;
+        let unicode_range = templates.unicode_range(&template.identifier);
+        Some(self.get_font_template_info(template, unicode_range))
     }
 
-    fn get_font_template_info(&mut self, template: Arc<FontTemplateData>) -> FontTemplateInfo {
+    /// Starts loading the deferred font faces of a web font family whose `unicode-range`
+    /// includes `codepoint`. They become available once they have been fetched.
+    fn load_deferred_web_fonts(&mut self, family_name: &LowercaseString, codepoint: char) {
+        let deferred_web_fonts = match self.deferred_web_fonts.get_mut(family_name) {
+            Some(deferred_web_fonts) => deferred_web_fonts,
+            None => return,
+        };
+
+        let (needed, remaining): (Vec<_>, Vec<_>) = mem::replace(deferred_web_fonts, vec![])
+            .into_iter()
+            .partition(|font| font.unicode_range.contains(codepoint));
+        *deferred_web_fonts = remaining;
+
+        for font in needed {
+            debug!(
+                "Loading deferred @font-face {} for {:?}",
+                family_name, codepoint
+            );
+            self.handle_add_web_font(
+                family_name.clone(),
+                font.sources,
+                Some(font.unicode_range),
+                WebFontLoadEvent::DeferredLoaded,
+                font.sender,
+            );
+        }
+    }
+
+    fn get_font_template_info(
+        &mut self,
+        template: Arc<FontTemplateData>,
+        unicode_range: Option<UnicodeRanges>,
+    ) -> FontTemplateInfo {
         let webrender_api = &self.webrender_api;
         let webrender_fonts = &mut self.webrender_fonts;
 
@@ -408,6 +548,7 @@ impl FontCache {
         FontTemplateInfo {
             font_template: template,
             font_key: font_key,
+            unicode_range,
         }
     }
 
@@ -415,19 +556,21 @@ impl FontCache {
         &mut self,
         template_descriptor: &FontTemplateDescriptor,
         family_descriptor: &FontFamilyDescriptor,
+        codepoint: Option<char>,
     ) -> Option<FontTemplateInfo> {
-        match family_descriptor.scope {
-            FontSearchScope::Any => self
-                .find_font_in_web_family(&template_descriptor, &family_descriptor.name)
-                .or_else(|| {
-                    self.find_font_in_local_family(&template_descriptor, &family_descriptor.name)
-                }),
-
-            FontSearchScope::Local => {
-                self.find_font_in_local_family(&template_descriptor, &family_descriptor.name)
-            },
+        if family_descriptor.scope == FontSearchScope::Any {
+            let maybe_template_info = self.find_font_in_web_family(
+                &template_descriptor,
+                &family_descriptor.name,
+                codepoint,
+            );
+            if maybe_template_info.is_some() {
+                return maybe_template_info;
+            }
         }
-        .map(|t| self.get_font_template_info(t))
+
+        self.find_font_in_local_family(&template_descriptor, &family_descriptor.name)
+            .map(|t| self.get_font_template_info(t, None))
     }
 }
 
@@ -458,6 +601,7 @@ impl FontCacheThread {
                     generic_fonts,
                     local_families: HashMap::new(),
                     web_families: HashMap::new(),
+                    deferred_web_fonts: HashMap::new(),
                     font_context: FontContextHandle::new(),
                     core_resource_thread,
                     webrender_api,
@@ -473,16 +617,41 @@ impl FontCacheThread {
         FontCacheThread { chan: chan }
     }
 
+    /// Starts loading a font face from the `sources` of an `@font-face` rule. `sender` receives
+    /// `WebFontLoadEvent::Loaded` once the face has been loaded or all of its sources failed.
     pub fn add_web_font(
         &self,
         family: FamilyName,
         sources: EffectiveSources,
-        sender: IpcSender<()>,
+        unicode_range: Option<UnicodeRanges>,
+        sender: IpcSender<WebFontLoadEvent>,
     ) {
         self.chan
             .send(Command::AddWebFont(
                 LowercaseString::new(&family.name),
                 sources,
+                unicode_range,
+                WebFontLoadEvent::Loaded,
+                sender,
+            ))
+            .unwrap();
+    }
+
+    /// Adds a font face whose `sources` are only fetched once text needs one of the code points
+    /// in `unicode_range`. `sender` receives `WebFontLoadEvent::DeferredLoaded` once that has
+    /// happened and the face has been loaded or all of its sources failed.
+    pub fn add_deferred_web_font(
+        &self,
+        family: FamilyName,
+        sources: EffectiveSources,
+        unicode_range: UnicodeRanges,
+        sender: IpcSender<WebFontLoadEvent>,
+    ) {
+        self.chan
+            .send(Command::AddDeferredWebFont(
+                LowercaseString::new(&family.name),
+                sources,
+                unicode_range,
                 sender,
             ))
             .unwrap();
@@ -504,10 +673,16 @@ impl FontSource for FontCacheThread {
         &mut self,
         key: webrender_api::FontKey,
         size: Au,
+        variations: Vec<webrender_api::FontVariation>,
     ) -> webrender_api::FontInstanceKey {
         let (response_chan, response_port) = ipc::channel().expect("failed to create IPC channel");
         self.chan
-            .send(Command::GetFontInstance(key, size, response_chan))
+            .send(Command::GetFontInstance(
+                key,
+                size,
+                variations,
+                response_chan,
+            ))
             .expect("failed to send message to font cache thread");
 
         let instance_key = response_port.recv();
@@ -526,12 +701,14 @@ impl FontSource for FontCacheThread {
         &mut self,
         template_descriptor: FontTemplateDescriptor,
         family_descriptor: FontFamilyDescriptor,
+        codepoint: Option<char>,
     ) -> Option<FontTemplateInfo> {
         let (response_chan, response_port) = ipc::channel().expect("failed to create IPC channel");
         self.chan
             .send(Command::GetFontTemplate(
                 template_descriptor,
                 family_descriptor,
+                codepoint,
                 response_chan,
             ))
             .expect("failed to send message to font cache thread");
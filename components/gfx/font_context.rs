@@ -13,8 +13,9 @@ use app_units::Au;
 use fnv::FnvHasher;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use servo_arc::Arc;
+use servo_atoms::Atom;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::default::Default;
 use std::hash::{BuildHasherDefault, Hash, Hasher};
 use std::rc::Rc;
@@ -33,12 +34,14 @@ pub trait FontSource {
         &mut self,
         key: webrender_api::FontKey,
         size: Au,
+        variations: Vec<webrender_api::FontVariation>,
     ) -> webrender_api::FontInstanceKey;
 
     fn font_template(
         &mut self,
         template_descriptor: FontTemplateDescriptor,
         family_descriptor: FontFamilyDescriptor,
+        codepoint: Option<char>,
     ) -> Option<FontTemplateInfo>;
 }
 
@@ -56,6 +59,11 @@ pub struct FontContext<S: FontSource> {
     // See bug https://github.com/servo/servo/issues/3300
     font_cache: HashMap<FontCacheKey, Option<FontRef>>,
     font_template_cache: HashMap<FontTemplateCacheKey, Option<FontTemplateInfo>>,
+    /// The templates found so far whose use is restricted by a `unicode-range`, so that a
+    /// single request to the font cache thread covers every code point of their range.
+    unicode_range_template_cache: HashMap<FontTemplateCacheKey, Vec<FontTemplateInfo>>,
+    /// The code points for which no template was found.
+    missing_codepoint_cache: HashMap<FontTemplateCacheKey, HashSet<char>>,
 
     font_group_cache:
         HashMap<FontGroupCacheKey, Rc<RefCell<FontGroup>>, BuildHasherDefault<FnvHasher>>,
@@ -71,6 +79,8 @@ impl<S: FontSource> FontContext<S> {
             font_source,
             font_cache: HashMap::new(),
             font_template_cache: HashMap::new(),
+            unicode_range_template_cache: HashMap::new(),
+            missing_codepoint_cache: HashMap::new(),
             font_group_cache: HashMap::with_hasher(Default::default()),
             epoch: 0,
         }
@@ -84,6 +94,8 @@ impl<S: FontSource> FontContext<S> {
 
         self.font_cache.clear();
         self.font_template_cache.clear();
+        self.unicode_range_template_cache.clear();
+        self.missing_codepoint_cache.clear();
         self.font_group_cache.clear();
         self.epoch = current_epoch
     }
@@ -108,16 +120,25 @@ impl<S: FontSource> FontContext<S> {
         font_group
     }
 
-    /// Returns a font matching the parameters. Fonts are cached, so repeated calls will return a
-    /// reference to the same underlying `Font`.
+    /// Returns a font matching the parameters. If a `codepoint` is given, the font may be used
+    /// to render it, which rules out web fonts whose `unicode-range` doesn't include it. Fonts
+    /// are cached, so repeated calls will return a reference to the same underlying `Font`.
     pub fn font(
         &mut self,
         font_descriptor: &FontDescriptor,
         family_descriptor: &FontFamilyDescriptor,
+        codepoint: Option<char>,
     ) -> Option<FontRef> {
+        let template_info = self.font_template(
+            &font_descriptor.template_descriptor,
+            family_descriptor,
+            codepoint,
+        )?;
+
         let cache_key = FontCacheKey {
             font_descriptor: font_descriptor.clone(),
             family_descriptor: family_descriptor.clone(),
+            template_identifier: template_info.font_template.identifier.clone(),
         };
 
         self.font_cache
@@ -130,11 +151,8 @@ impl<S: FontSource> FontContext<S> {
                 );
 
                 let font = self
-                    .font_template(&font_descriptor.template_descriptor, family_descriptor)
-                    .and_then(|template_info| {
-                        self.create_font(template_info, font_descriptor.to_owned())
-                            .ok()
-                    })
+                    .create_font(template_info, font_descriptor.to_owned())
+                    .ok()
                     .map(|font| Rc::new(RefCell::new(font)));
 
                 self.font_cache.insert(cache_key, font.clone());
@@ -146,27 +164,82 @@ impl<S: FontSource> FontContext<S> {
         &mut self,
         template_descriptor: &FontTemplateDescriptor,
         family_descriptor: &FontFamilyDescriptor,
+        codepoint: Option<char>,
     ) -> Option<FontTemplateInfo> {
         let cache_key = FontTemplateCacheKey {
             template_descriptor: template_descriptor.clone(),
             family_descriptor: family_descriptor.clone(),
         };
 
-        self.font_template_cache.get(&cache_key).map(|v| v.clone()).unwrap_or_else(|| {
-            debug!(
-                "FontContext::font_template cache miss for template_descriptor={:?} family_descriptor={:?}",
-                template_descriptor,
-                family_descriptor
-            );
-
-            let template_info = self.font_source.font_template(
-                template_descriptor.clone(),
-                family_descriptor.clone(),
-            );
-
-            self.font_template_cache.insert(cache_key, template_info.clone());
-            template_info
-        })
+        // Most templates can be used for any code point, in which case the one found for the
+        // family as a whole will do.
+        if let Some(template_info) = self.font_template_cache.get(&cache_key) {
+            let usable = match (template_info, codepoint) {
+                (_, None) => true,
+                (&Some(ref template_info), Some(_)) => template_info.unicode_range.is_none(),
+                (&None, Some(_)) => false,
+            };
+            if usable {
+                return template_info.clone();
+            }
+        }
+
+        if let Some(codepoint) = codepoint {
+            let template_info =
+                self.unicode_range_template_cache
+                    .get(&cache_key)
+                    .and_then(|templates| {
+                        templates.iter().find(|template_info| {
+                            template_info
+                                .unicode_range
+                                .as_ref()
+                                .map_or(false, |unicode_range| unicode_range.contains(codepoint))
+                        })
+                    });
+            if let Some(template_info) = template_info {
+                return Some(template_info.clone());
+            }
+            if self
+                .missing_codepoint_cache
+                .get(&cache_key)
+                .map_or(false, |codepoints| codepoints.contains(&codepoint))
+            {
+                return None;
+            }
+        }
+
+        debug!(
+            "FontContext::font_template cache miss for template_descriptor={:?} family_descriptor={:?} codepoint={:?}",
+            template_descriptor,
+            family_descriptor,
+            codepoint
+        );
+
+        let template_info = self.font_source.font_template(
+            template_descriptor.clone(),
+            family_descriptor.clone(),
+            codepoint,
+        );
+
+        match (&template_info, codepoint) {
+            (&Some(ref info), Some(_)) if info.unicode_range.is_some() => {
+                self.unicode_range_template_cache
+                    .entry(cache_key)
+                    .or_insert_with(Vec::new)
+                    .push(info.clone());
+            },
+            (&None, Some(codepoint)) => {
+                self.missing_codepoint_cache
+                    .entry(cache_key)
+                    .or_insert_with(HashSet::new)
+                    .insert(codepoint);
+            },
+            _ => {
+                self.font_template_cache
+                    .insert(cache_key, template_info.clone());
+            },
+        }
+        template_info
     }
 
     /// Create a `Font` for use in layout calculations, from a `FontTemplateData` returned by the
@@ -184,20 +257,27 @@ impl<S: FontSource> FontContext<S> {
             FontVariantCaps::Normal => descriptor.pt_size,
         };
 
-        let handle = FontHandle::new_from_template(
+        let mut handle = FontHandle::new_from_template(
             &self.platform_handle,
             info.font_template,
             Some(actual_pt_size),
         )?;
-
-        let font_instance_key = self
-            .font_source
-            .get_font_instance(info.font_key, actual_pt_size);
+        handle.set_variations(&descriptor.variation_settings);
+
+        let variations = descriptor
+            .variation_settings
+            .iter()
+            .map(|&variation| variation.into())
+            .collect();
+        let font_instance_key =
+            self.font_source
+                .get_font_instance(info.font_key, actual_pt_size, variations);
         Ok(Font::new(
             handle,
             descriptor,
             actual_pt_size,
             font_instance_key,
+            info.unicode_range,
         ))
     }
 }
@@ -213,9 +293,10 @@ impl<S: FontSource> MallocSizeOf for FontContext<S> {
 struct FontCacheKey {
     font_descriptor: FontDescriptor,
     family_descriptor: FontFamilyDescriptor,
+    template_identifier: Atom,
 }
 
-#[derive(Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 struct FontTemplateCacheKey {
     template_descriptor: FontTemplateDescriptor,
     family_descriptor: FontFamilyDescriptor,
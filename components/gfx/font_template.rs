@@ -80,6 +80,31 @@ impl<'a> From<&'a FontStyleStruct> for FontTemplateDescriptor {
     }
 }
 
+/// The code points that a font template may be used for, as given by the `unicode-range`
+/// descriptor of the `@font-face` rule it was loaded from.
+///
+/// <https://drafts.csswg.org/css-fonts/#unicode-range-desc>
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct UnicodeRanges(Vec<(u32, u32)>);
+
+impl UnicodeRanges {
+    /// Creates a set of code points from inclusive `(start, end)` ranges.
+    pub fn new<I>(ranges: I) -> UnicodeRanges
+    where
+        I: IntoIterator<Item = (u32, u32)>,
+    {
+        UnicodeRanges(ranges.into_iter().collect())
+    }
+
+    /// Returns true if `codepoint` falls within one of the ranges.
+    pub fn contains(&self, codepoint: char) -> bool {
+        let codepoint = codepoint as u32;
+        self.0
+            .iter()
+            .any(|&(start, end)| start <= codepoint && codepoint <= end)
+    }
+}
+
 /// This describes all the information needed to create
 /// font instance handles. It contains a unique
 /// FontTemplateData structure that is platform specific.
@@ -90,6 +115,7 @@ pub struct FontTemplate {
     // GWTODO: Add code path to unset the strong_ref for web fonts!
     strong_ref: Option<Arc<FontTemplateData>>,
     is_valid: bool,
+    unicode_range: Option<UnicodeRanges>,
 }
 
 impl Debug for FontTemplate {
@@ -102,7 +128,11 @@ impl Debug for FontTemplate {
 /// is common, regardless of the number of instances of
 /// this font handle per thread.
 impl FontTemplate {
-    pub fn new(identifier: Atom, maybe_bytes: Option<Vec<u8>>) -> Result<FontTemplate, IoError> {
+    pub fn new(
+        identifier: Atom,
+        maybe_bytes: Option<Vec<u8>>,
+        unicode_range: Option<UnicodeRanges>,
+    ) -> Result<FontTemplate, IoError> {
         let maybe_data = match maybe_bytes {
             Some(_) => Some(FontTemplateData::new(identifier.clone(), maybe_bytes)?),
             None => None,
@@ -124,6 +154,7 @@ impl FontTemplate {
             weak_ref: maybe_weak_ref,
             strong_ref: maybe_strong_ref,
             is_valid: true,
+            unicode_range,
         })
     }
 
@@ -131,6 +162,20 @@ impl FontTemplate {
         &self.identifier
     }
 
+    /// The code points this template may be used for, if it was restricted by a `unicode-range`.
+    pub fn unicode_range(&self) -> Option<&UnicodeRanges> {
+        self.unicode_range.as_ref()
+    }
+
+    /// Returns true if this template may be used for `codepoint`, or for any code point at all
+    /// when `codepoint` is `None`.
+    pub fn covers(&self, codepoint: Option<char>) -> bool {
+        match (&self.unicode_range, codepoint) {
+            (Some(unicode_range), Some(codepoint)) => unicode_range.contains(codepoint),
+            _ => true,
+        }
+    }
+
     /// Get the descriptor. Returns `None` when instantiating the data fails.
     pub fn descriptor(
         &mut self,
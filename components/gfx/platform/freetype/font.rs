@@ -4,15 +4,15 @@
 
 use super::c_str_to_string;
 use crate::font::{FontHandleMethods, FontMetrics, FontTableMethods};
-use crate::font::{FontTableTag, FractionalPixel, GPOS, GSUB, KERN};
+use crate::font::{FontTableTag, FontVariation, FractionalPixel, GPOS, GSUB, KERN};
 use crate::platform::font_context::FontContextHandle;
 use crate::platform::font_template::FontTemplateData;
 use crate::text::glyph::GlyphId;
-use crate::text::util::fixed_to_float;
+use crate::text::util::{fixed_to_float, float_to_fixed};
 use app_units::Au;
 use freetype::freetype::FT_Sfnt_Tag;
 use freetype::freetype::{FT_Done_Face, FT_New_Face, FT_New_Memory_Face};
-use freetype::freetype::{FT_F26Dot6, FT_Face, FT_FaceRec};
+use freetype::freetype::{FT_Error, FT_F26Dot6, FT_Face, FT_FaceRec, FT_Fixed};
 use freetype::freetype::{FT_Get_Char_Index, FT_Get_Postscript_Name};
 use freetype::freetype::{FT_Get_Kerning, FT_Get_Sfnt_Table, FT_Load_Sfnt_Table};
 use freetype::freetype::{FT_GlyphSlot, FT_Library, FT_Long, FT_ULong};
@@ -23,9 +23,9 @@ use freetype::succeeded;
 use freetype::tt_os2::TT_OS2;
 use servo_atoms::Atom;
 use std::ffi::CString;
-use std::os::raw::{c_char, c_long};
+use std::os::raw::{c_char, c_long, c_void};
 use std::sync::Arc;
-use std::{mem, ptr};
+use std::{mem, ptr, slice};
 use style::computed_values::font_stretch::T as FontStretch;
 use style::computed_values::font_weight::T as FontWeight;
 use style::values::computed::font::FontStyle;
@@ -45,6 +45,37 @@ fn fixed_to_float_ft(f: i32) -> f64 {
     fixed_to_float(6, f)
 }
 
+// The multiple masters API, used for variable fonts, is not part of the
+// freetype bindings.
+#[repr(C)]
+struct FtVarAxis {
+    name: *mut c_char,
+    minimum: FT_Fixed,
+    default: FT_Fixed,
+    maximum: FT_Fixed,
+    tag: FT_ULong,
+    strid: FT_UInt,
+}
+
+#[repr(C)]
+struct FtMMVar {
+    num_axis: FT_UInt,
+    num_designs: FT_UInt,
+    num_namedstyles: FT_UInt,
+    axis: *mut FtVarAxis,
+    namedstyle: *mut c_void,
+}
+
+extern "C" {
+    fn FT_Get_MM_Var(face: FT_Face, amaster: *mut *mut FtMMVar) -> FT_Error;
+    fn FT_Done_MM_Var(library: FT_Library, amaster: *mut FtMMVar) -> FT_Error;
+    fn FT_Set_Var_Design_Coordinates(
+        face: FT_Face,
+        num_coords: FT_UInt,
+        coords: *mut FT_Fixed,
+    ) -> FT_Error;
+}
+
 #[derive(Debug)]
 pub struct FontTable {
     buffer: Vec<u8>,
@@ -153,6 +184,42 @@ impl FontHandleMethods for FontHandle {
         Ok(handle)
     }
 
+    fn set_variations(&mut self, variations: &[FontVariation]) {
+        if variations.is_empty() {
+            return;
+        }
+        unsafe {
+            let mut mm_var = ptr::null_mut();
+            if !succeeded(FT_Get_MM_Var(self.face, &mut mm_var)) || mm_var.is_null() {
+                return;
+            }
+
+            // FreeType wants a coordinate for every axis, in the order the font lists them.
+            let axes = slice::from_raw_parts((*mm_var).axis, (*mm_var).num_axis as usize);
+            let mut coords: Vec<FT_Fixed> = axes
+                .iter()
+                .map(|axis| {
+                    let coord = variations
+                        .iter()
+                        .find(|variation| variation.tag as FT_ULong == axis.tag)
+                        .map_or(axis.default, |variation| {
+                            float_to_fixed(16, variation.value.into_inner() as f64) as FT_Fixed
+                        });
+                    coord.max(axis.minimum).min(axis.maximum)
+                })
+                .collect();
+
+            if !succeeded(FT_Set_Var_Design_Coordinates(
+                self.face,
+                coords.len() as FT_UInt,
+                coords.as_mut_ptr(),
+            )) {
+                debug!("Failed to set variation coordinates");
+            }
+            FT_Done_MM_Var(self.handle.ctx.ctx, mm_var);
+        }
+    }
+
     fn template(&self) -> Arc<FontTemplateData> {
         self.font_data.clone()
     }
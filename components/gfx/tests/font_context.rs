@@ -8,7 +8,7 @@ use gfx::font::{
 };
 use gfx::font_cache_thread::{FontTemplateInfo, FontTemplates};
 use gfx::font_context::{FontContext, FontContextHandle, FontSource};
-use gfx::font_template::FontTemplateDescriptor;
+use gfx::font_template::{FontTemplateDescriptor, UnicodeRanges};
 use servo_arc::Arc;
 use servo_atoms::Atom;
 use std::cell::Cell;
@@ -19,11 +19,12 @@ use std::path::PathBuf;
 use std::rc::Rc;
 use style::properties::longhands::font_variant_caps::computed_value::T as FontVariantCaps;
 use style::properties::style_structs::Font as FontStyleStruct;
+use style::properties::ComputedValues;
 use style::values::computed::font::{
     FamilyName, FontFamily, FontFamilyList, FontFamilyNameSyntax, FontSize,
 };
 use style::values::computed::font::{FontStretch, FontWeight, SingleFontFamily};
-use style::values::generics::font::FontStyle;
+use style::values::generics::font::{FontSettings, FontStyle, FontTag, VariationValue};
 
 struct TestFontSource {
     handle: FontContextHandle,
@@ -34,17 +35,37 @@ struct TestFontSource {
 impl TestFontSource {
     fn new() -> TestFontSource {
         let mut csstest_ascii = FontTemplates::new();
-        Self::add_face(&mut csstest_ascii, "csstest-ascii", None);
+        Self::add_face(&mut csstest_ascii, "csstest-ascii", None, None);
 
         let mut csstest_basic = FontTemplates::new();
-        Self::add_face(&mut csstest_basic, "csstest-basic-regular", None);
+        Self::add_face(&mut csstest_basic, "csstest-basic-regular", None, None);
+
+        let mut csstest_segmented = FontTemplates::new();
+        Self::add_face(
+            &mut csstest_segmented,
+            "csstest-ascii",
+            Some("segmented-ascii"),
+            Some(UnicodeRanges::new(vec![(0x0, 0x7F)])),
+        );
+        Self::add_face(
+            &mut csstest_segmented,
+            "csstest-basic-regular",
+            Some("segmented-latin1"),
+            Some(UnicodeRanges::new(vec![(0x80, 0xFF)])),
+        );
 
         let mut fallback = FontTemplates::new();
-        Self::add_face(&mut fallback, "csstest-basic-regular", Some("fallback"));
+        Self::add_face(
+            &mut fallback,
+            "csstest-basic-regular",
+            Some("fallback"),
+            None,
+        );
 
         let mut families = HashMap::new();
         families.insert("CSSTest ASCII".to_owned(), csstest_ascii);
         families.insert("CSSTest Basic".to_owned(), csstest_basic);
+        families.insert("CSSTest Segmented".to_owned(), csstest_segmented);
         families.insert(fallback_font_families(None)[0].to_owned(), fallback);
 
         TestFontSource {
@@ -54,7 +75,12 @@ impl TestFontSource {
         }
     }
 
-    fn add_face(family: &mut FontTemplates, name: &str, identifier: Option<&str>) {
+    fn add_face(
+        family: &mut FontTemplates,
+        name: &str,
+        identifier: Option<&str>,
+        unicode_range: Option<UnicodeRanges>,
+    ) {
         let mut path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "support", "CSSTest"]
             .iter()
             .collect();
@@ -63,7 +89,11 @@ impl TestFontSource {
         let file = File::open(path).unwrap();
         let identifier = Atom::from(identifier.unwrap_or(name));
 
-        family.add_template(identifier, Some(file.bytes().map(|b| b.unwrap()).collect()))
+        family.add_template(
+            identifier,
+            Some(file.bytes().map(|b| b.unwrap()).collect()),
+            unicode_range,
+        )
     }
 }
 
@@ -72,6 +102,7 @@ impl FontSource for TestFontSource {
         &mut self,
         _key: webrender_api::FontKey,
         _size: Au,
+        _variations: Vec<webrender_api::FontVariation>,
     ) -> webrender_api::FontInstanceKey {
         webrender_api::FontInstanceKey(webrender_api::IdNamespace(0), 0)
     }
@@ -80,16 +111,21 @@ impl FontSource for TestFontSource {
         &mut self,
         template_descriptor: FontTemplateDescriptor,
         family_descriptor: FontFamilyDescriptor,
+        codepoint: Option<char>,
     ) -> Option<FontTemplateInfo> {
         let handle = &self.handle;
 
         self.find_font_count.set(self.find_font_count.get() + 1);
         self.families
             .get_mut(family_descriptor.name())
-            .and_then(|family| family.find_font_for_style(&template_descriptor, handle))
-            .map(|template| FontTemplateInfo {
-                font_template: template,
-                font_key: webrender_api::FontKey(webrender_api::IdNamespace(0), 0),
+            .and_then(|family| {
+                family
+                    .find_font_for_style(&template_descriptor, handle, codepoint)
+                    .map(|template| FontTemplateInfo {
+                        unicode_range: family.unicode_range(&template.identifier),
+                        font_template: template,
+                        font_key: webrender_api::FontKey(webrender_api::IdNamespace(0), 0),
+                    })
             })
     }
 }
@@ -103,6 +139,7 @@ fn style() -> FontStyleStruct {
         font_size: FontSize::medium(),
         font_stretch: FontStretch::hundred(),
         hash: 0,
+        ..ComputedValues::initial_values().get_font().clone()
     };
     style.compute_font_hash();
     style
@@ -189,6 +226,47 @@ fn test_font_group_find_by_codepoint() {
     assert_eq!(count.get(), 2, "both fonts should now have been loaded");
 }
 
+#[test]
+fn test_font_group_find_by_codepoint_with_unicode_range() {
+    let source = TestFontSource::new();
+    let count = source.find_font_count.clone();
+    let mut context = FontContext::new(source);
+
+    let mut style = style();
+    style.set_font_family(font_family(vec!["CSSTest Segmented"]));
+
+    let group = context.font_group(Arc::new(style));
+
+    let font = group
+        .borrow_mut()
+        .find_by_codepoint(&mut context, 'a')
+        .unwrap();
+    assert_eq!(&*font.borrow().identifier(), "segmented-ascii");
+    assert_eq!(count.get(), 1);
+
+    let font = group
+        .borrow_mut()
+        .find_by_codepoint(&mut context, 'á')
+        .unwrap();
+    assert_eq!(
+        &*font.borrow().identifier(),
+        "segmented-latin1",
+        "the font whose unicode-range includes the code point should be used"
+    );
+    assert_eq!(count.get(), 2);
+
+    let font = group
+        .borrow_mut()
+        .find_by_codepoint(&mut context, 'b')
+        .unwrap();
+    assert_eq!(&*font.borrow().identifier(), "segmented-ascii");
+    assert_eq!(
+        count.get(),
+        2,
+        "fonts already loaded for the family should be reused"
+    );
+}
+
 #[test]
 fn test_font_fallback() {
     let source = TestFontSource::new();
@@ -234,15 +312,20 @@ fn test_font_template_is_cached() {
         },
         variant: FontVariantCaps::Normal,
         pt_size: Au(10),
+        variation_settings: vec![],
     };
 
     let family_descriptor =
         FontFamilyDescriptor::new(FontFamilyName::from("CSSTest Basic"), FontSearchScope::Any);
 
-    let font1 = context.font(&font_descriptor, &family_descriptor).unwrap();
+    let font1 = context
+        .font(&font_descriptor, &family_descriptor, None)
+        .unwrap();
 
     font_descriptor.pt_size = Au(20);
-    let font2 = context.font(&font_descriptor, &family_descriptor).unwrap();
+    let font2 = context
+        .font(&font_descriptor, &family_descriptor, None)
+        .unwrap();
 
     assert_ne!(
         font1.borrow().actual_pt_size,
@@ -256,3 +339,91 @@ fn test_font_template_is_cached() {
         "we should only have fetched the template data from the cache thread once"
     );
 }
+
+#[test]
+fn test_font_descriptor_variation_settings() {
+    let wght = u32::from_be_bytes(*b"wght");
+    let wdth = u32::from_be_bytes(*b"wdth");
+    let mut style = style();
+    style.font_variation_settings = FontSettings(
+        vec![
+            VariationValue {
+                tag: FontTag(wght),
+                value: 300.,
+            },
+            VariationValue {
+                tag: FontTag(wdth),
+                value: 80.,
+            },
+            VariationValue {
+                tag: FontTag(wght),
+                value: 700.,
+            },
+        ]
+        .into_boxed_slice(),
+    );
+
+    let descriptor = FontDescriptor::from(&style);
+    let variations: Vec<(u32, f32)> = descriptor
+        .variation_settings
+        .iter()
+        .map(|variation| (variation.tag, variation.value.into_inner()))
+        .collect();
+    assert_eq!(
+        variations,
+        vec![(wdth, 80.), (wght, 700.)],
+        "the last value given for an axis should win"
+    );
+}
+
+#[test]
+fn test_unicode_range_font_template_is_cached() {
+    let source = TestFontSource::new();
+    let count = source.find_font_count.clone();
+    let mut context = FontContext::new(source);
+
+    let font_descriptor = FontDescriptor {
+        template_descriptor: FontTemplateDescriptor {
+            weight: FontWeight::normal(),
+            stretch: FontStretch::hundred(),
+            style: FontStyle::Normal,
+        },
+        variant: FontVariantCaps::Normal,
+        pt_size: Au(10),
+        variation_settings: vec![],
+    };
+
+    let family_descriptor = FontFamilyDescriptor::new(
+        FontFamilyName::from("CSSTest Segmented"),
+        FontSearchScope::Any,
+    );
+
+    for codepoint in "abc".chars() {
+        let font = context
+            .font(&font_descriptor, &family_descriptor, Some(codepoint))
+            .unwrap();
+        assert_eq!(&*font.borrow().identifier(), "segmented-ascii");
+    }
+    assert_eq!(
+        count.get(),
+        1,
+        "the template should be reused for every code point in its unicode-range"
+    );
+
+    let font = context
+        .font(&font_descriptor, &family_descriptor, Some('á'))
+        .unwrap();
+    assert_eq!(&*font.borrow().identifier(), "segmented-latin1");
+    assert_eq!(count.get(), 2);
+
+    for _ in 0..2 {
+        assert!(context
+            .font(&font_descriptor, &family_descriptor, Some('ā'))
+            .is_none());
+    }
+    assert_eq!(
+        count.get(),
+        3,
+        "a code point without a template should only be looked up once"
+    );
+}
@@ -34,6 +34,7 @@ fn test_font_template_descriptor() {
         let mut template = FontTemplate::new(
             Atom::from(filename),
             Some(file.bytes().map(|b| b.unwrap()).collect()),
+            None,
         )
         .unwrap();
 
@@ -78,3 +79,15 @@ fn test_font_template_descriptor() {
         }
     );
 }
+
+#[test]
+fn test_unicode_ranges_contains() {
+    use gfx::font_template::UnicodeRanges;
+
+    let ranges = UnicodeRanges::new(vec![(0x0, 0x7F), (0x370, 0x3FF)]);
+    assert!(ranges.contains('a'));
+    assert!(ranges.contains('\u{7F}'));
+    assert!(ranges.contains('λ'));
+    assert!(!ranges.contains('á'));
+    assert!(!ranges.contains('\u{400}'));
+}
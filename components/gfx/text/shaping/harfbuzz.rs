@@ -28,6 +28,7 @@ use harfbuzz_sys::hb_font_funcs_set_nominal_glyph_func;
 use harfbuzz_sys::hb_font_set_funcs;
 use harfbuzz_sys::hb_font_set_ppem;
 use harfbuzz_sys::hb_font_set_scale;
+use harfbuzz_sys::hb_font_set_variations;
 use harfbuzz_sys::hb_glyph_info_t;
 use harfbuzz_sys::hb_glyph_position_t;
 use harfbuzz_sys::{hb_blob_create, hb_face_create_for_tables};
@@ -36,7 +37,7 @@ use harfbuzz_sys::{hb_buffer_get_glyph_infos, hb_shape};
 use harfbuzz_sys::{hb_buffer_set_direction, hb_buffer_set_script};
 use harfbuzz_sys::{hb_buffer_t, hb_codepoint_t, hb_font_funcs_t};
 use harfbuzz_sys::{hb_face_t, hb_font_t};
-use harfbuzz_sys::{hb_position_t, hb_tag_t};
+use harfbuzz_sys::{hb_position_t, hb_tag_t, hb_variation_t};
 use harfbuzz_sys::{HB_DIRECTION_LTR, HB_DIRECTION_RTL, HB_MEMORY_MODE_READONLY};
 use std::os::raw::{c_char, c_int, c_uint, c_void};
 use std::{char, cmp, ptr};
@@ -166,6 +167,21 @@ impl Shaper {
                 Shaper::float_to_fixed(pt_size) as c_int,
             );
 
+            // Select the coordinates of variable fonts, so that feature variations in the
+            // GSUB and GPOS tables apply.
+            let variations: Vec<hb_variation_t> = (*font)
+                .descriptor
+                .variation_settings
+                .iter()
+                .map(|variation| hb_variation_t {
+                    tag: variation.tag,
+                    value: variation.value.into_inner(),
+                })
+                .collect();
+            if !variations.is_empty() {
+                hb_font_set_variations(hb_font, variations.as_ptr(), variations.len() as c_uint);
+            }
+
             // configure static function callbacks.
             hb_font_set_funcs(
                 hb_font,
@@ -216,7 +232,17 @@ impl ShaperMethods for Shaper {
                 text.len() as c_int,
             );
 
-            let mut features = Vec::new();
+            // The shaping flags come last so that they override the styled features.
+            let mut features: Vec<hb_feature_t> = options
+                .features
+                .iter()
+                .map(|feature| hb_feature_t {
+                    tag: feature.tag,
+                    value: feature.value,
+                    start: 0,
+                    end: hb_buffer_get_length(hb_buffer),
+                })
+                .collect();
             if options
                 .flags
                 .contains(ShapingFlags::IGNORE_LIGATURES_SHAPING_FLAG)
@@ -33,6 +33,7 @@ pub fn update_animation_state<E>(
     new_animations_receiver: &Receiver<Animation>,
     pipeline_id: PipelineId,
     timer: &Timer,
+    animating_images: bool,
 ) where
     E: TElement,
 {
@@ -146,7 +147,8 @@ pub fn update_animation_state<E>(
             .push(new_running_animation)
     }
 
-    let animation_state = if running_animations.is_empty() {
+    // Animated images need ticks too.
+    let animation_state = if running_animations.is_empty() && !animating_images {
         AnimationState::NoAnimationsPresent
     } else {
         AnimationState::AnimationsPresent
@@ -657,7 +657,8 @@ impl BlockFlow {
             } else {
                 BlockType::AbsoluteNonReplaced
             }
-        } else if self.is_inline_flex_item() {
+        } else if self.is_inline_flex_item() || self.is_grid_item() {
+            // The inline sizes of grid items are also set by their parent.
             BlockType::InlineFlexItem
         } else if self.base.flags.is_float() {
             if self.fragment.is_replaced() {
@@ -1682,7 +1683,7 @@ impl BlockFlow {
     /// Determines the type of formatting context this is. See the definition of
     /// `FormattingContextType`.
     pub fn formatting_context_type(&self) -> FormattingContextType {
-        if self.is_inline_flex_item() || self.is_block_flex_item() {
+        if self.is_inline_flex_item() || self.is_block_flex_item() || self.is_grid_item() {
             return FormattingContextType::Other;
         }
         let style = self.fragment.style();
@@ -1695,7 +1696,8 @@ impl BlockFlow {
             Display::TableRowGroup |
             Display::Table |
             Display::InlineBlock |
-            Display::Flex => FormattingContextType::Other,
+            Display::Flex |
+            Display::Grid => FormattingContextType::Other,
             _ if style.get_box().overflow_x != StyleOverflow::Visible ||
                 style.get_box().overflow_y != StyleOverflow::Visible ||
                 style.is_multicol() =>
@@ -1830,7 +1832,8 @@ impl BlockFlow {
 
     fn is_inline_block_or_inline_flex(&self) -> bool {
         self.fragment.style().get_box().display == Display::InlineBlock ||
-            self.fragment.style().get_box().display == Display::InlineFlex
+            self.fragment.style().get_box().display == Display::InlineFlex ||
+            self.fragment.style().get_box().display == Display::InlineGrid
     }
 
     /// Computes the content portion (only) of the intrinsic inline sizes of this flow. This is
@@ -2078,6 +2081,10 @@ impl BlockFlow {
             .contains(FragmentFlags::IS_BLOCK_FLEX_ITEM)
     }
 
+    pub fn is_grid_item(&self) -> bool {
+        self.fragment.flags.contains(FragmentFlags::IS_GRID_ITEM)
+    }
+
     pub fn mark_scrolling_overflow(&mut self, has_scrolling_overflow: bool) {
         if has_scrolling_overflow {
             self.flags.insert(BlockFlowFlags::HAS_SCROLLING_OVERFLOW);
@@ -5,12 +5,15 @@
 //! Data needed by the layout thread.
 
 use crate::display_list::items::{OpaqueNode, WebRenderImageInfo};
+use crate::display_list::SvgImageCache;
+use crate::image_animation::AnimatedImages;
 use crate::opaque_node::OpaqueNodeMethods;
 use fnv::FnvHasher;
 use gfx::font_cache_thread::FontCacheThread;
 use gfx::font_context::FontContext;
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
 use msg::constellation_msg::PipelineId;
+use net_traits::image::base::Image;
 use net_traits::image_cache::{CanRequestImages, ImageCache, ImageState};
 use net_traits::image_cache::{ImageOrMetadataAvailable, UsePlaceholder};
 use parking_lot::RwLock;
@@ -76,6 +79,12 @@ pub struct LayoutContext<'a> {
         >,
     >,
 
+    /// The rasterized inline `<svg>` elements of the document.
+    pub svg_image_cache: Arc<Mutex<SvgImageCache>>,
+
+    /// The animated images of the document.
+    pub animated_images: Arc<Mutex<AnimatedImages>>,
+
     /// Paint worklets
     pub registered_painters: &'a dyn RegisteredPainters,
 
@@ -161,6 +170,14 @@ impl<'a> LayoutContext<'a> {
         }
     }
 
+    /// Keeps time for the frames of the given image if it's animated.
+    pub fn register_animated_image(&self, image: &Arc<Image>) {
+        if image.is_animated() {
+            let now = self.style_context.timer.seconds();
+            self.animated_images.lock().unwrap().register(image, now);
+        }
+    }
+
     pub fn get_webrender_image_for_url(
         &self,
         node: OpaqueNode,
@@ -177,6 +194,7 @@ impl<'a> LayoutContext<'a> {
 
         match self.get_or_request_image_or_meta(node, url.clone(), use_placeholder) {
             Some(ImageOrMetadataAvailable::ImageAvailable(image, _)) => {
+                self.register_animated_image(&image);
                 let image_info = WebRenderImageInfo::from_image(&*image);
                 if image_info.key.is_none() {
                     Some(image_info)
@@ -24,8 +24,9 @@ use crate::display_list::items::{TextOrientation, WebRenderImageInfo};
 use crate::display_list::ToLayout;
 use crate::flow::{BaseFlow, Flow, FlowFlags};
 use crate::flow_ref::FlowRef;
+use crate::fragment::ScannedTextFragmentInfo;
 use crate::fragment::SpecificFragmentInfo;
-use crate::fragment::{CanvasFragmentSource, CoordinateSystem, Fragment, ScannedTextFragmentInfo};
+use crate::fragment::{CanvasFragmentSource, CoordinateSystem, Fragment, FragmentFlags};
 use crate::inline::InlineFragmentNodeFlags;
 use crate::model::MaybeAuto;
 use crate::table_cell::CollapsedBordersForCell;
@@ -1784,8 +1785,50 @@ impl Fragment {
             SpecificFragmentInfo::InlineBlock(_) |
             SpecificFragmentInfo::InlineAbsoluteHypothetical(_) |
             SpecificFragmentInfo::InlineAbsolute(_) |
-            SpecificFragmentInfo::TruncatedFragment(_) |
-            SpecificFragmentInfo::Svg(_) => {
+            SpecificFragmentInfo::TruncatedFragment(_) => {
+                if opts::get().show_debug_fragment_borders {
+                    self.build_debug_borders_around_fragment(
+                        state,
+                        stacking_relative_border_box,
+                        clip,
+                    );
+                }
+            },
+            SpecificFragmentInfo::Svg(ref svg_fragment_info) => {
+                if !stacking_relative_content_box.is_empty() {
+                    let size = stacking_relative_content_box.size;
+                    let device_pixel_ratio =
+                        state.layout_context.style_context.device_pixel_ratio();
+                    let image_key = state
+                        .layout_context
+                        .svg_image_cache
+                        .lock()
+                        .unwrap()
+                        .get_or_rasterize(
+                            &svg_fragment_info.tree,
+                            svg_fragment_info.tree_hash,
+                            Size2D::new(size.width.to_f32_px(), size.height.to_f32_px()),
+                            device_pixel_ratio.get(),
+                            self.style.get_inherited_text().color,
+                        );
+                    if let Some(image_key) = image_key {
+                        let base = create_base_display_item(state);
+                        state.add_image_item(
+                            base,
+                            webrender_api::ImageDisplayItem {
+                                bounds: stacking_relative_content_box.to_layout(),
+                                common: items::empty_common_item_properties(),
+                                image_key,
+                                stretch_size: stacking_relative_content_box.size.to_layout(),
+                                tile_spacing: LayoutSize::zero(),
+                                image_rendering: ImageRendering::Auto,
+                                alpha_type: webrender_api::AlphaType::PremultipliedAlpha,
+                                color: webrender_api::ColorF::WHITE,
+                            },
+                        );
+                    }
+                }
+
                 if opts::get().show_debug_fragment_borders {
                     self.build_debug_borders_around_fragment(
                         state,
@@ -2826,6 +2869,12 @@ impl BlockFlow {
             return Some(StackingContextType::PseudoFloat);
         }
 
+        // Grid items paint atomically, like inline blocks.
+        // See https://drafts.csswg.org/css-grid/#z-order
+        if self.fragment.flags.contains(FragmentFlags::IS_GRID_ITEM) {
+            return Some(StackingContextType::PseudoFloat);
+        }
+
         None
     }
 }
@@ -767,9 +767,10 @@ pub struct WebRenderImageInfo {
 impl WebRenderImageInfo {
     #[inline]
     pub fn from_image(image: &Image) -> WebRenderImageInfo {
+        let natural_size = image.metadata();
         WebRenderImageInfo {
-            width: image.width,
-            height: image.height,
+            width: natural_size.width,
+            height: natural_size.height,
             key: image.id,
         }
     }
@@ -8,6 +8,7 @@ pub use self::builder::IndexableText;
 pub use self::builder::StackingContextCollectionFlags;
 pub use self::builder::StackingContextCollectionState;
 pub use self::conversions::ToLayout;
+pub use self::svg::SvgImageCache;
 
 mod background;
 mod border;
@@ -15,4 +16,5 @@ mod builder;
 mod conversions;
 mod gradient;
 pub mod items;
+mod svg;
 mod webrender_helpers;
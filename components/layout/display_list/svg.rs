@@ -0,0 +1,117 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Rasterization of inline `<svg>` elements into WebRender images.
+
+use euclid::default::Size2D;
+use fnv::FnvHashMap;
+use style::values::RGBA;
+use webrender_api::units::DeviceIntSize;
+use webrender_api::{ImageData, ImageDescriptor, ImageFormat, ImageKey, RenderApi, Transaction};
+
+/// Identifies one rasterization of an SVG subtree.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+struct SvgImageId {
+    tree_hash: u64,
+    width: u32,
+    height: u32,
+    current_color: (u8, u8, u8, u8),
+}
+
+struct SvgImage {
+    /// The WebRender image, or `None` if the rasterization was empty.
+    key: Option<ImageKey>,
+    /// Whether a display list built since the last eviction used this image.
+    used: bool,
+}
+
+/// The WebRender images of the inline `<svg>` elements of a document, kept
+/// across reflows so that an unchanged element isn't rasterized again.
+pub struct SvgImageCache {
+    webrender_api: RenderApi,
+    images: FnvHashMap<SvgImageId, SvgImage>,
+    /// Images that the last display list didn't use, to be deleted once it
+    /// has been sent to WebRender.
+    unused_keys: Vec<ImageKey>,
+}
+
+impl SvgImageCache {
+    pub fn new(webrender_api: RenderApi) -> SvgImageCache {
+        SvgImageCache {
+            webrender_api,
+            images: FnvHashMap::default(),
+            unused_keys: vec![],
+        }
+    }
+
+    /// Returns the image of `tree` drawn into a viewport of `size` CSS pixels
+    /// at the given device pixel ratio, rasterizing it if needed.
+    pub fn get_or_rasterize(
+        &mut self,
+        tree: &servo_svg::Element,
+        tree_hash: u64,
+        size: Size2D<f32>,
+        device_pixel_ratio: f32,
+        current_color: RGBA,
+    ) -> Option<ImageKey> {
+        let id = SvgImageId {
+            tree_hash,
+            width: (size.width * device_pixel_ratio).ceil() as u32,
+            height: (size.height * device_pixel_ratio).ceil() as u32,
+            current_color: (
+                current_color.red,
+                current_color.green,
+                current_color.blue,
+                current_color.alpha,
+            ),
+        };
+        if let Some(image) = self.images.get_mut(&id) {
+            image.used = true;
+            return image.key;
+        }
+
+        let raster = servo_svg::rasterize(tree, size, device_pixel_ratio, current_color);
+        let key = if raster.width == 0 || raster.height == 0 {
+            None
+        } else {
+            let descriptor = ImageDescriptor {
+                size: DeviceIntSize::new(raster.width as i32, raster.height as i32),
+                stride: None,
+                format: ImageFormat::BGRA8,
+                offset: 0,
+                is_opaque: false,
+                allow_mipmaps: false,
+            };
+            let key = self.webrender_api.generate_image_key();
+            let mut txn = Transaction::new();
+            txn.add_image(key, descriptor, ImageData::new(raster.bytes), None);
+            self.webrender_api.update_resources(txn.resource_updates);
+            Some(key)
+        };
+        self.images.insert(id, SvgImage { key, used: true });
+        key
+    }
+
+    /// Forgets the images that weren't used by the display list that was just
+    /// built. Their keys are released by `delete_unused_images`.
+    pub fn evict_unused_images(&mut self) {
+        let unused_keys = &mut self.unused_keys;
+        self.images.retain(|_, image| {
+            if !image.used {
+                unused_keys.extend(image.key);
+                return false;
+            }
+            image.used = false;
+            true
+        });
+    }
+
+    /// Deletes the evicted images as part of the transaction that replaces the
+    /// display list which last used them.
+    pub fn delete_unused_images(&mut self, txn: &mut Transaction) {
+        for key in self.unused_keys.drain(..) {
+            txn.delete_image(key);
+        }
+    }
+}
@@ -38,6 +38,7 @@ impl Serialize for FlowList {
                 FlowClass::TableRow => to_value(f.as_table_row()).unwrap(),
                 FlowClass::TableCell => to_value(f.as_table_cell()).unwrap(),
                 FlowClass::Flex => to_value(f.as_flex()).unwrap(),
+                FlowClass::Grid => to_value(f.as_grid()).unwrap(),
                 FlowClass::ListItem |
                 FlowClass::TableColGroup |
                 FlowClass::TableCaption |
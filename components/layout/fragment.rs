@@ -39,7 +39,9 @@ use serde::ser::{Serialize, SerializeStruct, Serializer};
 use servo_url::ServoUrl;
 use std::borrow::ToOwned;
 use std::cmp::{max, min, Ordering};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::LinkedList;
+use std::hash::{Hash, Hasher};
 use std::sync::{Arc, Mutex};
 use std::{f32, fmt};
 use style::computed_values::border_collapse::T as BorderCollapse;
@@ -384,13 +386,21 @@ impl MediaFragmentInfo {
 pub struct SvgFragmentInfo {
     pub dom_width: Au,
     pub dom_height: Au,
+    /// The SVG subtree to rasterize, shared between the fragments of this element.
+    pub tree: Arc<servo_svg::Element>,
+    /// A hash of `tree`, used to find its rasterizations in the SVG image cache.
+    pub tree_hash: u64,
 }
 
 impl SvgFragmentInfo {
     pub fn new(data: SVGSVGData) -> SvgFragmentInfo {
+        let mut hasher = DefaultHasher::new();
+        data.tree.hash(&mut hasher);
         SvgFragmentInfo {
             dom_width: Au::from_px(data.width as i32),
             dom_height: Au::from_px(data.height as i32),
+            tree: Arc::new(data.tree),
+            tree_hash: hasher.finish(),
         }
     }
 }
@@ -446,12 +456,15 @@ impl ImageFragmentInfo {
 
         let (image, metadata) = match image_or_metadata {
             Some(ImageOrMetadata::Image(i)) => {
-                let height = (i.height as f64 / current_pixel_density) as u32;
-                let width = (i.width as f64 / current_pixel_density) as u32;
+                layout_context.register_animated_image(&i);
+                let natural_size = i.metadata();
+                let height = (natural_size.height as f64 / current_pixel_density) as u32;
+                let width = (natural_size.width as f64 / current_pixel_density) as u32;
                 (
                     Some(Arc::new(Image {
                         height: height,
                         width: width,
+                        density: 1.,
                         ..(*i).clone()
                     })),
                     Some(ImageMetadata {
@@ -2746,6 +2759,14 @@ impl Fragment {
             return true;
         }
 
+        // Grid items with a z-index establish stacking contexts even when they are statically
+        // positioned. See https://drafts.csswg.org/css-grid/#z-order
+        if self.flags.contains(FragmentFlags::IS_GRID_ITEM) &&
+            !self.style().get_position().z_index.is_auto()
+        {
+            return true;
+        }
+
         // Statically positioned fragments don't establish stacking contexts if the previous
         // conditions are not fulfilled. Furthermore, z-index doesn't apply to statically
         // positioned fragments.
@@ -2772,6 +2793,10 @@ impl Fragment {
             return self.style().get_position().z_index.integer_or(0);
         }
 
+        if self.flags.contains(FragmentFlags::IS_GRID_ITEM) {
+            return self.style().get_position().z_index.integer_or(0);
+        }
+
         match self.style().get_box().display {
             Display::Flex => self.style().get_position().z_index.integer_or(0),
             _ => 0,
@@ -3403,6 +3428,8 @@ bitflags! {
         const IS_BLOCK_FLEX_ITEM = 0b0000_0010;
         /// Whether this fragment represents the generated text from a text-overflow clip.
         const IS_ELLIPSIS = 0b0000_0100;
+        /// Whether this fragment represents a child in a grid container.
+        const IS_GRID_ITEM = 0b0000_1000;
     }
 }
 
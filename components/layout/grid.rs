@@ -0,0 +1,1543 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Layout for elements with a CSS `display` property of `grid`.
+//!
+//! This implements the parts of <https://drafts.csswg.org/css-grid/> needed for common
+//! layouts: explicit tracks from `grid-template-*`, implicit tracks from `grid-auto-*`,
+//! line-based and named-area placement, the auto-placement algorithm, and a simplified
+//! track sizing algorithm supporting fixed, intrinsic, `minmax()` and flexible (`fr`) tracks.
+//!
+//! Known limitations:
+//!   * `auto-fill` and `auto-fit` repetitions repeat their tracks exactly once.
+//!   * Lines before the start of the explicit grid don't create implicit tracks; items placed
+//!     there are shifted to the first track instead.
+//!   * Vertical writing modes, subgrids and baseline alignment are not supported.
+
+use crate::block::{AbsoluteAssignBSizesTraversal, BlockFlow};
+use crate::context::LayoutContext;
+use crate::display_list::{
+    BorderPaintingMode, DisplayListBuildState, StackingContextCollectionState,
+};
+use crate::floats::FloatKind;
+use crate::flow::{Flow, FlowClass, FlowFlags, GetBaseFlow, ImmutableFlowUtils, OpaqueFlow};
+use crate::fragment::{Fragment, FragmentBorderBoxIterator, Overflow};
+use crate::layout_debug;
+use crate::model::{self, AdjoiningMargins, CollapsibleMargins, IntrinsicISizes, MaybeAuto};
+use crate::traversal::PreorderFlowTraversal;
+use app_units::{Au, MAX_AU};
+use euclid::default::Point2D;
+use servo_atoms::Atom;
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::ops::Range;
+use style::computed_values::align_self::T as AlignSelf;
+use style::logical_geometry::{Direction, LogicalSize};
+use style::properties::ComputedValues;
+use style::servo::restyle_damage::ServoRestyleDamage;
+use style::values::computed::length::NonNegativeLengthPercentageOrNormal;
+use style::values::computed::position::GridTemplateAreas;
+use style::values::computed::{GridLine, Integer, LengthPercentage, Size};
+use style::values::generics::grid::{GridTemplateComponent, RepeatCount};
+use style::values::generics::grid::{TrackBreadth, TrackListValue, TrackSize};
+use style::values::specified::position::AutoFlow;
+use style::values::CustomIdent;
+
+/// The sizing functions and line names of one axis of a grid container.
+struct TrackDefinitions {
+    /// The sizing functions of the tracks given by `grid-template-rows` or
+    /// `grid-template-columns`, with repetitions expanded.
+    explicit: Vec<TrackSize<LengthPercentage>>,
+    /// The sizing functions given by `grid-auto-rows` or `grid-auto-columns`, used in turn by
+    /// every track after the explicit ones.
+    implicit: Vec<TrackSize<LengthPercentage>>,
+    /// The names of each line of the explicit grid, including the ones implicitly given by
+    /// `grid-template-areas`.
+    line_names: Vec<Vec<Atom>>,
+}
+
+impl TrackDefinitions {
+    /// Collects the track definitions of the columns (for `Direction::Inline`) or the rows (for
+    /// `Direction::Block`) of a grid container.
+    fn new(style: &ComputedValues, direction: Direction) -> TrackDefinitions {
+        let position = style.get_position();
+        let (template, implicit) = match direction {
+            Direction::Inline => (&position.grid_template_columns, &position.grid_auto_columns),
+            Direction::Block => (&position.grid_template_rows, &position.grid_auto_rows),
+        };
+
+        let mut explicit = vec![];
+        let mut line_names = vec![vec![]];
+        if let GridTemplateComponent::TrackList(ref list) = *template {
+            for (index, value) in list.values.iter().enumerate() {
+                add_line_names(&mut line_names, list.line_names.get(index).map(|n| &**n));
+                match *value {
+                    TrackListValue::TrackSize(ref size) => {
+                        explicit.push(size.clone());
+                        line_names.push(vec![]);
+                    },
+                    TrackListValue::TrackRepeat(ref repeat) => {
+                        let count = match repeat.count {
+                            RepeatCount::Number(count) => max(count, 1) as usize,
+                            RepeatCount::AutoFill | RepeatCount::AutoFit => 1,
+                        };
+                        for _ in 0..count {
+                            for (index, size) in repeat.track_sizes.iter().enumerate() {
+                                add_line_names(
+                                    &mut line_names,
+                                    repeat.line_names.get(index).map(|n| &**n),
+                                );
+                                explicit.push(size.clone());
+                                line_names.push(vec![]);
+                            }
+                            add_line_names(
+                                &mut line_names,
+                                repeat
+                                    .line_names
+                                    .get(repeat.track_sizes.len())
+                                    .map(|n| &**n),
+                            );
+                        }
+                    },
+                }
+            }
+            add_line_names(
+                &mut line_names,
+                list.line_names.get(list.values.len()).map(|n| &**n),
+            );
+        }
+
+        // Named areas define the `<name>-start` and `<name>-end` lines, and can make the explicit
+        // grid larger than the template.
+        if let GridTemplateAreas::Areas(ref areas) = position.grid_template_areas {
+            let track_count = match direction {
+                Direction::Inline => areas.0.width as usize,
+                Direction::Block => areas.0.strings.len(),
+            };
+            if line_names.len() < track_count + 1 {
+                line_names.resize(track_count + 1, vec![]);
+            }
+            for area in areas.0.areas.iter() {
+                let lines = match direction {
+                    Direction::Inline => &area.columns,
+                    Direction::Block => &area.rows,
+                };
+                line_names[lines.start as usize - 1]
+                    .push(Atom::from(format!("{}-start", area.name)));
+                line_names[lines.end as usize - 1].push(Atom::from(format!("{}-end", area.name)));
+            }
+        }
+
+        TrackDefinitions {
+            explicit: explicit,
+            implicit: implicit.0.iter().cloned().collect(),
+            line_names: line_names,
+        }
+    }
+
+    /// The number of tracks of the explicit grid.
+    fn explicit_track_count(&self) -> usize {
+        self.line_names.len() - 1
+    }
+
+    /// Returns the sizing function of the track at `index`.
+    fn sizing_function(&self, index: usize) -> TrackSize<LengthPercentage> {
+        if let Some(size) = self.explicit.get(index) {
+            return size.clone();
+        }
+        if self.implicit.is_empty() {
+            return TrackSize::default();
+        }
+        self.implicit[(index - self.explicit.len()) % self.implicit.len()].clone()
+    }
+
+    /// Returns the index of the `nth` line named `name`, counting backwards from the end of the
+    /// explicit grid if `nth` is negative. If there aren't enough such lines, all the lines of
+    /// the implicit grid are assumed to have that name.
+    fn find_named_line(&self, name: &Atom, nth: Integer) -> isize {
+        let lines: Vec<usize> = self
+            .line_names
+            .iter()
+            .enumerate()
+            .filter(|&(_, names)| names.contains(name))
+            .map(|(index, _)| index)
+            .collect();
+        let count = lines.len() as isize;
+        if nth > 0 {
+            match lines.get(nth as usize - 1) {
+                Some(&index) => index as isize,
+                None => self.explicit_track_count() as isize + nth as isize - count,
+            }
+        } else {
+            let nth = -nth as isize;
+            if nth <= count {
+                lines[(count - nth) as usize] as isize
+            } else {
+                count - nth
+            }
+        }
+    }
+
+    /// Resolves a `<grid-line>` that isn't `auto` nor a span to a line index, which may be
+    /// negative for lines before the start of the explicit grid.
+    ///
+    /// <https://drafts.csswg.org/css-grid/#line-placement>
+    fn resolve_line(&self, line: &GridLine, suffix: &str) -> isize {
+        if line.ident.is_empty() {
+            return if line.line_num > 0 {
+                line.line_num as isize - 1
+            } else {
+                self.line_names.len() as isize + line.line_num as isize
+            };
+        }
+        if line.line_num != 0 {
+            return self.find_named_line(&line.ident, line.line_num);
+        }
+        let area_line = Atom::from(format!("{}-{}", line.ident, suffix));
+        if self
+            .line_names
+            .iter()
+            .any(|names| names.contains(&area_line))
+        {
+            self.find_named_line(&area_line, 1)
+        } else {
+            self.find_named_line(&line.ident, 1)
+        }
+    }
+
+    /// Returns the line that a `span` grid line reaches when counting from the line `from`.
+    fn resolve_span(&self, from: isize, span: &GridLine, forward: bool) -> isize {
+        let count = max(span.line_num, 1) as isize;
+        let step = if forward { 1 } else { -1 };
+        if span.ident.is_empty() {
+            return from + count * step;
+        }
+
+        let mut remaining = count;
+        let mut index = from;
+        loop {
+            index += step;
+            let is_named = index < 0 ||
+                index >= self.line_names.len() as isize ||
+                self.line_names[index as usize].contains(&span.ident);
+            if is_named {
+                remaining -= 1;
+                if remaining == 0 {
+                    return index;
+                }
+            }
+        }
+    }
+
+    /// Resolves the placement of an item in this axis from its `grid-*-start` and
+    /// `grid-*-end` properties.
+    ///
+    /// <https://drafts.csswg.org/css-grid/#placement>
+    fn resolve_placement(&self, start: &GridLine, end: &GridLine) -> AxisPlacement {
+        let is_auto = |line: &GridLine| line.ident.is_empty() && line.line_num == 0;
+        let start_line = if start.is_span || is_auto(start) {
+            None
+        } else {
+            Some(self.resolve_line(start, "start"))
+        };
+        let end_line = if end.is_span || is_auto(end) {
+            None
+        } else {
+            Some(self.resolve_line(end, "end"))
+        };
+
+        let (start_line, end_line) = match (start_line, end_line) {
+            (Some(start_line), Some(end_line)) => {
+                if start_line < end_line {
+                    (start_line, end_line)
+                } else if start_line > end_line {
+                    (end_line, start_line)
+                } else {
+                    (start_line, start_line + 1)
+                }
+            },
+            (Some(start_line), None) if end.is_span => {
+                (start_line, self.resolve_span(start_line, end, true))
+            },
+            (Some(start_line), None) => (start_line, start_line + 1),
+            (None, Some(end_line)) if start.is_span => {
+                (self.resolve_span(end_line, start, false), end_line)
+            },
+            (None, Some(end_line)) => (end_line - 1, end_line),
+            (None, None) => {
+                let span = if start.is_span {
+                    start
+                } else if end.is_span {
+                    end
+                } else {
+                    return AxisPlacement::Auto(1);
+                };
+                // Named spans of auto-placed items are treated as spans of one track.
+                if !span.ident.is_empty() {
+                    return AxisPlacement::Auto(1);
+                }
+                return AxisPlacement::Auto(max(span.line_num, 1) as usize);
+            },
+        };
+
+        let shift = max(-start_line, 0);
+        AxisPlacement::Definite((start_line + shift) as usize..(end_line + shift) as usize)
+    }
+}
+
+fn add_line_names(line_names: &mut Vec<Vec<Atom>>, names: Option<&[CustomIdent]>) {
+    if let Some(names) = names {
+        line_names
+            .last_mut()
+            .unwrap()
+            .extend(names.iter().map(|name| name.0.clone()));
+    }
+}
+
+/// The placement of a grid item in one axis, before auto-placement.
+#[derive(Clone, Debug)]
+enum AxisPlacement {
+    /// The item occupies the given range of tracks.
+    Definite(Range<usize>),
+    /// The item has to be auto-placed, and spans the given number of tracks.
+    Auto(usize),
+}
+
+impl AxisPlacement {
+    fn span(&self) -> usize {
+        match *self {
+            AxisPlacement::Definite(ref tracks) => tracks.len(),
+            AxisPlacement::Auto(span) => span,
+        }
+    }
+}
+
+/// The cells of a grid that are occupied by items that have been placed already. Cells are
+/// indexed by the track of the axis along which auto-placement adds tracks, then by the track
+/// of the other axis.
+struct OccupancyGrid {
+    cells: Vec<Vec<bool>>,
+}
+
+impl OccupancyGrid {
+    fn is_free(&self, major: &Range<usize>, minor: &Range<usize>) -> bool {
+        major.clone().all(|index| match self.cells.get(index) {
+            Some(cells) => minor
+                .clone()
+                .all(|index| !cells.get(index).cloned().unwrap_or(false)),
+            None => true,
+        })
+    }
+
+    fn occupy(&mut self, major: &Range<usize>, minor: &Range<usize>) {
+        if self.cells.len() < major.end {
+            self.cells.resize(major.end, vec![]);
+        }
+        for cells in &mut self.cells[major.clone()] {
+            if cells.len() < minor.end {
+                cells.resize(minor.end, false);
+            }
+            for cell in &mut cells[minor.clone()] {
+                *cell = true;
+            }
+        }
+    }
+}
+
+/// Resolves the grid area of each of the given items, as `(index, major, minor)` tuples with the
+/// tracks of the axis along which auto-placement adds tracks first.
+///
+/// <https://drafts.csswg.org/css-grid/#auto-placement-algo>
+fn auto_place(
+    items: Vec<(usize, AxisPlacement, AxisPlacement)>,
+    explicit_minor_count: usize,
+    dense: bool,
+) -> Vec<(usize, Range<usize>, Range<usize>)> {
+    let mut grid = OccupancyGrid { cells: vec![] };
+    let mut placed = Vec::with_capacity(items.len());
+    let mut locked = vec![];
+    let mut remaining = vec![];
+
+    // Step 1: Position anything that's not auto-positioned.
+    for (index, major, minor) in items {
+        match (major, minor) {
+            (AxisPlacement::Definite(major), AxisPlacement::Definite(minor)) => {
+                grid.occupy(&major, &minor);
+                placed.push((index, major, minor));
+            },
+            (AxisPlacement::Definite(major), AxisPlacement::Auto(span)) => {
+                locked.push((index, major, span))
+            },
+            (AxisPlacement::Auto(span), minor) => remaining.push((index, span, minor)),
+        }
+    }
+
+    // Step 2: Process the items locked to a given row (or column for `grid-auto-flow: column`).
+    let mut minor_count = explicit_minor_count;
+    let mut cursors = HashMap::new();
+    for (index, major, span) in locked {
+        let mut start = if dense {
+            0
+        } else {
+            cursors.get(&major.start).cloned().unwrap_or(0)
+        };
+        while !grid.is_free(&major, &(start..start + span)) {
+            start += 1;
+        }
+        let minor = start..start + span;
+        grid.occupy(&major, &minor);
+        cursors.insert(major.start, minor.end);
+        minor_count = max(minor_count, minor.end);
+        placed.push((index, major, minor));
+    }
+
+    // Step 3: Determine the columns (or rows) in the implicit grid.
+    for &(_, _, ref minor) in &remaining {
+        minor_count = max(
+            minor_count,
+            match *minor {
+                AxisPlacement::Definite(ref minor) => minor.end,
+                AxisPlacement::Auto(span) => span,
+            },
+        );
+    }
+
+    // Step 4: Position the remaining grid items.
+    let (mut cursor_major, mut cursor_minor) = (0, 0);
+    for (index, span, minor) in remaining {
+        if dense {
+            cursor_major = 0;
+            cursor_minor = 0;
+        }
+        let minor = match minor {
+            AxisPlacement::Definite(minor) => {
+                if !dense && minor.start < cursor_minor {
+                    cursor_major += 1;
+                }
+                while !grid.is_free(&(cursor_major..cursor_major + span), &minor) {
+                    cursor_major += 1;
+                }
+                cursor_minor = minor.start;
+                minor
+            },
+            AxisPlacement::Auto(minor_span) => loop {
+                if cursor_minor + minor_span > minor_count {
+                    cursor_major += 1;
+                    cursor_minor = 0;
+                    continue;
+                }
+                let minor = cursor_minor..cursor_minor + minor_span;
+                if grid.is_free(&(cursor_major..cursor_major + span), &minor) {
+                    cursor_minor = minor.end;
+                    break minor;
+                }
+                cursor_minor += 1;
+            },
+        };
+        let major = cursor_major..cursor_major + span;
+        grid.occupy(&major, &minor);
+        placed.push((index, major, minor));
+    }
+
+    placed
+}
+
+/// The contribution of a grid item to the size of the tracks it spans in one axis, including
+/// its margins, borders and padding.
+#[derive(Clone, Debug)]
+struct Contribution {
+    tracks: Range<usize>,
+    min_content: Au,
+    max_content: Au,
+}
+
+/// A track sizing function with percentages resolved against the size of the grid container.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Breadth {
+    Fixed(Au),
+    Flex(f32),
+    MinContent,
+    MaxContent,
+    Auto,
+}
+
+impl Breadth {
+    fn new(breadth: &TrackBreadth<LengthPercentage>, available_size: Option<Au>) -> Breadth {
+        match *breadth {
+            // Percentages that can't be resolved are treated as `auto`.
+            TrackBreadth::Breadth(ref length) => length
+                .maybe_to_used_value(available_size)
+                .map_or(Breadth::Auto, Breadth::Fixed),
+            TrackBreadth::Fr(factor) => Breadth::Flex(factor),
+            TrackBreadth::MinContent => Breadth::MinContent,
+            TrackBreadth::MaxContent => Breadth::MaxContent,
+            TrackBreadth::Auto => Breadth::Auto,
+        }
+    }
+
+    fn is_intrinsic(&self) -> bool {
+        match *self {
+            Breadth::MinContent | Breadth::MaxContent | Breadth::Auto => true,
+            Breadth::Fixed(_) | Breadth::Flex(_) => false,
+        }
+    }
+}
+
+/// A track during the track sizing algorithm.
+///
+/// <https://drafts.csswg.org/css-grid/#algo-terms>
+#[derive(Debug)]
+struct SizingTrack {
+    min_function: Breadth,
+    max_function: Breadth,
+    /// The argument of `fit-content()`, if this track uses it.
+    fit_content_limit: Option<Au>,
+    base_size: Au,
+    /// The growth limit of the track, or `None` if it is infinite.
+    growth_limit: Option<Au>,
+}
+
+impl SizingTrack {
+    fn new(size: &TrackSize<LengthPercentage>, available_size: Option<Au>) -> SizingTrack {
+        let (min_function, max_function, fit_content_limit) = match *size {
+            TrackSize::Breadth(ref breadth) => match Breadth::new(breadth, available_size) {
+                Breadth::Flex(factor) => (Breadth::Auto, Breadth::Flex(factor), None),
+                breadth => (breadth, breadth, None),
+            },
+            TrackSize::Minmax(ref min_breadth, ref max_breadth) => {
+                let min_function = match Breadth::new(min_breadth, available_size) {
+                    Breadth::Flex(_) => Breadth::Auto,
+                    breadth => breadth,
+                };
+                (
+                    min_function,
+                    Breadth::new(max_breadth, available_size),
+                    None,
+                )
+            },
+            TrackSize::FitContent(ref limit) => {
+                let limit = match Breadth::new(limit, available_size) {
+                    Breadth::Fixed(limit) => Some(limit),
+                    _ => None,
+                };
+                (Breadth::Auto, Breadth::MaxContent, limit)
+            },
+        };
+
+        let base_size = match min_function {
+            Breadth::Fixed(size) => size,
+            _ => Au(0),
+        };
+        let growth_limit = match max_function {
+            Breadth::Fixed(size) => Some(max(size, base_size)),
+            _ => None,
+        };
+        SizingTrack {
+            min_function: min_function,
+            max_function: max_function,
+            fit_content_limit: fit_content_limit,
+            base_size: base_size,
+            growth_limit: growth_limit,
+        }
+    }
+
+    fn flex_factor(&self) -> Option<f32> {
+        match self.max_function {
+            Breadth::Flex(factor) => Some(factor),
+            _ => None,
+        }
+    }
+
+    /// Sizes this track to fit an item that spans only this track.
+    ///
+    /// <https://drafts.csswg.org/css-grid/#algo-single-span-items>
+    fn fit_item(&mut self, contribution: &Contribution) {
+        match self.min_function {
+            Breadth::MinContent | Breadth::Auto => {
+                self.base_size = max(self.base_size, contribution.min_content)
+            },
+            Breadth::MaxContent => self.base_size = max(self.base_size, contribution.max_content),
+            Breadth::Fixed(_) | Breadth::Flex(_) => {},
+        }
+
+        let limit = match self.max_function {
+            Breadth::MinContent => contribution.min_content,
+            Breadth::MaxContent | Breadth::Auto => contribution.max_content,
+            Breadth::Fixed(_) | Breadth::Flex(_) => return,
+        };
+        let limit = match self.fit_content_limit {
+            Some(fit_content_limit) => min(limit, fit_content_limit),
+            None => limit,
+        };
+        self.growth_limit = Some(max(self.growth_limit.unwrap_or(Au(0)), limit));
+    }
+}
+
+/// Distributes the extra space needed by an item spanning several tracks, none of them flexible,
+/// equally between the intrinsically-sized tracks it spans.
+///
+/// <https://drafts.csswg.org/css-grid/#algo-spanning-items>
+fn fit_spanning_item(tracks: &mut [SizingTrack], contribution: &Contribution, gap: Au) {
+    if tracks.iter().any(|track| track.flex_factor().is_some()) {
+        return;
+    }
+    let gaps = gap * (tracks.len() as i32 - 1);
+
+    let base_sizes = tracks
+        .iter()
+        .fold(gaps, |size, track| size + track.base_size);
+    let extra_space = contribution.min_content - base_sizes;
+    let count = tracks
+        .iter()
+        .filter(|track| track.min_function.is_intrinsic())
+        .count() as i32;
+    if extra_space > Au(0) && count > 0 {
+        for track in tracks
+            .iter_mut()
+            .filter(|track| track.min_function.is_intrinsic())
+        {
+            track.base_size += extra_space / count;
+        }
+    }
+
+    let growth_limits = tracks.iter().fold(gaps, |size, track| {
+        size + track.growth_limit.unwrap_or(track.base_size)
+    });
+    let extra_space = contribution.max_content - growth_limits;
+    let count = tracks
+        .iter()
+        .filter(|track| track.max_function.is_intrinsic())
+        .count() as i32;
+    if extra_space > Au(0) && count > 0 {
+        for track in tracks
+            .iter_mut()
+            .filter(|track| track.max_function.is_intrinsic())
+        {
+            track.growth_limit =
+                Some(track.growth_limit.unwrap_or(track.base_size) + extra_space / count);
+        }
+    }
+}
+
+/// Finds the size of one `fr` when `space` is shared between the flexible tracks.
+///
+/// <https://drafts.csswg.org/css-grid/#algo-find-fr-size>
+fn find_fr_size(tracks: &[SizingTrack], space: Au) -> Au {
+    let mut inflexible = vec![false; tracks.len()];
+    loop {
+        let mut leftover_space = space;
+        let mut flex_factor_sum = 0.;
+        for (track, &inflexible) in tracks.iter().zip(inflexible.iter()) {
+            match track.flex_factor() {
+                Some(factor) if !inflexible => flex_factor_sum += factor,
+                _ => leftover_space -= track.base_size,
+            }
+        }
+        let flex_factor_sum = if flex_factor_sum < 1. {
+            1.
+        } else {
+            flex_factor_sum
+        };
+        let fr_size = max(leftover_space, Au(0)).scale_by(1. / flex_factor_sum);
+
+        // Flexible tracks whose base size is larger than their share are treated as inflexible,
+        // and the size of a `fr` is found again.
+        let too_large = tracks.iter().enumerate().position(|(index, track)| {
+            !inflexible[index] &&
+                track
+                    .flex_factor()
+                    .map_or(false, |factor| fr_size.scale_by(factor) < track.base_size)
+        });
+        match too_large {
+            Some(index) => inflexible[index] = true,
+            None => return fr_size,
+        }
+    }
+}
+
+/// Runs the track sizing algorithm for one axis of a grid container and returns the used size
+/// of each track. `available_size` is the size of the content box of the container in this axis
+/// if it is definite.
+///
+/// <https://drafts.csswg.org/css-grid/#algo-track-sizing>
+fn size_tracks(
+    definitions: &TrackDefinitions,
+    track_count: usize,
+    contributions: &[Contribution],
+    available_size: Option<Au>,
+    gap: Au,
+) -> Vec<Au> {
+    let mut tracks: Vec<SizingTrack> = (0..track_count)
+        .map(|index| SizingTrack::new(&definitions.sizing_function(index), available_size))
+        .collect();
+    let gaps = gap * max(track_count as i32 - 1, 0);
+
+    // Resolve intrinsic track sizes, starting with the items that span a single track.
+    for contribution in contributions {
+        if contribution.tracks.len() == 1 {
+            tracks[contribution.tracks.start].fit_item(contribution);
+        }
+    }
+    for contribution in contributions {
+        if contribution.tracks.len() > 1 {
+            fit_spanning_item(&mut tracks[contribution.tracks.clone()], contribution, gap);
+        }
+    }
+    for track in tracks.iter_mut() {
+        let growth_limit = track.growth_limit.unwrap_or(track.base_size);
+        track.growth_limit = Some(max(growth_limit, track.base_size));
+    }
+
+    // Maximize the tracks, growing them up to their growth limits.
+    match available_size {
+        Some(available_size) => {
+            let mut free_space = tracks.iter().fold(available_size - gaps, |space, track| {
+                space - track.base_size
+            });
+            loop {
+                let growable: Vec<usize> = (0..track_count)
+                    .filter(|&index| {
+                        tracks[index].flex_factor().is_none() &&
+                            Some(tracks[index].base_size) < tracks[index].growth_limit
+                    })
+                    .collect();
+                if free_space <= Au(0) || growable.is_empty() {
+                    break;
+                }
+                let share = max(free_space / growable.len() as i32, Au(1));
+                for index in growable {
+                    let track = &mut tracks[index];
+                    let growth = min(
+                        min(share, track.growth_limit.unwrap() - track.base_size),
+                        free_space,
+                    );
+                    track.base_size += growth;
+                    free_space -= growth;
+                }
+            }
+        },
+        None => {
+            for track in tracks.iter_mut() {
+                if track.flex_factor().is_none() {
+                    track.base_size = track.growth_limit.unwrap();
+                }
+            }
+        },
+    }
+
+    // Expand the flexible tracks.
+    if tracks.iter().any(|track| track.flex_factor().is_some()) {
+        let fr_size = match available_size {
+            Some(available_size) => find_fr_size(&tracks, available_size - gaps),
+            None => {
+                let mut fr_size = Au(0);
+                for track in &tracks {
+                    if let Some(factor) = track.flex_factor() {
+                        let factor = if factor > 1. { factor } else { 1. };
+                        fr_size = max(fr_size, track.base_size.scale_by(1. / factor));
+                    }
+                }
+                for contribution in contributions {
+                    let spanned = &tracks[contribution.tracks.clone()];
+                    let flex_factor_sum: f32 =
+                        spanned.iter().filter_map(|track| track.flex_factor()).sum();
+                    if flex_factor_sum == 0. {
+                        continue;
+                    }
+                    let flex_factor_sum = if flex_factor_sum < 1. {
+                        1.
+                    } else {
+                        flex_factor_sum
+                    };
+                    let inflexible_size = spanned
+                        .iter()
+                        .filter(|track| track.flex_factor().is_none())
+                        .fold(gap * (spanned.len() as i32 - 1), |size, track| {
+                            size + track.base_size
+                        });
+                    let space = max(contribution.max_content - inflexible_size, Au(0));
+                    fr_size = max(fr_size, space.scale_by(1. / flex_factor_sum));
+                }
+                fr_size
+            },
+        };
+        for track in tracks.iter_mut() {
+            if let Some(factor) = track.flex_factor() {
+                track.base_size = max(track.base_size, fr_size.scale_by(factor));
+            }
+        }
+    }
+
+    // Stretch `auto` tracks into the remaining free space.
+    if let Some(available_size) = available_size {
+        let free_space = tracks.iter().fold(available_size - gaps, |space, track| {
+            space - track.base_size
+        });
+        let count = tracks
+            .iter()
+            .filter(|track| track.max_function == Breadth::Auto)
+            .count() as i32;
+        if free_space > Au(0) && count > 0 {
+            for track in tracks
+                .iter_mut()
+                .filter(|track| track.max_function == Breadth::Auto)
+            {
+                track.base_size += free_space / count;
+            }
+        }
+    }
+
+    tracks.into_iter().map(|track| track.base_size).collect()
+}
+
+/// Returns the offset of the start of a range of tracks from the start of the first track, and
+/// the size of the range including the gaps inside of it.
+fn track_range_extent(sizes: &[Au], gap: Au, tracks: &Range<usize>) -> (Au, Au) {
+    let start = sizes[..tracks.start]
+        .iter()
+        .fold(Au(0), |offset, size| offset + *size + gap);
+    let size = sizes[tracks.clone()]
+        .iter()
+        .fold(gap * (tracks.len() as i32 - 1), |total, size| total + *size);
+    (start, size)
+}
+
+/// Returns the sum of the given track sizes and the gaps between them.
+fn total_track_size(sizes: &[Au], gap: Au) -> Au {
+    sizes
+        .iter()
+        .fold(gap * max(sizes.len() as i32 - 1, 0), |total, size| {
+            total + *size
+        })
+}
+
+/// Returns the used value of `row-gap` or `column-gap`. `normal` is zero in grid containers.
+fn used_gap(gap: &NonNegativeLengthPercentageOrNormal, content_size: Option<Au>) -> Au {
+    match *gap {
+        NonNegativeLengthPercentageOrNormal::LengthPercentage(ref length) => {
+            length.0.maybe_to_used_value(content_size).unwrap_or(Au(0))
+        },
+        NonNegativeLengthPercentageOrNormal::Normal => Au(0),
+    }
+}
+
+/// A child of a grid container, and the grid area it occupies.
+#[derive(Debug, Serialize)]
+struct GridItem {
+    /// The index of the actual flow in our child list.
+    index: usize,
+    /// The range of columns that this item spans.
+    columns: Range<usize>,
+    /// The range of rows that this item spans.
+    rows: Range<usize>,
+}
+
+#[allow(unsafe_code)]
+unsafe impl crate::flow::HasBaseFlow for GridFlow {}
+
+/// A block with the CSS `display` property equal to `grid`.
+#[derive(Debug, Serialize)]
+#[repr(C)]
+pub struct GridFlow {
+    /// Data common to all block flows.
+    block_flow: BlockFlow,
+    /// The grid items of this container, in order-modified document order.
+    items: Vec<GridItem>,
+    /// The used size of each column of the grid.
+    columns: Vec<Au>,
+    /// The used size of each row of the grid.
+    rows: Vec<Au>,
+}
+
+impl GridFlow {
+    pub fn from_fragment(fragment: Fragment, flotation: Option<FloatKind>) -> GridFlow {
+        GridFlow {
+            block_flow: BlockFlow::from_fragment_and_float_kind(fragment, flotation),
+            items: Vec::new(),
+            columns: Vec::new(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Resolves the grid area of every grid item, and the number of rows and columns of the
+    /// grid.
+    fn place_items(&mut self) {
+        let style = self.block_flow.fragment.style.clone();
+        let columns = TrackDefinitions::new(&style, Direction::Inline);
+        let rows = TrackDefinitions::new(&style, Direction::Block);
+        let auto_flow = style.get_position().grid_auto_flow;
+        let column_flow = auto_flow.autoflow == AutoFlow::Column;
+
+        let mut children: Vec<(usize, i32, AxisPlacement, AxisPlacement)> = self
+            .block_flow
+            .base
+            .children
+            .iter()
+            .enumerate()
+            .filter(|&(_, flow)| {
+                !flow
+                    .as_block()
+                    .base
+                    .flags
+                    .contains(FlowFlags::IS_ABSOLUTELY_POSITIONED)
+            })
+            .map(|(index, flow)| {
+                let position = flow.as_block().fragment.style.get_position();
+                let column = columns
+                    .resolve_placement(&position.grid_column_start, &position.grid_column_end);
+                let row = rows.resolve_placement(&position.grid_row_start, &position.grid_row_end);
+                (index, position.order, column, row)
+            })
+            .collect();
+        // FIXME: This should be done during flow construction, like for flex items.
+        children.sort_by_key(|&(_, order, _, _)| order);
+
+        let placed = if column_flow {
+            let items = children
+                .into_iter()
+                .map(|(index, _, column, row)| (index, column, row))
+                .collect();
+            auto_place(items, rows.explicit_track_count(), auto_flow.dense)
+                .into_iter()
+                .map(|(index, columns, rows)| GridItem {
+                    index: index,
+                    columns: columns,
+                    rows: rows,
+                })
+                .collect()
+        } else {
+            let items = children
+                .into_iter()
+                .map(|(index, _, column, row)| (index, row, column))
+                .collect();
+            auto_place(items, columns.explicit_track_count(), auto_flow.dense)
+                .into_iter()
+                .map(|(index, rows, columns)| GridItem {
+                    index: index,
+                    columns: columns,
+                    rows: rows,
+                })
+                .collect()
+        };
+        self.items = placed;
+
+        let column_count = self
+            .items
+            .iter()
+            .fold(columns.explicit_track_count(), |count, item| {
+                max(count, item.columns.end)
+            });
+        let row_count = self
+            .items
+            .iter()
+            .fold(rows.explicit_track_count(), |count, item| {
+                max(count, item.rows.end)
+            });
+        self.columns = vec![Au(0); column_count];
+        self.rows = vec![Au(0); row_count];
+    }
+
+    /// Returns the contribution of each grid item to the size of the columns, from the intrinsic
+    /// inline sizes of the items.
+    fn inline_contributions(&self) -> Vec<Contribution> {
+        let children = self.block_flow.base.children.iter().collect::<Vec<_>>();
+        self.items
+            .iter()
+            .map(|item| {
+                let sizes = &children[item.index].base().intrinsic_inline_sizes;
+                Contribution {
+                    tracks: item.columns.clone(),
+                    min_content: sizes.minimum_inline_size,
+                    max_content: sizes.preferred_inline_size,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the contribution of each grid item to the size of the rows, from the block sizes
+    /// that the items have been laid out with.
+    fn block_contributions(&mut self) -> Vec<Contribution> {
+        let mut children = self.block_flow.base.children.random_access_mut();
+        self.items
+            .iter()
+            .map(|item| {
+                let fragment = &children.get(item.index).as_block().fragment;
+                let size = fragment.border_box.size.block + fragment.margin.block_start_end();
+                Contribution {
+                    tracks: item.rows.clone(),
+                    min_content: size,
+                    max_content: size,
+                }
+            })
+            .collect()
+    }
+
+    /// Sizes the columns, then gives each grid item the inline size and position of its grid
+    /// area.
+    fn assign_item_inline_sizes(&mut self, inline_start_content_edge: Au, content_inline_size: Au) {
+        let style = self.block_flow.fragment.style.clone();
+        let definitions = TrackDefinitions::new(&style, Direction::Inline);
+        let column_gap = used_gap(&style.get_position().column_gap, Some(content_inline_size));
+        let contributions = self.inline_contributions();
+        self.columns = size_tracks(
+            &definitions,
+            self.columns.len(),
+            &contributions,
+            Some(content_inline_size),
+            column_gap,
+        );
+
+        let container_mode = self.block_flow.base.block_container_writing_mode;
+        let containing_block_text_align = style.get_inherited_text().text_align;
+
+        let mut children = self.block_flow.base.children.random_access_mut();
+        for item in &self.items {
+            let (area_start, area_size) =
+                track_range_extent(&self.columns, column_gap, &item.columns);
+            let block = children.get(item.index).as_mut_block();
+
+            block.base.block_container_writing_mode = container_mode;
+            block.base.block_container_inline_size = area_size;
+            // The block size of the grid area isn't known yet.
+            block.base.block_container_explicit_block_size = None;
+            // Per CSS 2.1 § 16.3.1, text alignment propagates to all children in flow.
+            //
+            // TODO(#2265, pcwalton): Do this in the cascade instead.
+            block.base.flags.set_text_align(containing_block_text_align);
+
+            // These methods compute auto margins to zero length, which are adjusted below.
+            block.fragment.compute_border_and_padding(area_size);
+            block.fragment.compute_inline_direction_margins(area_size);
+            block.fragment.compute_block_direction_margins(area_size);
+
+            let margin = block.fragment.style().logical_margin();
+            let margin_inline_start = MaybeAuto::from_style(margin.inline_start, area_size);
+            let margin_inline_end = MaybeAuto::from_style(margin.inline_end, area_size);
+            let margins =
+                margin_inline_start.specified_or_zero() + margin_inline_end.specified_or_zero();
+            let border_padding = block.fragment.border_padding.inline_start_end();
+            let box_sizing_boundary = block.fragment.box_sizing_boundary(Direction::Inline);
+
+            // Items with an automatic inline size are stretched to fill their grid area.
+            let size = match block.fragment.style().content_inline_size() {
+                Size::Auto => area_size - margins - border_padding + box_sizing_boundary,
+                Size::LengthPercentage(ref length) => length.to_used_value(area_size),
+            };
+            let max_size = block
+                .fragment
+                .style()
+                .max_inline_size()
+                .to_used_value(area_size)
+                .unwrap_or(MAX_AU);
+            let min_size = block
+                .fragment
+                .style()
+                .min_inline_size()
+                .to_used_value(area_size)
+                .unwrap_or(Au(0));
+            let content_size = max(min_size, min(size, max_size)) - box_sizing_boundary;
+            let inline_size = max(content_size, Au(0)) + border_padding;
+
+            let free_space = max(area_size - inline_size - margins, Au(0));
+            let (margin_inline_start, margin_inline_end) =
+                match (margin_inline_start, margin_inline_end) {
+                    (MaybeAuto::Auto, MaybeAuto::Auto) => {
+                        (free_space / 2, free_space - free_space / 2)
+                    },
+                    (MaybeAuto::Auto, MaybeAuto::Specified(end)) => (free_space, end),
+                    (MaybeAuto::Specified(start), MaybeAuto::Auto) => (start, free_space),
+                    (MaybeAuto::Specified(start), MaybeAuto::Specified(end)) => (start, end),
+                };
+
+            block.fragment.margin.inline_start = margin_inline_start;
+            block.fragment.margin.inline_end = margin_inline_end;
+            block.fragment.border_box.start.i = margin_inline_start;
+            block.fragment.border_box.size.inline = inline_size;
+            block.base.position.start.i = inline_start_content_edge + area_start;
+            block.base.position.size.inline = inline_size + margin_inline_start + margin_inline_end;
+        }
+    }
+
+    /// Sizes the rows, then positions each grid item in the block direction and aligns it
+    /// inside of its grid area according to `align-self`.
+    fn assign_item_block_sizes(&mut self, layout_context: &LayoutContext) {
+        let _scope = layout_debug_scope!("grid::assign_item_block_sizes");
+
+        let style = self.block_flow.fragment.style.clone();
+        let box_border = self
+            .block_flow
+            .fragment
+            .box_sizing_boundary(Direction::Block);
+        let parent_container_size = self
+            .block_flow
+            .explicit_block_containing_size(layout_context.shared_context());
+        // https://drafts.csswg.org/css-ui-3/#box-sizing
+        let explicit_content_size = self
+            .block_flow
+            .explicit_block_size(parent_container_size)
+            .map(|x| max(x - box_border, Au(0)));
+
+        let definitions = TrackDefinitions::new(&style, Direction::Block);
+        let row_gap = used_gap(&style.get_position().row_gap, explicit_content_size);
+        let contributions = self.block_contributions();
+        self.rows = size_tracks(
+            &definitions,
+            self.rows.len(),
+            &contributions,
+            explicit_content_size,
+            row_gap,
+        );
+
+        let block_start_content_edge = self.block_flow.fragment.border_padding.block_start;
+        let mut children = self.block_flow.base.children.random_access_mut();
+        for item in &self.items {
+            let (area_start, area_size) = track_range_extent(&self.rows, row_gap, &item.rows);
+            let block = children.get(item.index).as_mut_block();
+
+            let margin = block.fragment.style().logical_margin();
+            let mut margin_block_start = block.fragment.margin.block_start;
+            let mut margin_block_end = block.fragment.margin.block_end;
+            let mut free_space =
+                area_size - block.base.position.size.block - margin_block_start - margin_block_end;
+
+            if margin.block_start.is_auto() || margin.block_end.is_auto() {
+                let free_space = max(free_space, Au(0));
+                match (margin.block_start.is_auto(), margin.block_end.is_auto()) {
+                    (true, true) => {
+                        margin_block_start = free_space / 2;
+                        margin_block_end = free_space - free_space / 2;
+                    },
+                    (true, false) => margin_block_start = free_space,
+                    _ => margin_block_end = free_space,
+                }
+                block.fragment.margin.block_start = margin_block_start;
+                block.fragment.margin.block_end = margin_block_end;
+                free_space = Au(0);
+            }
+
+            let self_align = block.fragment.style().get_position().align_self;
+            if self_align == AlignSelf::Stretch &&
+                block.fragment.style().content_block_size().is_auto()
+            {
+                free_space = Au(0);
+                block.base.block_container_explicit_block_size = Some(area_size);
+                block.base.position.size.block =
+                    max(area_size - margin_block_start - margin_block_end, Au(0));
+                block.fragment.border_box.size.block = block.base.position.size.block;
+                // FIXME: Like for flex items, the children of a stretched grid item should be
+                // laid out again against its new block size.
+            }
+
+            let alignment_offset = match self_align {
+                AlignSelf::FlexEnd => free_space,
+                AlignSelf::Center => free_space / 2,
+                _ => Au(0),
+            };
+            block.base.position.start.b =
+                block_start_content_edge + area_start + margin_block_start + alignment_offset;
+        }
+
+        let content_block_size =
+            explicit_content_size.unwrap_or_else(|| total_track_size(&self.rows, row_gap));
+        let total_block_size =
+            content_block_size + self.block_flow.fragment.border_padding.block_start_end();
+        self.block_flow.fragment.border_box.size.block = total_block_size;
+        self.block_flow.base.position.size.block = total_block_size;
+    }
+}
+
+impl Flow for GridFlow {
+    fn class(&self) -> FlowClass {
+        FlowClass::Grid
+    }
+
+    fn as_grid(&self) -> &GridFlow {
+        self
+    }
+
+    fn as_block(&self) -> &BlockFlow {
+        &self.block_flow
+    }
+
+    fn as_mut_block(&mut self) -> &mut BlockFlow {
+        &mut self.block_flow
+    }
+
+    fn mark_as_root(&mut self) {
+        self.block_flow.mark_as_root();
+    }
+
+    fn bubble_inline_sizes(&mut self) {
+        let _scope = layout_debug_scope!(
+            "grid::bubble_inline_sizes {:x}",
+            self.block_flow.base.debug_id()
+        );
+
+        // Grid Section 6.1: Generate anonymous grid items:
+        // This part was handled in the flow constructor.
+        self.place_items();
+
+        // FIXME(emilio): This doesn't handle at all writing-modes.
+        let fixed_width =
+            !model::style_length(self.block_flow.fragment.style().get_position().width, None)
+                .is_auto();
+
+        let mut computation = self.block_flow.fragment.compute_intrinsic_inline_sizes();
+        if !fixed_width {
+            let style = self.block_flow.fragment.style.clone();
+            let definitions = TrackDefinitions::new(&style, Direction::Inline);
+            let column_gap = used_gap(&style.get_position().column_gap, None);
+            let contributions = self.inline_contributions();
+            let min_content_contributions: Vec<Contribution> = contributions
+                .iter()
+                .map(|contribution| Contribution {
+                    max_content: contribution.min_content,
+                    ..contribution.clone()
+                })
+                .collect();
+
+            let column_count = self.columns.len();
+            let minimum_inline_size = total_track_size(
+                &size_tracks(
+                    &definitions,
+                    column_count,
+                    &min_content_contributions,
+                    None,
+                    column_gap,
+                ),
+                column_gap,
+            );
+            let preferred_inline_size = total_track_size(
+                &size_tracks(&definitions, column_count, &contributions, None, column_gap),
+                column_gap,
+            );
+            computation.union_block(&IntrinsicISizes {
+                minimum_inline_size: minimum_inline_size,
+                preferred_inline_size: max(minimum_inline_size, preferred_inline_size),
+            });
+        }
+        self.block_flow.base.intrinsic_inline_sizes = computation.finish();
+    }
+
+    fn assign_inline_sizes(&mut self, layout_context: &LayoutContext) {
+        let _scope = layout_debug_scope!(
+            "grid::assign_inline_sizes {:x}",
+            self.block_flow.base.debug_id()
+        );
+        debug!("assign_inline_sizes");
+
+        if !self
+            .block_flow
+            .base
+            .restyle_damage
+            .intersects(ServoRestyleDamage::REFLOW_OUT_OF_FLOW | ServoRestyleDamage::REFLOW)
+        {
+            return;
+        }
+
+        self.block_flow
+            .initialize_container_size_for_root(layout_context.shared_context());
+
+        // Our inline-size was set to the inline-size of the containing block by the flow's parent.
+        // Now compute the real value.
+        let containing_block_inline_size = self.block_flow.base.block_container_inline_size;
+        self.block_flow.compute_used_inline_size(
+            layout_context.shared_context(),
+            containing_block_inline_size,
+        );
+        if self.block_flow.base.flags.is_float() {
+            self.block_flow
+                .float
+                .as_mut()
+                .unwrap()
+                .containing_inline_size = containing_block_inline_size
+        }
+
+        // Move in from the inline-start border edge.
+        let inline_start_content_edge = self.block_flow.fragment.border_box.start.i +
+            self.block_flow.fragment.border_padding.inline_start;
+        let padding_and_borders = self.block_flow.fragment.border_padding.inline_start_end();
+        let content_inline_size =
+            self.block_flow.fragment.border_box.size.inline - padding_and_borders;
+        debug!("content_inline_size = {:?}", content_inline_size);
+
+        self.block_flow.base.position.size.inline = content_inline_size;
+        if ImmutableFlowUtils::child_count(self as &dyn Flow) == 0 {
+            return;
+        }
+        self.assign_item_inline_sizes(inline_start_content_edge, content_inline_size);
+    }
+
+    fn assign_block_size(&mut self, layout_context: &LayoutContext) {
+        self.assign_item_block_sizes(layout_context);
+        let block_start =
+            AdjoiningMargins::from_margin(self.block_flow.fragment.margin.block_start);
+        let block_end = AdjoiningMargins::from_margin(self.block_flow.fragment.margin.block_end);
+        self.block_flow.base.collapsible_margins =
+            CollapsibleMargins::Collapse(block_start, block_end);
+
+        // TODO: assign proper static position for absolute descendants.
+        if (&*self as &dyn Flow).contains_roots_of_absolute_flow_tree() {
+            // Assign block-sizes for all flows in this absolute flow tree.
+            // This is preorder because the block-size of an absolute flow may depend on
+            // the block-size of its containing block, which may also be an absolute flow.
+            let assign_abs_b_sizes = AbsoluteAssignBSizesTraversal(layout_context.shared_context());
+            assign_abs_b_sizes.traverse_absolute_flows(&mut *self);
+        }
+    }
+
+    fn compute_stacking_relative_position(&mut self, layout_context: &LayoutContext) {
+        self.block_flow
+            .compute_stacking_relative_position(layout_context)
+    }
+
+    fn place_float_if_applicable<'a>(&mut self) {
+        self.block_flow.place_float_if_applicable()
+    }
+
+    fn update_late_computed_inline_position_if_necessary(&mut self, inline_position: Au) {
+        self.block_flow
+            .update_late_computed_inline_position_if_necessary(inline_position)
+    }
+
+    fn update_late_computed_block_position_if_necessary(&mut self, block_position: Au) {
+        self.block_flow
+            .update_late_computed_block_position_if_necessary(block_position)
+    }
+
+    fn build_display_list(&mut self, state: &mut DisplayListBuildState) {
+        // Draw the rest of the block.
+        self.as_mut_block()
+            .build_display_list_for_block(state, BorderPaintingMode::Separate)
+    }
+
+    fn collect_stacking_contexts(&mut self, state: &mut StackingContextCollectionState) {
+        self.block_flow.collect_stacking_contexts(state);
+    }
+
+    fn repair_style(&mut self, new_style: &crate::ServoArc<ComputedValues>) {
+        self.block_flow.repair_style(new_style)
+    }
+
+    fn compute_overflow(&self) -> Overflow {
+        self.block_flow.compute_overflow()
+    }
+
+    fn contains_roots_of_absolute_flow_tree(&self) -> bool {
+        self.block_flow.contains_roots_of_absolute_flow_tree()
+    }
+
+    fn is_absolute_containing_block(&self) -> bool {
+        self.block_flow.is_absolute_containing_block()
+    }
+
+    fn generated_containing_block_size(&self, flow: OpaqueFlow) -> LogicalSize<Au> {
+        self.block_flow.generated_containing_block_size(flow)
+    }
+
+    fn iterate_through_fragment_border_boxes(
+        &self,
+        iterator: &mut dyn FragmentBorderBoxIterator,
+        level: i32,
+        stacking_context_position: &Point2D<Au>,
+    ) {
+        self.block_flow.iterate_through_fragment_border_boxes(
+            iterator,
+            level,
+            stacking_context_position,
+        );
+    }
+
+    fn mutate_fragments(&mut self, mutator: &mut dyn FnMut(&mut Fragment)) {
+        self.block_flow.mutate_fragments(mutator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use style::values::computed::Length;
+
+    fn fixed(px: f32) -> TrackSize<LengthPercentage> {
+        TrackSize::Breadth(TrackBreadth::Breadth(LengthPercentage::new(
+            Length::new(px),
+            None,
+        )))
+    }
+
+    fn flex(factor: f32) -> TrackSize<LengthPercentage> {
+        TrackSize::Breadth(TrackBreadth::Fr(factor))
+    }
+
+    fn definitions(explicit: Vec<TrackSize<LengthPercentage>>) -> TrackDefinitions {
+        TrackDefinitions {
+            line_names: vec![vec![]; explicit.len() + 1],
+            explicit: explicit,
+            implicit: vec![],
+        }
+    }
+
+    fn sorted(
+        mut placed: Vec<(usize, Range<usize>, Range<usize>)>,
+    ) -> Vec<(usize, Range<usize>, Range<usize>)> {
+        placed.sort_by_key(|&(index, _, _)| index);
+        placed
+    }
+
+    #[test]
+    fn test_occupancy_grid() {
+        let mut grid = OccupancyGrid { cells: vec![] };
+        assert!(grid.is_free(&(0..2), &(0..2)));
+        grid.occupy(&(1..2), &(1..3));
+        assert!(grid.is_free(&(0..1), &(0..3)));
+        assert!(grid.is_free(&(1..2), &(0..1)));
+        assert!(!grid.is_free(&(0..2), &(2..3)));
+        assert!(grid.is_free(&(2..5), &(0..5)));
+    }
+
+    #[test]
+    fn test_auto_place_sparse() {
+        let items = vec![
+            (0, AxisPlacement::Auto(1), AxisPlacement::Auto(1)),
+            (1, AxisPlacement::Auto(1), AxisPlacement::Auto(2)),
+            (2, AxisPlacement::Auto(1), AxisPlacement::Auto(1)),
+        ];
+        assert_eq!(
+            sorted(auto_place(items, 3, false)),
+            vec![(0, 0..1, 0..1), (1, 0..1, 1..3), (2, 1..2, 0..1)]
+        );
+    }
+
+    #[test]
+    fn test_auto_place_definite_items_first() {
+        let items = vec![
+            (0, AxisPlacement::Auto(1), AxisPlacement::Auto(1)),
+            (
+                1,
+                AxisPlacement::Definite(0..1),
+                AxisPlacement::Definite(0..1),
+            ),
+            (2, AxisPlacement::Definite(1..2), AxisPlacement::Auto(1)),
+            (3, AxisPlacement::Definite(1..2), AxisPlacement::Auto(1)),
+        ];
+        assert_eq!(
+            sorted(auto_place(items, 2, false)),
+            vec![
+                (0, 0..1, 1..2),
+                (1, 0..1, 0..1),
+                (2, 1..2, 0..1),
+                (3, 1..2, 1..2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_auto_place_dense() {
+        let items = || {
+            vec![
+                (0, AxisPlacement::Auto(1), AxisPlacement::Auto(2)),
+                (1, AxisPlacement::Auto(1), AxisPlacement::Auto(3)),
+                (2, AxisPlacement::Auto(1), AxisPlacement::Auto(1)),
+            ]
+        };
+        assert_eq!(
+            sorted(auto_place(items(), 3, false)),
+            vec![(0, 0..1, 0..2), (1, 1..2, 0..3), (2, 2..3, 0..1)]
+        );
+        assert_eq!(
+            sorted(auto_place(items(), 3, true)),
+            vec![(0, 0..1, 0..2), (1, 1..2, 0..3), (2, 0..1, 2..3)]
+        );
+    }
+
+    #[test]
+    fn test_auto_place_grows_implicit_minor_tracks() {
+        let items = vec![
+            (0, AxisPlacement::Auto(1), AxisPlacement::Auto(1)),
+            (1, AxisPlacement::Auto(1), AxisPlacement::Auto(3)),
+        ];
+        assert_eq!(
+            sorted(auto_place(items, 1, false)),
+            vec![(0, 0..1, 0..1), (1, 1..2, 0..3)]
+        );
+    }
+
+    #[test]
+    fn test_find_fr_size() {
+        let mut tracks = vec![
+            SizingTrack::new(&flex(1.), None),
+            SizingTrack::new(&flex(1.), None),
+        ];
+        assert_eq!(find_fr_size(&tracks, Au::from_px(300)), Au::from_px(150));
+
+        // A flexible track whose base size is larger than its share keeps its base size.
+        tracks[0].base_size = Au::from_px(250);
+        assert_eq!(find_fr_size(&tracks, Au::from_px(300)), Au::from_px(50));
+    }
+
+    #[test]
+    fn test_size_tracks_definite() {
+        let definitions = definitions(vec![fixed(100.), flex(1.), TrackSize::default()]);
+        let contributions = [Contribution {
+            tracks: 2..3,
+            min_content: Au::from_px(50),
+            max_content: Au::from_px(80),
+        }];
+        assert_eq!(
+            size_tracks(
+                &definitions,
+                3,
+                &contributions,
+                Some(Au::from_px(500)),
+                Au::from_px(10)
+            ),
+            vec![Au::from_px(100), Au::from_px(300), Au::from_px(80)]
+        );
+    }
+
+    #[test]
+    fn test_size_tracks_stretches_auto_tracks() {
+        let definitions = definitions(vec![fixed(100.), TrackSize::default()]);
+        let contributions = [Contribution {
+            tracks: 1..2,
+            min_content: Au::from_px(50),
+            max_content: Au::from_px(80),
+        }];
+        assert_eq!(
+            size_tracks(
+                &definitions,
+                2,
+                &contributions,
+                Some(Au::from_px(500)),
+                Au(0)
+            ),
+            vec![Au::from_px(100), Au::from_px(400)]
+        );
+    }
+
+    #[test]
+    fn test_size_tracks_indefinite_spanning_item() {
+        let definitions = definitions(vec![]);
+        let contributions = [Contribution {
+            tracks: 0..2,
+            min_content: Au::from_px(100),
+            max_content: Au::from_px(200),
+        }];
+        assert_eq!(
+            size_tracks(&definitions, 2, &contributions, None, Au(0)),
+            vec![Au::from_px(100), Au::from_px(100)]
+        );
+    }
+
+    #[test]
+    fn test_track_extents() {
+        let sizes = [Au::from_px(10), Au::from_px(20), Au::from_px(30)];
+        assert_eq!(
+            track_range_extent(&sizes, Au::from_px(5), &(1..3)),
+            (Au::from_px(15), Au::from_px(55))
+        );
+        assert_eq!(total_track_size(&sizes, Au::from_px(5)), Au::from_px(70));
+        assert_eq!(total_track_size(&[], Au::from_px(5)), Au(0));
+    }
+}
@@ -0,0 +1,131 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Frame timing for animated images.
+//!
+//! Every use of an image shares its WebRender image, so an animated image is
+//! advanced by replacing the contents of that image with the next frame. Only
+//! the images in the current display list advance, on the animation ticks of
+//! the compositor.
+
+use crate::display_list::items::{DisplayItem, DisplayList};
+use fnv::FnvHashMap;
+use net_traits::image::base::Image;
+use std::sync::Arc;
+use webrender_api::units::DeviceIntSize;
+use webrender_api::{DirtyRect, ImageData, ImageDescriptor, ImageFormat, ImageKey, Transaction};
+
+struct ImageAnimationState {
+    image: Arc<Image>,
+    /// The index of the frame being displayed.
+    active_frame: usize,
+    /// When the active frame was first displayed, in seconds.
+    frame_start_time: f64,
+    /// Whether the current display list shows the image.
+    visible: bool,
+}
+
+impl ImageAnimationState {
+    fn frame_duration(&self, index: usize) -> f64 {
+        self.image.frames[index]
+            .delay
+            .map_or(0., |delay| delay.as_secs_f64())
+    }
+}
+
+/// The animated images of a document, keyed by their WebRender image.
+#[derive(Default)]
+pub struct AnimatedImages {
+    images: FnvHashMap<ImageKey, ImageAnimationState>,
+}
+
+impl AnimatedImages {
+    /// Starts keeping time for the given image at `now`, if it's animated and
+    /// isn't known yet.
+    pub fn register(&mut self, image: &Arc<Image>, now: f64) {
+        let key = match image.id {
+            Some(key) if image.is_animated() => key,
+            _ => return,
+        };
+        self.images
+            .entry(key)
+            .or_insert_with(|| ImageAnimationState {
+                image: image.clone(),
+                active_frame: 0,
+                frame_start_time: now,
+                visible: false,
+            });
+    }
+
+    /// Notes which animated images the given display list shows, returning
+    /// whether there are any.
+    pub fn update_visibility(&mut self, display_list: &DisplayList) -> bool {
+        if self.images.is_empty() {
+            return false;
+        }
+        for state in self.images.values_mut() {
+            state.visible = false;
+        }
+        for item in &display_list.list {
+            if let DisplayItem::Image(ref image) = *item {
+                if let Some(state) = self.images.get_mut(&image.item.image_key) {
+                    state.visible = true;
+                }
+            }
+        }
+        self.images.values().any(|state| state.visible)
+    }
+
+    /// Switches the visible images to the frames due at `now`, adding the
+    /// updates of their WebRender images to the transaction. Returns whether
+    /// any image changed.
+    pub fn advance(&mut self, now: f64, txn: &mut Transaction) -> bool {
+        let mut changed = false;
+        for (key, state) in self.images.iter_mut() {
+            if !state.visible {
+                continue;
+            }
+
+            let frame_count = state.image.frames.len();
+            let loop_duration: f64 = (0..frame_count)
+                .map(|index| state.frame_duration(index))
+                .sum();
+            if loop_duration <= 0. {
+                continue;
+            }
+
+            // Skip whole loops, which happens when the image wasn't visible
+            // for a while.
+            let mut frame = state.active_frame;
+            let mut frame_start_time = state.frame_start_time;
+            let elapsed = now - frame_start_time;
+            if elapsed >= loop_duration {
+                frame_start_time += (elapsed / loop_duration).floor() * loop_duration;
+            }
+            while now - frame_start_time >= state.frame_duration(frame) {
+                frame_start_time += state.frame_duration(frame);
+                frame = (frame + 1) % frame_count;
+            }
+            state.frame_start_time = frame_start_time;
+            if frame == state.active_frame {
+                continue;
+            }
+            state.active_frame = frame;
+
+            let mut bytes = state.image.frame_bytes(frame).to_vec();
+            let is_opaque = pixels::rgba8_premultiply_inplace(&mut bytes);
+            let descriptor = ImageDescriptor {
+                size: DeviceIntSize::new(state.image.width as i32, state.image.height as i32),
+                stride: None,
+                format: ImageFormat::BGRA8,
+                offset: 0,
+                is_opaque,
+                allow_mipmaps: true,
+            };
+            txn.update_image(*key, descriptor, ImageData::new(bytes), &DirtyRect::All);
+            changed = true;
+        }
+        changed
+    }
+}
@@ -33,6 +33,8 @@ mod flow_list;
 pub mod flow_ref;
 mod fragment;
 mod generated_content;
+mod grid;
+pub mod image_animation;
 pub mod incremental;
 mod inline;
 mod linked_list;
@@ -10,7 +10,7 @@ use crate::fragment::{ScannedTextFragmentInfo, SpecificFragmentInfo, UnscannedTe
 use crate::inline::{InlineFragmentNodeFlags, InlineFragments};
 use crate::linked_list::split_off_head;
 use app_units::Au;
-use gfx::font::{FontMetrics, FontRef, RunMetrics, ShapingFlags, ShapingOptions};
+use gfx::font::{FontFeature, FontMetrics, FontRef, RunMetrics, ShapingFlags, ShapingOptions};
 use gfx::text::glyph::ByteIndex;
 use gfx::text::text_run::TextRun;
 use gfx::text::util::{self, CompressionMode};
@@ -20,12 +20,16 @@ use std::borrow::ToOwned;
 use std::collections::LinkedList;
 use std::mem;
 use std::sync::Arc;
+use style::computed_values::font_kerning::T as FontKerning;
+use style::computed_values::font_variant_position::T as FontVariantPosition;
 use style::computed_values::text_rendering::T as TextRendering;
 use style::computed_values::white_space::T as WhiteSpace;
 use style::computed_values::word_break::T as WordBreak;
 use style::logical_geometry::{LogicalSize, WritingMode};
 use style::properties::style_structs::Font as FontStyleStruct;
 use style::properties::ComputedValues;
+use style::values::computed::font::FontVariantNumeric;
+use style::values::computed::font::{FontVariantEastAsian, FontVariantLigatures};
 use style::values::generics::text::LineHeight;
 use style::values::specified::text::{TextTransform, TextTransformCase};
 use unicode_bidi as bidi;
@@ -181,10 +185,12 @@ impl TextRunScanner {
             let word_spacing;
             let text_rendering;
             let word_break;
+            let features;
             {
                 let in_fragment = self.clump.front().unwrap();
                 let font_style = in_fragment.style().clone_font();
                 let inherited_text_style = in_fragment.style().get_inherited_text();
+                features = font_features_for_style(&font_style);
                 font_group = font_context.font_group(font_style);
                 compression = match in_fragment.white_space() {
                     WhiteSpace::Normal | WhiteSpace::Nowrap => {
@@ -335,11 +341,12 @@ impl TextRunScanner {
                 word_spacing,
                 script: Script::Common,
                 flags: flags,
+                features,
             };
 
             let mut result = Vec::with_capacity(run_info_list.len());
             for run_info in run_info_list {
-                let mut options = options;
+                let mut options = options.clone();
                 options.script = run_info.script;
                 if run_info.bidi_level.is_rtl() {
                     options.flags.insert(ShapingFlags::RTL_FLAG);
@@ -524,6 +531,90 @@ pub fn line_height_from_style(style: &ComputedValues, metrics: &FontMetrics) ->
     }
 }
 
+/// Returns the OpenType features requested by `font-kerning`, the `font-variant-*` properties
+/// and `font-feature-settings`, with the later ones taking precedence.
+///
+/// https://drafts.csswg.org/css-fonts-3/#feature-precedence
+fn font_features_for_style(style: &FontStyleStruct) -> Vec<FontFeature> {
+    const LIGATURES: &[(FontVariantLigatures, &[u8; 4], u32)] = &[
+        (FontVariantLigatures::COMMON_LIGATURES, b"liga", 1),
+        (FontVariantLigatures::COMMON_LIGATURES, b"clig", 1),
+        (FontVariantLigatures::NO_COMMON_LIGATURES, b"liga", 0),
+        (FontVariantLigatures::NO_COMMON_LIGATURES, b"clig", 0),
+        (FontVariantLigatures::DISCRETIONARY_LIGATURES, b"dlig", 1),
+        (FontVariantLigatures::NO_DISCRETIONARY_LIGATURES, b"dlig", 0),
+        (FontVariantLigatures::HISTORICAL_LIGATURES, b"hlig", 1),
+        (FontVariantLigatures::NO_HISTORICAL_LIGATURES, b"hlig", 0),
+        (FontVariantLigatures::CONTEXTUAL, b"calt", 1),
+        (FontVariantLigatures::NO_CONTEXTUAL, b"calt", 0),
+    ];
+    const NUMERIC: &[(FontVariantNumeric, &[u8; 4])] = &[
+        (FontVariantNumeric::LINING_NUMS, b"lnum"),
+        (FontVariantNumeric::OLDSTYLE_NUMS, b"onum"),
+        (FontVariantNumeric::PROPORTIONAL_NUMS, b"pnum"),
+        (FontVariantNumeric::TABULAR_NUMS, b"tnum"),
+        (FontVariantNumeric::DIAGONAL_FRACTIONS, b"frac"),
+        (FontVariantNumeric::STACKED_FRACTIONS, b"afrc"),
+        (FontVariantNumeric::ORDINAL, b"ordn"),
+        (FontVariantNumeric::SLASHED_ZERO, b"zero"),
+    ];
+    const EAST_ASIAN: &[(FontVariantEastAsian, &[u8; 4])] = &[
+        (FontVariantEastAsian::JIS78, b"jp78"),
+        (FontVariantEastAsian::JIS83, b"jp83"),
+        (FontVariantEastAsian::JIS90, b"jp90"),
+        (FontVariantEastAsian::JIS04, b"jp04"),
+        (FontVariantEastAsian::SIMPLIFIED, b"smpl"),
+        (FontVariantEastAsian::TRADITIONAL, b"trad"),
+        (FontVariantEastAsian::FULL_WIDTH, b"fwid"),
+        (FontVariantEastAsian::PROPORTIONAL_WIDTH, b"pwid"),
+        (FontVariantEastAsian::RUBY, b"ruby"),
+    ];
+
+    fn feature(tag: &[u8; 4], value: u32) -> FontFeature {
+        FontFeature::new(u32::from_be_bytes(*tag), value)
+    }
+
+    let mut features = Vec::new();
+    match style.font_kerning {
+        FontKerning::Auto => {},
+        FontKerning::Normal => features.push(feature(b"kern", 1)),
+        FontKerning::None => features.push(feature(b"kern", 0)),
+    }
+
+    let ligatures = style.font_variant_ligatures;
+    if ligatures.contains(FontVariantLigatures::NONE) {
+        for tag in &[b"liga", b"clig", b"dlig", b"hlig", b"calt"] {
+            features.push(feature(tag, 0));
+        }
+    }
+    for &(flag, tag, value) in LIGATURES {
+        if ligatures.contains(flag) {
+            features.push(feature(tag, value));
+        }
+    }
+    for &(flag, tag) in NUMERIC {
+        if style.font_variant_numeric.contains(flag) {
+            features.push(feature(tag, 1));
+        }
+    }
+    for &(flag, tag) in EAST_ASIAN {
+        if style.font_variant_east_asian.contains(flag) {
+            features.push(feature(tag, 1));
+        }
+    }
+
+    match style.font_variant_position {
+        FontVariantPosition::Normal => {},
+        FontVariantPosition::Sub => features.push(feature(b"subs", 1)),
+        FontVariantPosition::Super => features.push(feature(b"sups", 1)),
+    }
+
+    for setting in style.font_feature_settings.0.iter() {
+        features.push(FontFeature::new(setting.tag.0, setting.value.max(0) as u32));
+    }
+    features
+}
+
 fn split_first_fragment_at_newline_if_necessary(fragments: &mut LinkedList<Fragment>) {
     if fragments.is_empty() {
         return;
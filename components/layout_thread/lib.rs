@@ -32,8 +32,9 @@ use euclid::{default::Size2D as UntypedSize2D, Point2D, Rect, Scale, Size2D};
 use fnv::FnvHashMap;
 use fxhash::{FxHashMap, FxHashSet};
 use gfx::font;
-use gfx::font_cache_thread::FontCacheThread;
+use gfx::font_cache_thread::{FontCacheThread, WebFontLoadEvent};
 use gfx::font_context;
+use gfx::font_template::UnicodeRanges;
 use gfx_traits::{node_id_from_scroll_id, Epoch};
 use histogram::Histogram;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
@@ -45,9 +46,10 @@ use layout::context::LayoutContext;
 use layout::context::RegisteredPainter;
 use layout::context::RegisteredPainters;
 use layout::display_list::items::{OpaqueNode, WebRenderImageInfo};
-use layout::display_list::{IndexableText, ToLayout};
+use layout::display_list::{IndexableText, SvgImageCache, ToLayout};
 use layout::flow::{Flow, GetBaseFlow, ImmutableFlowUtils, MutableOwnedFlowUtils};
 use layout::flow_ref::FlowRef;
+use layout::image_animation::AnimatedImages;
 use layout::incremental::{RelayoutMode, SpecialRestyleDamage};
 use layout::layout_debug;
 use layout::parallel;
@@ -83,8 +85,9 @@ use script_layout_interface::message::{QueryMsg, ReflowComplete, ReflowGoal, Scr
 use script_layout_interface::rpc::TextIndexResponse;
 use script_layout_interface::rpc::{LayoutRPC, OffsetParentResponse, StyleResponse};
 use script_layout_interface::wrapper_traits::LayoutNode;
+use script_traits::LayoutMsg as ConstellationMsg;
 use script_traits::Painter;
-use script_traits::{ConstellationControlMsg, LayoutControlMsg, LayoutMsg as ConstellationMsg};
+use script_traits::{AnimationState, ConstellationControlMsg, LayoutControlMsg};
 use script_traits::{DrawAPaintImageResult, IFrameSizeMsg, PaintWorkletError, WindowSizeType};
 use script_traits::{ScrollState, UntrustedNodeAddress};
 use selectors::Element;
@@ -109,7 +112,9 @@ use style::context::{SharedStyleContext, ThreadLocalStyleContextCreationInfo};
 use style::dom::{ShowSubtree, ShowSubtreeDataAndPrimaryValues, TDocument, TElement, TNode};
 use style::driver;
 use style::error_reporting::RustLogReporter;
+use style::font_face::FontFace;
 use style::global_style_data::{GLOBAL_STYLE_DATA, STYLE_THREAD_POOL};
+use style::invalidation::element::relative_selector::invalidate_relative_selector_anchors;
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::logical_geometry::LogicalPoint;
 use style::media_queries::{Device, MediaList, MediaType};
@@ -125,6 +130,7 @@ use style::thread_state::{self, ThreadState};
 use style::timer::Timer;
 use style::traversal::DomTraversal;
 use style::traversal_flags::TraversalFlags;
+use style_traits::media_features::DeviceFeatures;
 use style_traits::CSSPixel;
 use style_traits::DevicePixel;
 use style_traits::SpeculativePainter;
@@ -153,10 +159,10 @@ pub struct LayoutThread {
     pipeline_port: Receiver<LayoutControlMsg>,
 
     /// The port on which we receive messages from the font cache thread.
-    font_cache_receiver: Receiver<()>,
+    font_cache_receiver: Receiver<WebFontLoadEvent>,
 
     /// The channel on which the font cache can send messages to us.
-    font_cache_sender: IpcSender<()>,
+    font_cache_sender: IpcSender<WebFontLoadEvent>,
 
     /// A means of communication with the background hang monitor.
     background_hang_monitor: Box<dyn BackgroundHangMonitor>,
@@ -226,6 +232,16 @@ pub struct LayoutThread {
 
     webrender_image_cache: Arc<RwLock<FnvHashMap<(ServoUrl, UsePlaceholder), WebRenderImageInfo>>>,
 
+    /// The rasterized inline `<svg>` elements of the document.
+    svg_image_cache: Arc<Mutex<SvgImageCache>>,
+
+    /// The animated images of the document.
+    animated_images: Arc<Mutex<AnimatedImages>>,
+
+    /// Whether the current display list shows animated images, which need
+    /// animation ticks.
+    animating_images: Cell<bool>,
+
     /// The executors for paint worklets.
     registered_painters: RegisteredPaintersImpl,
 
@@ -313,6 +329,7 @@ impl LayoutThreadFactory for LayoutThread {
         load_webfonts_synchronously: bool,
         initial_window_size: Size2D<u32, DeviceIndependentPixel>,
         device_pixels_per_px: Option<f32>,
+        device_features: DeviceFeatures,
         dump_display_list: bool,
         dump_display_list_json: bool,
         dump_style_tree: bool,
@@ -362,6 +379,7 @@ impl LayoutThreadFactory for LayoutThread {
                         load_webfonts_synchronously,
                         initial_window_size,
                         device_pixels_per_px,
+                        device_features,
                         dump_display_list,
                         dump_display_list_json,
                         dump_style_tree,
@@ -478,7 +496,7 @@ fn add_font_face_rules(
     guard: &SharedRwLockReadGuard,
     device: &Device,
     font_cache_thread: &FontCacheThread,
-    font_cache_sender: &IpcSender<()>,
+    font_cache_sender: &IpcSender<WebFontLoadEvent>,
     outstanding_web_fonts_counter: &Arc<AtomicUsize>,
     load_webfonts_synchronously: bool,
 ) {
@@ -490,6 +508,7 @@ fn add_font_face_rules(
                 font_cache_thread.add_web_font(
                     font_face.family().clone(),
                     effective_sources,
+                    unicode_range(&font_face),
                     sender.clone(),
                 );
                 receiver.recv().unwrap();
@@ -499,17 +518,36 @@ fn add_font_face_rules(
         stylesheet.effective_font_face_rules(&device, guard, |rule| {
             if let Some(font_face) = rule.font_face() {
                 let effective_sources = font_face.effective_sources();
-                outstanding_web_fonts_counter.fetch_add(1, Ordering::SeqCst);
-                font_cache_thread.add_web_font(
-                    font_face.family().clone(),
-                    effective_sources,
-                    (*font_cache_sender).clone(),
-                );
+                match unicode_range(&font_face) {
+                    // Faces restricted by a `unicode-range` are only fetched once some text
+                    // needs them, so they don't count as outstanding until then.
+                    Some(unicode_range) => font_cache_thread.add_deferred_web_font(
+                        font_face.family().clone(),
+                        effective_sources,
+                        unicode_range,
+                        (*font_cache_sender).clone(),
+                    ),
+                    None => {
+                        outstanding_web_fonts_counter.fetch_add(1, Ordering::SeqCst);
+                        font_cache_thread.add_web_font(
+                            font_face.family().clone(),
+                            effective_sources,
+                            None,
+                            (*font_cache_sender).clone(),
+                        );
+                    },
+                }
             }
         })
     }
 }
 
+fn unicode_range(font_face: &FontFace) -> Option<UnicodeRanges> {
+    font_face
+        .unicode_range()
+        .map(|ranges| UnicodeRanges::new(ranges.iter().map(|range| (range.start, range.end))))
+}
+
 impl LayoutThread {
     /// Creates a new `LayoutThread` structure.
     fn new(
@@ -533,6 +571,7 @@ impl LayoutThread {
         load_webfonts_synchronously: bool,
         initial_window_size: Size2D<u32, DeviceIndependentPixel>,
         device_pixels_per_px: Option<f32>,
+        device_features: DeviceFeatures,
         dump_display_list: bool,
         dump_display_list_json: bool,
         dump_style_tree: bool,
@@ -548,6 +587,7 @@ impl LayoutThread {
             MediaType::screen(),
             initial_window_size.to_f32() * Scale::new(1.0),
             Scale::new(device_pixels_per_px.unwrap_or(1.0)),
+            device_features,
         );
 
         // Create the channel on which new animations can be sent.
@@ -611,6 +651,11 @@ impl LayoutThread {
                 element_inner_text_response: String::new(),
             })),
             webrender_image_cache: Arc::new(RwLock::new(FnvHashMap::default())),
+            svg_image_cache: Arc::new(Mutex::new(SvgImageCache::new(
+                webrender_api_sender.create_api(),
+            ))),
+            animated_images: Default::default(),
+            animating_images: Cell::new(false),
             timer: if pref!(layout.animations.test.enabled) {
                 Timer::test_mode()
             } else {
@@ -675,6 +720,8 @@ impl LayoutThread {
             image_cache: self.image_cache.clone(),
             font_cache_thread: Mutex::new(self.font_cache_thread.clone()),
             webrender_image_cache: self.webrender_image_cache.clone(),
+            svg_image_cache: self.svg_image_cache.clone(),
+            animated_images: self.animated_images.clone(),
             pending_images: if script_initiated_layout {
                 Some(Mutex::new(vec![]))
             } else {
@@ -723,7 +770,7 @@ impl LayoutThread {
         enum Request {
             FromPipeline(LayoutControlMsg),
             FromScript(Msg),
-            FromFontCache,
+            FromFontCache(WebFontLoadEvent),
         }
 
         // Notify the background-hang-monitor we are waiting for an event.
@@ -732,7 +779,7 @@ impl LayoutThread {
         let request = select! {
             recv(self.pipeline_port) -> msg => Request::FromPipeline(msg.unwrap()),
             recv(self.port) -> msg => Request::FromScript(msg.unwrap()),
-            recv(self.font_cache_receiver) -> msg => { msg.unwrap(); Request::FromFontCache }
+            recv(self.font_cache_receiver) -> msg => Request::FromFontCache(msg.unwrap()),
         };
 
         self.busy.store(true, Ordering::Relaxed);
@@ -758,9 +805,11 @@ impl LayoutThread {
                 true
             },
             Request::FromScript(msg) => self.handle_request_helper(msg, possibly_locked_rw_data),
-            Request::FromFontCache => {
+            Request::FromFontCache(event) => {
                 let _rw_data = possibly_locked_rw_data.lock();
-                self.outstanding_web_fonts.fetch_sub(1, Ordering::SeqCst);
+                if event == WebFontLoadEvent::Loaded {
+                    self.outstanding_web_fonts.fetch_sub(1, Ordering::SeqCst);
+                }
                 font_context::invalidate_font_caches();
                 self.script_chan
                     .send(ConstellationControlMsg::WebFontLoaded(self.id))
@@ -954,6 +1003,7 @@ impl LayoutThread {
             self.load_webfonts_synchronously,
             self.initial_window_size,
             self.device_pixels_per_px,
+            *self.stylist.device().device_features(),
             self.dump_display_list,
             self.dump_display_list_json,
             self.dump_style_tree,
@@ -1204,6 +1254,13 @@ impl LayoutThread {
                             IndexableText::default(),
                         );
                         rw_data.display_list = Some(build_state.to_display_list());
+                        self.svg_image_cache.lock().unwrap().evict_unused_images();
+                        let animating_images = self
+                            .animated_images
+                            .lock()
+                            .unwrap()
+                            .update_visibility(rw_data.display_list.as_ref().unwrap());
+                        self.set_animating_images(animating_images);
                     }
                 }
 
@@ -1253,6 +1310,10 @@ impl LayoutThread {
                     .maybe_observe_paint_time(self, epoch, is_contentful.0);
 
                 let mut txn = webrender_api::Transaction::new();
+                self.svg_image_cache
+                    .lock()
+                    .unwrap()
+                    .delete_unused_images(&mut txn);
                 txn.set_display_list(
                     webrender_api::Epoch(epoch.0),
                     None,
@@ -1348,6 +1409,7 @@ impl LayoutThread {
 
         let initial_viewport = data.window_size.initial_viewport;
         let device_pixel_ratio = data.window_size.device_pixel_ratio;
+        let device_features = data.window_size.device_features;
         let old_viewport_size = self.viewport_size;
         let current_screen_size = Size2D::new(
             Au::from_f32_px(initial_viewport.width),
@@ -1368,7 +1430,12 @@ impl LayoutThread {
         };
 
         let had_used_viewport_units = self.stylist.device().used_viewport_units();
-        let device = Device::new(MediaType::screen(), initial_viewport, device_pixel_ratio);
+        let device = Device::new(
+            MediaType::screen(),
+            initial_viewport,
+            device_pixel_ratio,
+            device_features,
+        );
         let sheet_origins_affected_by_device_change = self.stylist.set_device(device, &guards);
 
         self.stylist
@@ -1494,6 +1561,12 @@ impl LayoutThread {
 
         self.stylist.flush(&guards, Some(element), Some(&map));
 
+        // Changes to elements can affect the `:has()` selectors of their
+        // ancestors and previous siblings, which the traversal never revisits.
+        for el in &elements_with_snapshot {
+            invalidate_relative_selector_anchors(*el, &map, &self.stylist);
+        }
+
         // Create a layout context for use throughout the following passes.
         let mut layout_context = self.build_layout_context(guards.clone(), true, &map);
 
@@ -1717,6 +1790,23 @@ impl LayoutThread {
         rw_data.scroll_offsets = layout_scroll_states
     }
 
+    /// Asks for animation ticks while the display list shows animated images,
+    /// unless CSS animations already do.
+    fn set_animating_images(&self, animating_images: bool) {
+        if self.animating_images.replace(animating_images) == animating_images {
+            return;
+        }
+        let animation_state = if animating_images || !self.running_animations.read().is_empty() {
+            AnimationState::AnimationsPresent
+        } else {
+            AnimationState::NoAnimationsPresent
+        };
+        let msg = ConstellationMsg::ChangeRunningAnimationsState(self.id, animation_state);
+        if let Err(e) = self.constellation_chan.send(msg) {
+            warn!("Layout animation state to constellation failed ({}).", e);
+        }
+    }
+
     fn tick_all_animations<'a, 'b>(&mut self, possibly_locked_rw_data: &mut RwData<'a, 'b>) {
         let mut rw_data = possibly_locked_rw_data.lock();
         self.tick_animations(&mut rw_data);
@@ -1730,6 +1820,20 @@ impl LayoutThread {
             );
         }
 
+        // Animated images only need their WebRender images updated.
+        if self.animating_images.get() {
+            let mut txn = webrender_api::Transaction::new();
+            let now = self.timer.seconds();
+            if self.animated_images.lock().unwrap().advance(now, &mut txn) {
+                txn.generate_frame();
+                self.webrender_api
+                    .send_transaction(self.webrender_document, txn);
+            }
+        }
+        if self.running_animations.read().is_empty() {
+            return;
+        }
+
         if let Some(mut root_flow) = self.root_flow.borrow().clone() {
             let reflow_info = Reflow {
                 page_clip_rect: Rect::max_rect(),
@@ -1805,6 +1909,7 @@ impl LayoutThread {
                 &self.new_animations_receiver,
                 self.id,
                 &self.timer,
+                self.animating_images.get(),
             );
         }
 
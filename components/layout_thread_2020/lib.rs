@@ -30,8 +30,9 @@ use euclid::{default::Size2D as UntypedSize2D, Point2D, Rect, Scale, Size2D};
 use fnv::FnvHashMap;
 use fxhash::FxHashMap;
 use gfx::font;
-use gfx::font_cache_thread::FontCacheThread;
+use gfx::font_cache_thread::{FontCacheThread, WebFontLoadEvent};
 use gfx::font_context;
+use gfx::font_template::UnicodeRanges;
 use gfx_traits::{node_id_from_scroll_id, Epoch};
 use histogram::Histogram;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
@@ -89,7 +90,9 @@ use style::context::{SharedStyleContext, ThreadLocalStyleContextCreationInfo};
 use style::dom::{ShowSubtree, TDocument, TElement, TNode};
 use style::driver;
 use style::error_reporting::RustLogReporter;
+use style::font_face::FontFace;
 use style::global_style_data::{GLOBAL_STYLE_DATA, STYLE_THREAD_POOL};
+use style::invalidation::element::relative_selector::invalidate_relative_selector_anchors;
 use style::invalidation::element::restyle_hints::RestyleHint;
 use style::media_queries::{Device, MediaList, MediaType};
 use style::properties::PropertyId;
@@ -103,6 +106,7 @@ use style::thread_state::{self, ThreadState};
 use style::timer::Timer;
 use style::traversal::DomTraversal;
 use style::traversal_flags::TraversalFlags;
+use style_traits::media_features::DeviceFeatures;
 use style_traits::CSSPixel;
 use style_traits::DevicePixel;
 use style_traits::SpeculativePainter;
@@ -131,10 +135,10 @@ pub struct LayoutThread {
     pipeline_port: Receiver<LayoutControlMsg>,
 
     /// The port on which we receive messages from the font cache thread.
-    font_cache_receiver: Receiver<()>,
+    font_cache_receiver: Receiver<WebFontLoadEvent>,
 
     /// The channel on which the font cache can send messages to us.
-    font_cache_sender: IpcSender<()>,
+    font_cache_sender: IpcSender<WebFontLoadEvent>,
 
     /// A means of communication with the background hang monitor.
     background_hang_monitor: Box<dyn BackgroundHangMonitor>,
@@ -248,6 +252,7 @@ impl LayoutThreadFactory for LayoutThread {
         load_webfonts_synchronously: bool,
         initial_window_size: Size2D<u32, DeviceIndependentPixel>,
         device_pixels_per_px: Option<f32>,
+        device_features: DeviceFeatures,
         _dump_display_list: bool,
         _dump_display_list_json: bool,
         _dump_style_tree: bool,
@@ -296,6 +301,7 @@ impl LayoutThreadFactory for LayoutThread {
                         load_webfonts_synchronously,
                         initial_window_size,
                         device_pixels_per_px,
+                        device_features,
                         relayout_event,
                     );
 
@@ -405,7 +411,7 @@ fn add_font_face_rules(
     guard: &SharedRwLockReadGuard,
     device: &Device,
     font_cache_thread: &FontCacheThread,
-    font_cache_sender: &IpcSender<()>,
+    font_cache_sender: &IpcSender<WebFontLoadEvent>,
     outstanding_web_fonts_counter: &Arc<AtomicUsize>,
     load_webfonts_synchronously: bool,
 ) {
@@ -417,6 +423,7 @@ fn add_font_face_rules(
                 font_cache_thread.add_web_font(
                     font_face.family().clone(),
                     effective_sources,
+                    unicode_range(&font_face),
                     sender.clone(),
                 );
                 receiver.recv().unwrap();
@@ -426,17 +433,36 @@ fn add_font_face_rules(
         stylesheet.effective_font_face_rules(&device, guard, |rule| {
             if let Some(font_face) = rule.font_face() {
                 let effective_sources = font_face.effective_sources();
-                outstanding_web_fonts_counter.fetch_add(1, Ordering::SeqCst);
-                font_cache_thread.add_web_font(
-                    font_face.family().clone(),
-                    effective_sources,
-                    (*font_cache_sender).clone(),
-                );
+                match unicode_range(&font_face) {
+                    // Faces restricted by a `unicode-range` are only fetched once some text
+                    // needs them, so they don't count as outstanding until then.
+                    Some(unicode_range) => font_cache_thread.add_deferred_web_font(
+                        font_face.family().clone(),
+                        effective_sources,
+                        unicode_range,
+                        (*font_cache_sender).clone(),
+                    ),
+                    None => {
+                        outstanding_web_fonts_counter.fetch_add(1, Ordering::SeqCst);
+                        font_cache_thread.add_web_font(
+                            font_face.family().clone(),
+                            effective_sources,
+                            None,
+                            (*font_cache_sender).clone(),
+                        );
+                    },
+                }
             }
         })
     }
 }
 
+fn unicode_range(font_face: &FontFace) -> Option<UnicodeRanges> {
+    font_face
+        .unicode_range()
+        .map(|ranges| UnicodeRanges::new(ranges.iter().map(|range| (range.start, range.end))))
+}
+
 impl LayoutThread {
     /// Creates a new `LayoutThread` structure.
     fn new(
@@ -459,6 +485,7 @@ impl LayoutThread {
         load_webfonts_synchronously: bool,
         initial_window_size: Size2D<u32, DeviceIndependentPixel>,
         device_pixels_per_px: Option<f32>,
+        device_features: DeviceFeatures,
         relayout_event: bool,
     ) -> LayoutThread {
         // The device pixel ratio is incorrect (it does not have the hidpi value),
@@ -467,6 +494,7 @@ impl LayoutThread {
             MediaType::screen(),
             initial_window_size.to_f32() * Scale::new(1.0),
             Scale::new(device_pixels_per_px.unwrap_or(1.0)),
+            device_features,
         );
 
         // Create the channel on which new animations can be sent.
@@ -612,7 +640,7 @@ impl LayoutThread {
         enum Request {
             FromPipeline(LayoutControlMsg),
             FromScript(Msg),
-            FromFontCache,
+            FromFontCache(WebFontLoadEvent),
         }
 
         // Notify the background-hang-monitor we are waiting for an event.
@@ -621,7 +649,7 @@ impl LayoutThread {
         let request = select! {
             recv(self.pipeline_port) -> msg => Request::FromPipeline(msg.unwrap()),
             recv(self.port) -> msg => Request::FromScript(msg.unwrap()),
-            recv(self.font_cache_receiver) -> msg => { msg.unwrap(); Request::FromFontCache }
+            recv(self.font_cache_receiver) -> msg => Request::FromFontCache(msg.unwrap()),
         };
 
         self.busy.store(true, Ordering::Relaxed);
@@ -647,9 +675,11 @@ impl LayoutThread {
                 true
             },
             Request::FromScript(msg) => self.handle_request_helper(msg, possibly_locked_rw_data),
-            Request::FromFontCache => {
+            Request::FromFontCache(event) => {
                 let _rw_data = possibly_locked_rw_data.lock();
-                self.outstanding_web_fonts.fetch_sub(1, Ordering::SeqCst);
+                if event == WebFontLoadEvent::Loaded {
+                    self.outstanding_web_fonts.fetch_sub(1, Ordering::SeqCst);
+                }
                 font_context::invalidate_font_caches();
                 self.script_chan
                     .send(ConstellationControlMsg::WebFontLoaded(self.id))
@@ -820,6 +850,7 @@ impl LayoutThread {
             self.load_webfonts_synchronously,
             self.initial_window_size,
             self.device_pixels_per_px,
+            *self.stylist.device().device_features(),
             false, // dump_display_list
             false, // dump_display_list_json
             false, // dump_style_tree
@@ -981,6 +1012,7 @@ impl LayoutThread {
 
         let initial_viewport = data.window_size.initial_viewport;
         let device_pixel_ratio = data.window_size.device_pixel_ratio;
+        let device_features = data.window_size.device_features;
         let old_viewport_size = self.viewport_size;
         let current_screen_size = Size2D::new(
             Au::from_f32_px(initial_viewport.width),
@@ -1001,7 +1033,12 @@ impl LayoutThread {
         };
 
         let had_used_viewport_units = self.stylist.device().used_viewport_units();
-        let device = Device::new(MediaType::screen(), initial_viewport, device_pixel_ratio);
+        let device = Device::new(
+            MediaType::screen(),
+            initial_viewport,
+            device_pixel_ratio,
+            device_features,
+        );
         let sheet_origins_affected_by_device_change = self.stylist.set_device(device, &guards);
 
         self.stylist
@@ -1121,6 +1158,12 @@ impl LayoutThread {
 
         self.stylist.flush(&guards, Some(element), Some(&map));
 
+        // Changes to elements can affect the `:has()` selectors of their
+        // ancestors and previous siblings, which the traversal never revisits.
+        for el in &elements_with_snapshot {
+            invalidate_relative_selector_anchors(*el, &map, &self.stylist);
+        }
+
         // Create a layout context for use throughout the following passes.
         let mut layout_context = self.build_layout_context(guards.clone(), &map);
 
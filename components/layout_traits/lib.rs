@@ -24,6 +24,7 @@ use servo_geometry::DeviceIndependentPixel;
 use servo_url::ServoUrl;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use style_traits::media_features::DeviceFeatures;
 
 // A static method creating a layout thread
 // Here to remove the compositor -> layout dependency
@@ -50,6 +51,7 @@ pub trait LayoutThreadFactory {
         load_webfonts_synchronously: bool,
         initial_window_size: Size2D<u32, DeviceIndependentPixel>,
         device_pixels_per_px: Option<f32>,
+        device_features: DeviceFeatures,
         dump_display_list: bool,
         dump_display_list_json: bool,
         dump_style_tree: bool,
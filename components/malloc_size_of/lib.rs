@@ -738,6 +738,10 @@ where
             Component::Slotted(ref selector) | Component::Host(Some(ref selector)) => {
                 selector.size_of(ops)
             },
+            Component::Is(ref selectors) | Component::Where(ref selectors) => {
+                selectors.size_of(ops)
+            },
+            Component::Has(ref relative_selectors) => relative_selectors.size_of(ops),
             Component::PseudoElement(ref pseudo) => (*pseudo).size_of(ops),
             Component::Combinator(..) |
             Component::ExplicitAnyNamespace |
@@ -764,11 +768,23 @@ where
             Component::FirstOfType |
             Component::LastOfType |
             Component::OnlyOfType |
-            Component::Host(None) => 0,
+            Component::Host(None) |
+            Component::RelativeSelectorAnchor => 0,
         }
     }
 }
 
+impl<Impl: selectors::parser::SelectorImpl> MallocSizeOf
+    for selectors::parser::RelativeSelector<Impl>
+where
+    Impl::NonTSPseudoClass: MallocSizeOf,
+    Impl::PseudoElement: MallocSizeOf,
+{
+    fn size_of(&self, ops: &mut MallocSizeOfOps) -> usize {
+        self.selector.size_of(ops)
+    }
+}
+
 impl<Impl: selectors::parser::SelectorImpl> MallocSizeOf
     for selectors::attr::AttrSelectorWithOptionalNamespace<Impl>
 {
@@ -825,6 +841,8 @@ malloc_size_of_is_0!(std::sync::atomic::AtomicBool);
 malloc_size_of_is_0!(std::sync::atomic::AtomicIsize);
 malloc_size_of_is_0!(std::sync::atomic::AtomicUsize);
 
+malloc_size_of_is_0!(std::time::Duration);
+
 malloc_size_of_is_0!(Range<u8>, Range<u16>, Range<u32>, Range<u64>, Range<usize>);
 malloc_size_of_is_0!(Range<i8>, Range<i16>, Range<i32>, Range<i64>, Range<isize>);
 malloc_size_of_is_0!(Range<f32>, Range<f64>);
@@ -3,13 +3,17 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::hosts::replace_host;
-use hyper::client::connect::{Connect, Destination};
+use crate::proxy::{tunnel, ProxyConfig};
+use futures::future;
+use hyper::client::connect::{Connect, Connected, Destination};
 use hyper::client::HttpConnector as HyperHttpConnector;
 use hyper::rt::Future;
 use hyper::{Body, Client};
 use hyper_openssl::HttpsConnector;
 use openssl::ssl::{SslConnector, SslConnectorBuilder, SslMethod, SslOptions};
 use openssl::x509;
+use std::io;
+use tokio::net::TcpStream;
 use tokio::prelude::future::Executor;
 
 pub const BUF_SIZE: usize = 32768;
@@ -28,16 +32,49 @@ impl HttpConnector {
 }
 
 impl Connect for HttpConnector {
-    type Transport = <HyperHttpConnector as Connect>::Transport;
-    type Error = <HyperHttpConnector as Connect>::Error;
-    type Future = <HyperHttpConnector as Connect>::Future;
+    type Transport = TcpStream;
+    type Error = io::Error;
+    type Future = Box<dyn Future<Item = (TcpStream, Connected), Error = io::Error> + Send>;
 
     fn connect(&self, dest: Destination) -> Self::Future {
-        // Perform host replacement when making the actual TCP connection.
-        let mut new_dest = dest.clone();
-        let addr = replace_host(dest.host());
-        new_dest.set_host(&*addr).unwrap();
-        self.inner.connect(new_dest)
+        let proxy_config = ProxyConfig::from_prefs();
+        let proxy = match proxy_config.proxy_for(dest.scheme(), dest.host(), dest.port()) {
+            Some(proxy) => proxy.clone(),
+            None => {
+                // Perform host replacement when making the actual TCP connection.
+                let mut new_dest = dest.clone();
+                let addr = replace_host(dest.host());
+                new_dest.set_host(&*addr).unwrap();
+                return Box::new(self.inner.connect(new_dest));
+            },
+        };
+
+        // Connect to the proxy instead of the destination.
+        let mut proxy_dest = dest.clone();
+        let proxy_host = replace_host(proxy.host_str().unwrap());
+        if let Err(e) = proxy_dest
+            .set_scheme("http")
+            .and_then(|_| proxy_dest.set_host(&*proxy_host))
+        {
+            return Box::new(future::err(io::Error::new(io::ErrorKind::InvalidInput, e)));
+        }
+        proxy_dest.set_port(proxy.port_or_known_default());
+        let connecting = self.inner.connect(proxy_dest);
+
+        if dest.scheme() == "https" {
+            // Tunnel through the proxy, TLS is then negotiated with the destination itself.
+            let authority = format!(
+                "{}:{}",
+                replace_host(dest.host()),
+                dest.port().unwrap_or(443)
+            );
+            Box::new(connecting.and_then(move |(stream, connected)| {
+                tunnel(stream, authority, &proxy).map(|stream| (stream, connected))
+            }))
+        } else {
+            // Requests written to the proxy use the absolute-form of their target.
+            Box::new(connecting.map(|(stream, connected)| (stream, connected.proxy(true))))
+        }
     }
 }
 
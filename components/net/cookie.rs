@@ -5,14 +5,46 @@
 //! Implementation of cookie creation and matching as specified by
 //! http://tools.ietf.org/html/rfc6265
 
+use cookie_rs::SameSite;
+use hyper::Method;
 use hyper_serde::{self, Serde};
 use net_traits::pub_domains::is_pub_domain;
-use net_traits::CookieSource;
+use net_traits::{CookieSource, SiteForCookies};
 use servo_url::ServoUrl;
 use std::borrow::ToOwned;
 use std::net::{Ipv4Addr, Ipv6Addr};
 use time::{at, now, Duration, Tm};
 
+/// How same-site the context a cookie is read or written from is, which
+/// decides the `SameSite` cookies it can see.
+///
+/// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.3.7.1>
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SameSiteContext {
+    /// A same-site context, which sees every cookie.
+    SameSite,
+    /// A cross-site top-level navigation with a safe method, which also sees
+    /// `SameSite=Lax` cookies.
+    LaxCrossSite,
+    /// Any other cross-site context, which only sees cookies without a
+    /// `SameSite` restriction.
+    CrossSite,
+}
+
+impl SameSiteContext {
+    pub fn new(url: &ServoUrl, site_for_cookies: &SiteForCookies, method: &Method) -> Self {
+        if site_for_cookies.is_same_site(url) {
+            return SameSiteContext::SameSite;
+        }
+        match *site_for_cookies {
+            SiteForCookies::TopLevelNavigation(_) if method.is_safe() => {
+                SameSiteContext::LaxCrossSite
+            },
+            _ => SameSiteContext::CrossSite,
+        }
+    }
+}
+
 /// A stored cookie that wraps the definition in cookie-rs. This is used to implement
 /// various behaviours defined in the spec that rely on an associated request URL,
 /// which cookie-rs and hyper's header parsing do not support.
@@ -139,6 +171,40 @@ impl Cookie {
         })
     }
 
+    /// The cookie's `SameSite` attribute, `None` when it is absent.
+    pub fn same_site(&self) -> Option<SameSite> {
+        match self.cookie.same_site() {
+            Some(SameSite::Strict) => Some(SameSite::Strict),
+            Some(SameSite::Lax) => Some(SameSite::Lax),
+            _ => None,
+        }
+    }
+
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.3.7.1>
+    pub fn visible_in_context(&self, context: SameSiteContext) -> bool {
+        match (self.same_site(), context) {
+            (None, _) | (_, SameSiteContext::SameSite) => true,
+            (Some(SameSite::Lax), SameSiteContext::LaxCrossSite) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether this cookie may be stored for `url` on behalf of
+    /// `site_for_cookies`: cookies with a `SameSite` attribute are ignored
+    /// when set from a cross-site context. A top-level navigation is its own
+    /// site for cookies, so its responses may always set them.
+    ///
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.4>, step 13
+    pub fn settable_from(&self, url: &ServoUrl, site_for_cookies: &SiteForCookies) -> bool {
+        if self.same_site().is_none() {
+            return true;
+        }
+        match *site_for_cookies {
+            SiteForCookies::TopLevelNavigation(_) => true,
+            _ => site_for_cookies.is_same_site(url),
+        }
+    }
+
     pub fn touch(&mut self) {
         self.last_access = now();
     }
@@ -5,7 +5,7 @@
 //! Implementation of cookie storage as specified in
 //! http://tools.ietf.org/html/rfc6265
 
-use crate::cookie::Cookie;
+use crate::cookie::{Cookie, SameSiteContext};
 use net_traits::pub_domains::reg_suffix;
 use net_traits::CookieSource;
 use servo_url::ServoUrl;
@@ -145,7 +145,12 @@ impl CookieStorage {
     }
 
     // http://tools.ietf.org/html/rfc6265#section-5.4
-    pub fn cookies_for_url(&mut self, url: &ServoUrl, source: CookieSource) -> Option<String> {
+    pub fn cookies_for_url(
+        &mut self,
+        url: &ServoUrl,
+        source: CookieSource,
+        context: SameSiteContext,
+    ) -> Option<String> {
         let filterer = |c: &&mut Cookie| -> bool {
             info!(
                 " === SENT COOKIE : {} {} {:?} {:?}",
@@ -159,7 +164,7 @@ impl CookieStorage {
                 c.appropriate_for_url(url, source)
             );
             // Step 1
-            c.appropriate_for_url(url, source)
+            c.appropriate_for_url(url, source) && c.visible_in_context(context)
         };
         // Step 2
         let domain = reg_host(url.host_str().unwrap_or(""));
@@ -14,14 +14,15 @@ use headers::{AccessControlExposeHeaders, ContentType, HeaderMapExt, Range};
 use http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use hyper::Method;
 use hyper::StatusCode;
-use ipc_channel::ipc::IpcReceiver;
+use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use mime::{self, Mime};
 use net_traits::blob_url_store::{parse_blob_url, BlobURLStoreError};
+use net_traits::csp::CheckResult;
 use net_traits::filemanager_thread::RelativePos;
 use net_traits::request::{CredentialsMode, Destination, Referrer, Request, RequestMode};
 use net_traits::request::{Origin, ResponseTainting, Window};
 use net_traits::response::{Response, ResponseBody, ResponseType};
-use net_traits::ResourceAttribute;
+use net_traits::{CustomResponseMediator, ResourceAttribute};
 use net_traits::{FetchTaskTarget, NetworkError, ReferrerPolicy, ResourceFetchTiming};
 use servo_url::ServoUrl;
 use std::borrow::Cow;
@@ -50,6 +51,7 @@ pub struct FetchContext {
     pub state: Arc<HttpState>,
     pub user_agent: Cow<'static, str>,
     pub devtools_chan: Option<Sender<DevtoolsControlMsg>>,
+    pub swmanager_chan: Option<IpcSender<CustomResponseMediator>>,
     pub filemanager: FileManager,
     pub cancellation_listener: Arc<Mutex<CancellationListener>>,
     pub timing: Arc<Mutex<ResourceFetchTiming>>,
@@ -162,7 +164,8 @@ pub fn main_fetch(
     }
 
     // Step 3.
-    // TODO: handle content security policy violations.
+    // Report-only violations are reported together with enforced ones in step 5,
+    // since both come out of the same check.
 
     // Step 4.
     // TODO: handle upgrade to a potentially secure URL.
@@ -174,7 +177,17 @@ pub fn main_fetch(
         )));
     }
     // TODO: handle blocking as mixed content.
-    // TODO: handle blocking by content security policy.
+    if let Some(ref csp_list) = request.csp_list {
+        let (check_result, violations) = csp_list.should_request_be_blocked(request);
+        if !violations.is_empty() {
+            target.process_csp_violations(request, violations);
+        }
+        if check_result == CheckResult::Blocked {
+            response = Some(Response::network_error(NetworkError::Internal(
+                "Blocked by Content-Security-Policy".into(),
+            )));
+        }
+    }
 
     // Step 6
     // TODO: handle request's client's referrer policy.
@@ -356,7 +369,8 @@ pub fn main_fetch(
 
         // Step 17.
         // TODO: handle blocking as mixed content.
-        // TODO: handle blocking by content security policy.
+        // Blocking by content security policy already happened in step 5, which
+        // runs again for every redirect.
         let blocked_error_response;
         let internal_response = if should_replace_with_nosniff_error {
             // Defer rebinding result
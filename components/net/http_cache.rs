@@ -5,9 +5,10 @@
 #![deny(missing_docs)]
 
 //! A memory cache implementing the logic specified in <http://tools.ietf.org/html/rfc7234>
-//! and <http://tools.ietf.org/html/rfc7232>.
+//! and <http://tools.ietf.org/html/rfc7232>, optionally backed by a persistent store.
 
 use crate::fetch::methods::{Data, DoneChannel};
+use crate::http_cache_store::{HttpCacheStore, StoredResource};
 use crossbeam_channel::{unbounded, Sender};
 use headers::{
     CacheControl, ContentRange, Expires, HeaderMapExt, LastModified, Pragma, Range, Vary,
@@ -26,6 +27,7 @@ use servo_arc::Arc;
 use servo_url::ServoUrl;
 use std::collections::HashMap;
 use std::ops::Bound;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 use std::time::SystemTime;
@@ -63,6 +65,8 @@ struct CachedResource {
 
 #[derive(Clone, MallocSizeOf)]
 struct MeasurableCachedResource {
+    /// Identifier of the resource in the persistent store.
+    id: String,
     metadata: CachedMetadata,
     location_url: Option<Result<ServoUrl, String>>,
     https_state: HttpsState,
@@ -125,6 +129,9 @@ pub struct CachedResponse {
 pub struct HttpCache {
     /// cached responses.
     entries: HashMap<CacheKey, Vec<CachedResource>>,
+    /// The persistent store backing this cache, if any.
+    #[ignore_malloc_size_of = "Lives on disk"]
+    store: Option<Mutex<HttpCacheStore>>,
 }
 
 /// Determine if a given response is cacheable based on the initial metadata received.
@@ -290,6 +297,114 @@ fn get_expiry_adjustment_from_request_headers(request: &Request, expires: Durati
     expires
 }
 
+/// Calculating Secondary Keys with Vary <https://tools.ietf.org/html/rfc7234#section-4.1>
+fn vary_matches(
+    cached_headers: &HeaderMap,
+    original_request_headers: &HeaderMap,
+    request_headers: &HeaderMap,
+) -> bool {
+    let vary_value = match cached_headers.typed_get::<Vary>() {
+        Some(vary_value) => vary_value,
+        None => return true,
+    };
+    if vary_value.is_any() {
+        return false;
+    }
+    // For every header name found in the Vary header of the stored response.
+    for vary_val in vary_value.iter_strs() {
+        match request_headers.get(vary_val) {
+            Some(header_data) => {
+                // If the header is present in the request.
+                if let Some(original_header_data) = original_request_headers.get(vary_val) {
+                    // Check that the value of the nominated header field,
+                    // in the original request, matches the value in the current request.
+                    if original_header_data != header_data {
+                        return false;
+                    }
+                }
+            },
+            None => {
+                // If a header field is absent from a request,
+                // it can only match a stored response if those headers,
+                // were also absent in the original request.
+                if original_request_headers.get(vary_val).is_some() {
+                    return false;
+                }
+            },
+        }
+    }
+    true
+}
+
+/// Convert a CachedResource into the record written to the persistent store.
+fn stored_resource_from_cached(
+    entry_key: &CacheKey,
+    cached_resource: &CachedResource,
+) -> StoredResource {
+    let metadata = &cached_resource.data.metadata;
+    StoredResource {
+        id: cached_resource.data.id.clone(),
+        url: entry_key.url.clone(),
+        request_headers: cached_resource.request_headers.lock().unwrap().clone(),
+        response_headers: metadata.headers.lock().unwrap().clone(),
+        final_url: metadata.data.final_url.clone(),
+        content_type: metadata.data.content_type.clone(),
+        charset: metadata.data.charset.clone(),
+        metadata_status: metadata.data.status.clone(),
+        location_url: cached_resource.data.location_url.clone(),
+        https_state: cached_resource.data.https_state,
+        status: cached_resource
+            .data
+            .status
+            .as_ref()
+            .map(|&(code, ref reason)| (code.as_u16(), reason.clone())),
+        raw_status: cached_resource.data.raw_status.clone(),
+        url_list: cached_resource.data.url_list.clone(),
+        expires: cached_resource.data.expires.num_seconds(),
+        last_validated: cached_resource.data.last_validated.to_timespec().sec,
+        has_body: match *cached_resource.body.lock().unwrap() {
+            ResponseBody::Empty => false,
+            ResponseBody::Receiving(..) | ResponseBody::Done(..) => true,
+        },
+    }
+}
+
+/// Re-create a CachedResource from a record read from the persistent store.
+fn cached_resource_from_stored(record: StoredResource, body: Vec<u8>) -> CachedResource {
+    let body = if record.has_body {
+        ResponseBody::Done(body)
+    } else {
+        ResponseBody::Empty
+    };
+    CachedResource {
+        request_headers: Arc::new(Mutex::new(record.request_headers)),
+        body: Arc::new(Mutex::new(body)),
+        aborted: Arc::new(AtomicBool::new(false)),
+        awaiting_body: Arc::new(Mutex::new(vec![])),
+        data: Measurable(MeasurableCachedResource {
+            id: record.id,
+            metadata: CachedMetadata {
+                headers: Arc::new(Mutex::new(record.response_headers)),
+                data: Measurable(MeasurableCachedMetadata {
+                    final_url: record.final_url,
+                    content_type: record.content_type,
+                    charset: record.charset,
+                    status: record.metadata_status,
+                }),
+            },
+            location_url: record.location_url,
+            https_state: record.https_state,
+            status: record.status.and_then(|(code, reason)| {
+                StatusCode::from_u16(code).ok().map(|code| (code, reason))
+            }),
+            raw_status: record.raw_status,
+            url_list: record.url_list,
+            expires: Duration::seconds(record.expires),
+            last_validated: time::at(Timespec::new(record.last_validated, 0)),
+        }),
+    }
+}
+
 /// Create a CachedResponse from a request and a CachedResource.
 fn create_cached_response(
     request: &Request,
@@ -349,6 +464,7 @@ fn create_resource_with_bytes_from_resource(
         aborted: Arc::new(AtomicBool::new(false)),
         awaiting_body: Arc::new(Mutex::new(vec![])),
         data: Measurable(MeasurableCachedResource {
+            id: resource.data.id.clone(),
             metadata: resource.data.metadata.clone(),
             location_url: resource.data.location_url.clone(),
             https_state: resource.data.https_state.clone(),
@@ -560,6 +676,100 @@ impl HttpCache {
     pub fn new() -> HttpCache {
         HttpCache {
             entries: HashMap::new(),
+            store: None,
+        }
+    }
+
+    /// Create a new memory cache instance, backed by a persistent store in the `root` directory
+    /// holding at most `max_size` bytes. Resources stored there by a previous instance are used
+    /// to construct responses. Falls back to a memory-only cache if the store cannot be opened.
+    pub fn new_with_store(root: PathBuf, max_size: u64) -> HttpCache {
+        let store = match HttpCacheStore::open(root, max_size) {
+            Ok(store) => Some(Mutex::new(store)),
+            Err(e) => {
+                warn!("Could not open the persistent http cache: {}", e);
+                None
+            },
+        };
+        HttpCache {
+            entries: HashMap::new(),
+            store,
+        }
+    }
+
+    /// Load the resources for a key from the persistent store.
+    fn load_from_store(&self, entry_key: &CacheKey) -> Vec<CachedResource> {
+        match self.store {
+            Some(ref store) => store
+                .lock()
+                .unwrap()
+                .load(&entry_key.url)
+                .into_iter()
+                .map(|(record, body)| cached_resource_from_stored(record, body))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Ensure the resources for a key that only live in the persistent store are in memory.
+    fn ensure_loaded(&mut self, entry_key: &CacheKey) {
+        if self.entries.contains_key(entry_key) {
+            return;
+        }
+        let loaded = self.load_from_store(entry_key);
+        if !loaded.is_empty() {
+            self.entries.insert(entry_key.clone(), loaded);
+        }
+    }
+
+    /// Write a resource with a complete body to the persistent store,
+    /// replacing older resources that would be selected by the same requests.
+    fn persist(&self, entry_key: &CacheKey, cached_resource: &CachedResource) {
+        let store = match self.store {
+            Some(ref store) => store,
+            None => return,
+        };
+        let body = match *cached_resource.body.lock().unwrap() {
+            ResponseBody::Done(ref body) => body.clone(),
+            ResponseBody::Empty => vec![],
+            ResponseBody::Receiving(..) => return,
+        };
+        let record = stored_resource_from_cached(entry_key, cached_resource);
+        let mut store = store.lock().unwrap();
+        if store.contains(&record.id) {
+            return;
+        }
+        let superseded: Vec<String> =
+            store
+                .records(&entry_key.url)
+                .into_iter()
+                .filter(|stored| {
+                    stored.raw_status.as_ref().map(|s| s.0) ==
+                        record.raw_status.as_ref().map(|s| s.0) &&
+                        vary_matches(
+                            &stored.response_headers,
+                            &stored.request_headers,
+                            &record.request_headers,
+                        )
+                })
+                .map(|stored| stored.id.clone())
+                .collect();
+        store.insert(record, &body, &superseded);
+    }
+
+    /// Write updated metadata of a resource to the persistent store.
+    fn update_stored(&self, entry_key: &CacheKey, cached_resource: &CachedResource) {
+        if let Some(ref store) = self.store {
+            let record = stored_resource_from_cached(entry_key, cached_resource);
+            store.lock().unwrap().update(&record);
+        }
+    }
+
+    /// Remove every resource from the cache, including the persistent store.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        if let Some(ref store) = self.store {
+            store.lock().unwrap().clear();
         }
     }
 
@@ -576,52 +786,30 @@ impl HttpCache {
             return None;
         }
         let entry_key = CacheKey::new(request.clone());
-        let resources = self
-            .entries
-            .get(&entry_key)?
-            .into_iter()
-            .filter(|r| !r.aborted.load(Ordering::Relaxed));
+        let from_store;
+        let resources = match self.entries.get(&entry_key) {
+            Some(resources) => resources,
+            None => {
+                // Resources stored by a previous session are only on disk.
+                from_store = self.load_from_store(&entry_key);
+                if from_store.is_empty() {
+                    return None;
+                }
+                &from_store
+            },
+        };
         let mut candidates = vec![];
-        for cached_resource in resources {
-            let mut can_be_constructed = true;
+        for cached_resource in resources
+            .iter()
+            .filter(|r| !r.aborted.load(Ordering::Relaxed))
+        {
             let cached_headers = cached_resource.data.metadata.headers.lock().unwrap();
             let original_request_headers = cached_resource.request_headers.lock().unwrap();
-            if let Some(vary_value) = cached_headers.typed_get::<Vary>() {
-                if vary_value.is_any() {
-                    can_be_constructed = false
-                } else {
-                    // For every header name found in the Vary header of the stored response.
-                    // Calculating Secondary Keys with Vary <https://tools.ietf.org/html/rfc7234#section-4.1>
-                    for vary_val in vary_value.iter_strs() {
-                        match request.headers.get(vary_val) {
-                            Some(header_data) => {
-                                // If the header is present in the request.
-                                if let Some(original_header_data) =
-                                    original_request_headers.get(vary_val)
-                                {
-                                    // Check that the value of the nominated header field,
-                                    // in the original request, matches the value in the current request.
-                                    if original_header_data != header_data {
-                                        can_be_constructed = false;
-                                        break;
-                                    }
-                                }
-                            },
-                            None => {
-                                // If a header field is absent from a request,
-                                // it can only match a stored response if those headers,
-                                // were also absent in the original request.
-                                can_be_constructed =
-                                    original_request_headers.get(vary_val).is_none();
-                            },
-                        }
-                        if !can_be_constructed {
-                            break;
-                        }
-                    }
-                }
-            }
-            if can_be_constructed {
+            if vary_matches(
+                &*cached_headers,
+                &*original_request_headers,
+                &request.headers,
+            ) {
                 candidates.push(cached_resource);
             }
         }
@@ -678,6 +866,12 @@ impl HttpCache {
                     .iter()
                     .filter(|resource| resource.data.raw_status == response.raw_status);
                 for cached_resource in relevant_cached_resources {
+                    if !cached_resource.aborted.load(Ordering::Relaxed) &&
+                        !response.is_network_error()
+                    {
+                        // The body is now complete, and can be persisted.
+                        self.persist(&entry_key, cached_resource);
+                    }
                     let mut awaiting_consumers = cached_resource.awaiting_body.lock().unwrap();
                     for done_sender in awaiting_consumers.drain(..) {
                         if cached_resource.aborted.load(Ordering::Relaxed) ||
@@ -708,6 +902,8 @@ impl HttpCache {
     ) -> Option<Response> {
         assert_eq!(response.status.map(|s| s.0), Some(StatusCode::NOT_MODIFIED));
         let entry_key = CacheKey::new(request.clone());
+        self.ensure_loaded(&entry_key);
+        let mut refreshed = None;
         if let Some(cached_resources) = self.entries.get_mut(&entry_key) {
             for cached_resource in cached_resources.iter_mut() {
                 // done_chan will have been set to Some(..) by http_network_fetch.
@@ -745,13 +941,19 @@ impl HttpCache {
                 constructed_response.raw_status = cached_resource.data.raw_status.clone();
                 constructed_response.url_list = cached_resource.data.url_list.clone();
                 cached_resource.data.expires = get_response_expiry(&constructed_response);
-                let mut stored_headers = cached_resource.data.metadata.headers.lock().unwrap();
-                stored_headers.extend(response.headers);
-                constructed_response.headers = stored_headers.clone();
-                return Some(constructed_response);
+                cached_resource.data.last_validated = time::now();
+                {
+                    let mut stored_headers = cached_resource.data.metadata.headers.lock().unwrap();
+                    stored_headers.extend(response.headers);
+                    constructed_response.headers = stored_headers.clone();
+                }
+                refreshed = Some((cached_resource.clone(), constructed_response));
+                break;
             }
         }
-        None
+        let (cached_resource, constructed_response) = refreshed?;
+        self.update_stored(&entry_key, &cached_resource);
+        Some(constructed_response)
     }
 
     fn invalidate_for_url(&mut self, url: &ServoUrl) {
@@ -761,6 +963,9 @@ impl HttpCache {
                 cached_resource.data.expires = Duration::seconds(0i64);
             }
         }
+        if let Some(ref store) = self.store {
+            store.lock().unwrap().invalidate(url);
+        }
     }
 
     /// Invalidation.
@@ -835,6 +1040,7 @@ impl HttpCache {
             aborted: response.aborted.clone(),
             awaiting_body: Arc::new(Mutex::new(vec![])),
             data: Measurable(MeasurableCachedResource {
+                id: uuid::Uuid::new_v4().to_simple().to_string(),
                 metadata: cacheable_metadata,
                 location_url: response.location_url.clone(),
                 https_state: response.https_state.clone(),
@@ -845,6 +1051,10 @@ impl HttpCache {
                 last_validated: time::now(),
             }),
         };
+        // Resources with a complete body are persisted right away,
+        // others once their body is complete, see `update_awaiting_consumers`.
+        self.ensure_loaded(&entry_key);
+        self.persist(&entry_key, &entry_resource);
         let entry = self.entries.entry(entry_key).or_insert(vec![]);
         entry.push(entry_resource);
         // TODO: Complete incomplete responses, including 206 response, when stored here.
@@ -0,0 +1,367 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+#![deny(missing_docs)]
+
+//! A persistent, size-bounded storage layer backing the `HttpCache`.
+//!
+//! Every stored resource is written as two files in the cache directory:
+//! `<id>.body` holding the response body, and `<id>.json` holding the
+//! serialized `StoredResource` record. Both are written to a temporary file
+//! first and then renamed into place, the record last, so that a crash can at
+//! worst leave an orphaned body behind, which is removed on the next startup.
+
+use http::HeaderMap;
+use net_traits::response::HttpsState;
+use servo_url::ServoUrl;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+const RECORD_EXTENSION: &'static str = "json";
+const BODY_EXTENSION: &'static str = "body";
+const TEMP_EXTENSION: &'static str = "tmp";
+
+/// The serializable form of a cached resource, without its body.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct StoredResource {
+    /// Unique identifier of this resource, used to name its files.
+    pub id: String,
+    /// The URL this resource is stored under.
+    pub url: ServoUrl,
+    /// Headers of the request that produced this resource, needed for Vary matching.
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    pub request_headers: HeaderMap,
+    /// Headers of the stored response.
+    #[serde(
+        deserialize_with = "::hyper_serde::deserialize",
+        serialize_with = "::hyper_serde::serialize"
+    )]
+    pub response_headers: HeaderMap,
+    /// Final URL after redirects.
+    pub final_url: ServoUrl,
+    /// MIME type / subtype.
+    pub content_type: Option<String>,
+    /// Character set.
+    pub charset: Option<String>,
+    /// HTTP status, as reported in the metadata.
+    pub metadata_status: Option<(u16, Vec<u8>)>,
+    /// The location URL of the response, if any.
+    pub location_url: Option<Result<ServoUrl, String>>,
+    /// The HTTPS state of the response.
+    pub https_state: HttpsState,
+    /// Status code and reason phrase.
+    pub status: Option<(u16, String)>,
+    /// Raw status code and reason phrase.
+    pub raw_status: Option<(u16, Vec<u8>)>,
+    /// The URL list of the response.
+    pub url_list: Vec<ServoUrl>,
+    /// Freshness lifetime, in seconds.
+    pub expires: i64,
+    /// Time of the last validation, in seconds since the epoch.
+    pub last_validated: i64,
+    /// Whether the response had a body at all, as opposed to an empty one.
+    pub has_body: bool,
+}
+
+/// Book-keeping for a resource present on disk.
+struct DiskEntry {
+    record: StoredResource,
+    size: u64,
+    last_accessed: u64,
+}
+
+/// An on-disk store of cached resources, evicting the least recently used
+/// ones whenever its total size exceeds the configured limit.
+pub struct HttpCacheStore {
+    /// The directory holding the cache files.
+    root: PathBuf,
+    /// The maximum number of bytes the store may occupy.
+    max_size: u64,
+    /// The number of bytes currently occupied.
+    total_size: u64,
+    /// A logical clock used to order accesses for eviction.
+    access_clock: u64,
+    /// The resources present on disk, keyed by URL.
+    entries: HashMap<ServoUrl, Vec<DiskEntry>>,
+}
+
+impl HttpCacheStore {
+    /// Open the store located at `root`, creating the directory if needed,
+    /// and index the resources stored there by a previous session.
+    pub fn open(root: PathBuf, max_size: u64) -> io::Result<HttpCacheStore> {
+        fs::create_dir_all(&root)?;
+        let mut store = HttpCacheStore {
+            root,
+            max_size,
+            total_size: 0,
+            access_clock: 0,
+            entries: HashMap::new(),
+        };
+        store.load_index()?;
+        store.evict_if_needed();
+        Ok(store)
+    }
+
+    fn record_path(&self, id: &str) -> PathBuf {
+        self.root.join(id).with_extension(RECORD_EXTENSION)
+    }
+
+    fn body_path(&self, id: &str) -> PathBuf {
+        self.root.join(id).with_extension(BODY_EXTENSION)
+    }
+
+    fn load_index(&mut self) -> io::Result<()> {
+        let mut records = vec![];
+        let mut bodies = vec![];
+        for dir_entry in fs::read_dir(&self.root)? {
+            let path = dir_entry?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(RECORD_EXTENSION) => records.push(path),
+                Some(BODY_EXTENSION) => bodies.push(path),
+                // Leftovers of an interrupted write.
+                Some(TEMP_EXTENSION) => {
+                    let _ = fs::remove_file(&path);
+                },
+                _ => {},
+            }
+        }
+        // Oldest records first, so that the logical clock reflects their age.
+        records.sort_by_key(|path| fs::metadata(path).and_then(|m| m.modified()).ok());
+        for path in records {
+            let record: StoredResource = match read_file(&path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            {
+                Some(record) => record,
+                None => {
+                    warn!("Discarding unreadable http cache record {}", path.display());
+                    let _ = fs::remove_file(&path);
+                    continue;
+                },
+            };
+            let size = match fs::metadata(self.body_path(&record.id)) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => {
+                    // The body went missing, the record is useless.
+                    let _ = fs::remove_file(&path);
+                    continue;
+                },
+            };
+            self.access_clock += 1;
+            self.total_size += size;
+            self.entries
+                .entry(record.url.clone())
+                .or_insert(vec![])
+                .push(DiskEntry {
+                    record,
+                    size,
+                    last_accessed: self.access_clock,
+                });
+        }
+        // Remove bodies whose record was never committed.
+        for path in bodies {
+            let id = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(id) => id.to_owned(),
+                None => continue,
+            };
+            if !self.contains(&id) {
+                let _ = fs::remove_file(&path);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a resource with the given id is present on disk.
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries
+            .values()
+            .any(|entries| entries.iter().any(|entry| entry.record.id == id))
+    }
+
+    /// The records stored for a URL, without their bodies.
+    pub fn records(&self, url: &ServoUrl) -> Vec<&StoredResource> {
+        match self.entries.get(url) {
+            Some(entries) => entries.iter().map(|entry| &entry.record).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Load every resource stored for a URL, along with its body.
+    /// Resources whose body can no longer be read are dropped from the store.
+    pub fn load(&mut self, url: &ServoUrl) -> Vec<(StoredResource, Vec<u8>)> {
+        let ids: Vec<String> = self
+            .records(url)
+            .iter()
+            .map(|record| record.id.clone())
+            .collect();
+        let mut loaded = vec![];
+        for id in ids {
+            match read_file(&self.body_path(&id)) {
+                Ok(body) => {
+                    self.access_clock += 1;
+                    let clock = self.access_clock;
+                    if let Some(entry) = self.entry_mut(url, &id) {
+                        entry.last_accessed = clock;
+                        loaded.push((entry.record.clone(), body));
+                    }
+                },
+                Err(e) => {
+                    warn!("Failed to read http cache body for {}: {}", url, e);
+                    self.remove(url, &id);
+                },
+            }
+        }
+        loaded
+    }
+
+    fn entry_mut(&mut self, url: &ServoUrl, id: &str) -> Option<&mut DiskEntry> {
+        self.entries
+            .get_mut(url)?
+            .iter_mut()
+            .find(|entry| entry.record.id == id)
+    }
+
+    /// Write a resource to disk, replacing the resources listed in `superseded`.
+    /// Resources larger than an eighth of the store are not persisted.
+    pub fn insert(&mut self, record: StoredResource, body: &[u8], superseded: &[String]) {
+        let url = record.url.clone();
+        for id in superseded {
+            self.remove(&url, id);
+        }
+        let size = body.len() as u64;
+        if size > self.max_size / 8 {
+            return;
+        }
+        if let Err(e) = self
+            .write_atomically(&self.body_path(&record.id), body)
+            .and_then(|_| self.write_record(&record))
+        {
+            warn!("Failed to write {} to the http cache: {}", url, e);
+            let _ = fs::remove_file(self.body_path(&record.id));
+            return;
+        }
+        self.access_clock += 1;
+        self.total_size += size;
+        self.entries.entry(url).or_insert(vec![]).push(DiskEntry {
+            record,
+            size,
+            last_accessed: self.access_clock,
+        });
+        self.evict_if_needed();
+    }
+
+    /// Persist updated metadata of a resource that is already on disk.
+    pub fn update(&mut self, record: &StoredResource) {
+        if let Some(entry) = self.entry_mut(&record.url, &record.id) {
+            entry.record = record.clone();
+        } else {
+            return;
+        }
+        if let Err(e) = self.write_record(record) {
+            warn!("Failed to update {} in the http cache: {}", record.url, e);
+        }
+    }
+
+    /// Mark every resource stored for a URL as requiring validation.
+    pub fn invalidate(&mut self, url: &ServoUrl) {
+        let records: Vec<StoredResource> = self
+            .records(url)
+            .into_iter()
+            .filter(|record| record.expires != 0)
+            .map(|record| StoredResource {
+                expires: 0,
+                ..record.clone()
+            })
+            .collect();
+        for record in records {
+            self.update(&record);
+        }
+    }
+
+    /// Remove a resource from disk.
+    pub fn remove(&mut self, url: &ServoUrl, id: &str) {
+        let removed = match self.entries.get_mut(url) {
+            Some(entries) => match entries.iter().position(|entry| entry.record.id == id) {
+                Some(index) => entries.remove(index),
+                None => return,
+            },
+            None => return,
+        };
+        if self
+            .entries
+            .get(url)
+            .map_or(false, |entries| entries.is_empty())
+        {
+            self.entries.remove(url);
+        }
+        self.total_size -= removed.size;
+        // Remove the record first, a body without a record is cleaned up on startup.
+        let _ = fs::remove_file(self.record_path(id));
+        let _ = fs::remove_file(self.body_path(id));
+    }
+
+    /// Remove every resource from disk.
+    pub fn clear(&mut self) {
+        let all: Vec<(ServoUrl, String)> = self
+            .entries
+            .iter()
+            .flat_map(|(url, entries)| {
+                entries
+                    .iter()
+                    .map(move |entry| (url.clone(), entry.record.id.clone()))
+            })
+            .collect();
+        for (url, id) in all {
+            self.remove(&url, &id);
+        }
+    }
+
+    /// The number of bytes of response bodies currently stored.
+    pub fn size(&self) -> u64 {
+        self.total_size
+    }
+
+    fn evict_if_needed(&mut self) {
+        while self.total_size > self.max_size {
+            let oldest = self
+                .entries
+                .iter()
+                .flat_map(|(url, entries)| entries.iter().map(move |entry| (url, entry)))
+                .min_by_key(|&(_, entry)| entry.last_accessed)
+                .map(|(url, entry)| (url.clone(), entry.record.id.clone()));
+            match oldest {
+                Some((url, id)) => self.remove(&url, &id),
+                None => break,
+            }
+        }
+    }
+
+    fn write_record(&self, record: &StoredResource) -> io::Result<()> {
+        let serialized = serde_json::to_vec(record)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.write_atomically(&self.record_path(&record.id), &serialized)
+    }
+
+    fn write_atomically(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let temp_path = path.with_extension(format!("{}.{}", extension, TEMP_EXTENSION));
+        {
+            let mut file = File::create(&temp_path)?;
+            file.write_all(bytes)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temp_path, path)
+    }
+}
+
+fn read_file(path: &Path) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![];
+    File::open(path)?.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
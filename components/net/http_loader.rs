@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::connector::{create_http_client, Connector};
-use crate::cookie;
+use crate::cookie::{self, SameSiteContext};
 use crate::cookie_storage::CookieStorage;
 use crate::decoder::Decoder;
 use crate::fetch::cors_cache::CorsCache;
@@ -13,6 +13,7 @@ use crate::fetch::methods::{
 use crate::fetch::methods::{Data, DoneChannel, FetchContext, Target};
 use crate::hsts::HstsList;
 use crate::http_cache::HttpCache;
+use crate::proxy::{proxy_authorization_value, proxy_credentials, ProxyConfig};
 use crate::resource_thread::AuthCache;
 use crossbeam_channel::{unbounded, Sender};
 use devtools_traits::{
@@ -35,14 +36,18 @@ use http::uri::Authority;
 use http::{HeaderMap, Request as HyperRequest};
 use hyper::{Body, Client, Method, Response as HyperResponse, StatusCode};
 use hyper_serde::Serde;
+use ipc_channel::ipc;
 use msg::constellation_msg::{HistoryStateId, PipelineId};
+use net_traits::csp::CspList;
 use net_traits::quality::{quality_to_value, Quality, QualityItem};
 use net_traits::request::{CacheMode, CredentialsMode, Destination, Origin};
-use net_traits::request::{RedirectMode, Referrer, Request, RequestMode};
+use net_traits::request::{RedirectMode, Referrer, Request, RequestBuilder, RequestMode};
 use net_traits::request::{ResponseTainting, ServiceWorkersMode};
 use net_traits::response::{HttpsState, Response, ResponseBody, ResponseType};
-use net_traits::{CookieSource, FetchMetadata, NetworkError, ReferrerPolicy};
-use net_traits::{RedirectEndValue, RedirectStartValue, ResourceAttribute, ResourceFetchTiming};
+use net_traits::ResourceFetchTiming;
+use net_traits::SiteForCookies;
+use net_traits::{CookieSource, CustomResponseMediator, FetchMetadata, NetworkError};
+use net_traits::{RedirectEndValue, RedirectStartValue, ReferrerPolicy, ResourceAttribute};
 use openssl::ssl::SslConnectorBuilder;
 use servo_url::{ImmutableOrigin, ServoUrl};
 use std::collections::{HashMap, HashSet};
@@ -66,6 +71,7 @@ pub struct HttpState {
     pub cookie_jar: RwLock<CookieStorage>,
     pub http_cache: RwLock<HttpCache>,
     pub auth_cache: RwLock<AuthCache>,
+    pub proxy_auth_cache: RwLock<AuthCache>,
     pub history_states: RwLock<HashMap<HistoryStateId, Vec<u8>>>,
     pub client: Client<Connector, Body>,
 }
@@ -76,6 +82,7 @@ impl HttpState {
             hsts_list: RwLock::new(HstsList::new()),
             cookie_jar: RwLock::new(CookieStorage::new(150)),
             auth_cache: RwLock::new(AuthCache::new()),
+            proxy_auth_cache: RwLock::new(AuthCache::new()),
             history_states: RwLock::new(HashMap::new()),
             http_cache: RwLock::new(HttpCache::new()),
             client: create_http_client(ssl_connector_builder, HANDLE.lock().unwrap().executor()),
@@ -231,9 +238,10 @@ pub fn set_request_cookies(
     url: &ServoUrl,
     headers: &mut HeaderMap,
     cookie_jar: &RwLock<CookieStorage>,
+    context: SameSiteContext,
 ) {
     let mut cookie_jar = cookie_jar.write().unwrap();
-    if let Some(cookie_list) = cookie_jar.cookies_for_url(url, CookieSource::HTTP) {
+    if let Some(cookie_list) = cookie_jar.cookies_for_url(url, CookieSource::HTTP, context) {
         headers.insert(
             header::COOKIE,
             HeaderValue::from_bytes(cookie_list.as_bytes()).unwrap(),
@@ -241,12 +249,19 @@ pub fn set_request_cookies(
     }
 }
 
-fn set_cookie_for_url(cookie_jar: &RwLock<CookieStorage>, request: &ServoUrl, cookie_val: &str) {
+fn set_cookie_for_url(
+    cookie_jar: &RwLock<CookieStorage>,
+    request: &ServoUrl,
+    cookie_val: &str,
+    site_for_cookies: &SiteForCookies,
+) {
     let mut cookie_jar = cookie_jar.write().unwrap();
     let source = CookieSource::HTTP;
 
     if let Some(cookie) = cookie::Cookie::from_cookie_string(cookie_val.into(), request, source) {
-        cookie_jar.push(cookie, request, source);
+        if cookie.settable_from(request, site_for_cookies) {
+            cookie_jar.push(cookie, request, source);
+        }
     }
 }
 
@@ -254,10 +269,11 @@ fn set_cookies_from_headers(
     url: &ServoUrl,
     headers: &HeaderMap,
     cookie_jar: &RwLock<CookieStorage>,
+    site_for_cookies: &SiteForCookies,
 ) {
     for cookie in headers.get_all(header::SET_COOKIE) {
         if let Ok(cookie_str) = cookie.to_str() {
-            set_cookie_for_url(&cookie_jar, &url, &cookie_str);
+            set_cookie_for_url(&cookie_jar, &url, &cookie_str, site_for_cookies);
         }
     }
 }
@@ -465,6 +481,61 @@ fn obtain_response(
     )
 }
 
+/// [Handle fetch](https://w3c.github.io/ServiceWorker/#handle-fetch), by letting the
+/// service worker controlling the request, if any, provide the response.
+fn handle_fetch(request: &Request, context: &FetchContext) -> Option<Response> {
+    // Asking the service worker manager blocks the fetch, so only do it when service
+    // workers can be registered at all.
+    if !pref!(dom.serviceworker.enabled) {
+        return None;
+    }
+    let swmanager_chan = context.swmanager_chan.as_ref()?;
+    if !request.is_navigation_request() && !request.is_subresource_request() {
+        return None;
+    }
+
+    let mut intercepted = RequestBuilder::new(request.current_url())
+        .method(request.method.clone())
+        .headers(request.headers.clone())
+        .destination(request.destination)
+        .mode(request.mode.clone())
+        .credentials_mode(request.credentials_mode)
+        .referrer(Some(request.referrer.clone()))
+        .referrer_policy(request.referrer_policy)
+        .pipeline_id(request.pipeline_id)
+        .redirect_mode(request.redirect_mode)
+        .integrity_metadata(request.integrity_metadata.clone())
+        .site_for_cookies(request.site_for_cookies.clone());
+    if let Origin::Origin(ref origin) = request.origin {
+        intercepted = intercepted.origin(origin.clone());
+    }
+
+    let (response_chan, response_port) = ipc::channel().unwrap();
+    let mediator = CustomResponseMediator {
+        response_chan: response_chan,
+        request: intercepted,
+    };
+    if swmanager_chan.send(mediator).is_err() {
+        return None;
+    }
+    let custom_response = match response_port.recv() {
+        Ok(Some(Ok(custom_response))) => custom_response,
+        Ok(Some(Err(error))) => return Some(Response::network_error(error)),
+        Ok(None) | Err(_) => return None,
+    };
+
+    let mut response = Response::new(
+        request.current_url(),
+        ResourceFetchTiming::new(request.timing_type()),
+    );
+    let (status, status_text) = custom_response.raw_status;
+    response.headers = custom_response.headers;
+    response.raw_status = Some((status.as_u16(), status_text.clone().into_bytes()));
+    response.status = Some((status, status_text));
+    *response.body.lock().unwrap() = ResponseBody::Done(custom_response.body);
+    Some(response)
+}
+
 /// [HTTP fetch](https://fetch.spec.whatwg.org#http-fetch)
 pub fn http_fetch(
     request: &mut Request,
@@ -486,11 +557,11 @@ pub fn http_fetch(
 
     // Step 3
     if request.service_workers_mode == ServiceWorkersMode::All {
-        // TODO: Substep 1
-        // Set response to the result of invoking handle fetch for request.
+        // Substep 1
+        response = handle_fetch(request, context);
 
         // Substep 2
-        if let Some(ref res) = response {
+        if let Some(ref mut res) = response {
             // Subsubstep 1
             // TODO: transmit body for request
 
@@ -508,7 +579,8 @@ pub fn http_fetch(
             }
 
             // Subsubstep 4
-            // TODO: set response's CSP list on actual_response
+            let csp_list = CspList::from_headers(&res.actual_response().headers);
+            res.actual_response_mut().csp_list = csp_list;
         }
     }
 
@@ -970,10 +1042,16 @@ fn http_network_or_cache_fetch(
         // Substep 1
         // TODO http://mxr.mozilla.org/servo/source/components/net/http_loader.rs#504
         // XXXManishearth http_loader has block_cookies: support content blocking here too
+        let same_site_context = SameSiteContext::new(
+            &current_url,
+            &http_request.site_for_cookies,
+            &http_request.method,
+        );
         set_request_cookies(
             &current_url,
             &mut http_request.headers,
             &context.state.cookie_jar,
+            same_site_context,
         );
         // Substep 2
         if !http_request.headers.contains_key(header::AUTHORIZATION) {
@@ -1005,7 +1083,27 @@ fn http_network_or_cache_fetch(
     }
 
     // Step 5.18
-    // TODO If there’s a proxy-authentication entry, use it as appropriate.
+    // Only requests sent to the proxy in absolute-form carry this header,
+    // tunnelled ones would leak it to the destination.
+    if current_url.scheme() == "http" &&
+        !http_request
+            .headers
+            .contains_key(header::PROXY_AUTHORIZATION)
+    {
+        if let Some(proxy) = ProxyConfig::from_prefs().proxy_for_url(&current_url) {
+            let proxy_auth_cache = context.state.proxy_auth_cache.read().unwrap();
+            if let Some(entry) = proxy_auth_cache
+                .entries
+                .get(&proxy.origin().ascii_serialization())
+            {
+                if let Ok(value) = HeaderValue::from_str(&proxy_authorization_value(entry)) {
+                    http_request
+                        .headers
+                        .insert(header::PROXY_AUTHORIZATION, value);
+                }
+            }
+        }
+    }
 
     // Step 5.19
     if let Ok(http_cache) = context.state.http_cache.read() {
@@ -1169,8 +1267,15 @@ fn http_network_or_cache_fetch(
 
     // Step 11
     if let Some((StatusCode::PROXY_AUTHENTICATION_REQUIRED, _)) = response.status.as_ref() {
+        // Credentials configured along with the proxy stand in for the user's answer
+        // to the prompt below, and don't need a window to be obtained.
+        let current_url = http_request.current_url();
+        let proxy_entry = ProxyConfig::from_prefs()
+            .proxy_for_url(&current_url)
+            .and_then(|proxy| Some((proxy.origin(), proxy_credentials(proxy)?)));
+
         // Step 1
-        if request_has_no_window {
+        if request_has_no_window && proxy_entry.is_none() {
             return Response::network_error(NetworkError::Internal(
                 "Can't find Window object".into(),
             ));
@@ -1181,15 +1286,33 @@ fn http_network_or_cache_fetch(
 
         // Step 3
         // FIXME: Prompt the user for proxy authentication credentials
-
-        // Wrong, but will have to do until we are able to prompt the user
-        // otherwise this creates an infinite loop
-        // We basically pretend that the user declined to enter credentials
-        return response;
+        match proxy_entry {
+            // Only retry if the configured credentials weren't already rejected.
+            Some((proxy_origin, entry))
+                if !http_request
+                    .headers
+                    .contains_key(header::PROXY_AUTHORIZATION) =>
+            {
+                context
+                    .state
+                    .proxy_auth_cache
+                    .write()
+                    .unwrap()
+                    .entries
+                    .insert(proxy_origin.ascii_serialization(), entry);
+            }
+            // We basically pretend that the user declined to enter credentials
+            _ => return response,
+        }
 
         // Step 4
-        // return http_network_or_cache_fetch(request, authentication_fetch_flag,
-        //                                    cors_flag, done_chan, context);
+        return http_network_or_cache_fetch(
+            http_request,
+            authentication_fetch_flag,
+            cors_flag,
+            done_chan,
+            context,
+        );
     }
 
     // Step 12
@@ -1420,14 +1543,19 @@ fn http_network_fetch(
     };
 
     // Step 13
-    // TODO this step isn't possible yet (CSP)
+    response.csp_list = CspList::from_headers(&response.headers);
 
     // Step 14, update the cached response, done via the shared response body.
 
     // TODO this step isn't possible yet
     // Step 15
     if credentials_flag {
-        set_cookies_from_headers(&url, &response.headers, &context.state.cookie_jar);
+        set_cookies_from_headers(
+            &url,
+            &response.headers,
+            &context.state.cookie_jar,
+            &request.site_for_cookies,
+        );
     }
 
     // TODO these steps
@@ -1466,6 +1594,7 @@ fn cors_preflight_fetch(
     preflight.origin = request.origin.clone();
     preflight.referrer = request.referrer.clone();
     preflight.referrer_policy = request.referrer_policy;
+    preflight.site_for_cookies = request.site_for_cookies.clone();
 
     // Step 2
     preflight
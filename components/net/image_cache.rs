@@ -33,8 +33,8 @@ use webrender_api::units::DeviceIntSize;
 // Helper functions.
 // ======================================================================
 
-fn decode_bytes_sync(key: LoadKey, bytes: &[u8]) -> DecoderMsg {
-    let image = load_from_memory(bytes);
+fn decode_bytes_sync(key: LoadKey, bytes: &[u8], device_pixel_ratio: f32) -> DecoderMsg {
+    let image = load_from_memory(bytes, device_pixel_ratio);
     DecoderMsg {
         key: key,
         image: image,
@@ -45,7 +45,7 @@ fn get_placeholder_image(
     webrender_api: &webrender_api::RenderApi,
     data: &[u8],
 ) -> io::Result<Arc<Image>> {
-    let mut image = load_from_memory(&data).unwrap();
+    let mut image = load_from_memory(&data, 1.).unwrap();
     set_webrender_image_key(webrender_api, &mut image);
     Ok(Arc::new(image))
 }
@@ -57,12 +57,12 @@ fn set_webrender_image_key(webrender_api: &webrender_api::RenderApi, image: &mut
     let mut bytes = Vec::new();
     let is_opaque = match image.format {
         PixelFormat::BGRA8 => {
-            bytes.extend_from_slice(&*image.bytes);
+            bytes.extend_from_slice(image.first_frame_bytes());
             pixels::rgba8_premultiply_inplace(bytes.as_mut_slice())
         },
         PixelFormat::RGB8 => {
-            bytes.reserve(image.bytes.len() / 3 * 4);
-            for bgr in image.bytes.chunks(3) {
+            bytes.reserve(image.first_frame_bytes().len() / 3 * 4);
+            for bgr in image.first_frame_bytes().chunks(3) {
                 bytes.extend_from_slice(&[bgr[2], bgr[1], bgr[0], 0xff]);
             }
 
@@ -304,6 +304,9 @@ struct ImageCacheStore {
 
     // Webrender API instance.
     webrender_api: webrender_api::RenderApi,
+
+    // The device pixel ratio that vector images are rasterized at.
+    device_pixel_ratio: f32,
 }
 
 impl ImageCacheStore {
@@ -375,7 +378,7 @@ pub struct ImageCacheImpl {
 }
 
 impl ImageCache for ImageCacheImpl {
-    fn new(webrender_api: webrender_api::RenderApi) -> ImageCacheImpl {
+    fn new(webrender_api: webrender_api::RenderApi, device_pixel_ratio: f32) -> ImageCacheImpl {
         debug!("New image cache");
 
         let rippy_data = resources::read_bytes(Resource::RippyPNG);
@@ -387,6 +390,7 @@ impl ImageCache for ImageCacheImpl {
                 placeholder_image: get_placeholder_image(&webrender_api, &rippy_data).ok(),
                 placeholder_url: ServoUrl::parse("chrome://resources/rippy.png").unwrap(),
                 webrender_api: webrender_api,
+                device_pixel_ratio: device_pixel_ratio,
             })),
         }
     }
@@ -407,13 +411,14 @@ impl ImageCache for ImageCacheImpl {
             return result;
         }
 
+        let device_pixel_ratio = store.device_pixel_ratio;
         let decoded = {
             let result = store.pending_loads.get_cached(url.clone(), can_request);
             match result {
                 CacheResult::Hit(key, pl) => match (&pl.result, &pl.metadata) {
                     (&Some(Ok(_)), _) => {
                         debug!("Sync decoding {} ({:?})", url, key);
-                        decode_bytes_sync(key, &pl.bytes.as_slice())
+                        decode_bytes_sync(key, &pl.bytes.as_slice(), device_pixel_ratio)
                     },
                     (&None, &Some(ref meta)) => {
                         debug!("Metadata available for {} ({:?})", url, key);
@@ -468,7 +473,8 @@ impl ImageCache for ImageCacheImpl {
     fn notify_pending_response(&self, id: PendingImageId, action: FetchResponseMsg) {
         match (action, id) {
             (FetchResponseMsg::ProcessRequestBody, _) |
-            (FetchResponseMsg::ProcessRequestEOF, _) => return,
+            (FetchResponseMsg::ProcessRequestEOF, _) |
+            (FetchResponseMsg::ProcessCspViolations(_), _) => return,
             (FetchResponseMsg::ProcessResponse(response), _) => {
                 let mut store = self.store.lock().unwrap();
                 let pending_load = store.pending_loads.get_by_key_mut(&id).unwrap();
@@ -506,17 +512,18 @@ impl ImageCache for ImageCacheImpl {
                 debug!("Received EOF for {:?}", key);
                 match result {
                     Ok(_) => {
-                        let bytes = {
+                        let (bytes, device_pixel_ratio) = {
                             let mut store = self.store.lock().unwrap();
+                            let device_pixel_ratio = store.device_pixel_ratio;
                             let pending_load = store.pending_loads.get_by_key_mut(&id).unwrap();
                             pending_load.result = Some(Ok(()));
                             debug!("Async decoding {} ({:?})", pending_load.url, key);
-                            pending_load.bytes.mark_complete()
+                            (pending_load.bytes.mark_complete(), device_pixel_ratio)
                         };
 
                         let local_store = self.store.clone();
                         thread::spawn(move || {
-                            let msg = decode_bytes_sync(key, &*bytes);
+                            let msg = decode_bytes_sync(key, &*bytes, device_pixel_ratio);
                             debug!("Image decoded");
                             local_store.lock().unwrap().handle_decoder(msg);
                         });
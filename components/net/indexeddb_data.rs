@@ -0,0 +1,1034 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The object stores and indexes of an IndexedDB database, and the operations on them.
+//!
+//! Values are kept in the serialized form produced by script, so the records of an index
+//! are computed by script and handed over along with the value they belong to.
+//!
+//! A database is saved in a directory, as a snapshot and a journal. Committing a
+//! transaction appends the operations that changed the database to the journal, so the
+//! cost of a commit depends on the size of its changes rather than on the size of the
+//! database. Once the journal is larger than the snapshot, the snapshot is replaced with
+//! one that includes it, and the journal starts over.
+
+use net_traits::indexeddb_thread::{AsyncOperation, CursorDirection, DatabaseMetadata};
+use net_traits::indexeddb_thread::{IndexMetadata, IndexedDBError, IndexedDBKey};
+use net_traits::indexeddb_thread::{IndexedDBKeyRange, IndexedDBRecord, KeyPath};
+use net_traits::indexeddb_thread::{ObjectStoreMetadata, OperationResult};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem;
+use std::ops::Bound;
+use std::path::Path;
+
+/// The largest key a key generator can produce, 2^53.
+const MAX_GENERATED_KEY: u64 = 1 << 53;
+
+/// The name of the file holding a snapshot of a saved database.
+const SNAPSHOT_FILE_NAME: &str = "snapshot";
+
+/// The name of the file the transactions committed since the snapshot are appended to.
+const JOURNAL_FILE_NAME: &str = "journal";
+
+/// The size below which the journal is never folded into the snapshot, in bytes.
+const MIN_COMPACTION_SIZE: u64 = 1 << 20;
+
+#[derive(Deserialize, Serialize)]
+struct Index {
+    metadata: IndexMetadata,
+    /// The primary keys of the records of the object store, by their key in the index.
+    records: BTreeMap<IndexedDBKey, BTreeSet<IndexedDBKey>>,
+    /// The keys each record has in the index, by primary key.
+    #[serde(skip)]
+    keys: BTreeMap<IndexedDBKey, Vec<IndexedDBKey>>,
+}
+
+impl Index {
+    fn new(metadata: IndexMetadata) -> Index {
+        Index {
+            metadata: metadata,
+            records: BTreeMap::new(),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Fill in the keys of each record, which aren't saved.
+    fn index_primary_keys(&mut self) {
+        self.keys.clear();
+        for (key, primary_keys) in &self.records {
+            for primary_key in primary_keys {
+                self.keys
+                    .entry(primary_key.clone())
+                    .or_insert_with(Vec::new)
+                    .push(key.clone());
+            }
+        }
+    }
+
+    /// Add a record to the index under `key`, returning whether it wasn't there already.
+    fn add(&mut self, key: IndexedDBKey, primary_key: IndexedDBKey) -> bool {
+        let added = self
+            .records
+            .entry(key.clone())
+            .or_insert_with(BTreeSet::new)
+            .insert(primary_key.clone());
+        if added {
+            self.keys
+                .entry(primary_key)
+                .or_insert_with(Vec::new)
+                .push(key);
+        }
+        added
+    }
+
+    /// Remove a record from the index under `key`.
+    fn remove(&mut self, key: &IndexedDBKey, primary_key: &IndexedDBKey) {
+        if let Some(primary_keys) = self.records.get_mut(key) {
+            primary_keys.remove(primary_key);
+            if primary_keys.is_empty() {
+                self.records.remove(key);
+            }
+        }
+        if let Some(keys) = self.keys.get_mut(primary_key) {
+            keys.retain(|other| other != key);
+            if keys.is_empty() {
+                self.keys.remove(primary_key);
+            }
+        }
+    }
+
+    /// Remove a record from the index, returning the keys it had in it.
+    fn remove_primary_key(&mut self, primary_key: &IndexedDBKey) -> Vec<IndexedDBKey> {
+        let keys = self.keys.remove(primary_key).unwrap_or_default();
+        for key in &keys {
+            if let Some(primary_keys) = self.records.get_mut(key) {
+                primary_keys.remove(primary_key);
+                if primary_keys.is_empty() {
+                    self.records.remove(key);
+                }
+            }
+        }
+        keys
+    }
+
+    /// Whether adding the given keys for a record would break the uniqueness of the index.
+    fn violates_uniqueness(&self, primary_key: &IndexedDBKey, keys: &[IndexedDBKey]) -> bool {
+        self.metadata.unique &&
+            keys.iter().any(|key| {
+                self.records.get(key).map_or(false, |primary_keys| {
+                    primary_keys.iter().any(|other| other != primary_key)
+                })
+            })
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct ObjectStore {
+    name: String,
+    key_path: Option<KeyPath>,
+    auto_increment: bool,
+    /// The next key of the key generator, if the object store has one.
+    current_number: u64,
+    records: BTreeMap<IndexedDBKey, Vec<u8>>,
+    indexes: BTreeMap<String, Index>,
+}
+
+impl ObjectStore {
+    fn metadata(&self) -> ObjectStoreMetadata {
+        ObjectStoreMetadata {
+            name: self.name.clone(),
+            key_path: self.key_path.clone(),
+            auto_increment: self.auto_increment,
+            indexes: self
+                .indexes
+                .values()
+                .map(|index| index.metadata.clone())
+                .collect(),
+        }
+    }
+
+    /// An object store with the same name, key generator and indexes, but no records.
+    fn cleared(&self) -> ObjectStore {
+        ObjectStore {
+            name: self.name.clone(),
+            key_path: self.key_path.clone(),
+            auto_increment: self.auto_increment,
+            current_number: self.current_number,
+            records: BTreeMap::new(),
+            indexes: self
+                .indexes
+                .iter()
+                .map(|(name, index)| (name.clone(), Index::new(index.metadata.clone())))
+                .collect(),
+        }
+    }
+
+    fn index(&self, name: &str) -> Result<&Index, IndexedDBError> {
+        self.indexes.get(name).ok_or(IndexedDBError::NotFound)
+    }
+
+    /// The records of the object store or of one of its indexes that are in the range,
+    /// in ascending order, as `(key, primary key, value)`.
+    fn records<'a>(
+        &'a self,
+        index: Option<&str>,
+        range: &'a IndexedDBKeyRange,
+    ) -> Result<Box<dyn DoubleEndedIterator<Item = Record<'a>> + 'a>, IndexedDBError> {
+        let bounds = match range_bounds(range) {
+            Some(bounds) => bounds,
+            None => return Ok(Box::new(None.into_iter())),
+        };
+        Ok(match index {
+            None => Box::new(
+                self.records
+                    .range(bounds)
+                    .map(|(key, value)| (key, key, value)),
+            ),
+            Some(name) => {
+                let records = &self.records;
+                Box::new(self.index(name)?.records.range(bounds).flat_map(
+                    move |(key, primary_keys)| {
+                        primary_keys
+                            .iter()
+                            .map(move |primary_key| (key, primary_key, &records[primary_key]))
+                    },
+                ))
+            },
+        })
+    }
+
+    fn remove_record(&mut self, primary_key: &IndexedDBKey, undo: &mut Vec<Change>) {
+        let value = match self.records.remove(primary_key) {
+            Some(value) => value,
+            None => return,
+        };
+        self.remove_index_records(primary_key, undo);
+        undo.push(Change::Record {
+            store: self.name.clone(),
+            key: primary_key.clone(),
+            value: Some(value),
+        });
+    }
+
+    fn remove_index_records(&mut self, primary_key: &IndexedDBKey, undo: &mut Vec<Change>) {
+        for index in self.indexes.values_mut() {
+            for key in index.remove_primary_key(primary_key) {
+                undo.push(Change::IndexRecord {
+                    store: self.name.clone(),
+                    index: index.metadata.name.clone(),
+                    key: key,
+                    primary_key: primary_key.clone(),
+                    added: false,
+                });
+            }
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#store-a-record-into-an-object-store>
+    fn put(
+        &mut self,
+        key: IndexedDBKey,
+        value: Vec<u8>,
+        index_keys: Vec<(String, Vec<IndexedDBKey>)>,
+        no_overwrite: bool,
+        undo: &mut Vec<Change>,
+    ) -> Result<IndexedDBKey, IndexedDBError> {
+        if no_overwrite && self.records.contains_key(&key) {
+            return Err(IndexedDBError::Constraint);
+        }
+        for &(ref name, ref keys) in &index_keys {
+            if self.index(name)?.violates_uniqueness(&key, keys) {
+                return Err(IndexedDBError::Constraint);
+            }
+        }
+
+        if self.auto_increment {
+            if let IndexedDBKey::Number(number) = key {
+                // Explicit keys push the key generator forward, so that it doesn't generate
+                // them later.
+                let next = number.floor() + 1.;
+                if next > self.current_number as f64 {
+                    undo.push(Change::KeyGenerator {
+                        store: self.name.clone(),
+                        current_number: self.current_number,
+                    });
+                    self.current_number = next.min(MAX_GENERATED_KEY as f64 + 1.) as u64;
+                }
+            }
+        }
+
+        self.remove_index_records(&key, undo);
+        let previous = self.records.insert(key.clone(), value);
+        undo.push(Change::Record {
+            store: self.name.clone(),
+            key: key.clone(),
+            value: previous,
+        });
+        for (name, keys) in index_keys {
+            let index = self.indexes.get_mut(&name).unwrap();
+            for index_key in keys {
+                if index.add(index_key.clone(), key.clone()) {
+                    undo.push(Change::IndexRecord {
+                        store: self.name.clone(),
+                        index: name.clone(),
+                        key: index_key,
+                        primary_key: key.clone(),
+                        added: true,
+                    });
+                }
+            }
+        }
+        Ok(key)
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#generate-a-key>
+    fn generate_key(&mut self, undo: &mut Vec<Change>) -> Result<IndexedDBKey, IndexedDBError> {
+        if self.current_number > MAX_GENERATED_KEY {
+            return Err(IndexedDBError::Constraint);
+        }
+        undo.push(Change::KeyGenerator {
+            store: self.name.clone(),
+            current_number: self.current_number,
+        });
+        let key = IndexedDBKey::Number(self.current_number as f64);
+        self.current_number += 1;
+        Ok(key)
+    }
+}
+
+type Record<'a> = (&'a IndexedDBKey, &'a IndexedDBKey, &'a Vec<u8>);
+
+type Bounds<'a> = (Bound<&'a IndexedDBKey>, Bound<&'a IndexedDBKey>);
+
+/// The bounds of a range, unless it is empty.
+fn range_bounds(range: &IndexedDBKeyRange) -> Option<Bounds> {
+    if let (Some(lower), Some(upper)) = (&range.lower, &range.upper) {
+        if lower > upper || (lower == upper && (range.lower_open || range.upper_open)) {
+            return None;
+        }
+    }
+    fn bound(key: &Option<IndexedDBKey>, open: bool) -> Bound<&IndexedDBKey> {
+        match *key {
+            None => Bound::Unbounded,
+            Some(ref key) if open => Bound::Excluded(key),
+            Some(ref key) => Bound::Included(key),
+        }
+    }
+    Some((
+        bound(&range.lower, range.lower_open),
+        bound(&range.upper, range.upper_open),
+    ))
+}
+
+/// Whether there are no keys between two bounds.
+fn bounds_are_empty(bounds: &Bounds) -> bool {
+    match *bounds {
+        (Bound::Included(lower), Bound::Included(upper)) => lower > upper,
+        (Bound::Included(lower), Bound::Excluded(upper)) |
+        (Bound::Excluded(lower), Bound::Included(upper)) |
+        (Bound::Excluded(lower), Bound::Excluded(upper)) => lower >= upper,
+        _ => false,
+    }
+}
+
+/// The later of two lower bounds, or the earlier of two upper bounds if `lower` is false.
+fn narrowest<'a>(
+    a: Bound<&'a IndexedDBKey>,
+    b: Bound<&'a IndexedDBKey>,
+    lower: bool,
+) -> Bound<&'a IndexedDBKey> {
+    let (a_key, b_key) = match (a, b) {
+        (Bound::Unbounded, _) => return b,
+        (_, Bound::Unbounded) => return a,
+        (Bound::Included(a_key), Bound::Included(b_key)) |
+        (Bound::Included(a_key), Bound::Excluded(b_key)) |
+        (Bound::Excluded(a_key), Bound::Included(b_key)) |
+        (Bound::Excluded(a_key), Bound::Excluded(b_key)) => (a_key, b_key),
+    };
+    if a_key == b_key {
+        match a {
+            Bound::Excluded(_) => a,
+            _ => b,
+        }
+    } else if (a_key > b_key) == lower {
+        a
+    } else {
+        b
+    }
+}
+
+fn to_record(record: Record) -> IndexedDBRecord {
+    IndexedDBRecord {
+        key: record.0.clone(),
+        primary_key: record.1.clone(),
+        value: record.2.clone(),
+    }
+}
+
+/// A change made to a database, recorded as what undoes it.
+enum Change {
+    Version(u64),
+    /// A record was stored or removed, and `value` is the one it had before, if any.
+    Record {
+        store: String,
+        key: IndexedDBKey,
+        value: Option<Vec<u8>>,
+    },
+    /// A record was added to an index under `key`, or removed from it.
+    IndexRecord {
+        store: String,
+        index: String,
+        key: IndexedDBKey,
+        primary_key: IndexedDBKey,
+        added: bool,
+    },
+    KeyGenerator {
+        store: String,
+        current_number: u64,
+    },
+    /// An object store was created, deleted or cleared, and `store` is what it was before.
+    Store {
+        name: String,
+        store: Option<ObjectStore>,
+    },
+    StoreRenamed {
+        name: String,
+        new_name: String,
+    },
+    /// An index was created or deleted, and `index` is what it was before.
+    Index {
+        store: String,
+        name: String,
+        index: Option<Index>,
+    },
+    IndexRenamed {
+        store: String,
+        name: String,
+        new_name: String,
+    },
+}
+
+/// The changes a transaction made to a database: the operations that made them, to save
+/// when the transaction is committed, and how to undo them when it is aborted.
+#[derive(Default)]
+pub struct Changes {
+    operations: Vec<AsyncOperation>,
+    undo: Vec<Change>,
+}
+
+impl Changes {
+    /// Whether the transaction changed anything.
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+}
+
+fn to_io_error(error: bincode::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+pub struct Database {
+    name: String,
+    version: u64,
+    stores: BTreeMap<String, ObjectStore>,
+    /// The number of transactions that changed the saved database.
+    commits: u64,
+    /// The size of the saved snapshot and journal, in bytes.
+    snapshot_size: u64,
+    journal_size: u64,
+}
+
+impl Database {
+    /// A database that doesn't exist yet, with version 0.
+    pub fn new(name: String) -> Database {
+        Database {
+            name: name,
+            version: 0,
+            stores: BTreeMap::new(),
+            commits: 0,
+            snapshot_size: 0,
+            journal_size: 0,
+        }
+    }
+
+    /// Read a database saved with `save` in the directory `dir`.
+    pub fn load(dir: &Path) -> io::Result<Database> {
+        let snapshot = File::open(dir.join(SNAPSHOT_FILE_NAME))?;
+        let snapshot_size = snapshot.metadata()?.len();
+        let (name, version, commits, mut stores): (
+            String,
+            u64,
+            u64,
+            BTreeMap<String, ObjectStore>,
+        ) = bincode::deserialize_from(BufReader::new(snapshot)).map_err(to_io_error)?;
+        for store in stores.values_mut() {
+            for index in store.indexes.values_mut() {
+                index.index_primary_keys();
+            }
+        }
+        let mut database = Database {
+            name: name,
+            version: version,
+            stores: stores,
+            commits: commits,
+            snapshot_size: snapshot_size,
+            journal_size: 0,
+        };
+
+        let journal_path = dir.join(JOURNAL_FILE_NAME);
+        if !journal_path.exists() {
+            return Ok(database);
+        }
+        let journal = File::open(&journal_path)?;
+        let journal_size = journal.metadata()?.len();
+        let mut journal = BufReader::new(journal);
+        let mut size = 0;
+        // Each entry is the length of the entry, followed by the number of the commit and
+        // its operations. An entry that was cut short by a crash ends the journal.
+        loop {
+            let mut length = [0; 8];
+            if journal_size - size < 8 || journal.read_exact(&mut length).is_err() {
+                break;
+            }
+            let length = u64::from_le_bytes(length);
+            if journal_size - size - 8 < length {
+                break;
+            }
+            let mut entry = vec![0; length as usize];
+            journal.read_exact(&mut entry)?;
+            let (commit, operations): (u64, Vec<AsyncOperation>) =
+                match bincode::deserialize(&entry) {
+                    Ok(entry) => entry,
+                    Err(_) => break,
+                };
+            size += 8 + length;
+            // The entries the snapshot includes are left over when saving the snapshot was
+            // interrupted before the journal was emptied.
+            if commit <= database.commits {
+                continue;
+            }
+            let mut changes = Changes::default();
+            for operation in operations {
+                if let Err(e) = database.perform(operation, &mut changes) {
+                    warn!("Couldn't replay an operation on {}: {:?}", database.name, e);
+                }
+            }
+            database.commits = commit;
+        }
+        if size < journal_size {
+            warn!("Ignoring the end of the journal of {}", database.name);
+            OpenOptions::new()
+                .write(true)
+                .open(&journal_path)?
+                .set_len(size)?;
+        }
+        database.journal_size = size;
+        Ok(database)
+    }
+
+    /// Save the changes made by a transaction to the database saved in the directory `dir`.
+    pub fn save(&mut self, dir: &Path, changes: &Changes) -> io::Result<()> {
+        self.commits += 1;
+        if self.snapshot_size == 0 {
+            return self.save_snapshot(dir);
+        }
+
+        let mut entry = vec![0; 8];
+        bincode::serialize_into(&mut entry, &(self.commits, &changes.operations))
+            .map_err(to_io_error)?;
+        let length = entry.len() as u64 - 8;
+        entry[..8].copy_from_slice(&length.to_le_bytes());
+        let path = dir.join(JOURNAL_FILE_NAME);
+        let mut journal = OpenOptions::new().append(true).create(true).open(&path)?;
+        if let Err(e) = journal.write_all(&entry).and_then(|_| journal.sync_data()) {
+            // Don't leave part of the entry for the next ones to be appended after.
+            let _ = journal.set_len(self.journal_size);
+            return Err(e);
+        }
+        self.journal_size += entry.len() as u64;
+
+        if self.journal_size > self.snapshot_size.max(MIN_COMPACTION_SIZE) {
+            if let Err(e) = self.save_snapshot(dir) {
+                // The commit is saved in the journal already.
+                warn!("Couldn't save a snapshot of {}: {}", self.name, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the saved snapshot with the database as it is now, and empty the journal.
+    fn save_snapshot(&mut self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let temporary_path = dir.join(SNAPSHOT_FILE_NAME).with_extension("tmp");
+        let mut file = BufWriter::new(File::create(&temporary_path)?);
+        let snapshot = (&self.name, self.version, self.commits, &self.stores);
+        bincode::serialize_into(&mut file, &snapshot).map_err(to_io_error)?;
+        let file = file.into_inner().map_err(io::Error::from)?;
+        file.sync_all()?;
+        let snapshot_size = file.metadata()?.len();
+        fs::rename(&temporary_path, dir.join(SNAPSHOT_FILE_NAME))?;
+        self.snapshot_size = snapshot_size;
+
+        File::create(dir.join(JOURNAL_FILE_NAME))?;
+        self.journal_size = 0;
+        Ok(())
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn metadata(&self) -> DatabaseMetadata {
+        DatabaseMetadata {
+            name: self.name.clone(),
+            version: self.version,
+            object_stores: self.stores.values().map(ObjectStore::metadata).collect(),
+        }
+    }
+
+    fn store(&self, name: &str) -> Result<&ObjectStore, IndexedDBError> {
+        self.stores.get(name).ok_or(IndexedDBError::NotFound)
+    }
+
+    fn store_mut(&mut self, name: &str) -> Result<&mut ObjectStore, IndexedDBError> {
+        self.stores.get_mut(name).ok_or(IndexedDBError::NotFound)
+    }
+
+    /// Undo the changes made by an aborted transaction.
+    pub fn undo(&mut self, changes: Changes) {
+        for change in changes.undo.into_iter().rev() {
+            match change {
+                Change::Version(version) => self.version = version,
+                Change::Record { store, key, value } => {
+                    let store = self.stores.get_mut(&store).unwrap();
+                    match value {
+                        Some(value) => store.records.insert(key, value),
+                        None => store.records.remove(&key),
+                    };
+                },
+                Change::IndexRecord {
+                    store,
+                    index,
+                    key,
+                    primary_key,
+                    added,
+                } => {
+                    let store = self.stores.get_mut(&store).unwrap();
+                    let index = store.indexes.get_mut(&index).unwrap();
+                    if added {
+                        index.remove(&key, &primary_key);
+                    } else {
+                        index.add(key, primary_key);
+                    }
+                },
+                Change::KeyGenerator {
+                    store,
+                    current_number,
+                } => {
+                    self.stores.get_mut(&store).unwrap().current_number = current_number;
+                },
+                Change::Store { name, store } => {
+                    match store {
+                        Some(store) => self.stores.insert(name, store),
+                        None => self.stores.remove(&name),
+                    };
+                },
+                Change::StoreRenamed { name, new_name } => {
+                    let mut store = self.stores.remove(&new_name).unwrap();
+                    store.name = name.clone();
+                    self.stores.insert(name, store);
+                },
+                Change::Index { store, name, index } => {
+                    let indexes = &mut self.stores.get_mut(&store).unwrap().indexes;
+                    match index {
+                        Some(index) => indexes.insert(name, index),
+                        None => indexes.remove(&name),
+                    };
+                },
+                Change::IndexRenamed {
+                    store,
+                    name,
+                    new_name,
+                } => {
+                    let indexes = &mut self.stores.get_mut(&store).unwrap().indexes;
+                    let mut index = indexes.remove(&new_name).unwrap();
+                    index.metadata.name = name.clone();
+                    indexes.insert(name, index);
+                },
+            }
+        }
+    }
+
+    pub fn perform(
+        &mut self,
+        operation: AsyncOperation,
+        changes: &mut Changes,
+    ) -> Result<OperationResult, IndexedDBError> {
+        let write = match operation {
+            AsyncOperation::GetRecord { .. } |
+            AsyncOperation::GetAllRecords { .. } |
+            AsyncOperation::Count { .. } |
+            AsyncOperation::Iterate { .. } => None,
+            _ => Some(operation.clone()),
+        };
+        let result = self.perform_operation(operation, &mut changes.undo)?;
+        changes.operations.extend(write);
+        Ok(result)
+    }
+
+    /// Perform an operation, recording how to undo the changes it makes in `undo`. An
+    /// operation that fails doesn't change anything.
+    fn perform_operation(
+        &mut self,
+        operation: AsyncOperation,
+        undo: &mut Vec<Change>,
+    ) -> Result<OperationResult, IndexedDBError> {
+        Ok(match operation {
+            AsyncOperation::GetRecord {
+                store,
+                index,
+                range,
+            } => {
+                let mut records = self
+                    .store(&store)?
+                    .records(index.as_ref().map(|s| &**s), &range)?;
+                OperationResult::Record(records.next().map(to_record))
+            },
+            AsyncOperation::GetAllRecords {
+                store,
+                index,
+                range,
+                count,
+            } => {
+                let records = self
+                    .store(&store)?
+                    .records(index.as_ref().map(|s| &**s), &range)?;
+                let records = match count {
+                    Some(count) if count > 0 => {
+                        records.take(count as usize).map(to_record).collect()
+                    },
+                    _ => records.map(to_record).collect(),
+                };
+                OperationResult::Records(records)
+            },
+            AsyncOperation::Count {
+                store,
+                index,
+                range,
+            } => {
+                let records = self
+                    .store(&store)?
+                    .records(index.as_ref().map(|s| &**s), &range)?;
+                OperationResult::Count(records.count() as u64)
+            },
+            AsyncOperation::Iterate {
+                store,
+                index,
+                range,
+                direction,
+                position,
+                object_store_position,
+                key,
+                primary_key,
+                count,
+            } => {
+                let store = self.store(&store)?;
+                let cursor = Cursor {
+                    store: store,
+                    index: index.as_ref().map(|s| &**s),
+                    range: &range,
+                    direction: direction,
+                };
+                OperationResult::Record(cursor.iterate(
+                    position,
+                    object_store_position,
+                    key,
+                    primary_key,
+                    count,
+                )?)
+            },
+            AsyncOperation::PutRecord {
+                store,
+                key,
+                value,
+                index_keys,
+                no_overwrite,
+            } => {
+                let store = self.store_mut(&store)?;
+                OperationResult::Key(store.put(key, value, index_keys, no_overwrite, undo)?)
+            },
+            AsyncOperation::GenerateKey { store } => {
+                OperationResult::Key(self.store_mut(&store)?.generate_key(undo)?)
+            },
+            AsyncOperation::DeleteRange { store, range } => {
+                let store = self.store_mut(&store)?;
+                let keys: Vec<_> = store
+                    .records(None, &range)?
+                    .map(|(key, _, _)| key.clone())
+                    .collect();
+                for key in keys {
+                    store.remove_record(&key, undo);
+                }
+                OperationResult::Done
+            },
+            AsyncOperation::Clear { store: name } => {
+                let store = self.store_mut(&name)?;
+                let cleared = store.cleared();
+                let store = mem::replace(store, cleared);
+                undo.push(Change::Store {
+                    name: name,
+                    store: Some(store),
+                });
+                OperationResult::Done
+            },
+            AsyncOperation::SetVersion(version) => {
+                undo.push(Change::Version(self.version));
+                self.version = version;
+                OperationResult::Done
+            },
+            AsyncOperation::CreateObjectStore(metadata) => {
+                if self.stores.contains_key(&metadata.name) {
+                    return Err(IndexedDBError::Constraint);
+                }
+                let store = ObjectStore {
+                    name: metadata.name.clone(),
+                    key_path: metadata.key_path,
+                    auto_increment: metadata.auto_increment,
+                    current_number: 1,
+                    records: BTreeMap::new(),
+                    indexes: BTreeMap::new(),
+                };
+                undo.push(Change::Store {
+                    name: metadata.name.clone(),
+                    store: None,
+                });
+                self.stores.insert(metadata.name, store);
+                OperationResult::Done
+            },
+            AsyncOperation::DeleteObjectStore(name) => {
+                let store = self.stores.remove(&name).ok_or(IndexedDBError::NotFound)?;
+                undo.push(Change::Store {
+                    name: name,
+                    store: Some(store),
+                });
+                OperationResult::Done
+            },
+            AsyncOperation::RenameObjectStore { name, new_name } => {
+                if self.stores.contains_key(&new_name) {
+                    return Err(IndexedDBError::Constraint);
+                }
+                let mut store = self.stores.remove(&name).ok_or(IndexedDBError::NotFound)?;
+                store.name = new_name.clone();
+                self.stores.insert(new_name.clone(), store);
+                undo.push(Change::StoreRenamed {
+                    name: name,
+                    new_name: new_name,
+                });
+                OperationResult::Done
+            },
+            AsyncOperation::CreateIndex { store, index, keys } => {
+                let object_store = self.store_mut(&store)?;
+                if object_store.indexes.contains_key(&index.name) {
+                    return Err(IndexedDBError::Constraint);
+                }
+                let mut new_index = Index::new(index);
+                for (primary_key, index_keys) in keys {
+                    if new_index.violates_uniqueness(&primary_key, &index_keys) {
+                        return Err(IndexedDBError::Constraint);
+                    }
+                    for index_key in index_keys {
+                        new_index.add(index_key, primary_key.clone());
+                    }
+                }
+                let name = new_index.metadata.name.clone();
+                object_store.indexes.insert(name.clone(), new_index);
+                undo.push(Change::Index {
+                    store: store,
+                    name: name,
+                    index: None,
+                });
+                OperationResult::Done
+            },
+            AsyncOperation::DeleteIndex { store, name } => {
+                let index = self
+                    .store_mut(&store)?
+                    .indexes
+                    .remove(&name)
+                    .ok_or(IndexedDBError::NotFound)?;
+                undo.push(Change::Index {
+                    store: store,
+                    name: name,
+                    index: Some(index),
+                });
+                OperationResult::Done
+            },
+            AsyncOperation::RenameIndex {
+                store,
+                name,
+                new_name,
+            } => {
+                let indexes = &mut self.store_mut(&store)?.indexes;
+                if indexes.contains_key(&new_name) {
+                    return Err(IndexedDBError::Constraint);
+                }
+                let mut index = indexes.remove(&name).ok_or(IndexedDBError::NotFound)?;
+                index.metadata.name = new_name.clone();
+                indexes.insert(new_name.clone(), index);
+                undo.push(Change::IndexRenamed {
+                    store: store,
+                    name: name,
+                    new_name: new_name,
+                });
+                OperationResult::Done
+            },
+        })
+    }
+}
+
+/// A cursor over the records of an object store or of an index.
+struct Cursor<'a> {
+    store: &'a ObjectStore,
+    index: Option<&'a str>,
+    range: &'a IndexedDBKeyRange,
+    direction: CursorDirection,
+}
+
+impl<'a> Cursor<'a> {
+    fn forward(&self) -> bool {
+        match self.direction {
+            CursorDirection::Next | CursorDirection::NextUnique => true,
+            CursorDirection::Prev | CursorDirection::PrevUnique => false,
+        }
+    }
+
+    fn unique(&self) -> bool {
+        match self.direction {
+            CursorDirection::NextUnique | CursorDirection::PrevUnique => true,
+            CursorDirection::Next | CursorDirection::Prev => false,
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#iterate-a-cursor>
+    fn iterate(
+        &self,
+        mut position: Option<IndexedDBKey>,
+        mut object_store_position: Option<IndexedDBKey>,
+        key: Option<IndexedDBKey>,
+        primary_key: Option<IndexedDBKey>,
+        count: u32,
+    ) -> Result<Option<IndexedDBRecord>, IndexedDBError> {
+        let mut found = None;
+        for _ in 0..count.max(1) {
+            let record = self
+                .next_records(
+                    position.as_ref(),
+                    object_store_position.as_ref(),
+                    key.as_ref(),
+                    primary_key.as_ref(),
+                )?
+                .next();
+            let mut record = match record {
+                Some(record) => to_record(record),
+                None => return Ok(None),
+            };
+            if self.direction == CursorDirection::PrevUnique && self.index.is_some() {
+                // Iterating backwards finds the last record with a given key, but unique
+                // cursors are positioned on the first one.
+                let range = IndexedDBKeyRange::only(record.key.clone());
+                let first = self
+                    .store
+                    .records(self.index, &range)?
+                    .next()
+                    .map(to_record);
+                if let Some(first) = first {
+                    record = first;
+                }
+            }
+            position = Some(record.key.clone());
+            object_store_position = Some(record.primary_key.clone());
+            found = Some(record);
+        }
+        Ok(found)
+    }
+
+    /// The records the cursor can move to, in the order it moves through them: the ones
+    /// in its range that come after its position and not before `key` and `primary_key`.
+    fn next_records<'b>(
+        &'b self,
+        position: Option<&'b IndexedDBKey>,
+        object_store_position: Option<&'b IndexedDBKey>,
+        key: Option<&'b IndexedDBKey>,
+        primary_key: Option<&'b IndexedDBKey>,
+    ) -> Result<Box<dyn Iterator<Item = Record<'a>> + 'b>, IndexedDBError> {
+        let forward = self.forward();
+        let unique = self.unique();
+
+        // Where the cursor starts from, among the keys of the records.
+        let mut start = match position {
+            Some(position) if self.index.is_some() && !unique => Bound::Included(position),
+            Some(position) => Bound::Excluded(position),
+            None => Bound::Unbounded,
+        };
+        if let Some(key) = key {
+            start = narrowest(start, Bound::Included(key), forward);
+        }
+        let bounds = match range_bounds(self.range) {
+            Some((lower, upper)) if forward => (narrowest(lower, start, true), upper),
+            Some((lower, upper)) => (lower, narrowest(upper, start, false)),
+            None => return Ok(Box::new(None.into_iter())),
+        };
+        if bounds_are_empty(&bounds) {
+            return Ok(Box::new(None.into_iter()));
+        }
+
+        let store = self.store;
+        let name = match self.index {
+            Some(name) => name,
+            None => {
+                let records = store
+                    .records
+                    .range(bounds)
+                    .map(|(key, value)| (key, key, value));
+                return Ok(if forward {
+                    Box::new(records)
+                } else {
+                    Box::new(records.rev())
+                });
+            },
+        };
+        let entries = store.index(name)?.records.range(bounds);
+        let entries: Box<dyn Iterator<Item = _>> = if forward {
+            Box::new(entries)
+        } else {
+            Box::new(entries.rev())
+        };
+        Ok(Box::new(entries.flat_map(
+            move |(record_key, primary_keys)| {
+                // Where the cursor starts from, among the records with the same key.
+                let mut start = Bound::Unbounded;
+                if let (Some(position), Some(object_store_position)) =
+                    (position, object_store_position)
+                {
+                    if !unique && record_key == position {
+                        start = Bound::Excluded(object_store_position);
+                    }
+                }
+                if let (Some(key), Some(primary_key)) = (key, primary_key) {
+                    if record_key == key {
+                        start = narrowest(start, Bound::Included(primary_key), forward);
+                    }
+                }
+                let primary_keys: Box<dyn Iterator<Item = _>> = if forward {
+                    Box::new(primary_keys.range((start, Bound::Unbounded)))
+                } else {
+                    Box::new(primary_keys.range((Bound::Unbounded, start)).rev())
+                };
+                primary_keys
+                    .map(move |primary_key| (record_key, primary_key, &store.records[primary_key]))
+            },
+        )))
+    }
+}
@@ -0,0 +1,447 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::indexeddb_data::{Changes, Database};
+use crate::storage_data::hashed_file_name;
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
+use net_traits::indexeddb_thread::{AsyncOperation, DatabaseMetadata, IndexedDBError};
+use net_traits::indexeddb_thread::{IndexedDBThreadMsg, IndexedDBTxnMode, OperationResult};
+use net_traits::indexeddb_thread::{VersionChange, WaitEvent};
+use servo_url::ImmutableOrigin;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+
+pub trait IndexedDBThreadFactory {
+    fn new(config_dir: Option<PathBuf>) -> Self;
+}
+
+impl IndexedDBThreadFactory for IpcSender<IndexedDBThreadMsg> {
+    /// Create an IndexedDB thread
+    fn new(config_dir: Option<PathBuf>) -> IpcSender<IndexedDBThreadMsg> {
+        let (chan, port) = ipc::channel().unwrap();
+        thread::Builder::new()
+            .name("IndexedDBManager".to_owned())
+            .spawn(move || {
+                IndexedDBManager::new(port, config_dir.map(|dir| dir.join("indexeddb"))).start();
+            })
+            .expect("Thread spawning failed");
+        chan
+    }
+}
+
+struct Transaction {
+    mode: IndexedDBTxnMode,
+    scope: Vec<String>,
+    changes: Changes,
+}
+
+/// A request that has to wait for the transactions it conflicts with to finish.
+enum PendingRequest {
+    Begin(IpcSender<u64>, IndexedDBTxnMode, Vec<String>),
+    Delete(IpcSender<Result<u64, IndexedDBError>>),
+}
+
+impl PendingRequest {
+    fn mode(&self) -> IndexedDBTxnMode {
+        match *self {
+            PendingRequest::Begin(_, mode, _) => mode,
+            PendingRequest::Delete(_) => IndexedDBTxnMode::Versionchange,
+        }
+    }
+
+    fn scope(&self) -> &[String] {
+        match *self {
+            PendingRequest::Begin(_, _, ref scope) => scope,
+            PendingRequest::Delete(_) => &[],
+        }
+    }
+}
+
+/// Whether two transactions can't run at the same time.
+fn conflicts(
+    mode: IndexedDBTxnMode,
+    scope: &[String],
+    other_mode: IndexedDBTxnMode,
+    other_scope: &[String],
+) -> bool {
+    match (mode, other_mode) {
+        (IndexedDBTxnMode::Readonly, IndexedDBTxnMode::Readonly) => false,
+        (IndexedDBTxnMode::Versionchange, _) | (_, IndexedDBTxnMode::Versionchange) => true,
+        _ => scope.iter().any(|name| other_scope.contains(name)),
+    }
+}
+
+/// A request to upgrade or delete a database, waiting for the other connections to it to
+/// be closed.
+struct Wait {
+    sender: IpcSender<WaitEvent>,
+    version_change: VersionChange,
+    /// The other connections that were open when the request started waiting, and still
+    /// are.
+    connections: HashSet<u64>,
+    /// The connections that haven't fired the `versionchange` event yet.
+    unacknowledged: HashSet<u64>,
+    blocked: bool,
+}
+
+struct DatabaseState {
+    database: Database,
+    /// The directory the database is saved in, if it is persistent.
+    dir: Option<PathBuf>,
+    transactions: HashMap<u64, Transaction>,
+    pending: VecDeque<PendingRequest>,
+    /// The open connections, in any global, with where to send them version changes.
+    connections: HashMap<u64, IpcSender<VersionChange>>,
+    waits: Vec<Wait>,
+}
+
+impl DatabaseState {
+    fn save(&mut self, changes: &Changes) -> io::Result<()> {
+        match self.dir {
+            Some(ref dir) => self.database.save(dir, changes),
+            None => Ok(()),
+        }
+    }
+
+    fn delete(&mut self) -> io::Result<()> {
+        let name = self.database.metadata().name;
+        self.database = Database::new(name);
+        match self.dir {
+            Some(ref dir) if dir.exists() => fs::remove_dir_all(dir),
+            _ => Ok(()),
+        }
+    }
+
+    /// Tell the waiting requests whether they are blocked, and whether they can go on.
+    fn update_waits(&mut self) {
+        let connections = &self.connections;
+        for wait in &mut self.waits {
+            wait.connections.retain(|id| connections.contains_key(id));
+            wait.unacknowledged
+                .retain(|id| connections.contains_key(id));
+            if wait.connections.is_empty() {
+                let _ = wait.sender.send(WaitEvent::Done);
+            } else if wait.unacknowledged.is_empty() && !wait.blocked {
+                wait.blocked = true;
+                let _ = wait.sender.send(WaitEvent::Blocked(wait.version_change));
+            }
+        }
+        self.waits.retain(|wait| !wait.connections.is_empty());
+    }
+}
+
+/// The origin of the databases a message is about.
+fn message_origin(message: &IndexedDBThreadMsg) -> Option<&ImmutableOrigin> {
+    match *message {
+        IndexedDBThreadMsg::Open(_, ref origin, ..) |
+        IndexedDBThreadMsg::Close(ref origin, ..) |
+        IndexedDBThreadMsg::WaitForConnections(_, ref origin, ..) |
+        IndexedDBThreadMsg::VersionChangeFired(ref origin, ..) |
+        IndexedDBThreadMsg::BeginTransaction(_, ref origin, ..) |
+        IndexedDBThreadMsg::Operation(_, ref origin, ..) |
+        IndexedDBThreadMsg::Commit(_, ref origin, ..) |
+        IndexedDBThreadMsg::Abort(ref origin, ..) |
+        IndexedDBThreadMsg::DeleteDatabase(_, ref origin, _) => Some(origin),
+        IndexedDBThreadMsg::Exit(_) => None,
+    }
+}
+
+/// Hands the messages over to a thread per origin, so that the databases of an origin
+/// don't wait for the transactions of other origins to be saved.
+struct IndexedDBManager {
+    port: IpcReceiver<IndexedDBThreadMsg>,
+    /// The directory in which databases are saved, if they are persistent.
+    dir: Option<PathBuf>,
+    /// The threads of the origins whose databases were accessed so far.
+    origins: HashMap<String, (Sender<IndexedDBThreadMsg>, JoinHandle<()>)>,
+}
+
+impl IndexedDBManager {
+    fn new(port: IpcReceiver<IndexedDBThreadMsg>, dir: Option<PathBuf>) -> IndexedDBManager {
+        IndexedDBManager {
+            port: port,
+            dir: dir,
+            origins: HashMap::new(),
+        }
+    }
+}
+
+impl IndexedDBManager {
+    fn start(&mut self) {
+        loop {
+            let message = self.port.recv().unwrap();
+            let origin = match message_origin(&message) {
+                Some(origin) => origin.ascii_serialization(),
+                None => {
+                    if let IndexedDBThreadMsg::Exit(sender) = message {
+                        self.exit();
+                        let _ = sender.send(());
+                    }
+                    break;
+                },
+            };
+            let dir = &self.dir;
+            let (ref sender, _) = *self.origins.entry(origin.clone()).or_insert_with(|| {
+                // Origins can be too long for a file name, so their directory is named
+                // after their hash.
+                let dir = dir.as_ref().map(|dir| dir.join(hashed_file_name(&origin)));
+                let (sender, receiver) = unbounded();
+                let thread = thread::Builder::new()
+                    .name("IndexedDBOrigin".to_owned())
+                    .spawn(move || OriginDatabases::new(receiver, dir).start())
+                    .expect("Thread spawning failed");
+                (sender, thread)
+            });
+            let _ = sender.send(message);
+        }
+    }
+
+    /// Wait for the threads of the origins to finish handling the messages they were sent.
+    fn exit(&mut self) {
+        for (_, (sender, thread)) in self.origins.drain() {
+            drop(sender);
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The databases of an origin.
+struct OriginDatabases {
+    receiver: Receiver<IndexedDBThreadMsg>,
+    /// The directory in which the databases are saved, if they are persistent.
+    dir: Option<PathBuf>,
+    /// The databases that were accessed so far, by name.
+    databases: HashMap<String, DatabaseState>,
+    next_connection_id: u64,
+    next_transaction_id: u64,
+}
+
+impl OriginDatabases {
+    fn new(receiver: Receiver<IndexedDBThreadMsg>, dir: Option<PathBuf>) -> OriginDatabases {
+        OriginDatabases {
+            receiver: receiver,
+            dir: dir,
+            databases: HashMap::new(),
+            next_connection_id: 0,
+            next_transaction_id: 0,
+        }
+    }
+
+    /// Handle messages until the manager exits. Committed transactions are saved eagerly,
+    /// so there is nothing to clean up then.
+    fn start(&mut self) {
+        while let Ok(message) = self.receiver.recv() {
+            match message {
+                IndexedDBThreadMsg::Open(sender, _, name, version_change_sender) => {
+                    let _ = sender.send(Ok(self.open(name, version_change_sender)));
+                },
+                IndexedDBThreadMsg::Close(_, name, id) => self.close(name, id),
+                IndexedDBThreadMsg::WaitForConnections(sender, _, name, except, new_version) => {
+                    self.wait_for_connections(sender, name, except, new_version)
+                },
+                IndexedDBThreadMsg::VersionChangeFired(_, name, id) => {
+                    let state = self.database_state(name);
+                    for wait in &mut state.waits {
+                        wait.unacknowledged.remove(&id);
+                    }
+                    state.update_waits();
+                },
+                IndexedDBThreadMsg::BeginTransaction(sender, _, name, mode, scope) => {
+                    self.enqueue(name, PendingRequest::Begin(sender, mode, scope))
+                },
+                IndexedDBThreadMsg::Operation(sender, _, name, id, operation) => {
+                    let _ = sender.send(self.perform(name, id, operation));
+                },
+                IndexedDBThreadMsg::Commit(sender, _, name, id) => {
+                    let _ = sender.send(self.commit(name, id));
+                },
+                IndexedDBThreadMsg::Abort(_, name, id) => self.abort(name, id),
+                IndexedDBThreadMsg::DeleteDatabase(sender, _, name) => {
+                    self.enqueue(name, PendingRequest::Delete(sender))
+                },
+                // Handled by the manager.
+                IndexedDBThreadMsg::Exit(_) => {},
+            }
+        }
+    }
+
+    fn database_state(&mut self, name: String) -> &mut DatabaseState {
+        let dir = &self.dir;
+        self.databases.entry(name.clone()).or_insert_with(|| {
+            // Database names can be too long for a file name, so their directory is named
+            // after their hash. The name of the database is saved in it.
+            let dir = dir.as_ref().map(|dir| dir.join(hashed_file_name(&name)));
+            let database = match dir {
+                Some(ref dir) if dir.exists() => Database::load(dir).unwrap_or_else(|e| {
+                    warn!("Couldn't read {}: {}", dir.display(), e);
+                    Database::new(name.clone())
+                }),
+                _ => Database::new(name),
+            };
+            DatabaseState {
+                database: database,
+                dir: dir,
+                transactions: HashMap::new(),
+                pending: VecDeque::new(),
+                connections: HashMap::new(),
+                waits: vec![],
+            }
+        })
+    }
+
+    fn open(
+        &mut self,
+        name: String,
+        version_change_sender: IpcSender<VersionChange>,
+    ) -> (u64, DatabaseMetadata) {
+        let id = self.next_connection_id;
+        self.next_connection_id += 1;
+        let state = self.database_state(name);
+        state.connections.insert(id, version_change_sender);
+        (id, state.database.metadata())
+    }
+
+    fn close(&mut self, name: String, id: u64) {
+        let state = self.database_state(name);
+        state.connections.remove(&id);
+        state.update_waits();
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#open-a-database> steps 5.7.1-5.7.5 and
+    /// <https://w3c.github.io/IndexedDB/#delete-a-database> steps 4-7, for the connections
+    /// of every global.
+    fn wait_for_connections(
+        &mut self,
+        sender: IpcSender<WaitEvent>,
+        name: String,
+        except: Option<u64>,
+        new_version: Option<u64>,
+    ) {
+        let state = self.database_state(name);
+        let version_change = VersionChange {
+            old_version: state.database.version(),
+            new_version: new_version,
+        };
+        // Connections whose global went away can't be told, and don't block anything.
+        state.connections.retain(|&id, connection| {
+            Some(id) == except || connection.send(version_change).is_ok()
+        });
+        let connections: HashSet<u64> = state
+            .connections
+            .keys()
+            .cloned()
+            .filter(|&id| Some(id) != except)
+            .collect();
+        state.waits.push(Wait {
+            sender: sender,
+            version_change: version_change,
+            unacknowledged: connections.clone(),
+            connections: connections,
+            blocked: false,
+        });
+        state.update_waits();
+    }
+
+    fn enqueue(&mut self, name: String, request: PendingRequest) {
+        self.database_state(name.clone()).pending.push_back(request);
+        self.process_pending(name);
+    }
+
+    /// Start the pending requests that don't conflict with running transactions, nor
+    /// with the requests that are waiting before them.
+    fn process_pending(&mut self, name: String) {
+        let next_transaction_id = &mut self.next_transaction_id;
+        let state = self
+            .databases
+            .get_mut(&name)
+            .expect("Database wasn't opened");
+        let mut waiting: Vec<PendingRequest> = vec![];
+        while let Some(request) = state.pending.pop_front() {
+            let blocked = state.transactions.values().any(|transaction| {
+                conflicts(
+                    request.mode(),
+                    request.scope(),
+                    transaction.mode,
+                    &transaction.scope,
+                )
+            }) || waiting.iter().any(|other| {
+                conflicts(request.mode(), request.scope(), other.mode(), other.scope())
+            });
+            if blocked {
+                waiting.push(request);
+                continue;
+            }
+            match request {
+                PendingRequest::Begin(sender, mode, scope) => {
+                    let id = *next_transaction_id;
+                    *next_transaction_id += 1;
+                    state.transactions.insert(
+                        id,
+                        Transaction {
+                            mode: mode,
+                            scope: scope,
+                            changes: Changes::default(),
+                        },
+                    );
+                    let _ = sender.send(id);
+                },
+                PendingRequest::Delete(sender) => {
+                    let version = state.database.version();
+                    let result = state.delete().map(|_| version).map_err(|e| {
+                        warn!("Couldn't delete a database: {}", e);
+                        IndexedDBError::Unknown
+                    });
+                    let _ = sender.send(result);
+                },
+            }
+        }
+        state.pending = waiting.into();
+    }
+
+    fn perform(
+        &mut self,
+        name: String,
+        id: u64,
+        operation: AsyncOperation,
+    ) -> Result<OperationResult, IndexedDBError> {
+        let state = self.database_state(name);
+        let transaction = match state.transactions.get_mut(&id) {
+            Some(transaction) => transaction,
+            None => return Err(IndexedDBError::Unknown),
+        };
+        state.database.perform(operation, &mut transaction.changes)
+    }
+
+    fn commit(&mut self, name: String, id: u64) -> Result<(), IndexedDBError> {
+        let state = self.database_state(name.clone());
+        let transaction = match state.transactions.remove(&id) {
+            Some(transaction) => transaction,
+            None => return Err(IndexedDBError::Unknown),
+        };
+        let mut result = Ok(());
+        if !transaction.changes.is_empty() {
+            if let Err(e) = state.save(&transaction.changes) {
+                warn!("Couldn't save a database: {}", e);
+                state.database.undo(transaction.changes);
+                result = Err(IndexedDBError::Unknown);
+            }
+        }
+        self.process_pending(name);
+        result
+    }
+
+    fn abort(&mut self, name: String, id: u64) {
+        {
+            let state = self.database_state(name.clone());
+            if let Some(transaction) = state.transactions.remove(&id) {
+                state.database.undo(transaction.changes);
+            }
+        }
+        self.process_pending(name);
+    }
+}
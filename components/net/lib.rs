@@ -28,10 +28,15 @@ pub mod filemanager_thread;
 mod hosts;
 pub mod hsts;
 pub mod http_cache;
+mod http_cache_store;
 pub mod http_loader;
 pub mod image_cache;
+pub mod indexeddb_data;
+mod indexeddb_thread;
 pub mod mime_classifier;
+pub mod proxy;
 pub mod resource_thread;
+pub mod storage_data;
 mod storage_thread;
 pub mod subresource_integrity;
 mod websocket_loader;
@@ -0,0 +1,222 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Support for sending requests through an HTTP proxy, configured with the
+//! `network.proxy.http`, `network.proxy.https` and `network.proxy.no-proxy` preferences.
+//!
+//! Plain HTTP requests are sent to the proxy in absolute-form, while HTTPS requests
+//! are tunnelled through it with a `CONNECT` request.
+
+use crate::resource_thread::AuthCacheEntry;
+use percent_encoding::percent_decode_str;
+use servo_url::ServoUrl;
+use std::io;
+use tokio::io::{read, write_all};
+use tokio::net::TcpStream;
+use tokio::prelude::future::{self, Loop};
+use tokio::prelude::Future;
+
+/// The largest response to a `CONNECT` request we are willing to buffer.
+const MAX_TUNNEL_RESPONSE_SIZE: usize = 16 * 1024;
+
+/// The proxies to use for outgoing requests.
+#[derive(Clone, Debug, Default)]
+pub struct ProxyConfig {
+    /// The proxy used for `http` URLs.
+    pub http: Option<ServoUrl>,
+    /// The proxy used for `https` URLs.
+    pub https: Option<ServoUrl>,
+    /// Hosts that are always connected to directly.
+    pub no_proxy: Vec<String>,
+}
+
+impl ProxyConfig {
+    /// Create a configuration from proxy URLs and a comma-separated list of hosts
+    /// that must not be proxied. Empty or invalid proxy URLs are ignored.
+    pub fn new(http: &str, https: &str, no_proxy: &str) -> ProxyConfig {
+        ProxyConfig {
+            http: parse_proxy_url(http),
+            https: parse_proxy_url(https),
+            no_proxy: no_proxy
+                .split(',')
+                .map(|host| host.trim().to_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect(),
+        }
+    }
+
+    /// The configuration specified by the current preferences.
+    pub fn from_prefs() -> ProxyConfig {
+        ProxyConfig::new(
+            &pref!(network.proxy.http),
+            &pref!(network.proxy.https),
+            &pref!(network.proxy.no_proxy),
+        )
+    }
+
+    /// The proxy to connect through to reach the given scheme, host and port, if any.
+    pub fn proxy_for(&self, scheme: &str, host: &str, port: Option<u16>) -> Option<&ServoUrl> {
+        let proxy = match scheme {
+            "http" => self.http.as_ref(),
+            "https" => self.https.as_ref(),
+            _ => None,
+        }?;
+        if self.bypasses(host, port) {
+            return None;
+        }
+        Some(proxy)
+    }
+
+    /// The proxy to connect through to fetch the given URL, if any.
+    pub fn proxy_for_url(&self, url: &ServoUrl) -> Option<&ServoUrl> {
+        self.proxy_for(url.scheme(), url.host_str()?, url.port_or_known_default())
+    }
+
+    /// Whether the no-proxy list matches the given host and port. Entries match the host itself
+    /// and all of its subdomains, optionally restricted to a port, and `*` matches every host.
+    fn bypasses(&self, host: &str, port: Option<u16>) -> bool {
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_lowercase();
+        self.no_proxy.iter().any(|entry| {
+            if entry == "*" {
+                return true;
+            }
+            let (entry_host, entry_port) = split_host_and_port(entry);
+            if let Some(entry_port) = entry_port {
+                if Some(entry_port) != port {
+                    return false;
+                }
+            }
+            let entry_host = entry_host.trim_start_matches('.');
+            host == entry_host || host.ends_with(&format!(".{}", entry_host))
+        })
+    }
+}
+
+fn parse_proxy_url(url: &str) -> Option<ServoUrl> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+    // Like most tools reading proxy settings, allow the scheme to be omitted.
+    let parsed = if url.contains("://") {
+        ServoUrl::parse(url)
+    } else {
+        ServoUrl::parse(&format!("http://{}", url))
+    };
+    let proxy = parsed
+        .ok()
+        .filter(|proxy| proxy.scheme() == "http" && proxy.host_str().is_some());
+    if proxy.is_none() {
+        warn!("Ignoring invalid proxy URL {}", url);
+    }
+    proxy
+}
+
+fn split_host_and_port(entry: &str) -> (&str, Option<u16>) {
+    // Avoid mistaking the colons of an IPv6 address for a port separator.
+    if let Some(end) = entry.rfind(']') {
+        let host = entry[..end].trim_start_matches('[');
+        let port = entry[end + 1..].trim_start_matches(':').parse().ok();
+        return (host, port);
+    }
+    match entry.rfind(':') {
+        Some(index) if entry[..index].find(':').is_none() => {
+            (&entry[..index], entry[index + 1..].parse().ok())
+        },
+        _ => (entry, None),
+    }
+}
+
+/// The credentials embedded in a proxy URL, as an entry for the proxy-authentication cache.
+pub fn proxy_credentials(proxy: &ServoUrl) -> Option<AuthCacheEntry> {
+    if proxy.username().is_empty() {
+        return None;
+    }
+    let decode = |s: &str| percent_decode_str(s).decode_utf8_lossy().into_owned();
+    Some(AuthCacheEntry {
+        user_name: decode(proxy.username()),
+        password: decode(proxy.password().unwrap_or("")),
+    })
+}
+
+/// The value of a `Proxy-Authorization` header carrying the given credentials.
+pub fn proxy_authorization_value(credentials: &AuthCacheEntry) -> String {
+    let encoded = base64::encode(&format!(
+        "{}:{}",
+        credentials.user_name, credentials.password
+    ));
+    format!("Basic {}", encoded)
+}
+
+/// Ask the proxy at the other end of `stream` to open a tunnel to `authority`,
+/// and resolve to the stream once the tunnel is established.
+pub fn tunnel(
+    stream: TcpStream,
+    authority: String,
+    proxy: &ServoUrl,
+) -> impl Future<Item = TcpStream, Error = io::Error> + Send {
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    // Proxy credentials are sent preemptively, since the 407 response to a CONNECT request
+    // never reaches the fetch algorithm that would otherwise handle the challenge.
+    if let Some(credentials) = proxy_credentials(proxy) {
+        request.push_str(&format!(
+            "Proxy-Authorization: {}\r\n",
+            proxy_authorization_value(&credentials)
+        ));
+    }
+    request.push_str("\r\n");
+    write_all(stream, request.into_bytes()).and_then(|(stream, _)| read_tunnel_response(stream))
+}
+
+fn read_tunnel_response(stream: TcpStream) -> impl Future<Item = TcpStream, Error = io::Error> {
+    future::loop_fn((stream, vec![]), |(stream, mut response)| {
+        read(stream, vec![0; 1024]).and_then(move |(stream, buffer, read_bytes)| {
+            if read_bytes == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "proxy closed the connection while opening a tunnel",
+                ));
+            }
+            response.extend_from_slice(&buffer[..read_bytes]);
+            if !response.windows(4).any(|window| window == b"\r\n\r\n") {
+                if response.len() > MAX_TUNNEL_RESPONSE_SIZE {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "proxy response to CONNECT is too large",
+                    ));
+                }
+                return Ok(Loop::Continue((stream, response)));
+            }
+            match tunnel_response_status(&response) {
+                Some(200..=299) => Ok(Loop::Break(stream)),
+                Some(407) => Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "proxy authentication required",
+                )),
+                Some(status) => Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("proxy refused to open a tunnel ({})", status),
+                )),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "malformed proxy response to CONNECT",
+                )),
+            }
+        })
+    })
+}
+
+/// The status code of the status line at the start of `response`.
+fn tunnel_response_status(response: &[u8]) -> Option<u16> {
+    let end = response.iter().position(|&byte| byte == b'\r')?;
+    let status_line = String::from_utf8_lossy(&response[..end]);
+    let mut parts = status_line.split(' ');
+    if !parts.next()?.starts_with("HTTP/1.") {
+        return None;
+    }
+    parts.next()?.parse().ok()
+}
@@ -5,7 +5,7 @@
 //! A thread that takes a URL and streams back the binary data.
 
 use crate::connector::{create_http_client, create_ssl_connector_builder};
-use crate::cookie;
+use crate::cookie::{self, SameSiteContext};
 use crate::cookie_storage::CookieStorage;
 use crate::fetch::cors_cache::CorsCache;
 use crate::fetch::methods::{fetch, CancellationListener, FetchContext};
@@ -13,15 +13,18 @@ use crate::filemanager_thread::FileManager;
 use crate::hsts::HstsList;
 use crate::http_cache::HttpCache;
 use crate::http_loader::{http_redirect_fetch, HttpState, HANDLE};
+use crate::indexeddb_thread::IndexedDBThreadFactory;
 use crate::storage_thread::StorageThreadFactory;
 use crate::websocket_loader;
 use crossbeam_channel::Sender;
 use devtools_traits::DevtoolsControlMsg;
 use embedder_traits::resources::{self, Resource};
 use embedder_traits::EmbedderProxy;
+use hyper::Method;
 use hyper_serde::Serde;
 use ipc_channel::ipc::{self, IpcReceiver, IpcReceiverSet, IpcSender};
 use malloc_size_of::{MallocSizeOf, MallocSizeOfOps};
+use net_traits::indexeddb_thread::IndexedDBThreadMsg;
 use net_traits::request::{Destination, RequestBuilder};
 use net_traits::response::{Response, ResponseInit};
 use net_traits::storage_thread::StorageThreadMsg;
@@ -29,7 +32,7 @@ use net_traits::WebSocketNetworkEvent;
 use net_traits::{CookieSource, CoreResourceMsg, CoreResourceThread};
 use net_traits::{CustomResponseMediator, FetchChannels};
 use net_traits::{FetchResponseMsg, ResourceThreads, WebSocketDomAction};
-use net_traits::{ResourceFetchTiming, ResourceTimingType};
+use net_traits::{ResourceFetchTiming, ResourceTimingType, SiteForCookies};
 use profile_traits::mem::ProfilerChan as MemProfilerChan;
 use profile_traits::mem::{Report, ReportKind, ReportsChan};
 use profile_traits::time::ProfilerChan;
@@ -64,10 +67,11 @@ pub fn new_resource_threads(
         config_dir.clone(),
         certificate_path,
     );
-    let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir);
+    let storage: IpcSender<StorageThreadMsg> = StorageThreadFactory::new(config_dir.clone());
+    let idb: IpcSender<IndexedDBThreadMsg> = IndexedDBThreadFactory::new(config_dir);
     (
-        ResourceThreads::new(public_core, storage.clone()),
-        ResourceThreads::new(private_core, storage),
+        ResourceThreads::new(public_core, storage.clone(), idb.clone()),
+        ResourceThreads::new(private_core, storage, idb),
     )
 }
 
@@ -125,12 +129,16 @@ fn create_http_states(
 ) -> (Arc<HttpState>, Arc<HttpState>) {
     let mut hsts_list = HstsList::from_servo_preload();
     let mut auth_cache = AuthCache::new();
-    let http_cache = HttpCache::new();
+    let mut http_cache = HttpCache::new();
     let mut cookie_jar = CookieStorage::new(150);
     if let Some(config_dir) = config_dir {
         read_json_from_file(&mut auth_cache, config_dir, "auth_cache.json");
         read_json_from_file(&mut hsts_list, config_dir, "hsts_list.json");
         read_json_from_file(&mut cookie_jar, config_dir, "cookie_jar.json");
+        if pref!(network.http_cache.disk.enabled) {
+            let max_size = pref!(network.http_cache.disk.max_size).max(0) as u64;
+            http_cache = HttpCache::new_with_store(config_dir.join("http_cache"), max_size);
+        }
     }
 
     let certs = match certificate_path {
@@ -142,6 +150,7 @@ fn create_http_states(
     let http_state = HttpState {
         cookie_jar: RwLock::new(cookie_jar),
         auth_cache: RwLock::new(auth_cache),
+        proxy_auth_cache: RwLock::new(AuthCache::new()),
         http_cache: RwLock::new(http_cache),
         hsts_list: RwLock::new(hsts_list),
         history_states: RwLock::new(HashMap::new()),
@@ -257,23 +266,31 @@ impl ResourceChannelManager {
             CoreResourceMsg::FetchRedirect(req_init, res_init, sender, cancel_chan) => self
                 .resource_manager
                 .fetch(req_init, Some(res_init), sender, http_state, cancel_chan),
-            CoreResourceMsg::SetCookieForUrl(request, cookie, source) => self
-                .resource_manager
-                .set_cookie_for_url(&request, cookie.into_inner(), source, http_state),
-            CoreResourceMsg::SetCookiesForUrl(request, cookies, source) => {
+            CoreResourceMsg::SetCookieForUrl(request, cookie, source) => {
+                self.resource_manager.set_cookie_for_url(
+                    &request,
+                    cookie.into_inner(),
+                    source,
+                    &SiteForCookies::UserAgent,
+                    http_state,
+                )
+            },
+            CoreResourceMsg::SetCookiesForUrl(request, cookies, source, site_for_cookies) => {
                 for cookie in cookies {
                     self.resource_manager.set_cookie_for_url(
                         &request,
                         cookie.into_inner(),
                         source,
+                        &site_for_cookies,
                         http_state,
                     );
                 }
             },
-            CoreResourceMsg::GetCookiesForUrl(url, consumer, source) => {
+            CoreResourceMsg::GetCookiesForUrl(url, consumer, source, site_for_cookies) => {
                 let mut cookie_jar = http_state.cookie_jar.write().unwrap();
+                let context = SameSiteContext::new(&url, &site_for_cookies, &Method::GET);
                 consumer
-                    .send(cookie_jar.cookies_for_url(&url, source))
+                    .send(cookie_jar.cookies_for_url(&url, source, context))
                     .unwrap();
             },
             CoreResourceMsg::NetworkMediator(mediator_chan) => {
@@ -447,9 +464,13 @@ impl CoreResourceManager {
         request: &ServoUrl,
         cookie: cookie_rs::Cookie<'static>,
         source: CookieSource,
+        site_for_cookies: &SiteForCookies,
         http_state: &Arc<HttpState>,
     ) {
         if let Some(cookie) = cookie::Cookie::new_wrapped(cookie, request, source) {
+            if !cookie.settable_from(request, site_for_cookies) {
+                return;
+            }
             let mut cookie_jar = http_state.cookie_jar.write().unwrap();
             cookie_jar.push(cookie, request, source)
         }
@@ -466,6 +487,7 @@ impl CoreResourceManager {
         let http_state = http_state.clone();
         let ua = self.user_agent.clone();
         let dc = self.devtools_chan.clone();
+        let swmanager_chan = self.swmanager_chan.clone();
         let filemanager = self.filemanager.clone();
 
         let timing_type = match request_builder.destination {
@@ -478,11 +500,11 @@ impl CoreResourceManager {
             // XXXManishearth: Check origin against pipeline id (also ensure that the mode is allowed)
             // todo load context / mimesniff in fetch
             // todo referrer policy?
-            // todo service worker stuff
             let context = FetchContext {
                 state: http_state,
                 user_agent: ua,
                 devtools_chan: dc,
+                swmanager_chan: swmanager_chan,
                 filemanager: filemanager,
                 cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(cancel_chan))),
                 timing: Arc::new(Mutex::new(ResourceFetchTiming::new(request.timing_type()))),
@@ -0,0 +1,357 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The key/value pairs stored by the Web Storage API, by origin.
+//!
+//! Persistent data is kept on disk as one snapshot and one journal per origin. Every change
+//! is appended to the journal of its origin, and the journal is folded into a new snapshot
+//! once it outgrows the snapshot, so the cost of a write doesn't depend on how much data is
+//! stored. Snapshots are written to a temporary file that then replaces the previous one,
+//! and a partially written journal entry is ignored when loading, so a crash loses at most
+//! the last change.
+
+use crate::resource_thread;
+use openssl::sha::sha256;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// The name of the file in which all the localStorage data used to be saved.
+const LEGACY_FILE_NAME: &str = "local_data.json";
+
+/// How many bytes the journal of an origin can grow past the size of its snapshot before
+/// a new snapshot is written.
+const JOURNAL_SLACK: u64 = 64 * 1024;
+
+#[derive(Deserialize, Serialize)]
+enum JournalEntry {
+    Set(String, String),
+    Remove(String),
+    Clear,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Snapshot {
+    origin: String,
+    entries: BTreeMap<String, String>,
+}
+
+/// The files in which the data of an origin is saved.
+struct OriginFiles {
+    snapshot_path: PathBuf,
+    journal_path: PathBuf,
+    /// The journal, opened for appending once the origin is first modified.
+    journal: Option<File>,
+    journal_len: u64,
+    snapshot_len: u64,
+}
+
+impl OriginFiles {
+    fn new(dir: &Path, origin: &str) -> OriginFiles {
+        // Origins can be longer than a file name can be, and contain characters that can't
+        // be used in one, so files are named after a hash of the origin. The origin itself is
+        // kept in the snapshot.
+        let name = hashed_file_name(origin);
+        OriginFiles {
+            snapshot_path: dir.join(format!("{}.json", name)),
+            journal_path: dir.join(format!("{}.journal", name)),
+            journal: None,
+            journal_len: 0,
+            snapshot_len: 0,
+        }
+    }
+
+    /// Read the saved data of the origin, replaying its journal over its snapshot. Returns
+    /// whether the journal had any entries.
+    fn load(&mut self, entries: &mut BTreeMap<String, String>) -> bool {
+        if let Ok(file) = File::open(&self.snapshot_path) {
+            self.snapshot_len = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            match serde_json::from_reader::<_, Snapshot>(BufReader::new(file)) {
+                Ok(snapshot) => *entries = snapshot.entries,
+                Err(e) => warn!("Couldn't read {}: {}", self.snapshot_path.display(), e),
+            }
+        }
+
+        let file = match File::open(&self.journal_path) {
+            Ok(file) => file,
+            Err(_) => return false,
+        };
+        let mut replayed = false;
+        for line in BufReader::new(file).lines() {
+            // A crash while appending an entry leaves it incomplete, and nothing was
+            // appended after it.
+            let entry = match line.map(|line| serde_json::from_str(&line)) {
+                Ok(Ok(entry)) => entry,
+                _ => break,
+            };
+            match entry {
+                JournalEntry::Set(name, value) => {
+                    entries.insert(name, value);
+                },
+                JournalEntry::Remove(name) => {
+                    entries.remove(&name);
+                },
+                JournalEntry::Clear => entries.clear(),
+            }
+            replayed = true;
+        }
+        replayed
+    }
+
+    fn append(&mut self, entry: &JournalEntry) -> io::Result<()> {
+        if self.journal.is_none() {
+            if let Some(dir) = self.journal_path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let journal = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.journal_path)?;
+            self.journal_len = journal.metadata()?.len();
+            self.journal = Some(journal);
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        self.journal.as_mut().unwrap().write_all(line.as_bytes())?;
+        self.journal_len += line.len() as u64;
+        Ok(())
+    }
+
+    fn needs_snapshot(&self) -> bool {
+        self.journal_len > self.snapshot_len + JOURNAL_SLACK
+    }
+
+    /// Replace the snapshot with the given data, and empty the journal.
+    fn write_snapshot(
+        &mut self,
+        origin: &str,
+        entries: &BTreeMap<String, String>,
+    ) -> io::Result<()> {
+        if let Some(dir) = self.snapshot_path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let snapshot = Snapshot {
+            origin: origin.to_owned(),
+            entries: entries.clone(),
+        };
+        let data = serde_json::to_vec(&snapshot)?;
+        let temporary_path = self.snapshot_path.with_extension("json.tmp");
+        {
+            let mut file = File::create(&temporary_path)?;
+            file.write_all(&data)?;
+            file.sync_all()?;
+        }
+        fs::rename(&temporary_path, &self.snapshot_path)?;
+        self.snapshot_len = data.len() as u64;
+
+        // Replaying the journal over the new snapshot would give the same data, so a
+        // crash before the journal is emptied loses nothing.
+        match self.journal {
+            Some(ref journal) => journal.set_len(0)?,
+            None => {
+                if self.journal_path.exists() {
+                    File::create(&self.journal_path)?;
+                }
+            },
+        }
+        self.journal_len = 0;
+        Ok(())
+    }
+
+    fn remove(&mut self) {
+        self.journal = None;
+        for path in &[&self.snapshot_path, &self.journal_path] {
+            if let Err(e) = fs::remove_file(path) {
+                if e.kind() != io::ErrorKind::NotFound {
+                    warn!("Couldn't remove {}: {}", path.display(), e);
+                }
+            }
+        }
+        self.journal_len = 0;
+        self.snapshot_len = 0;
+    }
+}
+
+struct OriginData {
+    /// The size of the names and values of all the entries, in bytes.
+    total: usize,
+    entries: BTreeMap<String, String>,
+    files: Option<OriginFiles>,
+}
+
+impl OriginData {
+    fn record(&mut self, origin: &str, entry: JournalEntry) {
+        let (files, entries) = match *self {
+            OriginData {
+                files: Some(ref mut files),
+                ref entries,
+                ..
+            } => (files, entries),
+            _ => return,
+        };
+        let result = files.append(&entry).and_then(|_| {
+            if files.needs_snapshot() {
+                files.write_snapshot(origin, entries)
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = result {
+            warn!("Couldn't save the storage data of {}: {}", origin, e);
+        }
+    }
+}
+
+/// A fixed-length file name for a name that can't be used as is, from its SHA-256 hash.
+pub(crate) fn hashed_file_name(name: &str) -> String {
+    sha256(name.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn entries_size(entries: &BTreeMap<String, String>) -> usize {
+    entries
+        .iter()
+        .map(|(name, value)| name.as_bytes().len() + value.as_bytes().len())
+        .sum()
+}
+
+/// The storage data of all origins, for one type of storage.
+pub struct StorageData {
+    /// The directory in which the data is saved, if it is persistent.
+    dir: Option<PathBuf>,
+    /// The data of the origins that were accessed so far.
+    origins: HashMap<String, OriginData>,
+}
+
+impl StorageData {
+    /// Create storage data that only lives in memory.
+    pub fn new_in_memory() -> StorageData {
+        StorageData {
+            dir: None,
+            origins: HashMap::new(),
+        }
+    }
+
+    /// Create storage data that is saved in the given directory. The data of an origin is
+    /// read the first time it is accessed. Data saved by previous versions in `config_dir`
+    /// is moved to the new directory.
+    pub fn new_persistent(dir: PathBuf, config_dir: &Path) -> StorageData {
+        let mut data = StorageData {
+            dir: Some(dir),
+            origins: HashMap::new(),
+        };
+        data.import_legacy_file(config_dir);
+        data
+    }
+
+    fn import_legacy_file(&mut self, config_dir: &Path) {
+        let legacy_path = config_dir.join(LEGACY_FILE_NAME);
+        if !legacy_path.exists() {
+            return;
+        }
+        let mut legacy_data: HashMap<String, (usize, BTreeMap<String, String>)> = HashMap::new();
+        resource_thread::read_json_from_file(&mut legacy_data, config_dir, LEGACY_FILE_NAME);
+        for (origin, (_, entries)) in legacy_data {
+            let data = self.origin_mut(&origin);
+            if !data.entries.is_empty() {
+                continue;
+            }
+            data.total = entries_size(&entries);
+            data.entries = entries;
+            if let Some(ref mut files) = data.files {
+                if let Err(e) = files.write_snapshot(&origin, &data.entries) {
+                    warn!("Couldn't import the storage data of {}: {}", origin, e);
+                    return;
+                }
+            }
+        }
+        if let Err(e) = fs::remove_file(&legacy_path) {
+            warn!("Couldn't remove {}: {}", legacy_path.display(), e);
+        }
+    }
+
+    fn origin_mut(&mut self, origin: &str) -> &mut OriginData {
+        let dir = &self.dir;
+        self.origins.entry(origin.to_owned()).or_insert_with(|| {
+            let mut entries = BTreeMap::new();
+            let files = dir.as_ref().map(|dir| {
+                let mut files = OriginFiles::new(dir, origin);
+                // Start over from a fresh snapshot, rather than appending to a journal
+                // that could end with an incomplete entry.
+                if files.load(&mut entries) {
+                    if let Err(e) = files.write_snapshot(origin, &entries) {
+                        warn!("Couldn't save the storage data of {}: {}", origin, e);
+                    }
+                }
+                files
+            });
+            OriginData {
+                total: entries_size(&entries),
+                entries: entries,
+                files: files,
+            }
+        })
+    }
+
+    /// The entries of the given origin.
+    pub fn entries(&mut self, origin: &str) -> &BTreeMap<String, String> {
+        &self.origin_mut(origin).entries
+    }
+
+    /// The size of the names and values of the entries of the given origin, in bytes.
+    pub fn total_size(&mut self, origin: &str) -> usize {
+        self.origin_mut(origin).total
+    }
+
+    /// Set the value of an entry, returning its previous value if any. The quota is
+    /// enforced by the caller.
+    pub fn set_item(&mut self, origin: &str, name: String, value: String) -> Option<String> {
+        let data = self.origin_mut(origin);
+        data.total += value.as_bytes().len();
+        let old_value = data.entries.insert(name.clone(), value.clone());
+        match old_value {
+            Some(ref old_value) => data.total -= old_value.as_bytes().len(),
+            None => data.total += name.as_bytes().len(),
+        }
+        if old_value.as_ref() != Some(&value) {
+            data.record(origin, JournalEntry::Set(name, value));
+        }
+        old_value
+    }
+
+    /// Remove an entry, returning its value if it existed.
+    pub fn remove_item(&mut self, origin: &str, name: &str) -> Option<String> {
+        let data = self.origin_mut(origin);
+        let old_value = data.entries.remove(name)?;
+        data.total -= name.as_bytes().len() + old_value.as_bytes().len();
+        data.record(origin, JournalEntry::Remove(name.to_owned()));
+        Some(old_value)
+    }
+
+    /// Remove all the entries of an origin, returning whether there were any.
+    pub fn clear(&mut self, origin: &str) -> bool {
+        let data = self.origin_mut(origin);
+        if data.entries.is_empty() {
+            return false;
+        }
+        data.entries.clear();
+        data.total = 0;
+        data.record(origin, JournalEntry::Clear);
+        true
+    }
+
+    /// Forget everything about an origin, including the files its data was saved to.
+    pub fn remove_origin(&mut self, origin: &str) {
+        if let Some(mut data) = self.origins.remove(origin) {
+            if let Some(ref mut files) = data.files {
+                files.remove();
+            }
+        } else if let Some(ref dir) = self.dir {
+            OriginFiles::new(dir, origin).remove();
+        }
+    }
+}
@@ -2,13 +2,11 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use crate::resource_thread;
+use crate::storage_data::StorageData;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use net_traits::storage_thread::{StorageThreadMsg, StorageType};
 use servo_url::ServoUrl;
 use std::borrow::ToOwned;
-use std::collections::BTreeMap;
-use std::collections::HashMap;
 use std::path::PathBuf;
 use std::thread;
 
@@ -34,22 +32,22 @@ impl StorageThreadFactory for IpcSender<StorageThreadMsg> {
 
 struct StorageManager {
     port: IpcReceiver<StorageThreadMsg>,
-    session_data: HashMap<String, (usize, BTreeMap<String, String>)>,
-    local_data: HashMap<String, (usize, BTreeMap<String, String>)>,
-    config_dir: Option<PathBuf>,
+    session_data: StorageData,
+    local_data: StorageData,
 }
 
 impl StorageManager {
     fn new(port: IpcReceiver<StorageThreadMsg>, config_dir: Option<PathBuf>) -> StorageManager {
-        let mut local_data = HashMap::new();
-        if let Some(ref config_dir) = config_dir {
-            resource_thread::read_json_from_file(&mut local_data, config_dir, "local_data.json");
-        }
+        let local_data = match config_dir {
+            Some(ref config_dir) => {
+                StorageData::new_persistent(config_dir.join("local_storage"), config_dir)
+            },
+            None => StorageData::new_in_memory(),
+        };
         StorageManager {
             port: port,
-            session_data: HashMap::new(),
+            session_data: StorageData::new_in_memory(),
             local_data: local_data,
-            config_dir: config_dir,
         }
     }
 }
@@ -68,22 +66,20 @@ impl StorageManager {
                     self.keys(sender, url, storage_type)
                 },
                 StorageThreadMsg::SetItem(sender, url, storage_type, name, value) => {
-                    self.set_item(sender, url, storage_type, name, value);
-                    self.save_state()
+                    self.set_item(sender, url, storage_type, name, value)
                 },
                 StorageThreadMsg::GetItem(sender, url, storage_type, name) => {
                     self.request_item(sender, url, storage_type, name)
                 },
                 StorageThreadMsg::RemoveItem(sender, url, storage_type, name) => {
-                    self.remove_item(sender, url, storage_type, name);
-                    self.save_state()
+                    self.remove_item(sender, url, storage_type, name)
                 },
                 StorageThreadMsg::Clear(sender, url, storage_type) => {
-                    self.clear(sender, url, storage_type);
-                    self.save_state()
+                    self.clear(sender, url, storage_type)
                 },
+                StorageThreadMsg::ClearOrigin(url) => self.clear_origin(url),
                 StorageThreadMsg::Exit(sender) => {
-                    // Nothing to do since we save localstorage set eagerly.
+                    // Nothing to do since we save localstorage changes eagerly.
                     let _ = sender.send(());
                     break;
                 },
@@ -91,62 +87,36 @@ impl StorageManager {
         }
     }
 
-    fn save_state(&self) {
-        if let Some(ref config_dir) = self.config_dir {
-            resource_thread::write_json_to_file(&self.local_data, config_dir, "local_data.json");
-        }
-    }
-
-    fn select_data(
-        &self,
-        storage_type: StorageType,
-    ) -> &HashMap<String, (usize, BTreeMap<String, String>)> {
-        match storage_type {
-            StorageType::Session => &self.session_data,
-            StorageType::Local => &self.local_data,
-        }
-    }
-
-    fn select_data_mut(
-        &mut self,
-        storage_type: StorageType,
-    ) -> &mut HashMap<String, (usize, BTreeMap<String, String>)> {
+    fn select_data_mut(&mut self, storage_type: StorageType) -> &mut StorageData {
         match storage_type {
             StorageType::Session => &mut self.session_data,
             StorageType::Local => &mut self.local_data,
         }
     }
 
-    fn length(&self, sender: IpcSender<usize>, url: ServoUrl, storage_type: StorageType) {
+    fn length(&mut self, sender: IpcSender<usize>, url: ServoUrl, storage_type: StorageType) {
         let origin = self.origin_as_string(url);
-        let data = self.select_data(storage_type);
-        sender
-            .send(data.get(&origin).map_or(0, |&(_, ref entry)| entry.len()))
-            .unwrap();
+        let data = self.select_data_mut(storage_type);
+        sender.send(data.entries(&origin).len()).unwrap();
     }
 
     fn key(
-        &self,
+        &mut self,
         sender: IpcSender<Option<String>>,
         url: ServoUrl,
         storage_type: StorageType,
         index: u32,
     ) {
         let origin = self.origin_as_string(url);
-        let data = self.select_data(storage_type);
-        let key = data
-            .get(&origin)
-            .and_then(|&(_, ref entry)| entry.keys().nth(index as usize))
-            .cloned();
+        let data = self.select_data_mut(storage_type);
+        let key = data.entries(&origin).keys().nth(index as usize).cloned();
         sender.send(key).unwrap();
     }
 
-    fn keys(&self, sender: IpcSender<Vec<String>>, url: ServoUrl, storage_type: StorageType) {
+    fn keys(&mut self, sender: IpcSender<Vec<String>>, url: ServoUrl, storage_type: StorageType) {
         let origin = self.origin_as_string(url);
-        let data = self.select_data(storage_type);
-        let keys = data
-            .get(&origin)
-            .map_or(vec![], |&(_, ref entry)| entry.keys().cloned().collect());
+        let data = self.select_data_mut(storage_type);
+        let keys = data.entries(&origin).keys().cloned().collect();
 
         sender.send(keys).unwrap();
     }
@@ -165,68 +135,43 @@ impl StorageManager {
     ) {
         let origin = self.origin_as_string(url);
 
-        let (this_storage_size, other_storage_size) = {
-            let local_data = self.select_data(StorageType::Local);
-            let session_data = self.select_data(StorageType::Session);
-            let local_data_size = local_data.get(&origin).map_or(0, |&(total, _)| total);
-            let session_data_size = session_data.get(&origin).map_or(0, |&(total, _)| total);
-            match storage_type {
-                StorageType::Local => (local_data_size, session_data_size),
-                StorageType::Session => (session_data_size, local_data_size),
-            }
-        };
+        let local_data_size = self.local_data.total_size(&origin);
+        let session_data_size = self.session_data.total_size(&origin);
 
         let data = self.select_data_mut(storage_type);
-        if !data.contains_key(&origin) {
-            data.insert(origin.clone(), (0, BTreeMap::new()));
+        let mut new_total_size = local_data_size + session_data_size + value.as_bytes().len();
+        match data.entries(&origin).get(&name) {
+            Some(old_value) => new_total_size -= old_value.as_bytes().len(),
+            None => new_total_size += name.as_bytes().len(),
+        }
+        if new_total_size > QUOTA_SIZE_LIMIT {
+            return sender.send(Err(())).unwrap();
         }
 
-        let message = data
-            .get_mut(&origin)
-            .map(|&mut (ref mut total, ref mut entry)| {
-                let mut new_total_size = this_storage_size + value.as_bytes().len();
-                if let Some(old_value) = entry.get(&name) {
-                    new_total_size -= old_value.as_bytes().len();
+        let message = match data.set_item(&origin, name, value.clone()) {
+            None => Ok((true, None)),
+            Some(old) => {
+                if old == value {
+                    Ok((false, None))
                 } else {
-                    new_total_size += name.as_bytes().len();
+                    Ok((true, Some(old)))
                 }
-
-                if (new_total_size + other_storage_size) > QUOTA_SIZE_LIMIT {
-                    return Err(());
-                }
-
-                let message =
-                    entry
-                        .insert(name.clone(), value.clone())
-                        .map_or(Ok((true, None)), |old| {
-                            if old == value {
-                                Ok((false, None))
-                            } else {
-                                Ok((true, Some(old)))
-                            }
-                        });
-                *total = new_total_size;
-                message
-            })
-            .unwrap();
+            },
+        };
         sender.send(message).unwrap();
     }
 
     fn request_item(
-        &self,
+        &mut self,
         sender: IpcSender<Option<String>>,
         url: ServoUrl,
         storage_type: StorageType,
         name: String,
     ) {
         let origin = self.origin_as_string(url);
-        let data = self.select_data(storage_type);
+        let data = self.select_data_mut(storage_type);
         sender
-            .send(
-                data.get(&origin)
-                    .and_then(|&(_, ref entry)| entry.get(&name))
-                    .map(String::clone),
-            )
+            .send(data.entries(&origin).get(&name).cloned())
             .unwrap();
     }
 
@@ -240,34 +185,21 @@ impl StorageManager {
     ) {
         let origin = self.origin_as_string(url);
         let data = self.select_data_mut(storage_type);
-        let old_value = data
-            .get_mut(&origin)
-            .and_then(|&mut (ref mut total, ref mut entry)| {
-                entry.remove(&name).and_then(|old| {
-                    *total -= name.as_bytes().len() + old.as_bytes().len();
-                    Some(old)
-                })
-            });
-        sender.send(old_value).unwrap();
+        sender.send(data.remove_item(&origin, &name)).unwrap();
     }
 
     fn clear(&mut self, sender: IpcSender<bool>, url: ServoUrl, storage_type: StorageType) {
         let origin = self.origin_as_string(url);
         let data = self.select_data_mut(storage_type);
-        sender
-            .send(
-                data.get_mut(&origin)
-                    .map_or(false, |&mut (ref mut total, ref mut entry)| {
-                        if !entry.is_empty() {
-                            entry.clear();
-                            *total = 0;
-                            true
-                        } else {
-                            false
-                        }
-                    }),
-            )
-            .unwrap();
+        sender.send(data.clear(&origin)).unwrap();
+    }
+
+    /// Remove all the storage data of the origin of the given url, including the data
+    /// saved on disk.
+    fn clear_origin(&mut self, url: ServoUrl) {
+        let origin = self.origin_as_string(url);
+        self.session_data.remove_origin(&origin);
+        self.local_data.remove_origin(&origin);
     }
 
     fn origin_as_string(&self, url: ServoUrl) -> String {
@@ -2,7 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use net::cookie::Cookie;
+use net::cookie::{Cookie, SameSiteContext};
 use net::cookie_storage::CookieStorage;
 use net_traits::CookieSource;
 use servo_url::ServoUrl;
@@ -247,7 +247,9 @@ fn test_insecure_cookies_cannot_evict_secure_cookie() {
 
     let source = CookieSource::HTTP;
     assert_eq!(
-        storage.cookies_for_url(&secure_url, source).unwrap(),
+        storage
+            .cookies_for_url(&secure_url, source, SameSiteContext::SameSite)
+            .unwrap(),
         "foo=bar; foo2=bar"
     );
 
@@ -255,7 +257,9 @@ fn test_insecure_cookies_cannot_evict_secure_cookie() {
         ServoUrl::parse("https://home.example.org:8888/foo/cookie-parser-result?0001").unwrap();
     let source = CookieSource::HTTP;
     assert_eq!(
-        storage.cookies_for_url(&url, source).unwrap(),
+        storage
+            .cookies_for_url(&url, source, SameSiteContext::SameSite)
+            .unwrap(),
         "foo3=bar; foo4=value; foo=bar; foo2=bar"
     );
 
@@ -263,7 +267,9 @@ fn test_insecure_cookies_cannot_evict_secure_cookie() {
         ServoUrl::parse("https://home.example.org:8888/foo/bar/cookie-parser-result?0001").unwrap();
     let source = CookieSource::HTTP;
     assert_eq!(
-        storage.cookies_for_url(&url, source).unwrap(),
+        storage
+            .cookies_for_url(&url, source, SameSiteContext::SameSite)
+            .unwrap(),
         "foo4=bar; foo3=bar; foo4=value; foo=bar; foo2=bar"
     );
 }
@@ -291,13 +297,20 @@ fn test_secure_cookies_eviction() {
     add_cookie_to_storage(&mut storage, &url, "foo4=value; Path=/foo");
 
     let source = CookieSource::HTTP;
-    assert_eq!(storage.cookies_for_url(&url, source).unwrap(), "foo2=value");
+    assert_eq!(
+        storage
+            .cookies_for_url(&url, source, SameSiteContext::SameSite)
+            .unwrap(),
+        "foo2=value"
+    );
 
     let url =
         ServoUrl::parse("https://home.example.org:8888/foo/cookie-parser-result?0001").unwrap();
     let source = CookieSource::HTTP;
     assert_eq!(
-        storage.cookies_for_url(&url, source).unwrap(),
+        storage
+            .cookies_for_url(&url, source, SameSiteContext::SameSite)
+            .unwrap(),
         "foo3=bar; foo4=value; foo2=value"
     );
 
@@ -305,7 +318,9 @@ fn test_secure_cookies_eviction() {
         ServoUrl::parse("https://home.example.org:8888/foo/bar/cookie-parser-result?0001").unwrap();
     let source = CookieSource::HTTP;
     assert_eq!(
-        storage.cookies_for_url(&url, source).unwrap(),
+        storage
+            .cookies_for_url(&url, source, SameSiteContext::SameSite)
+            .unwrap(),
         "foo4=bar; foo3=value; foo3=bar; foo4=value; foo2=value"
     );
 }
@@ -333,13 +348,20 @@ fn test_secure_cookies_eviction_non_http_source() {
     add_cookie_to_storage(&mut storage, &url, "foo4=value; Path=/foo");
 
     let source = CookieSource::HTTP;
-    assert_eq!(storage.cookies_for_url(&url, source).unwrap(), "foo2=value");
+    assert_eq!(
+        storage
+            .cookies_for_url(&url, source, SameSiteContext::SameSite)
+            .unwrap(),
+        "foo2=value"
+    );
 
     let url =
         ServoUrl::parse("https://home.example.org:8888/foo/cookie-parser-result?0001").unwrap();
     let source = CookieSource::HTTP;
     assert_eq!(
-        storage.cookies_for_url(&url, source).unwrap(),
+        storage
+            .cookies_for_url(&url, source, SameSiteContext::SameSite)
+            .unwrap(),
         "foo3=bar; foo4=value; foo2=value"
     );
 
@@ -347,7 +369,9 @@ fn test_secure_cookies_eviction_non_http_source() {
         ServoUrl::parse("https://home.example.org:8888/foo/bar/cookie-parser-result?0001").unwrap();
     let source = CookieSource::HTTP;
     assert_eq!(
-        storage.cookies_for_url(&url, source).unwrap(),
+        storage
+            .cookies_for_url(&url, source, SameSiteContext::SameSite)
+            .unwrap(),
         "foo4=bar; foo3=value; foo3=bar; foo4=value; foo2=value"
     );
 }
@@ -370,7 +394,7 @@ fn add_retrieve_cookies(
     // Get cookies for the test location
     let url = ServoUrl::parse(final_location).unwrap();
     storage
-        .cookies_for_url(&url, source)
+        .cookies_for_url(&url, source, SameSiteContext::SameSite)
         .unwrap_or("".to_string())
 }
 
@@ -2,12 +2,30 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use net::cookie::Cookie;
+use http::Method;
+use net::cookie::{Cookie, SameSiteContext};
 use net::cookie_storage::CookieStorage;
-use net_traits::CookieSource;
+use net_traits::request::RequestBuilder;
+use net_traits::{CookieSource, SiteForCookies};
 use servo_url::ServoUrl;
 
 fn run(set_location: &str, set_cookies: &[&str], final_location: &str) -> String {
+    run_from_site(
+        set_location,
+        set_cookies,
+        final_location,
+        &SiteForCookies::UserAgent,
+        &Method::GET,
+    )
+}
+
+fn run_from_site(
+    set_location: &str,
+    set_cookies: &[&str],
+    final_location: &str,
+    site_for_cookies: &SiteForCookies,
+    method: &Method,
+) -> String {
     let mut storage = CookieStorage::new(150);
     let url = ServoUrl::parse(set_location).unwrap();
     let source = CookieSource::HTTP;
@@ -16,17 +34,125 @@ fn run(set_location: &str, set_cookies: &[&str], final_location: &str) -> String
     for str_cookie in set_cookies {
         if let Some(cookie) = Cookie::from_cookie_string(str_cookie.to_owned().into(), &url, source)
         {
-            storage.push(cookie, &url, source);
+            if cookie.settable_from(&url, site_for_cookies) {
+                storage.push(cookie, &url, source);
+            }
         }
     }
 
     // Get cookies for the test location
     let url = ServoUrl::parse(final_location).unwrap();
+    let context = SameSiteContext::new(&url, site_for_cookies, method);
     storage
-        .cookies_for_url(&url, source)
+        .cookies_for_url(&url, source, context)
         .unwrap_or("".to_string())
 }
 
+fn site(url: &str) -> ServoUrl {
+    ServoUrl::parse(url).unwrap()
+}
+
+const SAME_SITE_COOKIES: &[&str] = &["none=1", "lax=1; SameSite=Lax", "strict=1; SameSite=Strict"];
+
+#[test]
+fn test_samesite_same_site_request() {
+    let top_level = site("http://www.example.org/").origin();
+    let r = run_from_site(
+        "http://home.example.org:8888/cookie-parser?samesite",
+        SAME_SITE_COOKIES,
+        "http://home.example.org:8888/cookie-parser-result?samesite",
+        &SiteForCookies::Document(Some(top_level)),
+        &Method::GET,
+    );
+    assert_eq!(&r, "none=1; lax=1; strict=1");
+}
+
+#[test]
+fn test_samesite_cross_site_subresource() {
+    let mut storage = CookieStorage::new(150);
+    let url = site("http://home.example.org:8888/cookie-parser?samesite");
+    for str_cookie in SAME_SITE_COOKIES {
+        let cookie =
+            Cookie::from_cookie_string(str_cookie.to_string(), &url, CookieSource::HTTP).unwrap();
+        storage.push(cookie, &url, CookieSource::HTTP);
+    }
+
+    let cross_site = SiteForCookies::Document(Some(site("http://other.test/").origin()));
+    let context = SameSiteContext::new(&url, &cross_site, &Method::GET);
+    let r = storage.cookies_for_url(&url, CookieSource::HTTP, context);
+    assert_eq!(r.as_ref().map(|s| &**s), Some("none=1"));
+
+    // A document nested in a cross-site frame has no site for cookies.
+    let context = SameSiteContext::new(&url, &SiteForCookies::Document(None), &Method::GET);
+    let r = storage.cookies_for_url(&url, CookieSource::HTTP, context);
+    assert_eq!(r.as_ref().map(|s| &**s), Some("none=1"));
+}
+
+#[test]
+fn test_samesite_request_without_site_for_cookies() {
+    let url = site("http://home.example.org:8888/cookie-parser?samesite");
+    let request = RequestBuilder::new(url.clone()).build();
+    let r = run_from_site(
+        url.as_str(),
+        SAME_SITE_COOKIES,
+        url.as_str(),
+        &request.site_for_cookies,
+        &Method::GET,
+    );
+    assert_eq!(&r, "none=1");
+}
+
+#[test]
+fn test_samesite_not_set_from_cross_site_context() {
+    let mut storage = CookieStorage::new(150);
+    let url = site("http://home.example.org:8888/cookie-parser?samesite");
+    let cross_site = SiteForCookies::Document(Some(site("http://other.test/").origin()));
+    for str_cookie in SAME_SITE_COOKIES {
+        let cookie =
+            Cookie::from_cookie_string(str_cookie.to_string(), &url, CookieSource::HTTP).unwrap();
+        if cookie.settable_from(&url, &cross_site) {
+            storage.push(cookie, &url, CookieSource::HTTP);
+        }
+    }
+    let r = storage.cookies_for_url(&url, CookieSource::HTTP, SameSiteContext::SameSite);
+    assert_eq!(r.as_ref().map(|s| &**s), Some("none=1"));
+}
+
+#[test]
+fn test_samesite_cross_site_top_level_navigation() {
+    let initiator = SiteForCookies::TopLevelNavigation(site("http://other.test/").origin());
+    let r = run_from_site(
+        "http://home.example.org:8888/cookie-parser?samesite",
+        SAME_SITE_COOKIES,
+        "http://home.example.org:8888/cookie-parser-result?samesite",
+        &initiator,
+        &Method::GET,
+    );
+    assert_eq!(&r, "none=1; lax=1");
+
+    let r = run_from_site(
+        "http://home.example.org:8888/cookie-parser?samesite",
+        SAME_SITE_COOKIES,
+        "http://home.example.org:8888/cookie-parser-result?samesite",
+        &initiator,
+        &Method::POST,
+    );
+    assert_eq!(&r, "none=1");
+}
+
+#[test]
+fn test_samesite_same_site_top_level_navigation() {
+    let initiator = SiteForCookies::TopLevelNavigation(site("https://example.org/").origin());
+    let r = run_from_site(
+        "http://home.example.org:8888/cookie-parser?samesite",
+        SAME_SITE_COOKIES,
+        "http://home.example.org:8888/cookie-parser-result?samesite",
+        &initiator,
+        &Method::POST,
+    );
+    assert_eq!(&r, "none=1; lax=1; strict=1");
+}
+
 // Following are all tests extracted from https://github.com/abarth/http-state.git
 // They are generated by `./mach update-net-cookies`
 // Test listing
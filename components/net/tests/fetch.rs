@@ -9,7 +9,7 @@ use crate::{
     create_embedder_proxy, fetch, make_server, make_ssl_server, new_fetch_context,
     DEFAULT_USER_AGENT,
 };
-use crossbeam_channel::{unbounded, Sender};
+use crossbeam_channel::{unbounded, Receiver, Sender};
 use devtools_traits::HttpRequest as DevtoolsHttpRequest;
 use devtools_traits::HttpResponse as DevtoolsHttpResponse;
 use headers::{AccessControlAllowCredentials, AccessControlAllowHeaders, AccessControlAllowOrigin};
@@ -22,6 +22,7 @@ use http::uri::Authority;
 use http::{Method, StatusCode};
 use hyper::body::Body;
 use hyper::{Request as HyperRequest, Response as HyperResponse};
+use ipc_channel::ipc::{self, IpcSender};
 use mime::{self, Mime};
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use net::connector::create_ssl_connector_builder;
@@ -30,12 +31,15 @@ use net::fetch::methods::{self, CancellationListener, FetchContext};
 use net::filemanager_thread::FileManager;
 use net::hsts::HstsEntry;
 use net::test::HttpState;
+use net_traits::csp::{CspList, PolicyDisposition, PolicySource, Violation};
 use net_traits::request::{Destination, Origin, RedirectMode, Referrer, Request, RequestMode};
 use net_traits::response::{CacheState, Response, ResponseBody, ResponseType};
+use net_traits::{CustomResponse, CustomResponseMediator};
 use net_traits::{
     FetchTaskTarget, IncludeSubdomains, NetworkError, ReferrerPolicy, ResourceFetchTiming,
     ResourceTimingType,
 };
+use servo_config::set_pref;
 use servo_url::{ImmutableOrigin, ServoUrl};
 use std::fs::File;
 use std::io::Read;
@@ -43,6 +47,7 @@ use std::iter::FromIterator;
 use std::path::Path;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
@@ -107,6 +112,22 @@ fn test_fetch_response_body_matches_const_message() {
     };
 }
 
+#[test]
+fn test_fetch_blocked_by_csp() {
+    let url = ServoUrl::parse("http://www.example.org/image.png").unwrap();
+    let origin = Origin::Origin(ServoUrl::parse("http://www.example.com").unwrap().origin());
+    let mut request = Request::new(url, Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Image;
+    request.csp_list = Some(CspList::parse(
+        "img-src 'self'",
+        PolicySource::Header,
+        PolicyDisposition::Enforce,
+    ));
+    let fetch_response = fetch(&mut request, None);
+    assert!(fetch_response.is_network_error());
+}
+
 #[test]
 fn test_fetch_aboutblank() {
     let url = ServoUrl::parse("about:blank").unwrap();
@@ -143,6 +164,7 @@ fn test_fetch_blob() {
             assert_eq!(self.buffer, self.expected);
             let _ = self.sender.send(response.clone());
         }
+        fn process_csp_violations(&mut self, _: &Request, _: Vec<Violation>) {}
     }
 
     let context = new_fetch_context(None, None);
@@ -663,6 +685,7 @@ fn test_fetch_with_hsts() {
         state: Arc::new(HttpState::new(ssl_client)),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: None,
+        swmanager_chan: None,
         filemanager: FileManager::new(create_embedder_proxy()),
         cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(None))),
         timing: Arc::new(Mutex::new(ResourceFetchTiming::new(
@@ -1120,3 +1143,77 @@ fn test_fetch_with_devtools() {
     assert_eq!(devhttprequest, httprequest);
     assert_eq!(devhttpresponse, httpresponse);
 }
+
+/// Stand in for the service worker manager, answering each request it is sent with
+/// `response`, and reporting the url of the request.
+fn intercept_fetches(
+    response: Option<CustomResponse>,
+) -> (IpcSender<CustomResponseMediator>, Receiver<ServoUrl>) {
+    let (swmanager_chan, swmanager_port) = ipc::channel::<CustomResponseMediator>().unwrap();
+    let (url_sender, url_receiver) = unbounded();
+    thread::spawn(move || {
+        while let Ok(mediator) = swmanager_port.recv() {
+            let _ = url_sender.send(mediator.request.url.clone());
+            let _ = mediator.response_chan.send(response.clone().map(Ok));
+        }
+    });
+    (swmanager_chan, url_receiver)
+}
+
+fn fetch_document_with_service_worker(
+    url: &ServoUrl,
+    swmanager_chan: IpcSender<CustomResponseMediator>,
+) -> Response {
+    let origin = Origin::Origin(url.origin());
+    let mut request = Request::new(url.clone(), Some(origin), None);
+    request.referrer = Referrer::NoReferrer;
+    request.destination = Destination::Document;
+    let mut context = new_fetch_context(None, None);
+    context.swmanager_chan = Some(swmanager_chan);
+    fetch_with_context(&mut request, &mut context)
+}
+
+#[test]
+fn test_fetch_controlled_by_service_worker() {
+    set_pref!(dom.serviceworker.enabled, true);
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        *response.body_mut() = b"From the network".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+    let (swmanager_chan, intercepted) = intercept_fetches(Some(CustomResponse::new(
+        HeaderMap::new(),
+        (StatusCode::OK, "OK".to_owned()),
+        b"From the service worker".to_vec(),
+    )));
+
+    let fetch_response = fetch_document_with_service_worker(&url, swmanager_chan);
+    let _ = server.close();
+
+    assert_eq!(intercepted.recv().unwrap(), url);
+    assert!(!fetch_response.is_network_error());
+    assert_eq!(fetch_response.status.as_ref().unwrap().0, StatusCode::OK);
+    match *fetch_response.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => assert_eq!(&**body, b"From the service worker"),
+        _ => panic!(),
+    };
+}
+
+#[test]
+fn test_fetch_not_controlled_by_service_worker_goes_to_network() {
+    set_pref!(dom.serviceworker.enabled, true);
+    let handler = move |_: HyperRequest<Body>, response: &mut HyperResponse<Body>| {
+        *response.body_mut() = b"From the network".to_vec().into();
+    };
+    let (server, url) = make_server(handler);
+    let (swmanager_chan, intercepted) = intercept_fetches(None);
+
+    let fetch_response = fetch_document_with_service_worker(&url, swmanager_chan);
+    let _ = server.close();
+
+    assert_eq!(intercepted.recv().unwrap(), url);
+    assert!(!fetch_response.is_network_error());
+    match *fetch_response.body.lock().unwrap() {
+        ResponseBody::Done(ref body) => assert_eq!(&**body, b"From the network"),
+        _ => panic!(),
+    };
+}
@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crossbeam_channel::unbounded;
-use http::header::{HeaderValue, EXPIRES};
+use http::header::{HeaderValue, CACHE_CONTROL, EXPIRES};
 use http::StatusCode;
 use msg::constellation_msg::TEST_PIPELINE_ID;
 use net::http_cache::HttpCache;
@@ -11,6 +11,32 @@ use net_traits::request::{Origin, Request};
 use net_traits::response::{Response, ResponseBody};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
 use servo_url::ServoUrl;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn temporary_cache_dir() -> PathBuf {
+    env::temp_dir().join(format!("servo-http-cache-{}", Uuid::new_v4()))
+}
+
+fn create_request(url: &ServoUrl) -> Request {
+    Request::new(
+        url.clone(),
+        Some(Origin::Origin(url.clone().origin())),
+        Some(TEST_PIPELINE_ID),
+    )
+}
+
+fn create_fresh_response(url: &ServoUrl, body: &[u8]) -> Response {
+    let timing = ResourceFetchTiming::new(ResourceTimingType::Navigation);
+    let mut response = Response::new(url.clone(), timing);
+    *response.body.lock().unwrap() = ResponseBody::Done(body.to_vec());
+    response
+        .headers
+        .insert(CACHE_CONTROL, HeaderValue::from_static("max-age=3600"));
+    response
+}
 
 #[test]
 fn test_refreshing_resource_sets_done_chan_the_appropriate_value() {
@@ -48,3 +74,141 @@ fn test_refreshing_resource_sets_done_chan_the_appropriate_value() {
         }
     })
 }
+
+#[test]
+fn test_stored_resource_survives_restart() {
+    let dir = temporary_cache_dir();
+    let url = ServoUrl::parse("https://servo.org/persisted").unwrap();
+    let request = create_request(&url);
+    {
+        let mut cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+        cache.store(&request, &create_fresh_response(&url, b"persisted body"));
+    }
+    let cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+    let cached = cache
+        .construct_response(&request, &mut None)
+        .expect("resource should have been read from disk");
+    assert!(!cached.needs_validation);
+    assert_eq!(
+        *cached.response.body.lock().unwrap(),
+        ResponseBody::Done(b"persisted body".to_vec())
+    );
+    assert_eq!(
+        cached.response.headers.get(CACHE_CONTROL).unwrap(),
+        "max-age=3600"
+    );
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_store_evicts_least_recently_used_resources() {
+    let dir = temporary_cache_dir();
+    let urls: Vec<ServoUrl> = (0..11)
+        .map(|i| ServoUrl::parse(&format!("https://servo.org/{}", i)).unwrap())
+        .collect();
+    {
+        // Ten bodies of 100 bytes fill up the store.
+        let mut cache = HttpCache::new_with_store(dir.clone(), 1000);
+        for url in &urls {
+            cache.store(&create_request(url), &create_fresh_response(url, &[0; 100]));
+        }
+    }
+    let cache = HttpCache::new_with_store(dir.clone(), 1000);
+    assert!(cache
+        .construct_response(&create_request(&urls[0]), &mut None)
+        .is_none());
+    for url in &urls[1..] {
+        assert!(cache
+            .construct_response(&create_request(url), &mut None)
+            .is_some());
+    }
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_store_skips_resources_too_large_for_the_store() {
+    let dir = temporary_cache_dir();
+    let url = ServoUrl::parse("https://servo.org/large").unwrap();
+    let request = create_request(&url);
+    {
+        let mut cache = HttpCache::new_with_store(dir.clone(), 1000);
+        cache.store(&request, &create_fresh_response(&url, &[0; 500]));
+        // Still served from memory for the current session.
+        assert!(cache.construct_response(&request, &mut None).is_some());
+    }
+    let cache = HttpCache::new_with_store(dir.clone(), 1000);
+    assert!(cache.construct_response(&request, &mut None).is_none());
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_invalidation_is_persisted() {
+    let dir = temporary_cache_dir();
+    let url = ServoUrl::parse("https://servo.org/invalidated").unwrap();
+    let request = create_request(&url);
+    {
+        let mut cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+        let response = create_fresh_response(&url, b"body");
+        cache.store(&request, &response);
+        cache.invalidate(&request, &response);
+    }
+    let cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+    let cached = cache.construct_response(&request, &mut None).unwrap();
+    assert!(cached.needs_validation);
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_refreshing_resource_read_from_disk() {
+    let dir = temporary_cache_dir();
+    let url = ServoUrl::parse("https://servo.org/refreshed").unwrap();
+    let request = create_request(&url);
+    {
+        let mut cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+        let response = create_fresh_response(&url, b"body");
+        cache.store(&request, &response);
+        cache.invalidate(&request, &response);
+    }
+    {
+        let mut cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+        let timing = ResourceFetchTiming::new(ResourceTimingType::Navigation);
+        let mut not_modified = Response::new(url.clone(), timing);
+        not_modified.status = Some((StatusCode::NOT_MODIFIED, String::from("304")));
+        not_modified
+            .headers
+            .insert(CACHE_CONTROL, HeaderValue::from_static("max-age=60"));
+        let mut done_chan = Some(unbounded());
+        let refreshed = cache
+            .refresh(&request, not_modified, &mut done_chan)
+            .expect("resource should have been read from disk");
+        assert!(done_chan.is_none());
+        assert_eq!(
+            *refreshed.body.lock().unwrap(),
+            ResponseBody::Done(b"body".to_vec())
+        );
+    }
+    let cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+    let cached = cache.construct_response(&request, &mut None).unwrap();
+    assert!(!cached.needs_validation);
+    assert_eq!(
+        cached.response.headers.get(CACHE_CONTROL).unwrap(),
+        "max-age=60"
+    );
+    let _ = fs::remove_dir_all(dir);
+}
+
+#[test]
+fn test_clear_removes_persisted_resources() {
+    let dir = temporary_cache_dir();
+    let url = ServoUrl::parse("https://servo.org/cleared").unwrap();
+    let request = create_request(&url);
+    {
+        let mut cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+        cache.store(&request, &create_fresh_response(&url, b"body"));
+        cache.clear();
+        assert!(cache.construct_response(&request, &mut None).is_none());
+    }
+    let cache = HttpCache::new_with_store(dir.clone(), 1 << 20);
+    assert!(cache.construct_response(&request, &mut None).is_none());
+    let _ = fs::remove_dir_all(dir);
+}
@@ -25,7 +25,7 @@ use http::{Method, StatusCode};
 use hyper::body::Body;
 use hyper::{Request as HyperRequest, Response as HyperResponse};
 use msg::constellation_msg::TEST_PIPELINE_ID;
-use net::cookie::Cookie;
+use net::cookie::{Cookie, SameSiteContext};
 use net::cookie_storage::CookieStorage;
 use net::resource_thread::AuthCacheEntry;
 use net::test::replace_host_table;
@@ -58,7 +58,7 @@ fn assert_cookie_for_domain(
 ) {
     let mut cookie_jar = cookie_jar.write().unwrap();
     let url = ServoUrl::parse(&*domain).unwrap();
-    let cookies = cookie_jar.cookies_for_url(&url, CookieSource::HTTP);
+    let cookies = cookie_jar.cookies_for_url(&url, CookieSource::HTTP, SameSiteContext::SameSite);
     assert_eq!(cookies.as_ref().map(|c| &**c), cookie);
 }
 
@@ -767,7 +767,7 @@ fn test_cookie_set_with_httponly_should_not_be_available_using_getcookiesforurl(
     );
     let mut cookie_jar = context.state.cookie_jar.write().unwrap();
     assert!(cookie_jar
-        .cookies_for_url(&url, CookieSource::NonHTTP)
+        .cookies_for_url(&url, CookieSource::NonHTTP, SameSiteContext::SameSite)
         .is_none());
 }
 
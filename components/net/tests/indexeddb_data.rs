@@ -0,0 +1,507 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use net::indexeddb_data::{Changes, Database};
+use net_traits::indexeddb_thread::{AsyncOperation, CursorDirection, IndexMetadata};
+use net_traits::indexeddb_thread::{IndexedDBError, IndexedDBKey, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{KeyPath, ObjectStoreMetadata, OperationResult};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+fn string(s: &str) -> IndexedDBKey {
+    IndexedDBKey::String(s.to_owned())
+}
+
+fn create_store(database: &mut Database, changes: &mut Changes, name: &str) {
+    let metadata = ObjectStoreMetadata {
+        name: name.to_owned(),
+        key_path: None,
+        auto_increment: true,
+        indexes: vec![],
+    };
+    database
+        .perform(AsyncOperation::CreateObjectStore(metadata), changes)
+        .unwrap();
+}
+
+fn put(
+    database: &mut Database,
+    changes: &mut Changes,
+    key: IndexedDBKey,
+    value: &[u8],
+    index_keys: Vec<(String, Vec<IndexedDBKey>)>,
+) -> Result<OperationResult, IndexedDBError> {
+    database.perform(
+        AsyncOperation::PutRecord {
+            store: "store".to_owned(),
+            key: key,
+            value: value.to_vec(),
+            index_keys: index_keys,
+            no_overwrite: true,
+        },
+        changes,
+    )
+}
+
+fn iterate(
+    database: &mut Database,
+    index: Option<&str>,
+    direction: CursorDirection,
+) -> Vec<IndexedDBKey> {
+    let mut keys = vec![];
+    let mut position = None;
+    let mut object_store_position = None;
+    loop {
+        let operation = AsyncOperation::Iterate {
+            store: "store".to_owned(),
+            index: index.map(str::to_owned),
+            range: IndexedDBKeyRange::unbounded(),
+            direction: direction,
+            position: position.clone(),
+            object_store_position: object_store_position.clone(),
+            key: None,
+            primary_key: None,
+            count: 1,
+        };
+        match database
+            .perform(operation, &mut Changes::default())
+            .unwrap()
+        {
+            OperationResult::Record(Some(record)) => {
+                keys.push(record.primary_key.clone());
+                position = Some(record.key);
+                object_store_position = Some(record.primary_key);
+            },
+            _ => return keys,
+        }
+    }
+}
+
+#[test]
+fn test_key_order() {
+    let mut keys = vec![
+        IndexedDBKey::Array(vec![]),
+        IndexedDBKey::Binary(vec![1]),
+        string("b"),
+        IndexedDBKey::Date(0.0),
+        IndexedDBKey::Number(2.0),
+        string("a"),
+        IndexedDBKey::Number(-1.0),
+        IndexedDBKey::Binary(vec![]),
+    ];
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            IndexedDBKey::Number(-1.0),
+            IndexedDBKey::Number(2.0),
+            IndexedDBKey::Date(0.0),
+            string("a"),
+            string("b"),
+            IndexedDBKey::Binary(vec![]),
+            IndexedDBKey::Binary(vec![1]),
+            IndexedDBKey::Array(vec![]),
+        ]
+    );
+    // Strings are compared by UTF-16 code unit.
+    assert!(string("\u{1f600}") < string("\u{ff61}"));
+}
+
+#[test]
+fn test_key_generator_and_uniqueness() {
+    let mut database = Database::new("db".to_owned());
+    let mut changes = Changes::default();
+    create_store(&mut database, &mut changes, "store");
+
+    let generate = |database: &mut Database, changes: &mut Changes| match database
+        .perform(
+            AsyncOperation::GenerateKey {
+                store: "store".to_owned(),
+            },
+            changes,
+        )
+        .unwrap()
+    {
+        OperationResult::Key(key) => key,
+        _ => panic!("Expected a key"),
+    };
+    assert_eq!(
+        generate(&mut database, &mut changes),
+        IndexedDBKey::Number(1.0)
+    );
+    put(
+        &mut database,
+        &mut changes,
+        IndexedDBKey::Number(10.0),
+        b"a",
+        vec![],
+    )
+    .unwrap();
+    assert_eq!(
+        generate(&mut database, &mut changes),
+        IndexedDBKey::Number(11.0)
+    );
+    assert_eq!(
+        put(
+            &mut database,
+            &mut changes,
+            IndexedDBKey::Number(10.0),
+            b"b",
+            vec![]
+        )
+        .unwrap_err(),
+        IndexedDBError::Constraint
+    );
+}
+
+#[test]
+fn test_index_cursor_directions() {
+    let mut database = Database::new("db".to_owned());
+    let mut changes = Changes::default();
+    create_store(&mut database, &mut changes, "store");
+    let index = IndexMetadata {
+        name: "index".to_owned(),
+        key_path: KeyPath::String("x".to_owned()),
+        unique: false,
+        multi_entry: false,
+    };
+    database
+        .perform(
+            AsyncOperation::CreateIndex {
+                store: "store".to_owned(),
+                index: index,
+                keys: vec![],
+            },
+            &mut changes,
+        )
+        .unwrap();
+    for &(primary_key, index_key) in &[(1.0, "b"), (2.0, "a"), (3.0, "b")] {
+        put(
+            &mut database,
+            &mut changes,
+            IndexedDBKey::Number(primary_key),
+            b"",
+            vec![("index".to_owned(), vec![string(index_key)])],
+        )
+        .unwrap();
+    }
+
+    let numbers = |keys: &[f64]| -> Vec<IndexedDBKey> {
+        keys.iter().map(|&key| IndexedDBKey::Number(key)).collect()
+    };
+    assert_eq!(
+        iterate(&mut database, None, CursorDirection::Prev),
+        numbers(&[3.0, 2.0, 1.0])
+    );
+    assert_eq!(
+        iterate(&mut database, Some("index"), CursorDirection::Next),
+        numbers(&[2.0, 1.0, 3.0])
+    );
+    assert_eq!(
+        iterate(&mut database, Some("index"), CursorDirection::NextUnique),
+        numbers(&[2.0, 1.0])
+    );
+    assert_eq!(
+        iterate(&mut database, Some("index"), CursorDirection::Prev),
+        numbers(&[3.0, 1.0, 2.0])
+    );
+    assert_eq!(
+        iterate(&mut database, Some("index"), CursorDirection::PrevUnique),
+        numbers(&[1.0, 2.0])
+    );
+}
+
+#[test]
+fn test_undo_restores_the_database() {
+    let mut database = Database::new("db".to_owned());
+    let mut changes = Changes::default();
+    create_store(&mut database, &mut changes, "store");
+    put(&mut database, &mut changes, string("a"), b"1", vec![]).unwrap();
+    assert!(!changes.is_empty());
+    let before = database.metadata();
+
+    let mut changes = Changes::default();
+    database
+        .perform(AsyncOperation::SetVersion(2), &mut changes)
+        .unwrap();
+    database
+        .perform(
+            AsyncOperation::RenameObjectStore {
+                name: "store".to_owned(),
+                new_name: "renamed".to_owned(),
+            },
+            &mut changes,
+        )
+        .unwrap();
+    create_store(&mut database, &mut changes, "store");
+    assert_eq!(database.metadata().object_stores.len(), 2);
+
+    database.undo(changes);
+    let after = database.metadata();
+    assert_eq!(after.version, before.version);
+    assert_eq!(after.object_stores.len(), 1);
+    assert_eq!(after.object_stores[0].name, "store");
+    match database
+        .perform(
+            AsyncOperation::Count {
+                store: "store".to_owned(),
+                index: None,
+                range: IndexedDBKeyRange::only(string("a")),
+            },
+            &mut Changes::default(),
+        )
+        .unwrap()
+    {
+        OperationResult::Count(count) => assert_eq!(count, 1),
+        _ => panic!("Expected a count"),
+    }
+}
+
+fn get(database: &mut Database, store: &str) -> Option<Vec<u8>> {
+    let operation = AsyncOperation::GetRecord {
+        store: store.to_owned(),
+        index: None,
+        range: IndexedDBKeyRange::unbounded(),
+    };
+    match database
+        .perform(operation, &mut Changes::default())
+        .unwrap()
+    {
+        OperationResult::Record(record) => record.map(|record| record.value),
+        _ => panic!("Expected a record"),
+    }
+}
+
+fn count(database: &mut Database) -> u64 {
+    let operation = AsyncOperation::Count {
+        store: "store".to_owned(),
+        index: None,
+        range: IndexedDBKeyRange::unbounded(),
+    };
+    match database
+        .perform(operation, &mut Changes::default())
+        .unwrap()
+    {
+        OperationResult::Count(count) => count,
+        _ => panic!("Expected a count"),
+    }
+}
+
+#[test]
+fn test_undo_restores_records() {
+    let mut database = Database::new("db".to_owned());
+    create_store(&mut database, &mut Changes::default(), "store");
+    let index = IndexMetadata {
+        name: "index".to_owned(),
+        key_path: KeyPath::String("x".to_owned()),
+        unique: true,
+        multi_entry: false,
+    };
+    database
+        .perform(
+            AsyncOperation::CreateIndex {
+                store: "store".to_owned(),
+                index: index,
+                keys: vec![],
+            },
+            &mut Changes::default(),
+        )
+        .unwrap();
+    let index_keys = |key: &str| vec![("index".to_owned(), vec![string(key)])];
+    put(
+        &mut database,
+        &mut Changes::default(),
+        string("a"),
+        b"1",
+        index_keys("x"),
+    )
+    .unwrap();
+
+    let mut changes = Changes::default();
+    database
+        .perform(
+            AsyncOperation::PutRecord {
+                store: "store".to_owned(),
+                key: string("a"),
+                value: b"2".to_vec(),
+                index_keys: index_keys("y"),
+                no_overwrite: false,
+            },
+            &mut changes,
+        )
+        .unwrap();
+    put(
+        &mut database,
+        &mut changes,
+        string("b"),
+        b"3",
+        index_keys("x"),
+    )
+    .unwrap();
+    database
+        .perform(
+            AsyncOperation::Clear {
+                store: "store".to_owned(),
+            },
+            &mut changes,
+        )
+        .unwrap();
+    put(&mut database, &mut changes, string("c"), b"4", vec![]).unwrap();
+    database.undo(changes);
+
+    assert_eq!(count(&mut database), 1);
+    assert_eq!(get(&mut database, "store"), Some(b"1".to_vec()));
+    // The record is back under its old key in the index, and only there.
+    assert_eq!(
+        put(
+            &mut database,
+            &mut Changes::default(),
+            string("b"),
+            b"5",
+            index_keys("x")
+        )
+        .unwrap_err(),
+        IndexedDBError::Constraint
+    );
+    put(
+        &mut database,
+        &mut Changes::default(),
+        string("b"),
+        b"5",
+        index_keys("y"),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_cursor_continues_to_key() {
+    let mut database = Database::new("db".to_owned());
+    let mut changes = Changes::default();
+    create_store(&mut database, &mut changes, "store");
+    for key in 0..10 {
+        put(
+            &mut database,
+            &mut changes,
+            IndexedDBKey::Number(key as f64),
+            b"",
+            vec![],
+        )
+        .unwrap();
+    }
+    let operation = AsyncOperation::Iterate {
+        store: "store".to_owned(),
+        index: None,
+        range: IndexedDBKeyRange::unbounded(),
+        direction: CursorDirection::Prev,
+        position: Some(IndexedDBKey::Number(8.0)),
+        object_store_position: Some(IndexedDBKey::Number(8.0)),
+        key: Some(IndexedDBKey::Number(5.5)),
+        primary_key: None,
+        count: 2,
+    };
+    match database.perform(operation, &mut changes).unwrap() {
+        OperationResult::Record(Some(record)) => assert_eq!(record.key, IndexedDBKey::Number(4.0)),
+        _ => panic!("Expected a record"),
+    }
+}
+
+fn new_dir() -> PathBuf {
+    env::temp_dir().join(format!("servo-indexeddb-{}", Uuid::new_v4()))
+}
+
+#[test]
+fn test_database_is_saved() {
+    let dir = new_dir();
+    let mut database = Database::new("db".to_owned());
+    let mut changes = Changes::default();
+    database
+        .perform(AsyncOperation::SetVersion(1), &mut changes)
+        .unwrap();
+    create_store(&mut database, &mut changes, "store");
+    put(&mut database, &mut changes, string("a"), b"1", vec![]).unwrap();
+    database.save(&dir, &changes).unwrap();
+
+    let mut loaded = Database::load(&dir).unwrap();
+    assert_eq!(loaded.version(), 1);
+    assert_eq!(loaded.metadata().name, "db");
+    assert_eq!(get(&mut loaded, "store"), Some(b"1".to_vec()));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_commits_are_appended_to_the_journal() {
+    let dir = new_dir();
+    let mut database = Database::new("db".to_owned());
+    let mut changes = Changes::default();
+    create_store(&mut database, &mut changes, "store");
+    database.save(&dir, &changes).unwrap();
+    let snapshot = fs::read(dir.join("snapshot")).unwrap();
+
+    let mut changes = Changes::default();
+    put(&mut database, &mut changes, string("a"), b"1", vec![]).unwrap();
+    database.save(&dir, &changes).unwrap();
+    let mut changes = Changes::default();
+    put(&mut database, &mut changes, string("b"), b"2", vec![]).unwrap();
+    database
+        .perform(
+            AsyncOperation::DeleteRange {
+                store: "store".to_owned(),
+                range: IndexedDBKeyRange::only(string("a")),
+            },
+            &mut changes,
+        )
+        .unwrap();
+    database.save(&dir, &changes).unwrap();
+    assert_eq!(fs::read(dir.join("snapshot")).unwrap(), snapshot);
+
+    let mut loaded = Database::load(&dir).unwrap();
+    assert_eq!(count(&mut loaded), 1);
+    assert_eq!(get(&mut loaded, "store"), Some(b"2".to_vec()));
+
+    // A commit cut short by a crash is ignored, and the next ones are kept.
+    let journal = dir.join("journal");
+    let size = fs::metadata(&journal).unwrap().len();
+    let mut file = OpenOptions::new().append(true).open(&journal).unwrap();
+    file.write_all(&[100, 0, 0, 0, 0, 0, 0, 0, 1]).unwrap();
+    let mut loaded = Database::load(&dir).unwrap();
+    assert_eq!(fs::metadata(&journal).unwrap().len(), size);
+    let mut changes = Changes::default();
+    put(&mut loaded, &mut changes, string("c"), b"3", vec![]).unwrap();
+    loaded.save(&dir, &changes).unwrap();
+    let mut loaded = Database::load(&dir).unwrap();
+    assert_eq!(count(&mut loaded), 2);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_journal_is_folded_into_the_snapshot() {
+    let dir = new_dir();
+    let mut database = Database::new("db".to_owned());
+    let mut changes = Changes::default();
+    create_store(&mut database, &mut changes, "store");
+    database.save(&dir, &changes).unwrap();
+
+    let value = vec![0; 1 << 16];
+    for key in 0..20 {
+        let mut changes = Changes::default();
+        put(
+            &mut database,
+            &mut changes,
+            IndexedDBKey::Number(key as f64),
+            &value,
+            vec![],
+        )
+        .unwrap();
+        database.save(&dir, &changes).unwrap();
+    }
+    let journal_size = fs::metadata(dir.join("journal")).unwrap().len();
+    assert!(journal_size < 1 << 20);
+
+    let mut loaded = Database::load(&dir).unwrap();
+    assert_eq!(count(&mut loaded), 20);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -16,8 +16,11 @@ mod filemanager_thread;
 mod hsts;
 mod http_cache;
 mod http_loader;
+mod indexeddb_data;
 mod mime_classifier;
+mod proxy;
 mod resource_thread;
+mod storage_data;
 mod subresource_integrity;
 
 use crossbeam_channel::{unbounded, Sender};
@@ -34,6 +37,7 @@ use net::fetch::cors_cache::CorsCache;
 use net::fetch::methods::{self, CancellationListener, FetchContext};
 use net::filemanager_thread::FileManager;
 use net::test::HttpState;
+use net_traits::csp::Violation;
 use net_traits::request::Request;
 use net_traits::response::Response;
 use net_traits::{FetchTaskTarget, ResourceFetchTiming, ResourceTimingType};
@@ -93,6 +97,7 @@ fn new_fetch_context(
         state: Arc::new(HttpState::new(ssl_connector)),
         user_agent: DEFAULT_USER_AGENT.into(),
         devtools_chan: dc,
+        swmanager_chan: None,
         filemanager: FileManager::new(sender),
         cancellation_listener: Arc::new(Mutex::new(CancellationListener::new(None))),
         timing: Arc::new(Mutex::new(ResourceFetchTiming::new(
@@ -109,6 +114,7 @@ impl FetchTaskTarget for FetchResponseCollector {
     fn process_response_eof(&mut self, response: &Response) {
         let _ = self.sender.send(response.clone());
     }
+    fn process_csp_violations(&mut self, _: &Request, _: Vec<Violation>) {}
 }
 
 fn fetch(request: &mut Request, dc: Option<Sender<DevtoolsControlMsg>>) -> Response {
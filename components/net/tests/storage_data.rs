@@ -0,0 +1,155 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use net::storage_data::StorageData;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const ORIGIN: &str = "https://example.com";
+
+fn temporary_config_dir() -> PathBuf {
+    let dir = env::temp_dir().join(format!("servo-storage-{}", Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn open(config_dir: &PathBuf) -> StorageData {
+    StorageData::new_persistent(config_dir.join("local_storage"), config_dir)
+}
+
+fn files_in(dir: &PathBuf) -> Vec<PathBuf> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries.map(|entry| entry.unwrap().path()).collect(),
+        Err(_) => vec![],
+    }
+}
+
+#[test]
+fn test_data_is_kept_across_restarts() {
+    let config_dir = temporary_config_dir();
+    {
+        let mut data = open(&config_dir);
+        data.set_item(ORIGIN, "a".to_owned(), "1".to_owned());
+        data.set_item(ORIGIN, "b".to_owned(), "2".to_owned());
+        data.set_item(ORIGIN, "a".to_owned(), "3".to_owned());
+        assert_eq!(data.remove_item(ORIGIN, "b"), Some("2".to_owned()));
+    }
+
+    let mut data = open(&config_dir);
+    assert_eq!(data.entries(ORIGIN).len(), 1);
+    assert_eq!(data.entries(ORIGIN).get("a"), Some(&"3".to_owned()));
+    assert_eq!(data.total_size(ORIGIN), 2);
+    assert!(data.entries("https://example.org").is_empty());
+
+    assert!(data.clear(ORIGIN));
+    assert!(!data.clear(ORIGIN));
+    assert!(open(&config_dir).entries(ORIGIN).is_empty());
+    fs::remove_dir_all(config_dir).unwrap();
+}
+
+#[test]
+fn test_incomplete_journal_entry_is_ignored() {
+    let config_dir = temporary_config_dir();
+    {
+        let mut data = open(&config_dir);
+        data.set_item(ORIGIN, "a".to_owned(), "1".to_owned());
+    }
+
+    let journal = files_in(&config_dir.join("local_storage"))
+        .into_iter()
+        .find(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "journal")
+        })
+        .unwrap();
+    let mut file = OpenOptions::new().append(true).open(&journal).unwrap();
+    file.write_all(b"{\"Set\":[\"b\",").unwrap();
+    drop(file);
+
+    let mut data = open(&config_dir);
+    assert_eq!(data.entries(ORIGIN).len(), 1);
+    assert_eq!(data.entries(ORIGIN).get("a"), Some(&"1".to_owned()));
+
+    // The replayed journal was folded into the snapshot, so new entries aren't appended
+    // after the incomplete one.
+    data.set_item(ORIGIN, "c".to_owned(), "2".to_owned());
+    drop(data);
+    assert_eq!(open(&config_dir).entries(ORIGIN).len(), 2);
+    fs::remove_dir_all(config_dir).unwrap();
+}
+
+#[test]
+fn test_remove_origin() {
+    let config_dir = temporary_config_dir();
+    let storage_dir = config_dir.join("local_storage");
+    {
+        let mut data = open(&config_dir);
+        data.set_item(ORIGIN, "a".to_owned(), "1".to_owned());
+        data.set_item("https://example.org", "b".to_owned(), "2".to_owned());
+        assert!(!files_in(&storage_dir).is_empty());
+    }
+
+    // The origin doesn't need to have been accessed since the data was loaded.
+    let mut data = open(&config_dir);
+    data.remove_origin(ORIGIN);
+    assert!(data.entries(ORIGIN).is_empty());
+    drop(data);
+
+    let mut data = open(&config_dir);
+    assert!(data.entries(ORIGIN).is_empty());
+    assert_eq!(data.entries("https://example.org").len(), 1);
+    data.remove_origin("https://example.org");
+    drop(data);
+    assert!(files_in(&storage_dir).is_empty());
+    fs::remove_dir_all(config_dir).unwrap();
+}
+
+#[test]
+fn test_long_origin_is_saved() {
+    let config_dir = temporary_config_dir();
+    // Hex-encoded, this wouldn't fit in a file name.
+    let origin = format!("https://{}.example.com", "a".repeat(200));
+    {
+        let mut data = open(&config_dir);
+        data.set_item(&origin, "a".to_owned(), "1".to_owned());
+    }
+
+    let mut data = open(&config_dir);
+    assert_eq!(data.entries(&origin).get("a"), Some(&"1".to_owned()));
+    fs::remove_dir_all(config_dir).unwrap();
+}
+
+#[test]
+fn test_legacy_file_is_imported() {
+    let config_dir = temporary_config_dir();
+    let legacy_path = config_dir.join("local_data.json");
+    fs::write(
+        &legacy_path,
+        format!("{{\"{}\":[2,{{\"a\":\"1\"}}]}}", ORIGIN).as_bytes(),
+    )
+    .unwrap();
+
+    open(&config_dir);
+    assert!(!legacy_path.exists());
+
+    let mut data = open(&config_dir);
+    assert_eq!(data.entries(ORIGIN).get("a"), Some(&"1".to_owned()));
+    assert_eq!(data.total_size(ORIGIN), 2);
+    fs::remove_dir_all(config_dir).unwrap();
+}
+
+#[test]
+fn test_in_memory_data() {
+    let mut data = StorageData::new_in_memory();
+    assert_eq!(data.set_item(ORIGIN, "a".to_owned(), "1".to_owned()), None);
+    assert_eq!(
+        data.set_item(ORIGIN, "a".to_owned(), "22".to_owned()),
+        Some("1".to_owned())
+    );
+    assert_eq!(data.total_size(ORIGIN), 3);
+    assert_eq!(data.remove_item(ORIGIN, "missing"), None);
+}
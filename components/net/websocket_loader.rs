@@ -3,7 +3,7 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::connector::create_ssl_connector_builder;
-use crate::cookie::Cookie;
+use crate::cookie::{Cookie, SameSiteContext};
 use crate::fetch::methods::should_be_blocked_due_to_bad_port;
 use crate::hosts::replace_host;
 use crate::http_loader::HttpState;
@@ -11,9 +11,11 @@ use embedder_traits::resources::{self, Resource};
 use headers::Host;
 use http::header::{self, HeaderMap, HeaderName, HeaderValue};
 use http::uri::Authority;
+use http::Method;
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
+use net_traits::csp::CheckResult;
 use net_traits::request::{RequestBuilder, RequestMode};
-use net_traits::{CookieSource, MessageData};
+use net_traits::{CookieSource, MessageData, SiteForCookies};
 use net_traits::{WebSocketDomAction, WebSocketNetworkEvent};
 use openssl::ssl::SslStream;
 use servo_url::ServoUrl;
@@ -37,6 +39,7 @@ struct Client<'a> {
     protocols: &'a [String],
     http_state: &'a Arc<HttpState>,
     resource_url: &'a ServoUrl,
+    site_for_cookies: &'a SiteForCookies,
     event_sender: &'a IpcSender<WebSocketNetworkEvent>,
     protocol_in_use: Option<String>,
     certificate_path: Option<String>,
@@ -69,7 +72,9 @@ impl<'a> Handler for Client<'a> {
         }
 
         let mut cookie_jar = self.http_state.cookie_jar.write().unwrap();
-        if let Some(cookie_list) = cookie_jar.cookies_for_url(self.resource_url, CookieSource::HTTP)
+        let context = SameSiteContext::new(self.resource_url, self.site_for_cookies, &Method::GET);
+        if let Some(cookie_list) =
+            cookie_jar.cookies_for_url(self.resource_url, CookieSource::HTTP, context)
         {
             req.headers_mut()
                 .push(("Cookie".into(), cookie_list.as_bytes().to_owned()))
@@ -94,7 +99,9 @@ impl<'a> Handler for Client<'a> {
                 if let Some(cookie) =
                     Cookie::from_cookie_string(s.into(), self.resource_url, CookieSource::HTTP)
                 {
-                    jar.push(cookie, self.resource_url, CookieSource::HTTP);
+                    if cookie.settable_from(self.resource_url, self.site_for_cookies) {
+                        jar.push(cookie, self.resource_url, CookieSource::HTTP);
+                    }
                 }
             }
         }
@@ -204,6 +211,20 @@ pub fn init(
                 return;
             }
 
+            if let Some(ref csp_list) = req_builder.csp_list {
+                let request = req_builder.clone().build();
+                let (check_result, violations) = csp_list.should_request_be_blocked(&request);
+                if !violations.is_empty() {
+                    let _ = resource_event_sender
+                        .send(WebSocketNetworkEvent::ReportCspViolations(violations));
+                }
+                if check_result == CheckResult::Blocked {
+                    debug!("Failed to establish a WebSocket connection: blocked by CSP");
+                    let _ = resource_event_sender.send(WebSocketNetworkEvent::Fail);
+                    return;
+                }
+            }
+
             let host = replace_host(req_builder.url.host_str().unwrap());
             let mut net_url = req_builder.url.clone().into_url();
             net_url.set_host(Some(&host)).unwrap();
@@ -228,6 +249,7 @@ pub fn init(
                 protocols: &protocols,
                 http_state: &http_state,
                 resource_url: &req_builder.url,
+                site_for_cookies: &req_builder.site_for_cookies,
                 event_sender: &resource_event_sender,
                 protocol_in_use: None,
                 certificate_path,
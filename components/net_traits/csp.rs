@@ -0,0 +1,622 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Parsing and enforcement of [Content Security Policies][csp].
+//!
+//! [csp]: https://w3c.github.io/webappsec-csp/
+
+use crate::request::{Destination, Origin, Request};
+use http::HeaderMap;
+use servo_url::{ImmutableOrigin, ServoUrl};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+const SCRIPT_SRC: &[&str] = &["script-src", "default-src"];
+const SCRIPT_SRC_ELEM: &[&str] = &["script-src-elem", "script-src", "default-src"];
+const SCRIPT_SRC_ATTR: &[&str] = &["script-src-attr", "script-src", "default-src"];
+const STYLE_SRC_ELEM: &[&str] = &["style-src-elem", "style-src", "default-src"];
+const STYLE_SRC_ATTR: &[&str] = &["style-src-attr", "style-src", "default-src"];
+const WORKER_SRC: &[&str] = &["worker-src", "child-src", "script-src", "default-src"];
+const FRAME_SRC: &[&str] = &["frame-src", "child-src", "default-src"];
+
+/// <https://w3c.github.io/webappsec-csp/#policy-disposition>
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum PolicyDisposition {
+    Enforce,
+    Report,
+}
+
+/// <https://w3c.github.io/webappsec-csp/#policy-source>
+#[derive(Clone, Copy, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum PolicySource {
+    Header,
+    Meta,
+}
+
+/// <https://w3c.github.io/webappsec-csp/#directives>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct Directive {
+    pub name: String,
+    pub value: Vec<String>,
+}
+
+/// <https://w3c.github.io/webappsec-csp/#content-security-policy-object>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct Policy {
+    pub directives: Vec<Directive>,
+    pub disposition: PolicyDisposition,
+    pub source: PolicySource,
+}
+
+/// The policies attached to a response, a document or a worker.
+#[derive(Clone, Debug, Default, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub struct CspList(pub Vec<Policy>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CheckResult {
+    Allowed,
+    Blocked,
+}
+
+/// The kind of inline content being checked, see
+/// <https://w3c.github.io/webappsec-csp/#should-block-inline>.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InlineCheckType {
+    Script,
+    ScriptAttribute,
+    Style,
+    StyleAttribute,
+    Navigation,
+}
+
+/// The kind of navigation being checked, which selects the governing directive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NavigationCheckType {
+    FormSubmission,
+    Frame,
+    Other,
+}
+
+/// <https://w3c.github.io/webappsec-csp/#violation-resource>
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ViolationResource {
+    Inline,
+    Eval,
+    Url(ServoUrl),
+}
+
+/// <https://w3c.github.io/webappsec-csp/#violation>
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Violation {
+    pub resource: ViolationResource,
+    /// The effective directive, e.g. `script-src-elem`.
+    pub directive: String,
+    /// The serialization of the violated policy.
+    pub policy: String,
+    pub disposition: PolicyDisposition,
+    /// The start of the blocked inline content, if the policy asked for it
+    /// with `'report-sample'`.
+    pub sample: String,
+}
+
+impl ViolationResource {
+    /// <https://w3c.github.io/webappsec-csp/#strip-url-for-use-in-reports>
+    pub fn blocked_uri(&self) -> String {
+        match *self {
+            ViolationResource::Inline => "inline".to_owned(),
+            ViolationResource::Eval => "eval".to_owned(),
+            ViolationResource::Url(ref url) => {
+                let mut url = url.as_url().clone();
+                if url.scheme() != "http" && url.scheme() != "https" {
+                    return url.scheme().to_owned();
+                }
+                url.set_fragment(None);
+                let _ = url.set_username("");
+                let _ = url.set_password(None);
+                url.into_string()
+            },
+        }
+    }
+}
+
+impl Directive {
+    fn has_keyword(&self, keyword: &str) -> bool {
+        self.value
+            .iter()
+            .any(|expression| expression.eq_ignore_ascii_case(keyword))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#match-url-to-source-list>
+    fn matches_url(&self, url: &ServoUrl, origin: &ImmutableOrigin, redirect_count: u32) -> bool {
+        self.value
+            .iter()
+            .any(|expression| url_matches_expression(expression, url, origin, redirect_count))
+    }
+
+    fn matches_nonce(&self, nonce: &str) -> bool {
+        !nonce.is_empty() &&
+            self.value
+                .iter()
+                .any(|expression| nonce_source(expression) == Some(nonce))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#allow-all-inline>
+    fn allows_all_inline_behavior(&self, check_type: InlineCheckType) -> bool {
+        let is_script = match check_type {
+            InlineCheckType::Script |
+            InlineCheckType::ScriptAttribute |
+            InlineCheckType::Navigation => true,
+            InlineCheckType::Style | InlineCheckType::StyleAttribute => false,
+        };
+        self.has_keyword("'unsafe-inline'") &&
+            !self.value.iter().any(|expression| {
+                nonce_source(expression).is_some() || hash_source(expression).is_some()
+            }) &&
+            !(is_script && self.has_keyword("'strict-dynamic'"))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#match-element-to-source-list>
+    fn allows_inline(&self, check_type: InlineCheckType, nonce: &str, source: &str) -> bool {
+        if self.allows_all_inline_behavior(check_type) {
+            return true;
+        }
+        let is_element =
+            check_type == InlineCheckType::Script || check_type == InlineCheckType::Style;
+        if is_element && self.matches_nonce(nonce) {
+            return true;
+        }
+        if is_element || self.has_keyword("'unsafe-hashes'") {
+            return self
+                .value
+                .iter()
+                .any(|expression| hash_matches(expression, source));
+        }
+        false
+    }
+}
+
+impl Policy {
+    /// <https://w3c.github.io/webappsec-csp/#parse-serialized-policy>
+    pub fn parse(serialized: &str, source: PolicySource, disposition: PolicyDisposition) -> Policy {
+        let mut directives: Vec<Directive> = vec![];
+        for token in serialized.split(';') {
+            let mut parts = token
+                .split(is_ascii_whitespace)
+                .filter(|part| !part.is_empty());
+            let name = match parts.next() {
+                Some(name) => name.to_ascii_lowercase(),
+                None => continue,
+            };
+            if !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') {
+                continue;
+            }
+            // Only the first occurrence of a directive is honoured.
+            if directives.iter().any(|directive| directive.name == name) {
+                continue;
+            }
+            let value = parts.map(str::to_owned).collect();
+            directives.push(Directive { name, value });
+        }
+        if source == PolicySource::Meta {
+            // https://w3c.github.io/webappsec-csp/#meta-element
+            directives.retain(|directive| {
+                !["report-uri", "frame-ancestors", "sandbox"].contains(&&*directive.name)
+            });
+        }
+        Policy {
+            directives,
+            disposition,
+            source,
+        }
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#serialized-policy>
+    pub fn serialize(&self) -> String {
+        self.directives
+            .iter()
+            .map(|directive| {
+                let mut serialized = directive.name.clone();
+                for expression in &directive.value {
+                    serialized.push(' ');
+                    serialized.push_str(expression);
+                }
+                serialized
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    fn directive(&self, name: &str) -> Option<&Directive> {
+        self.directives
+            .iter()
+            .find(|directive| directive.name == name)
+    }
+
+    /// The first directive of `fallbacks` present in this policy.
+    ///
+    /// <https://w3c.github.io/webappsec-csp/#directive-fallback-list>
+    fn effective_directive(&self, fallbacks: &[&str]) -> Option<&Directive> {
+        fallbacks
+            .iter()
+            .filter_map(|name| self.directive(name))
+            .next()
+    }
+}
+
+impl CspList {
+    /// Parses a serialized CSP list, i.e. the value of a
+    /// `Content-Security-Policy` header, which may hold several
+    /// comma-separated policies.
+    pub fn parse(
+        serialized: &str,
+        source: PolicySource,
+        disposition: PolicyDisposition,
+    ) -> CspList {
+        CspList(
+            serialized
+                .split(',')
+                .map(|policy| Policy::parse(policy, source, disposition))
+                .filter(|policy| !policy.directives.is_empty())
+                .collect(),
+        )
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#parse-response-csp>
+    pub fn from_headers(headers: &HeaderMap) -> Option<CspList> {
+        let mut list = CspList::default();
+        let headers_and_dispositions = [
+            ("content-security-policy", PolicyDisposition::Enforce),
+            (
+                "content-security-policy-report-only",
+                PolicyDisposition::Report,
+            ),
+        ];
+        for &(name, disposition) in headers_and_dispositions.iter() {
+            for value in headers.get_all(name) {
+                if let Ok(value) = value.to_str() {
+                    list.append(CspList::parse(value, PolicySource::Header, disposition));
+                }
+            }
+        }
+        if list.0.is_empty() {
+            None
+        } else {
+            Some(list)
+        }
+    }
+
+    pub fn append(&mut self, other: CspList) {
+        self.0.extend(other.0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-request>
+    pub fn should_request_be_blocked(&self, request: &Request) -> (CheckResult, Vec<Violation>) {
+        let fallbacks = match request_directive_fallbacks(request.destination) {
+            Some(fallbacks) => fallbacks,
+            None => return (CheckResult::Allowed, vec![]),
+        };
+        let origin = match request.origin {
+            Origin::Origin(ref origin) => origin,
+            Origin::Client => return (CheckResult::Allowed, vec![]),
+        };
+        let url = request.current_url();
+        let nonce = &request.cryptographic_nonce_metadata;
+        let is_script_or_style = fallbacks == SCRIPT_SRC_ELEM || fallbacks == STYLE_SRC_ELEM;
+        self.check(
+            fallbacks,
+            ViolationResource::Url(url.clone()),
+            "",
+            |directive| {
+                (is_script_or_style && directive.matches_nonce(nonce)) ||
+                    directive.matches_url(&url, origin, request.redirect_count)
+            },
+        )
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-inline>
+    pub fn should_inline_be_blocked(
+        &self,
+        check_type: InlineCheckType,
+        nonce: &str,
+        source: &str,
+    ) -> (CheckResult, Vec<Violation>) {
+        let fallbacks = match check_type {
+            InlineCheckType::Script | InlineCheckType::Navigation => SCRIPT_SRC_ELEM,
+            InlineCheckType::ScriptAttribute => SCRIPT_SRC_ATTR,
+            InlineCheckType::Style => STYLE_SRC_ELEM,
+            InlineCheckType::StyleAttribute => STYLE_SRC_ATTR,
+        };
+        self.check(fallbacks, ViolationResource::Inline, source, |directive| {
+            directive.allows_inline(check_type, nonce, source)
+        })
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#can-compile-strings>
+    pub fn is_js_evaluation_allowed(&self) -> (CheckResult, Vec<Violation>) {
+        self.check(SCRIPT_SRC, ViolationResource::Eval, "", |directive| {
+            directive.has_keyword("'unsafe-eval'")
+        })
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-navigation-request>
+    pub fn should_navigation_request_be_blocked(
+        &self,
+        url: &ServoUrl,
+        origin: &ImmutableOrigin,
+        check_type: NavigationCheckType,
+    ) -> (CheckResult, Vec<Violation>) {
+        let fallbacks: &[&str] = match check_type {
+            NavigationCheckType::FormSubmission => &["form-action"],
+            NavigationCheckType::Frame => FRAME_SRC,
+            NavigationCheckType::Other => &["navigate-to"],
+        };
+        self.check(
+            fallbacks,
+            ViolationResource::Url(url.clone()),
+            "",
+            |directive| directive.matches_url(url, origin, 0),
+        )
+    }
+
+    /// Runs `allows` against the effective directive of every policy,
+    /// collecting a violation for each policy that disallows the resource.
+    fn check<F>(
+        &self,
+        fallbacks: &[&str],
+        resource: ViolationResource,
+        sample: &str,
+        allows: F,
+    ) -> (CheckResult, Vec<Violation>)
+    where
+        F: Fn(&Directive) -> bool,
+    {
+        let mut result = CheckResult::Allowed;
+        let mut violations = vec![];
+        for policy in &self.0 {
+            let directive = match policy.effective_directive(fallbacks) {
+                Some(directive) => directive,
+                None => continue,
+            };
+            if allows(directive) {
+                continue;
+            }
+            if policy.disposition == PolicyDisposition::Enforce {
+                result = CheckResult::Blocked;
+            }
+            let sample = if directive.has_keyword("'report-sample'") {
+                sample.chars().take(40).collect()
+            } else {
+                String::new()
+            };
+            violations.push(Violation {
+                resource: resource.clone(),
+                directive: fallbacks[0].to_owned(),
+                policy: policy.serialize(),
+                disposition: policy.disposition,
+                sample,
+            });
+        }
+        (result, violations)
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#effective-directive-for-a-request>
+fn request_directive_fallbacks(destination: Destination) -> Option<&'static [&'static str]> {
+    let fallbacks: &'static [&'static str] = match destination {
+        Destination::None => &["connect-src", "default-src"],
+        Destination::Audio | Destination::Track | Destination::Video => {
+            &["media-src", "default-src"]
+        },
+        Destination::Font => &["font-src", "default-src"],
+        Destination::Image => &["img-src", "default-src"],
+        Destination::Manifest => &["manifest-src", "default-src"],
+        Destination::Embed | Destination::Object => &["object-src", "default-src"],
+        Destination::Script | Destination::Xslt => SCRIPT_SRC_ELEM,
+        Destination::ServiceWorker | Destination::SharedWorker | Destination::Worker => WORKER_SRC,
+        Destination::Style => STYLE_SRC_ELEM,
+        // Navigations are checked by the document initiating them.
+        Destination::Document | Destination::Report => return None,
+    };
+    Some(fallbacks)
+}
+
+fn is_ascii_whitespace(c: char) -> bool {
+    c.is_ascii_whitespace()
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-url-to-source-expression>
+fn url_matches_expression(
+    expression: &str,
+    url: &ServoUrl,
+    origin: &ImmutableOrigin,
+    redirect_count: u32,
+) -> bool {
+    if expression == "*" {
+        return ["http", "https", "ftp", "ws", "wss"].contains(&url.scheme()) ||
+            origin_scheme(origin) == Some(url.scheme());
+    }
+    if expression.eq_ignore_ascii_case("'self'") {
+        return url_matches_self(url, origin);
+    }
+    // Other keywords, nonces and hashes never match a URL.
+    if expression.starts_with('\'') {
+        return false;
+    }
+    if let Some(scheme) = scheme_source(expression) {
+        return scheme_part_matches(scheme, url.scheme());
+    }
+
+    // A host-source: [scheme "://"] host [":" port] [path].
+    let (scheme, rest) = match expression.find("://") {
+        Some(index) => (Some(&expression[..index]), &expression[index + 3..]),
+        None => (None, expression),
+    };
+    let host_end = rest.find(|c| c == ':' || c == '/').unwrap_or(rest.len());
+    let (host, rest) = rest.split_at(host_end);
+    let (port, path) = if rest.starts_with(':') {
+        let rest = &rest[1..];
+        let port_end = rest.find('/').unwrap_or(rest.len());
+        (Some(&rest[..port_end]), &rest[port_end..])
+    } else {
+        (None, rest)
+    };
+
+    let url_host = match url.host_str() {
+        Some(host) => host,
+        None => return false,
+    };
+    let scheme_matches = match scheme.or_else(|| origin_scheme(origin)) {
+        Some(scheme) => scheme_part_matches(scheme, url.scheme()),
+        None => false,
+    };
+    if !scheme_matches || !host_part_matches(host, url_host) || !port_part_matches(port, url) {
+        return false;
+    }
+    // Paths are ignored after a redirect, so as not to leak its target.
+    path.is_empty() || redirect_count > 0 || path_part_matches(path, url.as_url().path())
+}
+
+fn origin_scheme(origin: &ImmutableOrigin) -> Option<&str> {
+    match *origin {
+        ImmutableOrigin::Tuple(ref scheme, ..) => Some(scheme),
+        ImmutableOrigin::Opaque(_) => None,
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-url-to-self>
+fn url_matches_self(url: &ServoUrl, origin: &ImmutableOrigin) -> bool {
+    if url.origin() == *origin {
+        return true;
+    }
+    let (origin_scheme, origin_host, origin_port) = match *origin {
+        ImmutableOrigin::Tuple(ref scheme, ref host, port) => (scheme, host, port),
+        ImmutableOrigin::Opaque(_) => return false,
+    };
+    let same_host = url.host_str() == Some(&*origin_host.to_string());
+    let url_port = url.port_or_known_default();
+    let same_port = url_port == Some(origin_port) ||
+        (url_port == default_port(url.scheme()) &&
+            Some(origin_port) == default_port(origin_scheme));
+    let upgrade = url.scheme() == "https" ||
+        url.scheme() == "wss" ||
+        (origin_scheme == "http" && (url.scheme() == "http" || url.scheme() == "ws"));
+    same_host && same_port && upgrade
+}
+
+fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        "ftp" => Some(21),
+        _ => None,
+    }
+}
+
+/// Returns the scheme of a scheme-source such as `https:`.
+fn scheme_source(expression: &str) -> Option<&str> {
+    if !expression.ends_with(':') {
+        return None;
+    }
+    let scheme = &expression[..expression.len() - 1];
+    let mut chars = scheme.chars();
+    let valid = chars.next().map_or(false, |c| c.is_ascii_alphabetic()) &&
+        chars.all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+    if valid {
+        Some(scheme)
+    } else {
+        None
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-schemes>
+fn scheme_part_matches(expression: &str, scheme: &str) -> bool {
+    let expression = expression.to_ascii_lowercase();
+    expression == scheme ||
+        (expression == "http" && scheme == "https") ||
+        (expression == "ws" && (scheme == "wss" || scheme == "http" || scheme == "https")) ||
+        (expression == "wss" && scheme == "https")
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-hosts>
+fn host_part_matches(expression: &str, host: &str) -> bool {
+    if expression == "*" {
+        return true;
+    }
+    if expression.starts_with("*.") {
+        let suffix = expression[1..].to_ascii_lowercase();
+        return host.len() > suffix.len() && host.to_ascii_lowercase().ends_with(&suffix);
+    }
+    expression.eq_ignore_ascii_case(host)
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-ports>
+fn port_part_matches(expression: Option<&str>, url: &ServoUrl) -> bool {
+    let url_port = url.port_or_known_default();
+    match expression {
+        None => url_port == default_port(url.scheme()),
+        Some("*") => true,
+        Some(port) => match port.parse::<u16>() {
+            Ok(port) => url_port == Some(port) || (port == 80 && url_port == Some(443)),
+            Err(_) => false,
+        },
+    }
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-paths>
+fn path_part_matches(expression: &str, path: &str) -> bool {
+    if expression.ends_with('/') {
+        path.starts_with(expression)
+    } else {
+        path == expression
+    }
+}
+
+/// Returns the value of a nonce-source such as `'nonce-abc'`.
+fn nonce_source(expression: &str) -> Option<&str> {
+    let prefix = "'nonce-";
+    if expression.len() > prefix.len() + 1 &&
+        expression
+            .get(..prefix.len())
+            .map_or(false, |start| start.eq_ignore_ascii_case(prefix)) &&
+        expression.ends_with('\'')
+    {
+        Some(&expression[prefix.len()..expression.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// Returns the algorithm and base64 value of a hash-source such as
+/// `'sha256-...'`.
+fn hash_source(expression: &str) -> Option<(String, &str)> {
+    if !expression.starts_with('\'') || !expression.ends_with('\'') || expression.len() < 2 {
+        return None;
+    }
+    let inner = &expression[1..expression.len() - 1];
+    let dash = inner.find('-')?;
+    let algorithm = inner[..dash].to_ascii_lowercase();
+    if algorithm != "sha256" && algorithm != "sha384" && algorithm != "sha512" {
+        return None;
+    }
+    Some((algorithm, &inner[dash + 1..]))
+}
+
+/// <https://w3c.github.io/webappsec-csp/#match-element-to-source-list>, step 5.
+fn hash_matches(expression: &str, source: &str) -> bool {
+    let (algorithm, expected) = match hash_source(expression) {
+        Some(hash) => hash,
+        None => return false,
+    };
+    let digest = match &*algorithm {
+        "sha256" => base64::encode(&Sha256::digest(source.as_bytes())),
+        "sha384" => base64::encode(&Sha384::digest(source.as_bytes())),
+        _ => base64::encode(&Sha512::digest(source.as_bytes())),
+    };
+    // Both the base64 and base64url alphabets are accepted.
+    let expected = expected.replace('-', "+").replace('_', "/");
+    digest == expected
+}
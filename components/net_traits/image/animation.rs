@@ -0,0 +1,343 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Decoding of animated GIF and PNG images into complete frames.
+
+use gif::SetParameter;
+use std::time::Duration;
+
+/// The most memory the frames of a single image may use. Frames past this
+/// limit are dropped.
+const MAX_FRAME_BYTES: usize = 256 * 1024 * 1024;
+
+/// Like other browsers, frame delays this short are treated as
+/// `DEFAULT_FRAME_DELAY`, since many images rely on it.
+const MIN_FRAME_DELAY: Duration = Duration::from_millis(10);
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// How the pixels of a frame are combined with the canvas.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Blend {
+    /// The frame replaces the pixels of its region.
+    Source,
+    /// The frame is alpha-composited over its region.
+    Over,
+}
+
+/// What happens to the region of a frame once it has been displayed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Disposal {
+    /// The frame is left in place.
+    None,
+    /// The region is cleared to transparent black.
+    Background,
+    /// The region is restored to what it was before the frame was drawn.
+    Previous,
+}
+
+/// The area of the canvas a frame is drawn into, in pixels.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The frames of an image, each a complete RGBA image of the same size.
+pub struct Frames {
+    pub width: u32,
+    pub height: u32,
+    /// The pixels of every frame, one after another.
+    pub bytes: Vec<u8>,
+    /// How long each frame is displayed.
+    pub delays: Vec<Duration>,
+}
+
+/// Returns the number of bytes of an RGBA image of the given size, if it's
+/// not empty and fits within the memory limit.
+pub fn frame_size(width: u32, height: u32) -> Result<usize, String> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|area| area.checked_mul(4))
+        .filter(|&size| size > 0 && size <= MAX_FRAME_BYTES)
+        .ok_or_else(|| format!("Unsupported image size {}x{}", width, height))
+}
+
+/// Draws the frames of an animation, each of which may cover only part of
+/// the canvas, into complete frames.
+pub struct FrameCompositor {
+    canvas: Vec<u8>,
+    frames: Frames,
+}
+
+impl FrameCompositor {
+    pub fn new(width: u32, height: u32) -> Result<FrameCompositor, String> {
+        Ok(FrameCompositor {
+            canvas: vec![0; frame_size(width, height)?],
+            frames: Frames {
+                width,
+                height,
+                bytes: vec![],
+                delays: vec![],
+            },
+        })
+    }
+
+    /// Whether the frames decoded so far leave no room for another one.
+    pub fn is_full(&self) -> bool {
+        self.frames.bytes.len() + self.canvas.len() > MAX_FRAME_BYTES
+    }
+
+    /// Draws a frame of `region.width * region.height` RGBA pixels, records
+    /// the resulting canvas as the next frame, and then disposes of the region.
+    pub fn add_frame(
+        &mut self,
+        pixels: &[u8],
+        region: FrameRegion,
+        delay: Duration,
+        blend: Blend,
+        disposal: Disposal,
+    ) -> Result<(), String> {
+        if pixels.len() < region.width as usize * region.height as usize * 4 {
+            return Err(format!("Truncated frame of size {:?}", region));
+        }
+
+        let previous = match disposal {
+            Disposal::Previous => Some(self.canvas.clone()),
+            Disposal::None | Disposal::Background => None,
+        };
+
+        let canvas_width = self.frames.width as usize;
+        let x_range = region.x as usize..
+            (region.x.saturating_add(region.width)).min(self.frames.width) as usize;
+        let y_range = region.y as usize..
+            (region.y.saturating_add(region.height)).min(self.frames.height) as usize;
+        for y in y_range.clone() {
+            let row = (y - region.y as usize) * region.width as usize;
+            for x in x_range.clone() {
+                let source = (row + x - region.x as usize) * 4;
+                let source = &pixels[source..source + 4];
+                let destination = (y * canvas_width + x) * 4;
+                let destination = &mut self.canvas[destination..destination + 4];
+                match blend {
+                    Blend::Source => destination.copy_from_slice(source),
+                    Blend::Over => blend_over(source, destination),
+                }
+            }
+        }
+
+        self.frames.bytes.extend_from_slice(&self.canvas);
+        self.frames.delays.push(if delay <= MIN_FRAME_DELAY {
+            DEFAULT_FRAME_DELAY
+        } else {
+            delay
+        });
+
+        match disposal {
+            Disposal::None => {},
+            Disposal::Background => {
+                for y in y_range {
+                    let start = (y * canvas_width + x_range.start) * 4;
+                    let end = (y * canvas_width + x_range.end) * 4;
+                    for byte in &mut self.canvas[start..end] {
+                        *byte = 0;
+                    }
+                }
+            },
+            Disposal::Previous => self.canvas = previous.unwrap(),
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<Frames, String> {
+        if self.frames.delays.is_empty() {
+            return Err("Image has no frames".to_owned());
+        }
+        Ok(self.frames)
+    }
+}
+
+/// Composites a non-premultiplied RGBA pixel over another.
+fn blend_over(source: &[u8], destination: &mut [u8]) {
+    let source_alpha = source[3] as u32;
+    if source_alpha == 0 {
+        return;
+    }
+    if source_alpha == 255 {
+        destination.copy_from_slice(source);
+        return;
+    }
+    let destination_alpha = destination[3] as u32 * (255 - source_alpha) / 255;
+    let alpha = source_alpha + destination_alpha;
+    for i in 0..3 {
+        destination[i] = ((source[i] as u32 * source_alpha +
+            destination[i] as u32 * destination_alpha) /
+            alpha) as u8;
+    }
+    destination[3] = alpha as u8;
+}
+
+pub fn decode_gif(buffer: &[u8]) -> Result<Frames, String> {
+    let mut decoder = gif::Decoder::new(buffer);
+    decoder.set(gif::ColorOutput::RGBA);
+    let mut reader = decoder
+        .read_info()
+        .map_err(|error| format!("{:?}", error))?;
+    let mut compositor = FrameCompositor::new(reader.width() as u32, reader.height() as u32)?;
+    while let Some(frame) = reader
+        .read_next_frame()
+        .map_err(|error| format!("{:?}", error))?
+    {
+        let region = FrameRegion {
+            x: frame.left as u32,
+            y: frame.top as u32,
+            width: frame.width as u32,
+            height: frame.height as u32,
+        };
+        let disposal = match frame.dispose {
+            gif::DisposalMethod::Any | gif::DisposalMethod::Keep => Disposal::None,
+            gif::DisposalMethod::Background => Disposal::Background,
+            gif::DisposalMethod::Previous => Disposal::Previous,
+        };
+        // GIF delays are in hundredths of a second.
+        let delay = Duration::from_millis(frame.delay as u64 * 10);
+        compositor.add_frame(&frame.buffer, region, delay, Blend::Over, disposal)?;
+        if compositor.is_full() {
+            warn!("Dropping the frames of a GIF image past the memory limit");
+            break;
+        }
+    }
+    compositor.finish()
+}
+
+/// Whether the given PNG image has an animation control chunk before its
+/// image data, which makes it an APNG image.
+pub fn is_animated_png(buffer: &[u8]) -> bool {
+    // Skip the signature, then walk the chunks: a big-endian length, a type,
+    // the data and a checksum.
+    let mut rest = buffer.get(8..).unwrap_or(&[]);
+    while rest.len() >= 8 {
+        let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        match &rest[4..8] {
+            b"acTL" => return true,
+            b"IDAT" => return false,
+            _ => {},
+        }
+        rest = match length.checked_add(12).and_then(|end| rest.get(end..)) {
+            Some(rest) => rest,
+            None => return false,
+        };
+    }
+    false
+}
+
+pub fn decode_apng(buffer: &[u8]) -> Result<Frames, String> {
+    let mut decoder = png::Decoder::new(buffer);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let (info, mut reader) = decoder
+        .read_info()
+        .map_err(|error| format!("{:?}", error))?;
+    let animation_control = reader
+        .info()
+        .animation_control
+        .ok_or("PNG image has no animation control chunk")?;
+    let (color_type, _) = reader.output_color_type();
+    let mut compositor = FrameCompositor::new(info.width, info.height)?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+
+    // The default image is only part of the animation if a frame control
+    // chunk precedes it.
+    if reader.info().frame_control.is_none() {
+        reader
+            .next_frame(&mut buffer)
+            .map_err(|error| format!("{:?}", error))?;
+    }
+
+    for index in 0..animation_control.num_frames {
+        reader
+            .next_frame(&mut buffer)
+            .map_err(|error| format!("{:?}", error))?;
+        let frame_control = reader
+            .info()
+            .frame_control
+            .ok_or("APNG frame has no frame control chunk")?;
+        let region = FrameRegion {
+            x: frame_control.x_offset,
+            y: frame_control.y_offset,
+            width: frame_control.width,
+            height: frame_control.height,
+        };
+        let pixels = png_frame_to_rgba(
+            &buffer,
+            color_type,
+            region,
+            reader.output_line_size(frame_control.width),
+        )?;
+        // The delay is a fraction of a second, where a zero denominator means
+        // hundredths.
+        let denominator = match frame_control.delay_den {
+            0 => 100,
+            denominator => denominator as u64,
+        };
+        let delay = Duration::from_millis(frame_control.delay_num as u64 * 1000 / denominator);
+        let blend = match frame_control.blend_op {
+            png::BlendOp::Source => Blend::Source,
+            png::BlendOp::Over => Blend::Over,
+        };
+        let disposal = match frame_control.dispose_op {
+            png::DisposeOp::None => Disposal::None,
+            png::DisposeOp::Background => Disposal::Background,
+            // There is nothing to restore before the first frame.
+            png::DisposeOp::Previous if index == 0 => Disposal::Background,
+            png::DisposeOp::Previous => Disposal::Previous,
+        };
+        compositor.add_frame(&pixels, region, delay, blend, disposal)?;
+        if compositor.is_full() {
+            warn!("Dropping the frames of an APNG image past the memory limit");
+            break;
+        }
+    }
+    compositor.finish()
+}
+
+/// Converts the rows of a decoded PNG frame to RGBA pixels.
+fn png_frame_to_rgba(
+    buffer: &[u8],
+    color_type: png::ColorType,
+    region: FrameRegion,
+    line_size: usize,
+) -> Result<Vec<u8>, String> {
+    let width = region.width as usize;
+    let height = region.height as usize;
+    if buffer.len() < line_size * height {
+        return Err(format!("Truncated APNG frame of size {:?}", region));
+    }
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in buffer.chunks(line_size).take(height) {
+        match color_type {
+            png::ColorType::RGBA => rgba.extend_from_slice(&row[..width * 4]),
+            png::ColorType::RGB => {
+                for rgb in row[..width * 3].chunks(3) {
+                    rgba.extend_from_slice(&[rgb[0], rgb[1], rgb[2], 255]);
+                }
+            },
+            png::ColorType::GrayscaleAlpha => {
+                for gray_alpha in row[..width * 2].chunks(2) {
+                    let (gray, alpha) = (gray_alpha[0], gray_alpha[1]);
+                    rgba.extend_from_slice(&[gray, gray, gray, alpha]);
+                }
+            },
+            png::ColorType::Grayscale => {
+                for &gray in &row[..width] {
+                    rgba.extend_from_slice(&[gray, gray, gray, 255]);
+                }
+            },
+            png::ColorType::Indexed => {
+                return Err("APNG palette was not expanded".to_owned());
+            },
+        }
+    }
+    Ok(rgba)
+}
@@ -2,28 +2,78 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::image::animation::{self, Frames};
+use crate::image::webp;
+use cssparser::RGBA;
 use ipc_channel::ipc::IpcSharedMemory;
-use piston_image::{DynamicImage, ImageFormat};
+use piston_image::DynamicImage;
 use pixels::PixelFormat;
 use std::fmt;
+use std::ops::Range;
+use std::time::Duration;
 
 #[derive(Clone, Deserialize, MallocSizeOf, Serialize)]
 pub struct Image {
     pub width: u32,
     pub height: u32,
     pub format: PixelFormat,
+    /// The pixels of every frame, one after another.
     #[ignore_malloc_size_of = "Defined in ipc-channel"]
     pub bytes: IpcSharedMemory,
     #[ignore_malloc_size_of = "Defined in webrender_api"]
     pub id: Option<webrender_api::ImageKey>,
+    /// The number of image pixels per CSS pixel of the natural size. Vector
+    /// images are rasterized at the device pixel ratio, raster images are 1.
+    pub density: f64,
+    /// The frames of the image, of which still images have one.
+    pub frames: Vec<ImageFrame>,
+}
+
+/// A frame of an image, stored in `Image::bytes`.
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct ImageFrame {
+    /// How long the frame is displayed, or `None` if the image isn't animated.
+    pub delay: Option<Duration>,
+    /// The range of `Image::bytes` holding the pixels of the frame.
+    pub byte_range: Range<usize>,
+}
+
+impl Image {
+    pub fn is_animated(&self) -> bool {
+        self.frames.len() > 1
+    }
+
+    pub fn frame_bytes(&self, index: usize) -> &[u8] {
+        &self.bytes[self.frames[index].byte_range.clone()]
+    }
+
+    /// The pixels of the first frame, which is what is used wherever an
+    /// animated image can't animate, such as when drawn onto a canvas.
+    pub fn first_frame_bytes(&self) -> &[u8] {
+        self.frame_bytes(0)
+    }
+
+    /// The natural size of this image in CSS pixels.
+    pub fn metadata(&self) -> ImageMetadata {
+        ImageMetadata {
+            width: (self.width as f64 / self.density).round() as u32,
+            height: (self.height as f64 / self.density).round() as u32,
+        }
+    }
 }
 
 impl fmt::Debug for Image {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Image {{ width: {}, height: {}, format: {:?}, ..., id: {:?} }}",
-            self.width, self.height, self.format, self.id
+            "Image {{ width: {}, height: {}, format: {:?}, ..., id: {:?}, density: {}, \
+             frames: {} }}",
+            self.width,
+            self.height,
+            self.format,
+            self.id,
+            self.density,
+            self.frames.len()
         )
     }
 }
@@ -37,7 +87,20 @@ pub struct ImageMetadata {
 // FIXME: Images must not be copied every frame. Instead we should atomically
 // reference count them.
 
-pub fn load_from_memory(buffer: &[u8]) -> Option<Image> {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+    GIF,
+    JPEG,
+    PNG,
+    BMP,
+    ICO,
+    SVG,
+    WEBP,
+}
+
+/// Decodes an image. Vector images are rasterized at their natural size
+/// multiplied by `device_pixel_ratio`.
+pub fn load_from_memory(buffer: &[u8], device_pixel_ratio: f32) -> Option<Image> {
     if buffer.is_empty() {
         return None;
     }
@@ -48,6 +111,12 @@ pub fn load_from_memory(buffer: &[u8]) -> Option<Image> {
             debug!("{}", msg);
             None
         },
+        Ok(ImageFormat::SVG) => load_svg_from_memory(buffer, device_pixel_ratio),
+        Ok(ImageFormat::GIF) => load_frames(animation::decode_gif(buffer)),
+        Ok(ImageFormat::PNG) if animation::is_animated_png(buffer) => {
+            load_frames(animation::decode_apng(buffer))
+        },
+        Ok(ImageFormat::WEBP) => load_frames(webp::decode_webp(buffer)),
         Ok(_) => match piston_image::load_from_memory(buffer) {
             Ok(image) => {
                 let mut rgba = match image {
@@ -61,6 +130,11 @@ pub fn load_from_memory(buffer: &[u8]) -> Option<Image> {
                     format: PixelFormat::BGRA8,
                     bytes: IpcSharedMemory::from_bytes(&*rgba),
                     id: None,
+                    density: 1.,
+                    frames: vec![ImageFrame {
+                        delay: None,
+                        byte_range: 0..rgba.len(),
+                    }],
                 })
             },
             Err(e) => {
@@ -71,6 +145,67 @@ pub fn load_from_memory(buffer: &[u8]) -> Option<Image> {
     }
 }
 
+fn load_frames(frames: Result<Frames, String>) -> Option<Image> {
+    let mut frames = match frames {
+        Ok(frames) => frames,
+        Err(e) => {
+            debug!("Image decoding error: {}", e);
+            return None;
+        },
+    };
+    pixels::rgba8_byte_swap_colors_inplace(&mut frames.bytes);
+    let animated = frames.delays.len() > 1;
+    let frame_length = frames.bytes.len() / frames.delays.len();
+    Some(Image {
+        width: frames.width,
+        height: frames.height,
+        format: PixelFormat::BGRA8,
+        bytes: IpcSharedMemory::from_bytes(&frames.bytes),
+        id: None,
+        density: 1.,
+        frames: frames
+            .delays
+            .iter()
+            .enumerate()
+            .map(|(index, &delay)| ImageFrame {
+                delay: if animated { Some(delay) } else { None },
+                byte_range: index * frame_length..(index + 1) * frame_length,
+            })
+            .collect(),
+    })
+}
+
+fn load_svg_from_memory(buffer: &[u8], device_pixel_ratio: f32) -> Option<Image> {
+    let root = match servo_svg::parse(buffer) {
+        Ok(root) => root,
+        Err(e) => {
+            debug!("SVG image parsing error: {}", e);
+            return None;
+        },
+    };
+    let size = servo_svg::intrinsic_size(&root);
+    // SVG images are isolated documents, so `currentColor` is the initial color.
+    let raster = servo_svg::rasterize(&root, size, device_pixel_ratio, RGBA::new(0, 0, 0, 255));
+    if raster.width == 0 || raster.height == 0 {
+        debug!("SVG image has no area");
+        return None;
+    }
+    let mut bytes = raster.bytes;
+    pixels::rgba8_unpremultiply_inplace(&mut bytes);
+    Some(Image {
+        width: raster.width,
+        height: raster.height,
+        format: PixelFormat::BGRA8,
+        bytes: IpcSharedMemory::from_bytes(&bytes),
+        id: None,
+        density: device_pixel_ratio as f64,
+        frames: vec![ImageFrame {
+            delay: None,
+            byte_range: 0..bytes.len(),
+        }],
+    })
+}
+
 // https://developer.mozilla.org/en-US/docs/Web/HTML/Element/img
 pub fn detect_image_format(buffer: &[u8]) -> Result<ImageFormat, &str> {
     if is_gif(buffer) {
@@ -83,6 +218,10 @@ pub fn detect_image_format(buffer: &[u8]) -> Result<ImageFormat, &str> {
         Ok(ImageFormat::BMP)
     } else if is_ico(buffer) {
         Ok(ImageFormat::ICO)
+    } else if is_webp(buffer) {
+        Ok(ImageFormat::WEBP)
+    } else if servo_svg::is_svg(buffer) {
+        Ok(ImageFormat::SVG)
     } else {
         Err("Image Format Not Supported")
     }
@@ -107,3 +246,7 @@ fn is_bmp(buffer: &[u8]) -> bool {
 fn is_ico(buffer: &[u8]) -> bool {
     buffer.starts_with(&[0x00, 0x00, 0x01, 0x00])
 }
+
+fn is_webp(buffer: &[u8]) -> bool {
+    buffer.len() >= 12 && buffer.starts_with(b"RIFF") && &buffer[8..12] == b"WEBP"
+}
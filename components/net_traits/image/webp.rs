@@ -0,0 +1,179 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Decoding of still and animated WebP images. The RIFF container is parsed
+//! here, and the lossy and lossless bitstreams are decoded by libwebp.
+
+#![allow(unsafe_code)]
+
+use crate::image::animation::{self, Blend, Disposal, FrameCompositor, FrameRegion, Frames};
+use libwebp_sys::{WebPDecodeRGBAInto, WebPGetInfo};
+use std::iter;
+use std::os::raw::c_int;
+use std::time::Duration;
+
+/// Flags of the `VP8X` chunk.
+const ALPHA_FLAG: u8 = 0x10;
+const ANIMATION_FLAG: u8 = 0x02;
+
+/// Iterates over the `(fourcc, payload)` pairs of a sequence of RIFF chunks.
+fn chunks<'a>(mut data: &'a [u8]) -> impl Iterator<Item = (&'a [u8], &'a [u8])> {
+    iter::from_fn(move || {
+        if data.len() < 8 {
+            return None;
+        }
+        let fourcc = &data[..4];
+        let size = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+        let payload = data.get(8..size.checked_add(8)?)?;
+        // Chunks are padded to an even size.
+        data = data.get(8 + size + (size & 1)..).unwrap_or(&[]);
+        Some((fourcc, payload))
+    })
+}
+
+fn read_u24(bytes: &[u8]) -> u32 {
+    bytes[0] as u32 | (bytes[1] as u32) << 8 | (bytes[2] as u32) << 16
+}
+
+fn write_u24(bytes: &mut [u8], value: u32) {
+    bytes[..3].copy_from_slice(&value.to_le_bytes()[..3]);
+}
+
+fn push_chunk(file: &mut Vec<u8>, fourcc: &[u8], payload: &[u8]) {
+    file.extend_from_slice(fourcc);
+    file.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    file.extend_from_slice(payload);
+    if payload.len() % 2 == 1 {
+        file.push(0);
+    }
+}
+
+pub fn decode_webp(buffer: &[u8]) -> Result<Frames, String> {
+    let body = buffer.get(12..).ok_or("Truncated WebP image")?;
+    let mut chunks = chunks(body);
+    match chunks.next() {
+        Some((fourcc, header)) if fourcc == b"VP8X" && header.len() >= 10 => {
+            if header[0] & ANIMATION_FLAG != 0 {
+                let width = read_u24(&header[4..]) + 1;
+                let height = read_u24(&header[7..]) + 1;
+                return decode_animation(width, height, chunks);
+            }
+        },
+        Some(_) => {},
+        None => return Err("WebP image has no chunks".to_owned()),
+    }
+
+    let (width, height, bytes) = decode_bitstream(buffer)?;
+    Ok(Frames {
+        width,
+        height,
+        bytes,
+        delays: vec![Duration::from_millis(0)],
+    })
+}
+
+fn decode_animation<'a>(
+    width: u32,
+    height: u32,
+    chunks: impl Iterator<Item = (&'a [u8], &'a [u8])>,
+) -> Result<Frames, String> {
+    let mut compositor = FrameCompositor::new(width, height)?;
+    for (_, frame) in chunks.filter(|&(fourcc, _)| fourcc == b"ANMF") {
+        if frame.len() < 16 {
+            return Err("Truncated WebP animation frame".to_owned());
+        }
+        let region = FrameRegion {
+            x: read_u24(&frame[0..]) * 2,
+            y: read_u24(&frame[3..]) * 2,
+            width: read_u24(&frame[6..]) + 1,
+            height: read_u24(&frame[9..]) + 1,
+        };
+        let delay = Duration::from_millis(read_u24(&frame[12..]) as u64);
+        let blend = if frame[15] & 0b10 != 0 {
+            Blend::Source
+        } else {
+            Blend::Over
+        };
+        let disposal = if frame[15] & 0b1 != 0 {
+            Disposal::Background
+        } else {
+            Disposal::None
+        };
+
+        let file = frame_file(&frame[16..], region)?;
+        let (frame_width, frame_height, pixels) = decode_bitstream(&file)?;
+        if (frame_width, frame_height) != (region.width, region.height) {
+            return Err(format!(
+                "WebP frame of size {}x{} doesn't match {:?}",
+                frame_width, frame_height, region
+            ));
+        }
+        compositor.add_frame(&pixels, region, delay, blend, disposal)?;
+        if compositor.is_full() {
+            warn!("Dropping the frames of a WebP image past the memory limit");
+            break;
+        }
+    }
+    compositor.finish()
+}
+
+/// Wraps the data of an animation frame, an optional `ALPH` chunk and a `VP8 `
+/// or `VP8L` chunk, into a standalone WebP file that libwebp can decode.
+fn frame_file(data: &[u8], region: FrameRegion) -> Result<Vec<u8>, String> {
+    let mut alpha = None;
+    let mut image = None;
+    for (fourcc, payload) in chunks(data) {
+        if fourcc == b"ALPH" {
+            alpha = Some(payload);
+        } else if fourcc == b"VP8 " || fourcc == b"VP8L" {
+            image = Some((fourcc, payload));
+            break;
+        }
+    }
+    let (fourcc, payload) = image.ok_or("WebP animation frame has no image data")?;
+
+    let mut file_chunks = vec![];
+    // Only lossy frames keep their alpha channel in a separate chunk, which
+    // needs an extended header.
+    if let Some(alpha) = alpha.filter(|_| fourcc == b"VP8 ") {
+        let mut header = [0; 10];
+        header[0] = ALPHA_FLAG;
+        write_u24(&mut header[4..], region.width - 1);
+        write_u24(&mut header[7..], region.height - 1);
+        push_chunk(&mut file_chunks, b"VP8X", &header);
+        push_chunk(&mut file_chunks, b"ALPH", alpha);
+    }
+    push_chunk(&mut file_chunks, fourcc, payload);
+
+    let mut file = Vec::with_capacity(12 + file_chunks.len());
+    file.extend_from_slice(b"RIFF");
+    file.extend_from_slice(&(4 + file_chunks.len() as u32).to_le_bytes());
+    file.extend_from_slice(b"WEBP");
+    file.extend_from_slice(&file_chunks);
+    Ok(file)
+}
+
+/// Decodes a WebP file without animation into RGBA pixels.
+fn decode_bitstream(data: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let (mut width, mut height): (c_int, c_int) = (0, 0);
+    // The bindings take `size_t` lengths, whose Rust type depends on the
+    // version of bindgen they were generated with.
+    if unsafe { WebPGetInfo(data.as_ptr(), data.len() as _, &mut width, &mut height) } == 0 {
+        return Err("Invalid WebP image data".to_owned());
+    }
+    let mut pixels = vec![0; animation::frame_size(width as u32, height as u32)?];
+    let output = unsafe {
+        WebPDecodeRGBAInto(
+            data.as_ptr(),
+            data.len() as _,
+            pixels.as_mut_ptr(),
+            pixels.len() as _,
+            width * 4,
+        )
+    };
+    if output.is_null() {
+        return Err("WebP image decoding failed".to_owned());
+    }
+    Ok((width as u32, height as u32, pixels))
+}
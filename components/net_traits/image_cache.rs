@@ -100,7 +100,9 @@ pub enum UsePlaceholder {
 // ======================================================================
 
 pub trait ImageCache: Sync + Send {
-    fn new(webrender_api: webrender_api::RenderApi) -> Self
+    /// Create an image cache that rasterizes vector images for displays with
+    /// the given device pixel ratio.
+    fn new(webrender_api: webrender_api::RenderApi, device_pixel_ratio: f32) -> Self
     where
         Self: Sized;
 
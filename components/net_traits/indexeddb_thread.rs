@@ -0,0 +1,355 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use ipc_channel::ipc::IpcSender;
+use servo_url::ImmutableOrigin;
+use std::cmp::Ordering;
+
+/// <https://w3c.github.io/IndexedDB/#key-construct>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub enum IndexedDBKey {
+    Number(f64),
+    Date(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<IndexedDBKey>),
+}
+
+impl IndexedDBKey {
+    /// The position of the type of the key in the ordering of keys.
+    fn type_order(&self) -> u8 {
+        match *self {
+            IndexedDBKey::Number(_) => 0,
+            IndexedDBKey::Date(_) => 1,
+            IndexedDBKey::String(_) => 2,
+            IndexedDBKey::Binary(_) => 3,
+            IndexedDBKey::Array(_) => 4,
+        }
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#compare-two-keys>
+impl Ord for IndexedDBKey {
+    fn cmp(&self, other: &IndexedDBKey) -> Ordering {
+        match (self, other) {
+            (&IndexedDBKey::Number(a), &IndexedDBKey::Number(b)) |
+            (&IndexedDBKey::Date(a), &IndexedDBKey::Date(b)) => {
+                // Keys are never NaN.
+                a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+            },
+            // Strings are compared by code unit, not by code point.
+            (&IndexedDBKey::String(ref a), &IndexedDBKey::String(ref b)) => {
+                a.encode_utf16().cmp(b.encode_utf16())
+            },
+            (&IndexedDBKey::Binary(ref a), &IndexedDBKey::Binary(ref b)) => a.cmp(b),
+            (&IndexedDBKey::Array(ref a), &IndexedDBKey::Array(ref b)) => a.cmp(b),
+            _ => self.type_order().cmp(&other.type_order()),
+        }
+    }
+}
+
+impl PartialOrd for IndexedDBKey {
+    fn partial_cmp(&self, other: &IndexedDBKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for IndexedDBKey {
+    fn eq(&self, other: &IndexedDBKey) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IndexedDBKey {}
+
+/// <https://w3c.github.io/IndexedDB/#range-construct>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct IndexedDBKeyRange {
+    pub lower: Option<IndexedDBKey>,
+    pub upper: Option<IndexedDBKey>,
+    pub lower_open: bool,
+    pub upper_open: bool,
+}
+
+impl IndexedDBKeyRange {
+    /// A range containing every key.
+    pub fn unbounded() -> IndexedDBKeyRange {
+        IndexedDBKeyRange {
+            lower: None,
+            upper: None,
+            lower_open: false,
+            upper_open: false,
+        }
+    }
+
+    /// A range containing only the given key.
+    pub fn only(key: IndexedDBKey) -> IndexedDBKeyRange {
+        IndexedDBKeyRange {
+            lower: Some(key.clone()),
+            upper: Some(key),
+            lower_open: false,
+            upper_open: false,
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#in>
+    pub fn contains(&self, key: &IndexedDBKey) -> bool {
+        let above_lower = match self.lower {
+            Some(ref lower) if self.lower_open => key > lower,
+            Some(ref lower) => key >= lower,
+            None => true,
+        };
+        let below_upper = match self.upper {
+            Some(ref upper) if self.upper_open => key < upper,
+            Some(ref upper) => key <= upper,
+            None => true,
+        };
+        above_lower && below_upper
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#key-path-construct>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum KeyPath {
+    String(String),
+    Sequence(Vec<String>),
+}
+
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct IndexMetadata {
+    pub name: String,
+    pub key_path: KeyPath,
+    pub unique: bool,
+    pub multi_entry: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct ObjectStoreMetadata {
+    pub name: String,
+    pub key_path: Option<KeyPath>,
+    pub auto_increment: bool,
+    pub indexes: Vec<IndexMetadata>,
+}
+
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, Serialize)]
+pub struct DatabaseMetadata {
+    pub name: String,
+    /// The version of the database, zero if it doesn't exist yet.
+    pub version: u64,
+    pub object_stores: Vec<ObjectStoreMetadata>,
+}
+
+/// <https://w3c.github.io/IndexedDB/#transaction-mode>
+#[derive(Clone, Copy, Debug, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
+pub enum IndexedDBTxnMode {
+    Readonly,
+    Readwrite,
+    Versionchange,
+}
+
+/// <https://w3c.github.io/IndexedDB/#cursor-direction>
+#[derive(Clone, Copy, Debug, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
+pub enum CursorDirection {
+    Next,
+    NextUnique,
+    Prev,
+    PrevUnique,
+}
+
+/// A record of an object store or of an index. The key of an object store record is its
+/// primary key.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IndexedDBRecord {
+    pub key: IndexedDBKey,
+    pub primary_key: IndexedDBKey,
+    /// The serialized value of the record.
+    pub value: Vec<u8>,
+}
+
+/// The errors of the backend, named after the DOM exceptions they are reported as.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum IndexedDBError {
+    Constraint,
+    NotFound,
+    Unknown,
+}
+
+/// An operation on a database, performed as part of a transaction. Operations that read
+/// data apply to the given index of the object store, if any.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum AsyncOperation {
+    /// Get the first record in the range.
+    GetRecord {
+        store: String,
+        index: Option<String>,
+        range: IndexedDBKeyRange,
+    },
+    /// Get the records in the range, up to `count` of them.
+    GetAllRecords {
+        store: String,
+        index: Option<String>,
+        range: IndexedDBKeyRange,
+        count: Option<u32>,
+    },
+    /// Count the records in the range.
+    Count {
+        store: String,
+        index: Option<String>,
+        range: IndexedDBKeyRange,
+    },
+    /// Find the record that a cursor moves to after skipping `count - 1` records.
+    /// `position` and `object_store_position` are the current position of the cursor.
+    Iterate {
+        store: String,
+        index: Option<String>,
+        range: IndexedDBKeyRange,
+        direction: CursorDirection,
+        position: Option<IndexedDBKey>,
+        object_store_position: Option<IndexedDBKey>,
+        key: Option<IndexedDBKey>,
+        primary_key: Option<IndexedDBKey>,
+        count: u32,
+    },
+    /// Store a record, along with the keys it has in each index of its object store.
+    PutRecord {
+        store: String,
+        key: IndexedDBKey,
+        value: Vec<u8>,
+        index_keys: Vec<(String, Vec<IndexedDBKey>)>,
+        no_overwrite: bool,
+    },
+    /// Generate a key with the key generator of the object store.
+    GenerateKey {
+        store: String,
+    },
+    DeleteRange {
+        store: String,
+        range: IndexedDBKeyRange,
+    },
+    Clear {
+        store: String,
+    },
+    SetVersion(u64),
+    CreateObjectStore(ObjectStoreMetadata),
+    DeleteObjectStore(String),
+    RenameObjectStore {
+        name: String,
+        new_name: String,
+    },
+    /// Create an index, along with the keys the existing records have in it.
+    CreateIndex {
+        store: String,
+        index: IndexMetadata,
+        keys: Vec<(IndexedDBKey, Vec<IndexedDBKey>)>,
+    },
+    DeleteIndex {
+        store: String,
+        name: String,
+    },
+    RenameIndex {
+        store: String,
+        name: String,
+        new_name: String,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum OperationResult {
+    Key(IndexedDBKey),
+    Record(Option<IndexedDBRecord>),
+    Records(Vec<IndexedDBRecord>),
+    Count(u64),
+    Done,
+}
+
+/// Sent to the open connections to a database when another connection is about to
+/// upgrade or delete it.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct VersionChange {
+    pub old_version: u64,
+    /// The version the database is upgraded to, or `None` if it is deleted.
+    pub new_version: Option<u64>,
+}
+
+/// Sent to a request that waits for the other connections to a database to be closed.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum WaitEvent {
+    /// Some of the other connections are still open after they were told about the
+    /// version change.
+    Blocked(VersionChange),
+    /// All the other connections are closed.
+    Done,
+}
+
+/// Request operations on the IndexedDB databases of an origin.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum IndexedDBThreadMsg {
+    /// Open a connection to a database, replying with the id of the connection and the
+    /// metadata of the database, which doesn't exist until it is first upgraded. Version
+    /// changes requested by other connections are sent to the given sender
+    Open(
+        IpcSender<Result<(u64, DatabaseMetadata), IndexedDBError>>,
+        ImmutableOrigin,
+        String,
+        IpcSender<VersionChange>,
+    ),
+
+    /// Close a connection, resuming the requests that were waiting for it
+    Close(ImmutableOrigin, String, u64),
+
+    /// Tell the open connections to a database, other than the given one, that it is about
+    /// to be upgraded to the given version, or deleted if there is none. Replies with
+    /// `Blocked` if some of them stay open, and with `Done` once they are all closed
+    WaitForConnections(
+        IpcSender<WaitEvent>,
+        ImmutableOrigin,
+        String,
+        Option<u64>,
+        Option<u64>,
+    ),
+
+    /// Acknowledge that a connection fired the `versionchange` event it was sent, after
+    /// which it blocks the requests that wait for it if it is still open
+    VersionChangeFired(ImmutableOrigin, String, u64),
+
+    /// Start a transaction, replying with its id once the transactions whose scope
+    /// overlaps with it have finished
+    BeginTransaction(
+        IpcSender<u64>,
+        ImmutableOrigin,
+        String,
+        IndexedDBTxnMode,
+        Vec<String>,
+    ),
+
+    /// Perform an operation as part of a started transaction
+    Operation(
+        IpcSender<Result<OperationResult, IndexedDBError>>,
+        ImmutableOrigin,
+        String,
+        u64,
+        AsyncOperation,
+    ),
+
+    /// Save the changes made by a transaction and end it
+    Commit(
+        IpcSender<Result<(), IndexedDBError>>,
+        ImmutableOrigin,
+        String,
+        u64,
+    ),
+
+    /// Undo the changes made by a transaction and end it
+    Abort(ImmutableOrigin, String, u64),
+
+    /// Delete a database once its transactions have finished, replying with its version
+    DeleteDatabase(
+        IpcSender<Result<u64, IndexedDBError>>,
+        ImmutableOrigin,
+        String,
+    ),
+
+    /// Send a reply when done cleaning up thread resources and then shut it down
+    Exit(IpcSender<()>),
+}
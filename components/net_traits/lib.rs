@@ -15,7 +15,9 @@ extern crate malloc_size_of_derive;
 #[macro_use]
 extern crate serde;
 
+use crate::csp::Violation;
 use crate::filemanager_thread::FileManagerThreadMsg;
+use crate::indexeddb_thread::IndexedDBThreadMsg;
 use crate::request::{Request, RequestBuilder};
 use crate::response::{HttpsState, Response, ResponseInit};
 use crate::storage_thread::StorageThreadMsg;
@@ -30,13 +32,15 @@ use ipc_channel::router::ROUTER;
 use ipc_channel::Error as IpcError;
 use mime::Mime;
 use msg::constellation_msg::HistoryStateId;
-use servo_url::ServoUrl;
+use servo_url::{ImmutableOrigin, ServoUrl};
 use std::error::Error;
 use time::precise_time_ns;
 
 pub mod blob_url_store;
+pub mod csp;
 pub mod filemanager_thread;
 pub mod image_cache;
+pub mod indexeddb_thread;
 pub mod pub_domains;
 pub mod quality;
 pub mod request;
@@ -49,7 +53,9 @@ pub mod storage_thread;
 /// However, image handling is generally very integrated with the network stack (especially where
 /// caching is involved) and as a result it must live in here.
 pub mod image {
+    mod animation;
     pub mod base;
+    mod webp;
 }
 
 /// A loading context, for context-specific sniffing, as defined in
@@ -98,10 +104,15 @@ impl CustomResponse {
     }
 }
 
+/// A request sent to the service worker manager, to be answered by the service worker
+/// controlling it, if any.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CustomResponseMediator {
-    pub response_chan: IpcSender<Option<CustomResponse>>,
-    pub load_url: ServoUrl,
+    /// Receives `None` when no service worker provided a response, and the request
+    /// should go to the network.
+    pub response_chan: IpcSender<Option<Result<CustomResponse, NetworkError>>>,
+    /// The intercepted request, without its body.
+    pub request: RequestBuilder,
 }
 
 /// [Policies](https://w3c.github.io/webappsec-referrer-policy/#referrer-policy-states)
@@ -154,6 +165,7 @@ pub enum FetchResponseMsg {
     ProcessResponse(Result<FetchMetadata, NetworkError>),
     ProcessResponseChunk(Vec<u8>),
     ProcessResponseEOF(Result<ResourceFetchTiming, NetworkError>),
+    ProcessCspViolations(Vec<Violation>),
 }
 
 pub trait FetchTaskTarget {
@@ -179,6 +191,11 @@ pub trait FetchTaskTarget {
     ///
     /// Fired when the response is fully fetched
     fn process_response_eof(&mut self, response: &Response);
+
+    /// <https://w3c.github.io/webappsec-csp/#report-for-request>
+    ///
+    /// Fired when the request violates a content security policy of its client
+    fn process_csp_violations(&mut self, request: &Request, violations: Vec<Violation>);
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -207,6 +224,7 @@ pub trait FetchResponseListener {
     fn resource_timing(&self) -> &ResourceFetchTiming;
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming;
     fn submit_resource_timing(&mut self);
+    fn process_csp_violations(&mut self, violations: Vec<Violation>);
 }
 
 impl FetchTaskTarget for IpcSender<FetchResponseMsg> {
@@ -235,6 +253,10 @@ impl FetchTaskTarget for IpcSender<FetchResponseMsg> {
                 .clone())));
         }
     }
+
+    fn process_csp_violations(&mut self, _: &Request, violations: Vec<Violation>) {
+        let _ = self.send(FetchResponseMsg::ProcessCspViolations(violations));
+    }
 }
 
 pub trait Action<Listener> {
@@ -266,6 +288,9 @@ impl<T: FetchResponseListener> Action<T> for FetchResponseMsg {
                     Err(e) => listener.process_response_eof(Err(e)),
                 }
             },
+            FetchResponseMsg::ProcessCspViolations(violations) => {
+                listener.process_csp_violations(violations)
+            },
         }
     }
 }
@@ -297,13 +322,19 @@ where
 pub struct ResourceThreads {
     core_thread: CoreResourceThread,
     storage_thread: IpcSender<StorageThreadMsg>,
+    idb_thread: IpcSender<IndexedDBThreadMsg>,
 }
 
 impl ResourceThreads {
-    pub fn new(c: CoreResourceThread, s: IpcSender<StorageThreadMsg>) -> ResourceThreads {
+    pub fn new(
+        c: CoreResourceThread,
+        s: IpcSender<StorageThreadMsg>,
+        i: IpcSender<IndexedDBThreadMsg>,
+    ) -> ResourceThreads {
         ResourceThreads {
             core_thread: c,
             storage_thread: s,
+            idb_thread: i,
         }
     }
 }
@@ -328,6 +359,16 @@ impl IpcSend<StorageThreadMsg> for ResourceThreads {
     }
 }
 
+impl IpcSend<IndexedDBThreadMsg> for ResourceThreads {
+    fn send(&self, msg: IndexedDBThreadMsg) -> IpcSendResult {
+        self.idb_thread.send(msg)
+    }
+
+    fn sender(&self) -> IpcSender<IndexedDBThreadMsg> {
+        self.idb_thread.clone()
+    }
+}
+
 // Ignore the sub-fields
 malloc_size_of_is_0!(ResourceThreads);
 
@@ -355,6 +396,7 @@ pub enum WebSocketNetworkEvent {
     MessageReceived(MessageData),
     Close(Option<u16>, String),
     Fail,
+    ReportCspViolations(Vec<Violation>),
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -383,9 +425,19 @@ pub enum CoreResourceMsg {
     /// Store a cookie for a given originating URL
     SetCookieForUrl(ServoUrl, Serde<Cookie<'static>>, CookieSource),
     /// Store a set of cookies for a given originating URL
-    SetCookiesForUrl(ServoUrl, Vec<Serde<Cookie<'static>>>, CookieSource),
+    SetCookiesForUrl(
+        ServoUrl,
+        Vec<Serde<Cookie<'static>>>,
+        CookieSource,
+        SiteForCookies,
+    ),
     /// Retrieve the stored cookies for a given URL
-    GetCookiesForUrl(ServoUrl, IpcSender<Option<String>>, CookieSource),
+    GetCookiesForUrl(
+        ServoUrl,
+        IpcSender<Option<String>>,
+        CookieSource,
+        SiteForCookies,
+    ),
     /// Get a cookie by name for a given originating URL
     GetCookiesDataForUrl(
         ServoUrl,
@@ -562,6 +614,9 @@ pub struct Metadata {
     pub referrer_policy: Option<ReferrerPolicy>,
     /// Performance information for navigation events
     pub timing: Option<ResourceFetchTiming>,
+
+    /// Content security policies delivered with the response
+    pub csp_list: Option<CspList>,
 }
 
 impl Metadata {
@@ -579,6 +634,7 @@ impl Metadata {
             referrer: None,
             referrer_policy: None,
             timing: None,
+            csp_list: None,
         }
     }
 
@@ -610,6 +666,47 @@ pub enum CookieSource {
     NonHTTP,
 }
 
+/// The site on whose behalf cookies are read or written, used to decide
+/// which `SameSite` cookies apply.
+///
+/// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.2>
+#[derive(Clone, Debug, Deserialize, MallocSizeOf, PartialEq, Serialize)]
+pub enum SiteForCookies {
+    /// The user agent itself, e.g. for a URL typed by the user. Every request
+    /// made this way is same-site.
+    UserAgent,
+    /// A document, or a worker created by one, whose top-level document has
+    /// the given origin. `None` if one of its ancestors is cross-site with
+    /// the top-level document.
+    Document(Option<ImmutableOrigin>),
+    /// A top-level navigation initiated by a document with the given origin.
+    /// The navigation is its own site for cookies, but the initiator decides
+    /// whether `Strict` cookies are sent.
+    TopLevelNavigation(ImmutableOrigin),
+}
+
+impl Default for SiteForCookies {
+    fn default() -> SiteForCookies {
+        SiteForCookies::UserAgent
+    }
+}
+
+impl SiteForCookies {
+    /// Whether a request to `url` made on behalf of this site is same-site.
+    ///
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.2>
+    pub fn is_same_site(&self, url: &ServoUrl) -> bool {
+        match *self {
+            SiteForCookies::UserAgent => true,
+            SiteForCookies::Document(Some(ref origin)) |
+            SiteForCookies::TopLevelNavigation(ref origin) => {
+                pub_domains::is_same_site(origin, &url.origin())
+            },
+            SiteForCookies::Document(None) => false,
+        }
+    }
+}
+
 /// Network errors that have to be exported out of the loaders
 #[derive(Clone, Debug, Deserialize, Eq, MallocSizeOf, PartialEq, Serialize)]
 pub enum NetworkError {
@@ -147,11 +147,27 @@ pub fn is_reg_domain(domain: &str) -> bool {
 /// Returns the registered suffix for the host name if it is a domain.
 /// Leaves the host name alone if it is an IP address.
 pub fn reg_host(url: &ServoUrl) -> Option<Host> {
-    match url.origin() {
-        ImmutableOrigin::Tuple(_, Host::Domain(domain), _) => {
+    reg_host_for_origin(&url.origin())
+}
+
+/// The registered domain name (aka eTLD+1) for an origin, see `reg_host`.
+pub fn reg_host_for_origin(origin: &ImmutableOrigin) -> Option<Host> {
+    match *origin {
+        ImmutableOrigin::Tuple(_, Host::Domain(ref domain), _) => {
             Some(Host::Domain(String::from(reg_suffix(&*domain))))
         },
-        ImmutableOrigin::Tuple(_, ip, _) => Some(ip),
+        ImmutableOrigin::Tuple(_, ref ip, _) => Some(ip.clone()),
         ImmutableOrigin::Opaque(_) => None,
     }
 }
+
+/// Whether two origins are same-site, i.e. both have the same registered
+/// domain name, regardless of scheme.
+///
+/// <https://html.spec.whatwg.org/multipage/#schemelessly-same-site>
+pub fn is_same_site(a: &ImmutableOrigin, b: &ImmutableOrigin) -> bool {
+    match reg_host_for_origin(a) {
+        Some(host) => reg_host_for_origin(b) == Some(host),
+        None => a == b,
+    }
+}
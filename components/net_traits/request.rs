@@ -2,8 +2,10 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::csp::CspList;
 use crate::ReferrerPolicy;
 use crate::ResourceTimingType;
+use crate::SiteForCookies;
 use http::HeaderMap;
 use hyper::Method;
 use msg::constellation_msg::PipelineId;
@@ -178,6 +180,9 @@ pub struct RequestBuilder {
     // to keep track of redirects
     pub url_list: Vec<ServoUrl>,
     pub parser_metadata: ParserMetadata,
+    pub csp_list: Option<CspList>,
+    pub cryptographic_nonce_metadata: String,
+    pub site_for_cookies: SiteForCookies,
 }
 
 impl RequestBuilder {
@@ -204,6 +209,9 @@ impl RequestBuilder {
             integrity_metadata: "".to_owned(),
             url_list: vec![],
             parser_metadata: ParserMetadata::Default,
+            csp_list: None,
+            cryptographic_nonce_metadata: String::new(),
+            site_for_cookies: SiteForCookies::Document(None),
         }
     }
 
@@ -227,6 +235,14 @@ impl RequestBuilder {
         self
     }
 
+    pub fn service_workers_mode(
+        mut self,
+        service_workers_mode: ServiceWorkersMode,
+    ) -> RequestBuilder {
+        self.service_workers_mode = service_workers_mode;
+        self
+    }
+
     pub fn destination(mut self, destination: Destination) -> RequestBuilder {
         self.destination = destination;
         self
@@ -292,6 +308,21 @@ impl RequestBuilder {
         self
     }
 
+    pub fn csp_list(mut self, csp_list: Option<CspList>) -> RequestBuilder {
+        self.csp_list = csp_list;
+        self
+    }
+
+    pub fn cryptographic_nonce_metadata(mut self, nonce: String) -> RequestBuilder {
+        self.cryptographic_nonce_metadata = nonce;
+        self
+    }
+
+    pub fn site_for_cookies(mut self, site_for_cookies: SiteForCookies) -> RequestBuilder {
+        self.site_for_cookies = site_for_cookies;
+        self
+    }
+
     pub fn build(self) -> Request {
         let mut request = Request::new(
             self.url.clone(),
@@ -321,6 +352,9 @@ impl RequestBuilder {
         request.url_list = url_list;
         request.integrity_metadata = self.integrity_metadata;
         request.parser_metadata = self.parser_metadata;
+        request.csp_list = self.csp_list;
+        request.cryptographic_nonce_metadata = self.cryptographic_nonce_metadata;
+        request.site_for_cookies = self.site_for_cookies;
         request
     }
 }
@@ -388,6 +422,16 @@ pub struct Request {
     pub response_tainting: ResponseTainting,
     /// <https://fetch.spec.whatwg.org/#concept-request-parser-metadata>
     pub parser_metadata: ParserMetadata,
+    /// <https://html.spec.whatwg.org/multipage/#concept-settings-object-csp-list>
+    /// of the request's client
+    pub csp_list: Option<CspList>,
+    /// <https://fetch.spec.whatwg.org/#concept-request-nonce-metadata>
+    pub cryptographic_nonce_metadata: String,
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.2>
+    ///
+    /// Requests that don't say on whose behalf they're made are cross-site, so
+    /// they never carry or set `SameSite` cookies.
+    pub site_for_cookies: SiteForCookies,
 }
 
 impl Request {
@@ -420,6 +464,9 @@ impl Request {
             parser_metadata: ParserMetadata::Default,
             redirect_count: 0,
             response_tainting: ResponseTainting::Basic,
+            csp_list: None,
+            cryptographic_nonce_metadata: String::new(),
+            site_for_cookies: SiteForCookies::Document(None),
         }
     }
 
@@ -4,6 +4,7 @@
 
 //! The [Response](https://fetch.spec.whatwg.org/#responses) object
 //! resulting from a [fetch operation](https://fetch.spec.whatwg.org/#concept-fetch)
+use crate::csp::CspList;
 use crate::{FetchMetadata, FilteredMetadata, Metadata, NetworkError, ReferrerPolicy};
 use crate::{ResourceFetchTiming, ResourceTimingType};
 use headers::{AccessControlExposeHeaders, ContentType, HeaderMapExt};
@@ -115,6 +116,8 @@ pub struct Response {
     pub aborted: Arc<AtomicBool>,
     /// track network metrics
     pub resource_timing: ResourceFetchTiming,
+    /// [CSP list](https://fetch.spec.whatwg.org/#concept-response-csp-list)
+    pub csp_list: Option<CspList>,
 }
 
 impl Response {
@@ -138,6 +141,7 @@ impl Response {
             return_internal: true,
             aborted: Arc::new(AtomicBool::new(false)),
             resource_timing: resource_timing,
+            csp_list: None,
         }
     }
 
@@ -172,6 +176,7 @@ impl Response {
             return_internal: true,
             aborted: Arc::new(AtomicBool::new(false)),
             resource_timing: ResourceFetchTiming::new(ResourceTimingType::Error),
+            csp_list: None,
         }
     }
 
@@ -309,6 +314,7 @@ impl Response {
             metadata.https_state = response.https_state;
             metadata.referrer = response.referrer.clone();
             metadata.referrer_policy = response.referrer_policy.clone();
+            metadata.csp_list = response.csp_list.clone();
             metadata
         };
 
@@ -0,0 +1,225 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use http::header::HeaderValue;
+use http::HeaderMap;
+use net_traits::csp::{CheckResult, CspList, InlineCheckType, NavigationCheckType};
+use net_traits::csp::{PolicyDisposition, PolicySource, ViolationResource};
+use net_traits::request::{Destination, Origin, Request};
+use servo_url::ServoUrl;
+
+fn enforced(serialized: &str) -> CspList {
+    CspList::parse(serialized, PolicySource::Header, PolicyDisposition::Enforce)
+}
+
+fn image_request(url: &str, origin: &str) -> Request {
+    let origin = ServoUrl::parse(origin).unwrap().origin();
+    let mut request = Request::new(
+        ServoUrl::parse(url).unwrap(),
+        Some(Origin::Origin(origin)),
+        None,
+    );
+    request.destination = Destination::Image;
+    request
+}
+
+#[test]
+fn test_parse_policy() {
+    let list = enforced("Script-Src 'self' https://example.com;; img-src *; script-src 'none'");
+    assert_eq!(list.0.len(), 1);
+    let policy = &list.0[0];
+    assert_eq!(policy.directives.len(), 2);
+    assert_eq!(policy.directives[0].name, "script-src");
+    assert_eq!(
+        policy.directives[0].value,
+        vec!["'self'".to_owned(), "https://example.com".to_owned()]
+    );
+    assert_eq!(
+        policy.serialize(),
+        "script-src 'self' https://example.com; img-src *"
+    );
+}
+
+#[test]
+fn test_parse_multiple_policies() {
+    let list = enforced("default-src 'self', img-src *, ");
+    assert_eq!(list.0.len(), 2);
+}
+
+#[test]
+fn test_meta_policy_ignores_header_only_directives() {
+    let list = CspList::parse(
+        "frame-ancestors 'none'; sandbox; report-uri /csp; img-src 'self'",
+        PolicySource::Meta,
+        PolicyDisposition::Enforce,
+    );
+    assert_eq!(list.0[0].serialize(), "img-src 'self'");
+}
+
+#[test]
+fn test_from_headers() {
+    let mut headers = HeaderMap::new();
+    assert!(CspList::from_headers(&headers).is_none());
+
+    headers.append(
+        "content-security-policy",
+        HeaderValue::from_static("img-src 'self'"),
+    );
+    headers.append(
+        "content-security-policy-report-only",
+        HeaderValue::from_static("script-src 'none'"),
+    );
+    let list = CspList::from_headers(&headers).unwrap();
+    assert_eq!(list.0.len(), 2);
+    assert_eq!(list.0[0].disposition, PolicyDisposition::Enforce);
+    assert_eq!(list.0[1].disposition, PolicyDisposition::Report);
+}
+
+#[test]
+fn test_request_matching_self() {
+    let list = enforced("img-src 'self'");
+    let same_origin = image_request("https://example.com/a.png", "https://example.com/");
+    let (result, violations) = list.should_request_be_blocked(&same_origin);
+    assert_eq!(result, CheckResult::Allowed);
+    assert!(violations.is_empty());
+
+    let cross_origin = image_request("https://other.com/a.png", "https://example.com/");
+    let (result, violations) = list.should_request_be_blocked(&cross_origin);
+    assert_eq!(result, CheckResult::Blocked);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].directive, "img-src");
+    assert_eq!(
+        violations[0].resource.blocked_uri(),
+        "https://other.com/a.png"
+    );
+}
+
+#[test]
+fn test_request_matching_host_and_scheme_sources() {
+    let list = enforced("default-src https://*.example.com:443/images/ data:");
+    let origin = "https://site.org/";
+    let allowed = [
+        "https://cdn.example.com/images/a.png",
+        "data:image/png;base64,AAAA",
+    ];
+    for url in allowed.iter() {
+        let (result, _) = list.should_request_be_blocked(&image_request(url, origin));
+        assert_eq!(result, CheckResult::Allowed, "{}", url);
+    }
+    let blocked = [
+        "https://example.com/images/a.png",
+        "http://cdn.example.com/images/a.png",
+        "https://cdn.example.com/scripts/a.js",
+    ];
+    for url in blocked.iter() {
+        let (result, _) = list.should_request_be_blocked(&image_request(url, origin));
+        assert_eq!(result, CheckResult::Blocked, "{}", url);
+    }
+}
+
+#[test]
+fn test_request_without_governing_directive() {
+    let list = enforced("script-src 'none'");
+    let request = image_request("https://other.com/a.png", "https://example.com/");
+    let (result, violations) = list.should_request_be_blocked(&request);
+    assert_eq!(result, CheckResult::Allowed);
+    assert!(violations.is_empty());
+}
+
+#[test]
+fn test_inline_script_nonce_and_hash() {
+    let list =
+        enforced("script-src 'nonce-abc123' 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='");
+    let (result, _) = list.should_inline_be_blocked(InlineCheckType::Script, "abc123", "foo()");
+    assert_eq!(result, CheckResult::Allowed);
+    let (result, _) = list.should_inline_be_blocked(InlineCheckType::Script, "", "alert(1)");
+    assert_eq!(result, CheckResult::Allowed);
+    let (result, violations) =
+        list.should_inline_be_blocked(InlineCheckType::Script, "wrong", "alert(2)");
+    assert_eq!(result, CheckResult::Blocked);
+    assert_eq!(violations[0].resource, ViolationResource::Inline);
+    assert_eq!(violations[0].directive, "script-src-elem");
+
+    // Nonces never apply to event handler attributes.
+    let (result, _) =
+        list.should_inline_be_blocked(InlineCheckType::ScriptAttribute, "abc123", "foo()");
+    assert_eq!(result, CheckResult::Blocked);
+}
+
+#[test]
+fn test_non_ascii_nonce_prefix() {
+    // The multi-byte character straddles the end of the `'nonce-` prefix.
+    let list = enforced("script-src 'nonce\u{e9}-x'");
+    let (result, _) = list.should_inline_be_blocked(InlineCheckType::Script, "x", "foo()");
+    assert_eq!(result, CheckResult::Blocked);
+    let list = enforced("script-src 'unsafe-inline' 'nonce\u{e9}-x'");
+    let (result, _) = list.should_inline_be_blocked(InlineCheckType::Script, "", "foo()");
+    assert_eq!(result, CheckResult::Allowed);
+}
+
+#[test]
+fn test_inline_style_unsafe_inline() {
+    let list = enforced("style-src 'unsafe-inline'");
+    let (result, _) = list.should_inline_be_blocked(InlineCheckType::Style, "", "p {}");
+    assert_eq!(result, CheckResult::Allowed);
+    let (result, _) =
+        list.should_inline_be_blocked(InlineCheckType::StyleAttribute, "", "color: red");
+    assert_eq!(result, CheckResult::Allowed);
+}
+
+#[test]
+fn test_report_sample() {
+    let list = enforced("script-src 'report-sample'");
+    let (_, violations) =
+        list.should_inline_be_blocked(InlineCheckType::Script, "", "console.log('hello')");
+    assert_eq!(violations[0].sample, "console.log('hello')");
+}
+
+#[test]
+fn test_js_evaluation() {
+    let (result, violations) = enforced("script-src 'self'").is_js_evaluation_allowed();
+    assert_eq!(result, CheckResult::Blocked);
+    assert_eq!(violations[0].resource, ViolationResource::Eval);
+
+    let (result, _) = enforced("script-src 'self' 'unsafe-eval'").is_js_evaluation_allowed();
+    assert_eq!(result, CheckResult::Allowed);
+
+    let (result, _) = enforced("img-src 'none'").is_js_evaluation_allowed();
+    assert_eq!(result, CheckResult::Allowed);
+}
+
+#[test]
+fn test_report_only_disposition() {
+    let list = CspList::parse(
+        "script-src 'none'",
+        PolicySource::Header,
+        PolicyDisposition::Report,
+    );
+    let (result, violations) = list.is_js_evaluation_allowed();
+    assert_eq!(result, CheckResult::Allowed);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].disposition, PolicyDisposition::Report);
+}
+
+#[test]
+fn test_navigation_checks() {
+    let list = enforced("form-action 'self'; frame-src https://frames.example.com");
+    let origin = ServoUrl::parse("https://example.com/").unwrap().origin();
+    let url = ServoUrl::parse("https://evil.com/submit").unwrap();
+    let (result, _) = list.should_navigation_request_be_blocked(
+        &url,
+        &origin,
+        NavigationCheckType::FormSubmission,
+    );
+    assert_eq!(result, CheckResult::Blocked);
+
+    let url = ServoUrl::parse("https://frames.example.com/embed").unwrap();
+    let (result, _) =
+        list.should_navigation_request_be_blocked(&url, &origin, NavigationCheckType::Frame);
+    assert_eq!(result, CheckResult::Allowed);
+
+    let (result, _) =
+        list.should_navigation_request_be_blocked(&url, &origin, NavigationCheckType::Other);
+    assert_eq!(result, CheckResult::Allowed);
+}
@@ -2,7 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
-use net_traits::image::base::detect_image_format;
+use net_traits::image::base::{detect_image_format, load_from_memory};
+use std::time::Duration;
 
 #[test]
 fn test_supported_images() {
@@ -12,6 +13,10 @@ fn test_supported_images() {
     let png = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A];
     let bmp = [0x42, 0x4D];
     let ico = [0x00, 0x00, 0x01, 0x00];
+    let webp = b"RIFF\x1a\x00\x00\x00WEBPVP8L";
+    let svg = b"<svg xmlns='http://www.w3.org/2000/svg'></svg>";
+    let svg_with_prolog = b"<?xml version='1.0'?>\n<!-- icon -->\n<svg/>";
+    let html = b"<html><svg></svg></html>";
     let junk_format = [0x01, 0x02, 0x03, 0x04, 0x05];
 
     assert!(detect_image_format(&gif1).is_ok());
@@ -20,5 +25,131 @@ fn test_supported_images() {
     assert!(detect_image_format(&png).is_ok());
     assert!(detect_image_format(&bmp).is_ok());
     assert!(detect_image_format(&ico).is_ok());
+    assert!(detect_image_format(webp).is_ok());
+    assert!(detect_image_format(b"RIFF\x1a\x00\x00\x00WAVE").is_err());
+    assert!(detect_image_format(svg).is_ok());
+    assert!(detect_image_format(svg_with_prolog).is_ok());
+    assert!(detect_image_format(html).is_err());
     assert!(detect_image_format(&junk_format).is_err());
 }
+
+/// A 1x1 GIF with a red and a blue frame, shown for 100ms and 200ms.
+const ANIMATED_GIF: &[u8] = &[
+    b'G', b'I', b'F', b'8', b'9', b'a', 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, // Header
+    0xff, 0x00, 0x00, 0x00, 0x00, 0xff, // Global color table
+    0x21, 0xf9, 0x04, 0x00, 0x0a, 0x00, 0x00, 0x00, // Graphic control extension
+    0x2c, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, // Image descriptor
+    0x02, 0x02, 0x44, 0x01, 0x00, // Image data
+    0x21, 0xf9, 0x04, 0x00, 0x14, 0x00, 0x00, 0x00, // Graphic control extension
+    0x2c, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, // Image descriptor
+    0x02, 0x02, 0x4c, 0x01, 0x00, // Image data
+    0x3b, // Trailer
+];
+
+#[test]
+fn test_animated_gif() {
+    let image = load_from_memory(ANIMATED_GIF, 1.).unwrap();
+    assert!(image.is_animated());
+    assert_eq!((image.width, image.height), (1, 1));
+    assert_eq!(image.frames.len(), 2);
+    assert_eq!(image.frames[0].delay, Some(Duration::from_millis(100)));
+    assert_eq!(image.frames[1].delay, Some(Duration::from_millis(200)));
+    assert_eq!(image.first_frame_bytes(), &[0x00, 0x00, 0xff, 0xff]);
+    assert_eq!(image.frame_bytes(1), &[0xff, 0x00, 0x00, 0xff]);
+}
+
+#[test]
+fn test_still_gif() {
+    // Only the first frame, followed by the trailer.
+    let mut gif = ANIMATED_GIF[..42].to_vec();
+    gif.push(0x3b);
+    let image = load_from_memory(&gif, 1.).unwrap();
+    assert!(!image.is_animated());
+    assert_eq!(image.frames[0].delay, None);
+    assert_eq!(&*image.bytes, &[0x00, 0x00, 0xff, 0xff]);
+}
+
+#[test]
+fn test_lossless_webp() {
+    // A 2x2 image with red, green, blue and translucent white pixels.
+    let webp = [
+        b'R', b'I', b'F', b'F', 0x1c, 0x00, 0x00, 0x00, b'W', b'E', b'B', b'P', // Header
+        b'V', b'P', b'8', b'L', 0x0f, 0x00, 0x00, 0x00, 0x2f, 0x01, 0x40, 0x00, 0x10, 0x98, 0xff,
+        0xf9, 0x9f, 0xff, 0x03, 0xfe, 0x87, 0x26, 0x1f, 0x00, // Lossless bitstream
+    ];
+    let image = load_from_memory(&webp, 1.).unwrap();
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(
+        &*image.bytes,
+        &[
+            0x00, 0x00, 0xff, 0xff, 0x00, 0xff, 0x00, 0xff, 0xff, 0x00, 0x00, 0xff, 0xff, 0xff,
+            0xff, 0x80,
+        ][..]
+    );
+}
+
+#[test]
+fn test_lossy_webp_with_alpha() {
+    // A 2x2 orange image whose pixels are opaque, half transparent,
+    // transparent and opaque.
+    let webp = [
+        b'R', b'I', b'F', b'F', 0x62, 0x00, 0x00, 0x00, b'W', b'E', b'B', b'P', // Header
+        b'V', b'P', b'8', b'X', 0x0a, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        0x01, 0x00, 0x00, // Extended header
+        b'A', b'L', b'P', b'H', 0x05, 0x00, 0x00, 0x00, 0x00, 0xff, 0x80, 0x00, 0xff,
+        0x00, // Uncompressed alpha
+        b'V', b'P', b'8', b' ', 0x36, 0x00, 0x00, 0x00, 0x50, 0x02, 0x00, 0x9d, 0x01, 0x2a, 0x02,
+        0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x25, 0xa0, 0x02, 0x74, 0xba, 0x01, 0xf8, 0x01, 0xfa,
+        0x01, 0xfc, 0x00, 0x07, 0x90, 0x00, 0xfe, 0xed, 0xce, 0xdf, 0xff, 0x92, 0x0f, 0x5e, 0x4f,
+        0x3f, 0x3f, 0xf2, 0x42, 0x3f, 0xfe, 0xca, 0x53, 0x65, 0xfe, 0x26, 0x3f, 0xfb, 0x23, 0x00,
+        0x00, 0x00, // Lossy bitstream
+    ];
+    let image = load_from_memory(&webp, 1.).unwrap();
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(
+        &*image.bytes,
+        &[50, 101, 200, 255, 50, 101, 200, 128, 50, 101, 200, 0, 50, 101, 200, 255][..]
+    );
+}
+
+#[test]
+fn test_animated_webp() {
+    // A 2x2 animation of the lossless image shown for 100ms, then replaced
+    // by the lossy image with alpha for 50ms.
+    let webp = [
+        b'R', b'I', b'F', b'F', 0xb8, 0x00, 0x00, 0x00, b'W', b'E', b'B', b'P', // Header
+        b'V', b'P', b'8', b'X', 0x0a, 0x00, 0x00, 0x00, 0x12, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+        0x01, 0x00, 0x00, // Extended header
+        b'A', b'N', b'I', b'M', 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, // Animation parameters
+        b'A', b'N', b'M', b'F', 0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x01, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, // First frame
+        b'V', b'P', b'8', b'L', 0x10, 0x00, 0x00, 0x00, 0x2f, 0x01, 0x40, 0x00, 0x10, 0x98, 0xff,
+        0xf9, 0x9f, 0xff, 0x03, 0xfe, 0x87, 0x26, 0x1f, 0x00, // Lossless bitstream
+        b'A', b'N', b'M', b'F', 0x5c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+        0x00, 0x00, 0x01, 0x00, 0x00, 0x32, 0x00, 0x00, 0x02, // Second frame, not blended
+        b'A', b'L', b'P', b'H', 0x05, 0x00, 0x00, 0x00, 0x00, 0xff, 0x80, 0x00, 0xff,
+        0x00, // Uncompressed alpha
+        b'V', b'P', b'8', b' ', 0x36, 0x00, 0x00, 0x00, 0x50, 0x02, 0x00, 0x9d, 0x01, 0x2a, 0x02,
+        0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x25, 0xa0, 0x02, 0x74, 0xba, 0x01, 0xf8, 0x01, 0xfa,
+        0x01, 0xfc, 0x00, 0x07, 0x90, 0x00, 0xfe, 0xed, 0xce, 0xdf, 0xff, 0x92, 0x0f, 0x5e, 0x4f,
+        0x3f, 0x3f, 0xf2, 0x42, 0x3f, 0xfe, 0xca, 0x53, 0x65, 0xfe, 0x26, 0x3f, 0xfb, 0x23, 0x00,
+        0x00, 0x00, // Lossy bitstream
+    ];
+    let image = load_from_memory(&webp, 1.).unwrap();
+    assert!(image.is_animated());
+    assert_eq!((image.width, image.height), (2, 2));
+    assert_eq!(image.frames[0].delay, Some(Duration::from_millis(100)));
+    assert_eq!(image.frames[1].delay, Some(Duration::from_millis(50)));
+    assert_eq!(
+        image.first_frame_bytes(),
+        &[
+            0x00, 0x00, 0xff, 0xff, 0x00, 0xff, 0x00, 0xff, 0xff, 0x00, 0x00, 0xff, 0xff, 0xff,
+            0xff, 0x80,
+        ][..]
+    );
+    assert_eq!(
+        image.frame_bytes(1),
+        &[50, 101, 200, 255, 50, 101, 200, 128, 50, 101, 200, 0, 50, 101, 200, 255][..]
+    );
+}
@@ -79,6 +79,19 @@ pub fn rgba8_premultiply_inplace(pixels: &mut [u8]) -> bool {
     is_opaque
 }
 
+pub fn rgba8_unpremultiply_inplace(pixels: &mut [u8]) {
+    assert!(pixels.len() % 4 == 0);
+    for rgba in pixels.chunks_mut(4) {
+        let alpha = rgba[3] as u32;
+        if alpha == 0 || alpha == 255 {
+            continue;
+        }
+        for component in &mut rgba[0..3] {
+            *component = ((*component as u32 * 255 + alpha / 2) / alpha).min(255) as u8;
+        }
+    }
+}
+
 pub fn multiply_u8_color(a: u8, b: u8) -> u8 {
     return (a as u32 * b as u32 / 255) as u8;
 }
@@ -0,0 +1,192 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A module for writing time profiler traces out in the Chrome Trace Event JSON format, which
+//! can be loaded into `chrome://tracing` or the Perfetto UI.
+//!
+//! See https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU/
+
+use crate::time::category_name;
+use profile_traits::time::{ProfilerCategory, ProfilerThread, TimerMetadata};
+use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path;
+use std::process;
+
+/// An RAII class for writing the Chrome trace file.
+#[derive(Debug)]
+pub struct ChromeTrace {
+    file: BufWriter<fs::File>,
+    /// The id of the process the profiler runs in, which owns the counter tracks.
+    process_id: u32,
+    /// The processes for which a name has been written out.
+    processes: HashSet<u32>,
+    /// The threads, by process and thread id, for which a name has been written out.
+    threads: HashSet<(u32, u64)>,
+    /// Whether an event has been written yet, to know when a separator is needed.
+    has_events: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct TraceEvent<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cat: Option<&'static str>,
+    ph: &'static str,
+    ts: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dur: Option<f64>,
+    pid: u32,
+    tid: u64,
+    #[serde(skip_serializing_if = "Value::is_null")]
+    args: Value,
+}
+
+impl ChromeTrace {
+    /// Create a new ChromeTrace and write the opening of the JSON document out to disk.
+    pub fn new<P>(trace_file_path: P) -> io::Result<ChromeTrace>
+    where
+        P: AsRef<path::Path>,
+    {
+        let mut file = BufWriter::new(fs::File::create(trace_file_path)?);
+        writeln!(file, "{{\"displayTimeUnit\":\"ms\",\"traceEvents\":[")?;
+        Ok(ChromeTrace {
+            file: file,
+            process_id: process::id(),
+            processes: HashSet::new(),
+            threads: HashSet::new(),
+            has_events: false,
+        })
+    }
+
+    /// Write one timing as a complete event on the track of the thread it was recorded on.
+    /// Timings of nested categories on the same thread show up as nested spans.
+    pub fn write_one(
+        &mut self,
+        category: &(ProfilerCategory, Option<TimerMetadata>),
+        time: (u64, u64),
+        thread: &ProfilerThread,
+    ) {
+        self.thread_name(thread);
+        let args = match category.1 {
+            Some(ref meta) => json!({
+                "url": meta.url,
+                "iframe": meta.iframe == TimerMetadataFrameType::IFrame,
+                "incremental": meta.incremental == TimerMetadataReflowType::Incremental,
+            }),
+            None => Value::Null,
+        };
+        self.write_event(&TraceEvent {
+            name: category_name(&category.0),
+            cat: Some(category_group(category.0)),
+            ph: "X",
+            ts: to_microseconds(time.0),
+            dur: Some(to_microseconds(time.1.saturating_sub(time.0))),
+            pid: thread.process_id,
+            tid: thread.thread_id,
+            args: args,
+        });
+    }
+
+    /// Write a sample of a counter, where each value is drawn as a series of the counter track.
+    pub fn write_counter(&mut self, name: &str, time: u64, values: &[(String, u64)]) {
+        let pid = self.process_id;
+        self.process_name(pid);
+        let args = values
+            .iter()
+            .map(|&(ref series, value)| (series.clone(), Value::from(value)))
+            .collect();
+        self.write_event(&TraceEvent {
+            name: name,
+            cat: Some("memory"),
+            ph: "C",
+            ts: to_microseconds(time),
+            dur: None,
+            pid: pid,
+            tid: 0,
+            args: Value::Object(args),
+        });
+    }
+
+    /// Name the track of a thread the first time it is seen.
+    fn thread_name(&mut self, thread: &ProfilerThread) {
+        if !self.threads.insert((thread.process_id, thread.thread_id)) {
+            return;
+        }
+        self.process_name(thread.process_id);
+        self.write_event(&TraceEvent {
+            name: "thread_name",
+            cat: None,
+            ph: "M",
+            ts: 0.,
+            dur: None,
+            pid: thread.process_id,
+            tid: thread.thread_id,
+            args: json!({ "name": thread.name }),
+        });
+    }
+
+    /// Name the track of a process the first time it is seen.
+    fn process_name(&mut self, pid: u32) {
+        if !self.processes.insert(pid) {
+            return;
+        }
+        let name = if pid == self.process_id {
+            "Servo"
+        } else {
+            "Servo content process"
+        };
+        self.write_event(&TraceEvent {
+            name: "process_name",
+            cat: None,
+            ph: "M",
+            ts: 0.,
+            dur: None,
+            pid: pid,
+            tid: 0,
+            args: json!({ "name": name }),
+        });
+    }
+
+    fn write_event(&mut self, event: &TraceEvent) {
+        if self.has_events {
+            writeln!(&mut self.file, ",").unwrap();
+        }
+        serde_json::to_writer(&mut self.file, event).unwrap();
+        self.has_events = true;
+    }
+}
+
+impl Drop for ChromeTrace {
+    /// Close the JSON document and flush it out to disk on destruction.
+    fn drop(&mut self) {
+        writeln!(&mut self.file, "\n]}}").unwrap();
+        self.file.flush().unwrap();
+    }
+}
+
+/// The group of related categories a category belongs to, mostly following the numbering of
+/// `ProfilerCategory`.
+fn category_group(category: ProfilerCategory) -> &'static str {
+    match category {
+        ProfilerCategory::IpcReceiver | ProfilerCategory::IpcBytesReceiver => "ipc",
+        ProfilerCategory::ApplicationHeartbeat => "heartbeat",
+        _ => match (category as u32) >> 4 {
+            0x0 => "compositing",
+            0x1 => "layout",
+            0x3 => "network",
+            0x4 => "painting",
+            0x5 => "image",
+            0x6 | 0x7 => "script",
+            _ => "metrics",
+        },
+    }
+}
+
+fn to_microseconds(ns: u64) -> f64 {
+    ns as f64 / 1000.
+}
@@ -9,11 +9,13 @@ use ipc_channel::ipc::{self, IpcReceiver};
 use ipc_channel::router::ROUTER;
 use profile_traits::mem::ReportsChan;
 use profile_traits::mem::{ProfilerChan, ProfilerMsg, ReportKind, Reporter, ReporterRequest};
+use profile_traits::time;
 use std::borrow::ToOwned;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::thread;
 use std::time::Instant;
+use time_crate::precise_time_ns;
 
 pub struct Profiler {
     /// The port through which messages are received.
@@ -24,13 +26,16 @@ pub struct Profiler {
 
     /// Instant at which this profiler was created.
     created: Instant,
+
+    /// The time profiler, which records the totals of each report as counters.
+    time_profiler_chan: time::ProfilerChan,
 }
 
 const JEMALLOC_HEAP_ALLOCATED_STR: &'static str = "jemalloc-heap-allocated";
 const SYSTEM_HEAP_ALLOCATED_STR: &'static str = "system-heap-allocated";
 
 impl Profiler {
-    pub fn create(period: Option<f64>) -> ProfilerChan {
+    pub fn create(period: Option<f64>, time_profiler_chan: time::ProfilerChan) -> ProfilerChan {
         let (chan, port) = ipc::channel().unwrap();
 
         // Create the timer thread if a period was provided.
@@ -52,7 +57,7 @@ impl Profiler {
         thread::Builder::new()
             .name("Memory profiler".to_owned())
             .spawn(move || {
-                let mut mem_profiler = Profiler::new(port);
+                let mut mem_profiler = Profiler::new(port, time_profiler_chan);
                 mem_profiler.start();
             })
             .expect("Thread spawning failed");
@@ -78,11 +83,12 @@ impl Profiler {
         mem_profiler_chan
     }
 
-    pub fn new(port: IpcReceiver<ProfilerMsg>) -> Profiler {
+    pub fn new(port: IpcReceiver<ProfilerMsg>, time_profiler_chan: time::ProfilerChan) -> Profiler {
         Profiler {
             port: port,
             reporters: HashMap::new(),
             created: Instant::now(),
+            time_profiler_chan: time_profiler_chan,
         }
     }
 
@@ -209,6 +215,12 @@ impl Profiler {
 
         forest.print();
 
+        self.time_profiler_chan.send(time::ProfilerMsg::Counter(
+            "Memory".to_owned(),
+            precise_time_ns(),
+            forest.totals(),
+        ));
+
         println!("|");
         println!("End memory reports");
         println!("");
@@ -349,6 +361,14 @@ impl ReportsForest {
         t.insert(tail, size);
     }
 
+    // The size of each tree, keyed by its root's path segment. Only valid after `print`.
+    fn totals(&self) -> Vec<(String, u64)> {
+        self.trees
+            .iter()
+            .map(|(name, tree)| (name.clone(), tree.size as u64))
+            .collect()
+    }
+
     fn print(&mut self) {
         // Fill in sizes of interior nodes, and recursively sort the sub-trees.
         for (_, tree) in &mut self.trees {
@@ -4,6 +4,7 @@
 
 //! Timing functions.
 
+use crate::chrome_trace::ChromeTrace;
 use crate::heartbeats;
 use crate::trace_dump::TraceDump;
 use influent::client::{Client, Credentials};
@@ -12,7 +13,7 @@ use influent::measurement::{Measurement, Value};
 use ipc_channel::ipc::{self, IpcReceiver};
 use profile_traits::energy::{energy_interval_ms, read_energy_uj};
 use profile_traits::time::{
-    ProfilerCategory, ProfilerChan, ProfilerData, ProfilerMsg, TimerMetadata,
+    ProfilerCategory, ProfilerChan, ProfilerData, ProfilerMsg, ProfilerThread, TimerMetadata,
 };
 use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
 use servo_config::opts::OutputOptions;
@@ -99,78 +100,107 @@ impl Formattable for ProfilerCategory {
             ProfilerCategory::LayoutTextShaping => "| + ",
             _ => "",
         };
-        let name = match *self {
-            ProfilerCategory::Compositing => "Compositing",
-            ProfilerCategory::LayoutPerform => "Layout",
-            ProfilerCategory::LayoutStyleRecalc => "Style Recalc",
-            ProfilerCategory::LayoutTextShaping => "Text Shaping",
-            ProfilerCategory::LayoutRestyleDamagePropagation => "Restyle Damage Propagation",
-            ProfilerCategory::LayoutNonIncrementalReset => "Non-incremental reset (temporary)",
-            ProfilerCategory::LayoutSelectorMatch => "Selector Matching",
-            ProfilerCategory::LayoutTreeBuilder => "Tree Building",
-            ProfilerCategory::LayoutDamagePropagate => "Damage Propagation",
-            ProfilerCategory::LayoutDisplayListSorting => "Sorting Display List",
-            ProfilerCategory::LayoutGeneratedContent => "Generated Content Resolution",
-            ProfilerCategory::LayoutFloatPlacementSpeculation => "Float Placement Speculation",
-            ProfilerCategory::LayoutMain => "Primary Layout Pass",
-            ProfilerCategory::LayoutStoreOverflow => "Store Overflow",
-            ProfilerCategory::LayoutParallelWarmup => "Parallel Warmup",
-            ProfilerCategory::LayoutDispListBuild => "Display List Construction",
-            ProfilerCategory::NetHTTPRequestResponse => "Network HTTP Request/Response",
-            ProfilerCategory::PaintingPerTile => "Painting Per Tile",
-            ProfilerCategory::PaintingPrepBuff => "Buffer Prep",
-            ProfilerCategory::Painting => "Painting",
-            ProfilerCategory::ImageDecoding => "Image Decoding",
-            ProfilerCategory::ImageSaving => "Image Saving",
-            ProfilerCategory::ScriptAttachLayout => "Script Attach Layout",
-            ProfilerCategory::ScriptConstellationMsg => "Script Constellation Msg",
-            ProfilerCategory::ScriptDevtoolsMsg => "Script Devtools Msg",
-            ProfilerCategory::ScriptDocumentEvent => "Script Document Event",
-            ProfilerCategory::ScriptDomEvent => "Script Dom Event",
-            ProfilerCategory::ScriptEvaluate => "Script JS Evaluate",
-            ProfilerCategory::ScriptFileRead => "Script File Read",
-            ProfilerCategory::ScriptHistoryEvent => "Script History Event",
-            ProfilerCategory::ScriptImageCacheMsg => "Script Image Cache Msg",
-            ProfilerCategory::ScriptInputEvent => "Script Input Event",
-            ProfilerCategory::ScriptNetworkEvent => "Script Network Event",
-            ProfilerCategory::ScriptParseHTML => "Script Parse HTML",
-            ProfilerCategory::ScriptParseXML => "Script Parse XML",
-            ProfilerCategory::ScriptPlannedNavigation => "Script Planned Navigation",
-            ProfilerCategory::ScriptResize => "Script Resize",
-            ProfilerCategory::ScriptEvent => "Script Event",
-            ProfilerCategory::ScriptUpdateReplacedElement => "Script Update Replaced Element",
-            ProfilerCategory::ScriptSetScrollState => "Script Set Scroll State",
-            ProfilerCategory::ScriptSetViewport => "Script Set Viewport",
-            ProfilerCategory::ScriptTimerEvent => "Script Timer Event",
-            ProfilerCategory::ScriptStylesheetLoad => "Script Stylesheet Load",
-            ProfilerCategory::ScriptWebSocketEvent => "Script Web Socket Event",
-            ProfilerCategory::ScriptWorkerEvent => "Script Worker Event",
-            ProfilerCategory::ScriptServiceWorkerEvent => "Script Service Worker Event",
-            ProfilerCategory::ScriptEnterFullscreen => "Script Enter Fullscreen",
-            ProfilerCategory::ScriptExitFullscreen => "Script Exit Fullscreen",
-            ProfilerCategory::ScriptWebVREvent => "Script WebVR Event",
-            ProfilerCategory::ScriptWorkletEvent => "Script Worklet Event",
-            ProfilerCategory::ScriptPerformanceEvent => "Script Performance Event",
-            ProfilerCategory::TimeToFirstPaint => "Time To First Paint",
-            ProfilerCategory::TimeToFirstContentfulPaint => "Time To First Contentful Paint",
-            ProfilerCategory::TimeToInteractive => "Time to Interactive",
-            ProfilerCategory::IpcReceiver => "Blocked at IPC Receive",
-            ProfilerCategory::IpcBytesReceiver => "Blocked at IPC Bytes Receive",
-            ProfilerCategory::ApplicationHeartbeat => "Application Heartbeat",
-        };
-        format!("{}{}", padding, name)
+        format!("{}{}", padding, category_name(self))
+    }
+}
+
+/// The human-readable name of a category.
+pub fn category_name(category: &ProfilerCategory) -> &'static str {
+    match *category {
+        ProfilerCategory::Compositing => "Compositing",
+        ProfilerCategory::LayoutPerform => "Layout",
+        ProfilerCategory::LayoutStyleRecalc => "Style Recalc",
+        ProfilerCategory::LayoutTextShaping => "Text Shaping",
+        ProfilerCategory::LayoutRestyleDamagePropagation => "Restyle Damage Propagation",
+        ProfilerCategory::LayoutNonIncrementalReset => "Non-incremental reset (temporary)",
+        ProfilerCategory::LayoutSelectorMatch => "Selector Matching",
+        ProfilerCategory::LayoutTreeBuilder => "Tree Building",
+        ProfilerCategory::LayoutDamagePropagate => "Damage Propagation",
+        ProfilerCategory::LayoutDisplayListSorting => "Sorting Display List",
+        ProfilerCategory::LayoutGeneratedContent => "Generated Content Resolution",
+        ProfilerCategory::LayoutFloatPlacementSpeculation => "Float Placement Speculation",
+        ProfilerCategory::LayoutMain => "Primary Layout Pass",
+        ProfilerCategory::LayoutStoreOverflow => "Store Overflow",
+        ProfilerCategory::LayoutParallelWarmup => "Parallel Warmup",
+        ProfilerCategory::LayoutDispListBuild => "Display List Construction",
+        ProfilerCategory::NetHTTPRequestResponse => "Network HTTP Request/Response",
+        ProfilerCategory::PaintingPerTile => "Painting Per Tile",
+        ProfilerCategory::PaintingPrepBuff => "Buffer Prep",
+        ProfilerCategory::Painting => "Painting",
+        ProfilerCategory::ImageDecoding => "Image Decoding",
+        ProfilerCategory::ImageSaving => "Image Saving",
+        ProfilerCategory::ScriptAttachLayout => "Script Attach Layout",
+        ProfilerCategory::ScriptConstellationMsg => "Script Constellation Msg",
+        ProfilerCategory::ScriptDevtoolsMsg => "Script Devtools Msg",
+        ProfilerCategory::ScriptDocumentEvent => "Script Document Event",
+        ProfilerCategory::ScriptDomEvent => "Script Dom Event",
+        ProfilerCategory::ScriptEvaluate => "Script JS Evaluate",
+        ProfilerCategory::ScriptFileRead => "Script File Read",
+        ProfilerCategory::ScriptHistoryEvent => "Script History Event",
+        ProfilerCategory::ScriptImageCacheMsg => "Script Image Cache Msg",
+        ProfilerCategory::ScriptInputEvent => "Script Input Event",
+        ProfilerCategory::ScriptNetworkEvent => "Script Network Event",
+        ProfilerCategory::ScriptParseHTML => "Script Parse HTML",
+        ProfilerCategory::ScriptParseXML => "Script Parse XML",
+        ProfilerCategory::ScriptPlannedNavigation => "Script Planned Navigation",
+        ProfilerCategory::ScriptResize => "Script Resize",
+        ProfilerCategory::ScriptEvent => "Script Event",
+        ProfilerCategory::ScriptUpdateReplacedElement => "Script Update Replaced Element",
+        ProfilerCategory::ScriptSetScrollState => "Script Set Scroll State",
+        ProfilerCategory::ScriptSetViewport => "Script Set Viewport",
+        ProfilerCategory::ScriptTimerEvent => "Script Timer Event",
+        ProfilerCategory::ScriptStylesheetLoad => "Script Stylesheet Load",
+        ProfilerCategory::ScriptWebSocketEvent => "Script Web Socket Event",
+        ProfilerCategory::ScriptWorkerEvent => "Script Worker Event",
+        ProfilerCategory::ScriptServiceWorkerEvent => "Script Service Worker Event",
+        ProfilerCategory::ScriptEnterFullscreen => "Script Enter Fullscreen",
+        ProfilerCategory::ScriptExitFullscreen => "Script Exit Fullscreen",
+        ProfilerCategory::ScriptWebVREvent => "Script WebVR Event",
+        ProfilerCategory::ScriptWorkletEvent => "Script Worklet Event",
+        ProfilerCategory::ScriptPerformanceEvent => "Script Performance Event",
+        ProfilerCategory::ScriptDatabaseAccessEvent => "Script Database Access Event",
+        ProfilerCategory::ScriptPortMessage => "Script Port Message",
+        ProfilerCategory::TimeToFirstPaint => "Time To First Paint",
+        ProfilerCategory::TimeToFirstContentfulPaint => "Time To First Contentful Paint",
+        ProfilerCategory::TimeToInteractive => "Time to Interactive",
+        ProfilerCategory::IpcReceiver => "Blocked at IPC Receive",
+        ProfilerCategory::IpcBytesReceiver => "Blocked at IPC Bytes Receive",
+        ProfilerCategory::ApplicationHeartbeat => "Application Heartbeat",
     }
 }
 
 type ProfilerBuckets = BTreeMap<(ProfilerCategory, Option<TimerMetadata>), Vec<f64>>;
 
+/// A timeline of every profiled event, written out as it is received.
+pub enum Trace {
+    /// A self-contained HTML file.
+    Html(TraceDump),
+    /// A Chrome Trace Event JSON file, which records the thread of each event and counters.
+    Chrome(ChromeTrace),
+}
+
+impl Trace {
+    /// Create the trace for the given path. Paths ending in `.json` get a Chrome Trace Event
+    /// file, any other path gets an HTML file.
+    pub fn new(path: &str) -> io::Result<Trace> {
+        if Path::new(path)
+            .extension()
+            .map_or(false, |ext| ext == "json")
+        {
+            ChromeTrace::new(path).map(Trace::Chrome)
+        } else {
+            TraceDump::new(path).map(Trace::Html)
+        }
+    }
+}
+
 // back end of the profiler that handles data aggregation and performance metrics
 pub struct Profiler {
     pub port: IpcReceiver<ProfilerMsg>,
     buckets: ProfilerBuckets,
     output: Option<OutputOptions>,
     pub last_msg: Option<ProfilerMsg>,
-    trace: Option<TraceDump>,
+    trace: Option<Trace>,
     blocked_layout_queries: HashMap<String, u32>,
     profile_heartbeats: bool,
 }
@@ -189,7 +219,7 @@ impl Profiler {
                 thread::Builder::new()
                     .name("Time profiler".to_owned())
                     .spawn(move || {
-                        let trace = file_path.as_ref().and_then(|p| TraceDump::new(p).ok());
+                        let trace = file_path.as_ref().and_then(|p| Trace::new(p).ok());
                         let mut profiler =
                             Profiler::new(port, trace, Some(outputoption), profile_heartbeats);
                         profiler.start();
@@ -222,7 +252,7 @@ impl Profiler {
                     thread::Builder::new()
                         .name("Time profiler".to_owned())
                         .spawn(move || {
-                            let trace = file_path.as_ref().and_then(|p| TraceDump::new(p).ok());
+                            let trace = file_path.as_ref().and_then(|p| Trace::new(p).ok());
                             let mut profiler = Profiler::new(port, trace, None, profile_heartbeats);
                             profiler.start();
                         })
@@ -292,6 +322,7 @@ impl Profiler {
                             (ProfilerCategory::ApplicationHeartbeat, None),
                             (start_time, end_time),
                             (start_energy, end_energy),
+                            ProfilerThread::current_if_traced(),
                         )) {
                             return;
                         }
@@ -307,7 +338,7 @@ impl Profiler {
 
     pub fn new(
         port: IpcReceiver<ProfilerMsg>,
-        trace: Option<TraceDump>,
+        trace: Option<Trace>,
         output: Option<OutputOptions>,
         profile_heartbeats: bool,
     ) -> Profiler {
@@ -336,14 +367,27 @@ impl Profiler {
 
     fn handle_msg(&mut self, msg: ProfilerMsg) -> bool {
         match msg.clone() {
-            ProfilerMsg::Time(k, t, e) => {
+            ProfilerMsg::Time(k, t, e, thread) => {
                 heartbeats::maybe_heartbeat(&k.0, t.0, t.1, e.0, e.1, self.profile_heartbeats);
-                if let Some(ref mut trace) = self.trace {
-                    trace.write_one(&k, t, e);
+                match self.trace {
+                    Some(Trace::Html(ref mut trace)) => trace.write_one(&k, t, e),
+                    Some(Trace::Chrome(ref mut trace)) => {
+                        if let Some(ref thread) = thread {
+                            trace.write_one(&k, t, thread);
+                        }
+                    },
+                    None => {},
                 }
                 let ms = (t.1 - t.0) as f64 / 1000000f64;
                 self.find_or_insert(k, ms);
             },
+            ProfilerMsg::Counter(name, time, values) => {
+                if let Some(Trace::Chrome(ref mut trace)) = self.trace {
+                    trace.write_counter(&name, time, &values);
+                }
+                // Counters don't count as new data for the periodic printout.
+                return true;
+            },
             ProfilerMsg::Print => {
                 if let Some(ProfilerMsg::Time(..)) = self.last_msg {
                     // only print if more data has arrived since the last printout
@@ -365,6 +409,8 @@ impl Profiler {
             ProfilerMsg::Exit(chan) => {
                 heartbeats::cleanup();
                 self.print_buckets();
+                // Finish writing the trace before acknowledging the exit.
+                self.trace = None;
                 let _ = chan.send(());
                 return false;
             },
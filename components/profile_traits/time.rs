@@ -5,6 +5,8 @@
 use crate::energy::read_energy_uj;
 use ipc_channel::ipc::IpcSender;
 use servo_config::opts;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::{process, thread};
 use time::precise_time_ns;
 
 #[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
@@ -14,6 +16,45 @@ pub struct TimerMetadata {
     pub incremental: TimerMetadataReflowType,
 }
 
+/// The process and thread a timing was recorded on, so that traces can be laid out per thread.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct ProfilerThread {
+    pub process_id: u32,
+    /// An id that is unique among the threads of the process, unlike the thread name.
+    pub thread_id: u64,
+    pub name: String,
+}
+
+static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local!(static CURRENT_THREAD: ProfilerThread = {
+    let thread = thread::current();
+    let thread_id = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+    ProfilerThread {
+        process_id: process::id(),
+        thread_id: thread_id,
+        name: match thread.name() {
+            Some(name) => name.to_owned(),
+            None => format!("Thread {}", thread_id),
+        },
+    }
+});
+
+impl ProfilerThread {
+    pub fn current() -> ProfilerThread {
+        CURRENT_THREAD.with(|thread| thread.clone())
+    }
+
+    /// The current thread, if timings are written out to a trace file that needs it.
+    pub fn current_if_traced() -> Option<ProfilerThread> {
+        if opts::get().time_profiler_trace_path.is_some() {
+            Some(ProfilerThread::current())
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ProfilerChan(pub IpcSender<ProfilerMsg>);
 
@@ -38,7 +79,10 @@ pub enum ProfilerMsg {
         (ProfilerCategory, Option<TimerMetadata>),
         (u64, u64),
         (u64, u64),
+        Option<ProfilerThread>,
     ),
+    /// Sampled values of a named counter (e.g. memory usage) at a given time, in nanoseconds
+    Counter(String, u64, Vec<(String, u64)>),
     /// Message used to get time spend entries for a particular ProfilerBuckets (in nanoseconds)
     Get(
         (ProfilerCategory, Option<TimerMetadata>),
@@ -108,6 +152,8 @@ pub enum ProfilerCategory {
     ScriptWorkletEvent = 0x7a,
     ScriptPerformanceEvent = 0x7b,
     ScriptHistoryEvent = 0x7c,
+    ScriptDatabaseAccessEvent = 0x7d,
+    ScriptPortMessage = 0x7e,
     TimeToFirstPaint = 0x80,
     TimeToFirstContentfulPaint = 0x81,
     TimeToInteractive = 0x82,
@@ -176,5 +222,6 @@ pub fn send_profile_data(
         (category, meta),
         (start_time, end_time),
         (start_energy, end_energy),
+        ProfilerThread::current_if_traced(),
     ));
 }
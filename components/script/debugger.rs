@@ -0,0 +1,260 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The JavaScript debugger of a script thread.
+//!
+//! `resources/debugger.js` runs in a global of its own where SpiderMonkey's `Debugger`
+//! API is available, and the windows being debugged are added to it as debuggees.
+//! When script pauses, the debugger calls back into the script thread, which only
+//! services devtools requests until the client resumes it.
+
+use crate::dom::bindings::conversions::{jsstring_to_str, ToJSValConvertible};
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::globalscope::GlobalScope;
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::script_thread::ScriptThread;
+use devtools_traits::{DebuggerRequest, FrameEvaluation, ResumeLimit};
+use embedder_traits::resources::{self, Resource};
+use js::error::throw_type_error;
+use js::jsapi::{CallArgs, HandleValueArray, Heap, InitRealmStandardClasses};
+use js::jsapi::{JSAutoRealm, JSContext, JSObject, JS_ClearPendingException};
+use js::jsapi::{JS_IsExceptionPending, JS_NewGlobalObject, OnNewGlobalHookOption};
+use js::jsval::{JSVal, ObjectValue, UndefinedValue};
+use js::rust::wrappers::{EvaluateUtf8, JS_CallFunctionName, JS_DefineDebuggerObject};
+use js::rust::wrappers::{JS_DefineFunction, JS_WrapObject};
+use js::rust::{CompileOptionsWrapper, HandleValue, MutableHandleValue};
+use js::rust::{RealmOptions, SIMPLE_GLOBAL_CLASS};
+use msg::constellation_msg::PipelineId;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::ffi::CString;
+use std::ptr;
+
+/// The debugger of a script thread, shared by all the windows it debugs.
+#[derive(JSTraceable)]
+pub struct ScriptDebugger {
+    /// The global in which `debugger.js` runs.
+    global: Box<Heap<*mut JSObject>>,
+}
+
+impl ScriptDebugger {
+    #[allow(unsafe_code)]
+    pub fn new(cx: SafeJSContext) -> ScriptDebugger {
+        let global = Box::new(Heap::default());
+        unsafe {
+            let options = RealmOptions::default();
+            global.set(JS_NewGlobalObject(
+                *cx,
+                &SIMPLE_GLOBAL_CLASS,
+                ptr::null_mut(),
+                OnNewGlobalHookOption::DontFireOnNewGlobalHook,
+                &*options,
+            ));
+            assert!(!global.get().is_null());
+
+            let _ac = JSAutoRealm::new(*cx, global.get());
+            rooted!(in(*cx) let global_object = global.get());
+            assert!(InitRealmStandardClasses(*cx));
+            assert!(JS_DefineDebuggerObject(*cx, global_object.handle()));
+            let name = CString::new("servoPause").unwrap();
+            assert!(!JS_DefineFunction(
+                *cx,
+                global_object.handle(),
+                name.as_ptr(),
+                Some(servo_pause),
+                2,
+                0,
+            )
+            .is_null());
+
+            let source = resources::read_string(Resource::DebuggerJS);
+            let filename = CString::new("debugger.js").unwrap();
+            let options = CompileOptionsWrapper::new(*cx, filename.as_ptr(), 1);
+            rooted!(in(*cx) let mut rval = UndefinedValue());
+            if !EvaluateUtf8(
+                *cx,
+                options.ptr,
+                source.as_ptr() as *const _,
+                source.len() as libc::size_t,
+                rval.handle_mut(),
+            ) {
+                clear_pending_exception(*cx, "evaluating debugger.js");
+            }
+        }
+        ScriptDebugger { global }
+    }
+
+    /// Start debugging the scripts of the given global.
+    pub fn add_debuggee(&self, cx: SafeJSContext, pipeline_id: PipelineId, global: &GlobalScope) {
+        self.call_with_global(cx, "addDebuggee", pipeline_id, global);
+    }
+
+    /// Stop debugging the scripts of the given global.
+    pub fn remove_debuggee(
+        &self,
+        cx: SafeJSContext,
+        pipeline_id: PipelineId,
+        global: &GlobalScope,
+    ) {
+        self.call_with_global(cx, "removeDebuggee", pipeline_id, global);
+    }
+
+    /// Handle a request that doesn't affect whether script is paused.
+    pub fn handle_request(
+        &self,
+        cx: SafeJSContext,
+        pipeline_id: PipelineId,
+        request: DebuggerRequest,
+    ) {
+        match request {
+            DebuggerRequest::GetSources(reply) => {
+                let pipeline = serde_json::to_string(&pipeline_id).unwrap();
+                let sources = self.request(cx, ("sources", pipeline));
+                reply.send(sources.unwrap_or_default()).unwrap();
+            },
+            DebuggerRequest::GetSourceText(source, reply) => {
+                let text = self.request(cx, ("sourceText", source));
+                reply.send(text.unwrap_or(None)).unwrap();
+            },
+            DebuggerRequest::SetBreakpoint(source, line, reply) => {
+                let line = self.request(cx, ("setBreakpoint", source, line));
+                reply.send(line.unwrap_or(None)).unwrap();
+            },
+            DebuggerRequest::RemoveBreakpoint(source, line) => {
+                self.request::<_, ()>(cx, ("removeBreakpoint", source, line));
+            },
+            DebuggerRequest::Interrupt => {
+                self.request::<_, ()>(cx, ("interrupt",));
+            },
+            DebuggerRequest::GetFrames(reply) => {
+                let frames = self.request(cx, ("frames",));
+                reply.send(frames.unwrap_or_default()).unwrap();
+            },
+            DebuggerRequest::Evaluate(depth, expression, reply) => {
+                let evaluation: Option<FrameEvaluation> = self
+                    .request(cx, ("evaluate", depth, expression))
+                    .unwrap_or(None);
+                reply.send(evaluation).unwrap();
+            },
+            DebuggerRequest::Attach | DebuggerRequest::Detach | DebuggerRequest::Resume(_) => {
+                unreachable!("handled by the script thread")
+            },
+        }
+    }
+
+    /// Call `handleRequest` in the debugger global. The request and its result are
+    /// passed as JSON.
+    #[allow(unsafe_code)]
+    fn request<A: Serialize, R: DeserializeOwned>(
+        &self,
+        cx: SafeJSContext,
+        request: A,
+    ) -> Option<R> {
+        let request = serde_json::to_string(&request).unwrap();
+        unsafe {
+            let _ac = JSAutoRealm::new(*cx, self.global.get());
+            rooted!(in(*cx) let global = self.global.get());
+            rooted!(in(*cx) let mut argument = UndefinedValue());
+            request.to_jsval(*cx, argument.handle_mut());
+            let arguments = [argument.get()];
+            let arguments = HandleValueArray::from_rooted_slice(&arguments);
+            let name = CString::new("handleRequest").unwrap();
+            rooted!(in(*cx) let mut rval = UndefinedValue());
+            if !JS_CallFunctionName(
+                *cx,
+                global.handle(),
+                name.as_ptr(),
+                &arguments,
+                rval.handle_mut(),
+            ) {
+                clear_pending_exception(*cx, &request);
+                return None;
+            }
+            if !rval.is_string() {
+                return None;
+            }
+            let result = jsstring_to_str(*cx, rval.to_string());
+            serde_json::from_str(&result).ok()
+        }
+    }
+
+    #[allow(unsafe_code)]
+    fn call_with_global(
+        &self,
+        cx: SafeJSContext,
+        function: &str,
+        pipeline_id: PipelineId,
+        global: &GlobalScope,
+    ) {
+        let pipeline = serde_json::to_string(&pipeline_id).unwrap();
+        unsafe {
+            let _ac = JSAutoRealm::new(*cx, self.global.get());
+            rooted!(in(*cx) let debugger_global = self.global.get());
+            rooted!(in(*cx) let mut debuggee = global.reflector().get_jsobject().get());
+            if !JS_WrapObject(*cx, debuggee.handle_mut()) {
+                return clear_pending_exception(*cx, function);
+            }
+            rooted!(in(*cx) let mut pipeline_value = UndefinedValue());
+            pipeline.to_jsval(*cx, pipeline_value.handle_mut());
+            let arguments = [ObjectValue(debuggee.get()), pipeline_value.get()];
+            let arguments = HandleValueArray::from_rooted_slice(&arguments);
+            let name = CString::new(function).unwrap();
+            rooted!(in(*cx) let mut rval = UndefinedValue());
+            if !JS_CallFunctionName(
+                *cx,
+                debugger_global.handle(),
+                name.as_ptr(),
+                &arguments,
+                rval.handle_mut(),
+            ) {
+                clear_pending_exception(*cx, function);
+            }
+        }
+    }
+}
+
+/// The name of a resume limit in `debugger.js`.
+fn resume_limit_name(limit: ResumeLimit) -> &'static str {
+    match limit {
+        ResumeLimit::Next => "next",
+        ResumeLimit::Step => "step",
+        ResumeLimit::Finish => "finish",
+    }
+}
+
+#[allow(unsafe_code)]
+unsafe fn clear_pending_exception(cx: *mut JSContext, context: &str) {
+    warn!("Debugger script failed while handling {}.", context);
+    if JS_IsExceptionPending(cx) {
+        JS_ClearPendingException(cx);
+    }
+}
+
+/// `servoPause(pipeline, pauseInfo)`: notify devtools that script is paused, and block
+/// until it is resumed. Returns the name of the resume limit, if any.
+#[allow(unsafe_code)]
+unsafe extern "C" fn servo_pause(cx: *mut JSContext, argc: u32, vp: *mut JSVal) -> bool {
+    let args = CallArgs::from_vp(vp, argc);
+    let pipeline = HandleValue::from_raw(args.get(0));
+    let info = HandleValue::from_raw(args.get(1));
+    if !pipeline.is_string() || !info.is_string() {
+        throw_type_error(cx, "servoPause expects two strings");
+        return false;
+    }
+    let pipeline = jsstring_to_str(cx, pipeline.to_string());
+    let info = jsstring_to_str(cx, info.to_string());
+    let limit = match (serde_json::from_str(&pipeline), serde_json::from_str(&info)) {
+        (Ok(pipeline), Ok(info)) => ScriptThread::debugger_pause(pipeline, info),
+        _ => {
+            warn!("Invalid pause from the debugger: {} {}", pipeline, info);
+            None
+        },
+    };
+    let rval = MutableHandleValue::from_raw(args.rval());
+    match limit {
+        Some(limit) => resume_limit_name(limit).to_jsval(cx, rval),
+        None => rval.set(UndefinedValue()),
+    }
+    true
+}
@@ -7,6 +7,7 @@ use crate::dom::bindings::codegen::Bindings::CSSStyleDeclarationBinding::CSSStyl
 use crate::dom::bindings::codegen::Bindings::DOMRectBinding::DOMRectMethods;
 use crate::dom::bindings::codegen::Bindings::DocumentBinding::DocumentMethods;
 use crate::dom::bindings::codegen::Bindings::ElementBinding::ElementMethods;
+use crate::dom::bindings::codegen::Bindings::StyleSheetBinding::StyleSheetMethods;
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::conversions::{jsstring_to_str, ConversionResult, FromJSValConvertible};
 use crate::dom::bindings::inheritance::Castable;
@@ -16,12 +17,13 @@ use crate::dom::document::AnimationFrameCallback;
 use crate::dom::element::Element;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::node::{window_from_node, Node, ShadowIncluding};
+use crate::dom::stylesheet::StyleSheet;
 use crate::dom::window::Window;
 use crate::script_thread::Documents;
 use devtools_traits::TimelineMarkerType;
 use devtools_traits::{AutoMargins, CachedConsoleMessage, CachedConsoleMessageTypes};
 use devtools_traits::{ComputedNodeLayout, ConsoleAPI, PageError};
-use devtools_traits::{EvaluateJSReply, Modification, NodeInfo, TimelineMarker};
+use devtools_traits::{EvaluateJSReply, Modification, NodeInfo, StyleSheetInfo, TimelineMarker};
 use ipc_channel::ipc::IpcSender;
 use js::jsval::UndefinedValue;
 use js::rust::wrappers::ObjectClassName;
@@ -296,3 +298,61 @@ pub fn handle_reload(documents: &Documents, id: PipelineId) {
         win.Location().reload_without_origin_check();
     }
 }
+
+pub fn handle_get_stylesheets(
+    documents: &Documents,
+    pipeline: PipelineId,
+    reply: IpcSender<Vec<StyleSheetInfo>>,
+) {
+    let document = match documents.find_document(pipeline) {
+        None => return reply.send(vec![]).unwrap(),
+        Some(document) => document,
+    };
+
+    let stylesheets = (0..document.stylesheet_count())
+        .filter_map(|index| {
+            document.stylesheet_at(index).map(|sheet| {
+                let stylesheet = sheet.upcast::<StyleSheet>();
+                StyleSheetInfo {
+                    index: index,
+                    href: stylesheet.GetHref().map(String::from),
+                    title: stylesheet.GetTitle().map(String::from),
+                    disabled: sheet.disabled(),
+                    ruleCount: sheet.rule_count(),
+                }
+            })
+        })
+        .collect();
+    reply.send(stylesheets).unwrap();
+}
+
+pub fn handle_get_stylesheet_text(
+    documents: &Documents,
+    pipeline: PipelineId,
+    index: usize,
+    reply: IpcSender<Option<String>>,
+) {
+    let text = documents
+        .find_document(pipeline)
+        .and_then(|document| document.stylesheet_at(index))
+        .map(|sheet| sheet.text());
+    reply.send(text).unwrap();
+}
+
+pub fn handle_update_stylesheet(
+    documents: &Documents,
+    pipeline: PipelineId,
+    index: usize,
+    text: String,
+) {
+    match documents
+        .find_document(pipeline)
+        .and_then(|document| document.stylesheet_at(index))
+    {
+        Some(sheet) => sheet.set_text(&text),
+        None => warn!(
+            "stylesheet {} for pipeline id {} is not found",
+            index, pipeline
+        ),
+    }
+}
@@ -4,15 +4,15 @@
 
 use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::DomObject;
-use crate::dom::bindings::structuredclone::StructuredCloneData;
 use crate::script_runtime::CommonScriptMsg;
+use script_traits::transferable::StructuredSerializedData;
 
 /// Messages used to control the worker event loops
 pub enum WorkerScriptMsg {
     /// Common variants associated with the script messages
     Common(CommonScriptMsg),
     /// Message sent through Worker.postMessage
-    DOMMessage(StructuredCloneData),
+    DOMMessage(StructuredSerializedData),
 }
 
 pub struct SimpleWorkerErrorHandler<T: DomObject> {
@@ -12,6 +12,9 @@ use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::blob::{Blob, BlobImpl};
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::messageport::MessagePort;
+use crate::script_runtime::JSContext as SafeJSContext;
+use js::conversions::ToJSValConvertible;
 use js::glue::CopyJSStructuredCloneData;
 use js::glue::DeleteJSAutoStructuredCloneBuffer;
 use js::glue::GetLengthOfJSStructuredCloneData;
@@ -28,9 +31,13 @@ use js::jsapi::{JSObject, JS_ClearPendingException};
 use js::jsapi::{JSStructuredCloneCallbacks, JSStructuredCloneReader, JSStructuredCloneWriter};
 use js::jsapi::{JS_ReadBytes, JS_WriteBytes};
 use js::jsapi::{JS_ReadUint32Pair, JS_WriteUint32Pair};
+use js::jsval::UndefinedValue;
 use js::rust::wrappers::{JS_ReadStructuredClone, JS_WriteStructuredClone};
-use js::rust::{Handle, HandleValue, MutableHandleValue};
+use js::rust::{CustomAutoRooterGuard, Handle, HandleValue, MutableHandleValue};
 use libc::size_t;
+use msg::constellation_msg::MessagePortId;
+use script_traits::transferable::{MessagePortImpl, StructuredSerializedData};
+use std::collections::HashMap;
 use std::os::raw;
 use std::ptr;
 use std::slice;
@@ -44,6 +51,7 @@ enum StructuredCloneTags {
     /// To support additional types, add new tags with values incremented from the last one before Max.
     Min = 0xFFFF8000,
     DomBlob = 0xFFFF8001,
+    MessagePort = 0xFFFF8002,
     Max = 0xFFFFFFFF,
 }
 
@@ -189,27 +197,62 @@ unsafe extern "C" fn write_callback(
 }
 
 unsafe extern "C" fn read_transfer_callback(
-    _cx: *mut JSContext,
+    cx: *mut JSContext,
     _r: *mut JSStructuredCloneReader,
-    _tag: u32,
+    tag: u32,
     _content: *mut raw::c_void,
-    _extra_data: u64,
-    _closure: *mut raw::c_void,
-    _return_object: RawMutableHandleObject,
+    extra_data: u64,
+    closure: *mut raw::c_void,
+    return_object: RawMutableHandleObject,
 ) -> bool {
-    false
+    if tag != StructuredCloneTags::MessagePort as u32 {
+        return false;
+    }
+    let sc_holder = &mut *(closure as *mut StructuredCloneHolder);
+    let port_impl = MessagePort::transferred_port_id(extra_data).and_then(|port_id| {
+        sc_holder
+            .port_impls
+            .as_mut()
+            .and_then(|port_impls| port_impls.remove(&port_id))
+    });
+    let port_impl = match port_impl {
+        Some(port_impl) => port_impl,
+        None => return false,
+    };
+    let target_global = GlobalScope::from_context(cx);
+    let port = MessagePort::new_transferred(&target_global, port_impl);
+    return_object.set(port.reflector().get_jsobject().get());
+    sc_holder.message_ports.push(port);
+    true
 }
 
+/// <https://html.spec.whatwg.org/multipage/#structuredserializewithtransfer>
 unsafe extern "C" fn write_transfer_callback(
-    _cx: *mut JSContext,
-    _obj: RawHandleObject,
-    _closure: *mut raw::c_void,
-    _tag: *mut u32,
-    _ownership: *mut TransferableOwnership,
+    cx: *mut JSContext,
+    obj: RawHandleObject,
+    closure: *mut raw::c_void,
+    tag: *mut u32,
+    ownership: *mut TransferableOwnership,
     _content: *mut *mut raw::c_void,
-    _extra_data: *mut u64,
+    extra_data: *mut u64,
 ) -> bool {
-    false
+    let port = match root_from_handleobject::<MessagePort>(Handle::from_raw(obj), cx) {
+        Ok(port) => port,
+        Err(()) => return false,
+    };
+    let (port_impl, data) = match port.transfer() {
+        Ok(transferred) => transferred,
+        Err(()) => return false,
+    };
+    let sc_holder = &mut *(closure as *mut StructuredCloneHolder);
+    sc_holder
+        .port_impls
+        .get_or_insert_with(HashMap::new)
+        .insert(*port.message_port_id(), port_impl);
+    *tag = StructuredCloneTags::MessagePort as u32;
+    *ownership = TransferableOwnership::SCTAG_TMO_CUSTOM;
+    *extra_data = data;
+    true
 }
 
 unsafe extern "C" fn free_transfer_callback(
@@ -222,11 +265,11 @@ unsafe extern "C" fn free_transfer_callback(
 }
 
 unsafe extern "C" fn can_transfer_callback(
-    _cx: *mut JSContext,
-    _obj: RawHandleObject,
+    cx: *mut JSContext,
+    obj: RawHandleObject,
     _closure: *mut raw::c_void,
 ) -> bool {
-    false
+    root_from_handleobject::<MessagePort>(Handle::from_raw(obj), cx).is_ok()
 }
 
 unsafe extern "C" fn report_error_callback(_cx: *mut JSContext, _errorid: u32) {}
@@ -241,8 +284,141 @@ static STRUCTURED_CLONE_CALLBACKS: JSStructuredCloneCallbacks = JSStructuredClon
     canTransfer: Some(can_transfer_callback),
 };
 
+#[derive(Default)]
 struct StructuredCloneHolder {
     blob: Option<DomRoot<Blob>>,
+    /// The state of the message ports transferred along with the message.
+    port_impls: Option<HashMap<MessagePortId, MessagePortImpl>>,
+    /// The message ports created in the target global while reading the message.
+    message_ports: Vec<DomRoot<MessagePort>>,
+}
+
+/// Writes a structured clone of `message`, transferring the objects in `transfer`.
+/// Returns a `DataClone` error if that fails.
+unsafe fn write_clone(
+    cx: *mut JSContext,
+    message: HandleValue,
+    transfer: HandleValue,
+    sc_holder: &mut StructuredCloneHolder,
+) -> Fallible<Vec<u8>> {
+    let scbuf = NewJSAutoStructuredCloneBuffer(
+        StructuredCloneScope::DifferentProcess,
+        &STRUCTURED_CLONE_CALLBACKS,
+    );
+    let scdata = &mut ((*scbuf).data_);
+    let policy = CloneDataPolicy {
+        // TODO: SAB?
+        sharedArrayBuffer_: false,
+    };
+    let result = JS_WriteStructuredClone(
+        cx,
+        message,
+        scdata,
+        StructuredCloneScope::DifferentProcess,
+        policy,
+        &STRUCTURED_CLONE_CALLBACKS,
+        sc_holder as *mut StructuredCloneHolder as *mut raw::c_void,
+        transfer,
+    );
+    if !result {
+        JS_ClearPendingException(cx);
+        DeleteJSAutoStructuredCloneBuffer(scbuf);
+        return Err(Error::DataClone);
+    }
+
+    let nbytes = GetLengthOfJSStructuredCloneData(scdata);
+    let mut data = Vec::with_capacity(nbytes);
+    CopyJSStructuredCloneData(scdata, data.as_mut_ptr());
+    data.set_len(nbytes);
+
+    DeleteJSAutoStructuredCloneBuffer(scbuf);
+
+    Ok(data)
+}
+
+/// Reads a structured clone into `rval`, in the realm of `global`.
+/// Returns whether `JS_ReadStructuredClone` succeeded.
+fn read_clone(
+    global: &GlobalScope,
+    data: *mut u64,
+    nbytes: size_t,
+    rval: MutableHandleValue,
+    sc_holder: &mut StructuredCloneHolder,
+) -> bool {
+    let cx = global.get_cx();
+    let _ac = enter_realm(&*global);
+    unsafe {
+        let scbuf = NewJSAutoStructuredCloneBuffer(
+            StructuredCloneScope::DifferentProcess,
+            &STRUCTURED_CLONE_CALLBACKS,
+        );
+        let scdata = &mut ((*scbuf).data_);
+
+        WriteBytesToJSStructuredCloneData(data as *const u8, nbytes, scdata);
+
+        let result = JS_ReadStructuredClone(
+            *cx,
+            scdata,
+            JS_STRUCTURED_CLONE_VERSION,
+            StructuredCloneScope::DifferentProcess,
+            rval,
+            &STRUCTURED_CLONE_CALLBACKS,
+            sc_holder as *mut StructuredCloneHolder as *mut raw::c_void,
+        );
+        if !result {
+            JS_ClearPendingException(*cx);
+        }
+
+        DeleteJSAutoStructuredCloneBuffer(scbuf);
+
+        result
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#structuredserializewithtransfer>
+///
+/// Writes a structured clone of `message`, transferring the message ports in
+/// `transfer`. Returns a `DataClone` error if that fails.
+pub fn write(
+    cx: SafeJSContext,
+    message: HandleValue,
+    transfer: Option<CustomAutoRooterGuard<Option<Vec<*mut JSObject>>>>,
+) -> Fallible<StructuredSerializedData> {
+    let mut sc_holder = StructuredCloneHolder::default();
+    unsafe {
+        rooted!(in(*cx) let mut transfer_val = UndefinedValue());
+        if let Some(ref transfer) = transfer {
+            if let Some(ref transfer) = **transfer {
+                transfer.to_jsval(*cx, transfer_val.handle_mut());
+            }
+        }
+        let serialized = write_clone(*cx, message, transfer_val.handle(), &mut sc_holder)?;
+        Ok(StructuredSerializedData {
+            serialized: serialized,
+            ports: sc_holder.port_impls.take(),
+        })
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#structureddeserializewithtransfer>
+///
+/// Reads a structured clone into `rval`, returning the message ports which were
+/// transferred to `global` along with it.
+pub fn read(
+    global: &GlobalScope,
+    mut data: StructuredSerializedData,
+    rval: MutableHandleValue,
+) -> Result<Vec<DomRoot<MessagePort>>, ()> {
+    let mut sc_holder = StructuredCloneHolder {
+        port_impls: data.ports.take(),
+        ..Default::default()
+    };
+    let nbytes = data.serialized.len();
+    let bytes = data.serialized.as_mut_ptr() as *mut u64;
+    if !read_clone(global, bytes, nbytes, rval, &mut sc_holder) {
+        return Err(());
+    }
+    Ok(sc_holder.message_ports)
 }
 
 /// A buffer for a structured clone.
@@ -257,39 +433,10 @@ impl StructuredCloneData {
     // TODO: should this be unsafe?
     /// Writes a structured clone. Returns a `DataClone` error if that fails.
     pub fn write(cx: *mut JSContext, message: HandleValue) -> Fallible<StructuredCloneData> {
+        let mut sc_holder = StructuredCloneHolder::default();
         unsafe {
-            let scbuf = NewJSAutoStructuredCloneBuffer(
-                StructuredCloneScope::DifferentProcess,
-                &STRUCTURED_CLONE_CALLBACKS,
-            );
-            let scdata = &mut ((*scbuf).data_);
-            let policy = CloneDataPolicy {
-                // TODO: SAB?
-                sharedArrayBuffer_: false,
-            };
-            let result = JS_WriteStructuredClone(
-                cx,
-                message,
-                scdata,
-                StructuredCloneScope::DifferentProcess,
-                policy,
-                &STRUCTURED_CLONE_CALLBACKS,
-                ptr::null_mut(),
-                HandleValue::undefined(),
-            );
-            if !result {
-                JS_ClearPendingException(cx);
-                return Err(Error::DataClone);
-            }
-
-            let nbytes = GetLengthOfJSStructuredCloneData(scdata);
-            let mut data = Vec::with_capacity(nbytes);
-            CopyJSStructuredCloneData(scdata, data.as_mut_ptr());
-            data.set_len(nbytes);
-
-            DeleteJSAutoStructuredCloneBuffer(scbuf);
-
-            Ok(StructuredCloneData::Vector(data))
+            write_clone(cx, message, HandleValue::undefined(), &mut sc_holder)
+                .map(StructuredCloneData::Vector)
         }
     }
 
@@ -307,33 +454,9 @@ impl StructuredCloneData {
     ///
     /// Panics if `JS_ReadStructuredClone` fails.
     fn read_clone(global: &GlobalScope, data: *mut u64, nbytes: size_t, rval: MutableHandleValue) {
-        let cx = global.get_cx();
-        let _ac = enter_realm(&*global);
-        let mut sc_holder = StructuredCloneHolder { blob: None };
-        let sc_holder_ptr = &mut sc_holder as *mut _;
-        unsafe {
-            let scbuf = NewJSAutoStructuredCloneBuffer(
-                StructuredCloneScope::DifferentProcess,
-                &STRUCTURED_CLONE_CALLBACKS,
-            );
-            let scdata = &mut ((*scbuf).data_);
-
-            WriteBytesToJSStructuredCloneData(data as *const u8, nbytes, scdata);
-
-            assert!(JS_ReadStructuredClone(
-                *cx,
-                scdata,
-                JS_STRUCTURED_CLONE_VERSION,
-                StructuredCloneScope::DifferentProcess,
-                rval,
-                &STRUCTURED_CLONE_CALLBACKS,
-                sc_holder_ptr as *mut raw::c_void
-            ));
-
-            DeleteJSAutoStructuredCloneBuffer(scbuf);
-        }
+        let mut sc_holder = StructuredCloneHolder::default();
+        assert!(read_clone(global, data, nbytes, rval, &mut sc_holder));
     }
-
     /// Thunk for the actual `read_clone` method. Resolves proper variant for read_clone.
     pub fn read(self, global: &GlobalScope, rval: MutableHandleValue) {
         match self {
@@ -24,7 +24,10 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlcanvaselement::{CanvasContext, HTMLCanvasElement};
 use crate::dom::imagedata::ImageData;
 use crate::dom::node::{Node, NodeDamage};
+use crate::dom::path2d::Path2D;
+use crate::dom::textmetrics::TextMetrics;
 use crate::unpremultiplytable::UNPREMULTIPLY_TABLE;
+use canvas_traits::canvas::TextMetrics as CanvasTextMetrics;
 use canvas_traits::canvas::{Canvas2dMsg, CanvasId, CanvasMsg};
 use canvas_traits::canvas::{CompositionOrBlending, FillOrStrokeStyle, FillRule};
 use canvas_traits::canvas::{LineCapStyle, LineJoinStyle, LinearGradientStyle};
@@ -51,6 +54,15 @@ use std::cell::Cell;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::{fmt, mem};
+use style::context::QuirksMode;
+use style::parser::ParserContext;
+use style::properties::longhands::filter;
+use style::properties::shorthands::font;
+use style::properties::{Importance, PropertyDeclaration, PropertyDeclarationBlock};
+use style::properties::{ShorthandId, SourcePropertyDeclaration};
+use style::stylesheets::{CssRuleType, Origin};
+use style::values::specified::LineHeight;
+use style_traits::ParsingMode;
 
 #[must_root]
 #[derive(Clone, JSTraceable, MallocSizeOf)]
@@ -83,11 +95,15 @@ struct CanvasContextState {
     line_cap: LineCapStyle,
     line_join: LineJoinStyle,
     miter_limit: f64,
+    line_dash: Vec<f64>,
+    line_dash_offset: f64,
     transform: Transform2D<f32>,
     shadow_offset_x: f64,
     shadow_offset_y: f64,
     shadow_blur: f64,
     shadow_color: RGBA,
+    font: DOMString,
+    filter: DOMString,
 }
 
 impl CanvasContextState {
@@ -103,11 +119,15 @@ impl CanvasContextState {
             line_cap: LineCapStyle::Butt,
             line_join: LineJoinStyle::Miter,
             miter_limit: 10.0,
+            line_dash: Vec::new(),
+            line_dash_offset: 0.0,
             transform: Transform2D::identity(),
             shadow_offset_x: 0.0,
             shadow_offset_y: 0.0,
             shadow_blur: 0.0,
             shadow_color: RGBA::transparent(),
+            font: DOMString::from("10px sans-serif"),
+            filter: DOMString::from("none"),
         }
     }
 }
@@ -210,7 +230,7 @@ impl CanvasState {
 
         let image_size = Size2D::new(img.width, img.height);
         let image_data = match img.format {
-            PixelFormat::BGRA8 => img.bytes.to_vec(),
+            PixelFormat::BGRA8 => img.first_frame_bytes().to_vec(),
             pixel_format => unimplemented!("unsupported pixel format ({:?})", pixel_format),
         };
 
@@ -873,6 +893,44 @@ impl CanvasState {
         self.send_canvas_2d_msg(Canvas2dMsg::FillText(parsed_text, x, y, max_width));
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-measuretext
+    pub fn MeasureText(&self, global: &GlobalScope, text: DOMString) -> DomRoot<TextMetrics> {
+        let (sender, receiver) =
+            profiled_ipc::channel::<CanvasTextMetrics>(global.time_profiler_chan().clone())
+                .unwrap();
+        self.send_canvas_2d_msg(Canvas2dMsg::MeasureText(text.into(), sender));
+        TextMetrics::new(global, receiver.recv().unwrap())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-font
+    pub fn Font(&self) -> DOMString {
+        self.state.borrow().font.clone()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-font
+    pub fn SetFont(&self, value: DOMString) {
+        let font = match parse_font(&value) {
+            Some(font) => font,
+            None => return,
+        };
+        self.state.borrow_mut().font = DOMString::from(font.clone());
+        self.send_canvas_2d_msg(Canvas2dMsg::SetFont(font))
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-filter
+    pub fn Filter(&self) -> DOMString {
+        self.state.borrow().filter.clone()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-filter
+    pub fn SetFilter(&self, value: DOMString) {
+        if !is_valid_filter(&value) {
+            return;
+        }
+        self.state.borrow_mut().filter = value.clone();
+        self.send_canvas_2d_msg(Canvas2dMsg::SetFilter(value.into()))
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-linewidth
     pub fn LineWidth(&self) -> f64 {
         self.state.borrow().line_width
@@ -943,6 +1001,47 @@ impl CanvasState {
         self.send_canvas_2d_msg(Canvas2dMsg::SetMiterLimit(limit as f32))
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-setlinedash
+    pub fn SetLineDash(&self, segments: Vec<f64>) {
+        // Step 1.
+        if segments.iter().any(|s| !s.is_finite() || *s < 0.0) {
+            return;
+        }
+
+        // Step 2.
+        let segments = if segments.len() % 2 == 1 {
+            [&segments[..], &segments[..]].concat()
+        } else {
+            segments
+        };
+
+        // Step 3.
+        self.send_canvas_2d_msg(Canvas2dMsg::SetLineDash(
+            segments.iter().map(|s| *s as f32).collect(),
+        ));
+        self.state.borrow_mut().line_dash = segments;
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-getlinedash
+    pub fn GetLineDash(&self) -> Vec<f64> {
+        self.state.borrow().line_dash.clone()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-linedashoffset
+    pub fn LineDashOffset(&self) -> f64 {
+        self.state.borrow().line_dash_offset
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-linedashoffset
+    pub fn SetLineDashOffset(&self, offset: f64) {
+        if !offset.is_finite() {
+            return;
+        }
+
+        self.state.borrow_mut().line_dash_offset = offset;
+        self.send_canvas_2d_msg(Canvas2dMsg::SetLineDashOffset(offset as f32))
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-createimagedata
     pub fn CreateImageData(
         &self,
@@ -1173,9 +1272,16 @@ impl CanvasState {
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-fill
-    pub fn Fill(&self, _fill_rule: CanvasFillRule) {
-        // TODO: Process fill rule
-        self.send_canvas_2d_msg(Canvas2dMsg::Fill);
+    pub fn Fill(&self, fill_rule: CanvasFillRule) {
+        self.send_canvas_2d_msg(Canvas2dMsg::Fill(to_fill_rule(fill_rule)));
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-fill
+    pub fn Fill_(&self, path: &Path2D, fill_rule: CanvasFillRule) {
+        self.send_canvas_2d_msg(Canvas2dMsg::FillPath(
+            path.segments(),
+            to_fill_rule(fill_rule),
+        ));
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-stroke
@@ -1183,10 +1289,22 @@ impl CanvasState {
         self.send_canvas_2d_msg(Canvas2dMsg::Stroke);
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-stroke
+    pub fn Stroke_(&self, path: &Path2D) {
+        self.send_canvas_2d_msg(Canvas2dMsg::StrokePath(path.segments()));
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-clip
-    pub fn Clip(&self, _fill_rule: CanvasFillRule) {
-        // TODO: Process fill rule
-        self.send_canvas_2d_msg(Canvas2dMsg::Clip);
+    pub fn Clip(&self, fill_rule: CanvasFillRule) {
+        self.send_canvas_2d_msg(Canvas2dMsg::Clip(to_fill_rule(fill_rule)));
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-clip
+    pub fn Clip_(&self, path: &Path2D, fill_rule: CanvasFillRule) {
+        self.send_canvas_2d_msg(Canvas2dMsg::ClipPath(
+            path.segments(),
+            to_fill_rule(fill_rule),
+        ));
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-ispointinpath
@@ -1197,13 +1315,38 @@ impl CanvasState {
         y: f64,
         fill_rule: CanvasFillRule,
     ) -> bool {
-        let fill_rule = match fill_rule {
-            CanvasFillRule::Nonzero => FillRule::Nonzero,
-            CanvasFillRule::Evenodd => FillRule::Evenodd,
-        };
         let (sender, receiver) =
             profiled_ipc::channel::<bool>(global.time_profiler_chan().clone()).unwrap();
-        self.send_canvas_2d_msg(Canvas2dMsg::IsPointInPath(x, y, fill_rule, sender));
+        self.send_canvas_2d_msg(Canvas2dMsg::IsPointInPath(
+            x,
+            y,
+            to_fill_rule(fill_rule),
+            sender,
+        ));
+        receiver.recv().unwrap()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-ispointinpath
+    pub fn IsPointInPath_(
+        &self,
+        global: &GlobalScope,
+        path: &Path2D,
+        x: f64,
+        y: f64,
+        fill_rule: CanvasFillRule,
+    ) -> bool {
+        if !(x.is_finite() && y.is_finite()) {
+            return false;
+        }
+        let (sender, receiver) =
+            profiled_ipc::channel::<bool>(global.time_profiler_chan().clone()).unwrap();
+        self.send_canvas_2d_msg(Canvas2dMsg::IsPointInPathSegments(
+            path.segments(),
+            x,
+            y,
+            to_fill_rule(fill_rule),
+            sender,
+        ));
         receiver.recv().unwrap()
     }
 
@@ -1647,17 +1790,34 @@ impl CanvasRenderingContext2DMethods for CanvasRenderingContext2D {
         self.mark_as_dirty();
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-fill
+    fn Fill_(&self, path: &Path2D, fill_rule: CanvasFillRule) {
+        self.canvas_state.borrow().Fill_(path, fill_rule);
+        self.mark_as_dirty();
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-stroke
     fn Stroke(&self) {
         self.canvas_state.borrow().Stroke();
         self.mark_as_dirty();
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-stroke
+    fn Stroke_(&self, path: &Path2D) {
+        self.canvas_state.borrow().Stroke_(path);
+        self.mark_as_dirty();
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-clip
     fn Clip(&self, fill_rule: CanvasFillRule) {
         self.canvas_state.borrow().Clip(fill_rule)
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-clip
+    fn Clip_(&self, path: &Path2D, fill_rule: CanvasFillRule) {
+        self.canvas_state.borrow().Clip_(path, fill_rule)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-ispointinpath
     fn IsPointInPath(&self, x: f64, y: f64, fill_rule: CanvasFillRule) -> bool {
         self.canvas_state
@@ -1665,12 +1825,24 @@ impl CanvasRenderingContext2DMethods for CanvasRenderingContext2D {
             .IsPointInPath(&self.global(), x, y, fill_rule)
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-ispointinpath
+    fn IsPointInPath_(&self, path: &Path2D, x: f64, y: f64, fill_rule: CanvasFillRule) -> bool {
+        self.canvas_state
+            .borrow()
+            .IsPointInPath_(&self.global(), path, x, y, fill_rule)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-filltext
     fn FillText(&self, text: DOMString, x: f64, y: f64, max_width: Option<f64>) {
         self.canvas_state.borrow().FillText(text, x, y, max_width);
         self.mark_as_dirty();
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-measuretext
+    fn MeasureText(&self, text: DOMString) -> DomRoot<TextMetrics> {
+        self.canvas_state.borrow().MeasureText(&self.global(), text)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-drawimage
     fn DrawImage(&self, image: CanvasImageSource, dx: f64, dy: f64) -> ErrorResult {
         self.canvas_state
@@ -1952,6 +2124,46 @@ impl CanvasRenderingContext2DMethods for CanvasRenderingContext2D {
         self.canvas_state.borrow().SetMiterLimit(limit)
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-setlinedash
+    fn SetLineDash(&self, segments: Vec<f64>) {
+        self.canvas_state.borrow().SetLineDash(segments)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-getlinedash
+    fn GetLineDash(&self) -> Vec<f64> {
+        self.canvas_state.borrow().GetLineDash()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-linedashoffset
+    fn LineDashOffset(&self) -> f64 {
+        self.canvas_state.borrow().LineDashOffset()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-linedashoffset
+    fn SetLineDashOffset(&self, offset: f64) {
+        self.canvas_state.borrow().SetLineDashOffset(offset)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-font
+    fn Font(&self) -> DOMString {
+        self.canvas_state.borrow().Font()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-font
+    fn SetFont(&self, value: DOMString) {
+        self.canvas_state.borrow().SetFont(value)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-filter
+    fn Filter(&self) -> DOMString {
+        self.canvas_state.borrow().Filter()
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-context-2d-filter
+    fn SetFilter(&self, value: DOMString) {
+        self.canvas_state.borrow().SetFilter(value)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-context-2d-shadowoffsetx
     fn ShadowOffsetX(&self) -> f64 {
         self.canvas_state.borrow().ShadowOffsetX()
@@ -2021,8 +2233,61 @@ pub fn parse_color(string: &str) -> Result<RGBA, ()> {
     }
 }
 
+fn css_parser_context(url: &ServoUrl) -> ParserContext {
+    ParserContext::new(
+        Origin::Author,
+        url,
+        Some(CssRuleType::Style),
+        ParsingMode::DEFAULT,
+        QuirksMode::NoQuirks,
+        None,
+        None,
+    )
+}
+
+/// Parses a value of the `font` attribute as the CSS `font` shorthand, and
+/// returns its serialization with `line-height` forced to `normal`.
+fn parse_font(value: &str) -> Option<String> {
+    let url = ServoUrl::parse("about:blank").unwrap();
+    let context = css_parser_context(&url);
+    let mut input = ParserInput::new(value);
+    let mut parser = Parser::new(&mut input);
+    let mut declarations = SourcePropertyDeclaration::new();
+    font::parse_into(&mut declarations, &context, &mut parser).ok()?;
+    let mut block = PropertyDeclarationBlock::new();
+    block.extend(declarations.drain(), Importance::Normal);
+    block.push(
+        PropertyDeclaration::LineHeight(LineHeight::normal()),
+        Importance::Normal,
+    );
+    let mut serialization = String::new();
+    block
+        .shorthand_to_css(ShorthandId::Font, &mut serialization)
+        .ok()?;
+    Some(serialization)
+}
+
+/// Whether a value of the `filter` attribute parses as the CSS `filter`
+/// property.
+fn is_valid_filter(value: &str) -> bool {
+    let url = ServoUrl::parse("about:blank").unwrap();
+    let context = css_parser_context(&url);
+    let mut input = ParserInput::new(value);
+    let mut parser = Parser::new(&mut input);
+    parser
+        .parse_entirely(|parser| filter::parse(&context, parser))
+        .is_ok()
+}
+
 // Used by drawImage to determine if a source or destination rectangle is valid
 // Origin coordinates and size cannot be negative. Size has to be greater than zero
+fn to_fill_rule(fill_rule: CanvasFillRule) -> FillRule {
+    match fill_rule {
+        CanvasFillRule::Nonzero => FillRule::Nonzero,
+        CanvasFillRule::Evenodd => FillRule::Evenodd,
+    }
+}
+
 fn is_rect_valid(rect: Rect<f64>) -> bool {
     rect.size.width > 0.0 && rect.size.height > 0.0
 }
@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::dom::bindings::cell::DomRefCell;
 use crate::dom::bindings::codegen::Bindings::CSSStyleSheetBinding;
 use crate::dom::bindings::codegen::Bindings::CSSStyleSheetBinding::CSSStyleSheetMethods;
 use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
@@ -11,14 +12,18 @@ use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::cssrulelist::{CSSRuleList, RulesSource};
 use crate::dom::element::Element;
+use crate::dom::htmlelement::HTMLElement;
+use crate::dom::htmlstyleelement::HTMLStyleElement;
 use crate::dom::node::{stylesheets_owner_from_node, Node};
 use crate::dom::stylesheet::StyleSheet;
 use crate::dom::window::Window;
+use crate::stylesheet_loader::StylesheetLoader;
 use dom_struct::dom_struct;
 use servo_arc::Arc;
 use std::cell::Cell;
-use style::shared_lock::SharedRwLock;
+use style::shared_lock::{SharedRwLock, ToCssWithGuard};
 use style::stylesheets::Stylesheet as StyleStyleSheet;
+use style::stylesheets::StylesheetLoader as StyleStylesheetLoader;
 
 #[dom_struct]
 pub struct CSSStyleSheet {
@@ -28,6 +33,8 @@ pub struct CSSStyleSheet {
     #[ignore_malloc_size_of = "Arc"]
     style_stylesheet: Arc<StyleStyleSheet>,
     origin_clean: Cell<bool>,
+    /// The text this stylesheet was last parsed from by `set_text`.
+    source_text: DomRefCell<Option<String>>,
 }
 
 impl CSSStyleSheet {
@@ -44,6 +51,7 @@ impl CSSStyleSheet {
             rulelist: MutNullableDom::new(None),
             style_stylesheet: stylesheet,
             origin_clean: Cell::new(true),
+            source_text: DomRefCell::new(None),
         }
     }
 
@@ -101,6 +109,66 @@ impl CSSStyleSheet {
     pub fn set_origin_clean(&self, origin_clean: bool) {
         self.origin_clean.set(origin_clean);
     }
+
+    /// The number of top-level rules in this stylesheet.
+    pub fn rule_count(&self) -> usize {
+        let guard = self.shared_lock().read();
+        self.style_stylesheet.contents.rules(&guard).len()
+    }
+
+    /// The source text of this stylesheet: the text it was last given by `set_text`, or the
+    /// contents of its owner if that is a `<style>` element. The source of other stylesheets
+    /// isn't kept, so their rules are serialized, one per line.
+    pub fn text(&self) -> String {
+        if let Some(ref text) = *self.source_text.borrow() {
+            return text.clone();
+        }
+        if self.owner.is::<HTMLStyleElement>() {
+            return self.owner.upcast::<Node>().child_text_content().into();
+        }
+
+        let guard = self.shared_lock().read();
+        let serialized: Vec<String> = self
+            .style_stylesheet
+            .contents
+            .rules(&guard)
+            .iter()
+            .map(|rule| rule.to_css_string(&guard))
+            .collect();
+        serialized.join("\n")
+    }
+
+    /// Replace the rules of this stylesheet with the ones parsed from `css`,
+    /// and restyle the documents it applies to.
+    pub fn set_text(&self, css: &str) {
+        let global = self.global();
+        let window = global.as_window();
+        // Only HTML elements can load the stylesheets imported by the new rules.
+        let loader = self
+            .owner
+            .downcast::<HTMLElement>()
+            .map(StylesheetLoader::for_element);
+        let url_data = self.style_stylesheet.contents.url_data.read().clone();
+        StyleStyleSheet::update_from_str(
+            &self.style_stylesheet,
+            css,
+            url_data,
+            loader
+                .as_ref()
+                .map(|loader| loader as &dyn StyleStylesheetLoader),
+            window.css_error_reporter(),
+            0,
+        );
+        *self.source_text.borrow_mut() = Some(css.to_owned());
+
+        // The rules the CSSOM wrappers point to are gone.
+        if let Some(rulelist) = self.rulelist.get() {
+            rulelist.deparent_all();
+        }
+        self.rulelist.set(None);
+
+        stylesheets_owner_from_node(self.owner().upcast::<Node>()).invalidate_stylesheets();
+    }
 }
 
 impl CSSStyleSheetMethods for CSSStyleSheet {
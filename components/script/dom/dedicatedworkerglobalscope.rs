@@ -16,7 +16,7 @@ use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{DomRoot, RootCollection, ThreadLocalStackRoots};
 use crate::dom::bindings::str::DOMString;
-use crate::dom::bindings::structuredclone::StructuredCloneData;
+use crate::dom::bindings::structuredclone;
 use crate::dom::errorevent::ErrorEvent;
 use crate::dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
 use crate::dom::eventtarget::EventTarget;
@@ -25,6 +25,8 @@ use crate::dom::messageevent::MessageEvent;
 use crate::dom::worker::{TrustedWorkerAddress, Worker};
 use crate::dom::workerglobalscope::WorkerGlobalScope;
 use crate::fetch::load_whole_resource;
+use crate::script_module::{fetch_external_module_script, run_module_script};
+use crate::script_module::{ModuleOwner, ModuleTree, ScriptFetchOptions};
 use crate::script_runtime::ScriptThreadEventCategory::WorkerEvent;
 use crate::script_runtime::{
     new_child_runtime, CommonScriptMsg, JSContext as SafeJSContext, Runtime, ScriptChan, ScriptPort,
@@ -37,9 +39,10 @@ use dom_struct::dom_struct;
 use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
 use js::jsapi::JSContext;
+use js::jsapi::JSObject;
 use js::jsapi::JS_AddInterruptCallback;
 use js::jsval::UndefinedValue;
-use js::rust::HandleValue;
+use js::rust::{CustomAutoRooterGuard, HandleValue};
 use msg::constellation_msg::{PipelineId, TopLevelBrowsingContextId};
 use net_traits::image_cache::ImageCache;
 use net_traits::request::{CredentialsMode, Destination, ParserMetadata};
@@ -49,6 +52,7 @@ use script_traits::{TimerEvent, TimerSource, WorkerGlobalScopeInit, WorkerScript
 use servo_rand::random;
 use servo_url::ServoUrl;
 use std::mem::replace;
+use std::rc::Rc;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::thread;
@@ -301,6 +305,7 @@ impl DedicatedWorkerGlobalScope {
         worker_load_origin: WorkerScriptLoadOrigin,
         worker_name: String,
         worker_type: WorkerType,
+        worker_credentials: CredentialsMode,
         closing: Arc<AtomicBool>,
         image_cache: Arc<dyn ImageCache>,
     ) {
@@ -309,6 +314,8 @@ impl DedicatedWorkerGlobalScope {
         let top_level_browsing_context_id = TopLevelBrowsingContextId::installed();
         let current_global = GlobalScope::current().expect("No current global object");
         let origin = current_global.origin().immutable().clone();
+        let csp_list = current_global.get_csp_list();
+        let site_for_cookies = current_global.site_for_cookies();
         let parent = current_global.runtime_handle();
 
         thread::Builder::new()
@@ -330,6 +337,8 @@ impl DedicatedWorkerGlobalScope {
                 } = worker_load_origin;
 
                 let referrer = referrer_url.map(|referrer_url| Referrer::ReferrerUrl(referrer_url));
+                let module_url = worker_url.clone();
+                let module_referrer = referrer.clone();
 
                 let request = RequestBuilder::new(worker_url.clone())
                     .destination(Destination::Worker)
@@ -340,7 +349,9 @@ impl DedicatedWorkerGlobalScope {
                     .pipeline_id(pipeline_id)
                     .referrer(referrer)
                     .referrer_policy(referrer_policy)
-                    .origin(origin);
+                    .origin(origin)
+                    .csp_list(csp_list)
+                    .site_for_cookies(site_for_cookies.clone());
 
                 let runtime = unsafe { new_child_runtime(parent) };
 
@@ -381,27 +392,36 @@ impl DedicatedWorkerGlobalScope {
                 let scope = global.upcast::<WorkerGlobalScope>();
                 let global_scope = global.upcast::<GlobalScope>();
 
-                let (metadata, bytes) = match load_whole_resource(
-                    request,
-                    &global_scope.resource_threads().sender(),
-                    &global_scope,
-                ) {
-                    Err(_) => {
-                        println!("error loading script {}", serialized_worker_url);
-                        parent_sender
-                            .send(CommonScriptMsg::Task(
-                                WorkerEvent,
-                                Box::new(SimpleWorkerErrorHandler::new(worker)),
-                                pipeline_id,
-                                TaskSourceName::DOMManipulation,
-                            ))
-                            .unwrap();
-                        return;
+                let source = match worker_type {
+                    WorkerType::Classic => {
+                        let (metadata, bytes) = match load_whole_resource(
+                            request,
+                            &global_scope.resource_threads().sender(),
+                            &global_scope,
+                        ) {
+                            Err(_) => {
+                                warn!("error loading script {}", serialized_worker_url);
+                                parent_sender
+                                    .send(CommonScriptMsg::Task(
+                                        WorkerEvent,
+                                        Box::new(SimpleWorkerErrorHandler::new(worker)),
+                                        pipeline_id,
+                                        TaskSourceName::DOMManipulation,
+                                    ))
+                                    .unwrap();
+                                return;
+                            },
+                            Ok((metadata, bytes)) => (metadata, bytes),
+                        };
+                        scope.set_url(metadata.final_url);
+                        scope.set_csp_list(metadata.csp_list);
+                        Some(String::from_utf8_lossy(&bytes).into_owned())
                     },
-                    Ok((metadata, bytes)) => (metadata, bytes),
+                    // The module script is fetched with its imports by the
+                    // event loop, and runs once they're all fetched.
+                    WorkerType::Module => None,
                 };
-                scope.set_url(metadata.final_url);
-                let source = String::from_utf8_lossy(&bytes);
+                scope.set_site_for_cookies(site_for_cookies);
 
                 unsafe {
                     // Handle interrupt requests
@@ -414,7 +434,24 @@ impl DedicatedWorkerGlobalScope {
 
                 {
                     let _ar = AutoWorkerReset::new(&global, worker.clone());
-                    scope.execute_script(DOMString::from(source));
+                    match source {
+                        Some(source) => scope.execute_script(DOMString::from(source)),
+                        None => {
+                            let options = ScriptFetchOptions {
+                                credentials_mode: worker_credentials,
+                                referrer_policy,
+                                ..ScriptFetchOptions::default_classic_script()
+                            };
+                            fetch_external_module_script(
+                                global_scope,
+                                ModuleOwner::Worker,
+                                module_url,
+                                Destination::Worker,
+                                module_referrer,
+                                options,
+                            );
+                        },
+                    }
                 }
 
                 let reporter_name = format!("dedicated-worker-reporter-{}", random::<u64>());
@@ -431,6 +468,8 @@ impl DedicatedWorkerGlobalScope {
                             while !scope.is_closing() {
                                 run_worker_event_loop(&*global, Some(&worker));
                             }
+                            scope.upcast::<GlobalScope>().remove_message_ports_router();
+                            scope.upcast::<GlobalScope>().close_indexeddb_connections();
                         },
                         reporter_name,
                         parent_sender,
@@ -440,6 +479,37 @@ impl DedicatedWorkerGlobalScope {
             .expect("Thread spawning failed");
     }
 
+    /// Runs the script of a module worker once its module graph is fetched,
+    /// or reports the failure to fetch it to the `Worker` object.
+    pub fn run_module_worker_script(&self, module: Option<Rc<ModuleTree>>) {
+        let scope = self.upcast::<WorkerGlobalScope>();
+        let module = match module {
+            Some(module) => module,
+            None => {
+                warn!("error loading module script {}", *scope.get_url());
+                let worker = self.worker.borrow().as_ref().unwrap().clone();
+                let pipeline_id = self.upcast::<GlobalScope>().pipeline_id();
+                self.parent_sender
+                    .send(CommonScriptMsg::Task(
+                        WorkerEvent,
+                        Box::new(SimpleWorkerErrorHandler::new(worker)),
+                        Some(pipeline_id),
+                        TaskSourceName::DOMManipulation,
+                    ))
+                    .unwrap();
+                scope.close();
+                return;
+            },
+        };
+        scope.set_url(module.base_url().clone());
+
+        if scope.is_closing() {
+            return;
+        }
+
+        run_module_script(self.upcast(), &module);
+    }
+
     pub fn image_cache(&self) -> Arc<dyn ImageCache> {
         self.image_cache.clone()
     }
@@ -464,11 +534,25 @@ impl DedicatedWorkerGlobalScope {
         match msg {
             WorkerScriptMsg::DOMMessage(data) => {
                 let scope = self.upcast::<WorkerGlobalScope>();
-                let target = self.upcast();
+                let target = self.upcast::<EventTarget>();
                 let _ac = enter_realm(self);
                 rooted!(in(*scope.get_cx()) let mut message = UndefinedValue());
-                data.read(scope.upcast(), message.handle_mut());
-                MessageEvent::dispatch_jsval(target, scope.upcast(), message.handle(), None, None);
+                let ports = match structuredclone::read(scope.upcast(), data, message.handle_mut())
+                {
+                    Ok(ports) => ports,
+                    Err(()) => {
+                        target.fire_event(atom!("messageerror"));
+                        return;
+                    },
+                };
+                MessageEvent::dispatch_jsval(
+                    target,
+                    scope.upcast(),
+                    message.handle(),
+                    None,
+                    None,
+                    ports,
+                );
             },
             WorkerScriptMsg::Common(msg) => {
                 self.upcast::<WorkerGlobalScope>().process_event(msg);
@@ -561,8 +645,13 @@ unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
 
 impl DedicatedWorkerGlobalScopeMethods for DedicatedWorkerGlobalScope {
     // https://html.spec.whatwg.org/multipage/#dom-dedicatedworkerglobalscope-postmessage
-    fn PostMessage(&self, cx: SafeJSContext, message: HandleValue) -> ErrorResult {
-        let data = StructuredCloneData::write(*cx, message)?;
+    fn PostMessage(
+        &self,
+        cx: SafeJSContext,
+        message: HandleValue,
+        transfer: CustomAutoRooterGuard<Option<Vec<*mut JSObject>>>,
+    ) -> ErrorResult {
+        let data = structuredclone::write(cx, message, Some(transfer))?;
         let worker = self.worker.borrow().as_ref().unwrap().clone();
         let pipeline_id = self.upcast::<GlobalScope>().pipeline_id();
         let task = Box::new(task!(post_worker_message: move || {
@@ -126,12 +126,13 @@ use metrics::{
 };
 use mime::{self, Mime};
 use msg::constellation_msg::BrowsingContextId;
-use net_traits::pub_domains::is_pub_domain;
+use net_traits::csp::CspList;
+use net_traits::pub_domains::{is_pub_domain, is_same_site};
 use net_traits::request::RequestBuilder;
 use net_traits::response::HttpsState;
 use net_traits::CookieSource::NonHTTP;
 use net_traits::CoreResourceMsg::{GetCookiesForUrl, SetCookiesForUrl};
-use net_traits::{FetchResponseMsg, IpcSend, ReferrerPolicy};
+use net_traits::{FetchResponseMsg, IpcSend, ReferrerPolicy, SiteForCookies};
 use num_traits::ToPrimitive;
 use percent_encoding::percent_decode;
 use profile_traits::ipc as profile_ipc;
@@ -331,6 +332,8 @@ pub struct Document {
     origin: MutableOrigin,
     ///  https://w3c.github.io/webappsec-referrer-policy/#referrer-policy-states
     referrer_policy: Cell<Option<ReferrerPolicy>>,
+    /// <https://html.spec.whatwg.org/multipage/#concept-document-csp-list>
+    csp_list: DomRefCell<Option<CspList>>,
     /// <https://html.spec.whatwg.org/multipage/#dom-document-referrer>
     referrer: Option<String>,
     /// <https://html.spec.whatwg.org/multipage/#target-element>
@@ -1727,6 +1730,9 @@ impl Document {
         request: RequestBuilder,
         fetch_target: IpcSender<FetchResponseMsg>,
     ) {
+        let request = request
+            .csp_list(self.get_csp_list())
+            .site_for_cookies(self.site_for_cookies());
         let mut loader = self.loader.borrow_mut();
         loader.fetch_async(load, request, fetch_target);
     }
@@ -2754,6 +2760,7 @@ impl Document {
             origin: origin,
             referrer: referrer,
             referrer_policy: Cell::new(referrer_policy),
+            csp_list: DomRefCell::new(None),
             target_element: MutNullableDom::new(None),
             last_click_info: DomRefCell::new(None),
             ignore_destructive_writes_counter: Default::default(),
@@ -2932,7 +2939,12 @@ impl Document {
         let window_size = self.window().window_size();
         let viewport_size = window_size.initial_viewport;
         let device_pixel_ratio = window_size.device_pixel_ratio;
-        Device::new(MediaType::screen(), viewport_size, device_pixel_ratio)
+        Device::new(
+            MediaType::screen(),
+            viewport_size,
+            device_pixel_ratio,
+            window_size.device_features,
+        )
     }
 
     pub fn salvageable(&self) -> bool {
@@ -3042,6 +3054,51 @@ impl Document {
         return self.referrer_policy.get();
     }
 
+    pub fn set_csp_list(&self, csp_list: Option<CspList>) {
+        *self.csp_list.borrow_mut() = csp_list;
+    }
+
+    pub fn get_csp_list(&self) -> Option<CspList> {
+        self.csp_list.borrow().clone()
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#meta-element>
+    pub fn append_csp_list(&self, csp_list: CspList) {
+        let mut current = self.csp_list.borrow_mut();
+        match *current {
+            Some(ref mut current) => current.append(csp_list),
+            None => *current = Some(csp_list),
+        }
+    }
+
+    /// The origin of the top-level document, if every document between it
+    /// and this one is same-site with it.
+    ///
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.2.1>
+    pub fn site_for_cookies(&self) -> SiteForCookies {
+        let mut origins = vec![self.origin().immutable().clone()];
+        if let Some(window_proxy) = self.browsing_context() {
+            let mut ancestor = window_proxy.parent();
+            while let Some(proxy) = ancestor {
+                match proxy.document() {
+                    Some(document) => origins.push(document.origin().immutable().clone()),
+                    // Documents of other sites run in another script thread.
+                    None => return SiteForCookies::Document(None),
+                }
+                ancestor = proxy.parent();
+            }
+        }
+        let top_level_origin = origins.pop().unwrap();
+        if origins
+            .iter()
+            .all(|origin| is_same_site(origin, &top_level_origin))
+        {
+            SiteForCookies::Document(Some(top_level_origin))
+        } else {
+            SiteForCookies::Document(None)
+        }
+    }
+
     pub fn set_target_element(&self, node: Option<&Element>) {
         if let Some(ref element) = self.target_element.get() {
             element.set_target_state(false);
@@ -4168,7 +4225,7 @@ impl DocumentMethods for Document {
             .window
             .upcast::<GlobalScope>()
             .resource_threads()
-            .send(GetCookiesForUrl(url, tx, NonHTTP));
+            .send(GetCookiesForUrl(url, tx, NonHTTP, self.site_for_cookies()));
         let cookies = rx.recv().unwrap();
         Ok(cookies.map_or(DOMString::new(), DOMString::from))
     }
@@ -4193,7 +4250,12 @@ impl DocumentMethods for Document {
             .window
             .upcast::<GlobalScope>()
             .resource_threads()
-            .send(SetCookiesForUrl(self.url(), cookies, NonHTTP));
+            .send(SetCookiesForUrl(
+                self.url(),
+                cookies,
+                NonHTTP,
+                self.site_for_cookies(),
+            ));
         Ok(())
     }
 
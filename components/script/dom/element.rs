@@ -79,6 +79,7 @@ use crate::dom::promise::Promise;
 use crate::dom::raredata::ElementRareData;
 use crate::dom::servoparser::ServoParser;
 use crate::dom::shadowroot::{IsUserAgentWidget, ShadowRoot};
+use crate::dom::svgsvgelement::SVGSVGElement;
 use crate::dom::text::Text;
 use crate::dom::validation::Validatable;
 use crate::dom::virtualmethods::{vtable_for, VirtualMethods};
@@ -96,6 +97,7 @@ use html5ever::{LocalName, Namespace, Prefix, QualName};
 use js::jsapi::Heap;
 use js::jsval::JSVal;
 use msg::constellation_msg::InputMethodType;
+use net_traits::csp::InlineCheckType;
 use net_traits::request::CorsSettings;
 use ref_filter_map::ref_filter_map;
 use script_layout_interface::message::ReflowGoal;
@@ -315,6 +317,67 @@ impl Element {
         }
     }
 
+    /// Restyles the elements anchoring `:has()` selectors that may stop or
+    /// start matching when the children of this element change.
+    fn restyle_relative_selector_anchors(&self) {
+        if !self.upcast::<Node>().is_connected() {
+            return;
+        }
+
+        let has_sibling_anchors = |element: &Element| {
+            element
+                .selector_flags
+                .get()
+                .intersects(ElementSelectorFlags::HAS_SIBLING_RELATIVE_SELECTOR)
+        };
+
+        if has_sibling_anchors(self) {
+            for child in self.upcast::<Node>().children() {
+                if let Some(child) = child.downcast::<Element>() {
+                    child.restyle_if_relative_selector_anchor();
+                }
+            }
+        }
+
+        for ancestor in self
+            .upcast::<Node>()
+            .inclusive_ancestors(ShadowIncluding::No)
+        {
+            let ancestor = match ancestor.downcast::<Element>() {
+                Some(ancestor) => ancestor,
+                None => break,
+            };
+            ancestor.restyle_if_relative_selector_anchor();
+            let parent = ancestor.upcast::<Node>().GetParentElement();
+            if parent.map_or(false, |parent| has_sibling_anchors(&parent)) {
+                for sibling in ancestor.upcast::<Node>().preceding_siblings() {
+                    if let Some(sibling) = sibling.downcast::<Element>() {
+                        sibling.restyle_if_relative_selector_anchor();
+                    }
+                }
+            }
+        }
+    }
+
+    fn restyle_if_relative_selector_anchor(&self) {
+        let flags = self.selector_flags.get();
+        if flags.intersects(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR_NON_SUBJECT) {
+            // Selectors may depend on the anchor matching through any later
+            // combinator.
+            let doc = self.node.owner_doc();
+            for sibling in self.upcast::<Node>().inclusively_following_siblings() {
+                if let Some(sibling) = sibling.downcast::<Element>() {
+                    sibling.restyle(NodeDamage::OtherNodeDamage);
+                    doc.ensure_pending_restyle(sibling)
+                        .hint
+                        .insert(RestyleHint::restyle_subtree());
+                }
+            }
+        } else if flags.intersects(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR) {
+            self.restyle(NodeDamage::OtherNodeDamage);
+        }
+    }
+
     pub fn set_is(&self, is: LocalName) {
         *self.is.borrow_mut() = Some(is);
     }
@@ -525,6 +588,7 @@ pub trait RawLayoutElementHelpers {
         name: &LocalName,
     ) -> Option<&'a str>;
     unsafe fn get_attr_vals_for_layout<'a>(&'a self, name: &LocalName) -> Vec<&'a AttrValue>;
+    unsafe fn get_attrs_for_layout<'a>(&'a self) -> Vec<(LocalName, &'a str)>;
 }
 
 #[inline]
@@ -580,6 +644,18 @@ impl RawLayoutElementHelpers for Element {
             })
             .collect()
     }
+
+    #[inline]
+    unsafe fn get_attrs_for_layout<'a>(&'a self) -> Vec<(LocalName, &'a str)> {
+        let attrs = self.attrs.borrow_for_layout();
+        attrs
+            .iter()
+            .map(|attr| {
+                let attr = attr.to_layout();
+                (attr.local_name_atom_forever(), attr.value_ref_forever())
+            })
+            .collect()
+    }
 }
 
 pub trait LayoutElementHelpers {
@@ -2703,7 +2779,7 @@ impl VirtualMethods for Element {
                             _ => false,
                         };
 
-                        let block = if is_declaration {
+                        if is_declaration {
                             let mut value = AttrValue::String(String::new());
                             attr.swap_value(&mut value);
                             let (serialization, block) = match value {
@@ -2712,18 +2788,26 @@ impl VirtualMethods for Element {
                             };
                             let mut value = AttrValue::String(serialization);
                             attr.swap_value(&mut value);
-                            block
+                            Some(block)
+                        } else if !doc.global().is_inline_allowed(
+                            Some(self),
+                            InlineCheckType::StyleAttribute,
+                            "",
+                            &attr.value(),
+                        ) {
+                            // https://html.spec.whatwg.org/multipage/#the-style-attribute
+                            None
                         } else {
                             let win = window_from_node(self);
-                            Arc::new(doc.style_shared_lock().wrap(parse_style_attribute(
-                                &attr.value(),
-                                &doc.base_url(),
-                                win.css_error_reporter(),
-                                doc.quirks_mode(),
+                            Some(Arc::new(doc.style_shared_lock().wrap(
+                                parse_style_attribute(
+                                    &attr.value(),
+                                    &doc.base_url(),
+                                    win.css_error_reporter(),
+                                    doc.quirks_mode(),
+                                ),
                             )))
-                        };
-
-                        Some(block)
+                        }
                     },
                     AttributeMutation::Removed => None,
                 };
@@ -2779,6 +2863,10 @@ impl VirtualMethods for Element {
             },
         };
 
+        if *self.namespace() == ns!(svg) {
+            SVGSVGElement::dirty_outermost_svg(node);
+        }
+
         // Make sure we rev the version even if we didn't dirty the node. If we
         // don't do this, various attribute-dependent htmlcollections (like those
         // generated by getElementsByClassName) might become stale.
@@ -2891,6 +2979,8 @@ impl VirtualMethods for Element {
                 }
             }
         }
+
+        self.restyle_relative_selector_anchors();
     }
 
     fn adopting_steps(&self, old_doc: &Document) {
@@ -2955,6 +3045,10 @@ impl<'a> SelectorsElement for DomRoot<Element> {
             .next()
     }
 
+    fn first_element_child(&self) -> Option<DomRoot<Element>> {
+        self.node.children().filter_map(DomRoot::downcast).next()
+    }
+
     fn attr_matches(
         &self,
         ns: &NamespaceConstraint<&Namespace>,
@@ -31,6 +31,7 @@ use ipc_channel::router::ROUTER;
 use js::conversions::ToJSValConvertible;
 use js::jsval::UndefinedValue;
 use mime::{self, Mime};
+use net_traits::csp::Violation;
 use net_traits::request::{CacheMode, CorsSettings, CredentialsMode};
 use net_traits::request::{RequestBuilder, RequestMode};
 use net_traits::{CoreResourceMsg, FetchChannels, FetchMetadata};
@@ -237,6 +238,7 @@ impl EventSourceContext {
                 DOMString::from(self.origin.clone()),
                 None,
                 event_source.last_event_id.borrow().clone(),
+                vec![],
             )
         };
         // Step 7
@@ -414,6 +416,11 @@ impl FetchResponseListener for EventSourceContext {
     fn submit_resource_timing(&mut self) {
         network_listener::submit_timing(self)
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        self.resource_timing_global()
+            .report_csp_violations(violations, None);
+    }
 }
 
 impl ResourceTimingListener for EventSourceContext {
@@ -519,6 +526,8 @@ impl EventSource {
         let mut request = RequestBuilder::new(url_record)
             .origin(global.origin().immutable().clone())
             .pipeline_id(Some(global.pipeline_id()))
+            .csp_list(global.get_csp_list())
+            .site_for_cookies(global.site_for_cookies())
             // https://html.spec.whatwg.org/multipage/#create-a-potential-cors-request
             .use_url_credentials(true)
             .mode(RequestMode::CorsMode)
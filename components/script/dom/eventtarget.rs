@@ -39,6 +39,7 @@ use js::jsapi::{JSAutoRealm, JSFunction, JS_GetFunctionObject, SourceText};
 use js::rust::wrappers::CompileFunction;
 use js::rust::{AutoObjectVectorWrapper, CompileOptionsWrapper};
 use libc::c_char;
+use net_traits::csp::InlineCheckType;
 use servo_atoms::Atom;
 use servo_url::ServoUrl;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
@@ -441,6 +442,16 @@ impl EventTarget {
         ty: &str,
         source: DOMString,
     ) {
+        // https://html.spec.whatwg.org/multipage/#event-handler-attributes:concept-element-attributes-change-ext
+        // Step 3.
+        let element = self.downcast::<Element>();
+        if !self
+            .global()
+            .is_inline_allowed(element, InlineCheckType::ScriptAttribute, "", &source)
+        {
+            return;
+        }
+
         let handler = InternalRawUncompiledHandler {
             source: source,
             line: line,
@@ -6,28 +6,35 @@ use crate::dom::bindings::codegen::Bindings::EventBinding::{self, EventMethods};
 use crate::dom::bindings::codegen::Bindings::ExtendableEventBinding;
 use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
 use crate::dom::bindings::inheritance::Castable;
-use crate::dom::bindings::reflector::reflect_dom_object;
-use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot};
 use crate::dom::bindings::str::DOMString;
 use crate::dom::event::Event;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
 use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
-use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
+use js::jsapi::JSContext;
 use js::rust::HandleValue;
 use servo_atoms::Atom;
+use std::cell::Cell;
 
 // https://w3c.github.io/ServiceWorker/#extendable-event
 #[dom_struct]
 pub struct ExtendableEvent {
     event: Event,
-    extensions_allowed: bool,
+    /// <https://w3c.github.io/ServiceWorker/#extendableevent-pending-promises-count>
+    pending_promises: Cell<u32>,
+    /// Whether one of the extend lifetime promises was rejected.
+    rejected: Cell<bool>,
 }
 
 impl ExtendableEvent {
     pub fn new_inherited() -> ExtendableEvent {
         ExtendableEvent {
             event: Event::new_inherited(),
-            extensions_allowed: true,
+            pending_promises: Cell::new(0),
+            rejected: Cell::new(false),
         }
     }
     pub fn new(
@@ -61,14 +68,57 @@ impl ExtendableEvent {
         ))
     }
 
+    // https://w3c.github.io/ServiceWorker/#extendableevent-active
+    pub fn is_active(&self) -> bool {
+        self.event.dispatching() || self.pending_promises.get() > 0
+    }
+
+    /// Fire the event at the service worker, which gets notified once all the promises
+    /// extending the lifetime of the event have settled.
+    pub fn fire(&self, worker: &ServiceWorkerGlobalScope) {
+        self.event.fire(worker.upcast());
+        self.settle_if_done();
+    }
+
+    // https://w3c.github.io/ServiceWorker/#extendableevent-add-lifetime-promise
+    #[allow(unrooted_must_root)]
+    pub fn add_lifetime_promise(&self, promise: &Promise) {
+        self.pending_promises.set(self.pending_promises.get() + 1);
+        let handler = PromiseNativeHandler::new(
+            &self.global(),
+            Some(Box::new(LifetimePromiseHandler {
+                event: Dom::from_ref(self),
+                rejected: false,
+            })),
+            Some(Box::new(LifetimePromiseHandler {
+                event: Dom::from_ref(self),
+                rejected: true,
+            })),
+        );
+        promise.append_native_handler(&handler);
+    }
+
+    fn settle_if_done(&self) {
+        if self.is_active() {
+            return;
+        }
+        if let Some(worker) = self.global().downcast::<ServiceWorkerGlobalScope>() {
+            worker.lifetime_promises_settled(&self.event.type_(), self.rejected.get());
+        }
+    }
+
     // https://w3c.github.io/ServiceWorker/#wait-until-method
-    pub fn WaitUntil(&self, _cx: JSContext, _val: HandleValue) -> ErrorResult {
+    pub fn WaitUntil(&self, promise: &Promise) -> ErrorResult {
         // Step 1
-        if !self.extensions_allowed {
+        if !self.event.IsTrusted() {
             return Err(Error::InvalidState);
         }
         // Step 2
-        // TODO add a extended_promises array to enqueue the `val`
+        if !self.is_active() {
+            return Err(Error::InvalidState);
+        }
+        // Step 3
+        self.add_lifetime_promise(promise);
         Ok(())
     }
 
@@ -85,3 +135,22 @@ impl Default for ExtendableEventBinding::ExtendableEventInit {
         }
     }
 }
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct LifetimePromiseHandler {
+    event: Dom<ExtendableEvent>,
+    rejected: bool,
+}
+
+impl Callback for LifetimePromiseHandler {
+    // https://w3c.github.io/ServiceWorker/#extendableevent-add-lifetime-promise
+    fn callback(&self, _cx: *mut JSContext, _v: HandleValue) {
+        if self.rejected {
+            self.event.rejected.set(true);
+        }
+        let pending_promises = self.event.pending_promises.get() - 1;
+        self.event.pending_promises.set(pending_promises);
+        self.event.settle_if_done();
+    }
+}
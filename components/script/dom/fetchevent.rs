@@ -0,0 +1,260 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use crate::dom::bindings::codegen::Bindings::FetchEventBinding;
+use crate::dom::bindings::codegen::Bindings::FetchEventBinding::FetchEventMethods;
+use crate::dom::bindings::codegen::Bindings::ResponseBinding::{
+    ResponseMethods, ResponseType as DOMResponseType,
+};
+use crate::dom::bindings::conversions::root_from_handlevalue;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::event::{Event, EventStatus};
+use crate::dom::extendableevent::ExtendableEvent;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::promise::Promise;
+use crate::dom::promisenativehandler::{Callback, PromiseNativeHandler};
+use crate::dom::request::Request;
+use crate::dom::response::Response;
+use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
+use dom_struct::dom_struct;
+use http::header::HeaderMap;
+use hyper::StatusCode;
+use ipc_channel::ipc::IpcSender;
+use js::jsapi::JSContext;
+use js::rust::HandleValue;
+use js::typedarray::ArrayBuffer;
+use net_traits::{CustomResponse, NetworkError};
+use servo_atoms::Atom;
+use std::cell::Cell;
+
+type ResponseSender = IpcSender<Option<Result<CustomResponse, NetworkError>>>;
+
+// https://w3c.github.io/ServiceWorker/#fetchevent-interface
+#[dom_struct]
+pub struct FetchEvent {
+    event: ExtendableEvent,
+    request: Dom<Request>,
+    /// <https://w3c.github.io/ServiceWorker/#fetchevent-respond-with-entered-flag>
+    respond_with_entered: Cell<bool>,
+    /// Where the response to the intercepted request is sent, `None` if the event
+    /// was constructed by script.
+    #[ignore_malloc_size_of = "Defined in ipc-channel"]
+    response_chan: DomRefCell<Option<ResponseSender>>,
+}
+
+impl FetchEvent {
+    fn new_inherited(request: &Request, response_chan: Option<ResponseSender>) -> FetchEvent {
+        FetchEvent {
+            event: ExtendableEvent::new_inherited(),
+            request: Dom::from_ref(request),
+            respond_with_entered: Cell::new(false),
+            response_chan: DomRefCell::new(response_chan),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        type_: Atom,
+        bubbles: bool,
+        cancelable: bool,
+        request: &Request,
+        response_chan: Option<ResponseSender>,
+    ) -> DomRoot<FetchEvent> {
+        let ev = reflect_dom_object(
+            Box::new(FetchEvent::new_inherited(request, response_chan)),
+            global,
+            FetchEventBinding::Wrap,
+        );
+        {
+            let event = ev.upcast::<Event>();
+            event.init_event(type_, bubbles, cancelable);
+        }
+        ev
+    }
+
+    pub fn Constructor(
+        worker: &ServiceWorkerGlobalScope,
+        type_: DOMString,
+        init: &FetchEventBinding::FetchEventInit,
+    ) -> Fallible<DomRoot<FetchEvent>> {
+        Ok(FetchEvent::new(
+            worker.upcast(),
+            Atom::from(type_),
+            init.parent.parent.bubbles,
+            init.parent.parent.cancelable,
+            &init.request,
+            None,
+        ))
+    }
+
+    /// Fire a fetch event for an intercepted request at the service worker.
+    /// <https://w3c.github.io/ServiceWorker/#handle-fetch>
+    pub fn dispatch(worker: &ServiceWorkerGlobalScope, request: &Request, sender: ResponseSender) {
+        let event = FetchEvent::new(
+            worker.upcast(),
+            atom!("fetch"),
+            false,
+            true,
+            request,
+            Some(sender),
+        );
+        let status = event.upcast::<Event>().fire(worker.upcast());
+        if event.respond_with_entered.get() {
+            return;
+        }
+        // Without a call to respondWith, the request goes to the network, unless the
+        // event was canceled.
+        match status {
+            EventStatus::Canceled => event.respond(network_error("The fetch event was canceled")),
+            EventStatus::NotCanceled => event.respond(None),
+        }
+    }
+
+    fn respond(&self, result: Option<Result<CustomResponse, NetworkError>>) {
+        if let Some(sender) = self.response_chan.borrow_mut().take() {
+            let _ = sender.send(result);
+        }
+    }
+}
+
+impl FetchEventMethods for FetchEvent {
+    // https://w3c.github.io/ServiceWorker/#fetch-event-request
+    fn Request(&self) -> DomRoot<Request> {
+        DomRoot::from_ref(&*self.request)
+    }
+
+    // https://w3c.github.io/ServiceWorker/#fetch-event-respondwith
+    #[allow(unrooted_must_root)]
+    fn RespondWith(&self, promise: &Promise) -> ErrorResult {
+        let event = self.upcast::<Event>();
+        // Step 1
+        if !event.dispatching() {
+            return Err(Error::InvalidState);
+        }
+        // Step 2
+        if self.respond_with_entered.get() {
+            return Err(Error::InvalidState);
+        }
+        // Step 3
+        self.event.add_lifetime_promise(promise);
+        // Step 4
+        event.StopImmediatePropagation();
+        // Steps 5-6
+        self.respond_with_entered.set(true);
+        // Step 8
+        let handler = PromiseNativeHandler::new(
+            &self.global(),
+            Some(Box::new(RespondWithHandler {
+                event: Dom::from_ref(self),
+                fulfilled: true,
+            })),
+            Some(Box::new(RespondWithHandler {
+                event: Dom::from_ref(self),
+                fulfilled: false,
+            })),
+        );
+        promise.append_native_handler(&handler);
+        Ok(())
+    }
+
+    // https://dom.spec.whatwg.org/#dom-event-istrusted
+    fn IsTrusted(&self) -> bool {
+        self.event.IsTrusted()
+    }
+}
+
+fn network_error(message: &str) -> Option<Result<CustomResponse, NetworkError>> {
+    Some(Err(NetworkError::Internal(message.to_owned())))
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct RespondWithHandler {
+    event: Dom<FetchEvent>,
+    fulfilled: bool,
+}
+
+impl Callback for RespondWithHandler {
+    // https://w3c.github.io/ServiceWorker/#fetch-event-respondwith
+    #[allow(unrooted_must_root)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        // Step 8.1
+        if !self.fulfilled {
+            return self
+                .event
+                .respond(network_error("The respondWith promise was rejected"));
+        }
+        // Step 9.1
+        let response = match root_from_handlevalue::<Response>(v, cx) {
+            Ok(response) => response,
+            Err(()) => {
+                return self
+                    .event
+                    .respond(network_error("respondWith was not given a Response"));
+            },
+        };
+        // Step 9.3
+        if response.BodyUsed() {
+            return self
+                .event
+                .respond(network_error("The response's body was already used"));
+        }
+        let status = match StatusCode::from_u16(response.Status()) {
+            Ok(status) if response.Type() != DOMResponseType::Error => status,
+            _ => {
+                return self
+                    .event
+                    .respond(network_error("The response is an error"))
+            },
+        };
+        // Steps 9.4-9.7, reading the whole body once it's available.
+        let body_handler = PromiseNativeHandler::new(
+            &self.event.global(),
+            Some(Box::new(ResponseBodyHandler {
+                event: Dom::from_ref(&*self.event),
+                headers: response.Headers().get_headers_list(),
+                status: status,
+                status_text: String::from_utf8_lossy(&response.StatusText()).into_owned(),
+            })),
+            Some(Box::new(RespondWithHandler {
+                event: Dom::from_ref(&*self.event),
+                fulfilled: false,
+            })),
+        );
+        response.ArrayBuffer().append_native_handler(&body_handler);
+    }
+}
+
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+struct ResponseBodyHandler {
+    event: Dom<FetchEvent>,
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    headers: HeaderMap,
+    #[ignore_malloc_size_of = "Defined in hyper"]
+    status: StatusCode,
+    status_text: String,
+}
+
+impl Callback for ResponseBodyHandler {
+    #[allow(unsafe_code)]
+    fn callback(&self, cx: *mut JSContext, v: HandleValue) {
+        let body = unsafe {
+            typedarray!(in(cx) let buffer: ArrayBuffer = v.get().to_object());
+            buffer.map(|buffer| buffer.to_vec()).unwrap_or_default()
+        };
+        let response = CustomResponse::new(
+            self.headers.clone(),
+            (self.status, self.status_text.clone()),
+            body,
+        );
+        self.event.respond(Some(Ok(response)));
+    }
+}
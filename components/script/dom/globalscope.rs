@@ -3,44 +3,59 @@
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
 use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventInit;
 use crate::dom::bindings::codegen::Bindings::EventSourceBinding::EventSourceBinding::EventSourceMethods;
+use crate::dom::bindings::codegen::Bindings::SecurityPolicyViolationEventBinding::{
+    SecurityPolicyViolationEventDisposition, SecurityPolicyViolationEventInit,
+};
 use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
 use crate::dom::bindings::codegen::Bindings::WorkerGlobalScopeBinding::WorkerGlobalScopeMethods;
 use crate::dom::bindings::conversions::{root_from_object, root_from_object_static};
 use crate::dom::bindings::error::{report_pending_exception, ErrorInfo};
 use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::DomObject;
-use crate::dom::bindings::root::{DomRoot, MutNullableDom};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::settings_stack::{entry_global, incumbent_global, AutoEntryScript};
-use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::str::{DOMString, USVString};
 use crate::dom::bindings::weakref::DOMTracker;
+use crate::dom::console::Console;
 use crate::dom::crypto::Crypto;
 use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
+use crate::dom::element::Element;
 use crate::dom::errorevent::ErrorEvent;
 use crate::dom::event::{Event, EventBubbles, EventCancelable, EventStatus};
 use crate::dom::eventsource::EventSource;
 use crate::dom::eventtarget::EventTarget;
+use crate::dom::idbfactory::IDBFactory;
+use crate::dom::messageport::MessagePort;
+use crate::dom::node::Node;
 use crate::dom::paintworkletglobalscope::PaintWorkletGlobalScope;
 use crate::dom::performance::Performance;
+use crate::dom::securitypolicyviolationevent::SecurityPolicyViolationEvent;
 use crate::dom::window::Window;
 use crate::dom::workerglobalscope::WorkerGlobalScope;
 use crate::dom::workletglobalscope::WorkletGlobalScope;
 use crate::microtask::{Microtask, MicrotaskQueue};
+use crate::script_module::{ModuleMap, ModuleTree};
 use crate::script_runtime::{CommonScriptMsg, JSContext as SafeJSContext, ScriptChan, ScriptPort};
 use crate::script_thread::{MainThreadScriptChan, ScriptThread};
 use crate::task::TaskCanceller;
+use crate::task_source::database_access::DatabaseAccessTaskSource;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::performance_timeline::PerformanceTimelineTaskSource;
+use crate::task_source::port_message::PortMessageQueue;
 use crate::task_source::remote_event::RemoteEventTaskSource;
 use crate::task_source::websocket::WebsocketTaskSource;
-use crate::task_source::TaskSourceName;
+use crate::task_source::{TaskSource, TaskSourceName};
 use crate::timers::{IsInterval, OneshotTimerCallback, OneshotTimerHandle};
 use crate::timers::{OneshotTimers, TimerCallback};
 use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
 use dom_struct::dom_struct;
-use ipc_channel::ipc::IpcSender;
+use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::router::ROUTER;
 use js::glue::{IsWrapper, UnwrapObjectDynamic};
 use js::jsapi::JSObject;
 use js::jsapi::{CurrentGlobalOrNull, GetNonCCWObjectGlobal};
@@ -51,17 +66,20 @@ use js::rust::wrappers::EvaluateUtf8;
 use js::rust::{get_object_class, CompileOptionsWrapper, ParentRuntime, Runtime};
 use js::rust::{HandleValue, MutableHandleValue};
 use js::{JSCLASS_IS_DOMJSCLASS, JSCLASS_IS_GLOBAL};
-use msg::constellation_msg::PipelineId;
+use msg::constellation_msg::{MessagePortId, MessagePortRouterId, PipelineId};
+use net_traits::csp::{CheckResult, CspList, InlineCheckType, NavigationCheckType};
+use net_traits::csp::{PolicyDisposition, Violation};
 use net_traits::image_cache::ImageCache;
-use net_traits::{CoreResourceThread, IpcSend, ResourceThreads};
+use net_traits::{CoreResourceThread, IpcSend, ResourceThreads, SiteForCookies};
 use profile_traits::{mem as profile_mem, time as profile_time};
-use script_traits::{MsDuration, ScriptToConstellationChan, TimerEvent};
+use script_traits::transferable::{MessagePortMsg, PortMessageTask};
+use script_traits::{MsDuration, ScriptMsg, ScriptToConstellationChan, TimerEvent};
 use script_traits::{TimerEventId, TimerSchedulerMsg, TimerSource};
 use servo_url::{MutableOrigin, ServoUrl};
 use std::borrow::Cow;
 use std::cell::Cell;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::ffi::CString;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -77,12 +95,29 @@ impl Drop for AutoCloseWorker {
     }
 }
 
+/// The message ports managed by a global.
+#[derive(JSTraceable, MallocSizeOf)]
+#[must_root]
+pub enum MessagePortState {
+    /// The router receiving the tasks for the ports of the global, and the ports.
+    Managed(
+        MessagePortRouterId,
+        HashMap<MessagePortId, Dom<MessagePort>>,
+    ),
+    /// The global doesn't manage any message port yet.
+    UnManaged,
+}
+
 #[dom_struct]
 pub struct GlobalScope {
     eventtarget: EventTarget,
     crypto: MutNullableDom<Crypto>,
+    indexeddb: MutNullableDom<IDBFactory>,
     next_worker_id: Cell<WorkerId>,
 
+    /// The message ports of this global.
+    message_port_state: DomRefCell<MessagePortState>,
+
     /// Pipeline id associated with this global.
     pipeline_id: PipelineId,
 
@@ -164,6 +199,15 @@ pub struct GlobalScope {
 
     /// An optional string allowing the user agent to be set for testing.
     user_agent: Cow<'static, str>,
+
+    /// <https://html.spec.whatwg.org/multipage/#module-map>
+    #[ignore_malloc_size_of = "mozjs"]
+    module_map: DomRefCell<ModuleMap>,
+
+    /// The inline module scripts of this global, kept alive since the
+    /// functions they define may still call `import()` after they ran.
+    #[ignore_malloc_size_of = "mozjs"]
+    inline_module_scripts: DomRefCell<Vec<Rc<ModuleTree>>>,
 }
 
 impl GlobalScope {
@@ -184,7 +228,9 @@ impl GlobalScope {
         Self {
             eventtarget: EventTarget::new_inherited(),
             crypto: Default::default(),
+            indexeddb: Default::default(),
             next_worker_id: Cell::new(WorkerId(0)),
+            message_port_state: DomRefCell::new(MessagePortState::UnManaged),
             pipeline_id,
             devtools_wants_updates: Default::default(),
             console_timers: DomRefCell::new(Default::default()),
@@ -204,6 +250,8 @@ impl GlobalScope {
             consumed_rejections: Default::default(),
             is_headless,
             user_agent,
+            module_map: DomRefCell::new(HashMap::new()),
+            inline_module_scripts: DomRefCell::new(vec![]),
         }
     }
 
@@ -213,6 +261,151 @@ impl GlobalScope {
             .push(AutoCloseWorker(closing_worker));
     }
 
+    /// Start managing a message port created in this global, or transferred to it.
+    pub fn track_message_port(&self, port: &MessagePort, transferred: bool) {
+        let mut state = self.message_port_state.borrow_mut();
+        if let MessagePortState::UnManaged = *state {
+            *state = MessagePortState::Managed(self.new_message_port_router(), HashMap::new());
+        }
+        if let MessagePortState::Managed(router_id, ref mut ports) = *state {
+            let port_id = *port.message_port_id();
+            ports.insert(port_id, Dom::from_ref(port));
+            let msg = if transferred {
+                ScriptMsg::CompleteMessagePortTransfer(router_id, port_id)
+            } else {
+                ScriptMsg::NewMessagePort(router_id, port_id)
+            };
+            let _ = self.script_to_constellation_chan().send(msg);
+        }
+    }
+
+    /// Set up the router receiving the tasks for the ports managed by this global.
+    fn new_message_port_router(&self) -> MessagePortRouterId {
+        let router_id = MessagePortRouterId::new();
+        let (sender, receiver) = ipc::channel().expect("ipc channel failure");
+        let this = Trusted::new(self);
+        let task_source = self.port_message_queue();
+        let canceller = self.task_canceller(PortMessageQueue::NAME);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let this = this.clone();
+                let _ = match message.to() {
+                    Ok(MessagePortMsg::CompleteTransfer(port_id, tasks)) => task_source
+                        .queue_with_canceller(
+                            task!(complete_port_transfer: move || {
+                                this.root().complete_port_transfer(port_id, tasks);
+                            }),
+                            &canceller,
+                        ),
+                    Ok(MessagePortMsg::NewTask(port_id, task)) => task_source.queue_with_canceller(
+                        task!(route_task_to_port: move || {
+                            this.root().route_task_to_port(port_id, task);
+                        }),
+                        &canceller,
+                    ),
+                    Err(e) => return warn!("Error receiving a MessagePortMsg: {:?}", e),
+                };
+            }),
+        );
+        let _ = self
+            .script_to_constellation_chan()
+            .send(ScriptMsg::NewMessagePortRouter(router_id, sender));
+        router_id
+    }
+
+    fn managed_message_port(&self, port_id: &MessagePortId) -> Option<DomRoot<MessagePort>> {
+        match *self.message_port_state.borrow() {
+            MessagePortState::Managed(_, ref ports) => {
+                ports.get(port_id).map(|port| DomRoot::from_ref(&**port))
+            },
+            MessagePortState::UnManaged => None,
+        }
+    }
+
+    /// Stop managing a message port, because it was transferred out of this global
+    /// or closed.
+    pub fn untrack_message_port(&self, port_id: &MessagePortId, transferred: bool) {
+        if let MessagePortState::Managed(_, ref mut ports) = *self.message_port_state.borrow_mut() {
+            if ports.remove(port_id).is_none() {
+                return;
+            }
+        }
+        let msg = if transferred {
+            ScriptMsg::MessagePortShipped(*port_id)
+        } else {
+            ScriptMsg::RemoveMessagePort(*port_id)
+        };
+        let _ = self.script_to_constellation_chan().send(msg);
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#entangle>
+    pub fn entangle_ports(&self, port1: &MessagePort, port2: &MessagePort) {
+        port1.entangle(*port2.message_port_id());
+        port2.entangle(*port1.message_port_id());
+        let _ = self
+            .script_to_constellation_chan()
+            .send(ScriptMsg::EntanglePorts(
+                *port1.message_port_id(),
+                *port2.message_port_id(),
+            ));
+    }
+
+    /// Send a task to a message port, directly if it's managed by this global,
+    /// or through the constellation otherwise.
+    pub fn post_messageport_msg(&self, port_id: MessagePortId, task: PortMessageTask) {
+        if self.managed_message_port(&port_id).is_none() {
+            let _ = self
+                .script_to_constellation_chan()
+                .send(ScriptMsg::RerouteMessagePort(port_id, task));
+            return;
+        }
+        let this = Trusted::new(self);
+        let _ = self.port_message_queue().queue(
+            task!(post_message_to_local_port: move || {
+                this.root().route_task_to_port(port_id, task);
+            }),
+            self,
+        );
+    }
+
+    /// Hand a task to a port managed by this global, or send it back to the
+    /// constellation if the port was transferred out of this global since.
+    pub fn route_task_to_port(&self, port_id: MessagePortId, task: PortMessageTask) {
+        match self.managed_message_port(&port_id) {
+            Some(port) => port.handle_incoming(task),
+            None => {
+                let _ = self
+                    .script_to_constellation_chan()
+                    .send(ScriptMsg::RerouteMessagePort(port_id, task));
+            },
+        }
+    }
+
+    /// Complete the transfer of a port to this global, with the tasks which were sent
+    /// to it while it was being transferred.
+    pub fn complete_port_transfer(&self, port_id: MessagePortId, tasks: VecDeque<PortMessageTask>) {
+        match self.managed_message_port(&port_id) {
+            Some(port) => port.complete_transfer(tasks),
+            // The port was transferred again before its first transfer completed.
+            None => {
+                for task in tasks {
+                    self.route_task_to_port(port_id, task);
+                }
+            },
+        }
+    }
+
+    /// Stop routing tasks to the message ports of this global, which is going away.
+    pub fn remove_message_ports_router(&self) {
+        if let MessagePortState::Managed(router_id, _) = *self.message_port_state.borrow() {
+            let _ = self
+                .script_to_constellation_chan()
+                .send(ScriptMsg::RemoveMessagePortRouter(router_id));
+        }
+        *self.message_port_state.borrow_mut() = MessagePortState::UnManaged;
+    }
+
     pub fn track_event_source(&self, event_source: &EventSource) {
         self.event_source_tracker.track(event_source);
     }
@@ -310,6 +503,15 @@ impl GlobalScope {
         &self.consumed_rejections
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#module-map>
+    pub fn module_map(&self) -> &DomRefCell<ModuleMap> {
+        &self.module_map
+    }
+
+    pub fn add_inline_module_script(&self, module: Rc<ModuleTree>) {
+        self.inline_module_scripts.borrow_mut().push(module);
+    }
+
     #[allow(unsafe_code)]
     pub fn get_cx(&self) -> SafeJSContext {
         unsafe { SafeJSContext::from_ptr(Runtime::get()) }
@@ -319,6 +521,17 @@ impl GlobalScope {
         self.crypto.or_init(|| Crypto::new(self))
     }
 
+    pub fn indexeddb(&self) -> DomRoot<IDBFactory> {
+        self.indexeddb.or_init(|| IDBFactory::new(self))
+    }
+
+    /// Close the IndexedDB connections opened in this global, which is going away.
+    pub fn close_indexeddb_connections(&self) {
+        if let Some(factory) = self.indexeddb.get() {
+            factory.close_all_connections();
+        }
+    }
+
     /// Get next worker id.
     pub fn get_next_worker_id(&self) -> WorkerId {
         let worker_id = self.next_worker_id.get();
@@ -539,6 +752,30 @@ impl GlobalScope {
         unreachable!();
     }
 
+    /// `ScriptChan` to send messages to the database access task source of
+    /// this global scope.
+    pub fn database_access_task_source(&self) -> DatabaseAccessTaskSource {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.task_manager().database_access_task_source();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.database_access_task_source();
+        }
+        unreachable!();
+    }
+
+    /// `ScriptChan` to send messages to the port message queue of
+    /// this global scope.
+    pub fn port_message_queue(&self) -> PortMessageQueue {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.task_manager().port_message_queue();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.port_message_queue();
+        }
+        unreachable!();
+    }
+
     /// Evaluate JS code on this global scope.
     pub fn evaluate_js_on_global_with_result(&self, code: &str, rval: MutableHandleValue) -> bool {
         self.evaluate_script_on_global_with_result(code, "", rval, 1)
@@ -812,6 +1049,143 @@ impl GlobalScope {
     pub fn get_user_agent(&self) -> Cow<'static, str> {
         self.user_agent.clone()
     }
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-settings-object-csp-list>
+    pub fn get_csp_list(&self) -> Option<CspList> {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.Document().get_csp_list();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.get_csp_list();
+        }
+        None
+    }
+
+    /// <https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.2>
+    pub fn site_for_cookies(&self) -> SiteForCookies {
+        if let Some(window) = self.downcast::<Window>() {
+            return window.Document().site_for_cookies();
+        }
+        if let Some(worker) = self.downcast::<WorkerGlobalScope>() {
+            return worker.site_for_cookies();
+        }
+        SiteForCookies::Document(Some(self.origin().immutable().clone()))
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#can-compile-strings>
+    pub fn is_js_evaluation_allowed(&self) -> bool {
+        let csp_list = match self.get_csp_list() {
+            Some(csp_list) => csp_list,
+            None => return true,
+        };
+        let (check_result, violations) = csp_list.is_js_evaluation_allowed();
+        self.report_csp_violations(violations, None);
+        check_result == CheckResult::Allowed
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-inline>
+    pub fn is_inline_allowed(
+        &self,
+        element: Option<&Element>,
+        check_type: InlineCheckType,
+        nonce: &str,
+        source: &str,
+    ) -> bool {
+        let csp_list = match self.get_csp_list() {
+            Some(csp_list) => csp_list,
+            None => return true,
+        };
+        let (check_result, violations) =
+            csp_list.should_inline_be_blocked(check_type, nonce, source);
+        self.report_csp_violations(violations, element);
+        check_result == CheckResult::Allowed
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#should-block-navigation-request>
+    pub fn is_navigation_allowed(
+        &self,
+        element: Option<&Element>,
+        url: &ServoUrl,
+        check_type: NavigationCheckType,
+    ) -> bool {
+        let csp_list = match self.get_csp_list() {
+            Some(csp_list) => csp_list,
+            None => return true,
+        };
+        let (check_result, violations) = csp_list.should_navigation_request_be_blocked(
+            url,
+            self.origin().immutable(),
+            check_type,
+        );
+        self.report_csp_violations(violations, element);
+        check_result == CheckResult::Allowed
+    }
+
+    /// <https://w3c.github.io/webappsec-csp/#report-violation>
+    pub fn report_csp_violations(&self, violations: Vec<Violation>, element: Option<&Element>) {
+        for violation in violations {
+            let report_only = match violation.disposition {
+                PolicyDisposition::Enforce => "",
+                PolicyDisposition::Report => " (report only)",
+            };
+            let blocked_uri = violation.resource.blocked_uri();
+            Console::Warn(
+                self,
+                vec![DOMString::from(format!(
+                    "Content Security Policy{}: refused to load {} because it violates \
+                     the \"{}\" directive of the policy \"{}\"",
+                    report_only, blocked_uri, violation.directive, violation.policy
+                ))],
+            );
+
+            // Step 3. Violations are dispatched to the element if it is connected,
+            // and otherwise to the document or the worker global.
+            let target = match element {
+                Some(element) if element.upcast::<Node>().is_connected() => {
+                    Trusted::new(element.upcast::<EventTarget>())
+                },
+                _ => match self.downcast::<Window>() {
+                    Some(window) => Trusted::new(window.Document().upcast::<EventTarget>()),
+                    None => Trusted::new(self.upcast::<EventTarget>()),
+                },
+            };
+            let document_uri = self.get_url().into_string();
+            let task = task!(fire_security_policy_violation_event: move || {
+                let target = target.root();
+                let global = target.global();
+                let init = SecurityPolicyViolationEventInit {
+                    parent: EventInit {
+                        bubbles: true,
+                        cancelable: false,
+                    },
+                    documentURI: USVString(document_uri),
+                    referrer: USVString(String::new()),
+                    blockedURI: USVString(blocked_uri),
+                    effectiveDirective: DOMString::from(violation.directive.clone()),
+                    violatedDirective: DOMString::from(violation.directive),
+                    originalPolicy: DOMString::from(violation.policy),
+                    sourceFile: USVString(String::new()),
+                    sample: DOMString::from(violation.sample),
+                    disposition: match violation.disposition {
+                        PolicyDisposition::Enforce => SecurityPolicyViolationEventDisposition::Enforce,
+                        PolicyDisposition::Report => SecurityPolicyViolationEventDisposition::Report,
+                    },
+                    statusCode: 0,
+                    lineNumber: 0,
+                    columnNumber: 0,
+                };
+                let event = SecurityPolicyViolationEvent::new(
+                    &global,
+                    atom!("securitypolicyviolation"),
+                    EventBubbles::Bubbles,
+                    EventCancelable::NotCancelable,
+                    &init,
+                );
+                event.upcast::<Event>().fire(&target);
+            });
+            let _ = self.dom_manipulation_task_source().queue(task, self);
+        }
+    }
 }
 
 fn timestamp_in_ms(time: Timespec) -> u64 {
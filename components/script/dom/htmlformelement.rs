@@ -55,6 +55,7 @@ use headers::{ContentType, HeaderMapExt};
 use html5ever::{LocalName, Prefix};
 use hyper::Method;
 use mime::{self, Mime};
+use net_traits::csp::NavigationCheckType;
 use net_traits::http_percent_encode;
 use net_traits::request::Referrer;
 use script_traits::{HistoryEntryReplacement, LoadData, LoadOrigin};
@@ -379,6 +380,14 @@ impl HTMLFormElement {
             Ok(url) => url,
             Err(_) => return,
         };
+        // https://w3c.github.io/webappsec-csp/#directive-form-action
+        if !doc.global().is_navigation_allowed(
+            Some(self.upcast()),
+            &action_components,
+            NavigationCheckType::FormSubmission,
+        ) {
+            return;
+        }
         // Step 15-17
         let scheme = action_components.scheme().to_owned();
         let enctype = submitter.enctype();
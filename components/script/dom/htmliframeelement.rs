@@ -32,6 +32,7 @@ use euclid::Size2D;
 use html5ever::{LocalName, Prefix};
 use ipc_channel::ipc;
 use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
+use net_traits::csp::{InlineCheckType, NavigationCheckType};
 use net_traits::request::Referrer;
 use profile_traits::ipc as ProfiledIpc;
 use script_layout_interface::message::ReflowGoal;
@@ -131,6 +132,18 @@ impl HTMLIFrameElement {
 
         let document = document_from_node(self);
 
+        // https://w3c.github.io/webappsec-csp/#should-block-navigation-request
+        if nav_type == NavigationType::Regular &&
+            load_data.url.scheme() != "javascript" &&
+            !document.global().is_navigation_allowed(
+                Some(self.upcast()),
+                &load_data.url,
+                NavigationCheckType::Frame,
+            )
+        {
+            return;
+        }
+
         let mut load_blocker = self.load_blocker.borrow_mut();
         // Any oustanding load is finished from the point of view of the blocked
         // document; the new navigation will continue blocking it.
@@ -140,8 +153,19 @@ impl HTMLIFrameElement {
             let window_proxy = self.GetContentWindow();
             if let Some(window_proxy) = window_proxy {
                 // Important re security. See https://github.com/servo/servo/issues/23373
-                // TODO: check according to https://w3c.github.io/webappsec-csp/#should-block-navigation-request
-                if ScriptThread::check_load_origin(&load_data.load_origin, &document.url().origin())
+                // https://w3c.github.io/webappsec-csp/#should-block-navigation-request
+                let url = load_data.url.as_str().to_owned();
+                let allowed_by_csp = document.global().is_inline_allowed(
+                    Some(self.upcast()),
+                    InlineCheckType::Navigation,
+                    "",
+                    &url,
+                );
+                if allowed_by_csp &&
+                    ScriptThread::check_load_origin(
+                        &load_data.load_origin,
+                        &document.url().origin(),
+                    )
                 {
                     ScriptThread::eval_js_url(&window_proxy.global(), &mut load_data);
                 }
@@ -204,6 +228,7 @@ impl HTMLIFrameElement {
                             Size2D::new(rect.size.width.to_f32_px(), rect.size.height.to_f32_px())
                         },
                         device_pixel_ratio: window.device_pixel_ratio(),
+                        device_features: window.window_size().device_features,
                     },
                 };
 
@@ -72,6 +72,7 @@ use http::header::{self, HeaderMap, HeaderValue};
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use media::{glplayer_channel, GLPlayerMsg, GLPlayerMsgForward};
+use net_traits::csp::Violation;
 use net_traits::image::base::Image;
 use net_traits::image_cache::ImageResponse;
 use net_traits::request::{CredentialsMode, Destination, Referrer, RequestBuilder, RequestMode};
@@ -835,7 +836,9 @@ impl HTMLMediaElement {
             .origin(document.origin().immutable().clone())
             .pipeline_id(Some(self.global().pipeline_id()))
             .referrer(Some(Referrer::ReferrerUrl(document.url())))
-            .referrer_policy(document.get_referrer_policy());
+            .referrer_policy(document.get_referrer_policy())
+            .csp_list(document.get_csp_list())
+            .site_for_cookies(document.site_for_cookies());
 
         let mut current_fetch_context = self.current_fetch_context.borrow_mut();
         if let Some(ref mut current_fetch_context) = *current_fetch_context {
@@ -2706,6 +2709,12 @@ impl FetchResponseListener for HTMLMediaElementFetchListener {
     fn submit_resource_timing(&mut self) {
         network_listener::submit_timing(self)
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        let elem = self.elem.root();
+        self.resource_timing_global()
+            .report_csp_violations(violations, Some(elem.upcast::<Element>()));
+    }
 }
 
 impl ResourceTimingListener for HTMLMediaElementFetchListener {
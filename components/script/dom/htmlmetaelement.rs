@@ -22,6 +22,7 @@ use crate::dom::node::{
 use crate::dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use net_traits::csp::{CspList, PolicyDisposition, PolicySource};
 use parking_lot::RwLock;
 use servo_arc::Arc;
 use servo_config::pref;
@@ -98,6 +99,38 @@ impl HTMLMetaElement {
                 self.apply_referrer();
             }
         }
+
+        if let Some(ref http_equiv) = element.get_attribute(&ns!(), &local_name!("http-equiv")) {
+            let http_equiv = http_equiv.value().to_ascii_lowercase();
+            if http_equiv.trim_matches(HTML_SPACE_CHARACTERS) == "content-security-policy" {
+                self.apply_content_security_policy();
+            }
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#attr-meta-http-equiv-content-security-policy>
+    fn apply_content_security_policy(&self) {
+        // Step 1.
+        let parent_is_head = self
+            .upcast::<Node>()
+            .GetParentElement()
+            .map_or(false, |parent| parent.is::<HTMLHeadElement>());
+        if !parent_is_head {
+            return;
+        }
+        // Step 2-3.
+        let element = self.upcast::<Element>();
+        let content = match element.get_attribute(&ns!(), &local_name!("content")) {
+            Some(content) => content,
+            None => return,
+        };
+        let content = content.value();
+        if content.is_empty() {
+            return;
+        }
+        // Step 4-5.
+        let policies = CspList::parse(&content, PolicySource::Meta, PolicyDisposition::Enforce);
+        document_from_node(self).append_csp_list(policies);
     }
 
     #[allow(unrooted_must_root)]
@@ -26,14 +26,19 @@ use crate::dom::node::{BindContext, ChildrenMutation, CloneChildrenFlag, Node};
 use crate::dom::performanceresourcetiming::InitiatorType;
 use crate::dom::virtualmethods::VirtualMethods;
 use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
+use crate::script_module::{fetch_external_module_script, fetch_inline_module_script};
+use crate::script_module::{run_module_script, ModuleOwner, ModuleTree, ScriptFetchOptions};
+use crate::task_source::TaskSource;
 use dom_struct::dom_struct;
 use encoding_rs::Encoding;
 use html5ever::{LocalName, Prefix};
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use js::jsval::UndefinedValue;
+use net_traits::csp::{InlineCheckType, Violation};
 use net_traits::request::{
-    CorsSettings, CredentialsMode, Destination, Referrer, RequestBuilder, RequestMode,
+    CorsSettings, CredentialsMode, Destination, ParserMetadata, Referrer, RequestBuilder,
+    RequestMode,
 };
 use net_traits::{FetchMetadata, FetchResponseListener, Metadata, NetworkError};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
@@ -44,6 +49,7 @@ use std::fs::File;
 use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use style::str::{StaticStringVec, HTML_SPACE_CHARACTERS};
 use uuid::Uuid;
@@ -106,7 +112,7 @@ impl HTMLScriptElement {
 
 /// Supported script types as defined by
 /// <https://html.spec.whatwg.org/multipage/#javascript-mime-type>.
-static SCRIPT_JS_MIMES: StaticStringVec = &[
+pub static SCRIPT_JS_MIMES: StaticStringVec = &[
     "application/ecmascript",
     "application/javascript",
     "application/x-ecmascript",
@@ -137,6 +143,9 @@ pub struct ScriptOrigin {
     url: ServoUrl,
     external: bool,
     type_: ScriptType,
+    /// The fetched module script graph, for module scripts.
+    #[ignore_malloc_size_of = "Rc is hard"]
+    module: Option<Rc<ModuleTree>>,
 }
 
 impl ScriptOrigin {
@@ -146,6 +155,7 @@ impl ScriptOrigin {
             url: url,
             external: false,
             type_,
+            module: None,
         }
     }
 
@@ -155,6 +165,17 @@ impl ScriptOrigin {
             url: url,
             external: true,
             type_,
+            module: None,
+        }
+    }
+
+    pub fn module(module: Rc<ModuleTree>, external: bool) -> ScriptOrigin {
+        ScriptOrigin {
+            text: DOMString::new(),
+            url: module.base_url().clone(),
+            external,
+            type_: ScriptType::Module,
+            module: Some(module),
         }
     }
 }
@@ -246,18 +267,9 @@ impl FetchResponseListener for ClassicContext {
         // https://html.spec.whatwg.org/multipage/#prepare-a-script
         // Step 18.6 (When the chosen algorithm asynchronously completes).
         let elem = self.elem.root();
-        let document = document_from_node(&*elem);
-
-        match self.kind {
-            ExternalScriptKind::Asap => document.asap_script_loaded(&elem, load),
-            ExternalScriptKind::AsapInOrder => document.asap_in_order_script_loaded(&elem, load),
-            ExternalScriptKind::Deferred => document.deferred_script_loaded(&elem, load),
-            ExternalScriptKind::ParsingBlocking => {
-                document.pending_parsing_blocking_script_loaded(&elem, load)
-            },
-        }
+        elem.script_fetched(self.kind, load);
 
-        document.finish_load(LoadType::Script(self.url.clone()));
+        document_from_node(&*elem).finish_load(LoadType::Script(self.url.clone()));
     }
 
     fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
@@ -271,6 +283,12 @@ impl FetchResponseListener for ClassicContext {
     fn submit_resource_timing(&mut self) {
         network_listener::submit_timing(self)
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        let elem = self.elem.root();
+        self.resource_timing_global()
+            .report_csp_violations(violations, Some(elem.upcast::<Element>()));
+    }
 }
 
 impl ResourceTimingListener for ClassicContext {
@@ -299,6 +317,7 @@ fn fetch_a_classic_script(
     url: ServoUrl,
     cors_setting: Option<CorsSettings>,
     integrity_metadata: String,
+    cryptographic_nonce: String,
     character_encoding: &'static Encoding,
 ) {
     let doc = document_from_node(script);
@@ -322,7 +341,8 @@ fn fetch_a_classic_script(
         .pipeline_id(Some(script.global().pipeline_id()))
         .referrer(Some(Referrer::ReferrerUrl(doc.url())))
         .referrer_policy(doc.get_referrer_policy())
-        .integrity_metadata(integrity_metadata);
+        .integrity_metadata(integrity_metadata)
+        .cryptographic_nonce_metadata(cryptographic_nonce);
 
     // TODO: Step 3, Add custom steps to perform fetch
 
@@ -415,9 +435,23 @@ impl HTMLScriptElement {
             return;
         }
 
-        // TODO: Step 12: nomodule content attribute
+        // Step 12.
+        if element.has_attribute(&local_name!("nomodule")) && script_type == ScriptType::Classic {
+            return;
+        }
 
-        // TODO(#4577): Step 13: CSP.
+        // Step 13.
+        let nonce = element.get_string_attribute(&local_name!("nonce"));
+        if !element.has_attribute(&local_name!("src")) &&
+            !doc.global().is_inline_allowed(
+                Some(element),
+                InlineCheckType::Script,
+                &nonce,
+                &text,
+            )
+        {
+            return;
+        }
 
         // Step 14.
         let for_attribute = element.get_attribute(&ns!(), &local_name!("for"));
@@ -448,9 +482,13 @@ impl HTMLScriptElement {
         // Step 16.
         let cors_setting = cors_setting_for_element(element);
 
-        // TODO: Step 17: Module script credentials mode.
+        // Step 17.
+        let module_credentials_mode = match cors_setting {
+            Some(CorsSettings::UseCredentials) => CredentialsMode::Include,
+            _ => CredentialsMode::CredentialsSameOrigin,
+        };
 
-        // TODO: Step 18: Nonce.
+        // Step 18: Nonce, read in step 13.
 
         // Step 19: Integrity metadata.
         let im_attribute = element.get_attribute(&ns!(), &local_name!("integrity"));
@@ -462,9 +500,21 @@ impl HTMLScriptElement {
 
         // TODO: Step 20: referrer policy
 
-        // TODO: Step 21: parser state.
+        // Step 21.
+        let parser_metadata = if self.parser_inserted.get() {
+            ParserMetadata::ParserInserted
+        } else {
+            ParserMetadata::NotParserInserted
+        };
 
-        // TODO: Step 22: Fetch options
+        // Step 22.
+        let options = ScriptFetchOptions {
+            cryptographic_nonce: nonce.clone().into(),
+            integrity_metadata: integrity_metadata.to_owned(),
+            parser_metadata,
+            credentials_mode: module_credentials_mode,
+            referrer_policy: doc.get_referrer_policy(),
+        };
 
         // TODO: Step 23: environment settings object.
 
@@ -520,23 +570,31 @@ impl HTMLScriptElement {
                         url,
                         cors_setting,
                         integrity_metadata.to_owned(),
+                        nonce.into(),
                         encoding,
                     );
 
                     // Step 23.
-                    match kind {
-                        ExternalScriptKind::Deferred => doc.add_deferred_script(self),
-                        ExternalScriptKind::ParsingBlocking => {
-                            doc.set_pending_parsing_blocking_script(self, None)
-                        },
-                        ExternalScriptKind::AsapInOrder => doc.push_asap_in_order_script(self),
-                        ExternalScriptKind::Asap => doc.add_asap_script(self),
-                    }
+                    self.add_pending_script(&doc, kind);
                 },
                 ScriptType::Module => {
-                    warn!(
-                        "{} is a module script. It should be fixed after #23545 landed.",
-                        url.clone()
+                    // Preparation for step 26: the script runs once its module
+                    // graph is fetched, so it's pending from now on.
+                    let kind = self.module_script_kind(was_parser_inserted, r#async);
+                    self.add_pending_script(&doc, kind);
+
+                    // Step 24.6.
+                    fetch_external_module_script(
+                        &doc.global(),
+                        ModuleOwner::Script {
+                            element: Trusted::new(self),
+                            kind,
+                            external: true,
+                        },
+                        url,
+                        Destination::Script,
+                        Some(Referrer::ReferrerUrl(doc.url())),
+                        options,
                     );
                 },
             }
@@ -544,6 +602,38 @@ impl HTMLScriptElement {
             // Step 25.
             assert!(!text.is_empty());
 
+            // Step 25-2.
+            if let ScriptType::Module = script_type {
+                let kind = self.module_script_kind(was_parser_inserted, r#async);
+                self.add_pending_script(&doc, kind);
+
+                // Fetch the graph in a task, so that the script runs
+                // asynchronously even when it imports nothing.
+                let script = Trusted::new(self);
+                let text = String::from(text);
+                let line_number = self.line_number as u32;
+                let window = window_from_node(self);
+                let _ = window.task_manager().networking_task_source().queue(
+                    task!(fetch_inline_module_script: move || {
+                        let script = script.root();
+                        fetch_inline_module_script(
+                            &script.global(),
+                            ModuleOwner::Script {
+                                element: Trusted::new(&*script),
+                                kind,
+                                external: false,
+                            },
+                            &text,
+                            base_url,
+                            options,
+                            line_number,
+                        );
+                    }),
+                    window.upcast(),
+                );
+                return;
+            }
+
             // Step 25-1.
             let result = Ok(ScriptOrigin::internal(
                 text.clone(),
@@ -551,15 +641,6 @@ impl HTMLScriptElement {
                 script_type.clone(),
             ));
 
-            // TODO: Step 25-2.
-            if let ScriptType::Module = script_type {
-                warn!(
-                    "{} is a module script. It should be fixed after #23545 landed.",
-                    base_url.clone()
-                );
-                return;
-            }
-
             // Step 26.
             if was_parser_inserted &&
                 doc.get_current_parser()
@@ -646,7 +727,9 @@ impl HTMLScriptElement {
             Ok(script) => script,
         };
 
-        self.unminify_js(&mut script);
+        if script.type_ == ScriptType::Classic {
+            self.unminify_js(&mut script);
+        }
 
         // Step 3.
         let neutralized_doc = if script.external {
@@ -662,11 +745,22 @@ impl HTMLScriptElement {
         let document = document_from_node(self);
         let old_script = document.GetCurrentScript();
 
-        // Step 5.a.1.
-        document.set_current_script(Some(self));
+        match script.type_ {
+            ScriptType::Classic => {
+                // Step 5.a.1.
+                document.set_current_script(Some(self));
+
+                // Step 5.a.2.
+                self.run_a_classic_script(&script);
+            },
+            ScriptType::Module => {
+                // Step 5.b.1.
+                document.set_current_script(None);
 
-        // Step 5.a.2.
-        self.run_a_classic_script(&script);
+                // Step 5.b.2.
+                self.run_a_module_script(&script);
+            },
+        }
 
         // Step 6.
         document.set_current_script(old_script.as_deref());
@@ -708,6 +802,66 @@ impl HTMLScriptElement {
         );
     }
 
+    /// <https://html.spec.whatwg.org/multipage/#run-a-module-script>
+    pub fn run_a_module_script(&self, script: &ScriptOrigin) {
+        // TODO use a settings object rather than this element's document/window
+        // Step 2
+        let document = document_from_node(self);
+        if !document.is_fully_active() || !document.is_scripting_enabled() {
+            return;
+        }
+
+        // Steps 3-8
+        let module = script
+            .module
+            .as_ref()
+            .expect("Module script without a module graph");
+        let window = window_from_node(self);
+        run_module_script(window.upcast(), module);
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#prepare-a-script>
+    /// Step 26, for module scripts.
+    fn module_script_kind(&self, was_parser_inserted: bool, r#async: bool) -> ExternalScriptKind {
+        if was_parser_inserted && !r#async {
+            // Step 26.b: module, was parser-inserted, is not async.
+            ExternalScriptKind::Deferred
+        } else if !r#async && !self.non_blocking.get() {
+            // Step 26.d: module, is not async, is not non-blocking.
+            ExternalScriptKind::AsapInOrder
+        } else {
+            // Step 26.f: module.
+            ExternalScriptKind::Asap
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#prepare-a-script>
+    /// Step 26: add the script to the list of pending scripts of its kind.
+    fn add_pending_script(&self, doc: &Document, kind: ExternalScriptKind) {
+        match kind {
+            ExternalScriptKind::Deferred => doc.add_deferred_script(self),
+            ExternalScriptKind::ParsingBlocking => {
+                doc.set_pending_parsing_blocking_script(self, None)
+            },
+            ExternalScriptKind::AsapInOrder => doc.push_asap_in_order_script(self),
+            ExternalScriptKind::Asap => doc.add_asap_script(self),
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#prepare-a-script>
+    /// Step 26, when the script is ready.
+    pub fn script_fetched(&self, kind: ExternalScriptKind, result: ScriptResult) {
+        let document = document_from_node(self);
+        match kind {
+            ExternalScriptKind::Asap => document.asap_script_loaded(self, result),
+            ExternalScriptKind::AsapInOrder => document.asap_in_order_script_loaded(self, result),
+            ExternalScriptKind::Deferred => document.deferred_script_loaded(self, result),
+            ExternalScriptKind::ParsingBlocking => {
+                document.pending_parsing_blocking_script_loaded(self, result)
+            },
+        }
+    }
+
     pub fn queue_error_event(&self) {
         let window = window_from_node(self);
         window
@@ -902,6 +1056,11 @@ impl HTMLScriptElementMethods for HTMLScriptElement {
     // https://html.spec.whatwg.org/multipage/#dom-script-defer
     make_bool_setter!(SetDefer, "defer");
 
+    // https://html.spec.whatwg.org/multipage/#dom-script-nomodule
+    make_bool_getter!(NoModule, "nomodule");
+    // https://html.spec.whatwg.org/multipage/#dom-script-nomodule
+    make_bool_setter!(SetNoModule, "nomodule");
+
     // https://html.spec.whatwg.org/multipage/#dom-script-integrity
     make_getter!(Integrity, "integrity");
     // https://html.spec.whatwg.org/multipage/#dom-script-integrity
@@ -938,8 +1097,8 @@ impl HTMLScriptElementMethods for HTMLScriptElement {
     }
 }
 
-#[derive(Clone, Copy)]
-enum ExternalScriptKind {
+#[derive(Clone, Copy, JSTraceable)]
+pub enum ExternalScriptKind {
     Deferred,
     ParsingBlocking,
     AsapInOrder,
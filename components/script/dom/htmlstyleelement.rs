@@ -11,6 +11,7 @@ use crate::dom::bindings::root::{DomRoot, MutNullableDom};
 use crate::dom::cssstylesheet::CSSStyleSheet;
 use crate::dom::document::Document;
 use crate::dom::element::{Element, ElementCreator};
+use crate::dom::globalscope::GlobalScope;
 use crate::dom::htmlelement::HTMLElement;
 use crate::dom::node::{
     document_from_node, stylesheets_owner_from_node, window_from_node, BindContext,
@@ -22,6 +23,7 @@ use crate::stylesheet_loader::{StylesheetLoader, StylesheetOwner};
 use cssparser::{Parser as CssParser, ParserInput};
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
+use net_traits::csp::InlineCheckType;
 use net_traits::ReferrerPolicy;
 use servo_arc::Arc;
 use std::cell::Cell;
@@ -96,6 +98,19 @@ impl HTMLStyleElement {
         let data = node
             .GetTextContent()
             .expect("Element.textContent must be a string");
+
+        // https://html.spec.whatwg.org/multipage/#update-a-style-block
+        // Step 5.
+        let nonce = element.get_string_attribute(&local_name!("nonce"));
+        if !window.upcast::<GlobalScope>().is_inline_allowed(
+            Some(element),
+            InlineCheckType::Style,
+            &nonce,
+            &data,
+        ) {
+            return;
+        }
+
         let url = window.get_url();
         let css_error_reporter = window.css_error_reporter();
         let context = CssParserContext::new_for_cssom(
@@ -26,6 +26,7 @@ use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
+use net_traits::csp::Violation;
 use net_traits::image_cache::UsePlaceholder;
 use net_traits::image_cache::{CanRequestImages, ImageCache, ImageOrMetadataAvailable};
 use net_traits::image_cache::{ImageResponse, ImageState, PendingImageId};
@@ -167,7 +168,9 @@ impl HTMLVideoElement {
             .credentials_mode(CredentialsMode::Include)
             .use_url_credentials(true)
             .origin(document.origin().immutable().clone())
-            .pipeline_id(Some(document.global().pipeline_id()));
+            .pipeline_id(Some(document.global().pipeline_id()))
+            .csp_list(document.get_csp_list())
+            .site_for_cookies(document.site_for_cookies());
 
         // Step 5.
         // This delay must be independent from the ones created by HTMLMediaElement during
@@ -340,6 +343,12 @@ impl FetchResponseListener for PosterFrameFetchContext {
     fn submit_resource_timing(&mut self) {
         network_listener::submit_timing(self)
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        let elem = self.elem.root();
+        self.resource_timing_global()
+            .report_csp_violations(violations, Some(elem.upcast::<Element>()));
+    }
 }
 
 impl ResourceTimingListener for PosterFrameFetchContext {
@@ -0,0 +1,410 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBCursorBinding;
+use crate::dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use crate::dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorMethods;
+use crate::dom::bindings::codegen::UnionTypes::IDBObjectStoreOrIDBIndex;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::structuredclone::StructuredCloneData;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbcursorwithvalue::IDBCursorWithValue;
+use crate::dom::idbindex::IDBIndex;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbrequest::{IDBRequest, RequestSource};
+use crate::dom::idbtransaction::{IDBTransaction, RequestOperation, ResultConversion};
+use crate::indexed_db::{convert_value_to_key, extract_key, key_to_jsval};
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsapi::Heap;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::{AsyncOperation, CursorDirection, IndexedDBKey};
+use net_traits::indexeddb_thread::{IndexedDBKeyRange, IndexedDBRecord, IndexedDBTxnMode};
+use std::cell::Cell;
+
+impl From<IDBCursorDirection> for CursorDirection {
+    fn from(direction: IDBCursorDirection) -> CursorDirection {
+        match direction {
+            IDBCursorDirection::Next => CursorDirection::Next,
+            IDBCursorDirection::Nextunique => CursorDirection::NextUnique,
+            IDBCursorDirection::Prev => CursorDirection::Prev,
+            IDBCursorDirection::Prevunique => CursorDirection::PrevUnique,
+        }
+    }
+}
+
+/// The object store or the index a cursor iterates over.
+#[must_root]
+#[derive(JSTraceable, MallocSizeOf)]
+pub enum CursorSource {
+    ObjectStore(Dom<IDBObjectStore>),
+    Index(Dom<IDBIndex>),
+}
+
+// https://w3c.github.io/IndexedDB/#cursor-interface
+#[dom_struct]
+pub struct IDBCursor {
+    reflector_: Reflector,
+    source: CursorSource,
+    transaction: Dom<IDBTransaction>,
+    direction: CursorDirection,
+    range: IndexedDBKeyRange,
+    /// Whether the cursor doesn't get the values of the records it iterates over.
+    key_only: bool,
+    /// The key of the current record, which is the position of the cursor.
+    key: DomRefCell<Option<IndexedDBKey>>,
+    /// The primary key of the current record, which is the object store position of
+    /// cursors that iterate over an index.
+    primary_key: DomRefCell<Option<IndexedDBKey>>,
+    #[ignore_malloc_size_of = "mozjs"]
+    value: Heap<JSVal>,
+    /// <https://w3c.github.io/IndexedDB/#cursor-got-value-flag>
+    got_value: Cell<bool>,
+    request: MutNullableDom<IDBRequest>,
+}
+
+impl IDBCursor {
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(
+        source: CursorSource,
+        transaction: &IDBTransaction,
+        direction: CursorDirection,
+        range: IndexedDBKeyRange,
+        key_only: bool,
+    ) -> IDBCursor {
+        IDBCursor {
+            reflector_: Reflector::new(),
+            source: source,
+            transaction: Dom::from_ref(transaction),
+            direction: direction,
+            range: range,
+            key_only: key_only,
+            key: DomRefCell::new(None),
+            primary_key: DomRefCell::new(None),
+            value: Heap::default(),
+            got_value: Cell::new(false),
+            request: Default::default(),
+        }
+    }
+
+    /// Create a cursor, which is an `IDBCursorWithValue` unless it is key only.
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        global: &GlobalScope,
+        source: CursorSource,
+        transaction: &IDBTransaction,
+        direction: CursorDirection,
+        range: IndexedDBKeyRange,
+        key_only: bool,
+    ) -> DomRoot<IDBCursor> {
+        let cursor = IDBCursor::new_inherited(source, transaction, direction, range, key_only);
+        if key_only {
+            reflect_dom_object(Box::new(cursor), global, IDBCursorBinding::Wrap)
+        } else {
+            DomRoot::upcast(IDBCursorWithValue::new(global, cursor))
+        }
+    }
+
+    pub fn set_request(&self, request: &IDBRequest) {
+        self.request.set(Some(request));
+    }
+
+    pub fn value(&self) -> JSVal {
+        self.value.get()
+    }
+
+    /// The object store the records the cursor iterates over are in.
+    fn object_store(&self) -> DomRoot<IDBObjectStore> {
+        match self.source {
+            CursorSource::ObjectStore(ref store) => DomRoot::from_ref(&**store),
+            CursorSource::Index(ref index) => index.object_store(),
+        }
+    }
+
+    fn source_deleted(&self) -> bool {
+        match self.source {
+            CursorSource::ObjectStore(ref store) => store.metadata().is_none(),
+            CursorSource::Index(ref index) => index.metadata().is_none(),
+        }
+    }
+
+    /// The operation that moves the cursor to its next record.
+    pub fn iterate_operation(
+        &self,
+        key: Option<IndexedDBKey>,
+        primary_key: Option<IndexedDBKey>,
+        count: u32,
+    ) -> AsyncOperation {
+        let index = match self.source {
+            CursorSource::ObjectStore(_) => None,
+            CursorSource::Index(ref index) => Some(index.name()),
+        };
+        AsyncOperation::Iterate {
+            store: self.object_store().name(),
+            index: index,
+            range: self.range.clone(),
+            direction: self.direction,
+            position: self.key.borrow().clone(),
+            object_store_position: self.primary_key.borrow().clone(),
+            key: key,
+            primary_key: primary_key,
+            count: count,
+        }
+    }
+
+    /// Move the cursor to a record it found.
+    pub fn set_record(&self, record: IndexedDBRecord) {
+        *self.key.borrow_mut() = Some(record.key);
+        *self.primary_key.borrow_mut() = Some(record.primary_key);
+        if !self.key_only {
+            let global = self.global();
+            let cx = global.get_cx();
+            rooted!(in(*cx) let mut value = UndefinedValue());
+            StructuredCloneData::Vector(record.value).read(&global, value.handle_mut());
+            self.value.set(value.get());
+        }
+        self.got_value.set(true);
+    }
+
+    /// Reset the cursor after it didn't find another record.
+    pub fn set_exhausted(&self) {
+        *self.key.borrow_mut() = None;
+        *self.primary_key.borrow_mut() = None;
+        self.value.set(UndefinedValue());
+    }
+
+    /// Queue the request that moves the cursor.
+    #[allow(unrooted_must_root)]
+    fn iterate(
+        &self,
+        key: Option<IndexedDBKey>,
+        primary_key: Option<IndexedDBKey>,
+        count: u32,
+    ) -> ErrorResult {
+        self.got_value.set(false);
+        let request = self.request.get().expect("Cursor has no request");
+        request.reset();
+        self.transaction.add_request(
+            Some(&request),
+            RequestOperation::Iterate {
+                cursor: Dom::from_ref(self),
+                key: key,
+                primary_key: primary_key,
+                count: count,
+            },
+        );
+        Ok(())
+    }
+
+    fn check_iterable(&self) -> ErrorResult {
+        self.transaction.check_active()?;
+        if self.source_deleted() || !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+        Ok(())
+    }
+
+    fn check_writable(&self) -> ErrorResult {
+        self.transaction.check_active()?;
+        if self.transaction.mode() == IndexedDBTxnMode::Readonly {
+            return Err(Error::ReadOnly);
+        }
+        if self.source_deleted() || !self.got_value.get() || self.key_only {
+            return Err(Error::InvalidState);
+        }
+        Ok(())
+    }
+
+    fn is_forward(&self) -> bool {
+        match self.direction {
+            CursorDirection::Next | CursorDirection::NextUnique => true,
+            CursorDirection::Prev | CursorDirection::PrevUnique => false,
+        }
+    }
+
+    fn key_to_jsval(cx: SafeJSContext, key: &Option<IndexedDBKey>) -> JSVal {
+        rooted!(in(*cx) let mut value = UndefinedValue());
+        if let Some(ref key) = *key {
+            key_to_jsval(cx, key, value.handle_mut());
+        }
+        value.get()
+    }
+}
+
+impl IDBCursorMethods for IDBCursor {
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-source
+    fn Source(&self) -> IDBObjectStoreOrIDBIndex {
+        match self.source {
+            CursorSource::ObjectStore(ref store) => {
+                IDBObjectStoreOrIDBIndex::IDBObjectStore(DomRoot::from_ref(&**store))
+            },
+            CursorSource::Index(ref index) => {
+                IDBObjectStoreOrIDBIndex::IDBIndex(DomRoot::from_ref(&**index))
+            },
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-direction
+    fn Direction(&self) -> IDBCursorDirection {
+        match self.direction {
+            CursorDirection::Next => IDBCursorDirection::Next,
+            CursorDirection::NextUnique => IDBCursorDirection::Nextunique,
+            CursorDirection::Prev => IDBCursorDirection::Prev,
+            CursorDirection::PrevUnique => IDBCursorDirection::Prevunique,
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-key
+    fn Key(&self, cx: SafeJSContext) -> JSVal {
+        IDBCursor::key_to_jsval(cx, &self.key.borrow())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-primarykey
+    fn PrimaryKey(&self, cx: SafeJSContext) -> JSVal {
+        IDBCursor::key_to_jsval(cx, &self.primary_key.borrow())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-request
+    fn Request(&self) -> DomRoot<IDBRequest> {
+        self.request.get().expect("Cursor has no request")
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-advance
+    fn Advance(&self, count: u32) -> ErrorResult {
+        // Step 1.
+        if count == 0 {
+            return Err(Error::Type("The count must be greater than 0".to_owned()));
+        }
+        // Steps 2-5.
+        self.check_iterable()?;
+        // Steps 6-9.
+        self.iterate(None, None, count)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-continue
+    fn Continue(&self, cx: SafeJSContext, key: HandleValue) -> ErrorResult {
+        // Steps 1-4.
+        self.check_iterable()?;
+        // Step 5.
+        let key = if key.is_undefined() {
+            None
+        } else {
+            let key = convert_value_to_key(cx, key)?;
+            if let Some(ref position) = *self.key.borrow() {
+                let before = if self.is_forward() {
+                    key <= *position
+                } else {
+                    key >= *position
+                };
+                if before {
+                    return Err(Error::Data);
+                }
+            }
+            Some(key)
+        };
+        // Steps 6-9.
+        self.iterate(key, None, 1)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-continueprimarykey
+    fn ContinuePrimaryKey(
+        &self,
+        cx: SafeJSContext,
+        key: HandleValue,
+        primary_key: HandleValue,
+    ) -> ErrorResult {
+        // Steps 1-2.
+        self.transaction.check_active()?;
+        if self.source_deleted() {
+            return Err(Error::InvalidState);
+        }
+        // Steps 3-4.
+        if let CursorSource::ObjectStore(_) = self.source {
+            return Err(Error::InvalidAccess);
+        }
+        match self.direction {
+            CursorDirection::Next | CursorDirection::Prev => {},
+            CursorDirection::NextUnique | CursorDirection::PrevUnique => {
+                return Err(Error::InvalidAccess);
+            },
+        }
+        // Step 5.
+        if !self.got_value.get() {
+            return Err(Error::InvalidState);
+        }
+        // Steps 6-9.
+        let key = convert_value_to_key(cx, key)?;
+        let primary_key = convert_value_to_key(cx, primary_key)?;
+        // Steps 10-13.
+        if let (Some(ref position), Some(ref object_store_position)) =
+            (&*self.key.borrow(), &*self.primary_key.borrow())
+        {
+            let before = if self.is_forward() {
+                key < *position || (key == *position && primary_key <= *object_store_position)
+            } else {
+                key > *position || (key == *position && primary_key >= *object_store_position)
+            };
+            if before {
+                return Err(Error::Data);
+            }
+        }
+        // Steps 14-17.
+        self.iterate(Some(key), Some(primary_key), 1)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-update
+    #[allow(unrooted_must_root)]
+    fn Update(&self, cx: SafeJSContext, value: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_writable()?;
+        // Steps 6-7.
+        let serialized = StructuredCloneData::write(*cx, value)?.move_to_arraybuffer();
+        // Step 8.
+        let store = self.object_store();
+        let metadata = store.metadata().ok_or(Error::InvalidState)?;
+        let primary_key = self.primary_key.borrow().clone();
+        if let Some(ref key_path) = metadata.key_path {
+            if extract_key(cx, value, key_path, false)? != primary_key {
+                return Err(Error::Data);
+            }
+        }
+        // Step 9.
+        Ok(self.transaction.execute_request(
+            RequestSource::Cursor(Dom::from_ref(self)),
+            RequestOperation::Put {
+                store: store.name(),
+                value: serialized,
+                key: primary_key,
+                no_overwrite: false,
+            },
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbcursor-delete
+    #[allow(unrooted_must_root)]
+    fn Delete(&self) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 1-5.
+        self.check_writable()?;
+        // Step 6.
+        let primary_key = self
+            .primary_key
+            .borrow()
+            .clone()
+            .ok_or(Error::InvalidState)?;
+        Ok(self.transaction.execute_request(
+            RequestSource::Cursor(Dom::from_ref(self)),
+            RequestOperation::Backend(
+                AsyncOperation::DeleteRange {
+                    store: self.object_store().name(),
+                    range: IndexedDBKeyRange::only(primary_key),
+                },
+                ResultConversion::Undefined,
+            ),
+        ))
+    }
+}
@@ -0,0 +1,317 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBDatabaseBinding;
+use crate::dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBDatabaseMethods;
+use crate::dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBObjectStoreParameters;
+use crate::dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBTransactionMode;
+use crate::dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::domstringlist::DOMStringList;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbfactory::IDBFactory;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbtransaction::{IDBTransaction, RequestOperation, ResultConversion};
+use crate::indexed_db::is_valid_key_path;
+use dom_struct::dom_struct;
+use net_traits::indexeddb_thread::{AsyncOperation, DatabaseMetadata, IndexedDBTxnMode};
+use net_traits::indexeddb_thread::{KeyPath, ObjectStoreMetadata};
+use std::cell::{Cell, RefMut};
+
+// https://w3c.github.io/IndexedDB/#database-interface
+#[dom_struct]
+pub struct IDBDatabase {
+    eventtarget: EventTarget,
+    factory: Dom<IDBFactory>,
+    /// The id of the connection in the backend.
+    id: u64,
+    /// The schema of the database, as seen by this connection.
+    #[ignore_malloc_size_of = "Defined in net_traits"]
+    metadata: DomRefCell<DatabaseMetadata>,
+    /// <https://w3c.github.io/IndexedDB/#connection-close-pending-flag>
+    close_pending: Cell<bool>,
+    upgrade_transaction: MutNullableDom<IDBTransaction>,
+}
+
+impl IDBDatabase {
+    fn new_inherited(factory: &IDBFactory, id: u64, metadata: DatabaseMetadata) -> IDBDatabase {
+        IDBDatabase {
+            eventtarget: EventTarget::new_inherited(),
+            factory: Dom::from_ref(factory),
+            id: id,
+            metadata: DomRefCell::new(metadata),
+            close_pending: Cell::new(false),
+            upgrade_transaction: Default::default(),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        factory: &IDBFactory,
+        id: u64,
+        metadata: DatabaseMetadata,
+    ) -> DomRoot<IDBDatabase> {
+        reflect_dom_object(
+            Box::new(IDBDatabase::new_inherited(factory, id, metadata)),
+            global,
+            IDBDatabaseBinding::Wrap,
+        )
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn name(&self) -> String {
+        self.metadata.borrow().name.clone()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.metadata.borrow().version
+    }
+
+    pub fn set_version(&self, version: u64) {
+        self.metadata.borrow_mut().version = version;
+    }
+
+    pub fn metadata(&self) -> DatabaseMetadata {
+        self.metadata.borrow().clone()
+    }
+
+    pub fn set_metadata(&self, metadata: DatabaseMetadata) {
+        *self.metadata.borrow_mut() = metadata;
+    }
+
+    pub fn object_store_names(&self) -> Vec<String> {
+        self.metadata
+            .borrow()
+            .object_stores
+            .iter()
+            .map(|store| store.name.clone())
+            .collect()
+    }
+
+    pub fn store_metadata(&self, name: &str) -> Option<ObjectStoreMetadata> {
+        self.metadata
+            .borrow()
+            .object_stores
+            .iter()
+            .find(|store| store.name == name)
+            .cloned()
+    }
+
+    /// Mutably borrow the metadata of an object store, which is changed by the upgrade
+    /// transaction as the changes are requested.
+    pub fn store_metadata_mut(&self, name: &str) -> Option<RefMut<ObjectStoreMetadata>> {
+        let metadata = self.metadata.borrow_mut();
+        let position = metadata
+            .object_stores
+            .iter()
+            .position(|store| store.name == name)?;
+        Some(RefMut::map(metadata, |metadata| {
+            &mut metadata.object_stores[position]
+        }))
+    }
+
+    pub fn is_close_pending(&self) -> bool {
+        self.close_pending.get()
+    }
+
+    pub fn upgrade_transaction(&self) -> Option<DomRoot<IDBTransaction>> {
+        self.upgrade_transaction.get()
+    }
+
+    pub fn set_upgrade_transaction(&self, transaction: Option<&IDBTransaction>) {
+        self.upgrade_transaction.set(transaction);
+    }
+
+    /// Get the upgrade transaction, checking that schema changes can be requested.
+    fn active_upgrade_transaction(&self) -> Fallible<DomRoot<IDBTransaction>> {
+        let transaction = self.upgrade_transaction.get().ok_or(Error::InvalidState)?;
+        transaction.check_active()?;
+        Ok(transaction)
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#close-a-database-connection>
+    pub fn close_connection(&self) {
+        if self.close_pending.get() {
+            return;
+        }
+        self.close_pending.set(true);
+        self.factory.connection_closed(self);
+    }
+}
+
+impl IDBDatabaseMethods for IDBDatabase {
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-name
+    fn Name(&self) -> DOMString {
+        DOMString::from(self.name())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-version
+    fn Version(&self) -> u64 {
+        self.version()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-objectstorenames
+    fn ObjectStoreNames(&self) -> DomRoot<DOMStringList> {
+        let mut names = self.object_store_names();
+        names.sort();
+        DOMStringList::new(
+            &self.global(),
+            names.into_iter().map(DOMString::from).collect(),
+        )
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-transaction
+    fn Transaction(
+        &self,
+        store_names: StringOrStringSequence,
+        mode: IDBTransactionMode,
+    ) -> Fallible<DomRoot<IDBTransaction>> {
+        // Step 1.
+        if let Some(transaction) = self.upgrade_transaction.get() {
+            if !transaction.is_finished() {
+                return Err(Error::InvalidState);
+            }
+        }
+        // Step 2.
+        if self.close_pending.get() {
+            return Err(Error::InvalidState);
+        }
+        // Step 3.
+        let mut scope: Vec<String> = match store_names {
+            StringOrStringSequence::String(name) => vec![name.into()],
+            StringOrStringSequence::StringSequence(names) => {
+                names.into_iter().map(String::from).collect()
+            },
+        };
+        scope.sort();
+        scope.dedup();
+        // Step 4.
+        if scope.iter().any(|name| self.store_metadata(name).is_none()) {
+            return Err(Error::NotFound);
+        }
+        // Step 5.
+        if scope.is_empty() {
+            return Err(Error::InvalidAccess);
+        }
+        // Step 6.
+        let mode = match mode {
+            IDBTransactionMode::Readonly => IndexedDBTxnMode::Readonly,
+            IDBTransactionMode::Readwrite => IndexedDBTxnMode::Readwrite,
+            IDBTransactionMode::Versionchange => {
+                return Err(Error::Type(
+                    "Versionchange transactions can't be created explicitly".to_owned(),
+                ));
+            },
+        };
+        // Steps 7-9.
+        Ok(IDBTransaction::new(&self.global(), self, mode, scope))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-close
+    fn Close(&self) {
+        self.close_connection();
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-createobjectstore
+    fn CreateObjectStore(
+        &self,
+        name: DOMString,
+        options: &IDBObjectStoreParameters,
+    ) -> Fallible<DomRoot<IDBObjectStore>> {
+        // Steps 2-4.
+        let transaction = self.active_upgrade_transaction()?;
+        // Steps 5-6.
+        let key_path = options.keyPath.as_ref().map(|key_path| match *key_path {
+            StringOrStringSequence::String(ref path) => KeyPath::String(path.to_string()),
+            StringOrStringSequence::StringSequence(ref paths) => {
+                KeyPath::Sequence(paths.iter().map(|path| path.to_string()).collect())
+            },
+        });
+        if let Some(ref key_path) = key_path {
+            if !is_valid_key_path(key_path) {
+                return Err(Error::Syntax);
+            }
+        }
+        // Step 7.
+        let name = String::from(name);
+        if self.store_metadata(&name).is_some() {
+            return Err(Error::Constraint);
+        }
+        // Step 9.
+        if options.autoIncrement {
+            match key_path {
+                Some(KeyPath::String(ref path)) if path.is_empty() => {
+                    return Err(Error::InvalidAccess);
+                },
+                Some(KeyPath::Sequence(_)) => return Err(Error::InvalidAccess),
+                _ => {},
+            }
+        }
+        // Steps 10-12.
+        let metadata = ObjectStoreMetadata {
+            name: name.clone(),
+            key_path: key_path,
+            auto_increment: options.autoIncrement,
+            indexes: vec![],
+        };
+        self.metadata
+            .borrow_mut()
+            .object_stores
+            .push(metadata.clone());
+        transaction.object_store_created(&name);
+        transaction.add_request(
+            None,
+            RequestOperation::Backend(
+                AsyncOperation::CreateObjectStore(metadata),
+                ResultConversion::Undefined,
+            ),
+        );
+        Ok(transaction.object_store(&name))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-deleteobjectstore
+    fn DeleteObjectStore(&self, name: DOMString) -> ErrorResult {
+        // Steps 2-4.
+        let transaction = self.active_upgrade_transaction()?;
+        // Step 5.
+        let name = String::from(name);
+        if self.store_metadata(&name).is_none() {
+            return Err(Error::NotFound);
+        }
+        // Steps 6-7.
+        self.metadata
+            .borrow_mut()
+            .object_stores
+            .retain(|store| store.name != name);
+        transaction.object_store_deleted(&name);
+        transaction.add_request(
+            None,
+            RequestOperation::Backend(
+                AsyncOperation::DeleteObjectStore(name),
+                ResultConversion::Undefined,
+            ),
+        );
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onclose
+    event_handler!(close, GetOnclose, SetOnclose);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbdatabase-onversionchange
+    event_handler!(versionchange, GetOnversionchange, SetOnversionchange);
+}
@@ -0,0 +1,407 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBFactoryBinding;
+use crate::dom::bindings::codegen::Bindings::IDBFactoryBinding::IDBFactoryMethods;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::domexception::DOMErrorName;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbdatabase::IDBDatabase;
+use crate::dom::idbopendbrequest::IDBOpenDBRequest;
+use crate::dom::idbrequest::IDBRequest;
+use crate::dom::idbtransaction::IDBTransaction;
+use crate::dom::idbversionchangeevent::IDBVersionChangeEvent;
+use crate::indexed_db::convert_value_to_key;
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::task_source::database_access::DatabaseAccessTaskSource;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use ipc_channel::router::ROUTER;
+use js::jsval::{ObjectValue, UndefinedValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::{DatabaseMetadata, IndexedDBError, IndexedDBThreadMsg};
+use net_traits::indexeddb_thread::{VersionChange, WaitEvent};
+use net_traits::IpcSend;
+use profile_traits::ipc as ProfiledIpc;
+use std::cmp::Ordering;
+
+// https://w3c.github.io/IndexedDB/#factory-interface
+#[dom_struct]
+pub struct IDBFactory {
+    reflector_: Reflector,
+    /// The connections opened in this global, which haven't been closed. The backend keeps
+    /// track of the connections of every global.
+    connections: DomRefCell<Vec<Dom<IDBDatabase>>>,
+}
+
+impl IDBFactory {
+    fn new_inherited() -> IDBFactory {
+        IDBFactory {
+            reflector_: Reflector::new(),
+            connections: DomRefCell::new(vec![]),
+        }
+    }
+
+    pub fn new(global: &GlobalScope) -> DomRoot<IDBFactory> {
+        reflect_dom_object(
+            Box::new(IDBFactory::new_inherited()),
+            global,
+            IDBFactoryBinding::Wrap,
+        )
+    }
+
+    /// Fire `versionchange` at a connection when another connection, in any global, is
+    /// about to upgrade or delete the database, and let the backend know once it is done.
+    fn route_version_changes(
+        &self,
+        connection: &IDBDatabase,
+        receiver: ProfiledIpc::IpcReceiver<VersionChange>,
+    ) {
+        let global = self.global();
+        let connection = Trusted::new(connection);
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(DatabaseAccessTaskSource::NAME);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let version_change: VersionChange = message.to().unwrap();
+                let connection = connection.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(fire_versionchange: move || {
+                        let connection = connection.root();
+                        if !connection.is_close_pending() {
+                            IDBVersionChangeEvent::fire(
+                                connection.upcast(),
+                                atom!("versionchange"),
+                                version_change.old_version,
+                                version_change.new_version,
+                            );
+                        }
+                        let global = connection.global();
+                        let _ = global.resource_threads().send(
+                            IndexedDBThreadMsg::VersionChangeFired(
+                                global.origin().immutable().clone(),
+                                connection.name(),
+                                connection.id(),
+                            ),
+                        );
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+    }
+
+    /// Wait for the other connections to a database to be closed, firing `blocked` at the
+    /// request if any of them stays open after it was told about the version change. Then
+    /// go on with the upgrade of the given connection to the given version, or with the
+    /// deletion of the database if there is none.
+    fn wait_for_connections(
+        &self,
+        request: &IDBOpenDBRequest,
+        name: String,
+        upgrade: Option<(&IDBDatabase, u64)>,
+    ) {
+        let global = self.global();
+        let (sender, receiver) = ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
+        let except = upgrade.map(|(connection, _)| connection.id());
+        let new_version = upgrade.map(|(_, version)| version);
+        let this = Trusted::new(self);
+        let request = Trusted::new(request);
+        let upgrade = upgrade.map(|(connection, version)| (Trusted::new(connection), version));
+        let database_name = name.clone();
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(DatabaseAccessTaskSource::NAME);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let event: WaitEvent = message.to().unwrap();
+                let this = this.clone();
+                let request = request.clone();
+                let upgrade = upgrade.clone();
+                let name = database_name.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(wait_for_connections: move || {
+                        let request = request.root();
+                        match (event, upgrade) {
+                            (WaitEvent::Blocked(version_change), _) => {
+                                IDBVersionChangeEvent::fire(
+                                    request.upcast(),
+                                    atom!("blocked"),
+                                    version_change.old_version,
+                                    version_change.new_version,
+                                );
+                            },
+                            (WaitEvent::Done, Some((connection, version))) => {
+                                let connection = connection.root();
+                                IDBTransaction::new_upgrade(
+                                    &request.global(),
+                                    &connection,
+                                    &request,
+                                    version,
+                                );
+                            },
+                            (WaitEvent::Done, None) => {
+                                this.root().delete_database(&request, name);
+                            },
+                        }
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+        let _ = global
+            .resource_threads()
+            .send(IndexedDBThreadMsg::WaitForConnections(
+                sender,
+                global.origin().immutable().clone(),
+                name,
+                except,
+                new_version,
+            ));
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#open-a-database>
+    fn open_database(&self, request: &IDBOpenDBRequest, name: String, version: Option<u64>) {
+        let global = self.global();
+        let (sender, receiver) = ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
+        let (version_change_sender, version_change_receiver) =
+            ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
+        let this = Trusted::new(self);
+        let request = Trusted::new(request);
+        let database_name = name.clone();
+        let mut version_change_receiver = Some(version_change_receiver);
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(DatabaseAccessTaskSource::NAME);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let result: Result<(u64, DatabaseMetadata), IndexedDBError> = message.to().unwrap();
+                let this = this.clone();
+                let request = request.clone();
+                let name = database_name.clone();
+                let version_change_receiver = version_change_receiver
+                    .take()
+                    .expect("The backend replied twice");
+                let _ = task_source.queue_with_canceller(
+                    task!(database_opened: move || {
+                        this.root().database_opened(
+                            &request.root(),
+                            name,
+                            version,
+                            result,
+                            version_change_receiver,
+                        );
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+        let _ = global.resource_threads().send(IndexedDBThreadMsg::Open(
+            sender,
+            global.origin().immutable().clone(),
+            name,
+            version_change_sender,
+        ));
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#open-a-database>, once the backend has opened a
+    /// connection to the database.
+    fn database_opened(
+        &self,
+        request: &IDBOpenDBRequest,
+        name: String,
+        version: Option<u64>,
+        result: Result<(u64, DatabaseMetadata), IndexedDBError>,
+        version_change_receiver: ProfiledIpc::IpcReceiver<VersionChange>,
+    ) {
+        let (id, metadata) = match result {
+            Ok(connection) => connection,
+            Err(_) => return fire_request_error(request.upcast(), DOMErrorName::UnknownError),
+        };
+
+        // Steps 5.3-5.4.
+        let global = self.global();
+        let old_version = metadata.version;
+        let version = version.unwrap_or(old_version.max(1));
+        // Step 5.5.
+        if old_version > version {
+            let _ = global.resource_threads().send(IndexedDBThreadMsg::Close(
+                global.origin().immutable().clone(),
+                name,
+                id,
+            ));
+            return fire_request_error(request.upcast(), DOMErrorName::VersionError);
+        }
+        // Step 5.6.
+        let connection = IDBDatabase::new(&global, self, id, metadata);
+        self.connections
+            .borrow_mut()
+            .push(Dom::from_ref(&*connection));
+        self.route_version_changes(&connection, version_change_receiver);
+        // Step 5.7.
+        if old_version < version {
+            return self.wait_for_connections(request, name, Some((&connection, version)));
+        }
+        // Step 5.8.
+        let cx = global.get_cx();
+        rooted!(in(*cx) let result = ObjectValue(connection.reflector().get_jsobject().get()));
+        let request = request.upcast::<IDBRequest>();
+        request.set_result(result.handle());
+        request.upcast::<EventTarget>().fire_event(atom!("success"));
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#delete-a-database>, once the other connections
+    /// to the database are closed.
+    fn delete_database(&self, request: &IDBOpenDBRequest, name: String) {
+        let global = self.global();
+        let (sender, receiver) = ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
+        let request = Trusted::new(request);
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(DatabaseAccessTaskSource::NAME);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let result: Result<u64, IndexedDBError> = message.to().unwrap();
+                let request = request.clone();
+                let _ = task_source.queue_with_canceller(
+                    task!(database_deleted: move || {
+                        let request = request.root();
+                        let request = request.upcast::<IDBRequest>();
+                        match result {
+                            Ok(version) => {
+                                let cx = request.global().get_cx();
+                                rooted!(in(*cx) let undefined = UndefinedValue());
+                                request.set_result(undefined.handle());
+                                IDBVersionChangeEvent::fire(
+                                    request.upcast(),
+                                    atom!("success"),
+                                    version,
+                                    None,
+                                );
+                            },
+                            Err(_) => {
+                                fire_request_error(request, DOMErrorName::UnknownError);
+                            },
+                        }
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+        let _ = global
+            .resource_threads()
+            .send(IndexedDBThreadMsg::DeleteDatabase(
+                sender,
+                global.origin().immutable().clone(),
+                name,
+            ));
+    }
+
+    /// Forget a closed connection, and let the backend resume the requests that were
+    /// waiting for it.
+    pub fn connection_closed(&self, connection: &IDBDatabase) {
+        self.connections
+            .borrow_mut()
+            .retain(|other| &**other as *const _ != connection as *const _);
+        let global = self.global();
+        let _ = global.resource_threads().send(IndexedDBThreadMsg::Close(
+            global.origin().immutable().clone(),
+            connection.name(),
+            connection.id(),
+        ));
+    }
+
+    /// Close the connections of a global that goes away, so they don't keep blocking the
+    /// requests of other globals.
+    pub fn close_all_connections(&self) {
+        let connections: Vec<DomRoot<IDBDatabase>> = self
+            .connections
+            .borrow()
+            .iter()
+            .map(|connection| DomRoot::from_ref(&**connection))
+            .collect();
+        for connection in connections {
+            connection.close_connection();
+        }
+    }
+}
+
+/// Report the failure of a request that isn't made as part of a transaction.
+fn fire_request_error(request: &IDBRequest, error: DOMErrorName) {
+    request.set_error(error);
+    request
+        .upcast::<EventTarget>()
+        .fire_bubbling_cancelable_event(atom!("error"));
+}
+
+impl IDBFactoryMethods for IDBFactory {
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-open
+    fn Open(&self, name: DOMString, version: Option<u64>) -> Fallible<DomRoot<IDBOpenDBRequest>> {
+        // Step 1.
+        if version == Some(0) {
+            return Err(Error::Type("The version must be greater than 0".to_owned()));
+        }
+        // Steps 2-3.
+        let global = self.global();
+        if !global.origin().is_tuple() {
+            return Err(Error::Security);
+        }
+        // Steps 4-5.
+        let request = IDBOpenDBRequest::new(&global);
+        let this = Trusted::new(self);
+        let trusted_request = Trusted::new(&*request);
+        let name = String::from(name);
+        let _ = global.database_access_task_source().queue(
+            task!(open_database: move || {
+                let request = trusted_request.root();
+                this.root().open_database(&request, name, version);
+            }),
+            &global,
+        );
+        // Step 6.
+        Ok(request)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-deletedatabase
+    fn DeleteDatabase(&self, name: DOMString) -> Fallible<DomRoot<IDBOpenDBRequest>> {
+        // Steps 1-2.
+        let global = self.global();
+        if !global.origin().is_tuple() {
+            return Err(Error::Security);
+        }
+        // Steps 3-4.
+        let request = IDBOpenDBRequest::new(&global);
+        let this = Trusted::new(self);
+        let trusted_request = Trusted::new(&*request);
+        let name = String::from(name);
+        let _ = global.database_access_task_source().queue(
+            task!(delete_database: move || {
+                let request = trusted_request.root();
+                this.root().wait_for_connections(&request, name, None);
+            }),
+            &global,
+        );
+        // Step 5.
+        Ok(request)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbfactory-cmp
+    fn Cmp(&self, cx: SafeJSContext, first: HandleValue, second: HandleValue) -> Fallible<i16> {
+        let first = convert_value_to_key(cx, first)?;
+        let second = convert_value_to_key(cx, second)?;
+        Ok(match first.cmp(&second) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+}
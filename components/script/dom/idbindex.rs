@@ -0,0 +1,254 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use crate::dom::bindings::codegen::Bindings::IDBIndexBinding;
+use crate::dom::bindings::codegen::Bindings::IDBIndexBinding::IDBIndexMethods;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbcursor::CursorSource;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbrequest::{IDBRequest, RequestSource};
+use crate::dom::idbtransaction::{RequestOperation, ResultConversion};
+use crate::indexed_db::{convert_value_to_key_range, key_path_to_jsval};
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::{AsyncOperation, IndexMetadata};
+
+// https://w3c.github.io/IndexedDB/#index-interface
+#[dom_struct]
+pub struct IDBIndex {
+    reflector_: Reflector,
+    name: DomRefCell<String>,
+    object_store: Dom<IDBObjectStore>,
+}
+
+impl IDBIndex {
+    fn new_inherited(object_store: &IDBObjectStore, name: String) -> IDBIndex {
+        IDBIndex {
+            reflector_: Reflector::new(),
+            name: DomRefCell::new(name),
+            object_store: Dom::from_ref(object_store),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        object_store: &IDBObjectStore,
+        name: String,
+    ) -> DomRoot<IDBIndex> {
+        reflect_dom_object(
+            Box::new(IDBIndex::new_inherited(object_store, name)),
+            global,
+            IDBIndexBinding::Wrap,
+        )
+    }
+
+    pub fn name(&self) -> String {
+        self.name.borrow().clone()
+    }
+
+    pub fn object_store(&self) -> DomRoot<IDBObjectStore> {
+        DomRoot::from_ref(&*self.object_store)
+    }
+
+    /// The metadata of the index, `None` if it or its object store was deleted.
+    pub fn metadata(&self) -> Option<IndexMetadata> {
+        let name = self.name.borrow();
+        self.object_store
+            .metadata()?
+            .indexes
+            .into_iter()
+            .find(|index| index.name == *name)
+    }
+
+    /// Check that the index can be read as part of the transaction of its object store.
+    pub fn check_readable(&self) -> ErrorResult {
+        self.metadata().ok_or(Error::InvalidState)?;
+        self.object_store.transaction().check_active()
+    }
+
+    #[allow(unrooted_must_root)]
+    fn execute(
+        &self,
+        operation: AsyncOperation,
+        conversion: ResultConversion,
+    ) -> DomRoot<IDBRequest> {
+        self.object_store.transaction().execute_request(
+            RequestSource::Index(Dom::from_ref(self)),
+            RequestOperation::Backend(operation, conversion),
+        )
+    }
+
+    fn get(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        conversion: ResultConversion,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.execute(
+            AsyncOperation::GetRecord {
+                store: self.object_store.name(),
+                index: Some(self.name()),
+                range: range,
+            },
+            conversion,
+        ))
+    }
+
+    fn get_all(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        count: Option<u32>,
+        conversion: ResultConversion,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.execute(
+            AsyncOperation::GetAllRecords {
+                store: self.object_store.name(),
+                index: Some(self.name()),
+                range: range,
+                count: count,
+            },
+            conversion,
+        ))
+    }
+}
+
+impl IDBIndexMethods for IDBIndex {
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-name
+    fn Name(&self) -> DOMString {
+        DOMString::from(self.name())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-name
+    fn SetName(&self, value: DOMString) -> ErrorResult {
+        // Steps 3-7.
+        let metadata = self.object_store.check_upgrading()?;
+        let name = self.name();
+        if !metadata.indexes.iter().any(|index| index.name == name) {
+            return Err(Error::InvalidState);
+        }
+        // Step 8.
+        let new_name = String::from(value);
+        if new_name == name {
+            return Ok(());
+        }
+        // Step 9.
+        if metadata.indexes.iter().any(|index| index.name == new_name) {
+            return Err(Error::Constraint);
+        }
+        // Steps 10-11.
+        *self.name.borrow_mut() = new_name.clone();
+        self.object_store.index_renamed(&name, &new_name);
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-objectstore
+    fn ObjectStore(&self) -> DomRoot<IDBObjectStore> {
+        self.object_store()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-keypath
+    fn KeyPath(&self, cx: SafeJSContext) -> JSVal {
+        rooted!(in(*cx) let mut key_path = UndefinedValue());
+        if let Some(metadata) = self.metadata() {
+            key_path_to_jsval(cx, &metadata.key_path, key_path.handle_mut());
+        }
+        key_path.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-multientry
+    fn MultiEntry(&self) -> bool {
+        self.metadata()
+            .map_or(false, |metadata| metadata.multi_entry)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-unique
+    fn Unique(&self) -> bool {
+        self.metadata().map_or(false, |metadata| metadata.unique)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-get
+    fn Get(&self, cx: SafeJSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.get(cx, query, ResultConversion::Value)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getkey
+    fn GetKey(&self, cx: SafeJSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.get(cx, query, ResultConversion::PrimaryKey)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getall
+    fn GetAll(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        count: Option<u32>,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.get_all(cx, query, count, ResultConversion::Values)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-getallkeys
+    fn GetAllKeys(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        count: Option<u32>,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.get_all(cx, query, count, ResultConversion::PrimaryKeys)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-count
+    fn Count(&self, cx: SafeJSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.execute(
+            AsyncOperation::Count {
+                store: self.object_store.name(),
+                index: Some(self.name()),
+                range: range,
+            },
+            ResultConversion::Count,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-opencursor
+    #[allow(unrooted_must_root)]
+    fn OpenCursor(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let source = CursorSource::Index(Dom::from_ref(self));
+        self.object_store
+            .open_cursor(source, cx, query, direction, false)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbindex-openkeycursor
+    #[allow(unrooted_must_root)]
+    fn OpenKeyCursor(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let source = CursorSource::Index(Dom::from_ref(self));
+        self.object_store
+            .open_cursor(source, cx, query, direction, true)
+    }
+}
@@ -0,0 +1,550 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBCursorBinding::IDBCursorDirection;
+use crate::dom::bindings::codegen::Bindings::IDBObjectStoreBinding;
+use crate::dom::bindings::codegen::Bindings::IDBObjectStoreBinding::IDBIndexParameters;
+use crate::dom::bindings::codegen::Bindings::IDBObjectStoreBinding::IDBObjectStoreMethods;
+use crate::dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject, Reflector};
+use crate::dom::bindings::root::{Dom, DomRoot};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::structuredclone::StructuredCloneData;
+use crate::dom::domstringlist::DOMStringList;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbcursor::{CursorSource, IDBCursor};
+use crate::dom::idbindex::IDBIndex;
+use crate::dom::idbrequest::{IDBRequest, RequestSource};
+use crate::dom::idbtransaction::{IDBTransaction, RequestOperation, ResultConversion};
+use crate::indexed_db::{convert_value_to_key, convert_value_to_key_range};
+use crate::indexed_db::{could_inject_key, extract_key, is_valid_key_path, key_path_to_jsval};
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsval::{JSVal, NullValue};
+use js::rust::HandleValue;
+use net_traits::indexeddb_thread::{AsyncOperation, IndexMetadata, IndexedDBTxnMode};
+use net_traits::indexeddb_thread::{KeyPath, ObjectStoreMetadata};
+use std::collections::HashMap;
+
+// https://w3c.github.io/IndexedDB/#object-store-interface
+#[dom_struct]
+pub struct IDBObjectStore {
+    reflector_: Reflector,
+    name: DomRefCell<String>,
+    transaction: Dom<IDBTransaction>,
+    indexes: DomRefCell<HashMap<String, Dom<IDBIndex>>>,
+}
+
+impl IDBObjectStore {
+    fn new_inherited(transaction: &IDBTransaction, name: String) -> IDBObjectStore {
+        IDBObjectStore {
+            reflector_: Reflector::new(),
+            name: DomRefCell::new(name),
+            transaction: Dom::from_ref(transaction),
+            indexes: DomRefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn new(
+        global: &GlobalScope,
+        transaction: &IDBTransaction,
+        name: String,
+    ) -> DomRoot<IDBObjectStore> {
+        reflect_dom_object(
+            Box::new(IDBObjectStore::new_inherited(transaction, name)),
+            global,
+            IDBObjectStoreBinding::Wrap,
+        )
+    }
+
+    pub fn name(&self) -> String {
+        self.name.borrow().clone()
+    }
+
+    pub fn transaction(&self) -> DomRoot<IDBTransaction> {
+        DomRoot::from_ref(&*self.transaction)
+    }
+
+    /// The metadata of the object store, `None` if it was deleted.
+    pub fn metadata(&self) -> Option<ObjectStoreMetadata> {
+        self.transaction.db().store_metadata(&self.name.borrow())
+    }
+
+    /// Check that the object store can be read as part of its transaction.
+    pub fn check_readable(&self) -> Fallible<ObjectStoreMetadata> {
+        let metadata = self.metadata().ok_or(Error::InvalidState)?;
+        self.transaction.check_active()?;
+        Ok(metadata)
+    }
+
+    /// Check that the object store can be changed as part of its transaction.
+    fn check_writable(&self) -> Fallible<ObjectStoreMetadata> {
+        let metadata = self.metadata().ok_or(Error::InvalidState)?;
+        self.transaction.check_writable()?;
+        Ok(metadata)
+    }
+
+    /// Check that the indexes of the object store can be changed, which can only be
+    /// done by an upgrade transaction.
+    pub fn check_upgrading(&self) -> Fallible<ObjectStoreMetadata> {
+        if self.transaction.mode() != IndexedDBTxnMode::Versionchange {
+            return Err(Error::InvalidState);
+        }
+        let metadata = self.metadata().ok_or(Error::InvalidState)?;
+        self.transaction.check_active()?;
+        Ok(metadata)
+    }
+
+    #[allow(unrooted_must_root)]
+    fn execute(
+        &self,
+        operation: AsyncOperation,
+        conversion: ResultConversion,
+    ) -> DomRoot<IDBRequest> {
+        self.transaction.execute_request(
+            RequestSource::ObjectStore(Dom::from_ref(self)),
+            RequestOperation::Backend(operation, conversion),
+        )
+    }
+
+    /// Open a cursor over the records of the object store or of one of its indexes.
+    #[allow(unrooted_must_root)]
+    pub fn open_cursor(
+        &self,
+        source: CursorSource,
+        cx: SafeJSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+        key_only: bool,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        let range = convert_value_to_key_range(cx, query, false)?;
+        let request_source = match source {
+            CursorSource::ObjectStore(ref store) => RequestSource::ObjectStore(store.clone()),
+            CursorSource::Index(ref index) => RequestSource::Index(index.clone()),
+        };
+        let cursor = IDBCursor::new(
+            &self.global(),
+            source,
+            &self.transaction,
+            direction.into(),
+            range,
+            key_only,
+        );
+        let request = self.transaction.execute_request(
+            request_source,
+            RequestOperation::Iterate {
+                cursor: Dom::from_ref(&*cursor),
+                key: None,
+                primary_key: None,
+                count: 1,
+            },
+        );
+        cursor.set_request(&request);
+        Ok(request)
+    }
+
+    /// Get the object that represents one of the indexes of the object store.
+    fn index(&self, name: &str) -> DomRoot<IDBIndex> {
+        if let Some(index) = self.indexes.borrow().get(name) {
+            return DomRoot::from_ref(&**index);
+        }
+        let index = IDBIndex::new(&self.global(), self, name.to_owned());
+        self.indexes
+            .borrow_mut()
+            .insert(name.to_owned(), Dom::from_ref(&*index));
+        index
+    }
+
+    pub fn index_renamed(&self, name: &str, new_name: &str) {
+        if let Some(mut metadata) = self.transaction.db().store_metadata_mut(&self.name()) {
+            for index in metadata.indexes.iter_mut() {
+                if index.name == name {
+                    index.name = new_name.to_owned();
+                }
+            }
+        }
+        let mut indexes = self.indexes.borrow_mut();
+        if let Some(index) = indexes.remove(name) {
+            indexes.insert(new_name.to_owned(), index);
+        }
+        self.transaction.add_request(
+            None,
+            RequestOperation::Backend(
+                AsyncOperation::RenameIndex {
+                    store: self.name(),
+                    name: name.to_owned(),
+                    new_name: new_name.to_owned(),
+                },
+                ResultConversion::Undefined,
+            ),
+        );
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#add-or-put>
+    fn put(
+        &self,
+        cx: SafeJSContext,
+        value: HandleValue,
+        key: HandleValue,
+        no_overwrite: bool,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        // Steps 4-6.
+        let metadata = self.check_writable()?;
+        // Steps 7-8.
+        if metadata.key_path.is_some() && !key.is_undefined() {
+            return Err(Error::Data);
+        }
+        if metadata.key_path.is_none() && !metadata.auto_increment && key.is_undefined() {
+            return Err(Error::Data);
+        }
+        // Step 9.
+        let mut key = if key.is_undefined() {
+            None
+        } else {
+            Some(convert_value_to_key(cx, key)?)
+        };
+        // Steps 10-11.
+        let serialized = StructuredCloneData::write(*cx, value)?.move_to_arraybuffer();
+        // Step 12.
+        if let Some(ref key_path) = metadata.key_path {
+            key = extract_key(cx, value, key_path, false)?;
+            if key.is_none() {
+                if !metadata.auto_increment {
+                    return Err(Error::Data);
+                }
+                match *key_path {
+                    KeyPath::String(ref path) if could_inject_key(cx, value, path)? => {},
+                    _ => return Err(Error::Data),
+                }
+            }
+        }
+        // Step 13.
+        Ok(self.transaction.execute_request(
+            RequestSource::ObjectStore(Dom::from_ref(self)),
+            RequestOperation::Put {
+                store: self.name(),
+                value: serialized,
+                key: key,
+                no_overwrite: no_overwrite,
+            },
+        ))
+    }
+}
+
+impl IDBObjectStoreMethods for IDBObjectStore {
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-name
+    fn Name(&self) -> DOMString {
+        DOMString::from(self.name())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-name
+    fn SetName(&self, value: DOMString) -> ErrorResult {
+        // Steps 3-6.
+        self.check_upgrading()?;
+        // Step 7.
+        let new_name = String::from(value);
+        let name = self.name();
+        if new_name == name {
+            return Ok(());
+        }
+        // Step 8.
+        let db = self.transaction.db();
+        if db.store_metadata(&new_name).is_some() {
+            return Err(Error::Constraint);
+        }
+        // Steps 9-10.
+        if let Some(mut metadata) = db.store_metadata_mut(&name) {
+            metadata.name = new_name.clone();
+        }
+        *self.name.borrow_mut() = new_name.clone();
+        self.transaction.object_store_renamed(&name, &new_name);
+        self.transaction.add_request(
+            None,
+            RequestOperation::Backend(
+                AsyncOperation::RenameObjectStore {
+                    name: name,
+                    new_name: new_name,
+                },
+                ResultConversion::Undefined,
+            ),
+        );
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-keypath
+    fn KeyPath(&self, cx: SafeJSContext) -> JSVal {
+        rooted!(in(*cx) let mut key_path = NullValue());
+        if let Some(ref path) = self.metadata().and_then(|metadata| metadata.key_path) {
+            key_path_to_jsval(cx, path, key_path.handle_mut());
+        }
+        key_path.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-indexnames
+    fn IndexNames(&self) -> DomRoot<DOMStringList> {
+        let mut names: Vec<String> = self
+            .metadata()
+            .map(|metadata| {
+                metadata
+                    .indexes
+                    .into_iter()
+                    .map(|index| index.name)
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        DOMStringList::new(
+            &self.global(),
+            names.into_iter().map(DOMString::from).collect(),
+        )
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-transaction
+    fn Transaction(&self) -> DomRoot<IDBTransaction> {
+        self.transaction()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-autoincrement
+    fn AutoIncrement(&self) -> bool {
+        self.metadata()
+            .map_or(false, |metadata| metadata.auto_increment)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-put
+    fn Put(
+        &self,
+        cx: SafeJSContext,
+        value: HandleValue,
+        key: HandleValue,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.put(cx, value, key, false)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-add
+    fn Add(
+        &self,
+        cx: SafeJSContext,
+        value: HandleValue,
+        key: HandleValue,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.put(cx, value, key, true)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-delete
+    fn Delete(&self, cx: SafeJSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_writable()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.execute(
+            AsyncOperation::DeleteRange {
+                store: self.name(),
+                range: range,
+            },
+            ResultConversion::Undefined,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-clear
+    fn Clear(&self) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_writable()?;
+        Ok(self.execute(
+            AsyncOperation::Clear { store: self.name() },
+            ResultConversion::Undefined,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-get
+    fn Get(&self, cx: SafeJSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.execute(
+            AsyncOperation::GetRecord {
+                store: self.name(),
+                index: None,
+                range: range,
+            },
+            ResultConversion::Value,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getkey
+    fn GetKey(&self, cx: SafeJSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let range = convert_value_to_key_range(cx, query, true)?;
+        Ok(self.execute(
+            AsyncOperation::GetRecord {
+                store: self.name(),
+                index: None,
+                range: range,
+            },
+            ResultConversion::PrimaryKey,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getall
+    fn GetAll(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        count: Option<u32>,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.execute(
+            AsyncOperation::GetAllRecords {
+                store: self.name(),
+                index: None,
+                range: range,
+                count: count,
+            },
+            ResultConversion::Values,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-getallkeys
+    fn GetAllKeys(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        count: Option<u32>,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.execute(
+            AsyncOperation::GetAllRecords {
+                store: self.name(),
+                index: None,
+                range: range,
+                count: count,
+            },
+            ResultConversion::PrimaryKeys,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-count
+    fn Count(&self, cx: SafeJSContext, query: HandleValue) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let range = convert_value_to_key_range(cx, query, false)?;
+        Ok(self.execute(
+            AsyncOperation::Count {
+                store: self.name(),
+                index: None,
+                range: range,
+            },
+            ResultConversion::Count,
+        ))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-opencursor
+    #[allow(unrooted_must_root)]
+    fn OpenCursor(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let source = CursorSource::ObjectStore(Dom::from_ref(self));
+        self.open_cursor(source, cx, query, direction, false)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-openkeycursor
+    #[allow(unrooted_must_root)]
+    fn OpenKeyCursor(
+        &self,
+        cx: SafeJSContext,
+        query: HandleValue,
+        direction: IDBCursorDirection,
+    ) -> Fallible<DomRoot<IDBRequest>> {
+        self.check_readable()?;
+        let source = CursorSource::ObjectStore(Dom::from_ref(self));
+        self.open_cursor(source, cx, query, direction, true)
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-index
+    fn Index(&self, name: DOMString) -> Fallible<DomRoot<IDBIndex>> {
+        let metadata = self.metadata().ok_or(Error::InvalidState)?;
+        if self.transaction.is_finished() {
+            return Err(Error::InvalidState);
+        }
+        let name = String::from(name);
+        if !metadata.indexes.iter().any(|index| index.name == name) {
+            return Err(Error::NotFound);
+        }
+        Ok(self.index(&name))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-createindex
+    fn CreateIndex(
+        &self,
+        name: DOMString,
+        key_path: StringOrStringSequence,
+        options: &IDBIndexParameters,
+    ) -> Fallible<DomRoot<IDBIndex>> {
+        // Steps 2-6.
+        let metadata = self.check_upgrading()?;
+        // Step 7.
+        let name = String::from(name);
+        if metadata.indexes.iter().any(|index| index.name == name) {
+            return Err(Error::Constraint);
+        }
+        // Step 8.
+        let key_path = KeyPath::from(key_path);
+        if !is_valid_key_path(&key_path) {
+            return Err(Error::Syntax);
+        }
+        // Step 10.
+        if let KeyPath::Sequence(_) = key_path {
+            if options.multiEntry {
+                return Err(Error::InvalidAccess);
+            }
+        }
+        // Steps 11-13.
+        let index = IndexMetadata {
+            name: name.clone(),
+            key_path: key_path,
+            unique: options.unique,
+            multi_entry: options.multiEntry,
+        };
+        if let Some(mut metadata) = self.transaction.db().store_metadata_mut(&self.name()) {
+            metadata.indexes.push(index.clone());
+        }
+        self.transaction.add_request(
+            None,
+            RequestOperation::CreateIndex {
+                store: self.name(),
+                index: index,
+            },
+        );
+        Ok(self.index(&name))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbobjectstore-deleteindex
+    fn DeleteIndex(&self, name: DOMString) -> ErrorResult {
+        // Steps 2-5.
+        let metadata = self.check_upgrading()?;
+        // Step 6.
+        let name = String::from(name);
+        if !metadata.indexes.iter().any(|index| index.name == name) {
+            return Err(Error::NotFound);
+        }
+        // Steps 7-9.
+        if let Some(mut metadata) = self.transaction.db().store_metadata_mut(&self.name()) {
+            metadata.indexes.retain(|index| index.name != name);
+        }
+        self.indexes.borrow_mut().remove(&name);
+        self.transaction.add_request(
+            None,
+            RequestOperation::Backend(
+                AsyncOperation::DeleteIndex {
+                    store: self.name(),
+                    name: name,
+                },
+                ResultConversion::Undefined,
+            ),
+        );
+        Ok(())
+    }
+}
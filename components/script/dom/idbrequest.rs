@@ -0,0 +1,156 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::IDBRequestBinding;
+use crate::dom::bindings::codegen::Bindings::IDBRequestBinding::IDBRequestMethods;
+use crate::dom::bindings::codegen::Bindings::IDBRequestBinding::IDBRequestReadyState;
+use crate::dom::bindings::codegen::UnionTypes::IDBObjectStoreOrIDBIndexOrIDBCursor;
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbcursor::IDBCursor;
+use crate::dom::idbindex::IDBIndex;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbtransaction::IDBTransaction;
+use crate::script_runtime::JSContext as SafeJSContext;
+use dom_struct::dom_struct;
+use js::jsapi::Heap;
+use js::jsval::{JSVal, UndefinedValue};
+use js::rust::HandleValue;
+use std::cell::Cell;
+
+/// The object a request was made on.
+#[must_root]
+#[derive(JSTraceable, MallocSizeOf)]
+pub enum RequestSource {
+    ObjectStore(Dom<IDBObjectStore>),
+    Index(Dom<IDBIndex>),
+    Cursor(Dom<IDBCursor>),
+}
+
+#[dom_struct]
+pub struct IDBRequest {
+    eventtarget: EventTarget,
+    #[ignore_malloc_size_of = "mozjs"]
+    result: Heap<JSVal>,
+    error: MutNullableDom<DOMException>,
+    source: DomRefCell<Option<RequestSource>>,
+    transaction: MutNullableDom<IDBTransaction>,
+    done: Cell<bool>,
+}
+
+impl IDBRequest {
+    #[allow(unrooted_must_root)]
+    pub fn new_inherited(
+        source: Option<RequestSource>,
+        transaction: Option<&IDBTransaction>,
+    ) -> IDBRequest {
+        IDBRequest {
+            eventtarget: EventTarget::new_inherited(),
+            result: Heap::default(),
+            error: Default::default(),
+            source: DomRefCell::new(source),
+            transaction: MutNullableDom::new(transaction),
+            done: Cell::new(false),
+        }
+    }
+
+    #[allow(unrooted_must_root)]
+    pub fn new(
+        global: &GlobalScope,
+        source: Option<RequestSource>,
+        transaction: Option<&IDBTransaction>,
+    ) -> DomRoot<IDBRequest> {
+        reflect_dom_object(
+            Box::new(IDBRequest::new_inherited(source, transaction)),
+            global,
+            IDBRequestBinding::Wrap,
+        )
+    }
+
+    /// Report the successful outcome of the request.
+    pub fn set_result(&self, result: HandleValue) {
+        self.result.set(result.get());
+        self.error.set(None);
+        self.done.set(true);
+    }
+
+    /// Report the failure of the request.
+    pub fn set_error(&self, error: DOMErrorName) {
+        self.result.set(UndefinedValue());
+        self.error
+            .set(Some(&DOMException::new(&self.global(), error)));
+        self.done.set(true);
+    }
+
+    /// Make the request pending again, when the cursor it was made for is moved.
+    pub fn reset(&self) {
+        self.done.set(false);
+    }
+
+    pub fn error(&self) -> Option<DomRoot<DOMException>> {
+        self.error.get()
+    }
+
+    pub fn set_transaction(&self, transaction: Option<&IDBTransaction>) {
+        self.transaction.set(transaction);
+    }
+}
+
+impl IDBRequestMethods for IDBRequest {
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-result
+    fn Result(&self, _cx: SafeJSContext) -> Fallible<JSVal> {
+        if !self.done.get() {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.result.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-error
+    fn GetError(&self) -> Fallible<Option<DomRoot<DOMException>>> {
+        if !self.done.get() {
+            return Err(Error::InvalidState);
+        }
+        Ok(self.error.get())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-source
+    fn GetSource(&self) -> Option<IDBObjectStoreOrIDBIndexOrIDBCursor> {
+        self.source.borrow().as_ref().map(|source| match *source {
+            RequestSource::ObjectStore(ref store) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBObjectStore(DomRoot::from_ref(&**store))
+            },
+            RequestSource::Index(ref index) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBIndex(DomRoot::from_ref(&**index))
+            },
+            RequestSource::Cursor(ref cursor) => {
+                IDBObjectStoreOrIDBIndexOrIDBCursor::IDBCursor(DomRoot::from_ref(&**cursor))
+            },
+        })
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-transaction
+    fn GetTransaction(&self) -> Option<DomRoot<IDBTransaction>> {
+        self.transaction.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-readystate
+    fn ReadyState(&self) -> IDBRequestReadyState {
+        if self.done.get() {
+            IDBRequestReadyState::Done
+        } else {
+            IDBRequestReadyState::Pending
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-onsuccess
+    event_handler!(success, GetOnsuccess, SetOnsuccess);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbrequest-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
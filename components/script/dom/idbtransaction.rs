@@ -0,0 +1,890 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::EventBinding::EventMethods;
+use crate::dom::bindings::codegen::Bindings::IDBDatabaseBinding::IDBTransactionMode;
+use crate::dom::bindings::codegen::Bindings::IDBTransactionBinding;
+use crate::dom::bindings::codegen::Bindings::IDBTransactionBinding::IDBTransactionMethods;
+use crate::dom::bindings::error::{Error, ErrorResult, Fallible};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::structuredclone::StructuredCloneData;
+use crate::dom::domexception::{DOMErrorName, DOMException};
+use crate::dom::domstringlist::DOMStringList;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbcursor::IDBCursor;
+use crate::dom::idbdatabase::IDBDatabase;
+use crate::dom::idbobjectstore::IDBObjectStore;
+use crate::dom::idbopendbrequest::IDBOpenDBRequest;
+use crate::dom::idbrequest::{IDBRequest, RequestSource};
+use crate::dom::idbversionchangeevent::IDBVersionChangeEvent;
+use crate::indexed_db::{index_keys, inject_key, key_to_jsval, records_to_jsval};
+use crate::task_source::database_access::DatabaseAccessTaskSource;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use ipc_channel::router::ROUTER;
+use js::jsval::{DoubleValue, NullValue, ObjectValue, UndefinedValue};
+use js::rust::{HandleValue, MutableHandleValue};
+use net_traits::indexeddb_thread::ObjectStoreMetadata;
+use net_traits::indexeddb_thread::OperationResult;
+use net_traits::indexeddb_thread::{AsyncOperation, DatabaseMetadata, IndexMetadata};
+use net_traits::indexeddb_thread::{IndexedDBError, IndexedDBKey, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{IndexedDBThreadMsg, IndexedDBTxnMode, KeyPath};
+use net_traits::IpcSend;
+use profile_traits::ipc as ProfiledIpc;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+
+/// <https://w3c.github.io/IndexedDB/#transaction-lifetime>
+#[derive(Clone, Copy, Debug, JSTraceable, MallocSizeOf, PartialEq)]
+pub enum TransactionState {
+    Active,
+    Inactive,
+    Committing,
+    Finished,
+}
+
+/// How the result of a backend operation is reported to the request it was made for.
+#[derive(Clone, Copy, JSTraceable)]
+pub enum ResultConversion {
+    /// The value of the record, or undefined if there is none.
+    Value,
+    /// The primary key of the record, or undefined if there is none.
+    PrimaryKey,
+    Values,
+    PrimaryKeys,
+    Count,
+    Undefined,
+}
+
+/// An operation that is performed once the requests made before it are done.
+#[must_root]
+#[derive(JSTraceable)]
+pub enum RequestOperation {
+    Backend(AsyncOperation, ResultConversion),
+    /// Store a serialized value, with a key generated by the object store if it has none.
+    Put {
+        store: String,
+        value: Vec<u8>,
+        key: Option<IndexedDBKey>,
+        no_overwrite: bool,
+    },
+    /// Move a cursor to its next record.
+    Iterate {
+        cursor: Dom<IDBCursor>,
+        key: Option<IndexedDBKey>,
+        primary_key: Option<IndexedDBKey>,
+        count: u32,
+    },
+    /// Create an index, along with the keys the existing records have in it.
+    CreateIndex {
+        store: String,
+        index: IndexMetadata,
+    },
+}
+
+/// A request waiting to be processed. Requests without a `request` object are the
+/// changes made to the schema of the database by upgrade transactions.
+#[must_root]
+#[derive(JSTraceable)]
+struct PendingRequest {
+    request: Option<Dom<IDBRequest>>,
+    operation: RequestOperation,
+}
+
+fn error_name(error: IndexedDBError) -> DOMErrorName {
+    match error {
+        IndexedDBError::Constraint => DOMErrorName::ConstraintError,
+        IndexedDBError::NotFound => DOMErrorName::NotFoundError,
+        IndexedDBError::Unknown => DOMErrorName::UnknownError,
+    }
+}
+
+/// Convert the result of a backend operation to the result of the request it was made for.
+fn convert_result(
+    global: &GlobalScope,
+    result: OperationResult,
+    conversion: ResultConversion,
+    rval: MutableHandleValue,
+) {
+    match (result, conversion) {
+        (OperationResult::Record(Some(record)), ResultConversion::Value) => {
+            StructuredCloneData::Vector(record.value).read(global, rval)
+        },
+        (OperationResult::Record(Some(record)), ResultConversion::PrimaryKey) => {
+            key_to_jsval(global.get_cx(), &record.primary_key, rval)
+        },
+        (OperationResult::Records(records), ResultConversion::Values) => {
+            records_to_jsval(global, records, true, rval)
+        },
+        (OperationResult::Records(records), ResultConversion::PrimaryKeys) => {
+            records_to_jsval(global, records, false, rval)
+        },
+        (OperationResult::Count(count), ResultConversion::Count) => {
+            rval.set(DoubleValue(count as f64))
+        },
+        _ => rval.set(UndefinedValue()),
+    }
+}
+
+// https://w3c.github.io/IndexedDB/#transaction
+#[dom_struct]
+pub struct IDBTransaction {
+    eventtarget: EventTarget,
+    db: Dom<IDBDatabase>,
+    mode: IndexedDBTxnMode,
+    /// The names of the object stores the transaction can access.
+    scope: DomRefCell<Vec<String>>,
+    /// The id of the transaction in the backend, once it has started.
+    id: Cell<Option<u64>>,
+    state: Cell<TransactionState>,
+    #[ignore_malloc_size_of = "Defined in net_traits"]
+    requests: DomRefCell<VecDeque<PendingRequest>>,
+    /// Whether a request or the commit is being processed, or a task to process the next
+    /// request is queued.
+    processing: Cell<bool>,
+    /// The request whose operation the backend is performing.
+    current_request: MutNullableDom<IDBRequest>,
+    error: MutNullableDom<DOMException>,
+    object_stores: DomRefCell<HashMap<String, Dom<IDBObjectStore>>>,
+    /// The request that opened the connection, for upgrade transactions.
+    open_request: MutNullableDom<IDBOpenDBRequest>,
+    /// The version the database is upgraded to, for upgrade transactions.
+    new_version: u64,
+    /// The metadata the database is restored to if an upgrade transaction is aborted.
+    #[ignore_malloc_size_of = "Defined in net_traits"]
+    previous_metadata: DomRefCell<Option<DatabaseMetadata>>,
+}
+
+impl IDBTransaction {
+    fn new_inherited(
+        db: &IDBDatabase,
+        mode: IndexedDBTxnMode,
+        scope: Vec<String>,
+        state: TransactionState,
+        new_version: u64,
+    ) -> IDBTransaction {
+        IDBTransaction {
+            eventtarget: EventTarget::new_inherited(),
+            db: Dom::from_ref(db),
+            mode: mode,
+            scope: DomRefCell::new(scope),
+            id: Cell::new(None),
+            state: Cell::new(state),
+            requests: DomRefCell::new(VecDeque::new()),
+            processing: Cell::new(false),
+            current_request: Default::default(),
+            error: Default::default(),
+            object_stores: DomRefCell::new(HashMap::new()),
+            open_request: Default::default(),
+            new_version: new_version,
+            previous_metadata: DomRefCell::new(None),
+        }
+    }
+
+    /// Create a transaction, which stays active until the current task is done.
+    pub fn new(
+        global: &GlobalScope,
+        db: &IDBDatabase,
+        mode: IndexedDBTxnMode,
+        scope: Vec<String>,
+    ) -> DomRoot<IDBTransaction> {
+        let transaction = reflect_dom_object(
+            Box::new(IDBTransaction::new_inherited(
+                db,
+                mode,
+                scope,
+                TransactionState::Active,
+                0,
+            )),
+            global,
+            IDBTransactionBinding::Wrap,
+        );
+        let this = Trusted::new(&*transaction);
+        let _ = global.database_access_task_source().queue(
+            task!(deactivate_transaction: move || {
+                let transaction = this.root();
+                if transaction.state.get() == TransactionState::Active {
+                    transaction.state.set(TransactionState::Inactive);
+                    transaction.schedule_processing();
+                }
+            }),
+            global,
+        );
+        transaction.begin();
+        transaction
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#upgrade-transaction-steps>
+    pub fn new_upgrade(
+        global: &GlobalScope,
+        db: &IDBDatabase,
+        request: &IDBOpenDBRequest,
+        version: u64,
+    ) -> DomRoot<IDBTransaction> {
+        let transaction = reflect_dom_object(
+            Box::new(IDBTransaction::new_inherited(
+                db,
+                IndexedDBTxnMode::Versionchange,
+                db.object_store_names(),
+                TransactionState::Inactive,
+                version,
+            )),
+            global,
+            IDBTransactionBinding::Wrap,
+        );
+        transaction.open_request.set(Some(request));
+        *transaction.previous_metadata.borrow_mut() = Some(db.metadata());
+        db.set_upgrade_transaction(Some(&transaction));
+        transaction.begin();
+        transaction
+    }
+
+    /// Pass the reply of the backend to `callback` in a task, so that the script thread
+    /// never waits for the backend.
+    fn route_reply<T, F>(&self, receiver: ProfiledIpc::IpcReceiver<T>, callback: F)
+    where
+        T: DeserializeOwned + Serialize + Send + 'static,
+        F: FnOnce(&IDBTransaction, T) + Send + 'static,
+    {
+        let global = self.global();
+        let this = Trusted::new(self);
+        let task_source = global.database_access_task_source();
+        let canceller = global.task_canceller(DatabaseAccessTaskSource::NAME);
+        let mut callback = Some(callback);
+        ROUTER.add_route(
+            receiver.to_opaque(),
+            Box::new(move |message| {
+                let reply: T = message.to().unwrap();
+                let this = this.clone();
+                let callback = callback.take().expect("The backend replied twice");
+                let _ = task_source.queue_with_canceller(
+                    task!(backend_reply: move || {
+                        callback(&*this.root(), reply);
+                    }),
+                    &canceller,
+                );
+            }),
+        );
+    }
+
+    /// Ask the backend to start the transaction once it doesn't conflict with others.
+    fn begin(&self) {
+        let global = self.global();
+        let (sender, receiver) = ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
+        self.route_reply(receiver, |this, id| this.start(id));
+        let _ = global
+            .resource_threads()
+            .send(IndexedDBThreadMsg::BeginTransaction(
+                sender,
+                global.origin().immutable().clone(),
+                self.db.name(),
+                self.mode,
+                self.scope.borrow().clone(),
+            ));
+    }
+
+    fn start(&self, id: u64) {
+        self.id.set(Some(id));
+        if self.state.get() == TransactionState::Finished {
+            // The transaction was aborted before it started.
+            self.send_abort();
+            return;
+        }
+        if self.open_request.get().is_some() {
+            return self.start_upgrade();
+        }
+        self.schedule_processing();
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#upgrade-transaction-steps>
+    fn start_upgrade(&self) {
+        // No request is processed until `upgradeneeded` has been fired.
+        self.processing.set(true);
+        let operation = AsyncOperation::SetVersion(self.new_version);
+        self.perform(operation, |this, result| {
+            if this.is_finished() {
+                return;
+            }
+            if let Err(error) = result {
+                return this.abort_with_error(Some(error_name(error)));
+            }
+            if let Some(request) = this.open_request.get() {
+                this.fire_upgradeneeded(&request);
+            }
+            this.processing.set(false);
+            this.schedule_processing();
+        });
+    }
+
+    fn fire_upgradeneeded(&self, request: &IDBOpenDBRequest) {
+        let old_version = self.db.version();
+        self.db.set_version(self.new_version);
+
+        let cx = self.global().get_cx();
+        rooted!(in(*cx) let db = ObjectValue(self.db.reflector().get_jsobject().get()));
+        let request = request.upcast::<IDBRequest>();
+        request.set_result(db.handle());
+        request.set_transaction(Some(self));
+
+        self.state.set(TransactionState::Active);
+        IDBVersionChangeEvent::fire(
+            request.upcast(),
+            atom!("upgradeneeded"),
+            old_version,
+            Some(self.new_version),
+        );
+        if self.state.get() == TransactionState::Active {
+            self.state.set(TransactionState::Inactive);
+        }
+    }
+
+    pub fn db(&self) -> DomRoot<IDBDatabase> {
+        DomRoot::from_ref(&*self.db)
+    }
+
+    pub fn mode(&self) -> IndexedDBTxnMode {
+        self.mode
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state.get() == TransactionState::Active
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.state.get() == TransactionState::Finished
+    }
+
+    /// Check that requests can be made as part of the transaction.
+    pub fn check_active(&self) -> ErrorResult {
+        if !self.is_active() {
+            return Err(Error::TransactionInactive);
+        }
+        Ok(())
+    }
+
+    /// Check that requests changing data can be made as part of the transaction.
+    pub fn check_writable(&self) -> ErrorResult {
+        self.check_active()?;
+        if self.mode == IndexedDBTxnMode::Readonly {
+            return Err(Error::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Get the object that represents an object store in the transaction.
+    pub fn object_store(&self, name: &str) -> DomRoot<IDBObjectStore> {
+        if let Some(store) = self.object_stores.borrow().get(name) {
+            return DomRoot::from_ref(&**store);
+        }
+        let store = IDBObjectStore::new(&self.global(), self, name.to_owned());
+        self.object_stores
+            .borrow_mut()
+            .insert(name.to_owned(), Dom::from_ref(&*store));
+        store
+    }
+
+    /// Add an object store created by an upgrade transaction to its scope.
+    pub fn object_store_created(&self, name: &str) {
+        self.scope.borrow_mut().push(name.to_owned());
+    }
+
+    pub fn object_store_deleted(&self, name: &str) {
+        self.scope.borrow_mut().retain(|store| store != name);
+        self.object_stores.borrow_mut().remove(name);
+    }
+
+    pub fn object_store_renamed(&self, name: &str, new_name: &str) {
+        for store in self.scope.borrow_mut().iter_mut() {
+            if store == name {
+                *store = new_name.to_owned();
+            }
+        }
+        let mut object_stores = self.object_stores.borrow_mut();
+        if let Some(store) = object_stores.remove(name) {
+            object_stores.insert(new_name.to_owned(), store);
+        }
+    }
+
+    /// Queue an operation, reporting its outcome to `request`.
+    #[allow(unrooted_must_root)]
+    pub fn add_request(&self, request: Option<&IDBRequest>, operation: RequestOperation) {
+        self.requests.borrow_mut().push_back(PendingRequest {
+            request: request.map(Dom::from_ref),
+            operation: operation,
+        });
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#asynchronously-execute-a-request>
+    #[allow(unrooted_must_root)]
+    pub fn execute_request(
+        &self,
+        source: RequestSource,
+        operation: RequestOperation,
+    ) -> DomRoot<IDBRequest> {
+        let request = IDBRequest::new(&self.global(), Some(source), Some(self));
+        self.add_request(Some(&request), operation);
+        request
+    }
+
+    /// Queue a task to process the next request, once the transaction has started and
+    /// the task that made the requests is done.
+    fn schedule_processing(&self) {
+        if self.id.get().is_none() || self.processing.get() {
+            return;
+        }
+        match self.state.get() {
+            TransactionState::Inactive | TransactionState::Committing => {},
+            TransactionState::Active | TransactionState::Finished => return,
+        }
+        self.processing.set(true);
+        let this = Trusted::new(self);
+        let global = self.global();
+        let _ = global.database_access_task_source().queue(
+            task!(process_request: move || {
+                this.root().process_next_request();
+            }),
+            &global,
+        );
+    }
+
+    #[allow(unrooted_must_root)]
+    fn process_next_request(&self) {
+        match self.state.get() {
+            TransactionState::Inactive | TransactionState::Committing => {},
+            TransactionState::Active | TransactionState::Finished => {
+                return self.processing.set(false);
+            },
+        }
+        let next = self.requests.borrow_mut().pop_front();
+        let PendingRequest { request, operation } = match next {
+            Some(next) => next,
+            // https://w3c.github.io/IndexedDB/#commit-a-transaction
+            None => return self.commit(),
+        };
+        self.current_request
+            .set(request.as_ref().map(|request| &**request));
+        self.perform_request(operation);
+    }
+
+    /// Report the outcome of the request being processed, and go on with the next one.
+    fn request_done(&self, outcome: Result<(), DOMErrorName>, result: HandleValue) {
+        if self.is_finished() {
+            // The request was reported as aborted along with the transaction.
+            return;
+        }
+        match (self.current_request.take(), outcome) {
+            (Some(request), Ok(())) => {
+                request.set_result(result);
+                self.fire_success(&request);
+            },
+            (Some(request), Err(error)) => {
+                request.set_error(error);
+                self.fire_error(&request, error);
+            },
+            (None, Ok(())) => {},
+            // The changes to the schema were checked beforehand, so this is a failure of
+            // the backend.
+            (None, Err(error)) => self.abort_with_error(Some(error)),
+        }
+        self.processing.set(false);
+        self.schedule_processing();
+    }
+
+    fn request_failed(&self, error: DOMErrorName) {
+        self.request_done(Err(error), HandleValue::undefined());
+    }
+
+    /// Perform an operation as part of the transaction, passing its result to `callback`.
+    fn perform<F>(&self, operation: AsyncOperation, callback: F)
+    where
+        F: FnOnce(&IDBTransaction, Result<OperationResult, IndexedDBError>) + Send + 'static,
+    {
+        let global = self.global();
+        let id = self.id.get().expect("Transaction wasn't started");
+        let (sender, receiver) = ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
+        self.route_reply(receiver, callback);
+        let _ = global
+            .resource_threads()
+            .send(IndexedDBThreadMsg::Operation(
+                sender,
+                global.origin().immutable().clone(),
+                self.db.name(),
+                id,
+                operation,
+            ));
+    }
+
+    #[allow(unrooted_must_root)]
+    fn perform_request(&self, operation: RequestOperation) {
+        match operation {
+            RequestOperation::Backend(operation, conversion) => {
+                self.perform(operation, move |this, result| {
+                    let global = this.global();
+                    let cx = global.get_cx();
+                    rooted!(in(*cx) let mut rval = UndefinedValue());
+                    let outcome = result
+                        .map(|result| {
+                            convert_result(&global, result, conversion, rval.handle_mut())
+                        })
+                        .map_err(error_name);
+                    this.request_done(outcome, rval.handle());
+                });
+            },
+            RequestOperation::Put {
+                store,
+                value,
+                key,
+                no_overwrite,
+            } => {
+                let metadata = match self.db.store_metadata(&store) {
+                    Some(metadata) => metadata,
+                    None => return self.request_failed(DOMErrorName::InvalidStateError),
+                };
+                let operation = match key {
+                    Some(key) => return self.put_record(metadata, key, value, no_overwrite),
+                    None => AsyncOperation::GenerateKey { store: store },
+                };
+                self.perform(operation, move |this, result| match result {
+                    Ok(OperationResult::Key(key)) => {
+                        this.put_generated_record(metadata, key, value, no_overwrite)
+                    },
+                    Ok(_) => this.request_failed(DOMErrorName::UnknownError),
+                    Err(error) => this.request_failed(error_name(error)),
+                });
+            },
+            RequestOperation::Iterate {
+                cursor,
+                key,
+                primary_key,
+                count,
+            } => {
+                let operation = cursor.iterate_operation(key, primary_key, count);
+                let cursor = Trusted::new(&*cursor);
+                self.perform(operation, move |this, result| {
+                    let cursor = cursor.root();
+                    let cx = this.global().get_cx();
+                    rooted!(in(*cx) let mut rval = UndefinedValue());
+                    let outcome = result
+                        .map(|result| match result {
+                            OperationResult::Record(Some(record)) => {
+                                cursor.set_record(record);
+                                rval.set(ObjectValue(cursor.reflector().get_jsobject().get()));
+                            },
+                            _ => {
+                                cursor.set_exhausted();
+                                rval.set(NullValue());
+                            },
+                        })
+                        .map_err(error_name);
+                    this.request_done(outcome, rval.handle());
+                });
+            },
+            RequestOperation::CreateIndex { store, index } => {
+                let operation = AsyncOperation::GetAllRecords {
+                    store: store.clone(),
+                    index: None,
+                    range: IndexedDBKeyRange::unbounded(),
+                    count: None,
+                };
+                self.perform(operation, move |this, result| {
+                    let records = match result {
+                        Ok(OperationResult::Records(records)) => records,
+                        Ok(_) => vec![],
+                        Err(error) => return this.request_failed(error_name(error)),
+                    };
+                    let global = this.global();
+                    let cx = global.get_cx();
+                    let keys = records
+                        .into_iter()
+                        .map(|record| {
+                            rooted!(in(*cx) let mut value = UndefinedValue());
+                            StructuredCloneData::Vector(record.value)
+                                .read(&global, value.handle_mut());
+                            (record.primary_key, index_keys(cx, value.handle(), &index))
+                        })
+                        .collect();
+                    let operation = AsyncOperation::CreateIndex {
+                        store: store,
+                        index: index,
+                        keys: keys,
+                    };
+                    this.perform(operation, |this, result| {
+                        let outcome = result.map(|_| ()).map_err(error_name);
+                        this.request_done(outcome, HandleValue::undefined());
+                    });
+                });
+            },
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#store-a-record-into-an-object-store>, for a key
+    /// generated by the object store, which is injected into the value if the object
+    /// store has a key path.
+    fn put_generated_record(
+        &self,
+        metadata: ObjectStoreMetadata,
+        key: IndexedDBKey,
+        mut value: Vec<u8>,
+        no_overwrite: bool,
+    ) {
+        if let Some(KeyPath::String(ref path)) = metadata.key_path {
+            let global = self.global();
+            let cx = global.get_cx();
+            rooted!(in(*cx) let mut object = UndefinedValue());
+            StructuredCloneData::Vector(value).read(&global, object.handle_mut());
+            if inject_key(cx, object.handle(), &key, path).is_err() {
+                return self.request_failed(DOMErrorName::DataError);
+            }
+            value = match StructuredCloneData::write(*cx, object.handle()) {
+                Ok(data) => data.move_to_arraybuffer(),
+                Err(_) => return self.request_failed(DOMErrorName::DataCloneError),
+            };
+        }
+        self.put_record(metadata, key, value, no_overwrite);
+    }
+
+    /// Store a record, along with the keys it has in each index of its object store.
+    fn put_record(
+        &self,
+        metadata: ObjectStoreMetadata,
+        key: IndexedDBKey,
+        value: Vec<u8>,
+        no_overwrite: bool,
+    ) {
+        let global = self.global();
+        let cx = global.get_cx();
+        rooted!(in(*cx) let mut object = UndefinedValue());
+        StructuredCloneData::Vector(value.clone()).read(&global, object.handle_mut());
+        let index_keys = metadata
+            .indexes
+            .iter()
+            .map(|index| (index.name.clone(), index_keys(cx, object.handle(), index)))
+            .collect();
+        let operation = AsyncOperation::PutRecord {
+            store: metadata.name,
+            key: key,
+            value: value,
+            index_keys: index_keys,
+            no_overwrite: no_overwrite,
+        };
+        self.perform(operation, |this, result| {
+            let cx = this.global().get_cx();
+            rooted!(in(*cx) let mut rval = UndefinedValue());
+            let outcome = result
+                .map(|result| {
+                    if let OperationResult::Key(key) = result {
+                        key_to_jsval(cx, &key, rval.handle_mut());
+                    }
+                })
+                .map_err(error_name);
+            this.request_done(outcome, rval.handle());
+        });
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#fire-a-success-event>
+    fn fire_success(&self, request: &IDBRequest) {
+        let state = self.state.replace(TransactionState::Active);
+        request.upcast::<EventTarget>().fire_event(atom!("success"));
+        if self.state.get() == TransactionState::Active {
+            self.state.set(state);
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#fire-an-error-event>
+    fn fire_error(&self, request: &IDBRequest, error: DOMErrorName) {
+        let state = self.state.replace(TransactionState::Active);
+        let event = request
+            .upcast::<EventTarget>()
+            .fire_bubbling_cancelable_event(atom!("error"));
+        if self.state.get() == TransactionState::Active {
+            self.state.set(state);
+        }
+        if !event.DefaultPrevented() {
+            self.abort_with_error(Some(error));
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#commit-a-transaction>
+    fn commit(&self) {
+        self.state.set(TransactionState::Committing);
+        let global = self.global();
+        let id = self.id.get().expect("Transaction wasn't started");
+        let (sender, receiver) = ProfiledIpc::channel(global.time_profiler_chan().clone()).unwrap();
+        self.route_reply(receiver, |this, result| this.committed(result));
+        let _ = global.resource_threads().send(IndexedDBThreadMsg::Commit(
+            sender,
+            global.origin().immutable().clone(),
+            self.db.name(),
+            id,
+        ));
+    }
+
+    /// Finish the transaction once the backend has saved its changes.
+    fn committed(&self, result: Result<(), IndexedDBError>) {
+        if self.is_finished() {
+            return;
+        }
+        if let Err(error) = result {
+            return self.abort_with_error(Some(error_name(error)));
+        }
+        self.state.set(TransactionState::Finished);
+        self.upcast::<EventTarget>().fire_event(atom!("complete"));
+
+        if let Some(request) = self.open_request.get() {
+            self.db.set_upgrade_transaction(None);
+            let request = request.upcast::<IDBRequest>();
+            request.set_transaction(None);
+            request.upcast::<EventTarget>().fire_event(atom!("success"));
+        }
+    }
+
+    fn send_abort(&self) {
+        let global = self.global();
+        if let Some(id) = self.id.get() {
+            let _ = global.resource_threads().send(IndexedDBThreadMsg::Abort(
+                global.origin().immutable().clone(),
+                self.db.name(),
+                id,
+            ));
+        }
+    }
+
+    /// <https://w3c.github.io/IndexedDB/#abort-a-transaction>
+    pub fn abort_with_error(&self, error: Option<DOMErrorName>) {
+        if self.is_finished() {
+            return;
+        }
+        self.state.set(TransactionState::Finished);
+        let global = self.global();
+        if let Some(error) = error {
+            self.error.set(Some(&DOMException::new(&global, error)));
+        }
+        self.send_abort();
+        if let Some(metadata) = self.previous_metadata.borrow_mut().take() {
+            self.db.set_metadata(metadata);
+        }
+
+        let this = Trusted::new(self);
+        let _ = global.database_access_task_source().queue(
+            task!(abort_transaction: move || {
+                this.root().fire_abort();
+            }),
+            &global,
+        );
+    }
+
+    #[allow(unrooted_must_root)]
+    fn fire_abort(&self) {
+        let current_request = self.current_request.take();
+        let requests: Vec<DomRoot<IDBRequest>> =
+            current_request
+                .into_iter()
+                .chain(self.requests.borrow_mut().drain(..).filter_map(|pending| {
+                    pending.request.map(|request| DomRoot::from_ref(&*request))
+                }))
+                .collect();
+        for request in requests {
+            request.set_error(DOMErrorName::AbortError);
+            request
+                .upcast::<EventTarget>()
+                .fire_bubbling_cancelable_event(atom!("error"));
+        }
+        self.upcast::<EventTarget>()
+            .fire_bubbling_event(atom!("abort"));
+
+        if let Some(request) = self.open_request.get() {
+            self.db.set_upgrade_transaction(None);
+            self.db.close_connection();
+            let request = request.upcast::<IDBRequest>();
+            request.set_error(DOMErrorName::AbortError);
+            request.set_transaction(None);
+            request
+                .upcast::<EventTarget>()
+                .fire_bubbling_cancelable_event(atom!("error"));
+        }
+    }
+}
+
+impl IDBTransactionMethods for IDBTransaction {
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-objectstorenames
+    fn ObjectStoreNames(&self) -> DomRoot<DOMStringList> {
+        let mut names = if self.mode == IndexedDBTxnMode::Versionchange {
+            self.db.object_store_names()
+        } else {
+            self.scope.borrow().clone()
+        };
+        names.sort();
+        DOMStringList::new(
+            &self.global(),
+            names.into_iter().map(DOMString::from).collect(),
+        )
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-mode
+    fn Mode(&self) -> IDBTransactionMode {
+        match self.mode {
+            IndexedDBTxnMode::Readonly => IDBTransactionMode::Readonly,
+            IndexedDBTxnMode::Readwrite => IDBTransactionMode::Readwrite,
+            IndexedDBTxnMode::Versionchange => IDBTransactionMode::Versionchange,
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-db
+    fn Db(&self) -> DomRoot<IDBDatabase> {
+        self.db()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-error
+    fn GetError(&self) -> Option<DomRoot<DOMException>> {
+        self.error.get()
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-objectstore
+    fn ObjectStore(&self, name: DOMString) -> Fallible<DomRoot<IDBObjectStore>> {
+        if self.is_finished() {
+            return Err(Error::InvalidState);
+        }
+        let name = String::from(name);
+        if !self.scope.borrow().contains(&name) || self.db.store_metadata(&name).is_none() {
+            return Err(Error::NotFound);
+        }
+        Ok(self.object_store(&name))
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-commit
+    fn Commit(&self) -> ErrorResult {
+        self.check_active().map_err(|_| Error::InvalidState)?;
+        self.state.set(TransactionState::Committing);
+        self.schedule_processing();
+        Ok(())
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-abort
+    fn Abort(&self) -> ErrorResult {
+        match self.state.get() {
+            TransactionState::Committing | TransactionState::Finished => Err(Error::InvalidState),
+            TransactionState::Active | TransactionState::Inactive => {
+                self.abort_with_error(None);
+                Ok(())
+            },
+        }
+    }
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-onabort
+    event_handler!(abort, GetOnabort, SetOnabort);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-oncomplete
+    event_handler!(complete, GetOncomplete, SetOncomplete);
+
+    // https://w3c.github.io/IndexedDB/#dom-idbtransaction-onerror
+    event_handler!(error, GetOnerror, SetOnerror);
+}
@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::compartments::enter_realm;
 use crate::dom::bindings::codegen::Bindings::EventBinding::EventMethods;
 use crate::dom::bindings::codegen::Bindings::MessageEventBinding;
 use crate::dom::bindings::codegen::Bindings::MessageEventBinding::MessageEventMethods;
@@ -14,11 +15,13 @@ use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::event::Event;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::messageport::MessagePort;
 use crate::dom::windowproxy::WindowProxy;
 use crate::script_runtime::JSContext;
 use dom_struct::dom_struct;
-use js::jsapi::{Heap, JSObject};
-use js::jsval::JSVal;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{Heap, JSObject, JS_FreezeObject};
+use js::jsval::{JSVal, UndefinedValue};
 use js::rust::HandleValue;
 use servo_atoms::Atom;
 use std::ptr::NonNull;
@@ -31,6 +34,9 @@ pub struct MessageEvent {
     origin: DOMString,
     source: Option<Dom<WindowProxy>>,
     lastEventId: DOMString,
+    /// The frozen array of the ports transferred with the message.
+    #[ignore_malloc_size_of = "mozjs"]
+    ports: Heap<JSVal>,
 }
 
 impl MessageEvent {
@@ -41,6 +47,7 @@ impl MessageEvent {
             DOMString::new(),
             None,
             DOMString::new(),
+            vec![],
         )
     }
 
@@ -50,6 +57,7 @@ impl MessageEvent {
         origin: DOMString,
         source: Option<&WindowProxy>,
         lastEventId: DOMString,
+        ports: Vec<DomRoot<MessagePort>>,
     ) -> DomRoot<MessageEvent> {
         let ev = Box::new(MessageEvent {
             event: Event::new_inherited(),
@@ -57,9 +65,11 @@ impl MessageEvent {
             origin: origin,
             source: source.map(Dom::from_ref),
             lastEventId: lastEventId,
+            ports: Heap::default(),
         });
         let ev = reflect_dom_object(ev, global, MessageEventBinding::Wrap);
         ev.data.set(data.get());
+        ev.ports.set(frozen_array(global, ports));
 
         ev
     }
@@ -73,8 +83,9 @@ impl MessageEvent {
         origin: DOMString,
         source: Option<&WindowProxy>,
         lastEventId: DOMString,
+        ports: Vec<DomRoot<MessagePort>>,
     ) -> DomRoot<MessageEvent> {
-        let ev = MessageEvent::new_initialized(global, data, origin, source, lastEventId);
+        let ev = MessageEvent::new_initialized(global, data, origin, source, lastEventId, ports);
         {
             let event = ev.upcast::<Event>();
             event.init_event(type_, bubbles, cancelable);
@@ -100,6 +111,7 @@ impl MessageEvent {
             init.origin.clone(),
             source.as_ref().map(|source| &**source),
             init.lastEventId.clone(),
+            init.ports.clone(),
         );
         Ok(ev)
     }
@@ -112,6 +124,7 @@ impl MessageEvent {
         message: HandleValue,
         origin: Option<&str>,
         source: Option<&WindowProxy>,
+        ports: Vec<DomRoot<MessagePort>>,
     ) {
         let messageevent = MessageEvent::new(
             scope,
@@ -122,6 +135,7 @@ impl MessageEvent {
             DOMString::from(origin.unwrap_or("")),
             source,
             DOMString::new(),
+            ports,
         );
         messageevent.upcast::<Event>().fire(target);
     }
@@ -150,8 +164,27 @@ impl MessageEventMethods for MessageEvent {
         self.lastEventId.clone()
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-messageevent-ports
+    fn Ports(&self, _cx: JSContext) -> JSVal {
+        self.ports.get()
+    }
+
     // https://dom.spec.whatwg.org/#dom-event-istrusted
     fn IsTrusted(&self) -> bool {
         self.event.IsTrusted()
     }
 }
+
+/// Create a frozen JS array of the given ports, in the realm of `global`.
+#[allow(unsafe_code)]
+fn frozen_array(global: &GlobalScope, ports: Vec<DomRoot<MessagePort>>) -> JSVal {
+    let cx = global.get_cx();
+    let _ac = enter_realm(global);
+    rooted!(in(*cx) let mut array = UndefinedValue());
+    unsafe {
+        ports.to_jsval(*cx, array.handle_mut());
+        rooted!(in(*cx) let object = array.to_object());
+        assert!(JS_FreezeObject(*cx, object.handle().into()));
+    }
+    array.get()
+}
@@ -0,0 +1,255 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::compartments::enter_realm;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::EventHandlerBinding::EventHandlerNonNull;
+use crate::dom::bindings::codegen::Bindings::MessagePortBinding;
+use crate::dom::bindings::codegen::Bindings::MessagePortBinding::MessagePortMethods;
+use crate::dom::bindings::conversions::root_from_object;
+use crate::dom::bindings::error::{Error, ErrorResult};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::structuredclone;
+use crate::dom::eventtarget::EventTarget;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::messageevent::MessageEvent;
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::task_source::TaskSource;
+use dom_struct::dom_struct;
+use js::jsapi::JSObject;
+use js::jsval::UndefinedValue;
+use js::rust::{CustomAutoRooterGuard, HandleValue};
+use msg::constellation_msg::{MessagePortId, MessagePortIndex, PipelineNamespaceId};
+use script_traits::transferable::{MessagePortImpl, PortMessageTask};
+use std::collections::VecDeque;
+use std::num::NonZeroU32;
+use std::rc::Rc;
+
+// https://html.spec.whatwg.org/multipage/#messageport
+#[dom_struct]
+pub struct MessagePort {
+    eventtarget: EventTarget,
+    message_port_id: MessagePortId,
+    #[ignore_malloc_size_of = "Defined in script_traits"]
+    port_impl: DomRefCell<MessagePortImpl>,
+}
+
+impl MessagePort {
+    fn new_inherited(port_impl: MessagePortImpl) -> MessagePort {
+        MessagePort {
+            eventtarget: EventTarget::new_inherited(),
+            message_port_id: *port_impl.message_port_id(),
+            port_impl: DomRefCell::new(port_impl),
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#create-a-new-messageport-object>
+    pub fn new(owner: &GlobalScope) -> DomRoot<MessagePort> {
+        let port_impl = MessagePortImpl::new(MessagePortId::new());
+        let port = reflect_dom_object(
+            Box::new(MessagePort::new_inherited(port_impl)),
+            owner,
+            MessagePortBinding::Wrap,
+        );
+        owner.track_message_port(&port, false);
+        port
+    }
+
+    pub fn message_port_id(&self) -> &MessagePortId {
+        &self.message_port_id
+    }
+
+    pub fn entangle(&self, other_id: MessagePortId) {
+        self.port_impl.borrow_mut().entangle(other_id);
+    }
+
+    pub fn detached(&self) -> bool {
+        self.port_impl.borrow().detached()
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#message-ports> transfer steps.
+    ///
+    /// Detach the port, returning the state to send to the receiving global, and the
+    /// data identifying the port in the serialized message.
+    pub fn transfer(&self) -> Result<(MessagePortImpl, u64), ()> {
+        // Step 1.
+        if self.detached() {
+            return Err(());
+        }
+        // Steps 2-5.
+        let port_impl = self.port_impl.borrow_mut().transfer();
+        self.global()
+            .untrack_message_port(&self.message_port_id, true);
+        let PipelineNamespaceId(namespace_id) = self.message_port_id.namespace_id;
+        let MessagePortIndex(index) = self.message_port_id.index;
+        let extra_data = ((namespace_id as u64) << 32) | index.get() as u64;
+        Ok((port_impl, extra_data))
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#message-ports> transfer-receiving steps.
+    ///
+    /// Returns the id of the port identified by `extra_data` in a serialized message.
+    pub fn transferred_port_id(extra_data: u64) -> Option<MessagePortId> {
+        let index = NonZeroU32::new(extra_data as u32)?;
+        Some(MessagePortId {
+            namespace_id: PipelineNamespaceId((extra_data >> 32) as u32),
+            index: MessagePortIndex(index),
+        })
+    }
+
+    /// Create the port of `owner` for a port transferred to it.
+    pub fn new_transferred(
+        owner: &GlobalScope,
+        port_impl: MessagePortImpl,
+    ) -> DomRoot<MessagePort> {
+        let port = reflect_dom_object(
+            Box::new(MessagePort::new_inherited(port_impl)),
+            owner,
+            MessagePortBinding::Wrap,
+        );
+        owner.track_message_port(&port, true);
+        port
+    }
+
+    /// Handle a task sent to this port by its entangled port.
+    pub fn handle_incoming(&self, task: PortMessageTask) {
+        let dispatchable = self.port_impl.borrow_mut().handle_incoming(task);
+        if dispatchable {
+            self.dispatch_next_message();
+        }
+    }
+
+    /// Complete the transfer of this port, with the tasks which were sent to it while
+    /// it was being transferred.
+    pub fn complete_transfer(&self, tasks: VecDeque<PortMessageTask>) {
+        let dispatchable = self.port_impl.borrow_mut().complete_transfer(tasks);
+        self.queue_messages(dispatchable);
+    }
+
+    /// Queue a task on the port message queue for each message that can now be
+    /// dispatched. The messages stay with the port until then, so that they follow it
+    /// if it's transferred in the meantime.
+    fn queue_messages(&self, count: usize) {
+        let global = self.global();
+        for _ in 0..count {
+            let this = Trusted::new(self);
+            let _ = global.port_message_queue().queue(
+                task!(dispatch_port_message: move || {
+                    this.root().dispatch_next_message();
+                }),
+                &global,
+            );
+        }
+    }
+
+    fn dispatch_next_message(&self) {
+        let task = self.port_impl.borrow_mut().next_task();
+        if let Some(task) = task {
+            self.dispatch_message(task);
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#message-port-post-message-steps>
+    /// Steps 7.1-7.5 of the task added to the port message queue.
+    fn dispatch_message(&self, task: PortMessageTask) {
+        let PortMessageTask { origin, data } = task;
+        let global = self.global();
+        let _ac = enter_realm(self);
+        rooted!(in(*global.get_cx()) let mut message = UndefinedValue());
+        let ports = match structuredclone::read(&global, data, message.handle_mut()) {
+            Ok(ports) => ports,
+            Err(()) => {
+                self.upcast::<EventTarget>()
+                    .fire_event(atom!("messageerror"));
+                return;
+            },
+        };
+        MessageEvent::dispatch_jsval(
+            self.upcast(),
+            &global,
+            message.handle(),
+            Some(&origin.ascii_serialization()),
+            None,
+            ports,
+        );
+    }
+}
+
+impl MessagePortMethods for MessagePort {
+    // https://html.spec.whatwg.org/multipage/#dom-messageport-postmessage
+    fn PostMessage(
+        &self,
+        cx: SafeJSContext,
+        message: HandleValue,
+        transfer: CustomAutoRooterGuard<Option<Vec<*mut JSObject>>>,
+    ) -> ErrorResult {
+        if self.detached() {
+            return Ok(());
+        }
+        // Step 1.
+        let target_port = self.port_impl.borrow().entangled_port_id();
+        // Steps 2-3.
+        if let Some(ref transfer) = *transfer {
+            for object in transfer {
+                if let Ok(port) = root_from_object::<MessagePort>(*object, *cx) {
+                    let port_id = Some(port.message_port_id);
+                    if port_id == Some(self.message_port_id) || port_id == target_port {
+                        return Err(Error::DataClone);
+                    }
+                }
+            }
+        }
+        // Steps 4-5.
+        let data = structuredclone::write(cx, message, Some(transfer))?;
+        // Step 6.
+        let target_port = match target_port {
+            Some(target_port) => target_port,
+            None => return Ok(()),
+        };
+        // Step 7.
+        let global = self.global();
+        let task = PortMessageTask {
+            origin: global.origin().immutable().clone(),
+            data: data,
+        };
+        global.post_messageport_msg(target_port, task);
+        Ok(())
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-messageport-start
+    fn Start(&self) {
+        let dispatchable = self.port_impl.borrow_mut().start();
+        self.queue_messages(dispatchable);
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-messageport-close
+    fn Close(&self) {
+        if self.detached() {
+            return;
+        }
+        self.port_impl.borrow_mut().close();
+        self.global()
+            .untrack_message_port(&self.message_port_id, false);
+    }
+
+    // https://html.spec.whatwg.org/multipage/#handler-messageport-onmessage
+    fn GetOnmessage(&self) -> Option<Rc<EventHandlerNonNull>> {
+        self.upcast::<EventTarget>()
+            .get_event_handler_common("message")
+    }
+
+    // https://html.spec.whatwg.org/multipage/#handler-messageport-onmessage
+    fn SetOnmessage(&self, listener: Option<Rc<EventHandlerNonNull>>) {
+        self.upcast::<EventTarget>()
+            .set_event_handler_common("message", listener);
+        // The first time onmessage is set, the port message queue is enabled.
+        self.Start();
+    }
+
+    // https://html.spec.whatwg.org/multipage/#handler-messageport-onmessageerror
+    event_handler!(messageerror, GetOnmessageerror, SetOnmessageerror);
+}
@@ -2875,6 +2875,12 @@ impl VirtualMethods for Node {
             list.as_children_list().children_changed(mutation);
         }
         self.owner_doc().content_and_heritage_changed(self);
+        if self
+            .downcast::<Element>()
+            .map_or(false, |element| *element.namespace() == ns!(svg))
+        {
+            SVGSVGElement::dirty_outermost_svg(self);
+        }
     }
 
     // This handles the ranges mentioned in steps 2-3 when removing a node.
@@ -467,6 +467,15 @@ impl Request {
     pub fn get_request(&self) -> NetTraitsRequest {
         self.request.borrow().clone()
     }
+
+    /// Create a request object for a request intercepted by a service worker.
+    pub fn from_net_request(global: &GlobalScope, request: NetTraitsRequest) -> DomRoot<Request> {
+        let r = Request::new(global, request.url());
+        r.Headers().set_headers(request.headers.clone());
+        r.Headers().set_guard(Guard::Immutable);
+        *r.request.borrow_mut() = request;
+        r
+    }
 }
 
 fn net_request_from_global(global: &GlobalScope, url: ServoUrl) -> NetTraitsRequest {
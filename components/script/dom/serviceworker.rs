@@ -13,7 +13,7 @@ use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::{reflect_dom_object, DomObject};
 use crate::dom::bindings::root::DomRoot;
 use crate::dom::bindings::str::USVString;
-use crate::dom::bindings::structuredclone::StructuredCloneData;
+use crate::dom::bindings::structuredclone;
 use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
 use crate::script_runtime::JSContext;
@@ -97,8 +97,8 @@ impl ServiceWorkerMethods for ServiceWorker {
             return Err(Error::InvalidState);
         }
         // Step 7
-        let data = StructuredCloneData::write(*cx, message)?;
-        let msg_vec = DOMMessage(data.move_to_arraybuffer());
+        let data = structuredclone::write(cx, message, None)?;
+        let msg_vec = DOMMessage(data.serialized);
         let _ = self
             .global()
             .script_to_constellation_chan()
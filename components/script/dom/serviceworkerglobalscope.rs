@@ -12,12 +12,13 @@ use crate::dom::bindings::codegen::Bindings::WorkerBinding::WorkerType;
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::root::{DomRoot, RootCollection, ThreadLocalStackRoots};
 use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::structuredclone;
 use crate::dom::dedicatedworkerglobalscope::AutoWorkerReset;
-use crate::dom::event::Event;
-use crate::dom::eventtarget::EventTarget;
 use crate::dom::extendableevent::ExtendableEvent;
 use crate::dom::extendablemessageevent::ExtendableMessageEvent;
+use crate::dom::fetchevent::FetchEvent;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::request::Request;
 use crate::dom::worker::TrustedWorkerAddress;
 use crate::dom::workerglobalscope::WorkerGlobalScope;
 use crate::fetch::load_whole_resource;
@@ -34,11 +35,13 @@ use ipc_channel::router::ROUTER;
 use js::jsapi::{JSContext, JS_AddInterruptCallback};
 use js::jsval::UndefinedValue;
 use msg::constellation_msg::PipelineId;
+use net_traits::request::ServiceWorkersMode;
 use net_traits::request::{CredentialsMode, Destination, ParserMetadata, Referrer, RequestBuilder};
-use net_traits::{CustomResponseMediator, IpcSend};
+use net_traits::{CustomResponseMediator, IpcSend, SiteForCookies};
 use script_traits::{
     ScopeThings, ServiceWorkerMsg, TimerEvent, WorkerGlobalScopeInit, WorkerScriptLoadOrigin,
 };
+use servo_atoms::Atom;
 use servo_config::pref;
 use servo_rand::random;
 use servo_url::ServoUrl;
@@ -260,6 +263,7 @@ impl ServiceWorkerGlobalScope {
         devtools_receiver: IpcReceiver<DevtoolScriptControlMsg>,
         swmanager_sender: IpcSender<ServiceWorkerMsg>,
         scope_url: ServoUrl,
+        install: bool,
     ) {
         let ScopeThings {
             script_url,
@@ -269,11 +273,7 @@ impl ServiceWorkerGlobalScope {
         } = scope_things;
 
         let serialized_worker_url = script_url.to_string();
-        let origin = GlobalScope::current()
-            .expect("No current global object")
-            .origin()
-            .immutable()
-            .clone();
+        let origin = script_url.origin();
         thread::Builder::new()
             .name(format!("ServiceWorker for {}", serialized_worker_url))
             .spawn(move || {
@@ -288,30 +288,21 @@ impl ServiceWorkerGlobalScope {
                 } = worker_load_origin;
 
                 let referrer = referrer_url.map(|referrer_url| Referrer::ReferrerUrl(referrer_url));
+                // A service worker isn't nested in any document, so it's its
+                // own site for cookies.
+                let site_for_cookies = SiteForCookies::Document(Some(origin.clone()));
 
                 let request = RequestBuilder::new(script_url.clone())
                     .destination(Destination::ServiceWorker)
                     .credentials_mode(CredentialsMode::Include)
                     .parser_metadata(ParserMetadata::NotParserInserted)
                     .use_url_credentials(true)
+                    .service_workers_mode(ServiceWorkersMode::None)
                     .pipeline_id(pipeline_id)
                     .referrer(referrer)
                     .referrer_policy(referrer_policy)
-                    .origin(origin);
-
-                let (url, source) = match load_whole_resource(
-                    request,
-                    &init.resource_threads.sender(),
-                    &GlobalScope::current().expect("No current global object"),
-                ) {
-                    Err(_) => {
-                        println!("error loading script {}", serialized_worker_url);
-                        return;
-                    },
-                    Ok((metadata, bytes)) => {
-                        (metadata.final_url, String::from_utf8(bytes).unwrap())
-                    },
-                };
+                    .origin(origin)
+                    .site_for_cookies(site_for_cookies.clone());
 
                 let runtime = new_rt_and_cx();
 
@@ -323,7 +314,7 @@ impl ServiceWorkerGlobalScope {
                 let (timer_chan, timer_port) = unbounded();
                 let global = ServiceWorkerGlobalScope::new(
                     init,
-                    url,
+                    script_url,
                     devtools_mpsc_port,
                     runtime,
                     own_sender,
@@ -334,6 +325,29 @@ impl ServiceWorkerGlobalScope {
                     scope_url,
                 );
                 let scope = global.upcast::<WorkerGlobalScope>();
+                let global_scope = global.upcast::<GlobalScope>();
+
+                let (url, source) = match load_whole_resource(
+                    request,
+                    &global_scope.resource_threads().sender(),
+                    &global_scope,
+                ) {
+                    Err(_) => {
+                        warn!("error loading script {}", serialized_worker_url);
+                        if install {
+                            let _ = global
+                                .swmanager_sender
+                                .send(ServiceWorkerMsg::InstallFailed(global.scope_url.clone()));
+                        }
+                        return;
+                    },
+                    Ok((metadata, bytes)) => (
+                        metadata.final_url,
+                        String::from_utf8_lossy(&bytes).into_owned(),
+                    ),
+                };
+                scope.set_url(url);
+                scope.set_site_for_cookies(site_for_cookies);
 
                 unsafe {
                     // Handle interrupt requests
@@ -351,7 +365,10 @@ impl ServiceWorkerGlobalScope {
                     })
                     .expect("Thread spawning failed");
 
-                global.dispatch_activate();
+                if install {
+                    global.dispatch_lifecycle_event(atom!("install"));
+                    global_scope.perform_a_microtask_checkpoint();
+                }
                 let reporter_name = format!("service-worker-reporter-{}", random::<u64>());
                 scope
                     .upcast::<GlobalScope>()
@@ -366,6 +383,7 @@ impl ServiceWorkerGlobalScope {
                             while !scope.is_closing() {
                                 run_worker_event_loop(&*global, None);
                             }
+                            scope.upcast::<GlobalScope>().close_indexeddb_connections();
                         },
                         reporter_name,
                         scope.script_chan(),
@@ -414,18 +432,19 @@ impl ServiceWorkerGlobalScope {
                 let target = self.upcast();
                 let _ac = enter_realm(&*scope);
                 rooted!(in(*scope.get_cx()) let mut message = UndefinedValue());
-                data.read(scope.upcast(), message.handle_mut());
+                if structuredclone::read(scope.upcast(), data, message.handle_mut()).is_err() {
+                    return;
+                }
                 ExtendableMessageEvent::dispatch_jsval(target, scope.upcast(), message.handle());
             },
             CommonWorker(WorkerScriptMsg::Common(msg)) => {
                 self.upcast::<WorkerGlobalScope>().process_event(msg);
             },
             Response(mediator) => {
-                // TODO XXXcreativcoder This will eventually use a FetchEvent interface to fire event
-                // when we have the Request and Response dom api's implemented
-                // https://w3c.github.io/ServiceWorker/#fetchevent-interface
-                self.upcast::<EventTarget>().fire_event(atom!("fetch"));
-                let _ = mediator.response_chan.send(None);
+                let scope = self.upcast::<WorkerGlobalScope>();
+                let _ac = enter_realm(&*scope);
+                let request = Request::from_net_request(scope.upcast(), mediator.request.build());
+                FetchEvent::dispatch(self, &request, mediator.response_chan);
             },
             WakeUp => {},
         }
@@ -437,10 +456,32 @@ impl ServiceWorkerGlobalScope {
         })
     }
 
-    fn dispatch_activate(&self) {
-        let event = ExtendableEvent::new(self, atom!("activate"), false, false);
-        let event = (&*event).upcast::<Event>();
-        self.upcast::<EventTarget>().dispatch_event(event);
+    /// Fire an install or activate event.
+    /// <https://w3c.github.io/ServiceWorker/#installation-algorithm>
+    /// <https://w3c.github.io/ServiceWorker/#activation-algorithm>
+    fn dispatch_lifecycle_event(&self, type_: Atom) {
+        let _ac = enter_realm(self);
+        let event = ExtendableEvent::new(self, type_, false, false);
+        event.fire(self);
+    }
+
+    /// Called once the lifetime of an extendable event fired at this worker is no longer
+    /// extended, to move on to the next step of the service worker lifecycle.
+    pub fn lifetime_promises_settled(&self, type_: &Atom, rejected: bool) {
+        match *type_ {
+            atom!("install") if rejected => {
+                let _ = self
+                    .swmanager_sender
+                    .send(ServiceWorkerMsg::InstallFailed(self.scope_url.clone()));
+            },
+            atom!("install") => self.dispatch_lifecycle_event(atom!("activate")),
+            atom!("activate") => {
+                let _ = self
+                    .swmanager_sender
+                    .send(ServiceWorkerMsg::Activated(self.scope_url.clone()));
+            },
+            _ => {},
+        }
     }
 }
 
@@ -455,6 +496,15 @@ unsafe extern "C" fn interrupt_callback(cx: *mut JSContext) -> bool {
 }
 
 impl ServiceWorkerGlobalScopeMethods for ServiceWorkerGlobalScope {
+    // https://w3c.github.io/ServiceWorker/#dom-serviceworkerglobalscope-oninstall
+    event_handler!(install, GetOninstall, SetOninstall);
+
+    // https://w3c.github.io/ServiceWorker/#dom-serviceworkerglobalscope-onactivate
+    event_handler!(activate, GetOnactivate, SetOnactivate);
+
+    // https://w3c.github.io/ServiceWorker/#dom-serviceworkerglobalscope-onfetch
+    event_handler!(fetch, GetOnfetch, SetOnfetch);
+
     // https://w3c.github.io/ServiceWorker/#dom-serviceworkerglobalscope-onmessage
     event_handler!(message, GetOnmessage, SetOnmessage);
 
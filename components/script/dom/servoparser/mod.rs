@@ -46,6 +46,7 @@ use html5ever::{Attribute, ExpandedName, LocalName, QualName};
 use hyper_serde::Serde;
 use mime::{self, Mime};
 use msg::constellation_msg::PipelineId;
+use net_traits::csp::Violation;
 use net_traits::{FetchMetadata, FetchResponseListener, Metadata, NetworkError};
 use net_traits::{ResourceFetchTiming, ResourceTimingType};
 use profile_traits::time::{
@@ -846,6 +847,10 @@ impl FetchResponseListener for ParserContext {
             .performance()
             .queue_entry(performance_entry.upcast::<PerformanceEntry>(), true);
     }
+
+    fn process_csp_violations(&mut self, _violations: Vec<Violation>) {
+        // Navigation requests are checked by the initiating document.
+    }
 }
 
 impl PreInvoke for ParserContext {}
@@ -7,10 +7,13 @@ use crate::dom::bindings::codegen::Bindings::SVGSVGElementBinding;
 use crate::dom::bindings::inheritance::Castable;
 use crate::dom::bindings::root::{DomRoot, LayoutDom};
 use crate::dom::bindings::str::DOMString;
+use crate::dom::characterdata::{CharacterData, LayoutCharacterDataHelpers};
 use crate::dom::document::Document;
-use crate::dom::element::{AttributeMutation, Element, RawLayoutElementHelpers};
-use crate::dom::node::Node;
+use crate::dom::element::RawLayoutElementHelpers;
+use crate::dom::element::{AttributeMutation, Element, LayoutElementHelpers};
+use crate::dom::node::{LayoutNodeHelpers, Node, NodeDamage, ShadowIncluding};
 use crate::dom::svggraphicselement::SVGGraphicsElement;
+use crate::dom::text::Text;
 use crate::dom::virtualmethods::VirtualMethods;
 use dom_struct::dom_struct;
 use html5ever::{LocalName, Prefix};
@@ -48,6 +51,49 @@ impl SVGSVGElement {
             SVGSVGElementBinding::Wrap,
         )
     }
+
+    /// Layout rasterizes an `<svg>` element together with its whole subtree,
+    /// so changes to any part of it must dirty the outermost `<svg>` element.
+    pub fn dirty_outermost_svg(node: &Node) {
+        let outermost_svg = node
+            .inclusive_ancestors(ShadowIncluding::No)
+            .filter(|ancestor| ancestor.is::<SVGSVGElement>())
+            .last();
+        if let Some(svg) = outermost_svg {
+            svg.dirty(NodeDamage::OtherNodeDamage);
+        }
+    }
+}
+
+/// Copies an SVG element and its SVG descendants into the tree layout
+/// rasterizes. Elements nested deeper than the rasterizer supports are left
+/// out, which also keeps this recursion from overflowing the stack.
+#[allow(unsafe_code)]
+unsafe fn svg_tree_for_layout(element: LayoutDom<Element>, depth: usize) -> servo_svg::Element {
+    let mut tree = servo_svg::Element::new(&element.local_name());
+    tree.attributes = (*element.unsafe_get())
+        .get_attrs_for_layout()
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_owned()))
+        .collect();
+
+    let mut child = element.upcast::<Node>().first_child_ref();
+    while let Some(node) = child {
+        if let Some(element) = node.downcast::<Element>() {
+            if *element.namespace() == ns!(svg) && depth < servo_svg::MAX_NESTING_DEPTH {
+                tree.children
+                    .push(servo_svg::Node::Element(svg_tree_for_layout(
+                        element,
+                        depth + 1,
+                    )));
+            }
+        } else if node.downcast::<Text>().is_some() {
+            let text = node.downcast::<CharacterData>().unwrap().data_for_layout();
+            tree.children.push(servo_svg::Node::Text(text.to_owned()));
+        }
+        child = node.next_sibling_ref();
+    }
+    tree
 }
 
 pub trait LayoutSVGSVGElementHelpers {
@@ -69,6 +115,7 @@ impl LayoutSVGSVGElementHelpers for LayoutDom<SVGSVGElement> {
             SVGSVGData {
                 width: width_attr.map_or(DEFAULT_WIDTH, |val| val.as_uint()),
                 height: height_attr.map_or(DEFAULT_HEIGHT, |val| val.as_uint()),
+                tree: svg_tree_for_layout(self.upcast(), 1),
             }
         }
     }
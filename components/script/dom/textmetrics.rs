@@ -0,0 +1,98 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use crate::dom::bindings::codegen::Bindings::TextMetricsBinding;
+use crate::dom::bindings::codegen::Bindings::TextMetricsBinding::TextMetricsMethods;
+use crate::dom::bindings::num::Finite;
+use crate::dom::bindings::reflector::{reflect_dom_object, Reflector};
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::globalscope::GlobalScope;
+use canvas_traits::canvas;
+use dom_struct::dom_struct;
+
+// https://html.spec.whatwg.org/multipage/#textmetrics
+#[dom_struct]
+pub struct TextMetrics {
+    reflector_: Reflector,
+    metrics: canvas::TextMetrics,
+}
+
+impl TextMetrics {
+    fn new_inherited(metrics: canvas::TextMetrics) -> TextMetrics {
+        TextMetrics {
+            reflector_: Reflector::new(),
+            metrics: metrics,
+        }
+    }
+
+    pub fn new(global: &GlobalScope, metrics: canvas::TextMetrics) -> DomRoot<TextMetrics> {
+        reflect_dom_object(
+            Box::new(TextMetrics::new_inherited(metrics)),
+            global,
+            TextMetricsBinding::Wrap,
+        )
+    }
+}
+
+impl TextMetricsMethods for TextMetrics {
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-width
+    fn Width(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.width)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-actualboundingboxleft
+    fn ActualBoundingBoxLeft(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.actual_bounding_box_left)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-actualboundingboxright
+    fn ActualBoundingBoxRight(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.actual_bounding_box_right)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-fontboundingboxascent
+    fn FontBoundingBoxAscent(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.font_bounding_box_ascent)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-fontboundingboxdescent
+    fn FontBoundingBoxDescent(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.font_bounding_box_descent)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-actualboundingboxascent
+    fn ActualBoundingBoxAscent(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.actual_bounding_box_ascent)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-actualboundingboxdescent
+    fn ActualBoundingBoxDescent(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.actual_bounding_box_descent)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-emheightascent
+    fn EmHeightAscent(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.em_height_ascent)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-emheightdescent
+    fn EmHeightDescent(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.em_height_descent)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-hangingbaseline
+    fn HangingBaseline(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.hanging_baseline)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-alphabeticbaseline
+    fn AlphabeticBaseline(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.alphabetic_baseline)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-textmetrics-ideographicbaseline
+    fn IdeographicBaseline(&self) -> Finite<f64> {
+        Finite::wrap(self.metrics.ideographic_baseline)
+    }
+}
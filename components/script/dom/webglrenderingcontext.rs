@@ -583,8 +583,13 @@ impl WebGLRenderingContext {
                 };
 
                 let size = Size2D::new(img.width, img.height);
+                let bytes = if img.is_animated() {
+                    IpcSharedMemory::from_bytes(img.first_frame_bytes())
+                } else {
+                    img.bytes.clone()
+                };
 
-                TexPixels::new(img.bytes.clone(), size, img.format, false)
+                TexPixels::new(bytes, size, img.format, false)
             },
             // TODO(emilio): Getting canvas data is implemented in CanvasRenderingContext2D,
             // but we need to refactor it moving it to `HTMLCanvasElement` and support
@@ -204,7 +204,9 @@ impl WebSocket {
         let request = RequestBuilder::new(url_record)
             .origin(global.origin().immutable().clone())
             .mode(RequestMode::WebSocket { protocols })
-            .referrer(Some(Referrer::NoReferrer));
+            .referrer(Some(Referrer::NoReferrer))
+            .csp_list(global.get_csp_list())
+            .site_for_cookies(global.site_for_cookies());
 
         let channels = FetchChannels::WebSocket {
             event_sender: resource_event_sender,
@@ -240,6 +242,14 @@ impl WebSocket {
                 WebSocketNetworkEvent::Fail => {
                     fail_the_websocket_connection(address.clone(), &task_source, &canceller);
                 },
+                WebSocketNetworkEvent::ReportCspViolations(violations) => {
+                    let address = address.clone();
+                    let task = task!(report_csp_violations: move || {
+                        let ws = address.root();
+                        ws.global().report_csp_violations(violations, None);
+                    });
+                    let _ = task_source.queue_with_canceller(task, &canceller);
+                },
                 WebSocketNetworkEvent::Close(code, reason) => {
                     close_the_websocket_connection(
                         address.clone(),
@@ -598,6 +608,7 @@ impl TaskOnce for MessageReceivedTask {
                 message.handle(),
                 Some(&ws.origin().ascii_serialization()),
                 None,
+                vec![],
             );
         }
     }
@@ -24,7 +24,7 @@ use crate::dom::bindings::refcounted::Trusted;
 use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom};
 use crate::dom::bindings::str::{DOMString, USVString};
-use crate::dom::bindings::structuredclone::StructuredCloneData;
+use crate::dom::bindings::structuredclone;
 use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::bindings::utils::{GlobalStaticData, WindowProxyHandler};
 use crate::dom::bindings::weakref::DOMTracker;
@@ -39,6 +39,7 @@ use crate::dom::eventtarget::EventTarget;
 use crate::dom::globalscope::GlobalScope;
 use crate::dom::hashchangeevent::HashChangeEvent;
 use crate::dom::history::History;
+use crate::dom::idbfactory::IDBFactory;
 use crate::dom::location::Location;
 use crate::dom::mediaquerylist::{MediaQueryList, MediaQueryListMatchState};
 use crate::dom::mediaquerylistevent::MediaQueryListEvent;
@@ -80,12 +81,13 @@ use euclid::{Point2D, Rect, Scale, Size2D, Vector2D};
 use ipc_channel::ipc::{channel, IpcSender};
 use ipc_channel::router::ROUTER;
 use js::jsapi::JSAutoRealm;
+use js::jsapi::JSObject;
 use js::jsapi::JSPROP_ENUMERATE;
 use js::jsapi::{GCReason, JS_GC};
 use js::jsval::JSVal;
 use js::jsval::UndefinedValue;
 use js::rust::wrappers::JS_DefineProperty;
-use js::rust::HandleValue;
+use js::rust::{CustomAutoRooterGuard, HandleValue};
 use media::WindowGLContext;
 use msg::constellation_msg::PipelineId;
 use net_traits::image_cache::{ImageCache, ImageResponder, ImageResponse};
@@ -102,6 +104,7 @@ use script_layout_interface::rpc::{
     NodeScrollIdResponse, ResolvedStyleResponse, TextIndexResponse,
 };
 use script_layout_interface::{PendingImageState, TrustedNodeAddress};
+use script_traits::transferable::StructuredSerializedData;
 use script_traits::webdriver_msg::{WebDriverJSError, WebDriverJSResult};
 use script_traits::{ConstellationControlMsg, DocumentState, HistoryEntryReplacement, LoadData};
 use script_traits::{ScriptMsg, ScriptToConstellationChan, ScrollState, TimerEvent, TimerEventId};
@@ -606,6 +609,24 @@ impl WindowMethods for Window {
         receiver.recv().unwrap();
     }
 
+    // https://html.spec.whatwg.org/multipage/#dom-confirm
+    fn Confirm(&self, message: DOMString) -> bool {
+        let (sender, receiver) =
+            ProfiledIpc::channel(self.global().time_profiler_chan().clone()).unwrap();
+        let msg = EmbedderMsg::Confirm(message.to_string(), sender);
+        self.send_to_embedder(msg);
+        receiver.recv().unwrap_or(false)
+    }
+
+    // https://html.spec.whatwg.org/multipage/#dom-prompt
+    fn Prompt(&self, message: DOMString, default: DOMString) -> Option<DOMString> {
+        let (sender, receiver) =
+            ProfiledIpc::channel(self.global().time_profiler_chan().clone()).unwrap();
+        let msg = EmbedderMsg::Prompt(message.to_string(), default.to_string(), sender);
+        self.send_to_embedder(msg);
+        receiver.recv().unwrap_or(None).map(DOMString::from)
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-window-stop
     fn Stop(&self) {
         // TODO: Cancel ongoing navigation.
@@ -722,6 +743,11 @@ impl WindowMethods for Window {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    fn IndexedDB(&self) -> DomRoot<IDBFactory> {
+        self.upcast::<GlobalScope>().indexeddb()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-frameelement
     fn GetFrameElement(&self) -> Option<DomRoot<Element>> {
         // Steps 1-3.
@@ -912,7 +938,13 @@ impl WindowMethods for Window {
     }
 
     // https://html.spec.whatwg.org/multipage/#dom-window-postmessage
-    fn PostMessage(&self, cx: JSContext, message: HandleValue, origin: DOMString) -> ErrorResult {
+    fn PostMessage(
+        &self,
+        cx: JSContext,
+        message: HandleValue,
+        origin: DOMString,
+        transfer: CustomAutoRooterGuard<Option<Vec<*mut JSObject>>>,
+    ) -> ErrorResult {
         let source_global = GlobalScope::incumbent().expect("no incumbent global??");
         let source = source_global.as_window();
 
@@ -927,8 +959,7 @@ impl WindowMethods for Window {
         };
 
         // Step 1-2, 6-8.
-        // TODO(#12717): Should implement the `transfer` argument.
-        let data = StructuredCloneData::write(*cx, message)?;
+        let data = structuredclone::write(cx, message, Some(transfer))?;
 
         // Step 9.
         self.post_message(origin, &*source.window_proxy(), data);
@@ -1296,6 +1327,9 @@ impl Window {
         // script.
         // TODO: ensure that this doesn't happen!
 
+        self.upcast::<GlobalScope>().remove_message_ports_router();
+        self.upcast::<GlobalScope>().close_indexeddb_connections();
+
         self.current_state.set(WindowState::Zombie);
         *self.js_runtime.borrow_mut() = None;
         self.window_proxy.set(None);
@@ -2271,7 +2305,7 @@ impl Window {
         &self,
         target_origin: Option<ImmutableOrigin>,
         source: &WindowProxy,
-        serialize_with_transfer_result: StructuredCloneData,
+        serialize_with_transfer_result: StructuredSerializedData,
     ) {
         let this = Trusted::new(self);
         let source = Trusted::new(source);
@@ -2291,15 +2325,19 @@ impl Window {
             let obj = this.reflector().get_jsobject();
             let _ac = JSAutoRealm::new(*cx, obj.get());
             rooted!(in(*cx) let mut message_clone = UndefinedValue());
-            serialize_with_transfer_result.read(
+            let new_ports = match structuredclone::read(
                 this.upcast(),
+                serialize_with_transfer_result,
                 message_clone.handle_mut(),
-            );
-
-            // Step 7.6.
-            // TODO: MessagePort array.
+            ) {
+                Ok(new_ports) => new_ports,
+                Err(()) => {
+                    this.upcast::<EventTarget>().fire_event(atom!("messageerror"));
+                    return;
+                },
+            };
 
-            // Step 7.7.
+            // Step 7.6-7.7.
             // TODO(#12719): Set the other attributes.
             MessageEvent::dispatch_jsval(
                 this.upcast(),
@@ -2307,6 +2345,7 @@ impl Window {
                 message_clone.handle(),
                 None,
                 Some(&*source),
+                new_ports,
             );
         });
         // FIXME(nox): Why are errors silenced here?
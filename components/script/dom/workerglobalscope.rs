@@ -19,6 +19,7 @@ use crate::dom::bindings::trace::RootedTraceableBox;
 use crate::dom::crypto::Crypto;
 use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
 use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbfactory::IDBFactory;
 use crate::dom::performance::Performance;
 use crate::dom::promise::Promise;
 use crate::dom::serviceworkerglobalscope::ServiceWorkerGlobalScope;
@@ -29,10 +30,12 @@ use crate::fetch;
 use crate::script_runtime::JSContext;
 use crate::script_runtime::{get_reports, CommonScriptMsg, Runtime, ScriptChan, ScriptPort};
 use crate::task::TaskCanceller;
+use crate::task_source::database_access::DatabaseAccessTaskSource;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::performance_timeline::PerformanceTimelineTaskSource;
+use crate::task_source::port_message::PortMessageQueue;
 use crate::task_source::remote_event::RemoteEventTaskSource;
 use crate::task_source::websocket::WebsocketTaskSource;
 use crate::timers::{IsInterval, TimerCallback};
@@ -45,10 +48,11 @@ use js::jsval::UndefinedValue;
 use js::panic::maybe_resume_unwind;
 use js::rust::{HandleValue, ParentRuntime};
 use msg::constellation_msg::{PipelineId, PipelineNamespace};
+use net_traits::csp::CspList;
 use net_traits::request::{
     CredentialsMode, Destination, ParserMetadata, RequestBuilder as NetRequestInit,
 };
-use net_traits::IpcSend;
+use net_traits::{IpcSend, SiteForCookies};
 use script_traits::WorkerGlobalScopeInit;
 use script_traits::{TimerEvent, TimerEventId};
 use servo_url::{MutableOrigin, ServoUrl};
@@ -91,6 +95,10 @@ pub struct WorkerGlobalScope {
 
     worker_id: WorkerId,
     worker_url: DomRefCell<ServoUrl>,
+    /// <https://html.spec.whatwg.org/multipage/#concept-workerglobalscope-csp-list>
+    csp_list: DomRefCell<Option<CspList>>,
+    /// The site for cookies of the document that created this worker.
+    site_for_cookies: DomRefCell<SiteForCookies>,
     #[ignore_malloc_size_of = "Arc"]
     closing: Option<Arc<AtomicBool>>,
     #[ignore_malloc_size_of = "Defined in js"]
@@ -144,6 +152,8 @@ impl WorkerGlobalScope {
             worker_name,
             worker_type,
             worker_url: DomRefCell::new(worker_url),
+            csp_list: DomRefCell::new(None),
+            site_for_cookies: DomRefCell::new(SiteForCookies::Document(None)),
             closing,
             runtime,
             location: Default::default(),
@@ -188,6 +198,22 @@ impl WorkerGlobalScope {
         *self.worker_url.borrow_mut() = url;
     }
 
+    pub fn get_csp_list(&self) -> Option<CspList> {
+        self.csp_list.borrow().clone()
+    }
+
+    pub fn set_csp_list(&self, csp_list: Option<CspList>) {
+        *self.csp_list.borrow_mut() = csp_list;
+    }
+
+    pub fn site_for_cookies(&self) -> SiteForCookies {
+        self.site_for_cookies.borrow().clone()
+    }
+
+    pub fn set_site_for_cookies(&self, site_for_cookies: SiteForCookies) {
+        *self.site_for_cookies.borrow_mut() = site_for_cookies;
+    }
+
     pub fn get_worker_id(&self) -> WorkerId {
         self.worker_id.clone()
     }
@@ -220,6 +246,12 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
 
     // https://html.spec.whatwg.org/multipage/#dom-workerglobalscope-importscripts
     fn ImportScripts(&self, url_strings: Vec<DOMString>) -> ErrorResult {
+        if self.worker_type == WorkerType::Module {
+            return Err(Error::Type(
+                "importScripts() is not supported in module workers".to_owned(),
+            ));
+        }
+
         let mut urls = Vec::with_capacity(url_strings.len());
         for url in url_strings {
             let url = self.worker_url.borrow().join(&url);
@@ -239,7 +271,9 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
                 .use_url_credentials(true)
                 .origin(global_scope.origin().immutable().clone())
                 .pipeline_id(Some(self.upcast::<GlobalScope>().pipeline_id()))
-                .referrer_policy(None);
+                .referrer_policy(None)
+                .csp_list(global_scope.get_csp_list())
+                .site_for_cookies(global_scope.site_for_cookies());
 
             let (url, source) = match fetch::load_whole_resource(
                 request,
@@ -282,6 +316,11 @@ impl WorkerGlobalScopeMethods for WorkerGlobalScope {
         self.upcast::<GlobalScope>().crypto()
     }
 
+    // https://w3c.github.io/IndexedDB/#dom-windoworworkerglobalscope-indexeddb
+    fn IndexedDB(&self) -> DomRoot<IDBFactory> {
+        self.upcast::<GlobalScope>().indexeddb()
+    }
+
     // https://html.spec.whatwg.org/multipage/#dom-windowbase64-btoa
     fn Btoa(&self, btoa: DOMString) -> Fallible<DOMString> {
         base64_btoa(btoa)
@@ -465,6 +504,14 @@ impl WorkerGlobalScope {
         WebsocketTaskSource(self.script_chan(), self.pipeline_id())
     }
 
+    pub fn database_access_task_source(&self) -> DatabaseAccessTaskSource {
+        DatabaseAccessTaskSource(self.script_chan(), self.pipeline_id())
+    }
+
+    pub fn port_message_queue(&self) -> PortMessageQueue {
+        PortMessageQueue(self.script_chan(), self.pipeline_id())
+    }
+
     pub fn new_script_pair(&self) -> (Box<dyn ScriptChan + Send>, Box<dyn ScriptPort + Send>) {
         let dedicated = self.downcast::<DedicatedWorkerGlobalScope>();
         if let Some(dedicated) = dedicated {
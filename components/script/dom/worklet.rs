@@ -52,6 +52,7 @@ use net_traits::request::Destination;
 use net_traits::request::RequestBuilder;
 use net_traits::request::RequestMode;
 use net_traits::IpcSend;
+use net_traits::SiteForCookies;
 use servo_url::ImmutableOrigin;
 use servo_url::ServoUrl;
 use std::cmp::max;
@@ -143,6 +144,7 @@ impl WorkletMethods for Worklet {
             self.worklet_id,
             self.global_type,
             self.window.origin().immutable().clone(),
+            global.site_for_cookies(),
             global.api_base_url(),
             module_url_record,
             options.credentials.clone(),
@@ -300,6 +302,7 @@ impl WorkletThreadPool {
         worklet_id: WorkletId,
         global_type: WorkletGlobalScopeType,
         origin: ImmutableOrigin,
+        site_for_cookies: SiteForCookies,
         base_url: ServoUrl,
         script_url: ServoUrl,
         credentials: RequestCredentials,
@@ -317,6 +320,7 @@ impl WorkletThreadPool {
                 worklet_id: worklet_id,
                 global_type: global_type,
                 origin: origin.clone(),
+                site_for_cookies: site_for_cookies.clone(),
                 base_url: base_url.clone(),
                 script_url: script_url.clone(),
                 credentials: credentials,
@@ -371,6 +375,7 @@ enum WorkletControl {
         worklet_id: WorkletId,
         global_type: WorkletGlobalScopeType,
         origin: ImmutableOrigin,
+        site_for_cookies: SiteForCookies,
         base_url: ServoUrl,
         script_url: ServoUrl,
         credentials: RequestCredentials,
@@ -612,6 +617,7 @@ impl WorkletThread {
         global_scope: &WorkletGlobalScope,
         pipeline_id: PipelineId,
         origin: ImmutableOrigin,
+        site_for_cookies: SiteForCookies,
         script_url: ServoUrl,
         credentials: RequestCredentials,
         pending_tasks_struct: PendingTasksStruct,
@@ -630,7 +636,8 @@ impl WorkletThread {
             .destination(Destination::Script)
             .mode(RequestMode::CorsMode)
             .credentials_mode(credentials.into())
-            .origin(origin);
+            .origin(origin)
+            .site_for_cookies(site_for_cookies);
 
         let script = load_whole_resource(
             request,
@@ -692,6 +699,7 @@ impl WorkletThread {
                 worklet_id,
                 global_type,
                 origin,
+                site_for_cookies,
                 base_url,
                 script_url,
                 credentials,
@@ -704,6 +712,7 @@ impl WorkletThread {
                     &*global,
                     pipeline_id,
                     origin,
+                    site_for_cookies,
                     script_url,
                     credentials,
                     pending_tasks_struct,
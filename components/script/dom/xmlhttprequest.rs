@@ -59,6 +59,7 @@ use js::jsval::{JSVal, NullValue, UndefinedValue};
 use js::rust::wrappers::JS_ParseJSON;
 use js::typedarray::{ArrayBuffer, CreateWith};
 use mime::{self, Mime, Name};
+use net_traits::csp::Violation;
 use net_traits::request::{CredentialsMode, Destination, Referrer, RequestBuilder, RequestMode};
 use net_traits::trim_http_whitespace;
 use net_traits::CoreResourceMsg::Fetch;
@@ -281,6 +282,11 @@ impl XMLHttpRequest {
             fn submit_resource_timing(&mut self) {
                 network_listener::submit_timing(self)
             }
+
+            fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+                self.resource_timing_global()
+                    .report_csp_violations(violations, None);
+            }
         }
 
         impl ResourceTimingListener for XHRContext {
@@ -652,6 +658,8 @@ impl XMLHttpRequestMethods for XMLHttpRequest {
             .credentials_mode(credentials_mode)
             .use_url_credentials(use_url_credentials)
             .origin(self.global().origin().immutable().clone())
+            .csp_list(self.global().get_csp_list())
+            .site_for_cookies(self.global().site_for_cookies())
             .referrer(
                 self.referrer_url
                     .clone()
@@ -26,6 +26,7 @@ use crate::network_listener::{
 use crate::task_source::TaskSourceName;
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
+use net_traits::csp::Violation;
 use net_traits::request::RequestBuilder;
 use net_traits::request::{Request as NetTraitsRequest, ServiceWorkersMode};
 use net_traits::CoreResourceMsg::Fetch as NetTraitsFetch;
@@ -124,6 +125,13 @@ fn request_init_from_request(request: NetTraitsRequest) -> RequestBuilder {
         integrity_metadata: "".to_owned(),
         url_list: vec![],
         parser_metadata: request.parser_metadata,
+        csp_list: GlobalScope::current()
+            .expect("No current global object")
+            .get_csp_list(),
+        cryptographic_nonce_metadata: request.cryptographic_nonce_metadata.clone(),
+        site_for_cookies: GlobalScope::current()
+            .expect("No current global object")
+            .site_for_cookies(),
     }
 }
 
@@ -279,6 +287,11 @@ impl FetchResponseListener for FetchContext {
             _ => {},
         };
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        self.resource_timing_global()
+            .report_csp_violations(violations, None);
+    }
 }
 
 impl ResourceTimingListener for FetchContext {
@@ -327,6 +340,9 @@ pub fn load_whole_resource(
                 })
             },
             FetchResponseMsg::ProcessResponseChunk(data) => buf.extend_from_slice(&data),
+            FetchResponseMsg::ProcessCspViolations(violations) => {
+                global.report_csp_violations(violations, None)
+            },
             FetchResponseMsg::ProcessResponseEOF(Ok(_)) => {
                 let metadata = metadata.unwrap();
                 if let Some(timing) = &metadata.timing {
@@ -0,0 +1,432 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! The conversions between JS values and IndexedDB keys, and the evaluation of key paths.
+
+use crate::dom::bindings::codegen::UnionTypes::StringOrStringSequence;
+use crate::dom::bindings::conversions::root_from_handlevalue;
+use crate::dom::bindings::conversions::{get_property_jsval, jsstring_to_str};
+use crate::dom::bindings::error::{Error, Fallible};
+use crate::dom::bindings::str::DOMString;
+use crate::dom::bindings::structuredclone::StructuredCloneData;
+use crate::dom::bindings::utils::set_dictionary_property;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::idbkeyrange::IDBKeyRange;
+use crate::script_runtime::JSContext as SafeJSContext;
+use js::conversions::ToJSValConvertible;
+use js::jsapi::{ClippedTime, DateGetMsecSinceEpoch, JSObject, NewDateObject, ObjectIsDate};
+use js::jsapi::{HandleValueArray, JS_NewArrayObject, JS_NewPlainObject};
+use js::jsval::{DoubleValue, ObjectValue, UndefinedValue};
+use js::rust::wrappers::{JS_HasOwnProperty, JS_IsArrayObject};
+use js::rust::{HandleObject, HandleValue, MutableHandleValue};
+use js::typedarray::{ArrayBuffer, ArrayBufferView, CreateWith};
+use net_traits::indexeddb_thread::{IndexMetadata, IndexedDBKey, IndexedDBKeyRange};
+use net_traits::indexeddb_thread::{IndexedDBRecord, KeyPath};
+use std::ffi::CString;
+use std::ptr;
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-value-to-a-key>
+pub fn convert_value_to_key(cx: SafeJSContext, input: HandleValue) -> Fallible<IndexedDBKey> {
+    convert_value_to_key_with_seen(cx, input, &mut vec![])
+}
+
+#[allow(unsafe_code)]
+fn convert_value_to_key_with_seen(
+    cx: SafeJSContext,
+    input: HandleValue,
+    seen: &mut Vec<*mut JSObject>,
+) -> Fallible<IndexedDBKey> {
+    if input.is_number() {
+        let number = input.to_number();
+        if number.is_nan() {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKey::Number(number));
+    }
+    if input.is_string() {
+        let string = unsafe { jsstring_to_str(*cx, input.to_string()) };
+        return Ok(IndexedDBKey::String(string.into()));
+    }
+    if !input.is_object() {
+        return Err(Error::Data);
+    }
+
+    rooted!(in(*cx) let object = input.to_object());
+    unsafe {
+        let mut is_date = false;
+        if !ObjectIsDate(*cx, object.handle().into(), &mut is_date) {
+            return Err(Error::JSFailed);
+        }
+        if is_date {
+            let mut time = 0.;
+            if !DateGetMsecSinceEpoch(*cx, object.handle().into(), &mut time) {
+                return Err(Error::JSFailed);
+            }
+            if time.is_nan() {
+                return Err(Error::Data);
+            }
+            return Ok(IndexedDBKey::Date(time));
+        }
+
+        typedarray!(in(*cx) let buffer: ArrayBuffer = object.get());
+        if let Ok(buffer) = buffer {
+            return Ok(IndexedDBKey::Binary(buffer.to_vec()));
+        }
+        typedarray!(in(*cx) let view: ArrayBufferView = object.get());
+        if let Ok(view) = view {
+            return Ok(IndexedDBKey::Binary(view.to_vec()));
+        }
+
+        let mut is_array = false;
+        if !JS_IsArrayObject(*cx, input, &mut is_array) {
+            return Err(Error::JSFailed);
+        }
+        if !is_array || seen.contains(&object.get()) {
+            return Err(Error::Data);
+        }
+        seen.push(object.get());
+
+        rooted!(in(*cx) let mut length = UndefinedValue());
+        get_property_jsval(*cx, object.handle(), "length", length.handle_mut())?;
+        let length = if length.is_number() {
+            length.to_number() as u32
+        } else {
+            0
+        };
+        let mut keys = Vec::with_capacity(length as usize);
+        for index in 0..length {
+            let name = index.to_string();
+            if !has_own_property(cx, object.handle(), &name)? {
+                return Err(Error::Data);
+            }
+            rooted!(in(*cx) let mut entry = UndefinedValue());
+            get_property_jsval(*cx, object.handle(), &name, entry.handle_mut())?;
+            keys.push(convert_value_to_key_with_seen(cx, entry.handle(), seen)?);
+        }
+        seen.pop();
+        Ok(IndexedDBKey::Array(keys))
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-value-to-a-multientry-key>
+#[allow(unsafe_code)]
+fn convert_value_to_multi_entry_key(
+    cx: SafeJSContext,
+    input: HandleValue,
+) -> Fallible<IndexedDBKey> {
+    if !is_array(cx, input)? {
+        return convert_value_to_key(cx, input);
+    }
+    rooted!(in(*cx) let object = input.to_object());
+    let mut keys: Vec<IndexedDBKey> = vec![];
+    for index in 0..array_length(cx, object.handle())? {
+        rooted!(in(*cx) let mut entry = UndefinedValue());
+        unsafe {
+            get_property_jsval(*cx, object.handle(), &index.to_string(), entry.handle_mut())?;
+        }
+        // Invalid entries are skipped, and so are duplicates.
+        if let Ok(key) = convert_value_to_key(cx, entry.handle()) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    Ok(IndexedDBKey::Array(keys))
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-key-to-a-value>
+#[allow(unsafe_code)]
+pub fn key_to_jsval(cx: SafeJSContext, key: &IndexedDBKey, mut rval: MutableHandleValue) {
+    unsafe {
+        match *key {
+            IndexedDBKey::Number(number) => rval.set(DoubleValue(number)),
+            IndexedDBKey::String(ref string) => string.to_jsval(*cx, rval),
+            IndexedDBKey::Date(time) => {
+                rooted!(in(*cx) let date = NewDateObject(*cx, ClippedTime { t: time }));
+                rval.set(ObjectValue(date.get()));
+            },
+            IndexedDBKey::Binary(ref bytes) => {
+                rooted!(in(*cx) let mut buffer = ptr::null_mut::<JSObject>());
+                assert!(
+                    ArrayBuffer::create(*cx, CreateWith::Slice(bytes), buffer.handle_mut()).is_ok()
+                );
+                rval.set(ObjectValue(buffer.get()));
+            },
+            IndexedDBKey::Array(ref keys) => {
+                rooted!(in(*cx) let array = JS_NewArrayObject(*cx, &HandleValueArray::new()));
+                for (index, key) in keys.iter().enumerate() {
+                    rooted!(in(*cx) let mut entry = UndefinedValue());
+                    key_to_jsval(cx, key, entry.handle_mut());
+                    let _ = set_dictionary_property(
+                        *cx,
+                        array.handle(),
+                        &index.to_string(),
+                        entry.handle(),
+                    );
+                }
+                rval.set(ObjectValue(array.get()));
+            },
+        }
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#convert-a-value-to-a-key-range>
+pub fn convert_value_to_key_range(
+    cx: SafeJSContext,
+    input: HandleValue,
+    null_disallowed: bool,
+) -> Fallible<IndexedDBKeyRange> {
+    if let Ok(range) = root_from_handlevalue::<IDBKeyRange>(input, *cx) {
+        return Ok(range.inner().clone());
+    }
+    if input.is_undefined() || input.is_null() {
+        if null_disallowed {
+            return Err(Error::Data);
+        }
+        return Ok(IndexedDBKeyRange::unbounded());
+    }
+    Ok(IndexedDBKeyRange::only(convert_value_to_key(cx, input)?))
+}
+
+#[allow(unsafe_code)]
+fn has_own_property(cx: SafeJSContext, object: HandleObject, name: &str) -> Fallible<bool> {
+    let name = CString::new(name).unwrap();
+    let mut found = false;
+    if unsafe { !JS_HasOwnProperty(*cx, object, name.as_ptr(), &mut found) } {
+        return Err(Error::JSFailed);
+    }
+    Ok(found)
+}
+
+#[allow(unsafe_code)]
+fn is_array(cx: SafeJSContext, value: HandleValue) -> Fallible<bool> {
+    let mut is_array = false;
+    if unsafe { !JS_IsArrayObject(*cx, value, &mut is_array) } {
+        return Err(Error::JSFailed);
+    }
+    Ok(is_array)
+}
+
+#[allow(unsafe_code)]
+fn array_length(cx: SafeJSContext, array: HandleObject) -> Fallible<u32> {
+    rooted!(in(*cx) let mut length = UndefinedValue());
+    unsafe {
+        get_property_jsval(*cx, array, "length", length.handle_mut())?;
+    }
+    Ok(if length.is_number() {
+        length.to_number() as u32
+    } else {
+        0
+    })
+}
+
+/// <https://w3c.github.io/IndexedDB/#valid-key-path>
+pub fn is_valid_key_path(key_path: &KeyPath) -> bool {
+    fn is_identifier(name: &str) -> bool {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(first) if first.is_alphabetic() || first == '$' || first == '_' => {},
+            _ => return false,
+        }
+        chars.all(|c| c.is_alphanumeric() || c == '$' || c == '_')
+    }
+    fn is_valid(path: &str) -> bool {
+        path.is_empty() || path.split('.').all(is_identifier)
+    }
+    match *key_path {
+        KeyPath::String(ref path) => is_valid(path),
+        KeyPath::Sequence(ref paths) => {
+            !paths.is_empty() && paths.iter().all(|path| is_valid(path))
+        },
+    }
+}
+
+impl From<StringOrStringSequence> for KeyPath {
+    fn from(key_path: StringOrStringSequence) -> KeyPath {
+        match key_path {
+            StringOrStringSequence::String(path) => KeyPath::String(path.into()),
+            StringOrStringSequence::StringSequence(paths) => {
+                KeyPath::Sequence(paths.into_iter().map(String::from).collect())
+            },
+        }
+    }
+}
+
+/// Convert a key path to the value of the `keyPath` attribute of an object store or an index.
+#[allow(unsafe_code)]
+pub fn key_path_to_jsval(cx: SafeJSContext, key_path: &KeyPath, rval: MutableHandleValue) {
+    unsafe {
+        match *key_path {
+            KeyPath::String(ref path) => path.to_jsval(*cx, rval),
+            KeyPath::Sequence(ref paths) => {
+                let paths: Vec<DOMString> = paths.iter().map(|path| path.clone().into()).collect();
+                paths.to_jsval(*cx, rval)
+            },
+        }
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#evaluate-a-key-path-on-a-value>
+///
+/// Returns `false` if the key path doesn't resolve to a value.
+#[allow(unsafe_code)]
+fn evaluate_key_path(
+    cx: SafeJSContext,
+    value: HandleValue,
+    key_path: &str,
+    mut rval: MutableHandleValue,
+) -> Fallible<bool> {
+    rooted!(in(*cx) let mut current = value.get());
+    if !key_path.is_empty() {
+        for identifier in key_path.split('.') {
+            if current.is_string() && identifier == "length" {
+                let length = unsafe { jsstring_to_str(*cx, current.to_string()) }
+                    .encode_utf16()
+                    .count();
+                current.set(DoubleValue(length as f64));
+                continue;
+            }
+            if !current.is_object() {
+                return Ok(false);
+            }
+            rooted!(in(*cx) let object = current.to_object());
+            // The length of an array isn't an own property.
+            if identifier != "length" || !is_array(cx, current.handle())? {
+                if !has_own_property(cx, object.handle(), identifier)? {
+                    return Ok(false);
+                }
+            }
+            unsafe {
+                get_property_jsval(*cx, object.handle(), identifier, current.handle_mut())?;
+            }
+        }
+    }
+    rval.set(current.get());
+    Ok(true)
+}
+
+/// <https://w3c.github.io/IndexedDB/#extract-a-key-from-a-value-using-a-key-path>
+///
+/// Returns `None` if the key path doesn't resolve to a value, and a `DataError` if it
+/// resolves to a value that isn't a valid key.
+pub fn extract_key(
+    cx: SafeJSContext,
+    value: HandleValue,
+    key_path: &KeyPath,
+    multi_entry: bool,
+) -> Fallible<Option<IndexedDBKey>> {
+    match *key_path {
+        KeyPath::String(ref path) => {
+            rooted!(in(*cx) let mut result = UndefinedValue());
+            if !evaluate_key_path(cx, value, path, result.handle_mut())? {
+                return Ok(None);
+            }
+            let key = if multi_entry {
+                convert_value_to_multi_entry_key(cx, result.handle())?
+            } else {
+                convert_value_to_key(cx, result.handle())?
+            };
+            Ok(Some(key))
+        },
+        KeyPath::Sequence(ref paths) => {
+            let mut keys = Vec::with_capacity(paths.len());
+            for path in paths {
+                rooted!(in(*cx) let mut result = UndefinedValue());
+                if !evaluate_key_path(cx, value, path, result.handle_mut())? {
+                    return Ok(None);
+                }
+                keys.push(convert_value_to_key(cx, result.handle())?);
+            }
+            Ok(Some(IndexedDBKey::Array(keys)))
+        },
+    }
+}
+
+/// <https://w3c.github.io/IndexedDB/#check-that-a-key-could-be-injected-into-a-value>
+#[allow(unsafe_code)]
+pub fn could_inject_key(cx: SafeJSContext, value: HandleValue, key_path: &str) -> Fallible<bool> {
+    let identifiers: Vec<&str> = key_path.split('.').collect();
+    let (_, parents) = identifiers.split_last().unwrap();
+    rooted!(in(*cx) let mut current = value.get());
+    for identifier in parents {
+        if !current.is_object() {
+            return Ok(false);
+        }
+        rooted!(in(*cx) let object = current.to_object());
+        if !has_own_property(cx, object.handle(), identifier)? {
+            return Ok(true);
+        }
+        unsafe {
+            get_property_jsval(*cx, object.handle(), identifier, current.handle_mut())?;
+        }
+    }
+    Ok(current.is_object())
+}
+
+/// <https://w3c.github.io/IndexedDB/#inject-a-key-into-a-value-using-a-key-path>
+#[allow(unsafe_code)]
+pub fn inject_key(
+    cx: SafeJSContext,
+    value: HandleValue,
+    key: &IndexedDBKey,
+    key_path: &str,
+) -> Fallible<()> {
+    let identifiers: Vec<&str> = key_path.split('.').collect();
+    let (last, parents) = identifiers.split_last().unwrap();
+    rooted!(in(*cx) let mut current = value.get());
+    for identifier in parents {
+        assert!(current.is_object());
+        rooted!(in(*cx) let object = current.to_object());
+        if !has_own_property(cx, object.handle(), identifier)? {
+            rooted!(in(*cx) let child = unsafe { JS_NewPlainObject(*cx) });
+            rooted!(in(*cx) let child_value = ObjectValue(child.get()));
+            set_dictionary_property(*cx, object.handle(), identifier, child_value.handle())
+                .map_err(|_| Error::JSFailed)?;
+        }
+        unsafe {
+            get_property_jsval(*cx, object.handle(), identifier, current.handle_mut())?;
+        }
+    }
+    assert!(current.is_object());
+    rooted!(in(*cx) let object = current.to_object());
+    rooted!(in(*cx) let mut key_value = UndefinedValue());
+    key_to_jsval(cx, key, key_value.handle_mut());
+    set_dictionary_property(*cx, object.handle(), last, key_value.handle())
+        .map_err(|_| Error::JSFailed)
+}
+
+/// The keys a value has in an index. A value is not indexed if its key can't be extracted.
+pub fn index_keys(
+    cx: SafeJSContext,
+    value: HandleValue,
+    index: &IndexMetadata,
+) -> Vec<IndexedDBKey> {
+    match extract_key(cx, value, &index.key_path, index.multi_entry) {
+        Ok(Some(IndexedDBKey::Array(keys))) if index.multi_entry => keys,
+        Ok(Some(key)) => vec![key],
+        _ => vec![],
+    }
+}
+
+/// Convert records to an array of either their values or their primary keys.
+#[allow(unsafe_code)]
+pub fn records_to_jsval(
+    global: &GlobalScope,
+    records: Vec<IndexedDBRecord>,
+    values: bool,
+    mut rval: MutableHandleValue,
+) {
+    let cx = global.get_cx();
+    rooted!(in(*cx) let array = unsafe { JS_NewArrayObject(*cx, &HandleValueArray::new()) });
+    for (index, record) in records.into_iter().enumerate() {
+        rooted!(in(*cx) let mut entry = UndefinedValue());
+        if values {
+            StructuredCloneData::Vector(record.value).read(global, entry.handle_mut());
+        } else {
+            key_to_jsval(cx, &record.primary_key, entry.handle_mut());
+        }
+        let _ = set_dictionary_property(*cx, array.handle(), &index.to_string(), entry.handle());
+    }
+    rval.set(ObjectValue(array.get()));
+}
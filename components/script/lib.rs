@@ -51,6 +51,7 @@ extern crate style;
 mod task;
 mod body;
 pub mod clipboard_provider;
+mod debugger;
 mod devtools;
 pub mod document_loader;
 #[macro_use]
@@ -58,10 +59,12 @@ mod dom;
 mod compartments;
 pub mod fetch;
 mod image_listener;
+mod indexed_db;
 mod layout_image;
 mod mem;
 mod microtask;
 mod network_listener;
+mod script_module;
 pub mod script_runtime;
 #[allow(unsafe_code)]
 pub mod script_thread;
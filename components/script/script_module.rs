@@ -0,0 +1,994 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Module scripts: the module map of a global, fetching module script graphs,
+//! linking and evaluating them through SpiderMonkey, and the hooks the JS
+//! engine calls for `import` declarations, `import()` and `import.meta`.
+//!
+//! <https://html.spec.whatwg.org/multipage/#integration-with-the-javascript-module-system>
+
+use crate::document_loader::LoadType;
+use crate::dom::bindings::cell::DomRefCell;
+use crate::dom::bindings::codegen::Bindings::WindowBinding::WindowMethods;
+use crate::dom::bindings::conversions::{jsstring_to_str, ToJSValConvertible};
+use crate::dom::bindings::error::{report_pending_exception, throw_dom_exception, Error};
+use crate::dom::bindings::inheritance::Castable;
+use crate::dom::bindings::refcounted::Trusted;
+use crate::dom::bindings::reflector::DomObject;
+use crate::dom::bindings::root::DomRoot;
+use crate::dom::bindings::settings_stack::AutoEntryScript;
+use crate::dom::dedicatedworkerglobalscope::DedicatedWorkerGlobalScope;
+use crate::dom::globalscope::GlobalScope;
+use crate::dom::htmlscriptelement::SCRIPT_JS_MIMES;
+use crate::dom::htmlscriptelement::{ExternalScriptKind, HTMLScriptElement, ScriptOrigin};
+use crate::dom::performanceresourcetiming::InitiatorType;
+use crate::dom::promise::Promise;
+use crate::dom::window::Window;
+use crate::dom::workletglobalscope::WorkletGlobalScope;
+use crate::network_listener::{self, NetworkListener, PreInvoke, ResourceTimingListener};
+use crate::script_runtime::JSContext as SafeJSContext;
+use crate::task_source::TaskSourceName;
+use encoding_rs::UTF_8;
+use ipc_channel::ipc;
+use ipc_channel::router::ROUTER;
+use js::jsapi::Handle as RawHandle;
+use js::jsapi::HandleObject as RawHandleObject;
+use js::jsapi::HandleValue as RawHandleValue;
+use js::jsapi::{FinishDynamicModuleImport, SetModuleDynamicImportHook};
+use js::jsapi::{Heap, JSAutoRealm, JSContext, JSObject, JSRuntime, JSString};
+use js::jsapi::{JS_ClearPendingException, JS_NewUCStringCopyN, JS_SetPendingException};
+use js::jsapi::{ModuleEvaluate, ModuleInstantiate, SetModuleMetadataHook};
+use js::jsapi::{SetModulePrivate, SetModuleResolveHook, SourceText, JSPROP_ENUMERATE};
+use js::jsval::{JSVal, PrivateValue, UndefinedValue};
+use js::panic::{maybe_resume_unwind, wrap_panic};
+use js::rust::wrappers::JS_GetPendingException;
+use js::rust::wrappers::{CompileModule, GetRequestedModuleSpecifier, GetRequestedModules};
+use js::rust::wrappers::{JS_DefineProperty, JS_GetArrayLength, JS_GetElement};
+use js::rust::{CompileOptionsWrapper, Handle, HandleValue, IntoHandle};
+use mime::Mime;
+use net_traits::csp::Violation;
+use net_traits::request::{CredentialsMode, Destination, ParserMetadata, Referrer};
+use net_traits::request::{RequestBuilder, RequestMode};
+use net_traits::{CoreResourceMsg, FetchChannels, FetchMetadata, FetchResponseListener};
+use net_traits::{Metadata, NetworkError, ReferrerPolicy};
+use net_traits::{ResourceFetchTiming, ResourceTimingType};
+use servo_url::ServoUrl;
+use std::cell::Cell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+use std::panic::AssertUnwindSafe;
+use std::ptr;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+/// The module map of a global, keyed by the URLs modules were requested from.
+///
+/// <https://html.spec.whatwg.org/multipage/#module-map>
+pub type ModuleMap = HashMap<ServoUrl, ModuleStatus>;
+
+/// An entry of a module map.
+#[derive(JSTraceable)]
+pub enum ModuleStatus {
+    /// The module is being fetched, on behalf of these graph fetches.
+    Fetching(Vec<Rc<ModuleGraphFetch>>),
+    /// The module was fetched, or failed to be if `None`.
+    Fetched(Option<Rc<ModuleTree>>),
+}
+
+/// A module record of SpiderMonkey.
+#[derive(JSTraceable)]
+struct ModuleObject(Box<Heap<*mut JSObject>>);
+
+/// An exception to report in place of evaluating a module script.
+#[derive(JSTraceable)]
+struct RethrowError(Box<Heap<JSVal>>);
+
+impl RethrowError {
+    /// Takes the exception pending on `cx`.
+    #[allow(unsafe_code)]
+    unsafe fn take_pending(cx: *mut JSContext) -> RethrowError {
+        rooted!(in(cx) let mut exception = UndefinedValue());
+        if !JS_GetPendingException(cx, exception.handle_mut()) {
+            warn!("no pending exception to take");
+        }
+        JS_ClearPendingException(cx);
+        RethrowError(Heap::boxed(exception.get()))
+    }
+}
+
+impl Clone for RethrowError {
+    fn clone(&self) -> RethrowError {
+        RethrowError(Heap::boxed(self.0.get()))
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#script-fetch-options>
+#[derive(Clone, JSTraceable)]
+pub struct ScriptFetchOptions {
+    pub cryptographic_nonce: String,
+    pub integrity_metadata: String,
+    pub parser_metadata: ParserMetadata,
+    pub credentials_mode: CredentialsMode,
+    pub referrer_policy: Option<ReferrerPolicy>,
+}
+
+impl ScriptFetchOptions {
+    /// <https://html.spec.whatwg.org/multipage/#default-classic-script-fetch-options>
+    pub fn default_classic_script() -> ScriptFetchOptions {
+        ScriptFetchOptions {
+            cryptographic_nonce: String::new(),
+            integrity_metadata: String::new(),
+            parser_metadata: ParserMetadata::NotParserInserted,
+            credentials_mode: CredentialsMode::CredentialsSameOrigin,
+            referrer_policy: None,
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#descendant-script-fetch-options>
+    fn descendant_fetch_options(&self) -> ScriptFetchOptions {
+        ScriptFetchOptions {
+            integrity_metadata: String::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// What the hooks of the JS engine need to know about a module record, stored
+/// as its private value.
+#[derive(JSTraceable)]
+struct ModuleScript {
+    /// <https://html.spec.whatwg.org/multipage/#concept-script-base-url>
+    base_url: ServoUrl,
+    /// <https://html.spec.whatwg.org/multipage/#concept-script-script-fetch-options>
+    options: ScriptFetchOptions,
+}
+
+/// A module script, and the URLs of the modules it imports.
+///
+/// <https://html.spec.whatwg.org/multipage/#module-script>
+#[derive(JSTraceable)]
+pub struct ModuleTree {
+    /// The private value of the record. It's boxed so that it keeps its
+    /// address for as long as the record may be used.
+    script: Box<ModuleScript>,
+    /// <https://html.spec.whatwg.org/multipage/#concept-script-record>
+    record: Option<ModuleObject>,
+    /// <https://html.spec.whatwg.org/multipage/#concept-script-parse-error>
+    parse_error: Option<RethrowError>,
+    /// <https://html.spec.whatwg.org/multipage/#concept-script-error-to-rethrow>
+    error_to_rethrow: DomRefCell<Option<RethrowError>>,
+    /// The resolved URLs of the modules requested by the record.
+    requested_urls: Vec<ServoUrl>,
+}
+
+impl ModuleTree {
+    /// <https://html.spec.whatwg.org/multipage/#creating-a-module-script>
+    #[allow(unsafe_code)]
+    fn create(
+        global: &GlobalScope,
+        source: &str,
+        base_url: ServoUrl,
+        options: ScriptFetchOptions,
+        line_number: u32,
+    ) -> ModuleTree {
+        let script = Box::new(ModuleScript { base_url, options });
+        let compiled = unsafe { compile_module(global, source, &script, line_number) };
+        let (record, parse_error, requested_urls) = match compiled {
+            Ok((record, requested_urls)) => (Some(record), None, requested_urls),
+            Err(error) => (None, Some(error), vec![]),
+        };
+        ModuleTree {
+            script,
+            record,
+            parse_error,
+            error_to_rethrow: DomRefCell::new(None),
+            requested_urls,
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#concept-script-base-url>
+    pub fn base_url(&self) -> &ServoUrl {
+        &self.script.base_url
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#run-a-module-script>
+    /// Steps 6-7, leaving any exception pending on `cx`.
+    #[allow(unsafe_code)]
+    fn evaluate(&self, cx: SafeJSContext) -> bool {
+        unsafe {
+            if let Some(ref error) = *self.error_to_rethrow.borrow() {
+                JS_SetPendingException(*cx, error.0.handle());
+                return false;
+            }
+            let record = self
+                .record
+                .as_ref()
+                .expect("Module script without a record or an error to rethrow");
+            ModuleEvaluate(*cx, record.0.handle())
+        }
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#creating-a-module-script>
+/// Steps 7-11: compile the module, and resolve the specifiers it requests.
+#[allow(unsafe_code)]
+unsafe fn compile_module(
+    global: &GlobalScope,
+    source: &str,
+    script: &ModuleScript,
+    line_number: u32,
+) -> Result<(ModuleObject, Vec<ServoUrl>), RethrowError> {
+    let cx = global.get_cx();
+    let _ac = JSAutoRealm::new(*cx, global.reflector().get_jsobject().get());
+
+    // Step 7.
+    let filename = CString::new(script.base_url.as_str()).unwrap();
+    let compile_options = CompileOptionsWrapper::new(*cx, filename.as_ptr(), line_number);
+    let units: Vec<u16> = source.encode_utf16().collect();
+    let mut source = SourceText {
+        units_: units.as_ptr() as *const _,
+        length_: units.len() as u32,
+        ownsUnits_: false,
+        _phantom_0: PhantomData,
+    };
+    rooted!(in(*cx) let mut record = ptr::null_mut::<JSObject>());
+    if !CompileModule(*cx, compile_options.ptr, &mut source, record.handle_mut()) {
+        // Step 8.
+        return Err(RethrowError::take_pending(*cx));
+    }
+
+    // Step 9.
+    rooted!(in(*cx) let requested_modules = GetRequestedModules(*cx, record.handle()));
+    let mut length = 0;
+    if !JS_GetArrayLength(*cx, requested_modules.handle(), &mut length) {
+        return Err(RethrowError::take_pending(*cx));
+    }
+    let mut requested_urls = Vec::with_capacity(length as usize);
+    for index in 0..length {
+        rooted!(in(*cx) let mut requested_module = UndefinedValue());
+        if !JS_GetElement(
+            *cx,
+            requested_modules.handle(),
+            index,
+            requested_module.handle_mut(),
+        ) {
+            return Err(RethrowError::take_pending(*cx));
+        }
+        rooted!(in(*cx) let specifier = GetRequestedModuleSpecifier(
+            *cx,
+            requested_module.handle(),
+        ));
+        let specifier = jsstring_to_str(*cx, specifier.get());
+        match resolve_module_specifier(&script.base_url, &specifier) {
+            Ok(url) => {
+                if !requested_urls.contains(&url) {
+                    requested_urls.push(url);
+                }
+            },
+            Err(()) => {
+                // Step 9.2.
+                let message = format!("Invalid module specifier \"{}\"", specifier);
+                throw_dom_exception(cx, global, Error::Type(message));
+                return Err(RethrowError::take_pending(*cx));
+            },
+        }
+    }
+
+    // Step 10.
+    SetModulePrivate(
+        record.get(),
+        &PrivateValue(script as *const ModuleScript as *const c_void),
+    );
+
+    Ok((ModuleObject(Heap::boxed(record.get())), requested_urls))
+}
+
+/// <https://html.spec.whatwg.org/multipage/#resolve-a-module-specifier>
+fn resolve_module_specifier(base_url: &ServoUrl, specifier: &str) -> Result<ServoUrl, ()> {
+    // Step 1.
+    if let Ok(url) = ServoUrl::parse(specifier) {
+        return Ok(url);
+    }
+
+    // Step 2.
+    if !specifier.starts_with("/") && !specifier.starts_with("./") && !specifier.starts_with("../")
+    {
+        return Err(());
+    }
+
+    // Step 3.
+    base_url.join(specifier).map_err(|_| ())
+}
+
+/// What to do with a module script graph once it's fetched.
+#[derive(JSTraceable)]
+pub enum ModuleOwner {
+    /// Run it as the script of a `<script type="module">` element.
+    Script {
+        element: Trusted<HTMLScriptElement>,
+        kind: ExternalScriptKind,
+        external: bool,
+    },
+    /// Run it as the script of a module worker.
+    Worker,
+    /// Evaluate it, and settle the promise of an `import()` call.
+    DynamicImport(DynamicModuleImport),
+}
+
+impl ModuleOwner {
+    fn finish(&self, global: &GlobalScope, module: Option<Rc<ModuleTree>>) {
+        match *self {
+            ModuleOwner::Script {
+                ref element,
+                kind,
+                external,
+            } => {
+                let result = module
+                    .map(|module| ScriptOrigin::module(module, external))
+                    .ok_or_else(|| {
+                        NetworkError::Internal("Failed to fetch a module script".to_owned())
+                    });
+                element.root().script_fetched(kind, result);
+            },
+            ModuleOwner::Worker => global
+                .downcast::<DedicatedWorkerGlobalScope>()
+                .expect("Module worker script fetched by a global that's not a worker")
+                .run_module_worker_script(module),
+            ModuleOwner::DynamicImport(ref import) => {
+                let module = module.ok_or_else(|| {
+                    Error::Type(format!(
+                        "Failed to fetch dynamically imported module \"{}\"",
+                        import.specifier
+                    ))
+                });
+                import.finish(global, module)
+            },
+        }
+    }
+}
+
+/// An `import()` call waiting for its module script graph.
+#[derive(JSTraceable)]
+pub struct DynamicModuleImport {
+    promise: Rc<Promise>,
+    specifier: String,
+    /// The private value of the script or module that called `import()`.
+    referencing_private: Box<Heap<JSVal>>,
+}
+
+impl DynamicModuleImport {
+    /// <https://html.spec.whatwg.org/multipage/#hostimportmoduledynamically(referencingscriptormodule,-specifier,-promisecapability)>
+    /// Steps 6-7: evaluate the module, and let the JS engine settle the
+    /// promise with its namespace or the exception.
+    #[allow(unsafe_code)]
+    fn finish(&self, global: &GlobalScope, module: Result<Rc<ModuleTree>, Error>) {
+        let cx = global.get_cx();
+        let _ac = JSAutoRealm::new(*cx, self.promise.reflector().get_jsobject().get());
+        let _aes = AutoEntryScript::new(global);
+
+        match module {
+            Ok(module) => {
+                module.evaluate(cx);
+            },
+            Err(error) => throw_dom_exception(cx, global, error),
+        }
+
+        unsafe {
+            let specifier: Vec<u16> = self.specifier.encode_utf16().collect();
+            rooted!(in(*cx) let specifier = JS_NewUCStringCopyN(
+                *cx,
+                specifier.as_ptr(),
+                specifier.len(),
+            ));
+            if !FinishDynamicModuleImport(
+                *cx,
+                self.referencing_private.handle(),
+                specifier.handle().into_handle(),
+                self.promise.reflector().get_jsobject().into_handle(),
+            ) {
+                report_pending_exception(*cx, true);
+            }
+        }
+        maybe_resume_unwind();
+    }
+}
+
+/// The fetch of a module script graph: how many of its modules are still
+/// being fetched, and what to do with the graph once they all are.
+///
+/// Each graph fetch walks the graph on its own, only sharing the fetches of
+/// single modules through the module map, so that graphs with cycles don't
+/// wait for each other.
+///
+/// <https://html.spec.whatwg.org/multipage/#fetch-the-descendants-of-and-link-a-module-script>
+#[derive(JSTraceable)]
+pub struct ModuleGraphFetch {
+    owner: ModuleOwner,
+    /// The destination of the fetches of the graph.
+    destination: Destination,
+    /// The options the root of the graph is fetched with.
+    options: ScriptFetchOptions,
+    /// The root of the graph, once it's fetched.
+    root: DomRefCell<Option<Rc<ModuleTree>>>,
+    /// The URLs of the modules of the graph that were fetched or are being
+    /// fetched.
+    visited: DomRefCell<HashSet<ServoUrl>>,
+    /// The number of modules of the graph being fetched.
+    pending: Cell<usize>,
+    /// Whether fetching any module of the graph failed.
+    failed: Cell<bool>,
+}
+
+impl ModuleGraphFetch {
+    /// Creates the fetch of a graph whose root is about to be fetched.
+    fn new(
+        owner: ModuleOwner,
+        destination: Destination,
+        options: ScriptFetchOptions,
+    ) -> Rc<ModuleGraphFetch> {
+        Rc::new(ModuleGraphFetch {
+            owner,
+            destination,
+            options,
+            root: DomRefCell::new(None),
+            visited: DomRefCell::new(HashSet::new()),
+            pending: Cell::new(1),
+            failed: Cell::new(false),
+        })
+    }
+
+    /// Called when a module of the graph was fetched, or failed to be if
+    /// `module` is `None`.
+    fn module_fetched(self: Rc<Self>, global: &GlobalScope, module: Option<Rc<ModuleTree>>) {
+        if self.failed.get() {
+            return;
+        }
+
+        let module = match module {
+            Some(module) => module,
+            None => {
+                self.failed.set(true);
+                return self.owner.finish(global, None);
+            },
+        };
+
+        if self.root.borrow().is_none() {
+            *self.root.borrow_mut() = Some(module.clone());
+        }
+
+        // Fetch the descendants the graph doesn't have yet. The fetch of this
+        // module only counts as done afterwards, so that descendants found in
+        // the module map don't finish the graph early.
+        let descendants: Vec<ServoUrl> = {
+            let mut visited = self.visited.borrow_mut();
+            module
+                .requested_urls
+                .iter()
+                .filter(|url| visited.insert((*url).clone()))
+                .cloned()
+                .collect()
+        };
+        self.pending.set(self.pending.get() + descendants.len());
+        for url in descendants {
+            let referrer = Referrer::ReferrerUrl(module.base_url().clone());
+            fetch_single_module_script(global, self.clone(), url, Some(referrer), false);
+        }
+
+        self.pending.set(self.pending.get() - 1);
+        if self.pending.get() == 0 && !self.failed.get() {
+            let root = self.root.borrow().clone().unwrap();
+            link(global, &root);
+            self.owner.finish(global, Some(root));
+        }
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#fetch-the-descendants-of-and-link-a-module-script>
+/// Steps 5-6, once every module of the graph of `root` is fetched.
+#[allow(unsafe_code)]
+fn link(global: &GlobalScope, root: &ModuleTree) {
+    // Step 5.
+    let parse_error =
+        find_first_parse_error(&global.module_map().borrow(), root, &mut HashSet::new());
+    if let Some(parse_error) = parse_error {
+        *root.error_to_rethrow.borrow_mut() = Some(parse_error);
+        return;
+    }
+
+    // Step 6.
+    let record = root
+        .record
+        .as_ref()
+        .expect("Module script without a record or a parse error");
+    let cx = global.get_cx();
+    let _ac = JSAutoRealm::new(*cx, global.reflector().get_jsobject().get());
+    unsafe {
+        if !ModuleInstantiate(*cx, record.0.handle()) {
+            *root.error_to_rethrow.borrow_mut() = Some(RethrowError::take_pending(*cx));
+        }
+    }
+}
+
+/// <https://html.spec.whatwg.org/multipage/#finding-the-first-parse-error>
+fn find_first_parse_error(
+    module_map: &ModuleMap,
+    module: &ModuleTree,
+    discovered: &mut HashSet<ServoUrl>,
+) -> Option<RethrowError> {
+    // Step 2.
+    if let Some(ref parse_error) = module.parse_error {
+        return Some(parse_error.clone());
+    }
+
+    // Step 3.
+    for url in &module.requested_urls {
+        if !discovered.insert(url.clone()) {
+            continue;
+        }
+        if let Some(ModuleStatus::Fetched(Some(child))) = module_map.get(url) {
+            if let Some(parse_error) = find_first_parse_error(module_map, child, discovered) {
+                return Some(parse_error);
+            }
+        }
+    }
+
+    // Step 4.
+    None
+}
+
+/// <https://html.spec.whatwg.org/multipage/#fetch-a-module-script-tree>
+pub fn fetch_external_module_script(
+    global: &GlobalScope,
+    owner: ModuleOwner,
+    url: ServoUrl,
+    destination: Destination,
+    referrer: Option<Referrer>,
+    options: ScriptFetchOptions,
+) {
+    let graph = ModuleGraphFetch::new(owner, destination, options);
+    graph.visited.borrow_mut().insert(url.clone());
+    fetch_single_module_script(global, graph, url, referrer, true);
+}
+
+/// <https://html.spec.whatwg.org/multipage/#fetch-an-inline-module-script-graph>
+pub fn fetch_inline_module_script(
+    global: &GlobalScope,
+    owner: ModuleOwner,
+    source: &str,
+    base_url: ServoUrl,
+    options: ScriptFetchOptions,
+    line_number: u32,
+) {
+    // Step 1.
+    let module = Rc::new(ModuleTree::create(
+        global,
+        source,
+        base_url,
+        options.clone(),
+        line_number,
+    ));
+    global.add_inline_module_script(module.clone());
+
+    // Steps 2-3.
+    let graph = ModuleGraphFetch::new(owner, Destination::Script, options);
+    graph.module_fetched(global, Some(module));
+}
+
+/// <https://html.spec.whatwg.org/multipage/#fetch-a-single-module-script>
+fn fetch_single_module_script(
+    global: &GlobalScope,
+    graph: Rc<ModuleGraphFetch>,
+    url: ServoUrl,
+    referrer: Option<Referrer>,
+    top_level: bool,
+) {
+    // Steps 1-3.
+    let fetched = match global.module_map().borrow_mut().entry(url.clone()) {
+        Entry::Occupied(mut entry) => match *entry.get_mut() {
+            ModuleStatus::Fetching(ref mut graphs) => {
+                graphs.push(graph);
+                return;
+            },
+            ModuleStatus::Fetched(ref module) => Some(module.clone()),
+        },
+        Entry::Vacant(entry) => {
+            entry.insert(ModuleStatus::Fetching(vec![graph.clone()]));
+            None
+        },
+    };
+    if let Some(module) = fetched {
+        return graph.module_fetched(global, module);
+    }
+
+    let options = if top_level {
+        graph.options.clone()
+    } else {
+        graph.options.descendant_fetch_options()
+    };
+
+    // Step 5.
+    let mode = match graph.destination {
+        Destination::Worker | Destination::SharedWorker if top_level => RequestMode::SameOrigin,
+        _ => RequestMode::CorsMode,
+    };
+    let request = RequestBuilder::new(url.clone())
+        .destination(graph.destination)
+        .mode(mode)
+        .credentials_mode(options.credentials_mode)
+        .origin(global.origin().immutable().clone())
+        .pipeline_id(Some(global.pipeline_id()))
+        .referrer(referrer)
+        .referrer_policy(options.referrer_policy)
+        .parser_metadata(options.parser_metadata)
+        .integrity_metadata(options.integrity_metadata.clone())
+        .cryptographic_nonce_metadata(options.cryptographic_nonce.clone());
+
+    let context = Arc::new(Mutex::new(ModuleContext {
+        global: Trusted::new(global),
+        data: vec![],
+        metadata: None,
+        url: url.clone(),
+        options,
+        status: Ok(()),
+        resource_timing: ResourceFetchTiming::new(ResourceTimingType::Resource),
+    }));
+
+    let (action_sender, action_receiver) = ipc::channel().unwrap();
+    let listener = NetworkListener {
+        context,
+        task_source: global.networking_task_source(),
+        canceller: Some(global.task_canceller(TaskSourceName::Networking)),
+    };
+    ROUTER.add_route(
+        action_receiver.to_opaque(),
+        Box::new(move |message| {
+            listener.notify_fetch(message.to().unwrap());
+        }),
+    );
+
+    // Step 6.
+    match global.downcast::<Window>() {
+        Some(window) => {
+            window
+                .Document()
+                .fetch_async(LoadType::Script(url), request, action_sender);
+        },
+        None => {
+            let request = request
+                .csp_list(global.get_csp_list())
+                .site_for_cookies(global.site_for_cookies());
+            global
+                .core_resource_thread()
+                .send(CoreResourceMsg::Fetch(
+                    request,
+                    FetchChannels::ResponseMsg(action_sender, None),
+                ))
+                .unwrap();
+        },
+    }
+}
+
+/// Stores a module in the module map of `global` once it's fetched, and hands
+/// it to the graph fetches waiting for it.
+fn store_fetched_module(global: &GlobalScope, url: ServoUrl, module: Option<Rc<ModuleTree>>) {
+    let graphs = match global
+        .module_map()
+        .borrow_mut()
+        .insert(url, ModuleStatus::Fetched(module.clone()))
+    {
+        Some(ModuleStatus::Fetching(graphs)) => graphs,
+        _ => unreachable!("Module fetched without being fetched"),
+    };
+    for graph in graphs {
+        graph.module_fetched(global, module.clone());
+    }
+}
+
+/// The context required for asynchronously fetching a single module script.
+struct ModuleContext {
+    /// The global whose module map the module goes into.
+    global: Trusted<GlobalScope>,
+    /// The response body received to date.
+    data: Vec<u8>,
+    /// The response metadata received to date.
+    metadata: Option<Metadata>,
+    /// The URL the module was requested from, which keys the module map.
+    url: ServoUrl,
+    /// The options the module was fetched with.
+    options: ScriptFetchOptions,
+    /// Indicates whether the request failed, and why.
+    status: Result<(), NetworkError>,
+    /// Timing object for this resource.
+    resource_timing: ResourceFetchTiming,
+}
+
+impl FetchResponseListener for ModuleContext {
+    fn process_request_body(&mut self) {}
+
+    fn process_request_eof(&mut self) {}
+
+    fn process_response(&mut self, metadata: Result<FetchMetadata, NetworkError>) {
+        self.metadata = metadata.ok().map(|meta| match meta {
+            FetchMetadata::Unfiltered(m) => m,
+            FetchMetadata::Filtered { unsafe_, .. } => unsafe_,
+        });
+
+        let status_code = self
+            .metadata
+            .as_ref()
+            .and_then(|m| match m.status {
+                Some((c, _)) => Some(c),
+                _ => None,
+            })
+            .unwrap_or(0);
+
+        self.status = match status_code {
+            0 => Err(NetworkError::Internal(
+                "No http status code received".to_owned(),
+            )),
+            200..=299 => Ok(()), // HTTP ok status codes
+            _ => Err(NetworkError::Internal(format!(
+                "HTTP error code {}",
+                status_code
+            ))),
+        };
+    }
+
+    fn process_response_chunk(&mut self, mut chunk: Vec<u8>) {
+        if self.status.is_ok() {
+            self.data.append(&mut chunk);
+        }
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#fetch-a-single-module-script>
+    /// Steps 7-11.
+    fn process_response_eof(&mut self, response: Result<ResourceFetchTiming, NetworkError>) {
+        let global = self.global.root();
+
+        // Step 9.
+        let source = response.and(self.status.clone()).and_then(|_| {
+            let metadata = self.metadata.take().unwrap();
+            let is_javascript = metadata.content_type.map_or(false, |content_type| {
+                let mime: Mime = content_type.into_inner().into();
+                let essence = format!("{}/{}", mime.type_(), mime.subtype());
+                SCRIPT_JS_MIMES.contains(&essence.to_ascii_lowercase().as_str())
+            });
+            if !is_javascript {
+                return Err(NetworkError::Internal(
+                    "Module script without a JavaScript MIME type".to_owned(),
+                ));
+            }
+
+            // Step 10.
+            let (source_text, _) = UTF_8.decode_with_bom_removal(&self.data);
+            Ok((source_text.into_owned(), metadata.final_url))
+        });
+
+        // Step 11.
+        let module = match source {
+            Ok((source_text, final_url)) => Some(Rc::new(ModuleTree::create(
+                &global,
+                &source_text,
+                final_url,
+                self.options.clone(),
+                1,
+            ))),
+            Err(error) => {
+                warn!("error loading module script {}: {:?}", self.url, error);
+                None
+            },
+        };
+
+        store_fetched_module(&global, self.url.clone(), module);
+
+        if let Some(window) = global.downcast::<Window>() {
+            window
+                .Document()
+                .finish_load(LoadType::Script(self.url.clone()));
+        }
+    }
+
+    fn resource_timing_mut(&mut self) -> &mut ResourceFetchTiming {
+        &mut self.resource_timing
+    }
+
+    fn resource_timing(&self) -> &ResourceFetchTiming {
+        &self.resource_timing
+    }
+
+    fn submit_resource_timing(&mut self) {
+        network_listener::submit_timing(self)
+    }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        self.global.root().report_csp_violations(violations, None);
+    }
+}
+
+impl ResourceTimingListener for ModuleContext {
+    fn resource_timing_information(&self) -> (InitiatorType, ServoUrl) {
+        (
+            InitiatorType::LocalName("script".to_owned()),
+            self.url.clone(),
+        )
+    }
+
+    fn resource_timing_global(&self) -> DomRoot<GlobalScope> {
+        self.global.root()
+    }
+}
+
+impl PreInvoke for ModuleContext {}
+
+/// <https://html.spec.whatwg.org/multipage/#run-a-module-script>
+/// Steps 3-8.
+#[allow(unsafe_code)]
+pub fn run_module_script(global: &GlobalScope, module: &ModuleTree) {
+    let cx = global.get_cx();
+    let _ac = JSAutoRealm::new(*cx, global.reflector().get_jsobject().get());
+    let _aes = AutoEntryScript::new(global);
+
+    if !module.evaluate(cx) {
+        unsafe { report_pending_exception(*cx, true) };
+    }
+    maybe_resume_unwind();
+}
+
+/// Sets the hooks the JS engine calls for module scripts.
+#[allow(unsafe_code)]
+pub unsafe fn init_module_hooks(rt: *mut JSRuntime) {
+    SetModuleResolveHook(rt, Some(host_resolve_imported_module));
+    SetModuleMetadataHook(rt, Some(host_populate_import_meta));
+    SetModuleDynamicImportHook(rt, Some(host_import_module_dynamically));
+}
+
+/// The module script of a private value the JS engine passes to the hooks,
+/// which is undefined for classic scripts.
+#[allow(unsafe_code)]
+unsafe fn referencing_module_script<'a>(private: RawHandleValue) -> Option<&'a ModuleScript> {
+    let private = HandleValue::from_raw(private);
+    if private.is_undefined() {
+        return None;
+    }
+    (private.to_private() as *const ModuleScript).as_ref()
+}
+
+/// <https://html.spec.whatwg.org/multipage/#hostresolveimportedmodule(referencingscriptormodule,-specifier)>
+#[allow(unsafe_code)]
+unsafe extern "C" fn host_resolve_imported_module(
+    cx: *mut JSContext,
+    reference_private: RawHandleValue,
+    specifier: RawHandle<*mut JSString>,
+) -> *mut JSObject {
+    wrap_panic(
+        AssertUnwindSafe(|| {
+            let global = GlobalScope::from_context(cx);
+
+            // Steps 1-2.
+            let base_url = match referencing_module_script(reference_private) {
+                Some(script) => script.base_url.clone(),
+                None => global.api_base_url(),
+            };
+
+            // Steps 3-5.
+            let specifier = jsstring_to_str(cx, Handle::from_raw(specifier).get());
+            let record = resolve_module_specifier(&base_url, &specifier)
+                .ok()
+                .and_then(|url| match global.module_map().borrow().get(&url) {
+                    Some(ModuleStatus::Fetched(Some(module))) => {
+                        module.record.as_ref().map(|record| record.0.get())
+                    },
+                    _ => None,
+                });
+            record.unwrap_or_else(|| {
+                let message = format!("Module \"{}\" is not fetched", specifier);
+                throw_dom_exception(SafeJSContext::from_ptr(cx), &global, Error::Type(message));
+                ptr::null_mut()
+            })
+        }),
+        ptr::null_mut(),
+    )
+}
+
+/// <https://html.spec.whatwg.org/multipage/#hostgetimportmetaproperties>
+#[allow(unsafe_code)]
+unsafe extern "C" fn host_populate_import_meta(
+    cx: *mut JSContext,
+    reference_private: RawHandleValue,
+    meta_object: RawHandleObject,
+) -> bool {
+    wrap_panic(
+        AssertUnwindSafe(|| {
+            let global = GlobalScope::from_context(cx);
+
+            // Step 1.
+            let base_url = match referencing_module_script(reference_private) {
+                Some(script) => script.base_url.clone(),
+                None => global.api_base_url(),
+            };
+
+            // Step 2.
+            rooted!(in(cx) let mut url = UndefinedValue());
+            base_url.as_str().to_jsval(cx, url.handle_mut());
+            JS_DefineProperty(
+                cx,
+                Handle::from_raw(meta_object),
+                "url\0".as_ptr() as *const libc::c_char,
+                url.handle(),
+                JSPROP_ENUMERATE as u32,
+            )
+        }),
+        false,
+    )
+}
+
+/// <https://html.spec.whatwg.org/multipage/#hostimportmoduledynamically(referencingscriptormodule,-specifier,-promisecapability)>
+#[allow(unsafe_code, unrooted_must_root)]
+unsafe extern "C" fn host_import_module_dynamically(
+    cx: *mut JSContext,
+    reference_private: RawHandleValue,
+    specifier: RawHandle<*mut JSString>,
+    promise: RawHandleObject,
+) -> bool {
+    wrap_panic(
+        AssertUnwindSafe(|| {
+            let global = GlobalScope::from_context(cx);
+
+            // Steps 1-2. Imports from module scripts are fetched with the
+            // module as the referrer, like its static imports.
+            let (base_url, referrer, options) = match referencing_module_script(reference_private) {
+                Some(script) => (
+                    script.base_url.clone(),
+                    script.base_url.clone(),
+                    script.options.descendant_fetch_options(),
+                ),
+                None => (
+                    global.api_base_url(),
+                    global.get_url(),
+                    ScriptFetchOptions::default_classic_script(),
+                ),
+            };
+
+            let import = DynamicModuleImport {
+                promise: Promise::new_with_js_promise(
+                    Handle::from_raw(promise),
+                    SafeJSContext::from_ptr(cx),
+                ),
+                specifier: String::from(jsstring_to_str(cx, Handle::from_raw(specifier).get())),
+                referencing_private: Heap::boxed(HandleValue::from_raw(reference_private).get()),
+            };
+
+            // Step 3.
+            let url = match resolve_module_specifier(&base_url, &import.specifier) {
+                // Worklets can't fetch modules through their global.
+                Ok(_) if global.is::<WorkletGlobalScope>() => {
+                    let message = "import() is not supported in worklets".to_owned();
+                    import.finish(&global, Err(Error::Type(message)));
+                    return true;
+                },
+                Ok(url) => url,
+                Err(()) => {
+                    let message = format!("Invalid module specifier \"{}\"", import.specifier);
+                    import.finish(&global, Err(Error::Type(message)));
+                    return true;
+                },
+            };
+
+            // Steps 4-5.
+            fetch_external_module_script(
+                &global,
+                ModuleOwner::DynamicImport(import),
+                url,
+                Destination::Script,
+                Some(Referrer::ReferrerUrl(referrer)),
+                options,
+            );
+            true
+        }),
+        false,
+    )
+}
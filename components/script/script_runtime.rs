@@ -22,6 +22,7 @@ use crate::dom::globalscope::GlobalScope;
 use crate::dom::promise::Promise;
 use crate::dom::promiserejectionevent::PromiseRejectionEvent;
 use crate::microtask::{EnqueuedPromiseCallback, Microtask, MicrotaskQueue};
+use crate::script_module::init_module_hooks;
 use crate::script_thread::trace_thread;
 use crate::task::TaskBox;
 use crate::task_source::{TaskSource, TaskSourceName};
@@ -36,6 +37,7 @@ use js::jsapi::{
     JSJitCompilerOption, JS_SetOffthreadIonCompilationEnabled, JS_SetParallelParsingEnabled,
 };
 use js::jsapi::{JSObject, PromiseRejectionHandlingState, SetPreserveWrapperCallback};
+use js::jsapi::{JSSecurityCallbacks, JS_SetSecurityCallbacks};
 use js::jsapi::{SetJobQueue, SetProcessBuildIdOp, SetPromiseRejectionTrackerCallback};
 use js::jsval::UndefinedValue;
 use js::panic::wrap_panic;
@@ -130,6 +132,8 @@ pub enum ScriptThreadEventCategory {
     ExitFullscreen,
     WebVREvent,
     PerformanceTimelineTask,
+    DatabaseAccessEvent,
+    PortMessage,
 }
 
 /// An interface for receiving ScriptMsg values in an event loop. Used for synchronous DOM
@@ -196,6 +200,24 @@ unsafe extern "C" fn enqueue_promise_job(
     )
 }
 
+static SECURITY_CALLBACKS: JSSecurityCallbacks = JSSecurityCallbacks {
+    contentSecurityPolicyAllows: Some(content_security_policy_allows),
+    subsumes: None,
+};
+
+/// SM callback asking whether eval() and friends may run in the current global.
+/// https://w3c.github.io/webappsec-csp/#can-compile-strings
+#[allow(unsafe_code)]
+unsafe extern "C" fn content_security_policy_allows(cx: *mut RawJSContext) -> bool {
+    wrap_panic(
+        AssertUnwindSafe(|| {
+            let global = GlobalScope::from_context(cx);
+            global.is_js_evaluation_allowed()
+        }),
+        false,
+    )
+}
+
 #[allow(unsafe_code, unrooted_must_root)]
 /// https://html.spec.whatwg.org/multipage/#the-hostpromiserejectiontracker-implementation
 unsafe extern "C" fn promise_rejection_tracker(
@@ -413,6 +435,7 @@ unsafe fn new_rt_and_cx_with_parent(parent: Option<ParentRuntime>) -> Runtime {
         true
     }
     SetDOMCallbacks(cx, &DOM_CALLBACKS);
+    JS_SetSecurityCallbacks(cx, &SECURITY_CALLBACKS);
     SetPreserveWrapperCallback(cx, Some(empty_wrapper_callback));
     // Pre barriers aren't working correctly at the moment
     DisableIncrementalGC(cx);
@@ -424,6 +447,7 @@ unsafe fn new_rt_and_cx_with_parent(parent: Option<ParentRuntime>) -> Runtime {
     );
     SetJobQueue(cx, job_queue);
     SetPromiseRejectionTrackerCallback(cx, Some(promise_rejection_tracker), ptr::null_mut());
+    init_module_hooks(runtime.rt());
 
     set_gc_zeal_options(cx);
 
@@ -18,6 +18,7 @@
 //! loop.
 
 use crate::compartments::enter_realm;
+use crate::debugger::ScriptDebugger;
 use crate::devtools;
 use crate::document_loader::DocumentLoader;
 use crate::dom::bindings::cell::DomRefCell;
@@ -38,7 +39,6 @@ use crate::dom::bindings::reflector::DomObject;
 use crate::dom::bindings::root::ThreadLocalStackRoots;
 use crate::dom::bindings::root::{Dom, DomRoot, MutNullableDom, RootCollection};
 use crate::dom::bindings::str::DOMString;
-use crate::dom::bindings::structuredclone::StructuredCloneData;
 use crate::dom::bindings::trace::JSTraceable;
 use crate::dom::bindings::utils::WRAP_CALLBACKS;
 use crate::dom::customelementregistry::{
@@ -75,12 +75,14 @@ use crate::script_runtime::{CommonScriptMsg, ScriptChan, ScriptThreadEventCatego
 use crate::serviceworkerjob::{Job, JobQueue};
 use crate::task_manager::TaskManager;
 use crate::task_queue::{QueuedTask, QueuedTaskConversion, TaskQueue};
+use crate::task_source::database_access::DatabaseAccessTaskSource;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
 use crate::task_source::history_traversal::HistoryTraversalTaskSource;
 use crate::task_source::media_element::MediaElementTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::performance_timeline::PerformanceTimelineTaskSource;
+use crate::task_source::port_message::PortMessageQueue;
 use crate::task_source::remote_event::RemoteEventTaskSource;
 use crate::task_source::user_interaction::UserInteractionTaskSource;
 use crate::task_source::websocket::WebsocketTaskSource;
@@ -90,7 +92,7 @@ use crate::webdriver_handlers;
 use bluetooth_traits::BluetoothRequest;
 use canvas_traits::webgl::WebGLPipeline;
 use crossbeam_channel::{unbounded, Receiver, Sender};
-use devtools_traits::CSSError;
+use devtools_traits::{CSSError, DebuggerRequest, PauseInfo, ResumeLimit};
 use devtools_traits::{DevtoolScriptControlMsg, DevtoolsPageInfo};
 use devtools_traits::{ScriptToDevtoolsControlMsg, WorkerId};
 use embedder_traits::{EmbedderMsg, EventLoopWaker};
@@ -115,18 +117,20 @@ use msg::constellation_msg::{
 use msg::constellation_msg::{BrowsingContextId, HistoryStateId, PipelineId};
 use msg::constellation_msg::{HangAnnotation, MonitoredComponentId, MonitoredComponentType};
 use msg::constellation_msg::{PipelineNamespace, TopLevelBrowsingContextId};
+use net_traits::csp::{InlineCheckType, NavigationCheckType};
 use net_traits::image_cache::{ImageCache, PendingImageResponse};
 use net_traits::request::{CredentialsMode, Destination, RedirectMode, RequestBuilder};
 use net_traits::storage_thread::StorageType;
 use net_traits::{FetchMetadata, FetchResponseListener, FetchResponseMsg};
 use net_traits::{
     Metadata, NetworkError, ReferrerPolicy, ResourceFetchTiming, ResourceThreads,
-    ResourceTimingType,
+    ResourceTimingType, SiteForCookies,
 };
 use percent_encoding::percent_decode;
 use profile_traits::mem::{self as profile_mem, OpaqueSender, ReportsChan};
 use profile_traits::time::{self as profile_time, profile, ProfilerCategory};
 use script_layout_interface::message::{self, LayoutThreadInit, Msg, ReflowGoal};
+use script_traits::transferable::StructuredSerializedData;
 use script_traits::webdriver_msg::WebDriverScriptCommand;
 use script_traits::CompositorEvent::{
     CompositionEvent, KeyboardEvent, MouseButtonEvent, MouseMoveEvent, ResizeEvent, TouchEvent,
@@ -568,6 +572,10 @@ pub struct ScriptThread {
 
     remote_event_task_sender: Box<dyn ScriptChan>,
 
+    database_access_task_sender: Box<dyn ScriptChan>,
+
+    port_message_sender: Box<dyn ScriptChan>,
+
     /// A channel to hand out to threads that need to respond to a message from the script thread.
     control_chan: IpcSender<ConstellationControlMsg>,
 
@@ -690,6 +698,9 @@ pub struct ScriptThread {
 
     /// A set of all nodes ever created in this script thread
     node_ids: DomRefCell<HashSet<String>>,
+
+    /// The JavaScript debugger, once devtools started debugging a pipeline of this thread.
+    debugger: DomRefCell<Option<ScriptDebugger>>,
 }
 
 /// In the event of thread panic, all data on the stack runs its destructor. However, there
@@ -969,8 +980,13 @@ impl ScriptThread {
                 let sender = script_thread.script_sender.clone();
                 let task = task!(navigate_javascript: move || {
                     // Important re security. See https://github.com/servo/servo/issues/23373
-                    // TODO: check according to https://w3c.github.io/webappsec-csp/#should-block-navigation-request
-                    if let Some(window) = trusted_global.root().downcast::<Window>() {
+                    // https://w3c.github.io/webappsec-csp/#should-block-navigation-request
+                    let global = trusted_global.root();
+                    let url = load_data.url.as_str().to_owned();
+                    if !global.is_inline_allowed(None, InlineCheckType::Navigation, "", &url) {
+                        return;
+                    }
+                    if let Some(window) = global.downcast::<Window>() {
                         if ScriptThread::check_load_origin(&load_data.load_origin, &window.get_url().origin()) {
                             ScriptThread::eval_js_url(&trusted_global.root(), &mut load_data);
                             sender
@@ -984,6 +1000,16 @@ impl ScriptThread {
                     .queue(task, global.upcast())
                     .expect("Enqueing navigate js task on the DOM manipulation task source failed");
             } else {
+                if let Some(window) = script_thread.documents.borrow().find_window(pipeline_id) {
+                    let global = window.upcast::<GlobalScope>();
+                    if !global.is_navigation_allowed(
+                        None,
+                        &load_data.url,
+                        NavigationCheckType::Other,
+                    ) {
+                        return;
+                    }
+                }
                 script_thread
                     .script_sender
                     .send((pipeline_id, ScriptMsg::LoadUrl(load_data, replace)))
@@ -1278,6 +1304,8 @@ impl ScriptThread {
             file_reading_task_sender: boxed_script_sender.clone(),
             performance_timeline_task_sender: boxed_script_sender.clone(),
             remote_event_task_sender: boxed_script_sender.clone(),
+            database_access_task_sender: boxed_script_sender.clone(),
+            port_message_sender: boxed_script_sender.clone(),
 
             history_traversal_task_sender: chan.clone(),
 
@@ -1339,6 +1367,7 @@ impl ScriptThread {
             event_loop_waker: state.event_loop_waker,
 
             node_ids: Default::default(),
+            debugger: Default::default(),
         }
     }
 
@@ -1634,6 +1663,10 @@ impl ScriptThread {
             ScriptThreadEventCategory::PerformanceTimelineTask => {
                 ScriptHangAnnotation::PerformanceTimelineTask
             },
+            ScriptThreadEventCategory::DatabaseAccessEvent => {
+                ScriptHangAnnotation::DatabaseAccessEvent
+            },
+            ScriptThreadEventCategory::PortMessage => ScriptHangAnnotation::PortMessage,
         };
         self.background_hang_monitor
             .notify_activity(HangAnnotation::Script(hang_annotation));
@@ -1757,6 +1790,10 @@ impl ScriptThread {
                 ScriptThreadEventCategory::PerformanceTimelineTask => {
                     ProfilerCategory::ScriptPerformanceEvent
                 },
+                ScriptThreadEventCategory::DatabaseAccessEvent => {
+                    ProfilerCategory::ScriptDatabaseAccessEvent
+                },
+                ScriptThreadEventCategory::PortMessage => ProfilerCategory::ScriptPortMessage,
             };
             profile(profiler_cat, None, self.time_profiler_chan.clone(), f)
         } else {
@@ -2004,9 +2041,93 @@ impl ScriptThread {
                 devtools::handle_request_animation_frame(&*documents, id, name)
             },
             DevtoolScriptControlMsg::Reload(id) => devtools::handle_reload(&*documents, id),
+            DevtoolScriptControlMsg::GetStyleSheets(id, reply) => {
+                devtools::handle_get_stylesheets(&*documents, id, reply)
+            },
+            DevtoolScriptControlMsg::GetStyleSheetText(id, index, reply) => {
+                devtools::handle_get_stylesheet_text(&*documents, id, index, reply)
+            },
+            DevtoolScriptControlMsg::UpdateStyleSheet(id, index, text) => {
+                devtools::handle_update_stylesheet(&*documents, id, index, text)
+            },
+            DevtoolScriptControlMsg::Debugger(id, request) => {
+                self.handle_debugger_request(&*documents, id, request)
+            },
         }
     }
 
+    fn handle_debugger_request(
+        &self,
+        documents: &Documents,
+        id: PipelineId,
+        request: DebuggerRequest,
+    ) {
+        match request {
+            DebuggerRequest::Attach => {
+                let window = match documents.find_window(id) {
+                    Some(window) => window,
+                    None => return warn!("Message sent to closed pipeline {}.", id),
+                };
+                if self.debugger.borrow().is_none() {
+                    *self.debugger.borrow_mut() = Some(ScriptDebugger::new(self.get_cx()));
+                }
+                if let Some(ref debugger) = *self.debugger.borrow() {
+                    debugger.add_debuggee(self.get_cx(), id, window.upcast());
+                }
+            },
+            DebuggerRequest::Detach => {
+                if let (Some(debugger), Some(window)) =
+                    (&*self.debugger.borrow(), documents.find_window(id))
+                {
+                    debugger.remove_debuggee(self.get_cx(), id, window.upcast());
+                }
+            },
+            // Script isn't paused, there is nothing to resume.
+            DebuggerRequest::Resume(_) => {},
+            request => match *self.debugger.borrow() {
+                Some(ref debugger) => debugger.handle_request(self.get_cx(), id, request),
+                None => warn!("Debugger request sent to pipeline {} before attaching.", id),
+            },
+        }
+    }
+
+    /// Notify devtools that script in the given pipeline was paused by the debugger, then
+    /// only handle devtools messages until script is resumed. Returns where script should
+    /// pause again, if anywhere.
+    pub fn debugger_pause(pipeline_id: PipelineId, info: PauseInfo) -> Option<ResumeLimit> {
+        SCRIPT_THREAD_ROOT.with(|root| {
+            let script_thread = unsafe { &*root.get().unwrap() };
+            script_thread.run_debugger_pause_loop(pipeline_id, info)
+        })
+    }
+
+    fn run_debugger_pause_loop(
+        &self,
+        pipeline_id: PipelineId,
+        info: PauseInfo,
+    ) -> Option<ResumeLimit> {
+        let devtools_chan = self.devtools_chan.as_ref()?;
+        devtools_chan
+            .send(ScriptToDevtoolsControlMsg::DebuggerPaused(
+                pipeline_id,
+                info,
+            ))
+            .ok()?;
+        while let Ok(msg) = self.devtools_port.recv() {
+            match msg {
+                DevtoolScriptControlMsg::Debugger(_, DebuggerRequest::Resume(limit)) => {
+                    return limit;
+                },
+                msg @ DevtoolScriptControlMsg::Debugger(_, DebuggerRequest::Detach) => {
+                    self.handle_msg_from_devtools(msg);
+                    return None;
+                },
+                msg => self.handle_msg_from_devtools(msg),
+            }
+        }
+        None
+    }
+
     fn handle_msg_from_image_cache(&self, (id, response): (PipelineId, PendingImageResponse)) {
         let window = self.documents.borrow().find_window(id);
         if let Some(ref window) = window {
@@ -2489,7 +2610,7 @@ impl ScriptThread {
         source_pipeline_id: PipelineId,
         source_browsing_context: TopLevelBrowsingContextId,
         origin: Option<ImmutableOrigin>,
-        data: Vec<u8>,
+        data: StructuredSerializedData,
     ) {
         match { self.documents.borrow().find_window(pipeline_id) } {
             None => return warn!("postMessage after target pipeline {} closed.", pipeline_id),
@@ -2511,7 +2632,7 @@ impl ScriptThread {
                     Some(source) => source,
                 };
                 // FIXME(#22512): enqueues a task; unnecessary delay.
-                window.post_message(origin, &*source, StructuredCloneData::Vector(data))
+                window.post_message(origin, &*source, data)
             },
         }
     }
@@ -2762,6 +2883,14 @@ impl ScriptThread {
         WebsocketTaskSource(self.remote_event_task_sender.clone(), pipeline_id)
     }
 
+    pub fn database_access_task_source(&self, pipeline_id: PipelineId) -> DatabaseAccessTaskSource {
+        DatabaseAccessTaskSource(self.database_access_task_sender.clone(), pipeline_id)
+    }
+
+    pub fn port_message_queue(&self, pipeline_id: PipelineId) -> PortMessageQueue {
+        PortMessageQueue(self.port_message_sender.clone(), pipeline_id)
+    }
+
     /// Handles a request for the window title.
     fn handle_get_title_msg(&self, pipeline_id: PipelineId) {
         let document = match { self.documents.borrow().find_document(pipeline_id) } {
@@ -2828,6 +2957,9 @@ impl ScriptThread {
             if discard_bc == DiscardBrowsingContext::Yes {
                 window.window_proxy().discard_browsing_context();
             }
+            if let Some(ref debugger) = *self.debugger.borrow() {
+                debugger.remove_debuggee(self.get_cx(), id, window.upcast());
+            }
             window.clear_js_runtime();
         }
 
@@ -3150,6 +3282,8 @@ impl ScriptThread {
             self.user_interaction_task_source(incomplete.pipeline_id),
             self.remote_event_task_source(incomplete.pipeline_id),
             self.websocket_task_source(incomplete.pipeline_id),
+            self.database_access_task_source(incomplete.pipeline_id),
+            self.port_message_queue(incomplete.pipeline_id),
         );
         // Create the window and document objects.
         let window = Window::new(
@@ -3266,6 +3400,7 @@ impl ScriptThread {
             referrer_policy,
             incomplete.canceller,
         );
+        document.set_csp_list(metadata.csp_list.clone());
         document.set_ready_state(DocumentReadyState::Loading);
 
         self.documents
@@ -3651,6 +3786,18 @@ impl ScriptThread {
     /// argument until a notification is received that the fetch is complete.
     fn pre_page_load(&self, mut incomplete: InProgressLoad, load_data: LoadData) {
         let id = incomplete.pipeline_id.clone();
+        // https://tools.ietf.org/html/draft-ietf-httpbis-rfc6265bis-03#section-5.2.1
+        let site_for_cookies = match (incomplete.parent_info, &load_data.load_origin) {
+            (Some(parent_id), _) => match self.documents.borrow().find_document(parent_id) {
+                Some(parent) => parent.site_for_cookies(),
+                // The parent lives in another script thread, so it is cross-site.
+                None => SiteForCookies::Document(None),
+            },
+            (None, &LoadOrigin::Script(ref origin)) => {
+                SiteForCookies::TopLevelNavigation(origin.clone())
+            },
+            (None, _) => SiteForCookies::UserAgent,
+        };
         let req_init = RequestBuilder::new(load_data.url.clone())
             .method(load_data.method)
             .destination(Destination::Document)
@@ -3662,7 +3809,8 @@ impl ScriptThread {
             .headers(load_data.headers)
             .body(load_data.data)
             .redirect_mode(RedirectMode::Manual)
-            .origin(incomplete.origin.immutable().clone());
+            .origin(incomplete.origin.immutable().clone())
+            .site_for_cookies(site_for_cookies);
 
         let context = ParserContext::new(id, load_data.url);
         self.incomplete_parser_contexts
@@ -5,21 +5,26 @@
 //! The service worker manager persists the descriptor of any registered service workers.
 //! It also stores an active workers map, which holds descriptors of running service workers.
 //! If an active service worker timeouts, then it removes the descriptor entry from its
-//! active_workers map
+//! active_workers map.
+//! Fetches from the resource thread are handed to the service worker controlling them:
+//! navigations are controlled by the activated registration matching their URL, and the
+//! subresource requests of the resulting document by the same registration.
 
 use crate::dom::abstractworker::WorkerScriptMsg;
-use crate::dom::bindings::structuredclone::StructuredCloneData;
 use crate::dom::serviceworkerglobalscope::{ServiceWorkerGlobalScope, ServiceWorkerScriptMsg};
 use crate::dom::serviceworkerregistration::longest_prefix_match;
 use crossbeam_channel::{unbounded, Receiver, RecvError, Sender};
 use devtools_traits::{DevtoolsPageInfo, ScriptToDevtoolsControlMsg};
 use ipc_channel::ipc::{self, IpcSender};
 use ipc_channel::router::ROUTER;
+use msg::constellation_msg::PipelineId;
+use net_traits::request::{Destination, RequestBuilder};
 use net_traits::{CoreResourceMsg, CustomResponseMediator};
+use script_traits::transferable::StructuredSerializedData;
 use script_traits::{DOMMessage, SWManagerMsg, SWManagerSenders, ScopeThings, ServiceWorkerMsg};
 use servo_config::pref;
 use servo_url::ServoUrl;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::thread;
 
 enum Message {
@@ -32,6 +37,10 @@ pub struct ServiceWorkerManager {
     registered_workers: HashMap<ServoUrl, ScopeThings>,
     // map of active service worker descriptors
     active_workers: HashMap<ServoUrl, Sender<ServiceWorkerScriptMsg>>,
+    // scopes of the registrations whose service worker has been installed and activated
+    activated_scopes: HashSet<ServoUrl>,
+    // map of the documents controlled by a service worker to the scope of its registration
+    controlled_clients: HashMap<PipelineId, ServoUrl>,
     // own sender to send messages here
     own_sender: IpcSender<ServiceWorkerMsg>,
     // receiver to receive messages from constellation
@@ -49,6 +58,8 @@ impl ServiceWorkerManager {
         ServiceWorkerManager {
             registered_workers: HashMap::new(),
             active_workers: HashMap::new(),
+            activated_scopes: HashSet::new(),
+            controlled_clients: HashMap::new(),
             own_sender: own_sender,
             own_port: from_constellation_receiver,
             resource_receiver: resource_port,
@@ -85,6 +96,29 @@ impl ServiceWorkerManager {
         None
     }
 
+    /// Get the scope of the registration whose service worker controls the request, if any.
+    fn get_controlling_scope(&mut self, request: &RequestBuilder) -> Option<ServoUrl> {
+        if request.destination != Destination::Document {
+            return request
+                .pipeline_id
+                .and_then(|pipeline_id| self.controlled_clients.get(&pipeline_id).cloned());
+        }
+        let scope = self
+            .get_matching_scope(&request.url)
+            .filter(|scope| self.activated_scopes.contains(scope));
+        if let Some(pipeline_id) = request.pipeline_id {
+            match scope {
+                Some(ref scope) => {
+                    self.controlled_clients.insert(pipeline_id, scope.clone());
+                },
+                None => {
+                    self.controlled_clients.remove(&pipeline_id);
+                },
+            }
+        }
+        scope
+    }
+
     pub fn wakeup_serviceworker(
         &mut self,
         scope_url: ServoUrl,
@@ -105,6 +139,8 @@ impl ServiceWorkerManager {
                     page_info,
                 ));
             };
+            // A service worker that was never activated still has to be installed.
+            let install = !self.activated_scopes.contains(&scope_url);
             ServiceWorkerGlobalScope::run_serviceworker_scope(
                 scope_things.clone(),
                 sender.clone(),
@@ -112,6 +148,7 @@ impl ServiceWorkerManager {
                 devtools_receiver,
                 self.own_sender.clone(),
                 scope_url.clone(),
+                install,
             );
             // We store the activated worker
             self.active_workers.insert(scope_url, sender.clone());
@@ -136,7 +173,7 @@ impl ServiceWorkerManager {
 
     fn forward_message(&self, msg: DOMMessage, sender: &Sender<ServiceWorkerScriptMsg>) {
         let DOMMessage(data) = msg;
-        let data = StructuredCloneData::Vector(data);
+        let data = StructuredSerializedData::new(data);
         let _ = sender.send(ServiceWorkerScriptMsg::CommonWorker(
             WorkerScriptMsg::DOMMessage(data),
         ));
@@ -148,7 +185,9 @@ impl ServiceWorkerManager {
                 if self.registered_workers.contains_key(&scope) {
                     warn!("ScopeThings for {:?} already stored in SW-Manager", scope);
                 } else {
-                    self.registered_workers.insert(scope, scope_things);
+                    self.registered_workers.insert(scope.clone(), scope_things);
+                    // Run the service worker, so that it gets installed.
+                    let _ = self.wakeup_serviceworker(scope);
                 }
                 true
             },
@@ -160,6 +199,18 @@ impl ServiceWorkerManager {
                 }
                 true
             },
+            ServiceWorkerMsg::Activated(scope) => {
+                if self.registered_workers.contains_key(&scope) {
+                    self.activated_scopes.insert(scope);
+                }
+                true
+            },
+            ServiceWorkerMsg::InstallFailed(scope) => {
+                // The registration is cleared, so that the worker doesn't get run again.
+                let _ = self.registered_workers.remove(&scope);
+                let _ = self.active_workers.remove(&scope);
+                true
+            },
             ServiceWorkerMsg::ForwardDOMMessage(msg, scope_url) => {
                 if self.active_workers.contains_key(&scope_url) {
                     if let Some(ref sender) = self.active_workers.get(&scope_url) {
@@ -178,7 +229,7 @@ impl ServiceWorkerManager {
 
     fn handle_message_from_resource(&mut self, mediator: CustomResponseMediator) -> bool {
         if serviceworker_enabled() {
-            if let Some(scope) = self.get_matching_scope(&mediator.load_url) {
+            if let Some(scope) = self.get_controlling_scope(&mediator.request) {
                 if self.active_workers.contains_key(&scope) {
                     if let Some(sender) = self.active_workers.get(&scope) {
                         let _ = sender.send(ServiceWorkerScriptMsg::Response(mediator));
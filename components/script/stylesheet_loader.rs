@@ -22,6 +22,7 @@ use encoding_rs::UTF_8;
 use ipc_channel::ipc;
 use ipc_channel::router::ROUTER;
 use mime::{self, Mime};
+use net_traits::csp::Violation;
 use net_traits::request::{
     CorsSettings, CredentialsMode, Destination, Referrer, RequestBuilder, RequestMode,
 };
@@ -232,6 +233,12 @@ impl FetchResponseListener for StylesheetContext {
     fn submit_resource_timing(&mut self) {
         network_listener::submit_timing(self)
     }
+
+    fn process_csp_violations(&mut self, violations: Vec<Violation>) {
+        let elem = self.elem.root();
+        self.resource_timing_global()
+            .report_csp_violations(violations, Some(elem.upcast::<Element>()));
+    }
 }
 
 impl ResourceTimingListener for StylesheetContext {
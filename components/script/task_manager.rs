@@ -4,12 +4,14 @@
 
 use crate::dom::bindings::cell::DomRefCell;
 use crate::task::TaskCanceller;
+use crate::task_source::database_access::DatabaseAccessTaskSource;
 use crate::task_source::dom_manipulation::DOMManipulationTaskSource;
 use crate::task_source::file_reading::FileReadingTaskSource;
 use crate::task_source::history_traversal::HistoryTraversalTaskSource;
 use crate::task_source::media_element::MediaElementTaskSource;
 use crate::task_source::networking::NetworkingTaskSource;
 use crate::task_source::performance_timeline::PerformanceTimelineTaskSource;
+use crate::task_source::port_message::PortMessageQueue;
 use crate::task_source::remote_event::RemoteEventTaskSource;
 use crate::task_source::user_interaction::UserInteractionTaskSource;
 use crate::task_source::websocket::WebsocketTaskSource;
@@ -52,6 +54,10 @@ pub struct TaskManager {
     remote_event_task_source: RemoteEventTaskSource,
     #[ignore_malloc_size_of = "task sources are hard"]
     websocket_task_source: WebsocketTaskSource,
+    #[ignore_malloc_size_of = "task sources are hard"]
+    database_access_task_source: DatabaseAccessTaskSource,
+    #[ignore_malloc_size_of = "task sources are hard"]
+    port_message_queue: PortMessageQueue,
 }
 
 impl TaskManager {
@@ -65,6 +71,8 @@ impl TaskManager {
         user_interaction_task_source: UserInteractionTaskSource,
         remote_event_task_source: RemoteEventTaskSource,
         websocket_task_source: WebsocketTaskSource,
+        database_access_task_source: DatabaseAccessTaskSource,
+        port_message_queue: PortMessageQueue,
     ) -> Self {
         TaskManager {
             dom_manipulation_task_source,
@@ -76,6 +84,8 @@ impl TaskManager {
             user_interaction_task_source,
             remote_event_task_source,
             websocket_task_source,
+            database_access_task_source,
+            port_message_queue,
             task_cancellers: Default::default(),
         }
     }
@@ -152,6 +162,22 @@ impl TaskManager {
         Websocket
     );
 
+    task_source_functions!(
+        self,
+        database_access_task_source_with_canceller,
+        database_access_task_source,
+        DatabaseAccessTaskSource,
+        DatabaseAccess
+    );
+
+    task_source_functions!(
+        self,
+        port_message_queue_with_canceller,
+        port_message_queue,
+        PortMessageQueue,
+        PortMessage
+    );
+
     pub fn task_canceller(&self, name: TaskSourceName) -> TaskCanceller {
         let mut flags = self.task_cancellers.borrow_mut();
         let cancel_flag = flags.entry(name).or_insert(Default::default());
@@ -2,12 +2,14 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+pub mod database_access;
 pub mod dom_manipulation;
 pub mod file_reading;
 pub mod history_traversal;
 pub mod media_element;
 pub mod networking;
 pub mod performance_timeline;
+pub mod port_message;
 pub mod remote_event;
 pub mod user_interaction;
 pub mod websocket;
@@ -32,6 +34,8 @@ pub enum TaskSourceName {
     RemoteEvent,
     MediaElement,
     Websocket,
+    DatabaseAccess,
+    PortMessage,
 }
 
 impl TaskSourceName {
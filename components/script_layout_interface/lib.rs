@@ -116,6 +116,8 @@ pub struct HTMLCanvasData {
 pub struct SVGSVGData {
     pub width: u32,
     pub height: u32,
+    /// The `<svg>` element and its SVG descendants, for rasterization.
+    pub tree: servo_svg::Element,
 }
 
 /// The address of a node known to be valid. These are sent from script to layout.
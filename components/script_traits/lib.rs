@@ -17,9 +17,13 @@ extern crate malloc_size_of_derive;
 extern crate serde;
 
 mod script_msg;
+pub mod transferable;
 pub mod webdriver_msg;
 
+use crate::transferable::StructuredSerializedData;
+use crate::webdriver_msg::WebDriverWindowState;
 use crate::webdriver_msg::{LoadStatus, WebDriverScriptCommand};
+use crate::webdriver_msg::{WebDriverUserPromptAction, WebDriverUserPromptError};
 use bluetooth_traits::BluetoothRequest;
 use canvas_traits::webgl::WebGLPipeline;
 use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
@@ -56,6 +60,7 @@ use std::fmt;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 use std::time::Duration;
+use style_traits::media_features::DeviceFeatures;
 use style_traits::CSSPixel;
 use style_traits::SpeculativePainter;
 use webrender_api::units::{DeviceIntSize, DevicePixel, LayoutPixel};
@@ -325,7 +330,7 @@ pub enum ConstellationControlMsg {
         /// The expected origin of the target.
         target_origin: Option<ImmutableOrigin>,
         /// The data to be posted.
-        data: Vec<u8>,
+        data: StructuredSerializedData,
     },
     /// Updates the current pipeline ID of a given iframe.
     /// First PipelineId is for the parent, second is the new PipelineId for the frame.
@@ -768,6 +773,10 @@ pub struct WindowSizeData {
 
     /// The resolution of the window in dppx, not including any "pinch zoom" factor.
     pub device_pixel_ratio: Scale<f32, CSSPixel, DevicePixel>,
+
+    /// The characteristics of the device that media queries can test for,
+    /// besides its size and resolution.
+    pub device_features: DeviceFeatures,
 }
 
 /// The type of window size change.
@@ -811,6 +820,36 @@ pub enum WebDriverCommandMsg {
         Option<Rect<f32, CSSPixel>>,
         IpcSender<Option<Image>>,
     ),
+    /// Get the message of the user prompt open in the top-level browsing context.
+    GetAlertText(
+        TopLevelBrowsingContextId,
+        IpcSender<Result<String, WebDriverUserPromptError>>,
+    ),
+    /// Set the text field of the prompt open in the top-level browsing context.
+    SendAlertText(
+        TopLevelBrowsingContextId,
+        String,
+        IpcSender<Result<(), WebDriverUserPromptError>>,
+    ),
+    /// Accept or dismiss the user prompt open in the top-level browsing context, and get its
+    /// message.
+    HandleUserPrompt(
+        TopLevelBrowsingContextId,
+        WebDriverUserPromptAction,
+        IpcSender<Result<String, WebDriverUserPromptError>>,
+    ),
+    /// Get the IDs of all the top-level browsing contexts.
+    GetTopLevelBrowsingContexts(IpcSender<Vec<TopLevelBrowsingContextId>>),
+    /// Open a new top-level browsing context, and get its ID.
+    NewWindow(IpcSender<TopLevelBrowsingContextId>),
+    /// Close the top-level browsing context with the given ID.
+    CloseWindow(TopLevelBrowsingContextId, IpcSender<()>),
+    /// Maximize, minimize or make fullscreen the window.
+    SetWindowState(
+        TopLevelBrowsingContextId,
+        WebDriverWindowState,
+        IpcSender<WindowSizeData>,
+    ),
 }
 
 /// Messages to the constellation.
@@ -871,6 +910,8 @@ pub enum ConstellationMsg {
     DisableProfiler,
     /// Request to exit from fullscreen mode
     ExitFullScreen(TopLevelBrowsingContextId),
+    /// Remove the storage data of the origin of the given URL.
+    ClearStorageData(ServoUrl),
 }
 
 impl fmt::Debug for ConstellationMsg {
@@ -901,6 +942,7 @@ impl fmt::Debug for ConstellationMsg {
             EnableProfiler(..) => "EnableProfiler",
             DisableProfiler => "DisableProfiler",
             ExitFullScreen(..) => "ExitFullScreen",
+            ClearStorageData(..) => "ClearStorageData",
         };
         write!(formatter, "ConstellationMsg::{}", variant)
     }
@@ -2,6 +2,7 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
 
+use crate::transferable::{MessagePortMsg, PortMessageTask, StructuredSerializedData};
 use crate::AnimationState;
 use crate::AuxiliaryBrowsingContextLoadInfo;
 use crate::DocumentState;
@@ -20,6 +21,7 @@ use gfx_traits::Epoch;
 use ipc_channel::ipc::{IpcReceiver, IpcSender};
 use msg::constellation_msg::{BrowsingContextId, PipelineId, TopLevelBrowsingContextId};
 use msg::constellation_msg::{HistoryStateId, TraversalDirection};
+use msg::constellation_msg::{MessagePortId, MessagePortRouterId};
 use net_traits::request::RequestBuilder;
 use net_traits::storage_thread::StorageType;
 use net_traits::CoreResourceMsg;
@@ -111,6 +113,24 @@ pub enum HistoryEntryReplacement {
 pub enum ScriptMsg {
     /// Forward a message to the embedder.
     ForwardToEmbedder(EmbedderMsg),
+    /// A new message-port router was set up by a global, to receive the tasks
+    /// for the ports it manages.
+    NewMessagePortRouter(MessagePortRouterId, IpcSender<MessagePortMsg>),
+    /// A global is no longer managing any message ports.
+    RemoveMessagePortRouter(MessagePortRouterId),
+    /// A new message port was created by the global with the given router.
+    NewMessagePort(MessagePortRouterId, MessagePortId),
+    /// Entangle two message ports.
+    EntanglePorts(MessagePortId, MessagePortId),
+    /// A message port was transferred out of its global. Tasks for it are buffered
+    /// until its transfer is completed.
+    MessagePortShipped(MessagePortId),
+    /// A transferred message port was received by the global with the given router.
+    CompleteMessagePortTransfer(MessagePortRouterId, MessagePortId),
+    /// Send a task to a message port, through the router of the global managing it.
+    RerouteMessagePort(MessagePortId, PortMessageTask),
+    /// A message port was closed.
+    RemoveMessagePort(MessagePortId),
     /// Requests are sent to constellation and fetches are checked manually
     /// for cross-origin loads
     InitiateNavigateRequest(RequestBuilder, /* cancellation_chan */ IpcReceiver<()>),
@@ -167,7 +187,7 @@ pub enum ScriptMsg {
         /// The expected origin of the target.
         target_origin: Option<ImmutableOrigin>,
         /// The data to be posted.
-        data: Vec<u8>,
+        data: StructuredSerializedData,
     },
     /// Inform the constellation that a fragment was navigated to and whether or not it was a replacement navigation.
     NavigatedToFragment(ServoUrl, HistoryEntryReplacement),
@@ -227,6 +247,14 @@ impl fmt::Debug for ScriptMsg {
         use self::ScriptMsg::*;
         let variant = match *self {
             ForwardToEmbedder(..) => "ForwardToEmbedder",
+            NewMessagePortRouter(..) => "NewMessagePortRouter",
+            RemoveMessagePortRouter(..) => "RemoveMessagePortRouter",
+            NewMessagePort(..) => "NewMessagePort",
+            EntanglePorts(..) => "EntanglePorts",
+            MessagePortShipped(..) => "MessagePortShipped",
+            CompleteMessagePortTransfer(..) => "CompleteMessagePortTransfer",
+            RerouteMessagePort(..) => "RerouteMessagePort",
+            RemoveMessagePort(..) => "RemoveMessagePort",
             InitiateNavigateRequest(..) => "InitiateNavigateRequest",
             BroadcastStorageEvent(..) => "BroadcastStorageEvent",
             ChangeRunningAnimationsState(..) => "ChangeRunningAnimationsState",
@@ -301,6 +329,10 @@ pub enum ServiceWorkerMsg {
     RegisterServiceWorker(ScopeThings, ServoUrl),
     /// Timeout message sent by active service workers
     Timeout(ServoUrl),
+    /// Message sent by a service worker once it has been installed and activated
+    Activated(ServoUrl),
+    /// Message sent by a service worker whose installation failed
+    InstallFailed(ServoUrl),
     /// Message sent by constellation to forward to a running service worker
     ForwardDOMMessage(DOMMessage, ServoUrl),
     /// Exit the service worker manager
@@ -0,0 +1,192 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! This module contains the serializable parts of transferable objects,
+//! which are sent between event loops as part of a structured clone.
+//!
+//! <https://html.spec.whatwg.org/multipage/#transferable-objects>
+
+use msg::constellation_msg::MessagePortId;
+use servo_url::ImmutableOrigin;
+use std::collections::{HashMap, VecDeque};
+
+/// The result of serializing a message with a transfer list.
+/// <https://html.spec.whatwg.org/multipage/#structuredserializewithtransfer>
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StructuredSerializedData {
+    /// The data written by SpiderMonkey.
+    pub serialized: Vec<u8>,
+    /// The message ports transferred along with the data.
+    pub ports: Option<HashMap<MessagePortId, MessagePortImpl>>,
+}
+
+impl StructuredSerializedData {
+    /// Serialized data without any transferred objects.
+    pub fn new(serialized: Vec<u8>) -> StructuredSerializedData {
+        StructuredSerializedData {
+            serialized: serialized,
+            ports: None,
+        }
+    }
+}
+
+/// A task on the port message queue of a message port.
+/// <https://html.spec.whatwg.org/multipage/#port-message-queue>
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PortMessageTask {
+    /// The origin of the global that posted the message.
+    pub origin: ImmutableOrigin,
+    /// The posted message, with its transferred ports.
+    pub data: StructuredSerializedData,
+}
+
+/// Messages sent by the constellation to the message-port router of a global.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum MessagePortMsg {
+    /// The transfer of a port to this global is complete. Contains the tasks that
+    /// were sent to the port while it was being transferred.
+    CompleteTransfer(MessagePortId, VecDeque<PortMessageTask>),
+    /// A task for a port managed by this global.
+    NewTask(MessagePortId, PortMessageTask),
+}
+
+/// The state of a message port which is kept when the port is transferred.
+/// <https://html.spec.whatwg.org/multipage/#messageport>
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MessagePortImpl {
+    /// The id of the port.
+    message_port_id: MessagePortId,
+    /// The id of the port this one is entangled with, if any.
+    entangled_port: Option<MessagePortId>,
+    /// The tasks which can't be dispatched yet, because the port message queue
+    /// is disabled or the transfer of the port isn't complete yet.
+    message_buffer: VecDeque<PortMessageTask>,
+    /// The tasks received while awaiting the completion of the transfer, which
+    /// follow the tasks the constellation buffered during the transfer.
+    received_while_awaiting: VecDeque<PortMessageTask>,
+    /// <https://html.spec.whatwg.org/multipage/#port-message-queue> enabled flag.
+    enabled: bool,
+    /// Whether the port is waiting for the constellation to hand over the tasks
+    /// which were sent to it while it was being transferred.
+    awaiting_transfer: bool,
+    /// <https://html.spec.whatwg.org/multipage/#detached>
+    detached: bool,
+}
+
+impl MessagePortImpl {
+    /// Create a new, unentangled, port.
+    pub fn new(message_port_id: MessagePortId) -> MessagePortImpl {
+        MessagePortImpl {
+            message_port_id: message_port_id,
+            entangled_port: None,
+            message_buffer: VecDeque::new(),
+            received_while_awaiting: VecDeque::new(),
+            enabled: false,
+            awaiting_transfer: false,
+            detached: false,
+        }
+    }
+
+    /// The id of the port.
+    pub fn message_port_id(&self) -> &MessagePortId {
+        &self.message_port_id
+    }
+
+    /// The id of the port this one is entangled with, if any.
+    pub fn entangled_port_id(&self) -> Option<MessagePortId> {
+        self.entangled_port
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#entangle>
+    pub fn entangle(&mut self, other_id: MessagePortId) {
+        self.entangled_port = Some(other_id);
+    }
+
+    /// Whether the port has been transferred or closed.
+    pub fn detached(&self) -> bool {
+        self.detached
+    }
+
+    /// Detach the port from its current global, returning the state to send along
+    /// with the transferred port. The port's message queue starts disabled again
+    /// in the receiving global, and keeps the tasks that weren't dispatched yet.
+    pub fn transfer(&mut self) -> MessagePortImpl {
+        self.detached = true;
+        MessagePortImpl {
+            message_port_id: self.message_port_id,
+            entangled_port: self.entangled_port.take(),
+            message_buffer: self
+                .message_buffer
+                .drain(..)
+                .chain(self.received_while_awaiting.drain(..))
+                .collect(),
+            received_while_awaiting: VecDeque::new(),
+            enabled: false,
+            awaiting_transfer: true,
+            detached: false,
+        }
+    }
+
+    /// Close the port, dropping any task that wasn't dispatched yet.
+    pub fn close(&mut self) {
+        self.detached = true;
+        self.entangled_port = None;
+        self.message_buffer.clear();
+        self.received_while_awaiting.clear();
+    }
+
+    /// Buffer a task sent to the port, returning whether it can be dispatched now.
+    pub fn handle_incoming(&mut self, task: PortMessageTask) -> bool {
+        if self.detached {
+            return false;
+        }
+        if self.awaiting_transfer {
+            self.received_while_awaiting.push_back(task);
+            return false;
+        }
+        self.message_buffer.push_back(task);
+        self.enabled
+    }
+
+    /// <https://html.spec.whatwg.org/multipage/#dom-messageport-start>
+    ///
+    /// Returns the number of buffered tasks that can now be dispatched.
+    pub fn start(&mut self) -> usize {
+        if self.enabled {
+            return 0;
+        }
+        self.enabled = true;
+        self.dispatchable_tasks()
+    }
+
+    /// Complete the transfer of the port, with the tasks that were sent to the port
+    /// while it was being transferred.
+    ///
+    /// Returns the number of buffered tasks that can now be dispatched.
+    pub fn complete_transfer(&mut self, tasks: VecDeque<PortMessageTask>) -> usize {
+        if !self.awaiting_transfer {
+            return 0;
+        }
+        self.awaiting_transfer = false;
+        self.message_buffer.extend(tasks);
+        self.message_buffer
+            .extend(self.received_while_awaiting.drain(..));
+        self.dispatchable_tasks()
+    }
+
+    /// Take the next task to dispatch, if the port message queue is enabled.
+    pub fn next_task(&mut self) -> Option<PortMessageTask> {
+        if self.dispatchable_tasks() == 0 {
+            return None;
+        }
+        self.message_buffer.pop_front()
+    }
+
+    fn dispatchable_tasks(&self) -> usize {
+        if self.detached || !self.enabled || self.awaiting_transfer {
+            return 0;
+        }
+        self.message_buffer.len()
+    }
+}
@@ -134,3 +134,24 @@ pub enum LoadStatus {
     LoadComplete,
     LoadTimeout,
 }
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum WebDriverUserPromptAction {
+    Accept,
+    Dismiss,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub enum WebDriverUserPromptError {
+    /// There is no user prompt open in the top-level browsing context.
+    NoSuchAlert,
+    /// The user prompt that is open doesn't have a text field.
+    ElementNotInteractable,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum WebDriverWindowState {
+    Maximized,
+    Minimized,
+    Fullscreen,
+}
@@ -338,6 +338,26 @@ where
                     simple_selector_specificity(&ss, specificity);
                 }
             },
+            Component::Is(ref list) => {
+                // The specificity of the most specific selector in the list.
+                // Packed specificities compare like the specificities
+                // themselves.
+                //
+                // See: https://drafts.csswg.org/selectors-4/#specificity-rules
+                let max = list.iter().map(|s| s.specificity()).max().unwrap_or(0);
+                *specificity += Specificity::from(max);
+            },
+            Component::Has(ref list) => {
+                let max = list
+                    .iter()
+                    .map(|s| s.selector.specificity())
+                    .max()
+                    .unwrap_or(0);
+                *specificity += Specificity::from(max);
+            },
+            Component::Where(..) | Component::RelativeSelectorAnchor => {
+                // Does not affect specificity
+            },
         }
     }
 
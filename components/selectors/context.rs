@@ -132,6 +132,10 @@ where
     /// Whether we're inside a negation or not.
     in_negation: bool,
 
+    /// The element anchoring the relative selector of the `:has()` selector
+    /// that we're matching, if any.
+    relative_selector_anchor: Option<OpaqueElement>,
+
     /// An optional hook function for checking whether a pseudo-element
     /// should match when matching_mode is ForStatelessPseudoElement.
     pub pseudo_element_matching_fn: Option<&'a dyn Fn(&Impl::PseudoElement) -> bool>,
@@ -183,6 +187,7 @@ where
             current_host: None,
             nesting_level: 0,
             in_negation: false,
+            relative_selector_anchor: None,
             pseudo_element_matching_fn: None,
             extra_data: Default::default(),
             _impl: ::std::marker::PhantomData,
@@ -210,6 +215,12 @@ where
         self.in_negation
     }
 
+    /// The element anchoring the relative selector we're matching, if any.
+    #[inline]
+    pub fn relative_selector_anchor(&self) -> Option<OpaqueElement> {
+        self.relative_selector_anchor
+    }
+
     /// The quirks mode of the document.
     #[inline]
     pub fn quirks_mode(&self) -> QuirksMode {
@@ -247,10 +258,31 @@ where
     where
         F: FnOnce(&mut Self) -> R,
     {
-        debug_assert!(!self.in_negation, "Someone messed up parsing?");
+        // Negations can't contain negations directly, but they can through
+        // :is() or :has(), for example.
+        let original_in_negation = self.in_negation;
         self.in_negation = true;
         let result = self.nest(f);
-        self.in_negation = false;
+        self.in_negation = original_in_negation;
+        result
+    }
+
+    /// Runs F with a deeper nesting level, and with the given element as the
+    /// anchor of the relative selectors of a :has(..) selector.
+    ///
+    /// Links are matched as unvisited, so that :has() can't be used to find
+    /// out whether descendants are visited.
+    #[inline]
+    pub fn nest_for_relative_selector<F, R>(&mut self, anchor: OpaqueElement, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let original_anchor = self.relative_selector_anchor.replace(anchor);
+        let result = self
+            .with_visited_handling_mode(VisitedHandlingMode::AllLinksUnvisited, |context| {
+                context.nest(f)
+            });
+        self.relative_selector_anchor = original_anchor;
         result
     }
 
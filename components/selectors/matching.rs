@@ -7,6 +7,7 @@ use crate::bloom::{BloomFilter, BLOOM_HASH_MASK};
 use crate::nth_index_cache::NthIndexCacheInner;
 use crate::parser::{AncestorHashes, Combinator, Component, LocalName};
 use crate::parser::{NonTSPseudoClass, Selector, SelectorImpl, SelectorIter, SelectorList};
+use crate::parser::{RelativeSelector, RelativeSelectorMatchHint};
 use crate::tree::Element;
 use std::borrow::Borrow;
 use std::iter;
@@ -40,20 +41,46 @@ bitflags! {
         /// The element has an empty selector, so when a child is appended we
         /// might need to restyle the parent completely.
         const HAS_EMPTY_SELECTOR = 1 << 3;
+
+        /// The element anchors a `:has()` selector in the subject compound of
+        /// a selector, so when its subtree or later siblings change it might
+        /// need to be restyled.
+        const ANCHORS_RELATIVE_SELECTOR = 1 << 4;
+
+        /// The element anchors a `:has()` selector elsewhere than in the
+        /// subject compound, like in `.a:has(.b) .c`, so when its subtree or
+        /// later siblings change its descendants and later siblings might
+        /// need to be restyled too.
+        const ANCHORS_RELATIVE_SELECTOR_NON_SUBJECT = 1 << 5;
+
+        /// A child of the element anchors a `:has()` selector that looks at
+        /// its later siblings, so when a child is added or removed the
+        /// anchors among the children must be restyled.
+        const HAS_SIBLING_RELATIVE_SELECTOR = 1 << 6;
     }
 }
 
 impl ElementSelectorFlags {
     /// Returns the subset of flags that apply to the element.
     pub fn for_self(self) -> ElementSelectorFlags {
-        self & (ElementSelectorFlags::HAS_EMPTY_SELECTOR)
+        self & (ElementSelectorFlags::HAS_EMPTY_SELECTOR |
+            ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR |
+            ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR_NON_SUBJECT)
     }
 
     /// Returns the subset of flags that apply to the parent.
     pub fn for_parent(self) -> ElementSelectorFlags {
         self & (ElementSelectorFlags::HAS_SLOW_SELECTOR |
             ElementSelectorFlags::HAS_SLOW_SELECTOR_LATER_SIBLINGS |
-            ElementSelectorFlags::HAS_EDGE_CHILD_SELECTOR)
+            ElementSelectorFlags::HAS_EDGE_CHILD_SELECTOR |
+            ElementSelectorFlags::HAS_SIBLING_RELATIVE_SELECTOR)
+    }
+
+    /// Returns the subset of flags that make the element anchor a relative
+    /// selector.
+    pub fn for_relative_selector_anchor(self) -> ElementSelectorFlags {
+        self & (ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR |
+            ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR_NON_SUBJECT)
     }
 }
 
@@ -61,6 +88,7 @@ impl ElementSelectorFlags {
 struct LocalMatchingContext<'a, 'b: 'a, Impl: SelectorImpl> {
     shared: &'a mut MatchingContext<'b, Impl>,
     matches_hover_and_active_quirk: MatchesHoverAndActiveQuirk,
+    rightmost: Rightmost,
 }
 
 #[inline(always)]
@@ -237,11 +265,6 @@ where
         selector.combinator_at_parse_order(from_offset - 1); // This asserts.
     }
 
-    let mut local_context = LocalMatchingContext {
-        shared: context,
-        matches_hover_and_active_quirk: MatchesHoverAndActiveQuirk::No,
-    };
-
     // Find the end of the selector or the next combinator, then match
     // backwards, so that we match in the same order as
     // matches_complex_selector, which is usually faster.
@@ -258,6 +281,16 @@ where
     debug_assert!(from_offset >= 1);
     debug_assert!(from_offset <= selector.len());
 
+    let mut local_context = LocalMatchingContext {
+        shared: context,
+        matches_hover_and_active_quirk: MatchesHoverAndActiveQuirk::No,
+        rightmost: if from_offset == selector.len() {
+            Rightmost::Yes
+        } else {
+            Rightmost::No
+        },
+    };
+
     let iter = selector.iter_from(selector.len() - from_offset);
     debug_assert!(
         iter.clone().next().is_some() ||
@@ -376,6 +409,9 @@ fn matches_hover_and_active_quirk<Impl: SelectorImpl>(
         Component::Class(_) |
         Component::PseudoElement(_) |
         Component::Negation(_) |
+        Component::Is(_) |
+        Component::Where(_) |
+        Component::Has(_) |
         Component::FirstChild |
         Component::LastChild |
         Component::OnlyChild |
@@ -646,6 +682,7 @@ where
     let mut local_context = LocalMatchingContext {
         shared: context,
         matches_hover_and_active_quirk,
+        rightmost,
     };
     iter::once(selector)
         .chain(selector_iter)
@@ -817,14 +854,130 @@ where
             let mut local_context = LocalMatchingContext {
                 matches_hover_and_active_quirk: MatchesHoverAndActiveQuirk::No,
                 shared: context,
+                rightmost: Rightmost::No,
             };
             !negated
                 .iter()
                 .all(|ss| matches_simple_selector(ss, element, &mut local_context, flags_setter))
         }),
+        Component::Is(ref list) | Component::Where(ref list) => context.shared.nest(|context| {
+            list.iter().any(|selector| {
+                matches_complex_selector(selector.iter(), element, context, flags_setter)
+            })
+        }),
+        Component::Has(ref list) => {
+            let in_subject = context.rightmost == Rightmost::Yes &&
+                !context.shared.is_nested() &&
+                !context.shared.in_negation();
+            flags_setter(
+                element,
+                if in_subject {
+                    ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR
+                } else {
+                    ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR_NON_SUBJECT
+                },
+            );
+            if list.iter().any(|selector| selector.match_hint.is_sibling()) {
+                flags_setter(element, ElementSelectorFlags::HAS_SIBLING_RELATIVE_SELECTOR);
+            }
+            context
+                .shared
+                .nest_for_relative_selector(element.opaque(), |context| {
+                    list.iter()
+                        .any(|selector| matches_relative_selector(selector, element, context))
+                })
+        },
+        Component::RelativeSelectorAnchor => context
+            .shared
+            .relative_selector_anchor()
+            .map_or(false, |anchor| anchor == element.opaque()),
     }
 }
 
+/// Whether any element in the search scope of a relative selector, as given
+/// by its match hint, matches it when anchored at `anchor`.
+///
+/// The selector flags of the elements in the scope aren't set, since any
+/// change to them is handled by restyling the anchor, which has its own flags.
+fn matches_relative_selector<E>(
+    selector: &RelativeSelector<E::Impl>,
+    anchor: &E,
+    context: &mut MatchingContext<E::Impl>,
+) -> bool
+where
+    E: Element,
+{
+    let mut matches = |element: &E, context: &mut MatchingContext<E::Impl>| {
+        matches_complex_selector(selector.selector.iter(), element, context, &mut |_, _| {})
+    };
+
+    let mut next = match selector.match_hint {
+        RelativeSelectorMatchHint::InChild | RelativeSelectorMatchHint::InSubtree => {
+            anchor.first_element_child()
+        },
+        RelativeSelectorMatchHint::InNextSibling |
+        RelativeSelectorMatchHint::InNextSiblingSubtree |
+        RelativeSelectorMatchHint::InSibling |
+        RelativeSelectorMatchHint::InSiblingSubtree => anchor.next_sibling_element(),
+    };
+    while let Some(element) = next {
+        if matches(&element, context) {
+            return true;
+        }
+        if selector.match_hint.is_subtree() &&
+            matches_relative_selector_in_subtree(&element, context, &mut matches)
+        {
+            return true;
+        }
+        next = match selector.match_hint {
+            RelativeSelectorMatchHint::InNextSibling |
+            RelativeSelectorMatchHint::InNextSiblingSubtree => None,
+            RelativeSelectorMatchHint::InChild |
+            RelativeSelectorMatchHint::InSubtree |
+            RelativeSelectorMatchHint::InSibling |
+            RelativeSelectorMatchHint::InSiblingSubtree => element.next_sibling_element(),
+        };
+    }
+    false
+}
+
+/// Whether any descendant of `element` matches, in tree order.
+///
+/// The subtree is walked without recursion, so that deep trees can't overflow
+/// the stack.
+fn matches_relative_selector_in_subtree<E, F>(
+    element: &E,
+    context: &mut MatchingContext<E::Impl>,
+    matches: &mut F,
+) -> bool
+where
+    E: Element,
+    F: FnMut(&E, &mut MatchingContext<E::Impl>) -> bool,
+{
+    let root = element.opaque();
+    let mut next = element.first_element_child();
+    while let Some(current) = next {
+        if matches(&current, context) {
+            return true;
+        }
+        next = current.first_element_child();
+        // Once a subtree is done, move on to the next sibling of its closest
+        // ancestor that has one, without leaving the subtree of `element`.
+        let mut ancestor = current;
+        while next.is_none() {
+            next = ancestor.next_sibling_element();
+            if next.is_some() {
+                break;
+            }
+            match ancestor.parent_element() {
+                Some(parent) if parent.opaque() != root => ancestor = parent,
+                _ => break,
+            }
+        }
+    }
+    false
+}
+
 #[inline(always)]
 fn select_name<'a, T>(is_html: bool, local_name: &'a T, local_name_lower: &'a T) -> &'a T {
     if is_html {
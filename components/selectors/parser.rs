@@ -94,12 +94,22 @@ bitflags! {
         /// disallowed. If this flag is set, `AFTER_PSEUDO_ELEMENT` must be set
         /// as well.
         const AFTER_NON_STATEFUL_PSEUDO_ELEMENT = 1 << 4;
+        /// Whether we're inside the argument of `:has()`, where another
+        /// `:has()` isn't allowed.
+        const DISALLOW_RELATIVE_SELECTOR = 1 << 5;
         /// Whether we are after any of the pseudo-like things.
         const AFTER_PSEUDO = Self::AFTER_PART.bits | Self::AFTER_SLOTTED.bits | Self::AFTER_PSEUDO_ELEMENT.bits;
     }
 }
 
 impl SelectorParsingState {
+    /// The state to parse a nested selector with, like the argument of
+    /// `:is()`. Only the restriction on relative selectors carries over.
+    #[inline]
+    fn for_nested_selector(self) -> Self {
+        self & SelectorParsingState::DISALLOW_RELATIVE_SELECTOR
+    }
+
     #[inline]
     fn allows_functional_pseudo_classes(self) -> bool {
         !self.intersects(SelectorParsingState::AFTER_PSEUDO)
@@ -228,6 +238,16 @@ pub trait Parser<'i> {
         false
     }
 
+    /// Whether to parse the `:is()` and `:where()` pseudo-classes.
+    fn parse_is_and_where(&self) -> bool {
+        false
+    }
+
+    /// Whether to parse the `:has()` pseudo-class.
+    fn parse_has(&self) -> bool {
+        false
+    }
+
     /// This function can return an "Err" pseudo-element in order to support CSS2.1
     /// pseudo-elements.
     fn parse_non_ts_pseudo_class(
@@ -310,10 +330,9 @@ impl<Impl: SelectorImpl> SelectorList<Impl> {
     {
         let mut values = SmallVec::new();
         loop {
-            values.push(
-                input
-                    .parse_until_before(Delimiter::Comma, |input| parse_selector(parser, input))?,
-            );
+            values.push(input.parse_until_before(Delimiter::Comma, |input| {
+                parse_selector(parser, input, SelectorParsingState::empty())
+            })?);
             match input.next() {
                 Err(_) => return Ok(SelectorList(values)),
                 Ok(&Token::Comma) => continue,
@@ -332,13 +351,14 @@ impl<Impl: SelectorImpl> SelectorList<Impl> {
 fn parse_inner_compound_selector<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
 ) -> Result<Selector<Impl>, ParseError<'i, P::Error>>
 where
     P: Parser<'i, Impl = Impl>,
     Impl: SelectorImpl,
 {
     let location = input.current_source_location();
-    let selector = parse_selector(parser, input)?;
+    let selector = parse_selector(parser, input, state.for_nested_selector())?;
 
     // Ensure they're actually all compound selectors without pseudo-elements.
     if selector.has_pseudo_element() {
@@ -364,10 +384,82 @@ where
     Impl: SelectorImpl,
 {
     input
-        .parse_comma_separated(|input| parse_inner_compound_selector(parser, input))
+        .parse_comma_separated(|input| {
+            parse_inner_compound_selector(parser, input, SelectorParsingState::empty())
+        })
         .map(|selectors| selectors.into_boxed_slice())
 }
 
+/// Parses the argument of `:is()` or `:where()`, a comma separated list of
+/// complex selectors without pseudo-elements.
+fn parse_nested_selector_list<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
+) -> Result<ThinBoxedSlice<Selector<Impl>>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
+    let selectors = input.parse_comma_separated(|input| {
+        let location = input.current_source_location();
+        let selector = parse_selector(parser, input, state.for_nested_selector())?;
+        if selector.has_pseudo_element() || selector.is_slotted() || selector.is_part() {
+            return Err(
+                location.new_custom_error(SelectorParseErrorKind::PseudoElementInComplexSelector)
+            );
+        }
+        Ok(selector)
+    })?;
+    Ok(selectors.into_boxed_slice().into())
+}
+
+/// Parses the argument of `:has()`, a comma separated list of relative
+/// selectors without pseudo-elements.
+///
+/// <https://drafts.csswg.org/selectors-4/#relative>
+fn parse_relative_selector_list<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+) -> Result<ThinBoxedSlice<RelativeSelector<Impl>>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
+    let selectors = input.parse_comma_separated(|input| {
+        let location = input.current_source_location();
+        let mut builder = SelectorBuilder::default();
+        builder.push_simple_selector(Component::RelativeSelectorAnchor);
+
+        input.skip_whitespace();
+        let before_combinator = input.state();
+        let combinator = match input.next() {
+            Ok(&Token::Delim('>')) => Combinator::Child,
+            Ok(&Token::Delim('+')) => Combinator::NextSibling,
+            Ok(&Token::Delim('~')) => Combinator::LaterSibling,
+            _ => {
+                input.reset(&before_combinator);
+                Combinator::Descendant
+            },
+        };
+        builder.push_combinator(combinator);
+
+        let selector = parse_selector_with_builder(
+            parser,
+            input,
+            builder,
+            SelectorParsingState::DISALLOW_RELATIVE_SELECTOR,
+        )?;
+        if selector.has_pseudo_element() || selector.is_slotted() || selector.is_part() {
+            return Err(
+                location.new_custom_error(SelectorParseErrorKind::PseudoElementInComplexSelector)
+            );
+        }
+        Ok(RelativeSelector::new(selector))
+    })?;
+    Ok(selectors.into_boxed_slice().into())
+}
+
 /// Ancestor hashes for the bloom filter. We precompute these and store them
 /// inline with selectors to optimize cache performance during matching.
 /// This matters a lot.
@@ -504,6 +596,16 @@ where
                     }
                 }
             },
+            Is(ref list) | Where(ref list) => {
+                if !visitor.visit_selector_list(list) {
+                    return false;
+                }
+            },
+            Has(ref list) => {
+                if !visitor.visit_relative_selector_list(list) {
+                    return false;
+                }
+            },
 
             AttributeInNoNamespaceExists {
                 ref local_name,
@@ -1024,6 +1126,24 @@ pub enum Component<Impl: SelectorImpl> {
     ///
     /// See https://github.com/w3c/csswg-drafts/issues/2158
     Host(Option<Selector<Impl>>),
+    /// The `:is()` pseudo-class, which matches if any of its selectors does.
+    /// Its specificity is the one of its most specific selector.
+    ///
+    /// https://drafts.csswg.org/selectors-4/#matches
+    Is(ThinBoxedSlice<Selector<Impl>>),
+    /// The `:where()` pseudo-class, which is like `:is()` but always has zero
+    /// specificity.
+    ///
+    /// https://drafts.csswg.org/selectors-4/#zero-matches
+    Where(ThinBoxedSlice<Selector<Impl>>),
+    /// The `:has()` pseudo-class, which matches if any of its relative
+    /// selectors matches when anchored at the element.
+    ///
+    /// https://drafts.csswg.org/selectors-4/#relational
+    Has(ThinBoxedSlice<RelativeSelector<Impl>>),
+    /// The implicit leftmost compound selector of a relative selector, which
+    /// only matches the element that anchors it.
+    RelativeSelectorAnchor,
     PseudoElement(#[shmem(field_bound)] Impl::PseudoElement),
 }
 
@@ -1079,6 +1199,112 @@ impl<Impl: SelectorImpl> Component<Impl> {
     }
 }
 
+/// Where the elements that a relative selector may match are, relative to its
+/// anchor, which bounds the search for them during matching and invalidation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, ToShmem)]
+pub enum RelativeSelectorMatchHint {
+    /// The children of the anchor, as in `:has(> a)`.
+    InChild,
+    /// The descendants of the anchor, as in `:has(a)` or `:has(> a b)`.
+    InSubtree,
+    /// The next sibling of the anchor, as in `:has(+ a)`.
+    InNextSibling,
+    /// The descendants of the next sibling of the anchor, and the sibling
+    /// itself, as in `:has(+ a b)`.
+    InNextSiblingSubtree,
+    /// The later siblings of the anchor, as in `:has(~ a)` or `:has(+ a ~ b)`.
+    InSibling,
+    /// The descendants of the later siblings of the anchor, and the siblings
+    /// themselves, as in `:has(~ a b)`.
+    InSiblingSubtree,
+}
+
+impl RelativeSelectorMatchHint {
+    /// Whether the search starts at the siblings of the anchor rather than
+    /// its children.
+    #[inline]
+    pub fn is_sibling(&self) -> bool {
+        !matches!(
+            *self,
+            RelativeSelectorMatchHint::InChild | RelativeSelectorMatchHint::InSubtree
+        )
+    }
+
+    /// Whether the search descends into the subtrees of the elements it
+    /// starts at.
+    #[inline]
+    pub fn is_subtree(&self) -> bool {
+        matches!(
+            *self,
+            RelativeSelectorMatchHint::InSubtree |
+                RelativeSelectorMatchHint::InNextSiblingSubtree |
+                RelativeSelectorMatchHint::InSiblingSubtree
+        )
+    }
+}
+
+/// A relative selector, like the arguments of `:has()`.
+///
+/// The selector is stored with a leftmost `Component::RelativeSelectorAnchor`
+/// compound, followed by the combinator that relates the anchor to the rest of
+/// the selector, so that it can be matched like any other complex selector.
+#[derive(Clone, Debug, Eq, PartialEq, ToShmem)]
+#[shmem(no_bounds)]
+pub struct RelativeSelector<Impl: SelectorImpl> {
+    pub match_hint: RelativeSelectorMatchHint,
+    pub selector: Selector<Impl>,
+}
+
+impl<Impl: SelectorImpl> RelativeSelector<Impl> {
+    fn new(selector: Selector<Impl>) -> Self {
+        // The combinators in parse order, starting with the one that follows
+        // the anchor.
+        let mut combinators = selector
+            .iter_raw_match_order()
+            .rev()
+            .filter_map(|c| c.as_combinator());
+        let leading = combinators
+            .next()
+            .expect("Relative selector without a leading combinator?");
+        let match_hint = match leading {
+            Combinator::Descendant => RelativeSelectorMatchHint::InSubtree,
+            Combinator::Child => {
+                if combinators.any(|c| !c.is_sibling()) {
+                    RelativeSelectorMatchHint::InSubtree
+                } else {
+                    RelativeSelectorMatchHint::InChild
+                }
+            },
+            Combinator::NextSibling | Combinator::LaterSibling => {
+                // Sibling combinators only widen the search to the later
+                // siblings until the first descendant combinator.
+                let mut later_siblings = leading == Combinator::LaterSibling;
+                let mut subtree = false;
+                for combinator in combinators {
+                    if !combinator.is_sibling() {
+                        subtree = true;
+                    } else if !subtree {
+                        later_siblings = true;
+                    }
+                }
+                match (later_siblings, subtree) {
+                    (false, false) => RelativeSelectorMatchHint::InNextSibling,
+                    (false, true) => RelativeSelectorMatchHint::InNextSiblingSubtree,
+                    (true, false) => RelativeSelectorMatchHint::InSibling,
+                    (true, true) => RelativeSelectorMatchHint::InSiblingSubtree,
+                }
+            },
+            Combinator::PseudoElement | Combinator::SlotAssignment | Combinator::Part => {
+                unreachable!("Relative selectors don't start with a pseudo-element")
+            },
+        };
+        RelativeSelector {
+            match_hint,
+            selector,
+        }
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, ToShmem)]
 #[shmem(no_bounds)]
 pub struct LocalName<Impl: SelectorImpl> {
@@ -1111,21 +1337,29 @@ impl<Impl: SelectorImpl> Debug for LocalName<Impl> {
     }
 }
 
+fn serialize_selector_list<'a, Impl, I, W>(mut iter: I, dest: &mut W) -> fmt::Result
+where
+    Impl: SelectorImpl,
+    I: Iterator<Item = &'a Selector<Impl>>,
+    W: fmt::Write,
+{
+    let first = iter
+        .next()
+        .expect("Empty SelectorList, should contain at least one selector");
+    first.to_css(dest)?;
+    for selector in iter {
+        dest.write_str(", ")?;
+        selector.to_css(dest)?;
+    }
+    Ok(())
+}
+
 impl<Impl: SelectorImpl> ToCss for SelectorList<Impl> {
     fn to_css<W>(&self, dest: &mut W) -> fmt::Result
     where
         W: fmt::Write,
     {
-        let mut iter = self.0.iter();
-        let first = iter
-            .next()
-            .expect("Empty SelectorList, should contain at least one selector");
-        first.to_css(dest)?;
-        for selector in iter {
-            dest.write_str(", ")?;
-            selector.to_css(dest)?;
-        }
-        Ok(())
+        serialize_selector_list(self.0.iter(), dest)
     }
 }
 
@@ -1370,6 +1604,31 @@ impl<Impl: SelectorImpl> ToCss for Component<Impl> {
             Root => dest.write_str(":root"),
             Empty => dest.write_str(":empty"),
             Scope => dest.write_str(":scope"),
+            Is(ref list) | Where(ref list) => {
+                match *self {
+                    Is(..) => dest.write_str(":is(")?,
+                    Where(..) => dest.write_str(":where(")?,
+                    _ => unreachable!(),
+                }
+                serialize_selector_list(list.iter(), dest)?;
+                dest.write_char(')')
+            },
+            Has(ref list) => {
+                dest.write_str(":has(")?;
+                let mut first = true;
+                for relative_selector in list.iter() {
+                    if !first {
+                        dest.write_str(", ")?;
+                    }
+                    first = false;
+                    // The anchor serializes to nothing, which leaves the
+                    // whitespace in front of the leading combinator.
+                    let selector = relative_selector.selector.to_css_string();
+                    dest.write_str(selector.trim_start())?;
+                }
+                dest.write_char(')')
+            },
+            RelativeSelectorAnchor => Ok(()),
             Host(ref selector) => {
                 dest.write_str(":host")?;
                 if let Some(ref selector) = *selector {
@@ -1471,19 +1730,33 @@ fn display_to_css_identifier<T: Display, W: fmt::Write>(x: &T, dest: &mut W) ->
 fn parse_selector<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
 ) -> Result<Selector<Impl>, ParseError<'i, P::Error>>
 where
     P: Parser<'i, Impl = Impl>,
     Impl: SelectorImpl,
 {
-    let mut builder = SelectorBuilder::default();
+    parse_selector_with_builder(parser, input, SelectorBuilder::default(), state)
+}
 
+/// Like `parse_selector`, but appends the selector to what `builder` already
+/// contains, like the anchor of a relative selector.
+fn parse_selector_with_builder<'i, 't, P, Impl>(
+    parser: &P,
+    input: &mut CssParser<'i, 't>,
+    mut builder: SelectorBuilder<Impl>,
+    initial_state: SelectorParsingState,
+) -> Result<Selector<Impl>, ParseError<'i, P::Error>>
+where
+    P: Parser<'i, Impl = Impl>,
+    Impl: SelectorImpl,
+{
     let mut has_pseudo_element = false;
     let mut slotted = false;
     let mut part = false;
     'outer_loop: loop {
         // Parse a sequence of simple selectors.
-        let state = match parse_compound_selector(parser, input, &mut builder)? {
+        let state = match parse_compound_selector(parser, input, initial_state, &mut builder)? {
             Some(state) => state,
             None => {
                 return Err(input.new_custom_error(if builder.has_combinators() {
@@ -1549,7 +1822,7 @@ impl<Impl: SelectorImpl> Selector<Impl> {
     where
         P: Parser<'i, Impl = Impl>,
     {
-        parse_selector(parser, input)
+        parse_selector(parser, input, SelectorParsingState::empty())
     }
 }
 
@@ -1944,6 +2217,7 @@ fn parse_attribute_flags<'i, 't>(
 fn parse_negation<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
+    state: SelectorParsingState,
 ) -> Result<Component<Impl>, ParseError<'i, P::Error>>
 where
     P: Parser<'i, Impl = Impl>,
@@ -1965,7 +2239,8 @@ where
         Err(e) => return Err(e.into()),
     };
     if !is_type_sel {
-        match parse_one_simple_selector(parser, input, SelectorParsingState::INSIDE_NEGATION)? {
+        let state = state | SelectorParsingState::INSIDE_NEGATION;
+        match parse_one_simple_selector(parser, input, state)? {
             Some(SimpleSelectorParseResult::SimpleSelector(s)) => {
                 sequence.push(s);
             },
@@ -1996,6 +2271,7 @@ where
 fn parse_compound_selector<'i, 't, P, Impl>(
     parser: &P,
     input: &mut CssParser<'i, 't>,
+    mut state: SelectorParsingState,
     builder: &mut SelectorBuilder<Impl>,
 ) -> Result<Option<SelectorParsingState>, ParseError<'i, P::Error>>
 where
@@ -2016,7 +2292,6 @@ where
         empty = false;
     }
 
-    let mut state = SelectorParsingState::empty();
     loop {
         let parse_result = match parse_one_simple_selector(parser, input, state)? {
             None => break,
@@ -2076,15 +2351,27 @@ where
         "nth-of-type" => return Ok(parse_nth_pseudo_class(input, Component::NthOfType)?),
         "nth-last-child" => return Ok(parse_nth_pseudo_class(input, Component::NthLastChild)?),
         "nth-last-of-type" => return Ok(parse_nth_pseudo_class(input, Component::NthLastOfType)?),
-        "host" => return Ok(Component::Host(Some(parse_inner_compound_selector(parser, input)?))),
+        "host" => return Ok(Component::Host(Some(parse_inner_compound_selector(parser, input, state)?))),
         "not" => {
             if state.intersects(SelectorParsingState::INSIDE_NEGATION) {
                 return Err(input.new_custom_error(
                     SelectorParseErrorKind::UnexpectedIdent("not".into())
                 ));
             }
-            debug_assert!(state.is_empty());
-            return parse_negation(parser, input)
+            debug_assert!(state.for_nested_selector() == state);
+            return parse_negation(parser, input, state)
+        },
+        "is" if P::parse_is_and_where(parser) => {
+            return Ok(Component::Is(parse_nested_selector_list(parser, input, state)?))
+        },
+        "where" if P::parse_is_and_where(parser) => {
+            return Ok(Component::Where(parse_nested_selector_list(parser, input, state)?))
+        },
+        "has" if P::parse_has(parser) => {
+            if state.intersects(SelectorParsingState::DISALLOW_RELATIVE_SELECTOR) {
+                return Err(input.new_custom_error(SelectorParseErrorKind::InvalidState));
+            }
+            return Ok(Component::Has(parse_relative_selector_list(parser, input)?))
         },
         _ => {}
     }
@@ -2205,7 +2492,7 @@ where
                             );
                         }
                         let selector = input.parse_nested_block(|input| {
-                            parse_inner_compound_selector(parser, input)
+                            parse_inner_compound_selector(parser, input, state)
                         })?;
                         return Ok(Some(SimpleSelectorParseResult::SlottedPseudo(selector)));
                     }
@@ -2434,6 +2721,14 @@ pub mod tests {
             true
         }
 
+        fn parse_is_and_where(&self) -> bool {
+            true
+        }
+
+        fn parse_has(&self) -> bool {
+            true
+        }
+
         fn parse_non_ts_pseudo_class(
             &self,
             location: SourceLocation,
@@ -3064,6 +3359,46 @@ pub mod tests {
         assert!(parse("slot::slotted(div,foo)").is_err());
     }
 
+    #[test]
+    fn test_is_where_has() {
+        assert!(parse(":is()").is_err());
+        assert!(parse(":is(::before)").is_err());
+        assert!(parse(":where(div::before)").is_err());
+        assert!(parse(":has()").is_err());
+        assert!(parse(":has(::before)").is_err());
+        assert!(parse(":has(:has(a))").is_err());
+        assert!(parse(":has(:is(:has(a)))").is_err());
+        assert!(parse(":has(:not(:has(a)))").is_err());
+        assert!(parse(":not(:has(a))").is_ok());
+        assert!(parse(":is(:has(a))").is_ok());
+
+        let is = parse(":is(.a, #b, div > span)").unwrap();
+        assert_eq!(is.0[0].specificity(), specificity(1, 0, 0));
+        let where_ = parse(":where(.a, #b, div > span)").unwrap();
+        assert_eq!(where_.0[0].specificity(), specificity(0, 0, 0));
+        let has = parse("div:has(> .a, + b, ~ c d, e)").unwrap();
+        assert_eq!(has.0[0].specificity(), specificity(0, 1, 1));
+        assert!(parse(":not(:is(.a, .b)) :where(:is(c))").is_ok());
+
+        let has = match parse(":has(> .a, + b, ~ c d, e)").unwrap().0[0]
+            .iter()
+            .next()
+        {
+            Some(&Component::Has(ref list)) => list.clone(),
+            _ => unreachable!(),
+        };
+        let hints: Vec<_> = has.iter().map(|s| s.match_hint).collect();
+        assert_eq!(
+            hints,
+            vec![
+                RelativeSelectorMatchHint::InChild,
+                RelativeSelectorMatchHint::InNextSibling,
+                RelativeSelectorMatchHint::InSiblingSubtree,
+                RelativeSelectorMatchHint::InSubtree,
+            ]
+        );
+    }
+
     #[test]
     fn test_pseudo_iter() {
         let selector = &parse("q::before").unwrap().0[0];
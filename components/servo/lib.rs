@@ -114,6 +114,7 @@ use std::borrow::Cow;
 use std::cmp::max;
 use std::path::PathBuf;
 use std::rc::Rc;
+use style_traits::media_features::DeviceFeatures;
 use webrender::{RendererKind, ShaderPrecacheFlags};
 use webrender_traits::{WebrenderExternalImageHandlers, WebrenderImageHandlerType};
 use webvr::{VRServiceManager, WebVRCompositorHandler, WebVRThread};
@@ -301,7 +302,8 @@ where
             opts.time_profiler_trace_path.clone(),
             opts.profile_heartbeats,
         );
-        let mem_profiler_chan = profile_mem::Profiler::create(opts.mem_profiler_period);
+        let mem_profiler_chan =
+            profile_mem::Profiler::create(opts.mem_profiler_period, time_profiler_chan.clone());
         let debugger_chan = opts.debugger_port.map(|port| debugger::start_server(port));
         let devtools_chan = opts.devtools_port.map(|port| devtools::start_server(port));
 
@@ -506,6 +508,7 @@ where
             webvr_constellation_sender,
             glplayer_threads,
             event_loop_waker,
+            window.get_device_features(),
         );
 
         // Send the constellation's swmanager sender to service worker manager thread
@@ -705,6 +708,16 @@ where
                     );
                 }
             },
+
+            WindowEvent::ClearStorageData(url) => {
+                let msg = ConstellationMsg::ClearStorageData(url);
+                if let Err(e) = self.constellation_chan.send(msg) {
+                    warn!(
+                        "Sending ClearStorageData message to constellation failed ({:?}).",
+                        e
+                    );
+                }
+            },
         }
     }
 
@@ -825,6 +838,7 @@ fn create_constellation(
     webvr_constellation_sender: Option<Sender<Sender<ConstellationMsg>>>,
     glplayer_threads: Option<GLPlayerThreads>,
     event_loop_waker: Option<Box<dyn EventLoopWaker>>,
+    device_features: DeviceFeatures,
 ) -> (Sender<ConstellationMsg>, SWManagerSenders) {
     // Global configuration options, parsed from the command line.
     let opts = opts::get();
@@ -876,6 +890,7 @@ fn create_constellation(
         initial_state,
         opts.initial_window_size,
         opts.device_pixels_per_px,
+        device_features,
         opts.random_pipeline_closure_probability,
         opts.random_pipeline_closure_seed,
         opts.is_running_problem_test,
@@ -987,6 +1002,12 @@ fn create_sandbox() {
     ChildSandbox::new(content_process_sandbox_profile())
         .activate()
         .expect("Failed to activate sandbox!");
+
+    // Only let through the syscalls that script and layout need.
+    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+    constellation::content_process_seccomp_filter()
+        .activate()
+        .expect("Failed to activate seccomp filter!");
 }
 
 #[cfg(any(
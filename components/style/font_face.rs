@@ -22,7 +22,6 @@ use crate::values::specified::font::SpecifiedFontVariationSettings;
 use crate::values::specified::font::{AbsoluteFontWeight, FontStretch};
 use crate::values::specified::url::SpecifiedUrl;
 use crate::values::specified::Angle;
-#[cfg(feature = "gecko")]
 use cssparser::UnicodeRange;
 use cssparser::{AtRuleParser, DeclarationListParser, DeclarationParser, Parser};
 use cssparser::{CowRcStr, SourceLocation};
@@ -326,7 +325,7 @@ pub struct EffectiveSources(Vec<Source>);
 impl<'a> FontFace<'a> {
     /// Returns the list of effective sources for that font-face, that is the
     /// sources which don't list any format hint, or the ones which list at
-    /// least "truetype" or "opentype".
+    /// least one format we know how to load.
     pub fn effective_sources(&self) -> EffectiveSources {
         EffectiveSources(
             self.sources()
@@ -335,13 +334,11 @@ impl<'a> FontFace<'a> {
                 .filter(|source| {
                     if let Source::Url(ref url_source) = **source {
                         let hints = &url_source.format_hints;
-                        // We support only opentype fonts and truetype is an alias for
-                        // that format. Sources without format hints need to be
+                        // We support opentype fonts (truetype is an alias for that
+                        // format) and their WOFF and WOFF2 wrappers, which the font
+                        // sanitiser decodes. Sources without format hints need to be
                         // downloaded in case we support them.
-                        hints.is_empty() ||
-                            hints.iter().any(|hint| {
-                                hint == "truetype" || hint == "opentype" || hint == "woff"
-                            })
+                        hints.is_empty() || hints.iter().any(|hint| is_supported_format(hint))
                     } else {
                         true
                     }
@@ -350,6 +347,31 @@ impl<'a> FontFace<'a> {
                 .collect(),
         )
     }
+
+    /// Returns the ranges of code points this font face may be used for, or
+    /// `None` if it may be used for any code point.
+    pub fn unicode_range(&self) -> Option<&[UnicodeRange]> {
+        self.0.unicode_range.as_ref().map(|ranges| &**ranges)
+    }
+}
+
+/// Whether a `format()` hint names a font format that we can load.
+///
+/// <https://drafts.csswg.org/css-fonts/#src-desc>
+#[cfg(feature = "servo")]
+fn is_supported_format(hint: &str) -> bool {
+    [
+        "truetype",
+        "opentype",
+        "woff",
+        "woff2",
+        "truetype-variations",
+        "opentype-variations",
+        "woff-variations",
+        "woff2-variations",
+    ]
+    .iter()
+    .any(|format| hint.eq_ignore_ascii_case(format))
 }
 
 #[cfg(feature = "servo")]
@@ -587,5 +609,7 @@ font_face_descriptors! {
         "src" sources / mSrc: Vec<Source>,
     ]
     optional descriptors = [
+        /// The ranges of code points outside of which this font face should not be used.
+        "unicode-range" unicode_range / mUnicodeRange: Vec<UnicodeRange>,
     ]
 }
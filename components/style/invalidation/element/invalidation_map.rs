@@ -12,7 +12,7 @@ use crate::{Atom, LocalName, Namespace};
 use fallible::FallibleVec;
 use hashglobe::FailedAllocationError;
 use selectors::attr::NamespaceConstraint;
-use selectors::parser::{Combinator, Component};
+use selectors::parser::{Combinator, Component, RelativeSelector};
 use selectors::parser::{Selector, SelectorIter, Visit};
 use selectors::visitor::SelectorVisitor;
 use smallvec::SmallVec;
@@ -47,6 +47,14 @@ pub struct Dependency {
 
     /// The offset into the selector that we should match on.
     pub selector_offset: usize,
+
+    /// The dependency of the compound selector that contains `selector`, if
+    /// this is a dependency of a nested selector, like the ones in `:is()`.
+    ///
+    /// Once the nested selector matches, the compound selector that contains
+    /// it may match as well, so invalidation continues with the rest of the
+    /// parent selector.
+    pub parent: Option<Box<Dependency>>,
 }
 
 /// The kind of elements down the tree this dependency may affect.
@@ -89,7 +97,10 @@ impl Dependency {
     /// The kind of invalidation that this would generate.
     pub fn invalidation_kind(&self) -> DependencyInvalidationKind {
         match self.combinator() {
-            None => DependencyInvalidationKind::Element,
+            None => match self.parent {
+                Some(ref parent) => parent.invalidation_kind(),
+                None => DependencyInvalidationKind::Element,
+            },
             Some(Combinator::Child) | Some(Combinator::Descendant) => {
                 DependencyInvalidationKind::Descendants
             },
@@ -174,6 +185,68 @@ pub struct InvalidationMap {
     /// `other_attribute_affecting_selectors` too even if only the `id` has
     /// changed.
     pub has_id_attribute_selectors: bool,
+    /// What the relative selectors of `:has()` selectors depend on.
+    pub relative_selector_dependencies: RelativeSelectorDependencies,
+}
+
+/// What the relative selectors of `:has()` selectors depend on.
+///
+/// Changes to elements are checked against these before looking for the
+/// elements anchoring `:has()` selectors they may affect, which are restyled
+/// wholesale, see `invalidation::element::relative_selector`.
+#[derive(Debug, MallocSizeOf)]
+pub struct RelativeSelectorDependencies {
+    /// The classes relative selectors depend on.
+    pub classes: MaybeCaseInsensitiveHashMap<Atom, ()>,
+    /// The IDs relative selectors depend on.
+    pub ids: MaybeCaseInsensitiveHashMap<Atom, ()>,
+    /// Whether relative selectors depend on attributes, other than through
+    /// class and ID selectors.
+    pub other_attributes: bool,
+    /// The element states relative selectors depend on.
+    pub state: ElementState,
+}
+
+impl RelativeSelectorDependencies {
+    fn new() -> Self {
+        Self {
+            classes: MaybeCaseInsensitiveHashMap::new(),
+            ids: MaybeCaseInsensitiveHashMap::new(),
+            other_attributes: false,
+            state: ElementState::empty(),
+        }
+    }
+
+    /// Whether there are no relative selectors that depend on anything.
+    pub fn is_empty(&self) -> bool {
+        self.classes.iter().next().is_none() &&
+            self.ids.iter().next().is_none() &&
+            !self.other_attributes &&
+            self.state.is_empty()
+    }
+
+    fn note(
+        &mut self,
+        collector: RelativeSelectorDependencyCollector,
+        quirks_mode: QuirksMode,
+    ) -> Result<(), FailedAllocationError> {
+        for class in collector.classes {
+            self.classes.try_entry(class, quirks_mode)?.or_insert(());
+        }
+        for id in collector.ids {
+            self.ids.try_entry(id, quirks_mode)?.or_insert(());
+        }
+        self.other_attributes |= collector.other_attributes;
+        self.state |= collector.state;
+        Ok(())
+    }
+
+    fn clear(&mut self) {
+        self.classes.clear();
+        self.ids.clear();
+        self.other_attributes = false;
+        self.state = ElementState::empty();
+    }
 }
 
 impl InvalidationMap {
@@ -187,6 +260,7 @@ impl InvalidationMap {
             other_attribute_affecting_selectors: SelectorMap::new(),
             has_class_attribute_selectors: false,
             has_id_attribute_selectors: false,
+            relative_selector_dependencies: RelativeSelectorDependencies::new(),
         }
     }
 
@@ -212,6 +286,7 @@ impl InvalidationMap {
         self.other_attribute_affecting_selectors.clear();
         self.has_id_attribute_selectors = false;
         self.has_class_attribute_selectors = false;
+        self.relative_selector_dependencies.clear();
     }
 
     /// Adds a selector to this `InvalidationMap`.  Returns Err(..) to
@@ -223,33 +298,59 @@ impl InvalidationMap {
     ) -> Result<(), FailedAllocationError> {
         debug!("InvalidationMap::note_selector({:?})", selector);
 
+        let mut document_state = DocumentState::empty();
+        self.note_selector_with_parent(selector, None, &mut document_state, quirks_mode)?;
+
+        if !document_state.is_empty() {
+            self.document_state_selectors
+                .try_push(DocumentStateDependency {
+                    state: document_state,
+                    selector: selector.clone(),
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the dependencies of a selector, which is nested in the compound
+    /// selector of `parent` if any.
+    fn note_selector_with_parent(
+        &mut self,
+        selector: &Selector<SelectorImpl>,
+        parent: Option<&Dependency>,
+        document_state: &mut DocumentState,
+        quirks_mode: QuirksMode,
+    ) -> Result<(), FailedAllocationError> {
         let mut iter = selector.iter();
         let mut combinator;
         let mut index = 0;
 
-        let mut document_state = DocumentState::empty();
-
         loop {
             let sequence_start = index;
+            let dependency = Dependency {
+                selector: selector.clone(),
+                selector_offset: sequence_start,
+                parent: parent.map(|parent| Box::new(parent.clone())),
+            };
 
             let mut compound_visitor = CompoundSelectorDependencyCollector {
                 classes: SmallVec::new(),
                 ids: SmallVec::new(),
                 state: ElementState::empty(),
-                document_state: &mut document_state,
+                document_state: &mut *document_state,
                 other_attributes: false,
                 has_id_attribute_selectors: false,
                 has_class_attribute_selectors: false,
+                nested_selectors: SmallVec::new(),
+                relative_selector_dependencies: RelativeSelectorDependencyCollector::default(),
             };
 
             // Visit all the simple selectors in this sequence.
             //
-            // Note that this works because we can't have combinators nested
-            // inside simple selectors (i.e. in :not() or :-moz-any()).
-            //
-            // If we ever support that we'll need to visit nested complex
-            // selectors as well, in order to mark them as affecting descendants
-            // at least.
+            // Selectors nested inside simple selectors (i.e. in :is()) are
+            // collected to be noted separately below, with this compound
+            // selector as their parent, while the relative selectors of :has()
+            // are only tracked as a whole.
             for ss in &mut iter {
                 ss.visit(&mut compound_visitor);
                 index += 1; // Account for the simple selector.
@@ -257,34 +358,28 @@ impl InvalidationMap {
 
             self.has_id_attribute_selectors |= compound_visitor.has_id_attribute_selectors;
             self.has_class_attribute_selectors |= compound_visitor.has_class_attribute_selectors;
+            let nested_selectors = compound_visitor.nested_selectors;
+            self.relative_selector_dependencies
+                .note(compound_visitor.relative_selector_dependencies, quirks_mode)?;
 
             for class in compound_visitor.classes {
                 self.class_to_selector
                     .try_entry(class, quirks_mode)?
                     .or_insert_with(SmallVec::new)
-                    .try_push(Dependency {
-                        selector: selector.clone(),
-                        selector_offset: sequence_start,
-                    })?;
+                    .try_push(dependency.clone())?;
             }
 
             for id in compound_visitor.ids {
                 self.id_to_selector
                     .try_entry(id, quirks_mode)?
                     .or_insert_with(SmallVec::new)
-                    .try_push(Dependency {
-                        selector: selector.clone(),
-                        selector_offset: sequence_start,
-                    })?;
+                    .try_push(dependency.clone())?;
             }
 
             if !compound_visitor.state.is_empty() {
                 self.state_affecting_selectors.insert(
                     StateDependency {
-                        dep: Dependency {
-                            selector: selector.clone(),
-                            selector_offset: sequence_start,
-                        },
+                        dep: dependency.clone(),
                         state: compound_visitor.state,
                     },
                     quirks_mode,
@@ -292,11 +387,15 @@ impl InvalidationMap {
             }
 
             if compound_visitor.other_attributes {
-                self.other_attribute_affecting_selectors.insert(
-                    Dependency {
-                        selector: selector.clone(),
-                        selector_offset: sequence_start,
-                    },
+                self.other_attribute_affecting_selectors
+                    .insert(dependency.clone(), quirks_mode)?;
+            }
+
+            for nested_selector in nested_selectors {
+                self.note_selector_with_parent(
+                    &nested_selector,
+                    Some(&dependency),
+                    document_state,
                     quirks_mode,
                 )?;
             }
@@ -309,14 +408,6 @@ impl InvalidationMap {
             index += 1; // Account for the combinator.
         }
 
-        if !document_state.is_empty() {
-            self.document_state_selectors
-                .try_push(DocumentStateDependency {
-                    state: document_state,
-                    selector: selector.clone(),
-                })?;
-        }
-
         Ok(())
     }
 }
@@ -354,11 +445,32 @@ struct CompoundSelectorDependencyCollector<'a> {
 
     /// Whether there were attribute selectors with the class attribute.
     has_class_attribute_selectors: bool,
+
+    /// The selectors nested in this compound selector, like in :is().
+    nested_selectors: SmallVec<[Selector<SelectorImpl>; 1]>,
+
+    /// What the relative selectors in this compound selector depend on.
+    relative_selector_dependencies: RelativeSelectorDependencyCollector,
 }
 
 impl<'a> SelectorVisitor for CompoundSelectorDependencyCollector<'a> {
     type Impl = SelectorImpl;
 
+    fn visit_selector_list(&mut self, list: &[Selector<SelectorImpl>]) -> bool {
+        self.nested_selectors.extend(list.iter().cloned());
+        true
+    }
+
+    fn visit_relative_selector_list(&mut self, list: &[RelativeSelector<SelectorImpl>]) -> bool {
+        for relative_selector in list {
+            relative_selector
+                .selector
+                .visit(&mut self.relative_selector_dependencies);
+        }
+        *self.document_state |= self.relative_selector_dependencies.document_state;
+        true
+    }
+
     fn visit_simple_selector(&mut self, s: &Component<SelectorImpl>) -> bool {
         #[cfg(feature = "gecko")]
         use crate::selector_parser::NonTSPseudoClass;
@@ -405,3 +517,54 @@ impl<'a> SelectorVisitor for CompoundSelectorDependencyCollector<'a> {
         true
     }
 }
+
+/// A struct that collects what the relative selectors of `:has()` selectors
+/// depend on, including the selectors nested in them.
+#[derive(Default)]
+struct RelativeSelectorDependencyCollector {
+    classes: SmallVec<[Atom; 5]>,
+    ids: SmallVec<[Atom; 5]>,
+    other_attributes: bool,
+    state: ElementState,
+    document_state: DocumentState,
+}
+
+impl SelectorVisitor for RelativeSelectorDependencyCollector {
+    type Impl = SelectorImpl;
+
+    fn visit_simple_selector(&mut self, s: &Component<SelectorImpl>) -> bool {
+        #[cfg(feature = "gecko")]
+        use crate::selector_parser::NonTSPseudoClass;
+
+        match *s {
+            Component::ID(ref id) => {
+                self.ids.push(id.clone());
+            },
+            Component::Class(ref class) => {
+                self.classes.push(class.clone());
+            },
+            Component::NonTSPseudoClass(ref pc) => {
+                self.other_attributes |= pc.is_attr_based();
+                self.state |= match *pc {
+                    #[cfg(feature = "gecko")]
+                    NonTSPseudoClass::Dir(ref dir) => dir.element_state(),
+                    _ => pc.state_flag(),
+                };
+                self.document_state |= pc.document_state_flag();
+            },
+            _ => {},
+        }
+
+        true
+    }
+
+    fn visit_attribute_selector(
+        &mut self,
+        _constraint: &NamespaceConstraint<&Namespace>,
+        _local_name: &LocalName,
+        _local_name_lower: &LocalName,
+    ) -> bool {
+        self.other_attributes = true;
+        true
+    }
+}
@@ -7,6 +7,7 @@
 
 use crate::context::StackLimitChecker;
 use crate::dom::{TElement, TNode, TShadowRoot};
+use crate::invalidation::element::invalidation_map::{Dependency, DependencyInvalidationKind};
 use crate::selector_parser::SelectorImpl;
 use selectors::matching::matches_compound_selector_from;
 use selectors::matching::{CompoundSelectorMatchingResult, MatchingContext};
@@ -132,6 +133,9 @@ pub struct Invalidation<'a> {
     /// This order is a "parse order" offset, that is, zero is the leftmost part
     /// of the selector written as parsed / serialized.
     offset: usize,
+    /// The dependency of the compound selector that contains `selector`, if
+    /// it's nested, which continues the invalidation once `selector` matches.
+    parent: Option<&'a Dependency>,
     /// Whether the invalidation was already matched by any previous sibling or
     /// ancestor.
     ///
@@ -147,6 +151,7 @@ impl<'a> Invalidation<'a> {
         Self {
             selector,
             offset,
+            parent: None,
             matched_by_any_previous: false,
         }
     }
@@ -204,6 +209,58 @@ impl<'a> fmt::Debug for Invalidation<'a> {
     }
 }
 
+/// Pushes the invalidations for a dependency that may have started or stopped
+/// matching an element, in order to invalidate the rest of its selector.
+///
+/// Returns whether the element itself needs to be invalidated.
+pub fn push_dependency_invalidations<'a>(
+    dependency: &'a Dependency,
+    descendant_invalidations: &mut DescendantInvalidationLists<'a>,
+    sibling_invalidations: &mut InvalidationVector<'a>,
+) -> bool {
+    // A fully matched nested selector continues with the selector that
+    // contains it.
+    let mut dependency = dependency;
+    while dependency.selector_offset == 0 {
+        dependency = match dependency.parent {
+            Some(ref parent) => parent,
+            None => return true,
+        };
+    }
+
+    debug_assert_ne!(dependency.selector_offset, dependency.selector.len());
+
+    let invalidation = Invalidation {
+        selector: &dependency.selector,
+        offset: dependency.selector.len() - dependency.selector_offset + 1,
+        parent: dependency.parent.as_ref().map(|parent| &**parent),
+        matched_by_any_previous: false,
+    };
+
+    match dependency.invalidation_kind() {
+        DependencyInvalidationKind::Element => unreachable!(),
+        DependencyInvalidationKind::ElementAndDescendants => {
+            descendant_invalidations.dom_descendants.push(invalidation);
+            return true;
+        },
+        DependencyInvalidationKind::Descendants => {
+            descendant_invalidations.dom_descendants.push(invalidation);
+        },
+        DependencyInvalidationKind::Siblings => {
+            sibling_invalidations.push(invalidation);
+        },
+        DependencyInvalidationKind::Parts => {
+            descendant_invalidations.parts.push(invalidation);
+        },
+        DependencyInvalidationKind::SlottedElements => {
+            descendant_invalidations
+                .slotted_descendants
+                .push(invalidation);
+        },
+    }
+    false
+}
+
 /// The result of processing a single invalidation for a given element.
 struct SingleInvalidationResult {
     /// Whether the element itself was invalidated.
@@ -746,7 +803,16 @@ where
             CompoundSelectorMatchingResult::FullyMatched => {
                 debug!(" > Invalidation matched completely");
                 matched = true;
-                invalidated_self = true;
+                invalidated_self = match invalidation.parent {
+                    // The nested selector matched, so the compound selector
+                    // that contains it may match the element now too.
+                    Some(parent) => push_dependency_invalidations(
+                        parent,
+                        descendant_invalidations,
+                        sibling_invalidations,
+                    ),
+                    None => true,
+                };
             },
             CompoundSelectorMatchingResult::Matched {
                 next_combinator_offset,
@@ -822,6 +888,7 @@ where
                 let next_invalidation = Invalidation {
                     selector: invalidation.selector,
                     offset: next_combinator_offset + 1,
+                    parent: invalidation.parent,
                     matched_by_any_previous: false,
                 };
 
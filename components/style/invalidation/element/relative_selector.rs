@@ -0,0 +1,182 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Invalidation of the elements anchoring `:has()` selectors.
+//!
+//! Whether a `:has()` selector matches an element depends on its descendants
+//! or later siblings, which the regular invalidation never looks at, since it
+//! only goes down and forward from the changed element. Instead, matching
+//! flags the elements `:has()` selectors were matched against as anchors, and
+//! relevant changes restyle the anchors before or above the changed element.
+
+use crate::dom::TElement;
+use crate::element_state::ElementState;
+use crate::invalidation::element::element_wrapper::{ElementSnapshot, ElementWrapper};
+use crate::invalidation::element::invalidation_map::RelativeSelectorDependencies;
+use crate::invalidation::element::restyle_hints::RestyleHint;
+use crate::selector_parser::SnapshotMap;
+use crate::stylist::Stylist;
+use selectors::attr::CaseSensitivity;
+use selectors::matching::ElementSelectorFlags;
+use selectors::matching::QuirksMode;
+
+/// Restyles the elements whose `:has()` selectors may have stopped or started
+/// matching because of the changes in the snapshot of `element`.
+///
+/// This needs to happen before the traversal, since it restyles ancestors.
+pub fn invalidate_relative_selector_anchors<E>(
+    element: E,
+    snapshot_map: &SnapshotMap,
+    stylist: &Stylist,
+) where
+    E: TElement,
+{
+    let wrapper = ElementWrapper::new(element, snapshot_map);
+    let snapshot = match wrapper.snapshot() {
+        Some(snapshot) => snapshot,
+        None => return,
+    };
+    let state_changes = wrapper.state_changes();
+    if !snapshot.has_attrs() && state_changes.is_empty() {
+        return;
+    }
+
+    let quirks_mode = stylist.quirks_mode();
+    let affects_relative_selectors = stylist.iter_origins().any(|(cascade_data, _)| {
+        affects_dependencies(
+            element,
+            snapshot,
+            state_changes,
+            &cascade_data
+                .invalidation_map()
+                .relative_selector_dependencies,
+            quirks_mode,
+        )
+    });
+    if !affects_relative_selectors {
+        return;
+    }
+
+    debug!("Invalidating :has() anchors of {:?}", element);
+    restyle_relative_selector_anchors(element);
+}
+
+fn affects_dependencies<E, S>(
+    element: E,
+    snapshot: &S,
+    state_changes: ElementState,
+    dependencies: &RelativeSelectorDependencies,
+    quirks_mode: QuirksMode,
+) -> bool
+where
+    E: TElement,
+    S: ElementSnapshot,
+{
+    if dependencies.is_empty() {
+        return false;
+    }
+
+    if dependencies.state.intersects(state_changes) {
+        return true;
+    }
+
+    if !snapshot.has_attrs() {
+        return false;
+    }
+
+    // Attribute selectors may match the class and id attributes too.
+    if dependencies.other_attributes {
+        return true;
+    }
+
+    if snapshot.id_attr() != element.id() {
+        let has_dependency =
+            |id: Option<_>| id.map_or(false, |id| dependencies.ids.get(id, quirks_mode).is_some());
+        if has_dependency(snapshot.id_attr()) || has_dependency(element.id()) {
+            return true;
+        }
+    }
+
+    let mut affects_classes = false;
+    snapshot.each_class(|class| {
+        if !affects_classes &&
+            !element.has_class(class, CaseSensitivity::CaseSensitive) &&
+            dependencies.classes.get(class, quirks_mode).is_some()
+        {
+            affects_classes = true;
+        }
+    });
+    element.each_class(|class| {
+        if !affects_classes &&
+            !snapshot.has_class(class, CaseSensitivity::CaseSensitive) &&
+            dependencies.classes.get(class, quirks_mode).is_some()
+        {
+            affects_classes = true;
+        }
+    });
+    affects_classes
+}
+
+/// Restyles the `:has()` anchors that may depend on `element`: its ancestors,
+/// and the previous siblings of it and its ancestors.
+pub fn restyle_relative_selector_anchors<E>(element: E)
+where
+    E: TElement,
+{
+    let mut current = Some(element);
+    while let Some(element) = current {
+        let parent = element.parent_element();
+        let has_sibling_anchors = parent.map_or(false, |parent| {
+            parent.has_selector_flags(ElementSelectorFlags::HAS_SIBLING_RELATIVE_SELECTOR)
+        });
+        if has_sibling_anchors {
+            let mut sibling = element.prev_sibling_element();
+            while let Some(s) = sibling {
+                restyle_if_anchor(s);
+                sibling = s.prev_sibling_element();
+            }
+        }
+        if let Some(parent) = parent {
+            restyle_if_anchor(parent);
+        }
+        current = parent;
+    }
+}
+
+fn restyle_if_anchor<E>(element: E)
+where
+    E: TElement,
+{
+    if element.has_selector_flags(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR_NON_SUBJECT) {
+        // Selectors may depend on the anchor matching through any later
+        // combinator.
+        restyle(element, RestyleHint::restyle_subtree());
+        let mut sibling = element.next_sibling_element();
+        while let Some(s) = sibling {
+            restyle(s, RestyleHint::restyle_subtree());
+            sibling = s.next_sibling_element();
+        }
+    } else if element.has_selector_flags(ElementSelectorFlags::ANCHORS_RELATIVE_SELECTOR) {
+        restyle(element, RestyleHint::RESTYLE_SELF);
+    }
+}
+
+fn restyle<E>(element: E, hint: RestyleHint)
+where
+    E: TElement,
+{
+    match element.mutate_data() {
+        Some(mut data) => data.hint.insert(hint),
+        None => return,
+    }
+
+    let mut current = element.traversal_parent();
+    while let Some(parent) = current {
+        if parent.has_dirty_descendants() {
+            break;
+        }
+        unsafe { parent.set_dirty_descendants() };
+        current = parent.traversal_parent();
+    }
+}
@@ -11,8 +11,9 @@ use crate::dom::TElement;
 use crate::element_state::ElementState;
 use crate::invalidation::element::element_wrapper::{ElementSnapshot, ElementWrapper};
 use crate::invalidation::element::invalidation_map::*;
+use crate::invalidation::element::invalidator::push_dependency_invalidations;
+use crate::invalidation::element::invalidator::InvalidationProcessor;
 use crate::invalidation::element::invalidator::{DescendantInvalidationLists, InvalidationVector};
-use crate::invalidation::element::invalidator::{Invalidation, InvalidationProcessor};
 use crate::invalidation::element::restyle_hints::RestyleHint;
 use crate::selector_map::SelectorMap;
 use crate::selector_parser::Snapshot;
@@ -442,45 +443,11 @@ where
     fn note_dependency(&mut self, dependency: &'selectors Dependency) {
         debug_assert!(self.dependency_may_be_relevant(dependency));
 
-        let invalidation_kind = dependency.invalidation_kind();
-        if matches!(invalidation_kind, DependencyInvalidationKind::Element) {
-            self.invalidates_self = true;
-            return;
-        }
-
-        debug_assert_ne!(dependency.selector_offset, 0);
-        debug_assert_ne!(dependency.selector_offset, dependency.selector.len());
-
-        let invalidation = Invalidation::new(
-            &dependency.selector,
-            dependency.selector.len() - dependency.selector_offset + 1,
+        self.invalidates_self |= push_dependency_invalidations(
+            dependency,
+            self.descendant_invalidations,
+            self.sibling_invalidations,
         );
-
-        match invalidation_kind {
-            DependencyInvalidationKind::Element => unreachable!(),
-            DependencyInvalidationKind::ElementAndDescendants => {
-                self.invalidates_self = true;
-                self.descendant_invalidations
-                    .dom_descendants
-                    .push(invalidation);
-            },
-            DependencyInvalidationKind::Descendants => {
-                self.descendant_invalidations
-                    .dom_descendants
-                    .push(invalidation);
-            },
-            DependencyInvalidationKind::Siblings => {
-                self.sibling_invalidations.push(invalidation);
-            },
-            DependencyInvalidationKind::Parts => {
-                self.descendant_invalidations.parts.push(invalidation);
-            },
-            DependencyInvalidationKind::SlottedElements => {
-                self.descendant_invalidations
-                    .slotted_descendants
-                    .push(invalidation);
-            },
-        }
     }
 
     /// Returns whether `dependency` may cause us to invalidate the style of
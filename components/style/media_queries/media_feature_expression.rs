@@ -294,11 +294,11 @@ impl MediaFeatureExpression {
             let result = {
                 let mut feature_name = &**ident;
 
-                #[cfg(feature = "gecko")]
-                {
-                    if starts_with_ignore_ascii_case(feature_name, "-webkit-") {
-                        feature_name = &feature_name[8..];
-                        requirements.insert(ParsingRequirements::WEBKIT_PREFIX);
+                if starts_with_ignore_ascii_case(feature_name, "-webkit-") {
+                    feature_name = &feature_name[8..];
+                    requirements.insert(ParsingRequirements::WEBKIT_PREFIX);
+                    #[cfg(feature = "gecko")]
+                    {
                         if static_prefs::pref!("layout.css.prefixes.device-pixel-ratio-webkit") {
                             requirements.insert(
                                 ParsingRequirements::WEBKIT_DEVICE_PIXEL_RATIO_PREF_ENABLED,
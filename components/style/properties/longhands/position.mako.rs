@@ -351,8 +351,10 @@ ${helpers.predefined_type(
             "grid-%s-%s" % (kind, range),
             "GridLine",
             "Default::default()",
-            engines="gecko",
+            engines="gecko servo-2013",
+            servo_2013_pref="layout.grid.enabled",
             animation_value_type="discrete",
+            servo_restyle_damage="reflow",
             spec="https://drafts.csswg.org/css-grid/#propdef-grid-%s-%s" % (kind, range),
         )}
     % endfor
@@ -361,8 +363,10 @@ ${helpers.predefined_type(
         "grid-auto-%ss" % kind,
         "ImplicitGridTracks",
         "Default::default()",
-        engines="gecko",
+        engines="gecko servo-2013",
+        servo_2013_pref="layout.grid.enabled",
         animation_value_type="discrete",
+        servo_restyle_damage="reflow",
         spec="https://drafts.csswg.org/css-grid/#propdef-grid-auto-%ss" % kind,
     )}
 
@@ -370,9 +374,11 @@ ${helpers.predefined_type(
         "grid-template-%ss" % kind,
         "GridTemplateComponent",
         "specified::GenericGridTemplateComponent::None",
-        engines="gecko",
+        engines="gecko servo-2013",
+        servo_2013_pref="layout.grid.enabled",
         spec="https://drafts.csswg.org/css-grid/#propdef-grid-template-%ss" % kind,
         animation_value_type="ComputedValue",
+        servo_restyle_damage="reflow",
     )}
 
 % endfor
@@ -381,8 +387,10 @@ ${helpers.predefined_type(
     "grid-auto-flow",
     "GridAutoFlow",
     "computed::GridAutoFlow::row()",
-    engines="gecko",
+    engines="gecko servo-2013",
+    servo_2013_pref="layout.grid.enabled",
     animation_value_type="discrete",
+    servo_restyle_damage="reflow",
     spec="https://drafts.csswg.org/css-grid/#propdef-grid-auto-flow",
 )}
 
@@ -390,8 +398,10 @@ ${helpers.predefined_type(
     "grid-template-areas",
     "GridTemplateAreas",
     "computed::GridTemplateAreas::none()",
-    engines="gecko",
+    engines="gecko servo-2013",
+    servo_2013_pref="layout.grid.enabled",
     animation_value_type="discrete",
+    servo_restyle_damage="reflow",
     spec="https://drafts.csswg.org/css-grid/#propdef-grid-template-areas",
 )}
 
@@ -413,8 +423,9 @@ ${helpers.predefined_type(
     "row-gap",
     "length::NonNegativeLengthPercentageOrNormal",
     "computed::length::NonNegativeLengthPercentageOrNormal::normal()",
-    engines="gecko",
+    engines="gecko servo-2013",
     alias="grid-row-gap",
+    servo_2013_pref="layout.grid.enabled",
     spec="https://drafts.csswg.org/css-align-3/#propdef-row-gap",
     animation_value_type="NonNegativeLengthPercentageOrNormal",
     servo_restyle_damage="reflow",
@@ -18,16 +18,16 @@
         line-height
         font-family
         ${'font-size-adjust' if engine == 'gecko' else ''}
-        ${'font-kerning' if engine == 'gecko' else ''}
+        font-kerning
         ${'font-optical-sizing' if engine == 'gecko' else ''}
         ${'font-variant-alternates' if engine == 'gecko' else ''}
-        ${'font-variant-east-asian' if engine == 'gecko' else ''}
-        ${'font-variant-ligatures' if engine == 'gecko' else ''}
-        ${'font-variant-numeric' if engine == 'gecko' else ''}
-        ${'font-variant-position' if engine == 'gecko' else ''}
+        font-variant-east-asian
+        font-variant-ligatures
+        font-variant-numeric
+        font-variant-position
         ${'font-language-override' if engine == 'gecko' else ''}
-        ${'font-feature-settings' if engine == 'gecko' else ''}
-        ${'font-variation-settings' if engine == 'gecko' else ''}
+        font-feature-settings
+        font-variation-settings
     "
     derive_value_info="False"
     spec="https://drafts.csswg.org/css-fonts-3/#propdef-font"
@@ -42,17 +42,16 @@
     use crate::values::specified::font::{FontStretch, FontStretchKeyword};
 
     <%
-        gecko_sub_properties = "kerning language_override size_adjust \
-                                variant_alternates variant_east_asian \
-                                variant_ligatures variant_numeric \
-                                variant_position feature_settings \
-                                variation_settings optical_sizing".split()
+        reset_sub_properties = "kerning variant_east_asian variant_ligatures \
+                                variant_numeric variant_position \
+                                feature_settings variation_settings".split()
+        if engine == "gecko":
+            reset_sub_properties += "language_override size_adjust \
+                                     variant_alternates optical_sizing".split()
     %>
-    % if engine == "gecko":
-        % for prop in gecko_sub_properties:
-            use crate::properties::longhands::font_${prop};
-        % endfor
-    % endif
+    % for prop in reset_sub_properties:
+        use crate::properties::longhands::font_${prop};
+    % endfor
     use self::font_family::SpecifiedValue as FontFamily;
 
     pub fn parse_value<'i, 't>(
@@ -146,11 +145,9 @@
             font_size: size,
             line_height: line_height.unwrap_or(LineHeight::normal()),
             font_family: family,
-            % if engine == "gecko":
-                % for name in gecko_sub_properties:
-                    font_${name}: font_${name}::get_initial_specified_value(),
-                % endfor
-            % endif
+            % for name in reset_sub_properties:
+                font_${name}: font_${name}::get_initial_specified_value(),
+            % endfor
         })
     }
 
@@ -172,26 +169,19 @@
                 }
             % endif
 
-            % if engine == "gecko":
-            if let Some(v) = self.font_optical_sizing {
-                if v != &font_optical_sizing::get_initial_specified_value() {
-                    return Ok(());
-                }
-            }
-            if let Some(v) = self.font_variation_settings {
-                if v != &font_variation_settings::get_initial_specified_value() {
+            % for name in reset_sub_properties:
+            % if engine == "gecko" and name in ["optical_sizing", "variation_settings"]:
+            if let Some(v) = self.font_${name} {
+                if v != &font_${name}::get_initial_specified_value() {
                     return Ok(());
                 }
             }
-
-            % for name in gecko_sub_properties:
-            % if name != "optical_sizing" and name != "variation_settings":
+            % else:
             if self.font_${name} != &font_${name}::get_initial_specified_value() {
                 return Ok(());
             }
             % endif
             % endfor
-            % endif
 
             // Only font-stretch keywords are allowed as part as the font
             // shorthand.
@@ -300,22 +290,21 @@
                     flags="SHORTHAND_IN_GETCS"
                     sub_properties="font-variant-caps
                                     ${'font-variant-alternates' if engine == 'gecko' else ''}
-                                    ${'font-variant-east-asian' if engine == 'gecko' else ''}
-                                    ${'font-variant-ligatures' if engine == 'gecko' else ''}
-                                    ${'font-variant-numeric' if engine == 'gecko' else ''}
-                                    ${'font-variant-position' if engine == 'gecko' else ''}"
+                                    font-variant-east-asian
+                                    font-variant-ligatures
+                                    font-variant-numeric
+                                    font-variant-position"
                     spec="https://drafts.csswg.org/css-fonts-3/#propdef-font-variant">
-    <% gecko_sub_properties = "alternates east_asian ligatures numeric position".split() %>
     <%
         sub_properties = ["caps"]
         if engine == "gecko":
-            sub_properties += gecko_sub_properties
+            sub_properties.append("alternates")
+        sub_properties += "east_asian ligatures numeric position".split()
     %>
 
 % for prop in sub_properties:
     use crate::properties::longhands::font_variant_${prop};
 % endfor
-    #[allow(unused_imports)]
     use crate::values::specified::FontVariantLigatures;
 
     pub fn parse_value<'i, 't>(
@@ -331,9 +320,7 @@
         } else if input.try(|input| input.expect_ident_matching("none")).is_ok() {
             // The 'none' value sets 'font-variant-ligatures' to 'none' and resets all other sub properties
             // to their initial value.
-        % if engine == "gecko":
             ligatures = Some(FontVariantLigatures::none());
-        % endif
         } else {
             let mut has_custom_value: bool = false;
             loop {
@@ -371,11 +358,7 @@
         fn to_css<W>(&self, dest: &mut CssWriter<W>) -> fmt::Result where W: fmt::Write {
 
             let has_none_ligatures =
-            % if engine == "gecko":
                 self.font_variant_ligatures == &FontVariantLigatures::none();
-            % else:
-                false;
-            % endif
 
             const TOTAL_SUBPROPS: usize = ${len(sub_properties)};
             let mut nb_normals = 0;
@@ -43,7 +43,7 @@ use selectors::bloom::BloomFilter;
 use selectors::matching::VisitedHandlingMode;
 use selectors::matching::{matches_selector, ElementSelectorFlags, MatchingContext, MatchingMode};
 use selectors::parser::{AncestorHashes, Combinator, Component, Selector};
-use selectors::parser::{SelectorIter, Visit};
+use selectors::parser::{RelativeSelector, SelectorIter, Visit};
 use selectors::visitor::SelectorVisitor;
 use selectors::NthIndexCache;
 use servo_arc::{Arc, ArcBorrow};
@@ -1571,7 +1571,8 @@ fn component_needs_revalidation(
         Component::NthLastOfType(..) |
         Component::FirstOfType |
         Component::LastOfType |
-        Component::OnlyOfType => true,
+        Component::OnlyOfType |
+        Component::Has(..) => true,
         Component::NonTSPseudoClass(ref p) => p.needs_cache_revalidation(),
         _ => false,
     }
@@ -1584,9 +1585,8 @@ impl<'a> SelectorVisitor for StylistSelectorVisitor<'a> {
         self.needs_revalidation =
             self.needs_revalidation || combinator.map_or(false, |c| c.is_sibling());
 
-        // NOTE(emilio): This works properly right now because we can't store
-        // complex selectors in nested selectors, otherwise we may need to
-        // rethink this.
+        // NOTE(emilio): Nested complex selectors, like the ones in :is(),
+        // restore this when they're done, see visit_selector_list.
         //
         // Also, note that this call happens before we visit any of the simple
         // selectors in the next ComplexSelector, so we can use this to skip
@@ -1597,6 +1597,31 @@ impl<'a> SelectorVisitor for StylistSelectorVisitor<'a> {
         true
     }
 
+    fn visit_selector_list(&mut self, list: &[Selector<SelectorImpl>]) -> bool {
+        for selector in list {
+            // Each nested selector starts at the compound selector that
+            // contains it.
+            let passed_rightmost_selector = self.passed_rightmost_selector;
+            let result = selector.visit(self);
+            self.passed_rightmost_selector = passed_rightmost_selector;
+            if !result {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn visit_relative_selector_list(&mut self, list: &[RelativeSelector<SelectorImpl>]) -> bool {
+        // Relative selectors only match elements other than their anchor.
+        let passed_rightmost_selector = self.passed_rightmost_selector;
+        self.passed_rightmost_selector = true;
+        let result = list
+            .iter()
+            .all(|relative_selector| relative_selector.selector.visit(self));
+        self.passed_rightmost_selector = passed_rightmost_selector;
+        result
+    }
+
     fn visit_attribute_selector(
         &mut self,
         _ns: &NamespaceConstraint<&Namespace>,
@@ -87,6 +87,7 @@ pub enum CSSPixel {}
 //     / desktop_zoom => CSSPixel
 
 pub mod arc_slice;
+pub mod media_features;
 pub mod specified_value_info;
 #[macro_use]
 pub mod values;
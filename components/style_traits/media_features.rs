@@ -0,0 +1,65 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Characteristics of the output device and user preferences that media
+//! queries can be evaluated against, as reported by the embedder.
+
+/// The color scheme the user prefers.
+///
+/// <https://drafts.csswg.org/mediaqueries-5/#prefers-color-scheme>
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "servo", derive(Deserialize, Serialize, MallocSizeOf))]
+pub enum ColorSchemePreference {
+    /// The user prefers a light color scheme.
+    Light,
+    /// The user prefers a dark color scheme.
+    Dark,
+    /// The user has expressed no preference.
+    NoPreference,
+}
+
+bitflags! {
+    /// The capabilities of a pointing device.
+    ///
+    /// <https://drafts.csswg.org/mediaqueries-4/#mf-interaction>
+    #[cfg_attr(feature = "servo", derive(Deserialize, Serialize, MallocSizeOf))]
+    pub struct PointerCapabilities: u8 {
+        /// The device has limited accuracy, like a finger on a touchscreen.
+        const COARSE = 1 << 0;
+        /// The device is accurate, like a mouse.
+        const FINE = 1 << 1;
+        /// The device can hover over elements.
+        const HOVER = 1 << 2;
+    }
+}
+
+/// The media features of a window that can't be derived from its size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "servo", derive(Deserialize, Serialize, MallocSizeOf))]
+pub struct DeviceFeatures {
+    /// The color scheme the user prefers.
+    pub prefers_color_scheme: ColorSchemePreference,
+    /// Whether the user asked to minimize non-essential motion.
+    pub prefers_reduced_motion: bool,
+    /// The capabilities of the primary pointing device.
+    pub primary_pointer: PointerCapabilities,
+    /// The union of the capabilities of all available pointing devices.
+    pub all_pointers: PointerCapabilities,
+    /// The number of bits per color component of the output device,
+    /// or zero for a monochrome device.
+    pub color_bits_per_channel: u32,
+}
+
+impl Default for DeviceFeatures {
+    /// A desktop with a mouse and a true color display.
+    fn default() -> Self {
+        DeviceFeatures {
+            prefers_color_scheme: ColorSchemePreference::NoPreference,
+            prefers_reduced_motion: false,
+            primary_pointer: PointerCapabilities::FINE | PointerCapabilities::HOVER,
+            all_pointers: PointerCapabilities::FINE | PointerCapabilities::HOVER,
+            color_bits_per_channel: 8,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A software rasterizer for SVG documents, used for SVG images and for
+//! inline `<svg>` elements.
+//!
+//! This supports the basic shapes, paths, linear and radial gradients,
+//! transforms, nested viewports, `<use>` and simple text. Filters, masks,
+//! clipping paths, patterns and markers are not supported, and CSS style
+//! sheets are ignored in favour of presentation attributes and `style`
+//! attributes.
+
+#![deny(unsafe_code)]
+
+#[macro_use]
+extern crate log;
+
+pub mod parser;
+mod render;
+mod tree;
+
+pub use crate::render::{intrinsic_size, rasterize, Raster};
+pub use crate::tree::{parse, Element, Node, ParseError, MAX_NESTING_DEPTH};
+
+/// Whether the given bytes look like an SVG document, skipping any XML
+/// declaration, comments and doctype before the root element.
+pub fn is_svg(bytes: &[u8]) -> bool {
+    let mut rest = bytes;
+    if rest.starts_with(b"\xEF\xBB\xBF") {
+        rest = &rest[3..];
+    }
+    loop {
+        while rest.first().map_or(false, |b| b.is_ascii_whitespace()) {
+            rest = &rest[1..];
+        }
+        let end_marker: &[u8] = if rest.starts_with(b"<?") {
+            b"?>"
+        } else if rest.starts_with(b"<!--") {
+            b"-->"
+        } else if rest.starts_with(b"<!") {
+            b">"
+        } else {
+            break;
+        };
+        match rest
+            .windows(end_marker.len())
+            .position(|window| window == end_marker)
+        {
+            Some(position) => rest = &rest[position + end_marker.len()..],
+            None => return false,
+        }
+    }
+    rest.starts_with(b"<svg") &&
+        rest.get(4).map_or(false, |&b| {
+            b.is_ascii_whitespace() || b == b'>' || b == b'/'
+        })
+}
@@ -0,0 +1,508 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Parsers for the microsyntaxes used by SVG attributes: numbers, lengths,
+//! transform lists, path data and paints.
+
+use cssparser::{Color, Parser, ParserInput, RGBA};
+use euclid::default::Transform2D;
+use euclid::Angle;
+
+/// A cursor over an attribute value, following the SVG number grammar.
+struct Stream<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Stream<'a> {
+    fn new(string: &'a str) -> Stream<'a> {
+        Stream {
+            bytes: string.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).cloned()
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().map_or(false, |b| b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_comma_spaces(&mut self) {
+        self.skip_spaces();
+        if self.peek() == Some(b',') {
+            self.pos += 1;
+            self.skip_spaces();
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+        while self.peek().map_or(false, |b| b.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_spaces();
+        let start = self.pos;
+        if !self.eat(b'+') {
+            self.eat(b'-');
+        }
+        let mut digits = self.skip_digits();
+        if self.eat(b'.') {
+            digits += self.skip_digits();
+        }
+        if digits == 0 {
+            self.pos = start;
+            return None;
+        }
+        // Only treat `e` as an exponent if digits follow, so that `1em` parses
+        // as a number and a unit.
+        if let Some(b'e') | Some(b'E') = self.peek() {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if !self.eat(b'+') {
+                self.eat(b'-');
+            }
+            if self.skip_digits() == 0 {
+                self.pos = mantissa_end;
+            }
+        }
+        let string = std::str::from_utf8(&self.bytes[start..self.pos]).ok()?;
+        string.parse().ok()
+    }
+
+    /// A path data flag, which may be written without separators.
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_spaces();
+        let flag = match self.peek()? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        self.skip_comma_spaces();
+        Some(flag)
+    }
+
+    fn ident(&mut self) -> &'a str {
+        let start = self.pos;
+        while self
+            .peek()
+            .map_or(false, |b| b.is_ascii_alphabetic() || b == b'%')
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or("")
+    }
+}
+
+/// Parses a list of numbers separated by commas and/or whitespace.
+pub fn parse_number_list(string: &str) -> Vec<f32> {
+    let mut stream = Stream::new(string);
+    let mut numbers = vec![];
+    while let Some(number) = stream.number() {
+        numbers.push(number);
+        stream.skip_comma_spaces();
+    }
+    numbers
+}
+
+pub fn parse_number(string: &str) -> Option<f32> {
+    let mut stream = Stream::new(string);
+    let number = stream.number()?;
+    stream.skip_spaces();
+    if stream.at_end() {
+        Some(number)
+    } else {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LengthUnit {
+    None,
+    Px,
+    Em,
+    Ex,
+    In,
+    Cm,
+    Mm,
+    Pt,
+    Pc,
+    Percent,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Length {
+    pub value: f32,
+    pub unit: LengthUnit,
+}
+
+impl Length {
+    pub fn new(value: f32, unit: LengthUnit) -> Length {
+        Length { value, unit }
+    }
+
+    /// Resolves this length to user units, given the font size and the length
+    /// that percentages refer to.
+    pub fn resolve(&self, font_size: f32, percentage_basis: f32) -> f32 {
+        match self.unit {
+            LengthUnit::None | LengthUnit::Px => self.value,
+            LengthUnit::Em => self.value * font_size,
+            LengthUnit::Ex => self.value * font_size / 2.,
+            LengthUnit::In => self.value * 96.,
+            LengthUnit::Cm => self.value * 96. / 2.54,
+            LengthUnit::Mm => self.value * 96. / 25.4,
+            LengthUnit::Pt => self.value * 4. / 3.,
+            LengthUnit::Pc => self.value * 16.,
+            LengthUnit::Percent => self.value * percentage_basis / 100.,
+        }
+    }
+}
+
+fn length(stream: &mut Stream) -> Option<Length> {
+    let value = stream.number()?;
+    let unit = match &*stream.ident().to_ascii_lowercase() {
+        "" => LengthUnit::None,
+        "px" => LengthUnit::Px,
+        "em" => LengthUnit::Em,
+        "ex" => LengthUnit::Ex,
+        "in" => LengthUnit::In,
+        "cm" => LengthUnit::Cm,
+        "mm" => LengthUnit::Mm,
+        "pt" => LengthUnit::Pt,
+        "pc" => LengthUnit::Pc,
+        "%" => LengthUnit::Percent,
+        _ => return None,
+    };
+    Some(Length::new(value, unit))
+}
+
+pub fn parse_length(string: &str) -> Option<Length> {
+    let mut stream = Stream::new(string);
+    let length = length(&mut stream)?;
+    stream.skip_spaces();
+    if stream.at_end() {
+        Some(length)
+    } else {
+        None
+    }
+}
+
+/// Parses a list of lengths, as used by `stroke-dasharray`.
+pub fn parse_length_list(string: &str) -> Option<Vec<Length>> {
+    let mut stream = Stream::new(string);
+    let mut lengths = vec![];
+    loop {
+        stream.skip_spaces();
+        if stream.at_end() {
+            return Some(lengths);
+        }
+        lengths.push(length(&mut stream)?);
+        stream.skip_comma_spaces();
+    }
+}
+
+/// Parses a `transform` attribute. Returns `None` if the list is malformed,
+/// in which case the attribute must be ignored.
+pub fn parse_transform(string: &str) -> Option<Transform2D<f32>> {
+    let mut stream = Stream::new(string);
+    let mut transform = Transform2D::identity();
+    loop {
+        stream.skip_comma_spaces();
+        if stream.at_end() {
+            return Some(transform);
+        }
+        let name = stream.ident();
+        stream.skip_spaces();
+        if !stream.eat(b'(') {
+            return None;
+        }
+        let mut args = vec![];
+        while let Some(number) = stream.number() {
+            args.push(number);
+            stream.skip_comma_spaces();
+        }
+        if !stream.eat(b')') {
+            return None;
+        }
+        let next = match (name, &*args) {
+            ("matrix", &[a, b, c, d, e, f]) => Transform2D::row_major(a, b, c, d, e, f),
+            ("translate", &[tx]) => Transform2D::create_translation(tx, 0.),
+            ("translate", &[tx, ty]) => Transform2D::create_translation(tx, ty),
+            ("scale", &[s]) => Transform2D::create_scale(s, s),
+            ("scale", &[sx, sy]) => Transform2D::create_scale(sx, sy),
+            ("rotate", &[angle]) => Transform2D::create_rotation(Angle::degrees(angle)),
+            ("rotate", &[angle, cx, cy]) => Transform2D::create_translation(-cx, -cy)
+                .post_rotate(Angle::degrees(angle))
+                .post_translate(euclid::vec2(cx, cy)),
+            ("skewX", &[angle]) => {
+                Transform2D::row_major(1., 0., angle.to_radians().tan(), 1., 0., 0.)
+            },
+            ("skewY", &[angle]) => {
+                Transform2D::row_major(1., angle.to_radians().tan(), 0., 1., 0., 0.)
+            },
+            _ => return None,
+        };
+        // Each transform in the list applies to the coordinate system
+        // established by the ones after it.
+        transform = next.post_transform(&transform);
+    }
+}
+
+/// A path segment in absolute coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathSegment {
+    MoveTo(f32, f32),
+    LineTo(f32, f32),
+    QuadTo(f32, f32, f32, f32),
+    CubicTo(f32, f32, f32, f32, f32, f32),
+    ArcTo {
+        rx: f32,
+        ry: f32,
+        x_axis_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        x: f32,
+        y: f32,
+    },
+    Close,
+}
+
+/// Parses path data into absolute segments. Following the error handling rules
+/// for path data, everything up to the first error is returned.
+pub fn parse_path_data(string: &str) -> Vec<PathSegment> {
+    let mut stream = Stream::new(string);
+    let mut segments = vec![];
+    let (mut x, mut y) = (0., 0.);
+    let (mut start_x, mut start_y) = (0., 0.);
+    // The last control point of the previous segment if it was a curve, and
+    // the kind of curve, for the smooth curve commands to reflect.
+    let mut last_control: Option<(u8, f32, f32)> = None;
+    let mut command = None;
+
+    loop {
+        stream.skip_spaces();
+        let next = match stream.peek() {
+            None => break,
+            Some(b) if b.is_ascii_alphabetic() => {
+                stream.pos += 1;
+                b
+            },
+            // Repeated arguments reuse the previous command, with an implicit
+            // line after a move.
+            Some(_) => match command {
+                Some(b'M') => b'L',
+                Some(b'm') => b'l',
+                Some(b'Z') | Some(b'z') | None => break,
+                Some(previous) => previous,
+            },
+        };
+        if command.is_none() && next != b'M' && next != b'm' {
+            break;
+        }
+        command = Some(next);
+
+        let relative = next.is_ascii_lowercase();
+        let (ox, oy) = if relative { (x, y) } else { (0., 0.) };
+        macro_rules! numbers {
+            ($($name:ident),+) => {
+                $(
+                    let $name = match stream.number() {
+                        Some(n) => n,
+                        None => return segments,
+                    };
+                    stream.skip_comma_spaces();
+                )+
+            };
+        }
+
+        let mut control = None;
+        match next.to_ascii_uppercase() {
+            b'M' => {
+                numbers!(nx, ny);
+                x = ox + nx;
+                y = oy + ny;
+                start_x = x;
+                start_y = y;
+                segments.push(PathSegment::MoveTo(x, y));
+            },
+            b'L' => {
+                numbers!(nx, ny);
+                x = ox + nx;
+                y = oy + ny;
+                segments.push(PathSegment::LineTo(x, y));
+            },
+            b'H' => {
+                numbers!(nx);
+                x = ox + nx;
+                segments.push(PathSegment::LineTo(x, y));
+            },
+            b'V' => {
+                numbers!(ny);
+                y = oy + ny;
+                segments.push(PathSegment::LineTo(x, y));
+            },
+            b'C' => {
+                numbers!(x1, y1, x2, y2, nx, ny);
+                segments.push(PathSegment::CubicTo(
+                    ox + x1,
+                    oy + y1,
+                    ox + x2,
+                    oy + y2,
+                    ox + nx,
+                    oy + ny,
+                ));
+                control = Some((b'C', ox + x2, oy + y2));
+                x = ox + nx;
+                y = oy + ny;
+            },
+            b'S' => {
+                numbers!(x2, y2, nx, ny);
+                let (x1, y1) = match last_control {
+                    Some((b'C', cx, cy)) => (2. * x - cx, 2. * y - cy),
+                    _ => (x, y),
+                };
+                segments.push(PathSegment::CubicTo(
+                    x1,
+                    y1,
+                    ox + x2,
+                    oy + y2,
+                    ox + nx,
+                    oy + ny,
+                ));
+                control = Some((b'C', ox + x2, oy + y2));
+                x = ox + nx;
+                y = oy + ny;
+            },
+            b'Q' => {
+                numbers!(x1, y1, nx, ny);
+                segments.push(PathSegment::QuadTo(ox + x1, oy + y1, ox + nx, oy + ny));
+                control = Some((b'Q', ox + x1, oy + y1));
+                x = ox + nx;
+                y = oy + ny;
+            },
+            b'T' => {
+                numbers!(nx, ny);
+                let (x1, y1) = match last_control {
+                    Some((b'Q', cx, cy)) => (2. * x - cx, 2. * y - cy),
+                    _ => (x, y),
+                };
+                segments.push(PathSegment::QuadTo(x1, y1, ox + nx, oy + ny));
+                control = Some((b'Q', x1, y1));
+                x = ox + nx;
+                y = oy + ny;
+            },
+            b'A' => {
+                numbers!(rx, ry, x_axis_rotation);
+                let (large_arc, sweep) = match (stream.flag(), stream.flag()) {
+                    (Some(large_arc), Some(sweep)) => (large_arc, sweep),
+                    _ => return segments,
+                };
+                numbers!(nx, ny);
+                x = ox + nx;
+                y = oy + ny;
+                segments.push(PathSegment::ArcTo {
+                    rx,
+                    ry,
+                    x_axis_rotation,
+                    large_arc,
+                    sweep,
+                    x,
+                    y,
+                });
+            },
+            b'Z' => {
+                x = start_x;
+                y = start_y;
+                segments.push(PathSegment::Close);
+            },
+            _ => break,
+        }
+
+        last_control = control;
+    }
+    segments
+}
+
+/// How a shape is filled or stroked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Paint {
+    None,
+    Color(RGBA),
+    CurrentColor,
+    /// A reference to a paint server, with a fallback for when it's missing.
+    Url(String, Option<Box<Paint>>),
+}
+
+pub fn parse_color(string: &str) -> Option<Color> {
+    let mut input = ParserInput::new(string);
+    let mut parser = Parser::new(&mut input);
+    parser.parse_entirely(Color::parse).ok()
+}
+
+pub fn parse_paint(string: &str) -> Option<Paint> {
+    let string = string.trim();
+    if string == "none" {
+        return Some(Paint::None);
+    }
+    if string.starts_with("url(") {
+        let end = string.find(')')?;
+        let reference = string[4..end]
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'');
+        let fallback = string[end + 1..].trim();
+        let fallback = if fallback.is_empty() {
+            None
+        } else {
+            Some(Box::new(parse_paint(fallback)?))
+        };
+        return Some(Paint::Url(
+            reference.trim_start_matches('#').to_owned(),
+            fallback,
+        ));
+    }
+    Some(match parse_color(string)? {
+        Color::RGBA(rgba) => Paint::Color(rgba),
+        Color::CurrentColor => Paint::CurrentColor,
+    })
+}
+
+/// Splits a `style` attribute into its declarations.
+pub fn parse_style_declarations(string: &str) -> Vec<(String, String)> {
+    string
+        .split(';')
+        .filter_map(|declaration| {
+            let colon = declaration.find(':')?;
+            let name = declaration[..colon].trim().to_ascii_lowercase();
+            let value = declaration[colon + 1..].trim();
+            let value = value.trim_end_matches("!important").trim();
+            if name.is_empty() || value.is_empty() {
+                None
+            } else {
+                Some((name, value.to_owned()))
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,1381 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! Rasterization of SVG element trees with raqote.
+
+use crate::parser::{self, Length, LengthUnit, Paint, PathSegment};
+use crate::tree::{Element, Node, MAX_NESTING_DEPTH};
+use cssparser::{Color, RGBA};
+use euclid::default::{Point2D, Rect, Size2D, Transform2D};
+use font_kit::family_name::FamilyName;
+use font_kit::loaders::default::Font;
+use font_kit::properties::{Properties, Style as FontStyle, Weight};
+use font_kit::source::SystemSource;
+use raqote::{DrawOptions, DrawTarget, GradientStop, LineCap, LineJoin, Path, PathBuilder};
+use raqote::{SolidSource, Source, Spread, StrokeStyle, Winding};
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+/// The size of an SVG image that doesn't specify its own, from the CSS
+/// default object size.
+const DEFAULT_WIDTH: f32 = 300.;
+const DEFAULT_HEIGHT: f32 = 150.;
+
+/// The largest raster we are willing to allocate, in device pixels per side.
+const MAX_RASTER_SIZE: f32 = 8192.;
+
+/// How deeply `<use>` elements and gradient `href`s may reference each other,
+/// which also guards against reference cycles.
+const MAX_REFERENCE_DEPTH: usize = 16;
+
+/// How many elements a document may render in total, since `<use>` elements
+/// can multiply a small document into a huge number of draws.
+const MAX_RENDERED_ELEMENTS: usize = 100_000;
+
+/// A rasterized SVG image, as premultiplied BGRA pixels.
+pub struct Raster {
+    pub width: u32,
+    pub height: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// The size of the given `<svg>` element when used as an image, in CSS pixels.
+pub fn intrinsic_size(root: &Element) -> Size2D<f32> {
+    let length = |name| {
+        root.attribute(name)
+            .and_then(parser::parse_length)
+            .filter(|length| length.unit != LengthUnit::Percent && length.value >= 0.)
+            .map(|length| length.resolve(16., 0.))
+    };
+    let ratio = view_box(root)
+        .filter(|view_box| view_box.size.height > 0.)
+        .map(|view_box| view_box.size.width / view_box.size.height);
+    match (length("width"), length("height"), ratio) {
+        (Some(width), Some(height), _) => Size2D::new(width, height),
+        (Some(width), None, Some(ratio)) => Size2D::new(width, width / ratio),
+        (None, Some(height), Some(ratio)) => Size2D::new(height * ratio, height),
+        (Some(width), None, None) => Size2D::new(width, DEFAULT_HEIGHT),
+        (None, Some(height), None) => Size2D::new(DEFAULT_WIDTH, height),
+        (None, None, _) => Size2D::new(DEFAULT_WIDTH, DEFAULT_HEIGHT),
+    }
+}
+
+/// Rasterizes the given `<svg>` element into a viewport of `size` CSS pixels,
+/// at `device_pixel_ratio` device pixels per CSS pixel. `current_color` is the
+/// value of `currentColor` at the root.
+pub fn rasterize(
+    root: &Element,
+    size: Size2D<f32>,
+    device_pixel_ratio: f32,
+    current_color: RGBA,
+) -> Raster {
+    let width = (size.width * device_pixel_ratio)
+        .ceil()
+        .min(MAX_RASTER_SIZE)
+        .max(0.);
+    let height = (size.height * device_pixel_ratio)
+        .ceil()
+        .min(MAX_RASTER_SIZE)
+        .max(0.);
+    if width < 1. || height < 1. {
+        return Raster {
+            width: 0,
+            height: 0,
+            bytes: vec![],
+        };
+    }
+
+    let mut renderer = Renderer {
+        target: DrawTarget::new(width as i32, height as i32),
+        ids: HashMap::new(),
+        viewports: vec![size],
+        fonts: HashMap::new(),
+        depth: 0,
+        nesting: 0,
+        rendered: 0,
+    };
+    renderer.collect_ids(root);
+
+    let mut style = Style::initial();
+    style.color = current_color;
+    let transform =
+        view_box_transform(root, size).post_scale(device_pixel_ratio, device_pixel_ratio);
+    renderer.render_children(root, &style, &transform);
+
+    let mut bytes = Vec::with_capacity(width as usize * height as usize * 4);
+    for pixel in renderer.target.get_data() {
+        bytes.extend_from_slice(&[
+            *pixel as u8,
+            (*pixel >> 8) as u8,
+            (*pixel >> 16) as u8,
+            (*pixel >> 24) as u8,
+        ]);
+    }
+    Raster {
+        width: width as u32,
+        height: height as u32,
+        bytes,
+    }
+}
+
+fn view_box(element: &Element) -> Option<Rect<f32>> {
+    match *parser::parse_number_list(element.attribute("viewBox")?) {
+        [x, y, width, height] if width > 0. && height > 0. => {
+            Some(Rect::new(Point2D::new(x, y), Size2D::new(width, height)))
+        },
+        _ => None,
+    }
+}
+
+/// The transform from the `viewBox` of an element to a viewport of the given
+/// size, honoring `preserveAspectRatio`.
+fn view_box_transform(element: &Element, size: Size2D<f32>) -> Transform2D<f32> {
+    let view_box = match view_box(element) {
+        Some(view_box) => view_box,
+        None => return Transform2D::identity(),
+    };
+    let mut scale_x = size.width / view_box.size.width;
+    let mut scale_y = size.height / view_box.size.height;
+    let (mut offset_x, mut offset_y) = (0., 0.);
+
+    let mut aspect = element
+        .attribute("preserveAspectRatio")
+        .unwrap_or("")
+        .split_whitespace();
+    let align = aspect.next().unwrap_or("xMidYMid");
+    if align != "none" {
+        let scale = if aspect.next() == Some("slice") {
+            scale_x.max(scale_y)
+        } else {
+            scale_x.min(scale_y)
+        };
+        scale_x = scale;
+        scale_y = scale;
+        let extra_width = size.width - view_box.size.width * scale;
+        let extra_height = size.height - view_box.size.height * scale;
+        if align.starts_with("xMid") {
+            offset_x = extra_width / 2.;
+        } else if align.starts_with("xMax") {
+            offset_x = extra_width;
+        }
+        if align.ends_with("YMid") {
+            offset_y = extra_height / 2.;
+        } else if align.ends_with("YMax") {
+            offset_y = extra_height;
+        }
+    }
+    Transform2D::create_translation(-view_box.origin.x, -view_box.origin.y)
+        .post_scale(scale_x, scale_y)
+        .post_translate(euclid::vec2(offset_x, offset_y))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TextAnchor {
+    Start,
+    Middle,
+    End,
+}
+
+/// The inherited properties of an element.
+#[derive(Clone)]
+struct Style {
+    color: RGBA,
+    fill: Paint,
+    fill_opacity: f32,
+    fill_rule: Winding,
+    stroke: Paint,
+    stroke_opacity: f32,
+    stroke_width: Length,
+    stroke_linecap: LineCap,
+    stroke_linejoin: LineJoin,
+    stroke_miterlimit: f32,
+    stroke_dasharray: Vec<Length>,
+    stroke_dashoffset: Length,
+    font_family: String,
+    font_size: f32,
+    font_weight: Weight,
+    font_style: FontStyle,
+    text_anchor: TextAnchor,
+    visible: bool,
+}
+
+impl Style {
+    fn initial() -> Style {
+        Style {
+            color: RGBA::new(0, 0, 0, 255),
+            fill: Paint::Color(RGBA::new(0, 0, 0, 255)),
+            fill_opacity: 1.,
+            fill_rule: Winding::NonZero,
+            stroke: Paint::None,
+            stroke_opacity: 1.,
+            stroke_width: Length::new(1., LengthUnit::None),
+            stroke_linecap: LineCap::Butt,
+            stroke_linejoin: LineJoin::Miter,
+            stroke_miterlimit: 4.,
+            stroke_dasharray: vec![],
+            stroke_dashoffset: Length::new(0., LengthUnit::None),
+            font_family: "serif".to_owned(),
+            font_size: 16.,
+            font_weight: Weight::NORMAL,
+            font_style: FontStyle::Normal,
+            text_anchor: TextAnchor::Start,
+            visible: true,
+        }
+    }
+
+    /// Applies an inherited property, from either a presentation attribute or
+    /// a `style` declaration. Invalid values are ignored.
+    fn apply(&mut self, name: &str, value: &str) {
+        match name {
+            "color" => {
+                if let Some(Color::RGBA(rgba)) = parser::parse_color(value) {
+                    self.color = rgba;
+                }
+            },
+            "fill" => {
+                if let Some(paint) = parser::parse_paint(value) {
+                    self.fill = paint;
+                }
+            },
+            "stroke" => {
+                if let Some(paint) = parser::parse_paint(value) {
+                    self.stroke = paint;
+                }
+            },
+            "fill-opacity" => {
+                if let Some(opacity) = parse_opacity(value) {
+                    self.fill_opacity = opacity;
+                }
+            },
+            "stroke-opacity" => {
+                if let Some(opacity) = parse_opacity(value) {
+                    self.stroke_opacity = opacity;
+                }
+            },
+            "fill-rule" => match value {
+                "nonzero" => self.fill_rule = Winding::NonZero,
+                "evenodd" => self.fill_rule = Winding::EvenOdd,
+                _ => {},
+            },
+            "stroke-width" => {
+                if let Some(width) = parser::parse_length(value).filter(|l| l.value >= 0.) {
+                    self.stroke_width = width;
+                }
+            },
+            "stroke-linecap" => match value {
+                "butt" => self.stroke_linecap = LineCap::Butt,
+                "round" => self.stroke_linecap = LineCap::Round,
+                "square" => self.stroke_linecap = LineCap::Square,
+                _ => {},
+            },
+            "stroke-linejoin" => match value {
+                "miter" => self.stroke_linejoin = LineJoin::Miter,
+                "round" => self.stroke_linejoin = LineJoin::Round,
+                "bevel" => self.stroke_linejoin = LineJoin::Bevel,
+                _ => {},
+            },
+            "stroke-miterlimit" => {
+                if let Some(limit) = parser::parse_number(value).filter(|&l| l >= 1.) {
+                    self.stroke_miterlimit = limit;
+                }
+            },
+            "stroke-dasharray" => {
+                if value == "none" {
+                    self.stroke_dasharray = vec![];
+                } else if let Some(lengths) = parser::parse_length_list(value) {
+                    if lengths.iter().all(|length| length.value >= 0.) {
+                        self.stroke_dasharray = lengths;
+                    }
+                }
+            },
+            "stroke-dashoffset" => {
+                if let Some(offset) = parser::parse_length(value) {
+                    self.stroke_dashoffset = offset;
+                }
+            },
+            "font-family" => self.font_family = value.to_owned(),
+            "font-size" => {
+                if let Some(size) = parser::parse_length(value).filter(|l| l.value >= 0.) {
+                    self.font_size = size.resolve(self.font_size, self.font_size);
+                }
+            },
+            "font-weight" => {
+                self.font_weight = match value {
+                    "normal" => Weight::NORMAL,
+                    "bold" => Weight::BOLD,
+                    "bolder" => Weight((self.font_weight.0 + 300.).min(900.)),
+                    "lighter" => Weight((self.font_weight.0 - 300.).max(100.)),
+                    _ => match parser::parse_number(value) {
+                        Some(weight) if weight >= 1. && weight <= 1000. => Weight(weight),
+                        _ => return,
+                    },
+                }
+            },
+            "font-style" => match value {
+                "normal" => self.font_style = FontStyle::Normal,
+                "italic" => self.font_style = FontStyle::Italic,
+                "oblique" => self.font_style = FontStyle::Oblique,
+                _ => {},
+            },
+            "text-anchor" => match value {
+                "start" => self.text_anchor = TextAnchor::Start,
+                "middle" => self.text_anchor = TextAnchor::Middle,
+                "end" => self.text_anchor = TextAnchor::End,
+                _ => {},
+            },
+            "visibility" => match value {
+                "visible" => self.visible = true,
+                "hidden" | "collapse" => self.visible = false,
+                _ => {},
+            },
+            _ => {},
+        }
+    }
+
+    fn font_properties(&self) -> Properties {
+        let mut properties = Properties::new();
+        properties.weight(self.font_weight).style(self.font_style);
+        properties
+    }
+}
+
+fn parse_opacity(value: &str) -> Option<f32> {
+    let opacity = match parser::parse_length(value)? {
+        Length {
+            value,
+            unit: LengthUnit::None,
+        } => value,
+        Length {
+            value,
+            unit: LengthUnit::Percent,
+        } => value / 100.,
+        _ => return None,
+    };
+    Some(opacity.max(0.).min(1.))
+}
+
+/// The presentation attributes of an element followed by its `style`
+/// declarations, which take precedence.
+fn declarations(element: &Element) -> Vec<(String, String)> {
+    let mut declarations: Vec<(String, String)> = element
+        .attributes
+        .iter()
+        .filter(|&&(ref name, _)| name != "style")
+        .map(|&(ref name, ref value)| (name.clone(), value.trim().to_owned()))
+        .collect();
+    if let Some(style) = element.attribute("style") {
+        declarations.extend(parser::parse_style_declarations(style));
+    }
+    declarations
+}
+
+fn solid_source(color: RGBA, opacity: f32) -> SolidSource {
+    let alpha = (color.alpha as f32 * opacity).round() as u32;
+    let premultiply = |component: u8| (component as u32 * alpha / 255) as u8;
+    SolidSource {
+        r: premultiply(color.red),
+        g: premultiply(color.green),
+        b: premultiply(color.blue),
+        a: alpha as u8,
+    }
+}
+
+enum GradientShape {
+    Linear(Point2D<f32>, Point2D<f32>),
+    Radial {
+        center: Point2D<f32>,
+        radius: f32,
+        focus: Point2D<f32>,
+    },
+}
+
+/// A gradient paint server, defined in its own coordinate space.
+struct Gradient {
+    stops: Vec<GradientStop>,
+    shape: GradientShape,
+    spread: Spread,
+    /// The transform from the gradient's coordinate space to user space.
+    transform: Transform2D<f32>,
+}
+
+impl Gradient {
+    /// A source painting this gradient in its own coordinate space.
+    fn source(&self) -> Source<'static> {
+        self.source_with(&Transform2D::identity())
+    }
+
+    /// A source painting this gradient in user space, approximating any
+    /// non-uniform scale or skew of the gradient's coordinate space.
+    fn user_space_source(&self) -> Source<'static> {
+        self.source_with(&self.transform)
+    }
+
+    fn source_with(&self, transform: &Transform2D<f32>) -> Source<'static> {
+        let gradient = raqote::Gradient {
+            stops: self.stops.clone(),
+        };
+        match self.shape {
+            GradientShape::Linear(start, end) => Source::new_linear_gradient(
+                gradient,
+                transform.transform_point(start),
+                transform.transform_point(end),
+                self.spread,
+            ),
+            GradientShape::Radial {
+                center,
+                radius,
+                focus,
+            } => {
+                let scale = transform.determinant().abs().sqrt();
+                Source::new_two_circle_radial_gradient(
+                    gradient,
+                    transform.transform_point(focus),
+                    0.,
+                    transform.transform_point(center),
+                    radius * scale,
+                    self.spread,
+                )
+            },
+        }
+    }
+}
+
+enum ResolvedPaint {
+    Solid(SolidSource),
+    Gradient(Gradient),
+}
+
+/// A path being built in user space, tracking its bounding box for
+/// `objectBoundingBox` units.
+struct Outline {
+    builder: PathBuilder,
+    current: Point2D<f32>,
+    start: Point2D<f32>,
+    min: Point2D<f32>,
+    max: Point2D<f32>,
+    empty: bool,
+}
+
+impl Outline {
+    fn new() -> Outline {
+        Outline {
+            builder: PathBuilder::new(),
+            current: Point2D::zero(),
+            start: Point2D::zero(),
+            min: Point2D::zero(),
+            max: Point2D::zero(),
+            empty: true,
+        }
+    }
+
+    fn include(&mut self, x: f32, y: f32) {
+        if self.empty {
+            self.min = Point2D::new(x, y);
+            self.max = Point2D::new(x, y);
+            self.empty = false;
+        } else {
+            self.min = Point2D::new(self.min.x.min(x), self.min.y.min(y));
+            self.max = Point2D::new(self.max.x.max(x), self.max.y.max(y));
+        }
+    }
+
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.builder.move_to(x, y);
+        self.include(x, y);
+        self.current = Point2D::new(x, y);
+        self.start = self.current;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.builder.line_to(x, y);
+        self.include(x, y);
+        self.current = Point2D::new(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        self.builder.quad_to(x1, y1, x, y);
+        self.include(x1, y1);
+        self.include(x, y);
+        self.current = Point2D::new(x, y);
+    }
+
+    fn cubic_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        self.builder.cubic_to(x1, y1, x2, y2, x, y);
+        self.include(x1, y1);
+        self.include(x2, y2);
+        self.include(x, y);
+        self.current = Point2D::new(x, y);
+    }
+
+    fn close(&mut self) {
+        self.builder.close();
+        self.current = self.start;
+    }
+
+    /// Appends part of an ellipse with the given center and radii, rotated by
+    /// `rotation` radians, as cubic Béziers of at most a quarter turn each.
+    fn elliptical_arc(
+        &mut self,
+        center: Point2D<f32>,
+        rx: f32,
+        ry: f32,
+        rotation: f32,
+        start_angle: f32,
+        sweep_angle: f32,
+    ) {
+        let (sin_phi, cos_phi) = rotation.sin_cos();
+        let point = |x: f32, y: f32| {
+            (
+                center.x + cos_phi * rx * x - sin_phi * ry * y,
+                center.y + sin_phi * rx * x + cos_phi * ry * y,
+            )
+        };
+        let segments = (sweep_angle.abs() / (PI / 2.)).ceil().max(1.);
+        let delta = sweep_angle / segments;
+        let handle = 4. / 3. * (delta / 4.).tan();
+        let mut angle = start_angle;
+        for _ in 0..segments as usize {
+            let (sin1, cos1) = angle.sin_cos();
+            let (sin2, cos2) = (angle + delta).sin_cos();
+            let (x1, y1) = point(cos1 - handle * sin1, sin1 + handle * cos1);
+            let (x2, y2) = point(cos2 + handle * sin2, sin2 - handle * cos2);
+            let (x, y) = point(cos2, sin2);
+            self.cubic_to(x1, y1, x2, y2, x, y);
+            angle += delta;
+        }
+    }
+
+    /// Appends an SVG arc, converting from endpoint to center parameterization
+    /// as described in SVG 1.1 appendix F.6.
+    fn arc_to(
+        &mut self,
+        mut rx: f32,
+        mut ry: f32,
+        x_axis_rotation: f32,
+        large_arc: bool,
+        sweep: bool,
+        x: f32,
+        y: f32,
+    ) {
+        let from = self.current;
+        if from == Point2D::new(x, y) {
+            return;
+        }
+        rx = rx.abs();
+        ry = ry.abs();
+        if rx == 0. || ry == 0. {
+            return self.line_to(x, y);
+        }
+
+        let phi = x_axis_rotation.to_radians();
+        let (sin_phi, cos_phi) = phi.sin_cos();
+        let dx = (from.x - x) / 2.;
+        let dy = (from.y - y) / 2.;
+        let x1 = cos_phi * dx + sin_phi * dy;
+        let y1 = -sin_phi * dx + cos_phi * dy;
+
+        // Scale up radii that are too small to span the endpoints.
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if lambda > 1. {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut coefficient = (numerator / denominator).max(0.).sqrt();
+        if large_arc == sweep {
+            coefficient = -coefficient;
+        }
+        let cx1 = coefficient * rx * y1 / ry;
+        let cy1 = -coefficient * ry * x1 / rx;
+        let center = Point2D::new(
+            cos_phi * cx1 - sin_phi * cy1 + (from.x + x) / 2.,
+            sin_phi * cx1 + cos_phi * cy1 + (from.y + y) / 2.,
+        );
+
+        let angle = |ux: f32, uy: f32, vx: f32, vy: f32| {
+            let sign = if ux * vy - uy * vx < 0. { -1. } else { 1. };
+            let dot = ux * vx + uy * vy;
+            let length = (ux * ux + uy * uy).sqrt() * (vx * vx + vy * vy).sqrt();
+            sign * (dot / length).max(-1.).min(1.).acos()
+        };
+        let start_angle = angle(1., 0., (x1 - cx1) / rx, (y1 - cy1) / ry);
+        let mut sweep_angle = angle(
+            (x1 - cx1) / rx,
+            (y1 - cy1) / ry,
+            (-x1 - cx1) / rx,
+            (-y1 - cy1) / ry,
+        );
+        if !sweep && sweep_angle > 0. {
+            sweep_angle -= 2. * PI;
+        } else if sweep && sweep_angle < 0. {
+            sweep_angle += 2. * PI;
+        }
+
+        self.elliptical_arc(center, rx, ry, phi, start_angle, sweep_angle);
+        // Avoid accumulating rounding errors into the following segments.
+        self.current = Point2D::new(x, y);
+    }
+
+    fn ellipse(&mut self, cx: f32, cy: f32, rx: f32, ry: f32) {
+        self.move_to(cx + rx, cy);
+        self.elliptical_arc(Point2D::new(cx, cy), rx, ry, 0., 0., 2. * PI);
+        self.close();
+    }
+
+    fn bounds(&self) -> Rect<f32> {
+        Rect::new(self.min, (self.max - self.min).to_size())
+    }
+
+    fn finish(self, winding: Winding) -> (Path, Rect<f32>) {
+        let bounds = self.bounds();
+        let mut path = self.builder.finish();
+        path.winding = winding;
+        (path, bounds)
+    }
+}
+
+struct Renderer<'a> {
+    target: DrawTarget,
+    /// The elements of the document by `id`, for resolving references.
+    ids: HashMap<&'a str, &'a Element>,
+    /// The sizes of the enclosing viewports, which percentages refer to.
+    viewports: Vec<Size2D<f32>>,
+    fonts: HashMap<(String, u32, bool), Option<Font>>,
+    /// The current depth of `<use>` references.
+    depth: usize,
+    /// The current depth of rendered elements, including those reached
+    /// through `<use>` references.
+    nesting: usize,
+    /// The number of elements rendered so far.
+    rendered: usize,
+}
+
+impl<'a> Renderer<'a> {
+    fn collect_ids(&mut self, element: &'a Element) {
+        if let Some(id) = element.attribute("id") {
+            self.ids.entry(id).or_insert(element);
+        }
+        for child in element.elements() {
+            self.collect_ids(child);
+        }
+    }
+
+    fn viewport(&self) -> Size2D<f32> {
+        *self.viewports.last().unwrap()
+    }
+
+    /// Resolves a length attribute of an element to user units, with
+    /// percentages of the viewport width, height or diagonal.
+    fn length(&self, element: &Element, name: &str, style: &Style, default: f32) -> f32 {
+        let viewport = self.viewport();
+        let basis = match name {
+            "x" | "cx" | "dx" | "x1" | "x2" | "width" | "rx" => viewport.width,
+            "y" | "cy" | "dy" | "y1" | "y2" | "height" | "ry" => viewport.height,
+            _ => ((viewport.width.powi(2) + viewport.height.powi(2)) / 2.).sqrt(),
+        };
+        element
+            .attribute(name)
+            .and_then(|value| {
+                // Text positions can be lists; only the first value is used.
+                parser::parse_length(value.split_whitespace().next().unwrap_or(""))
+            })
+            .map_or(default, |length| length.resolve(style.font_size, basis))
+    }
+
+    fn diagonal_length(&self, length: &Length, style: &Style) -> f32 {
+        let viewport = self.viewport();
+        let basis = ((viewport.width.powi(2) + viewport.height.powi(2)) / 2.).sqrt();
+        length.resolve(style.font_size, basis)
+    }
+
+    fn render_children(&mut self, element: &Element, style: &Style, transform: &Transform2D<f32>) {
+        for child in element.elements() {
+            self.render_element(child, style, transform);
+        }
+    }
+
+    fn render_element(
+        &mut self,
+        element: &Element,
+        parent_style: &Style,
+        transform: &Transform2D<f32>,
+    ) {
+        if self.rendered == MAX_RENDERED_ELEMENTS {
+            warn!("Too many SVG elements to render");
+            self.rendered += 1;
+        }
+        if self.rendered > MAX_RENDERED_ELEMENTS {
+            return;
+        }
+        if self.nesting >= MAX_NESTING_DEPTH {
+            warn!("SVG elements are nested too deeply");
+            return;
+        }
+        self.rendered += 1;
+        self.nesting += 1;
+        self.render_element_contents(element, parent_style, transform);
+        self.nesting -= 1;
+    }
+
+    fn render_element_contents(
+        &mut self,
+        element: &Element,
+        parent_style: &Style,
+        transform: &Transform2D<f32>,
+    ) {
+        let mut style = parent_style.clone();
+        let mut opacity = 1.;
+        for (name, value) in declarations(element) {
+            match &*name {
+                "display" if value == "none" => return,
+                "opacity" => opacity = parse_opacity(&value).unwrap_or(opacity),
+                _ => style.apply(&name, &value),
+            }
+        }
+        let transform = match element
+            .attribute("transform")
+            .and_then(parser::parse_transform)
+        {
+            Some(element_transform) => element_transform.post_transform(transform),
+            None => *transform,
+        };
+
+        let renders = match &*element.name {
+            "svg" | "g" | "a" | "switch" | "use" | "rect" | "circle" | "ellipse" | "line" |
+            "polyline" | "polygon" | "path" | "text" => true,
+            _ => false,
+        };
+        if !renders || opacity == 0. {
+            return;
+        }
+        if opacity < 1. {
+            self.target.push_layer(opacity);
+        }
+        match &*element.name {
+            "svg" => self.render_nested_svg(element, &style, &transform),
+            "g" | "a" => self.render_children(element, &style, &transform),
+            "switch" => {
+                // Conditional processing attributes aren't supported, so the
+                // first child is always chosen.
+                if let Some(child) = element.elements().next() {
+                    self.render_element(child, &style, &transform);
+                }
+            },
+            "use" => self.render_use(element, &style, &transform),
+            "text" => self.render_text(element, &style, &transform),
+            _ => {
+                if let Some(outline) = self.shape_outline(element, &style) {
+                    self.draw_shape(outline, &style, &transform);
+                }
+            },
+        }
+        if opacity < 1. {
+            self.target.pop_layer();
+        }
+    }
+
+    fn render_nested_svg(
+        &mut self,
+        element: &Element,
+        style: &Style,
+        transform: &Transform2D<f32>,
+    ) {
+        let x = self.length(element, "x", style, 0.);
+        let y = self.length(element, "y", style, 0.);
+        let viewport = self.viewport();
+        let width = self.length(element, "width", style, viewport.width);
+        let height = self.length(element, "height", style, viewport.height);
+        self.render_viewport(element, element, style, transform, x, y, width, height);
+    }
+
+    /// Renders the children of `content` in a new viewport, mapping the
+    /// `viewBox` of `viewport_element` onto it.
+    fn render_viewport(
+        &mut self,
+        viewport_element: &Element,
+        content: &Element,
+        style: &Style,
+        transform: &Transform2D<f32>,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) {
+        if width <= 0. || height <= 0. {
+            return;
+        }
+        let size = Size2D::new(width, height);
+        let transform = view_box_transform(viewport_element, size)
+            .post_translate(euclid::vec2(x, y))
+            .post_transform(transform);
+        self.viewports
+            .push(view_box(viewport_element).map_or(size, |view_box| view_box.size));
+        self.render_children(content, style, &transform);
+        self.viewports.pop();
+    }
+
+    fn render_use(&mut self, element: &Element, style: &Style, transform: &Transform2D<f32>) {
+        let referenced = match element
+            .attribute("href")
+            .and_then(|href| self.ids.get(href.trim_start_matches('#')))
+        {
+            Some(referenced) => *referenced,
+            None => return,
+        };
+        if self.depth >= MAX_REFERENCE_DEPTH {
+            warn!("Too many nested SVG <use> references");
+            return;
+        }
+
+        let x = self.length(element, "x", style, 0.);
+        let y = self.length(element, "y", style, 0.);
+        self.depth += 1;
+        if referenced.name == "symbol" || referenced.name == "svg" {
+            let viewport = self.viewport();
+            let width = self.length(element, "width", style, viewport.width);
+            let height = self.length(element, "height", style, viewport.height);
+            self.render_viewport(
+                referenced, referenced, style, transform, x, y, width, height,
+            );
+        } else {
+            let transform = Transform2D::create_translation(x, y).post_transform(transform);
+            self.render_element(referenced, style, &transform);
+        }
+        self.depth -= 1;
+    }
+
+    fn shape_outline(&self, element: &Element, style: &Style) -> Option<Outline> {
+        let mut outline = Outline::new();
+        match &*element.name {
+            "rect" => {
+                let x = self.length(element, "x", style, 0.);
+                let y = self.length(element, "y", style, 0.);
+                let width = self.length(element, "width", style, 0.);
+                let height = self.length(element, "height", style, 0.);
+                if width <= 0. || height <= 0. {
+                    return None;
+                }
+                let rx = element
+                    .attribute("rx")
+                    .map(|_| self.length(element, "rx", style, 0.));
+                let ry = element
+                    .attribute("ry")
+                    .map(|_| self.length(element, "ry", style, 0.));
+                let (rx, ry) = match (rx, ry) {
+                    (Some(rx), Some(ry)) => (rx, ry),
+                    (Some(r), None) | (None, Some(r)) => (r, r),
+                    (None, None) => (0., 0.),
+                };
+                let rx = rx.max(0.).min(width / 2.);
+                let ry = ry.max(0.).min(height / 2.);
+                if rx == 0. || ry == 0. {
+                    outline.move_to(x, y);
+                    outline.line_to(x + width, y);
+                    outline.line_to(x + width, y + height);
+                    outline.line_to(x, y + height);
+                    outline.close();
+                } else {
+                    let corner = |outline: &mut Outline, cx: f32, cy: f32, start: f32| {
+                        outline.elliptical_arc(Point2D::new(cx, cy), rx, ry, 0., start, PI / 2.)
+                    };
+                    outline.move_to(x + rx, y);
+                    outline.line_to(x + width - rx, y);
+                    corner(&mut outline, x + width - rx, y + ry, -PI / 2.);
+                    outline.line_to(x + width, y + height - ry);
+                    corner(&mut outline, x + width - rx, y + height - ry, 0.);
+                    outline.line_to(x + rx, y + height);
+                    corner(&mut outline, x + rx, y + height - ry, PI / 2.);
+                    outline.line_to(x, y + ry);
+                    corner(&mut outline, x + rx, y + ry, PI);
+                    outline.close();
+                }
+            },
+            "circle" => {
+                let r = self.length(element, "r", style, 0.);
+                if r <= 0. {
+                    return None;
+                }
+                let cx = self.length(element, "cx", style, 0.);
+                let cy = self.length(element, "cy", style, 0.);
+                outline.ellipse(cx, cy, r, r);
+            },
+            "ellipse" => {
+                let rx = self.length(element, "rx", style, 0.);
+                let ry = self.length(element, "ry", style, 0.);
+                if rx <= 0. || ry <= 0. {
+                    return None;
+                }
+                let cx = self.length(element, "cx", style, 0.);
+                let cy = self.length(element, "cy", style, 0.);
+                outline.ellipse(cx, cy, rx, ry);
+            },
+            "line" => {
+                outline.move_to(
+                    self.length(element, "x1", style, 0.),
+                    self.length(element, "y1", style, 0.),
+                );
+                outline.line_to(
+                    self.length(element, "x2", style, 0.),
+                    self.length(element, "y2", style, 0.),
+                );
+            },
+            "polyline" | "polygon" => {
+                let points = parser::parse_number_list(element.attribute("points")?);
+                let mut pairs = points.chunks_exact(2);
+                let first = pairs.next()?;
+                outline.move_to(first[0], first[1]);
+                for point in pairs {
+                    outline.line_to(point[0], point[1]);
+                }
+                if element.name == "polygon" {
+                    outline.close();
+                }
+            },
+            "path" => {
+                for segment in parser::parse_path_data(element.attribute("d")?) {
+                    match segment {
+                        PathSegment::MoveTo(x, y) => outline.move_to(x, y),
+                        PathSegment::LineTo(x, y) => outline.line_to(x, y),
+                        PathSegment::QuadTo(x1, y1, x, y) => outline.quad_to(x1, y1, x, y),
+                        PathSegment::CubicTo(x1, y1, x2, y2, x, y) => {
+                            outline.cubic_to(x1, y1, x2, y2, x, y)
+                        },
+                        PathSegment::ArcTo {
+                            rx,
+                            ry,
+                            x_axis_rotation,
+                            large_arc,
+                            sweep,
+                            x,
+                            y,
+                        } => outline.arc_to(rx, ry, x_axis_rotation, large_arc, sweep, x, y),
+                        PathSegment::Close => outline.close(),
+                    }
+                }
+            },
+            _ => return None,
+        }
+        if outline.empty {
+            None
+        } else {
+            Some(outline)
+        }
+    }
+
+    fn draw_shape(&mut self, outline: Outline, style: &Style, transform: &Transform2D<f32>) {
+        if !style.visible {
+            return;
+        }
+        let (path, bounds) = outline.finish(style.fill_rule);
+        if let Some(paint) = self.resolve_paint(&style.fill, style.fill_opacity, style, &bounds) {
+            self.fill(&path, paint, transform);
+        }
+        let width = self.diagonal_length(&style.stroke_width, style);
+        if width > 0. {
+            if let Some(paint) =
+                self.resolve_paint(&style.stroke, style.stroke_opacity, style, &bounds)
+            {
+                let mut dash_array: Vec<f32> = style
+                    .stroke_dasharray
+                    .iter()
+                    .map(|length| self.diagonal_length(length, style))
+                    .collect();
+                if dash_array.len() % 2 == 1 {
+                    dash_array.extend(dash_array.clone());
+                }
+                if dash_array.iter().all(|&dash| dash == 0.) {
+                    dash_array.clear();
+                }
+                let mut stroke_style = StrokeStyle::default();
+                stroke_style.width = width;
+                stroke_style.cap = style.stroke_linecap;
+                stroke_style.join = style.stroke_linejoin;
+                stroke_style.miter_limit = style.stroke_miterlimit;
+                stroke_style.dash_array = dash_array;
+                stroke_style.dash_offset = self.diagonal_length(&style.stroke_dashoffset, style);
+                let source = match paint {
+                    ResolvedPaint::Solid(color) => Source::Solid(color),
+                    ResolvedPaint::Gradient(gradient) => gradient.user_space_source(),
+                };
+                self.target.set_transform(transform);
+                self.target
+                    .stroke(&path, &source, &stroke_style, &DrawOptions::new());
+            }
+        }
+    }
+
+    fn fill(&mut self, path: &Path, paint: ResolvedPaint, transform: &Transform2D<f32>) {
+        match paint {
+            ResolvedPaint::Solid(color) => {
+                self.target.set_transform(transform);
+                self.target
+                    .fill(path, &Source::Solid(color), &DrawOptions::new());
+            },
+            ResolvedPaint::Gradient(gradient) => {
+                // Paint in the gradient's own coordinate space, so that it can
+                // be skewed or scaled non-uniformly, with the path mapped into
+                // that space.
+                let inverse = match gradient.transform.inverse() {
+                    Some(inverse) => inverse,
+                    None => return,
+                };
+                let path = path.clone().transform(&inverse);
+                self.target
+                    .set_transform(&gradient.transform.post_transform(transform));
+                self.target
+                    .fill(&path, &gradient.source(), &DrawOptions::new());
+            },
+        }
+    }
+
+    /// Resolves a fill or stroke to what should be drawn, if anything, for a
+    /// shape with the given bounding box.
+    fn resolve_paint(
+        &self,
+        paint: &Paint,
+        opacity: f32,
+        style: &Style,
+        bounds: &Rect<f32>,
+    ) -> Option<ResolvedPaint> {
+        match *paint {
+            Paint::None => None,
+            Paint::Color(color) => Some(ResolvedPaint::Solid(solid_source(color, opacity))),
+            Paint::CurrentColor => Some(ResolvedPaint::Solid(solid_source(style.color, opacity))),
+            Paint::Url(ref id, ref fallback) => match self.ids.get(&**id) {
+                Some(element)
+                    if element.name == "linearGradient" || element.name == "radialGradient" =>
+                {
+                    self.gradient(element, opacity, style, bounds)
+                },
+                _ => fallback
+                    .as_ref()
+                    .and_then(|fallback| self.resolve_paint(fallback, opacity, style, bounds)),
+            },
+        }
+    }
+
+    /// Follows the `href` chain of a gradient to find an attribute.
+    fn gradient_attribute<'b>(&'b self, element: &'b Element, name: &str) -> Option<&'b str> {
+        let mut element = element;
+        for _ in 0..MAX_REFERENCE_DEPTH {
+            if let Some(value) = element.attribute(name) {
+                return Some(value);
+            }
+            element = self.gradient_reference(element)?;
+        }
+        None
+    }
+
+    fn gradient_reference<'b>(&'b self, element: &'b Element) -> Option<&'b Element> {
+        let href = element.attribute("href")?;
+        self.ids
+            .get(href.trim_start_matches('#'))
+            .cloned()
+            .filter(|referenced| {
+                referenced.name == "linearGradient" || referenced.name == "radialGradient"
+            })
+    }
+
+    /// The stops of a gradient, as offsets and non-premultiplied colors.
+    fn gradient_stops(&self, element: &Element, opacity: f32, style: &Style) -> Vec<(f32, RGBA)> {
+        let mut element = element;
+        for _ in 0..MAX_REFERENCE_DEPTH {
+            if element.elements().any(|child| child.name == "stop") {
+                break;
+            }
+            element = match self.gradient_reference(element) {
+                Some(referenced) => referenced,
+                None => return vec![],
+            };
+        }
+
+        let mut stops: Vec<(f32, RGBA)> = vec![];
+        for stop in element.elements().filter(|child| child.name == "stop") {
+            let mut color = RGBA::new(0, 0, 0, 255);
+            let mut stop_opacity = 1.;
+            let mut offset = 0.;
+            for (name, value) in declarations(stop) {
+                match &*name {
+                    "offset" => offset = parse_opacity(&value).unwrap_or(0.),
+                    "stop-color" => match parser::parse_color(&value) {
+                        Some(Color::RGBA(rgba)) => color = rgba,
+                        Some(Color::CurrentColor) => color = style.color,
+                        None => {},
+                    },
+                    "stop-opacity" => stop_opacity = parse_opacity(&value).unwrap_or(1.),
+                    _ => {},
+                }
+            }
+            // Offsets must not decrease.
+            if let Some(&(previous, _)) = stops.last() {
+                offset = offset.max(previous);
+            }
+            color.alpha = (color.alpha as f32 * stop_opacity * opacity).round() as u8;
+            stops.push((offset, color));
+        }
+        stops
+    }
+
+    fn gradient(
+        &self,
+        element: &Element,
+        opacity: f32,
+        style: &Style,
+        bounds: &Rect<f32>,
+    ) -> Option<ResolvedPaint> {
+        let stops = self.gradient_stops(element, opacity, style);
+        match stops.len() {
+            0 => return None,
+            1 => return Some(ResolvedPaint::Solid(solid_source(stops[0].1, 1.))),
+            _ => {},
+        }
+
+        let user_space =
+            self.gradient_attribute(element, "gradientUnits") == Some("userSpaceOnUse");
+        let viewport = self.viewport();
+        // Coordinates are fractions of the bounding box, unless they are in
+        // user space where percentages refer to the viewport.
+        let coordinate = |name: &str| -> Option<f32> {
+            let length = parser::parse_length(self.gradient_attribute(element, name)?)?;
+            if !user_space {
+                return Some(length.resolve(style.font_size, 1.));
+            }
+            let basis = match name {
+                "x1" | "x2" | "cx" | "fx" => viewport.width,
+                "y1" | "y2" | "cy" | "fy" => viewport.height,
+                _ => ((viewport.width.powi(2) + viewport.height.powi(2)) / 2.).sqrt(),
+            };
+            Some(length.resolve(style.font_size, basis))
+        };
+        // The default coordinates are percentages.
+        let fraction = |fraction: f32, basis: f32| {
+            if user_space {
+                fraction * basis
+            } else {
+                fraction
+            }
+        };
+        let diagonal = ((viewport.width.powi(2) + viewport.height.powi(2)) / 2.).sqrt();
+
+        let shape = if element.name == "linearGradient" {
+            GradientShape::Linear(
+                Point2D::new(
+                    coordinate("x1").unwrap_or(0.),
+                    coordinate("y1").unwrap_or(0.),
+                ),
+                Point2D::new(
+                    coordinate("x2").unwrap_or_else(|| fraction(1., viewport.width)),
+                    coordinate("y2").unwrap_or(0.),
+                ),
+            )
+        } else {
+            let center = Point2D::new(
+                coordinate("cx").unwrap_or_else(|| fraction(0.5, viewport.width)),
+                coordinate("cy").unwrap_or_else(|| fraction(0.5, viewport.height)),
+            );
+            let radius = coordinate("r").unwrap_or_else(|| fraction(0.5, diagonal));
+            if radius <= 0. {
+                let &(_, color) = stops.last().unwrap();
+                return Some(ResolvedPaint::Solid(solid_source(color, 1.)));
+            }
+            let focus = Point2D::new(
+                coordinate("fx").unwrap_or(center.x),
+                coordinate("fy").unwrap_or(center.y),
+            );
+            GradientShape::Radial {
+                center,
+                radius,
+                focus,
+            }
+        };
+
+        let spread = match self.gradient_attribute(element, "spreadMethod") {
+            Some("reflect") => Spread::Reflect,
+            Some("repeat") => Spread::Repeat,
+            _ => Spread::Pad,
+        };
+
+        let mut transform = self
+            .gradient_attribute(element, "gradientTransform")
+            .and_then(parser::parse_transform)
+            .unwrap_or_else(Transform2D::identity);
+        if !user_space {
+            if bounds.size.width <= 0. || bounds.size.height <= 0. {
+                return None;
+            }
+            transform = transform.post_transform(
+                &Transform2D::create_scale(bounds.size.width, bounds.size.height)
+                    .post_translate(bounds.origin.to_vector()),
+            );
+        }
+
+        let stops = stops
+            .into_iter()
+            .map(|(position, color)| GradientStop {
+                position,
+                color: raqote::Color::new(color.alpha, color.red, color.green, color.blue),
+            })
+            .collect();
+        Some(ResolvedPaint::Gradient(Gradient {
+            stops,
+            shape,
+            spread,
+            transform,
+        }))
+    }
+
+    fn font(&mut self, style: &Style) -> Option<Font> {
+        let key = (
+            style.font_family.clone(),
+            style.font_weight.0 as u32,
+            style.font_style != FontStyle::Normal,
+        );
+        let properties = style.font_properties();
+        self.fonts
+            .entry(key)
+            .or_insert_with(|| {
+                let mut families: Vec<FamilyName> = style
+                    .font_family
+                    .split(',')
+                    .map(|family| family.trim().trim_matches(|c| c == '"' || c == '\''))
+                    .filter(|family| !family.is_empty())
+                    .map(|family| match family {
+                        "serif" => FamilyName::Serif,
+                        "sans-serif" => FamilyName::SansSerif,
+                        "monospace" => FamilyName::Monospace,
+                        "cursive" => FamilyName::Cursive,
+                        "fantasy" => FamilyName::Fantasy,
+                        family => FamilyName::Title(family.to_owned()),
+                    })
+                    .collect();
+                families.push(FamilyName::SansSerif);
+                let handle = SystemSource::new()
+                    .select_best_match(&families, &properties)
+                    .map_err(|error| warn!("No font for SVG text: {:?}", error))
+                    .ok()?;
+                handle
+                    .load()
+                    .map_err(|error| warn!("Failed to load font for SVG text: {:?}", error))
+                    .ok()
+            })
+            .clone()
+    }
+
+    fn render_text(&mut self, element: &Element, style: &Style, transform: &Transform2D<f32>) {
+        let mut position = Point2D::zero();
+        let mut after_space = true;
+        self.render_text_content(element, style, transform, &mut position, &mut after_space);
+    }
+
+    /// Renders the text of a `<text>` or `<tspan>` element, advancing the
+    /// current text position. Whitespace is collapsed as with
+    /// `xml:space="default"`.
+    fn render_text_content(
+        &mut self,
+        element: &Element,
+        style: &Style,
+        transform: &Transform2D<f32>,
+        position: &mut Point2D<f32>,
+        after_space: &mut bool,
+    ) {
+        if element.attribute("x").is_some() {
+            position.x = self.length(element, "x", style, 0.);
+        }
+        if element.attribute("y").is_some() {
+            position.y = self.length(element, "y", style, 0.);
+        }
+        position.x += self.length(element, "dx", style, 0.);
+        position.y += self.length(element, "dy", style, 0.);
+
+        for child in &element.children {
+            match *child {
+                Node::Text(ref text) => {
+                    let mut collapsed = String::with_capacity(text.len());
+                    for c in text.chars().filter(|&c| c != '\n' && c != '\r') {
+                        let space = c.is_whitespace();
+                        if !(space && *after_space) {
+                            collapsed.push(if space { ' ' } else { c });
+                        }
+                        *after_space = space;
+                    }
+                    self.draw_text(&collapsed, style, transform, position);
+                },
+                Node::Element(ref child) if child.name == "tspan" || child.name == "a" => {
+                    let mut child_style = style.clone();
+                    let mut display = true;
+                    for (name, value) in declarations(child) {
+                        if name == "display" && value == "none" {
+                            display = false;
+                        }
+                        child_style.apply(&name, &value);
+                    }
+                    if display {
+                        self.render_text_content(
+                            child,
+                            &child_style,
+                            transform,
+                            position,
+                            after_space,
+                        );
+                    }
+                },
+                Node::Element(_) => {},
+            }
+        }
+    }
+
+    fn draw_text(
+        &mut self,
+        text: &str,
+        style: &Style,
+        transform: &Transform2D<f32>,
+        position: &mut Point2D<f32>,
+    ) {
+        if text.is_empty() || style.font_size <= 0. {
+            return;
+        }
+        let font = match self.font(style) {
+            Some(font) => font,
+            None => return,
+        };
+        let scale = style.font_size / font.metrics().units_per_em as f32;
+        let advance: f32 = text
+            .chars()
+            .filter_map(|c| font.glyph_for_char(c))
+            .filter_map(|glyph| font.advance(glyph).ok())
+            .map(|advance| advance.x * scale)
+            .sum();
+        let start = match style.text_anchor {
+            TextAnchor::Start => *position,
+            TextAnchor::Middle => Point2D::new(position.x - advance / 2., position.y),
+            TextAnchor::End => Point2D::new(position.x - advance, position.y),
+        };
+        position.x = start.x + advance;
+        if !style.visible {
+            return;
+        }
+
+        let bounds = Rect::new(
+            Point2D::new(start.x, start.y - style.font_size),
+            Size2D::new(advance, style.font_size),
+        );
+        // Glyph outlines aren't available to stroke, so only the fill is drawn.
+        let source = match self.resolve_paint(&style.fill, style.fill_opacity, style, &bounds) {
+            Some(ResolvedPaint::Solid(color)) => Source::Solid(color),
+            Some(ResolvedPaint::Gradient(gradient)) => gradient.user_space_source(),
+            None => return,
+        };
+        self.target.set_transform(transform);
+        self.target.draw_text(
+            &font,
+            style.font_size,
+            text,
+            start,
+            &source,
+            &DrawOptions::new(),
+        );
+    }
+}
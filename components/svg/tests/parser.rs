@@ -0,0 +1,115 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use cssparser::RGBA;
+use euclid::default::Transform2D;
+use servo_svg::parser::{
+    parse_length, parse_paint, parse_path_data, parse_transform, Length, LengthUnit, Paint,
+    PathSegment,
+};
+
+#[test]
+fn test_parse_path_data() {
+    assert_eq!(
+        parse_path_data("M10 10 h 20 v-5.5 l5,5 z"),
+        vec![
+            PathSegment::MoveTo(10., 10.),
+            PathSegment::LineTo(30., 10.),
+            PathSegment::LineTo(30., 4.5),
+            PathSegment::LineTo(35., 9.5),
+            PathSegment::Close,
+        ]
+    );
+    assert_eq!(
+        parse_path_data("M0 0 C 10 0 20 10 20 20 S 30 40 40 40"),
+        vec![
+            PathSegment::MoveTo(0., 0.),
+            PathSegment::CubicTo(10., 0., 20., 10., 20., 20.),
+            PathSegment::CubicTo(20., 30., 30., 40., 40., 40.),
+        ]
+    );
+    assert_eq!(
+        parse_path_data("M0,0Q10,10,20,0T40,0"),
+        vec![
+            PathSegment::MoveTo(0., 0.),
+            PathSegment::QuadTo(10., 10., 20., 0.),
+            PathSegment::QuadTo(30., -10., 40., 0.),
+        ]
+    );
+    assert_eq!(
+        parse_path_data("M0 0a5 5 0 1010 0"),
+        vec![
+            PathSegment::MoveTo(0., 0.),
+            PathSegment::ArcTo {
+                rx: 5.,
+                ry: 5.,
+                x_axis_rotation: 0.,
+                large_arc: true,
+                sweep: false,
+                x: 10.,
+                y: 0.,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_parse_path_data_stops_at_first_error() {
+    assert_eq!(
+        parse_path_data("M 1 2 L 3 4 L 5 x L 7 8"),
+        vec![PathSegment::MoveTo(1., 2.), PathSegment::LineTo(3., 4.)]
+    );
+    assert_eq!(parse_path_data("L 3 4"), vec![]);
+}
+
+#[test]
+fn test_parse_transform() {
+    assert_eq!(
+        parse_transform("translate(10, 20) scale(2)"),
+        Some(Transform2D::row_major(2., 0., 0., 2., 10., 20.))
+    );
+    assert_eq!(
+        parse_transform("matrix(1 2 3 4 5 6)"),
+        Some(Transform2D::row_major(1., 2., 3., 4., 5., 6.))
+    );
+    assert_eq!(parse_transform(""), Some(Transform2D::identity()));
+    assert_eq!(parse_transform("translate(10"), None);
+    assert_eq!(parse_transform("skew(10)"), None);
+}
+
+#[test]
+fn test_parse_length() {
+    assert_eq!(parse_length("12"), Some(Length::new(12., LengthUnit::None)));
+    assert_eq!(
+        parse_length(" 1.5em "),
+        Some(Length::new(1.5, LengthUnit::Em))
+    );
+    assert_eq!(
+        parse_length("50%"),
+        Some(Length::new(50., LengthUnit::Percent))
+    );
+    assert_eq!(parse_length("1in").unwrap().resolve(16., 100.), 96.);
+    assert_eq!(parse_length("2em").unwrap().resolve(16., 100.), 32.);
+    assert_eq!(parse_length("25%").unwrap().resolve(16., 200.), 50.);
+    assert_eq!(parse_length("12 px"), None);
+    assert_eq!(parse_length("auto"), None);
+}
+
+#[test]
+fn test_parse_paint() {
+    assert_eq!(parse_paint("none"), Some(Paint::None));
+    assert_eq!(parse_paint("currentColor"), Some(Paint::CurrentColor));
+    assert_eq!(
+        parse_paint("#f00"),
+        Some(Paint::Color(RGBA::new(255, 0, 0, 255)))
+    );
+    assert_eq!(
+        parse_paint("url(#gradient) blue"),
+        Some(Paint::Url(
+            "gradient".to_owned(),
+            Some(Box::new(Paint::Color(RGBA::new(0, 0, 255, 255))))
+        ))
+    );
+    assert_eq!(parse_paint("bogus"), None);
+}
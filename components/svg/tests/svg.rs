@@ -0,0 +1,141 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use cssparser::RGBA;
+use euclid::default::Size2D;
+use servo_svg::MAX_NESTING_DEPTH;
+use servo_svg::{intrinsic_size, is_svg, parse, rasterize, Node, ParseError, Raster};
+
+fn pixel(raster: &Raster, x: u32, y: u32) -> [u8; 4] {
+    let offset = ((y * raster.width + x) * 4) as usize;
+    let bgra = &raster.bytes[offset..offset + 4];
+    [bgra[0], bgra[1], bgra[2], bgra[3]]
+}
+
+#[test]
+fn test_is_svg() {
+    assert!(is_svg(b"<svg xmlns='http://www.w3.org/2000/svg'/>"));
+    assert!(is_svg(
+        b"\xEF\xBB\xBF<?xml version='1.0'?><!DOCTYPE svg><!-- <html> --> <svg>"
+    ));
+    assert!(!is_svg(b"<svgfoo>"));
+    assert!(!is_svg(b"<html><svg></svg></html>"));
+    assert!(!is_svg(b"<!-- unterminated <svg>"));
+}
+
+#[test]
+fn test_parse() {
+    let root = parse(
+        b"<svg xmlns='http://www.w3.org/2000/svg' width='10'><g id='a'><rect/></g>text</svg>",
+    )
+    .unwrap();
+    assert_eq!(root.name, "svg");
+    assert_eq!(root.attribute("width"), Some("10"));
+    assert_eq!(root.find_by_id("a").unwrap().elements().count(), 1);
+    assert_eq!(root.children[1], Node::Text("text".to_owned()));
+
+    match parse(b"<html xmlns='http://www.w3.org/1999/xhtml'/>") {
+        Err(ParseError::NotSvg) => {},
+        result => panic!("Unexpected result {:?}", result),
+    }
+    match parse(b"<svg><g></svg>") {
+        Err(ParseError::Xml(_)) => {},
+        result => panic!("Unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn test_parse_nesting_limit() {
+    let nested = |depth| {
+        let mut markup = "<svg>".to_owned();
+        markup.push_str(&"<g>".repeat(depth - 1));
+        markup.push_str(&"</g>".repeat(depth - 1));
+        markup.push_str("</svg>");
+        parse(markup.as_bytes())
+    };
+    assert!(nested(MAX_NESTING_DEPTH).is_ok());
+    match nested(MAX_NESTING_DEPTH + 1) {
+        Err(ParseError::TooDeeplyNested) => {},
+        result => panic!("Unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn test_intrinsic_size() {
+    let size = |markup: &str| intrinsic_size(&parse(markup.as_bytes()).unwrap());
+    assert_eq!(
+        size("<svg width='40' height='2em'/>"),
+        Size2D::new(40., 32.)
+    );
+    assert_eq!(
+        size("<svg width='40' viewBox='0 0 20 10'/>"),
+        Size2D::new(40., 20.)
+    );
+    assert_eq!(
+        size("<svg height='40' viewBox='0 0 20 10'/>"),
+        Size2D::new(80., 40.)
+    );
+    assert_eq!(size("<svg width='100%'/>"), Size2D::new(300., 150.));
+}
+
+#[test]
+fn test_rasterize_shapes() {
+    let root = parse(
+        b"<svg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 10 10'>\
+          <rect width='5' height='10' fill='#00f'/>\
+          <circle cx='7.5' cy='5' r='2' style='fill: currentColor; fill-opacity: 0.5'/>\
+          </svg>",
+    )
+    .unwrap();
+    let raster = rasterize(&root, Size2D::new(10., 10.), 2., RGBA::new(255, 0, 0, 255));
+    assert_eq!((raster.width, raster.height), (20, 20));
+    assert_eq!(raster.bytes.len(), 20 * 20 * 4);
+    assert_eq!(pixel(&raster, 2, 10), [255, 0, 0, 255]);
+    assert_eq!(pixel(&raster, 15, 10)[2], pixel(&raster, 15, 10)[3]);
+    assert!((pixel(&raster, 15, 10)[3] as i32 - 128).abs() <= 1);
+    assert_eq!(pixel(&raster, 15, 1), [0, 0, 0, 0]);
+}
+
+#[test]
+fn test_rasterize_transform_and_use() {
+    let root = parse(
+        b"<svg xmlns='http://www.w3.org/2000/svg' \
+               xmlns:xlink='http://www.w3.org/1999/xlink' width='20' height='20'>\
+          <defs><rect id='r' width='5' height='5' fill='lime'/></defs>\
+          <use xlink:href='#r' transform='translate(10 10)'/>\
+          </svg>",
+    )
+    .unwrap();
+    let raster = rasterize(&root, Size2D::new(20., 20.), 1., RGBA::new(0, 0, 0, 255));
+    assert_eq!(pixel(&raster, 2, 2), [0, 0, 0, 0]);
+    assert_eq!(pixel(&raster, 12, 12), [0, 255, 0, 255]);
+}
+
+#[test]
+fn test_rasterize_empty_viewport() {
+    let root = parse(b"<svg xmlns='http://www.w3.org/2000/svg'/>").unwrap();
+    let raster = rasterize(&root, Size2D::new(0., 10.), 1., RGBA::new(0, 0, 0, 255));
+    assert_eq!((raster.width, raster.height), (0, 0));
+    assert!(raster.bytes.is_empty());
+}
+
+#[test]
+fn test_rasterize_use_limit() {
+    // Each level uses the previous one ten times, for 10^9 rectangles in all.
+    let mut markup = "<svg xmlns='http://www.w3.org/2000/svg' \
+                      xmlns:xlink='http://www.w3.org/1999/xlink' width='10' height='10'>\
+                      <defs><rect id='l0' width='1' height='1'/>"
+        .to_owned();
+    for level in 1..10 {
+        markup.push_str(&format!("<g id='l{}'>", level));
+        for _ in 0..10 {
+            markup.push_str(&format!("<use xlink:href='#l{}'/>", level - 1));
+        }
+        markup.push_str("</g>");
+    }
+    markup.push_str("</defs><use xlink:href='#l9'/></svg>");
+    let root = parse(markup.as_bytes()).unwrap();
+    let raster = rasterize(&root, Size2D::new(10., 10.), 1., RGBA::new(0, 0, 0, 255));
+    assert_eq!(pixel(&raster, 0, 0), [0, 0, 0, 255]);
+}
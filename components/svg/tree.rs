@@ -0,0 +1,128 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+//! A minimal element tree for SVG documents, either parsed from markup or
+//! built from the DOM of an inline `<svg>` element.
+
+use std::fmt;
+use xml::reader::{EventReader, XmlEvent};
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+
+/// How deeply elements may be nested. Trees are walked recursively, so deeper
+/// documents could overflow the stack.
+pub const MAX_NESTING_DEPTH: usize = 256;
+
+#[derive(Clone, Debug, Hash, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// An element of an SVG document. Attributes are keyed by their local name.
+#[derive(Clone, Debug, Hash, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    pub fn new(name: &str) -> Element {
+        Element {
+            name: name.to_owned(),
+            attributes: vec![],
+            children: vec![],
+        }
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|&&(ref attr_name, _)| attr_name == name)
+            .map(|&(_, ref value)| &**value)
+    }
+
+    /// The child elements of this element, skipping text.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|child| match *child {
+            Node::Element(ref element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Finds the element with the given `id` in this subtree.
+    pub fn find_by_id(&self, id: &str) -> Option<&Element> {
+        if self.attribute("id") == Some(id) {
+            return Some(self);
+        }
+        self.elements()
+            .filter_map(|child| child.find_by_id(id))
+            .next()
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    /// The document is not well-formed XML.
+    Xml(String),
+    /// The root element of the document isn't an SVG `<svg>` element.
+    NotSvg,
+    /// Elements are nested more than `MAX_NESTING_DEPTH` levels deep.
+    TooDeeplyNested,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Xml(ref message) => write!(f, "Malformed SVG document: {}", message),
+            ParseError::NotSvg => write!(f, "Root element is not <svg>"),
+            ParseError::TooDeeplyNested => write!(f, "Elements are nested too deeply"),
+        }
+    }
+}
+
+/// Parses an SVG document into an element tree rooted at its `<svg>` element.
+pub fn parse(bytes: &[u8]) -> Result<Element, ParseError> {
+    let mut stack: Vec<Element> = vec![];
+    for event in EventReader::new(bytes) {
+        match event.map_err(|error| ParseError::Xml(error.to_string()))? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                if stack.is_empty() &&
+                    (name.local_name != "svg" ||
+                        name.namespace
+                            .as_ref()
+                            .map_or(false, |ns| ns != SVG_NAMESPACE))
+                {
+                    return Err(ParseError::NotSvg);
+                }
+                if stack.len() >= MAX_NESTING_DEPTH {
+                    return Err(ParseError::TooDeeplyNested);
+                }
+                let mut element = Element::new(&name.local_name);
+                element.attributes = attributes
+                    .into_iter()
+                    .map(|attr| (attr.name.local_name, attr.value))
+                    .collect();
+                stack.push(element);
+            },
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(Node::Element(element)),
+                    None => return Ok(element),
+                }
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) | XmlEvent::Whitespace(text) => {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(Node::Text(text));
+                }
+            },
+            _ => {},
+        }
+    }
+    Err(ParseError::Xml("Unexpected end of document".to_owned()))
+}
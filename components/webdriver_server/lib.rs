@@ -25,16 +25,18 @@ use crossbeam_channel::{after, unbounded, Receiver, Sender};
 use euclid::{Rect, Size2D};
 use hyper::Method;
 use image::{DynamicImage, ImageFormat, RgbImage};
-use ipc_channel::ipc::{self, IpcSender};
+use ipc_channel::ipc::{self, IpcReceiver, IpcSender};
 use ipc_channel::router::ROUTER;
 use keyboard_types::webdriver::send_keys;
 use msg::constellation_msg::{BrowsingContextId, TopLevelBrowsingContextId, TraversalDirection};
 use pixels::PixelFormat;
+use script_traits::webdriver_msg::WebDriverWindowState;
 use script_traits::webdriver_msg::{LoadStatus, WebDriverCookieError, WebDriverFrameId};
 use script_traits::webdriver_msg::{
     WebDriverJSError, WebDriverJSResult, WebDriverJSValue, WebDriverScriptCommand,
 };
-use script_traits::{ConstellationMsg, LoadData, LoadOrigin, WebDriverCommandMsg};
+use script_traits::webdriver_msg::{WebDriverUserPromptAction, WebDriverUserPromptError};
+use script_traits::{ConstellationMsg, LoadData, LoadOrigin, WebDriverCommandMsg, WindowSizeData};
 use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
 use serde::ser::{Serialize, Serializer};
 use serde_json::{json, Value};
@@ -54,6 +56,7 @@ use webdriver::actions::{
     PointerUpAction,
 };
 use webdriver::capabilities::{Capabilities, CapabilitiesMatching};
+use webdriver::command::NewWindowParameters;
 use webdriver::command::{ActionsParameters, SwitchToWindowParameters};
 use webdriver::command::{
     AddCookieParameters, GetParameters, JavascriptCommandParameters, LocatorParameters,
@@ -67,7 +70,8 @@ use webdriver::command::{
 use webdriver::common::{Cookie, Date, LocatorStrategy, Parameters, WebElement};
 use webdriver::error::{ErrorStatus, WebDriverError, WebDriverResult};
 use webdriver::httpapi::WebDriverExtensionRoute;
-use webdriver::response::{CookieResponse, CookiesResponse};
+use webdriver::response::NewWindowResponse;
+use webdriver::response::{CloseWindowResponse, CookieResponse, CookiesResponse};
 use webdriver::response::{ElementRectResponse, NewSessionResponse, ValueResponse};
 use webdriver::response::{TimeoutsResponse, WebDriverResponse, WindowRectResponse};
 use webdriver::server::{self, Session, WebDriverHandler};
@@ -142,6 +146,10 @@ pub struct WebDriverSession {
     strict_file_interactability: bool,
     unhandled_prompt_behavior: String,
 
+    /// The window handles of the top-level browsing contexts that have been exposed to the
+    /// client.
+    window_handles: HashMap<TopLevelBrowsingContextId, String>,
+
     // https://w3c.github.io/webdriver/#dfn-active-input-sources
     active_input_sources: Vec<InputSourceState>,
     // https://w3c.github.io/webdriver/#dfn-input-state-table
@@ -155,8 +163,13 @@ impl WebDriverSession {
         browsing_context_id: BrowsingContextId,
         top_level_browsing_context_id: TopLevelBrowsingContextId,
     ) -> WebDriverSession {
+        let id = Uuid::new_v4();
+        // The handle of the initial window is the session id.
+        let mut window_handles = HashMap::new();
+        window_handles.insert(top_level_browsing_context_id, id.to_string());
+
         WebDriverSession {
-            id: Uuid::new_v4(),
+            id: id,
             browsing_context_id: browsing_context_id,
             top_level_browsing_context_id: top_level_browsing_context_id,
 
@@ -169,11 +182,25 @@ impl WebDriverSession {
             strict_file_interactability: false,
             unhandled_prompt_behavior: "dismiss and notify".to_string(),
 
+            window_handles: window_handles,
+
             active_input_sources: Vec::new(),
             input_state_table: HashMap::new(),
             input_cancel_list: Vec::new(),
         }
     }
+
+    /// Returns the window handle of a top-level browsing context, creating a new one if it
+    /// doesn't have one yet.
+    fn window_handle(
+        &mut self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+    ) -> String {
+        self.window_handles
+            .entry(top_level_browsing_context_id)
+            .or_insert_with(|| Uuid::new_v4().to_string())
+            .clone()
+    }
 }
 
 struct Handler {
@@ -543,7 +570,10 @@ impl Handler {
                         },
                     }
 
-                    match processed.get("unhandledPromptBehavior") {
+                    match processed
+                        .get("unhandledPromptBehavior")
+                        .and_then(|behavior| behavior.as_str())
+                    {
                         Some(unhandled_prompt_behavior) => {
                             session.unhandled_prompt_behavior =
                                 unhandled_prompt_behavior.to_string()
@@ -723,6 +753,39 @@ impl Handler {
             .send(ConstellationMsg::WebDriverCommand(cmd_msg))
             .unwrap();
 
+        self.window_rect_after_resize(top_level_browsing_context_id, sender, receiver)
+    }
+
+    // https://w3c.github.io/webdriver/#maximize-window
+    // https://w3c.github.io/webdriver/#minimize-window
+    // https://w3c.github.io/webdriver/#fullscreen-window
+    fn handle_set_window_state(
+        &self,
+        state: WebDriverWindowState,
+    ) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let cmd_msg = WebDriverCommandMsg::SetWindowState(
+            top_level_browsing_context_id,
+            state,
+            sender.clone(),
+        );
+
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        self.window_rect_after_resize(top_level_browsing_context_id, sender, receiver)
+    }
+
+    /// Waits for the window to be resized, and returns its new size. The window may not be
+    /// resized at all, e.g. in headless mode, so the current size is used after a timeout.
+    fn window_rect_after_resize(
+        &self,
+        top_level_browsing_context_id: TopLevelBrowsingContextId,
+        sender: IpcSender<WindowSizeData>,
+        receiver: IpcReceiver<WindowSizeData>,
+    ) -> WebDriverResult<WebDriverResponse> {
         let timeout = self.resize_timeout;
         let constellation_chan = self.constellation_chan.clone();
         thread::spawn(move || {
@@ -819,26 +882,100 @@ impl Handler {
         )))
     }
 
-    fn handle_window_handle(&self) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there's only one window so just use the session
-        // id as the window id
-        let handle = self.session.as_ref().unwrap().id.to_string();
+    /// Returns the IDs of all the open top-level browsing contexts.
+    fn top_level_browsing_context_ids(&self) -> Vec<TopLevelBrowsingContextId> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::GetTopLevelBrowsingContexts(sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        receiver.recv().unwrap()
+    }
+
+    fn verify_top_level_browsing_context_is_open(&self) -> WebDriverResult<()> {
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        if self
+            .top_level_browsing_context_ids()
+            .contains(&top_level_browsing_context_id)
+        {
+            Ok(())
+        } else {
+            Err(WebDriverError::new(
+                ErrorStatus::NoSuchWindow,
+                "No such window",
+            ))
+        }
+    }
+
+    // https://w3c.github.io/webdriver/#get-window-handle
+    fn handle_window_handle(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let session = self.session_mut()?;
+        let top_level_browsing_context_id = session.top_level_browsing_context_id;
+        let handle = session.window_handle(top_level_browsing_context_id);
         Ok(WebDriverResponse::Generic(ValueResponse(
             serde_json::to_value(handle)?,
         )))
     }
 
-    fn handle_window_handles(&self) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there's only one window so just use the session
-        // id as the window id
-        let handles = vec![serde_json::to_value(
-            self.session.as_ref().unwrap().id.to_string(),
-        )?];
+    fn window_handles(&mut self) -> WebDriverResult<Vec<String>> {
+        let top_level_browsing_context_ids = self.top_level_browsing_context_ids();
+        let session = self.session_mut()?;
+        Ok(top_level_browsing_context_ids
+            .into_iter()
+            .map(|id| session.window_handle(id))
+            .collect())
+    }
+
+    // https://w3c.github.io/webdriver/#get-window-handles
+    fn handle_window_handles(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let handles = self.window_handles()?;
         Ok(WebDriverResponse::Generic(ValueResponse(
             serde_json::to_value(handles)?,
         )))
     }
 
+    // https://w3c.github.io/webdriver/#new-window
+    fn handle_new_window(
+        &mut self,
+        _parameters: &NewWindowParameters,
+    ) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let cmd_msg = WebDriverCommandMsg::NewWindow(sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+
+        let top_level_browsing_context_id = receiver.recv().unwrap();
+        let handle = self
+            .session_mut()?
+            .window_handle(top_level_browsing_context_id);
+        Ok(WebDriverResponse::NewWindow(NewWindowResponse {
+            handle: handle,
+            typ: "tab".to_string(),
+        }))
+    }
+
+    // https://w3c.github.io/webdriver/#close-window
+    fn handle_close_window(&mut self) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        let cmd_msg = WebDriverCommandMsg::CloseWindow(top_level_browsing_context_id, sender);
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(cmd_msg))
+            .unwrap();
+        receiver.recv().unwrap();
+
+        self.session_mut()?
+            .window_handles
+            .remove(&top_level_browsing_context_id);
+        let handles = self.window_handles()?;
+        if handles.is_empty() {
+            // Closing the last window ends the session.
+            self.session = None;
+        }
+        Ok(WebDriverResponse::CloseWindow(CloseWindowResponse(handles)))
+    }
+
     fn handle_find_element(
         &self,
         parameters: &LocatorParameters,
@@ -910,15 +1047,22 @@ impl Handler {
         &mut self,
         parameters: &SwitchToWindowParameters,
     ) -> WebDriverResult<WebDriverResponse> {
-        // For now we assume there is only one window which has the current
-        // session's id as window id
-        if parameters.handle == self.session.as_ref().unwrap().id.to_string() {
-            Ok(WebDriverResponse::Void)
-        } else {
-            Err(WebDriverError::new(
+        let top_level_browsing_context_ids = self.top_level_browsing_context_ids();
+        let session = self.session_mut()?;
+        let top_level_browsing_context_id = top_level_browsing_context_ids
+            .into_iter()
+            .find(|id| session.window_handles.get(id) == Some(&parameters.handle));
+        match top_level_browsing_context_id {
+            Some(top_level_browsing_context_id) => {
+                session.top_level_browsing_context_id = top_level_browsing_context_id;
+                session.browsing_context_id =
+                    BrowsingContextId::from(top_level_browsing_context_id);
+                Ok(WebDriverResponse::Void)
+            },
+            None => Err(WebDriverError::new(
                 ErrorStatus::NoSuchWindow,
                 "No such window",
-            ))
+            )),
         }
     }
 
@@ -1287,9 +1431,95 @@ impl Handler {
         }
     }
 
+    fn close_user_prompt(&self, action: WebDriverUserPromptAction) -> WebDriverResult<String> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(
+                WebDriverCommandMsg::HandleUserPrompt(
+                    top_level_browsing_context_id,
+                    action,
+                    sender,
+                ),
+            ))
+            .unwrap();
+        receiver.recv().unwrap().map_err(user_prompt_error)
+    }
+
+    fn user_prompt_text(&self) -> WebDriverResult<String> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(
+                WebDriverCommandMsg::GetAlertText(top_level_browsing_context_id, sender),
+            ))
+            .unwrap();
+        receiver.recv().unwrap().map_err(user_prompt_error)
+    }
+
+    // https://w3c.github.io/webdriver/#dfn-handle-any-user-prompts
+    fn handle_any_user_prompts(&self) -> WebDriverResult<()> {
+        let behavior = self.session()?.unhandled_prompt_behavior.clone();
+        let result = match &*behavior {
+            "accept" | "accept and notify" => {
+                self.close_user_prompt(WebDriverUserPromptAction::Accept)
+            },
+            "dismiss" | "dismiss and notify" => {
+                self.close_user_prompt(WebDriverUserPromptAction::Dismiss)
+            },
+            _ => self.user_prompt_text(),
+        };
+        let message = match result {
+            Ok(message) => message,
+            Err(ref error) if error.error == ErrorStatus::NoSuchAlert => return Ok(()),
+            Err(error) => return Err(error),
+        };
+        match &*behavior {
+            "accept" | "dismiss" => Ok(()),
+            _ => Err(WebDriverError::new(
+                ErrorStatus::UnexpectedAlertOpen,
+                message,
+            )),
+        }
+    }
+
     // https://w3c.github.io/webdriver/#dismiss-alert
     fn handle_dismiss_alert(&mut self) -> WebDriverResult<WebDriverResponse> {
-        // Since user prompts are not yet implement this will always succeed
+        self.close_user_prompt(WebDriverUserPromptAction::Dismiss)?;
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/#accept-alert
+    fn handle_accept_alert(&mut self) -> WebDriverResult<WebDriverResponse> {
+        self.close_user_prompt(WebDriverUserPromptAction::Accept)?;
+        Ok(WebDriverResponse::Void)
+    }
+
+    // https://w3c.github.io/webdriver/#get-alert-text
+    fn handle_get_alert_text(&self) -> WebDriverResult<WebDriverResponse> {
+        let message = self.user_prompt_text()?;
+        Ok(WebDriverResponse::Generic(ValueResponse(
+            serde_json::to_value(message)?,
+        )))
+    }
+
+    // https://w3c.github.io/webdriver/#send-alert-text
+    fn handle_send_alert_text(
+        &self,
+        params: &SendKeysParameters,
+    ) -> WebDriverResult<WebDriverResponse> {
+        let (sender, receiver) = ipc::channel().unwrap();
+        let top_level_browsing_context_id = self.session()?.top_level_browsing_context_id;
+        self.constellation_chan
+            .send(ConstellationMsg::WebDriverCommand(
+                WebDriverCommandMsg::SendAlertText(
+                    top_level_browsing_context_id,
+                    params.text.clone(),
+                    sender,
+                ),
+            ))
+            .unwrap();
+        receiver.recv().unwrap().map_err(user_prompt_error)?;
         Ok(WebDriverResponse::Void)
     }
 
@@ -1682,6 +1912,18 @@ impl Handler {
     }
 }
 
+fn user_prompt_error(error: WebDriverUserPromptError) -> WebDriverError {
+    match error {
+        WebDriverUserPromptError::NoSuchAlert => {
+            WebDriverError::new(ErrorStatus::NoSuchAlert, "No user prompt is open")
+        },
+        WebDriverUserPromptError::ElementNotInteractable => WebDriverError::new(
+            ErrorStatus::ElementNotInteractable,
+            "The user prompt doesn't have a text field",
+        ),
+    }
+}
+
 impl WebDriverHandler<ServoExtensionRoute> for Handler {
     fn handle_command(
         &mut self,
@@ -1699,6 +1941,39 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             },
         }
 
+        // Most commands operate on the current top-level browsing context, which could have
+        // been closed.
+        match msg.command {
+            WebDriverCommand::NewSession(_) |
+            WebDriverCommand::DeleteSession |
+            WebDriverCommand::Status |
+            WebDriverCommand::GetTimeouts |
+            WebDriverCommand::SetTimeouts(_) |
+            WebDriverCommand::GetWindowHandles |
+            WebDriverCommand::SwitchToWindow(_) |
+            WebDriverCommand::Extension(_) => {},
+            _ => self.verify_top_level_browsing_context_is_open()?,
+        }
+
+        // Commands that interact with the current page have to deal with any user prompt that
+        // is blocking it first.
+        match msg.command {
+            WebDriverCommand::NewSession(_) |
+            WebDriverCommand::DeleteSession |
+            WebDriverCommand::Status |
+            WebDriverCommand::GetTimeouts |
+            WebDriverCommand::SetTimeouts(_) |
+            WebDriverCommand::GetWindowHandle |
+            WebDriverCommand::GetWindowHandles |
+            WebDriverCommand::SwitchToWindow(_) |
+            WebDriverCommand::DismissAlert |
+            WebDriverCommand::AcceptAlert |
+            WebDriverCommand::GetAlertText |
+            WebDriverCommand::SendAlertText(_) |
+            WebDriverCommand::Extension(_) => {},
+            _ => self.handle_any_user_prompts()?,
+        }
+
         match msg.command {
             WebDriverCommand::NewSession(ref parameters) => self.handle_new_session(parameters),
             WebDriverCommand::DeleteSession => self.handle_delete_session(),
@@ -1716,6 +1991,17 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             WebDriverCommand::GetTitle => self.handle_title(),
             WebDriverCommand::GetWindowHandle => self.handle_window_handle(),
             WebDriverCommand::GetWindowHandles => self.handle_window_handles(),
+            WebDriverCommand::NewWindow(ref parameters) => self.handle_new_window(parameters),
+            WebDriverCommand::CloseWindow => self.handle_close_window(),
+            WebDriverCommand::MaximizeWindow => {
+                self.handle_set_window_state(WebDriverWindowState::Maximized)
+            },
+            WebDriverCommand::MinimizeWindow => {
+                self.handle_set_window_state(WebDriverWindowState::Minimized)
+            },
+            WebDriverCommand::FullscreenWindow => {
+                self.handle_set_window_state(WebDriverWindowState::Fullscreen)
+            },
             WebDriverCommand::SwitchToFrame(ref parameters) => {
                 self.handle_switch_to_frame(parameters)
             },
@@ -1758,6 +2044,9 @@ impl WebDriverHandler<ServoExtensionRoute> for Handler {
             },
             WebDriverCommand::ElementClick(ref element) => self.handle_element_click(element),
             WebDriverCommand::DismissAlert => self.handle_dismiss_alert(),
+            WebDriverCommand::AcceptAlert => self.handle_accept_alert(),
+            WebDriverCommand::GetAlertText => self.handle_get_alert_text(),
+            WebDriverCommand::SendAlertText(ref x) => self.handle_send_alert_text(x),
             WebDriverCommand::DeleteCookies => self.handle_delete_cookies(),
             WebDriverCommand::GetTimeouts => self.handle_get_timeouts(),
             WebDriverCommand::SetTimeouts(ref x) => self.handle_set_timeouts(x),
@@ -24,7 +24,7 @@ use std::mem;
 use std::rc::Rc;
 use std::thread;
 use std::time::Duration;
-use tinyfiledialogs::{self, MessageBoxIcon};
+use tinyfiledialogs::{self, MessageBoxIcon, OkCancel};
 
 pub struct Browser<Window: WindowPortsMethods + ?Sized> {
     current_url: Option<ServoUrl>,
@@ -34,8 +34,8 @@ pub struct Browser<Window: WindowPortsMethods + ?Sized> {
 
     // A rudimentary stack of "tabs".
     // EmbedderMsg::BrowserCreated will push onto it.
-    // EmbedderMsg::CloseBrowser will pop from it,
-    // and exit if it is empty afterwards.
+    // EmbedderMsg::CloseBrowser will remove the closed browser from it,
+    // select the last remaining one, and exit if it is empty afterwards.
     browsers: Vec<BrowserId>,
 
     title: Option<String>,
@@ -320,6 +320,49 @@ where
                             .push(WindowEvent::SendError(browser_id, reason));
                     }
                 },
+                EmbedderMsg::Confirm(message, sender) => {
+                    let accepted = if !opts::get().headless {
+                        thread::Builder::new()
+                            .name("display confirm dialog".to_owned())
+                            .spawn(move || {
+                                tinyfiledialogs::message_box_ok_cancel(
+                                    "Confirm",
+                                    &message,
+                                    MessageBoxIcon::Question,
+                                    OkCancel::Cancel,
+                                ) == OkCancel::Ok
+                            })
+                            .unwrap()
+                            .join()
+                            .expect("Thread spawning failed")
+                    } else {
+                        false
+                    };
+                    if let Err(e) = sender.send(accepted) {
+                        let reason = format!("Failed to send Confirm response: {}", e);
+                        self.event_queue
+                            .push(WindowEvent::SendError(browser_id, reason));
+                    }
+                },
+                EmbedderMsg::Prompt(message, default, sender) => {
+                    let value = if !opts::get().headless {
+                        thread::Builder::new()
+                            .name("display prompt dialog".to_owned())
+                            .spawn(move || {
+                                tinyfiledialogs::input_box("Prompt", &message, &default)
+                            })
+                            .unwrap()
+                            .join()
+                            .expect("Thread spawning failed")
+                    } else {
+                        None
+                    };
+                    if let Err(e) = sender.send(value) {
+                        let reason = format!("Failed to send Prompt response: {}", e);
+                        self.event_queue
+                            .push(WindowEvent::SendError(browser_id, reason));
+                    }
+                },
                 EmbedderMsg::AllowUnload(sender) => {
                     // Always allow unload for now.
                     if let Err(e) = sender.send(true) {
@@ -392,6 +435,12 @@ where
                 EmbedderMsg::SetFullscreenState(state) => {
                     self.window.set_fullscreen(state);
                 },
+                EmbedderMsg::SetMaximizedState(state) => {
+                    self.window.set_maximized(state);
+                },
+                EmbedderMsg::SetMinimizedState(state) => {
+                    self.window.set_minimized(state);
+                },
                 EmbedderMsg::LoadStart => {
                     self.loading_state = Some(LoadingState::Connecting);
                 },
@@ -399,8 +448,18 @@ where
                     self.loading_state = Some(LoadingState::Loaded);
                 },
                 EmbedderMsg::CloseBrowser => {
-                    // TODO: close the appropriate "tab".
-                    let _ = self.browsers.pop();
+                    // Close the "tab" of the browser that asked to be closed, falling back
+                    // to the most recent one if the message didn't say which.
+                    let index = browser_id
+                        .and_then(|id| self.browsers.iter().position(|browser| *browser == id));
+                    match index {
+                        Some(index) => {
+                            self.browsers.remove(index);
+                        },
+                        None => {
+                            let _ = self.browsers.pop();
+                        },
+                    }
                     if let Some(prev_browser_id) = self.browsers.last() {
                         self.browser_id = Some(*prev_browser_id);
                         self.event_queue
@@ -27,6 +27,9 @@ use servo::embedder_traits::Cursor;
 use servo::script_traits::{TouchEventType, WheelMode, WheelDelta};
 use servo::servo_config::{opts, pref};
 use servo::servo_geometry::DeviceIndependentPixel;
+use servo::style_traits::media_features::{
+    ColorSchemePreference, DeviceFeatures, PointerCapabilities,
+};
 use servo::style_traits::DevicePixel;
 use servo::webrender_api::ScrollLocation;
 use servo::webrender_api::units::{DeviceIntPoint, DeviceIntRect, DeviceIntSize};
@@ -324,12 +327,29 @@ impl WindowPortsMethods for Window {
 
     fn set_fullscreen(&self, state: bool) {
         if self.fullscreen.get() != state {
-            self.gl_context.borrow_mut().window()
-                .set_fullscreen(Some(self.primary_monitor.clone()));
+            let monitor = if state { Some(self.primary_monitor.clone()) } else { None };
+            self.gl_context.borrow_mut().window().set_fullscreen(monitor);
         }
         self.fullscreen.set(state);
     }
 
+    fn set_maximized(&self, state: bool) {
+        if state {
+            self.set_fullscreen(false);
+        }
+        self.gl_context.borrow_mut().window().set_maximized(state);
+    }
+
+    fn set_minimized(&self, state: bool) {
+        // winit can't minimize windows yet, so hide the window instead.
+        let context = self.gl_context.borrow_mut();
+        if state {
+            context.window().hide();
+        } else {
+            context.window().show();
+        }
+    }
+
     fn get_fullscreen(&self) -> bool {
         return self.fullscreen.get();
     }
@@ -656,6 +676,30 @@ impl WindowMethods for Window {
             _ => GlApi::None,
         }
     }
+
+    fn get_device_features(&self) -> DeviceFeatures {
+        // winit doesn't expose the system color scheme or motion settings,
+        // so those come from the preferences.
+        let prefers_color_scheme = match &*pref!(shell.prefers_color_scheme) {
+            "light" => ColorSchemePreference::Light,
+            "dark" => ColorSchemePreference::Dark,
+            _ => ColorSchemePreference::NoPreference,
+        };
+        // When mouse events are turned into touch events, the window behaves
+        // like a touchscreen.
+        let pointer = if opts::get().convert_mouse_to_touch {
+            PointerCapabilities::COARSE
+        } else {
+            PointerCapabilities::FINE | PointerCapabilities::HOVER
+        };
+        DeviceFeatures {
+            prefers_color_scheme,
+            prefers_reduced_motion: pref!(shell.prefers_reduced_motion),
+            primary_pointer: pointer,
+            all_pointers: pointer,
+            ..DeviceFeatures::default()
+        }
+    }
 }
 
 fn winit_phase_to_touch_event_type(phase: TouchPhase) -> TouchEventType {
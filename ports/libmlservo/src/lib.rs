@@ -16,7 +16,10 @@ use servo::keyboard_types::Key;
 use servo::servo_url::ServoUrl;
 use servo::webrender_api::units::{DevicePixel, DevicePoint, LayoutPixel};
 use simpleservo::{self, deinit, gl_glue, MouseButton, ServoGlue, SERVO};
-use simpleservo::{Coordinates, EventLoopWaker, HostTrait, InitOptions, VRInitOptions};
+use simpleservo::{
+    BrowserHandle, Coordinates, DeviceFeatures, EventLoopWaker, HostTrait, InitOptions,
+    VRInitOptions,
+};
 use smallvec::SmallVec;
 use std::cell::Cell;
 use std::ffi::CStr;
@@ -95,6 +98,11 @@ where
     })
 }
 
+/// This app has a single view, which shows the selected browser.
+fn selected(s: &ServoGlue) -> Result<BrowserHandle, &'static str> {
+    s.selected_browser().ok_or("No browser is selected")
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn init_servo(
     ctxt: EGLContext,
@@ -169,6 +177,7 @@ pub unsafe extern "C" fn init_servo(
         args,
         url: Some(url.to_string()),
         density: hidpi,
+        device_features: DeviceFeatures::default(),
         enable_subpixel_text_antialiasing: false,
         vr_init,
         xr_discovery,
@@ -219,8 +228,8 @@ pub unsafe extern "C" fn keyboard_servo(
     };
     // TODO: can the ML1 generate separate press and release events?
     let key2 = key.clone();
-    let _ = call(move |s| s.key_down(key2));
-    let _ = call(move |s| s.key_up(key));
+    let _ = call(move |s| s.key_down(selected(s)?, key2));
+    let _ = call(move |s| s.key_up(selected(s)?, key));
 }
 
 // Some magic numbers.
@@ -240,7 +249,7 @@ pub unsafe extern "C" fn move_servo(servo: *mut ServoInstance, x: f32, y: f32) {
         match servo.scroll_state {
             ScrollState::TriggerUp => {
                 servo.scroll_state = ScrollState::TriggerUp;
-                let _ = call(|s| s.move_mouse(x, y));
+                let _ = call(|s| s.move_mouse(selected(s)?, x, y));
             },
             ScrollState::TriggerDown(start)
                 if (start - point).square_length() < DRAG_CUTOFF_SQUARED =>
@@ -249,17 +258,17 @@ pub unsafe extern "C" fn move_servo(servo: *mut ServoInstance, x: f32, y: f32) {
             }
             ScrollState::TriggerDown(start) => {
                 servo.scroll_state = ScrollState::TriggerDragging(start, point);
-                let _ = call(|s| s.move_mouse(x, y));
+                let _ = call(|s| s.move_mouse(selected(s)?, x, y));
                 let delta = (point - start) * servo.scroll_scale;
                 let start = start.to_i32();
-                let _ = call(|s| s.scroll_start(delta.x, delta.y, start.x, start.y));
+                let _ = call(|s| s.scroll_start(selected(s)?, delta.x, delta.y, start.x, start.y));
             },
             ScrollState::TriggerDragging(start, prev) => {
                 servo.scroll_state = ScrollState::TriggerDragging(start, point);
-                let _ = call(|s| s.move_mouse(x, y));
+                let _ = call(|s| s.move_mouse(selected(s)?, x, y));
                 let delta = (point - prev) * servo.scroll_scale;
                 let start = start.to_i32();
-                let _ = call(|s| s.scroll(delta.x, delta.y, start.x, start.y));
+                let _ = call(|s| s.scroll(selected(s)?, delta.x, delta.y, start.x, start.y));
             },
         }
     }
@@ -273,20 +282,20 @@ pub unsafe extern "C" fn trigger_servo(servo: *mut ServoInstance, x: f32, y: f32
         match servo.scroll_state {
             ScrollState::TriggerUp if down => {
                 servo.scroll_state = ScrollState::TriggerDown(point);
-                let _ = call(|s| s.mouse_down(x, y, MouseButton::Left));
+                let _ = call(|s| s.mouse_down(selected(s)?, x, y, MouseButton::Left));
             },
             ScrollState::TriggerDown(start) if !down => {
                 servo.scroll_state = ScrollState::TriggerUp;
-                let _ = call(|s| s.mouse_up(start.x, start.y, MouseButton::Left));
-                let _ = call(|s| s.click(start.x, start.y));
-                let _ = call(|s| s.move_mouse(start.x, start.y));
+                let _ = call(|s| s.mouse_up(selected(s)?, start.x, start.y, MouseButton::Left));
+                let _ = call(|s| s.click(selected(s)?, start.x, start.y));
+                let _ = call(|s| s.move_mouse(selected(s)?, start.x, start.y));
             },
             ScrollState::TriggerDragging(start, prev) if !down => {
                 servo.scroll_state = ScrollState::TriggerUp;
                 let delta = (point - prev) * servo.scroll_scale;
                 let start = start.to_i32();
-                let _ = call(|s| s.scroll_end(delta.x, delta.y, start.x, start.y));
-                let _ = call(|s| s.mouse_up(x, y, MouseButton::Left));
+                let _ = call(|s| s.scroll_end(selected(s)?, delta.x, delta.y, start.x, start.y));
+                let _ = call(|s| s.mouse_up(selected(s)?, x, y, MouseButton::Left));
             },
             _ => return,
         }
@@ -297,11 +306,11 @@ pub unsafe extern "C" fn trigger_servo(servo: *mut ServoInstance, x: f32, y: f32
 pub unsafe extern "C" fn traverse_servo(_servo: *mut ServoInstance, delta: i32) {
     // Traverse the session history
     if delta == 0 {
-        let _ = call(|s| s.reload());
+        let _ = call(|s| s.reload(selected(s)?));
     } else if delta < 0 {
-        let _ = call(|s| s.go_back());
+        let _ = call(|s| s.go_back(selected(s)?));
     } else {
-        let _ = call(|s| s.go_forward());
+        let _ = call(|s| s.go_forward(selected(s)?));
     }
 }
 
@@ -317,7 +326,7 @@ pub unsafe extern "C" fn navigate_servo(_servo: *mut ServoInstance, text: *const
         search.query_pairs_mut().append_pair("q", text);
         ServoUrl::from_url(search)
     });
-    let _ = call(|s| s.load_uri(url.as_str()));
+    let _ = call(|s| s.load_uri(selected(s)?, url.as_str()));
 }
 
 // Some magic numbers for shutdown
@@ -365,14 +374,14 @@ impl HostTrait for HostCallbacks {
         MakeCurrent(self.disp, self.surf, self.surf, self.ctxt);
     }
 
-    fn on_alert(&self, _message: String) {}
-    fn on_load_started(&self) {}
-    fn on_load_ended(&self) {}
-    fn on_title_changed(&self, _title: String) {}
-    fn on_allow_navigation(&self, _url: String) -> bool {
+    fn on_alert(&self, _browser: BrowserHandle, _message: String) {}
+    fn on_load_started(&self, _browser: BrowserHandle) {}
+    fn on_load_ended(&self, _browser: BrowserHandle) {}
+    fn on_title_changed(&self, _browser: BrowserHandle, _title: String) {}
+    fn on_allow_navigation(&self, _browser: BrowserHandle, _url: String) -> bool {
         true
     }
-    fn on_url_changed(&self, url: String) {
+    fn on_url_changed(&self, _browser: BrowserHandle, url: String) {
         if let Ok(cstr) = CString::new(url.as_str()) {
             if let Some(url_update) = self.url_update.0 {
                 url_update(self.app, cstr.as_ptr());
@@ -380,19 +389,22 @@ impl HostTrait for HostCallbacks {
         }
     }
 
-    fn on_history_changed(&self, can_go_back: bool, can_go_forward: bool) {
+    fn on_history_changed(&self, _browser: BrowserHandle, can_go_back: bool, can_go_forward: bool) {
         if let Some(history_update) = self.history_update.0 {
             history_update(self.app, can_go_back, can_go_forward);
         }
     }
 
+    fn on_browser_created(&self, _browser: BrowserHandle) {}
+    fn on_browser_closed(&self, _browser: BrowserHandle) {}
+
     fn on_animating_changed(&self, _animating: bool) {}
 
     fn on_shutdown_complete(&self) {
         self.shut_down_complete.set(true);
     }
 
-    fn on_ime_state_changed(&self, show: bool) {
+    fn on_ime_state_changed(&self, _browser: BrowserHandle, show: bool) {
         if let Some(keyboard) = self.keyboard.0 {
             keyboard(self.app, show)
         }
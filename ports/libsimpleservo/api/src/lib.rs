@@ -27,7 +27,7 @@ use servo::webrender_api::ScrollLocation;
 use servo::webvr::{VRExternalShmemPtr, VRMainThreadHeartbeat, VRService, VRServiceManager};
 use servo::{self, gl, BrowserId, Servo};
 use servo_media::player::context as MediaPlayerContext;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::mem;
 use std::os::raw::c_void;
 use std::path::PathBuf;
@@ -42,11 +42,20 @@ thread_local! {
 /// and that perform_updates need to be called
 pub use servo::embedder_traits::EventLoopWaker;
 
+pub use servo::style_traits::media_features::{
+    ColorSchemePreference, DeviceFeatures, PointerCapabilities,
+};
+
+/// Identifies one of the browsers (top level browsing contexts, e.g. tabs) of a
+/// Servo instance. Handles are never reused.
+pub type BrowserHandle = u32;
+
 pub struct InitOptions {
     pub args: Vec<String>,
     pub url: Option<String>,
     pub coordinates: Coordinates,
     pub density: f32,
+    pub device_features: DeviceFeatures,
     pub vr_init: VRInitOptions,
     pub xr_discovery: Option<Box<dyn webxr_api::Discovery>>,
     pub enable_subpixel_text_antialiasing: bool,
@@ -83,6 +92,7 @@ impl Coordinates {
 }
 
 /// Callbacks. Implemented by embedder. Called by Servo.
+/// Callbacks about the content of a page are keyed by the browser showing it.
 pub trait HostTrait {
     /// Will be called from the thread used for the init call.
     /// Will be called when the GL buffer has been updated.
@@ -91,26 +101,33 @@ pub trait HostTrait {
     /// Time to make the targetted GL context current.
     fn make_current(&self);
     /// javascript window.alert()
-    fn on_alert(&self, msg: String);
+    fn on_alert(&self, browser: BrowserHandle, msg: String);
     /// Page starts loading.
     /// "Reload button" should be disabled.
     /// "Stop button" should be enabled.
     /// Throbber starts spinning.
-    fn on_load_started(&self);
+    fn on_load_started(&self, browser: BrowserHandle);
     /// Page has loaded.
     /// "Reload button" should be enabled.
     /// "Stop button" should be disabled.
     /// Throbber stops spinning.
-    fn on_load_ended(&self);
+    fn on_load_ended(&self, browser: BrowserHandle);
     /// Page title has changed.
-    fn on_title_changed(&self, title: String);
+    fn on_title_changed(&self, browser: BrowserHandle, title: String);
     /// Allow Navigation.
-    fn on_allow_navigation(&self, url: String) -> bool;
+    fn on_allow_navigation(&self, browser: BrowserHandle, url: String) -> bool;
     /// Page URL has changed.
-    fn on_url_changed(&self, url: String);
+    fn on_url_changed(&self, browser: BrowserHandle, url: String);
     /// Back/forward state has changed.
     /// Back/forward buttons need to be disabled/enabled.
-    fn on_history_changed(&self, can_go_back: bool, can_go_forward: bool);
+    fn on_history_changed(&self, browser: BrowserHandle, can_go_back: bool, can_go_forward: bool);
+    /// A browser has been created, either by new_browser() (including the
+    /// initial browser created by init()) or by content (window.open()).
+    /// The new browser is selected.
+    fn on_browser_created(&self, browser: BrowserHandle);
+    /// A browser has been closed by content (window.close()). If it was
+    /// selected, the most recently created remaining browser is selected.
+    fn on_browser_closed(&self, browser: BrowserHandle);
     /// Page animation state has changed. If animating, it's recommended
     /// that the embedder doesn't wait for the wake function to be called
     /// to call perform_updates. Usually, it means doing:
@@ -124,7 +141,7 @@ pub trait HostTrait {
     /// Servo finished shutting down.
     fn on_shutdown_complete(&self);
     /// A text input is focused.
-    fn on_ime_state_changed(&self, show: bool);
+    fn on_ime_state_changed(&self, browser: BrowserHandle, show: bool);
     /// Gets sytem clipboard contents
     fn get_clipboard_contents(&self) -> Option<String>;
     /// Sets system clipboard contents
@@ -135,15 +152,18 @@ pub struct ServoGlue {
     servo: Servo<ServoWindowCallbacks>,
     batch_mode: bool,
     callbacks: Rc<ServoWindowCallbacks>,
-    /// id of the top level browsing context. It is unique as tabs
-    /// are not supported yet. None until created.
-    browser_id: Option<BrowserId>,
-    // A rudimentary stack of "tabs".
-    // EmbedderMsg::BrowserCreated will push onto it.
-    // EmbedderMsg::CloseBrowser will pop from it,
-    // and exit if it is empty afterwards.
-    browsers: Vec<BrowserId>,
+    /// The open browsers, in creation order.
+    browsers: Vec<Browser>,
+    /// The browser that is painted and receives input events. None until
+    /// the first browser has been created, or after all have been closed.
+    selected_browser: Option<BrowserHandle>,
+    next_browser_handle: BrowserHandle,
     events: Vec<WindowEvent>,
+}
+
+struct Browser {
+    handle: BrowserHandle,
+    id: BrowserId,
     current_url: Option<ServoUrl>,
 }
 
@@ -193,6 +213,7 @@ pub fn init(
         gl: gl.clone(),
         coordinates: RefCell::new(init_opts.coordinates),
         density: init_opts.density,
+        device_features: Cell::new(init_opts.device_features),
         gl_context_pointer: init_opts.gl_context_pointer,
         native_display_pointer: init_opts.native_display_pointer,
     });
@@ -211,13 +232,12 @@ pub fn init(
             servo,
             batch_mode: false,
             callbacks: window_callbacks,
-            browser_id: None,
             browsers: vec![],
+            selected_browser: None,
+            next_browser_handle: 0,
             events: vec![],
-            current_url: Some(url.clone()),
         };
-        let browser_id = BrowserId::new();
-        let _ = servo_glue.process_event(WindowEvent::NewBrowser(url, browser_id));
+        let _ = servo_glue.open_browser(url);
         *s.borrow_mut() = Some(servo_glue);
     });
 
@@ -229,12 +249,97 @@ pub fn deinit() {
 }
 
 impl ServoGlue {
-    fn get_browser_id(&self) -> Result<BrowserId, &'static str> {
-        let browser_id = match self.browser_id {
-            Some(id) => id,
-            None => return Err("No BrowserId set yet."),
-        };
-        Ok(browser_id)
+    fn get_browser_id(&self, browser: BrowserHandle) -> Result<BrowserId, &'static str> {
+        self.browsers
+            .iter()
+            .find(|b| b.handle == browser)
+            .map(|b| b.id)
+            .ok_or("No such browser.")
+    }
+
+    fn get_browser_handle(&self, browser_id: Option<BrowserId>) -> Option<BrowserHandle> {
+        let browser_id = browser_id?;
+        self.browsers
+            .iter()
+            .find(|b| b.id == browser_id)
+            .map(|b| b.handle)
+    }
+
+    /// Input events are sent to the selected browser, so they can only
+    /// target that one. Input for another browser, e.g. one that has just
+    /// been closed, is dropped.
+    fn is_selected(&self, browser: BrowserHandle) -> bool {
+        if self.selected_browser == Some(browser) {
+            true
+        } else {
+            warn!("Dropping input for unselected browser {}", browser);
+            false
+        }
+    }
+
+    fn add_browser(&mut self, browser_id: BrowserId, url: Option<ServoUrl>) -> BrowserHandle {
+        let handle = self.next_browser_handle;
+        self.next_browser_handle += 1;
+        self.browsers.push(Browser {
+            handle,
+            id: browser_id,
+            current_url: url,
+        });
+        handle
+    }
+
+    fn remove_browser(&mut self, browser: BrowserHandle) {
+        self.browsers.retain(|b| b.handle != browser);
+        if self.selected_browser == Some(browser) {
+            self.selected_browser = None;
+            if let Some(last) = self.browsers.last() {
+                self.selected_browser = Some(last.handle);
+                self.events.push(WindowEvent::SelectBrowser(last.id));
+            }
+        }
+    }
+
+    fn open_browser(&mut self, url: ServoUrl) -> Result<BrowserHandle, &'static str> {
+        let browser_id = BrowserId::new();
+        let handle = self.add_browser(browser_id, Some(url.clone()));
+        self.process_event(WindowEvent::NewBrowser(url, browser_id))?;
+        Ok(handle)
+    }
+
+    /// Create a new browser and load an URL in it. This needs to be a valid url.
+    /// Will call on_browser_created once the browser exists, at which point
+    /// it becomes the selected browser.
+    pub fn new_browser(&mut self, url: &str) -> Result<BrowserHandle, &'static str> {
+        info!("new_browser: {}", url);
+        let url = ServoUrl::parse(url).map_err(|_| "Can't parse URL")?;
+        self.open_browser(url)
+    }
+
+    /// Paint a browser and send input events to it.
+    pub fn select_browser(&mut self, browser: BrowserHandle) -> Result<(), &'static str> {
+        info!("select_browser: {}", browser);
+        let browser_id = self.get_browser_id(browser)?;
+        self.selected_browser = Some(browser);
+        self.process_event(WindowEvent::SelectBrowser(browser_id))
+    }
+
+    /// Close a browser. If it was selected, the most recently created
+    /// remaining browser is selected. Closing the last browser shuts Servo
+    /// down, which will call on_shutdown_complete.
+    pub fn close_browser(&mut self, browser: BrowserHandle) -> Result<(), &'static str> {
+        info!("close_browser: {}", browser);
+        let browser_id = self.get_browser_id(browser)?;
+        self.events.push(WindowEvent::CloseBrowser(browser_id));
+        self.remove_browser(browser);
+        if self.browsers.is_empty() {
+            self.events.push(WindowEvent::Quit);
+        }
+        self.process_pending_events()
+    }
+
+    /// The browser that is painted and receives input events, if any.
+    pub fn selected_browser(&self) -> Option<BrowserHandle> {
+        self.selected_browser
     }
 
     /// Request shutdown. Will call on_shutdown_complete.
@@ -271,21 +376,21 @@ impl ServoGlue {
     }
 
     /// Load an URL. This needs to be a valid url.
-    pub fn load_uri(&mut self, url: &str) -> Result<(), &'static str> {
+    pub fn load_uri(&mut self, browser: BrowserHandle, url: &str) -> Result<(), &'static str> {
         info!("load_uri: {}", url);
         ServoUrl::parse(url)
             .map_err(|_| "Can't parse URL")
             .and_then(|url| {
-                let browser_id = self.get_browser_id()?;
+                let browser_id = self.get_browser_id(browser)?;
                 let event = WindowEvent::LoadUrl(browser_id, url);
                 self.process_event(event)
             })
     }
 
     /// Reload the page.
-    pub fn reload(&mut self) -> Result<(), &'static str> {
+    pub fn reload(&mut self, browser: BrowserHandle) -> Result<(), &'static str> {
         info!("reload");
-        let browser_id = self.get_browser_id()?;
+        let browser_id = self.get_browser_id(browser)?;
         let event = WindowEvent::Reload(browser_id);
         self.process_event(event)
     }
@@ -297,23 +402,24 @@ impl ServoGlue {
     }
 
     /// Stop loading the page.
-    pub fn stop(&mut self) -> Result<(), &'static str> {
+    pub fn stop(&mut self, browser: BrowserHandle) -> Result<(), &'static str> {
+        self.get_browser_id(browser)?;
         warn!("TODO can't stop won't stop");
         Ok(())
     }
 
     /// Go back in history.
-    pub fn go_back(&mut self) -> Result<(), &'static str> {
+    pub fn go_back(&mut self, browser: BrowserHandle) -> Result<(), &'static str> {
         info!("go_back");
-        let browser_id = self.get_browser_id()?;
+        let browser_id = self.get_browser_id(browser)?;
         let event = WindowEvent::Navigation(browser_id, TraversalDirection::Back(1));
         self.process_event(event)
     }
 
     /// Go forward in history.
-    pub fn go_forward(&mut self) -> Result<(), &'static str> {
+    pub fn go_forward(&mut self, browser: BrowserHandle) -> Result<(), &'static str> {
         info!("go_forward");
-        let browser_id = self.get_browser_id()?;
+        let browser_id = self.get_browser_id(browser)?;
         let event = WindowEvent::Navigation(browser_id, TraversalDirection::Forward(1));
         self.process_event(event)
     }
@@ -325,10 +431,28 @@ impl ServoGlue {
         self.process_event(WindowEvent::Resize)
     }
 
+    /// Let Servo know that the user preferences or input devices that media
+    /// queries can test for have changed.
+    pub fn set_device_features(&mut self, features: DeviceFeatures) -> Result<(), &'static str> {
+        info!("set_device_features");
+        self.callbacks.device_features.set(features);
+        self.process_event(WindowEvent::Resize)
+    }
+
     /// Start scrolling.
     /// x/y are scroll coordinates.
     /// dx/dy are scroll deltas.
-    pub fn scroll_start(&mut self, dx: f32, dy: f32, x: i32, y: i32) -> Result<(), &'static str> {
+    pub fn scroll_start(
+        &mut self,
+        browser: BrowserHandle,
+        dx: f32,
+        dy: f32,
+        x: i32,
+        y: i32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let delta = Vector2D::new(dx, dy);
         let scroll_location = ScrollLocation::Delta(delta);
         let event = WindowEvent::Scroll(scroll_location, Point2D::new(x, y), TouchEventType::Down);
@@ -338,7 +462,17 @@ impl ServoGlue {
     /// Scroll.
     /// x/y are scroll coordinates.
     /// dx/dy are scroll deltas.
-    pub fn scroll(&mut self, dx: f32, dy: f32, x: i32, y: i32) -> Result<(), &'static str> {
+    pub fn scroll(
+        &mut self,
+        browser: BrowserHandle,
+        dx: f32,
+        dy: f32,
+        x: i32,
+        y: i32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let delta = Vector2D::new(dx, dy);
         let scroll_location = ScrollLocation::Delta(delta);
         let event = WindowEvent::Scroll(scroll_location, Point2D::new(x, y), TouchEventType::Move);
@@ -348,7 +482,17 @@ impl ServoGlue {
     /// End scrolling.
     /// x/y are scroll coordinates.
     /// dx/dy are scroll deltas.
-    pub fn scroll_end(&mut self, dx: f32, dy: f32, x: i32, y: i32) -> Result<(), &'static str> {
+    pub fn scroll_end(
+        &mut self,
+        browser: BrowserHandle,
+        dx: f32,
+        dy: f32,
+        x: i32,
+        y: i32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let delta = Vector2D::new(dx, dy);
         let scroll_location = ScrollLocation::Delta(delta);
         let event = WindowEvent::Scroll(scroll_location, Point2D::new(x, y), TouchEventType::Up);
@@ -356,7 +500,16 @@ impl ServoGlue {
     }
 
     /// Touch event: press down
-    pub fn touch_down(&mut self, x: f32, y: f32, pointer_id: i32) -> Result<(), &'static str> {
+    pub fn touch_down(
+        &mut self,
+        browser: BrowserHandle,
+        x: f32,
+        y: f32,
+        pointer_id: i32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let event = WindowEvent::Touch(
             TouchEventType::Down,
             TouchId(pointer_id),
@@ -366,7 +519,16 @@ impl ServoGlue {
     }
 
     /// Touch event: move touching finger
-    pub fn touch_move(&mut self, x: f32, y: f32, pointer_id: i32) -> Result<(), &'static str> {
+    pub fn touch_move(
+        &mut self,
+        browser: BrowserHandle,
+        x: f32,
+        y: f32,
+        pointer_id: i32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let event = WindowEvent::Touch(
             TouchEventType::Move,
             TouchId(pointer_id),
@@ -376,7 +538,16 @@ impl ServoGlue {
     }
 
     /// Touch event: Lift touching finger
-    pub fn touch_up(&mut self, x: f32, y: f32, pointer_id: i32) -> Result<(), &'static str> {
+    pub fn touch_up(
+        &mut self,
+        browser: BrowserHandle,
+        x: f32,
+        y: f32,
+        pointer_id: i32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let event = WindowEvent::Touch(
             TouchEventType::Up,
             TouchId(pointer_id),
@@ -386,7 +557,16 @@ impl ServoGlue {
     }
 
     /// Cancel touch event
-    pub fn touch_cancel(&mut self, x: f32, y: f32, pointer_id: i32) -> Result<(), &'static str> {
+    pub fn touch_cancel(
+        &mut self,
+        browser: BrowserHandle,
+        x: f32,
+        y: f32,
+        pointer_id: i32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let event = WindowEvent::Touch(
             TouchEventType::Cancel,
             TouchId(pointer_id),
@@ -396,21 +576,47 @@ impl ServoGlue {
     }
 
     /// Register a mouse movement.
-    pub fn move_mouse(&mut self, x: f32, y: f32) -> Result<(), &'static str> {
+    pub fn move_mouse(
+        &mut self,
+        browser: BrowserHandle,
+        x: f32,
+        y: f32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let point = Point2D::new(x, y);
         let event = WindowEvent::MouseWindowMoveEventClass(point);
         self.process_event(event)
     }
 
     /// Register a mouse button press.
-    pub fn mouse_down(&mut self, x: f32, y: f32, button: MouseButton) -> Result<(), &'static str> {
+    pub fn mouse_down(
+        &mut self,
+        browser: BrowserHandle,
+        x: f32,
+        y: f32,
+        button: MouseButton,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let point = Point2D::new(x, y);
         let event = WindowEvent::MouseWindowEventClass(MouseWindowEvent::MouseDown(button, point));
         self.process_event(event)
     }
 
     /// Register a mouse button release.
-    pub fn mouse_up(&mut self, x: f32, y: f32, button: MouseButton) -> Result<(), &'static str> {
+    pub fn mouse_up(
+        &mut self,
+        browser: BrowserHandle,
+        x: f32,
+        y: f32,
+        button: MouseButton,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let point = Point2D::new(x, y);
         let event = WindowEvent::MouseWindowEventClass(MouseWindowEvent::MouseUp(button, point));
         self.process_event(event)
@@ -418,30 +624,63 @@ impl ServoGlue {
 
     /// Start pinchzoom.
     /// x/y are pinch origin coordinates.
-    pub fn pinchzoom_start(&mut self, factor: f32, _x: u32, _y: u32) -> Result<(), &'static str> {
+    pub fn pinchzoom_start(
+        &mut self,
+        browser: BrowserHandle,
+        factor: f32,
+        _x: u32,
+        _y: u32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         self.process_event(WindowEvent::PinchZoom(factor))
     }
 
     /// Pinchzoom.
     /// x/y are pinch origin coordinates.
-    pub fn pinchzoom(&mut self, factor: f32, _x: u32, _y: u32) -> Result<(), &'static str> {
+    pub fn pinchzoom(
+        &mut self,
+        browser: BrowserHandle,
+        factor: f32,
+        _x: u32,
+        _y: u32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         self.process_event(WindowEvent::PinchZoom(factor))
     }
 
     /// End pinchzoom.
     /// x/y are pinch origin coordinates.
-    pub fn pinchzoom_end(&mut self, factor: f32, _x: u32, _y: u32) -> Result<(), &'static str> {
+    pub fn pinchzoom_end(
+        &mut self,
+        browser: BrowserHandle,
+        factor: f32,
+        _x: u32,
+        _y: u32,
+    ) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         self.process_event(WindowEvent::PinchZoom(factor))
     }
 
     /// Perform a click.
-    pub fn click(&mut self, x: f32, y: f32) -> Result<(), &'static str> {
+    pub fn click(&mut self, browser: BrowserHandle, x: f32, y: f32) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let mouse_event = MouseWindowEvent::Click(MouseButton::Left, Point2D::new(x, y));
         let event = WindowEvent::MouseWindowEventClass(mouse_event);
         self.process_event(event)
     }
 
-    pub fn key_down(&mut self, key: Key) -> Result<(), &'static str> {
+    pub fn key_down(&mut self, browser: BrowserHandle, key: Key) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let key_event = KeyboardEvent {
             state: KeyState::Down,
             key,
@@ -450,7 +689,10 @@ impl ServoGlue {
         self.process_event(WindowEvent::Keyboard(key_event))
     }
 
-    pub fn key_up(&mut self, key: Key) -> Result<(), &'static str> {
+    pub fn key_up(&mut self, browser: BrowserHandle, key: Key) -> Result<(), &'static str> {
+        if !self.is_selected(browser) {
+            return Ok(());
+        }
         let key_event = KeyboardEvent {
             state: KeyState::Up,
             key,
@@ -461,6 +703,10 @@ impl ServoGlue {
 
     fn process_event(&mut self, event: WindowEvent) -> Result<(), &'static str> {
         self.events.push(event);
+        self.process_pending_events()
+    }
+
+    fn process_pending_events(&mut self) -> Result<(), &'static str> {
         if !self.batch_mode {
             self.perform_updates()
         } else {
@@ -470,47 +716,67 @@ impl ServoGlue {
 
     fn handle_servo_events(&mut self) -> Result<(), &'static str> {
         for (browser_id, event) in self.servo.get_events() {
+            let browser = self.get_browser_handle(browser_id);
             match event {
                 EmbedderMsg::ChangePageTitle(title) => {
-                    let fallback_title: String = if let Some(ref current_url) = self.current_url {
-                        current_url.to_string()
-                    } else {
-                        String::from("Untitled")
-                    };
-                    let title = match title {
-                        Some(ref title) if title.len() > 0 => &**title,
-                        _ => &fallback_title,
-                    };
-                    let title = format!("{} - Servo", title);
-                    self.callbacks.host_callbacks.on_title_changed(title);
+                    if let Some(browser) = browser {
+                        let current_url = self
+                            .browsers
+                            .iter()
+                            .find(|b| b.handle == browser)
+                            .and_then(|b| b.current_url.as_ref());
+                        let fallback_title: String = if let Some(current_url) = current_url {
+                            current_url.to_string()
+                        } else {
+                            String::from("Untitled")
+                        };
+                        let title = match title {
+                            Some(ref title) if title.len() > 0 => &**title,
+                            _ => &fallback_title,
+                        };
+                        let title = format!("{} - Servo", title);
+                        self.callbacks
+                            .host_callbacks
+                            .on_title_changed(browser, title);
+                    }
                 },
                 EmbedderMsg::AllowNavigationRequest(pipeline_id, url) => {
-                    if let Some(_browser_id) = browser_id {
+                    if let Some(browser) = browser {
                         let data: bool = self
                             .callbacks
                             .host_callbacks
-                            .on_allow_navigation(url.to_string());
+                            .on_allow_navigation(browser, url.to_string());
                         let window_event = WindowEvent::AllowNavigationResponse(pipeline_id, data);
                         self.events.push(window_event);
                         let _ = self.perform_updates();
                     }
                 },
                 EmbedderMsg::HistoryChanged(entries, current) => {
-                    let can_go_back = current > 0;
-                    let can_go_forward = current < entries.len() - 1;
-                    self.callbacks
-                        .host_callbacks
-                        .on_history_changed(can_go_back, can_go_forward);
-                    self.callbacks
-                        .host_callbacks
-                        .on_url_changed(entries[current].clone().to_string());
-                    self.current_url = Some(entries[current].clone());
+                    if let Some(browser) = browser {
+                        let can_go_back = current > 0;
+                        let can_go_forward = current < entries.len() - 1;
+                        self.callbacks.host_callbacks.on_history_changed(
+                            browser,
+                            can_go_back,
+                            can_go_forward,
+                        );
+                        self.callbacks
+                            .host_callbacks
+                            .on_url_changed(browser, entries[current].clone().to_string());
+                        if let Some(b) = self.browsers.iter_mut().find(|b| b.handle == browser) {
+                            b.current_url = Some(entries[current].clone());
+                        }
+                    }
                 },
                 EmbedderMsg::LoadStart => {
-                    self.callbacks.host_callbacks.on_load_started();
+                    if let Some(browser) = browser {
+                        self.callbacks.host_callbacks.on_load_started(browser);
+                    }
                 },
                 EmbedderMsg::LoadComplete => {
-                    self.callbacks.host_callbacks.on_load_ended();
+                    if let Some(browser) = browser {
+                        self.callbacks.host_callbacks.on_load_ended(browser);
+                    }
                 },
                 EmbedderMsg::GetSelectedBluetoothDevice(_, sender) => {
                     let _ = sender.send(None);
@@ -520,9 +786,21 @@ impl ServoGlue {
                 },
                 EmbedderMsg::Alert(message, sender) => {
                     info!("Alert: {}", message);
-                    self.callbacks.host_callbacks.on_alert(message);
+                    if let Some(browser) = browser {
+                        self.callbacks.host_callbacks.on_alert(browser, message);
+                    }
                     let _ = sender.send(());
                 },
+                EmbedderMsg::Confirm(message, sender) => {
+                    // FIXME: Let the host display confirm dialogs.
+                    info!("Confirm: {}", message);
+                    let _ = sender.send(false);
+                },
+                EmbedderMsg::Prompt(message, _default, sender) => {
+                    // FIXME: Let the host display prompt dialogs.
+                    info!("Prompt: {}", message);
+                    let _ = sender.send(None);
+                },
                 EmbedderMsg::AllowOpeningBrowser(response_chan) => {
                     // Note: would be a place to handle pop-ups config.
                     // see Step 7 of #the-rules-for-choosing-a-browsing-context-given-a-browsing-context-name
@@ -531,12 +809,17 @@ impl ServoGlue {
                     };
                 },
                 EmbedderMsg::BrowserCreated(new_browser_id) => {
-                    // TODO: properly handle a new "tab"
-                    self.browsers.push(new_browser_id);
-                    if self.browser_id.is_none() {
-                        self.browser_id = Some(new_browser_id);
-                    }
+                    // Browsers created by new_browser() are already known,
+                    // the other ones have been opened by content.
+                    let new_browser = match self.get_browser_handle(Some(new_browser_id)) {
+                        Some(new_browser) => new_browser,
+                        None => self.add_browser(new_browser_id, None),
+                    };
+                    self.selected_browser = Some(new_browser);
                     self.events.push(WindowEvent::SelectBrowser(new_browser_id));
+                    self.callbacks
+                        .host_callbacks
+                        .on_browser_created(new_browser);
                 },
                 EmbedderMsg::GetClipboardContents(sender) => {
                     let contents = self.callbacks.host_callbacks.get_clipboard_contents();
@@ -546,24 +829,32 @@ impl ServoGlue {
                     self.callbacks.host_callbacks.set_clipboard_contents(text);
                 },
                 EmbedderMsg::CloseBrowser => {
-                    // TODO: close the appropriate "tab".
-                    let _ = self.browsers.pop();
-                    if let Some(prev_browser_id) = self.browsers.last() {
-                        self.browser_id = Some(*prev_browser_id);
-                        self.events
-                            .push(WindowEvent::SelectBrowser(*prev_browser_id));
+                    if let Some(browser) = browser {
+                        self.remove_browser(browser);
+                        self.callbacks.host_callbacks.on_browser_closed(browser);
+                        if self.browsers.is_empty() {
+                            self.events.push(WindowEvent::Quit);
+                        }
                     } else {
-                        self.events.push(WindowEvent::Quit);
+                        warn!("Closing unknown browser {:?}", browser_id);
                     }
                 },
                 EmbedderMsg::Shutdown => {
                     self.callbacks.host_callbacks.on_shutdown_complete();
                 },
                 EmbedderMsg::ShowIME(..) => {
-                    self.callbacks.host_callbacks.on_ime_state_changed(true);
+                    if let Some(browser) = browser {
+                        self.callbacks
+                            .host_callbacks
+                            .on_ime_state_changed(browser, true);
+                    }
                 },
                 EmbedderMsg::HideIME => {
-                    self.callbacks.host_callbacks.on_ime_state_changed(false);
+                    if let Some(browser) = browser {
+                        self.callbacks
+                            .host_callbacks
+                            .on_ime_state_changed(browser, false);
+                    }
                 },
                 EmbedderMsg::Status(..) |
                 EmbedderMsg::SelectFiles(..) |
@@ -574,6 +865,8 @@ impl ServoGlue {
                 EmbedderMsg::NewFavicon(..) |
                 EmbedderMsg::HeadParsed |
                 EmbedderMsg::SetFullscreenState(..) |
+                EmbedderMsg::SetMaximizedState(..) |
+                EmbedderMsg::SetMinimizedState(..) |
                 EmbedderMsg::Panic(..) |
                 EmbedderMsg::ReportProfile(..) => {},
             }
@@ -595,6 +888,7 @@ struct ServoWindowCallbacks {
     host_callbacks: Box<dyn HostTrait>,
     coordinates: RefCell<Coordinates>,
     density: f32,
+    device_features: Cell<DeviceFeatures>,
     gl_context_pointer: Option<*const c_void>,
     native_display_pointer: Option<*const c_void>,
 }
@@ -678,6 +972,10 @@ impl WindowMethods for ServoWindowCallbacks {
         }
     }
 
+    fn get_device_features(&self) -> DeviceFeatures {
+        self.device_features.get()
+    }
+
     fn get_gl_context(&self) -> MediaPlayerContext::GlContext {
         match self.gl_context_pointer {
             Some(context) => MediaPlayerContext::GlContext::Egl(context as usize),
@@ -732,6 +1030,7 @@ impl ResourceReaderMethods for ResourceReaderInstance {
             Resource::MediaControlsJS => {
                 &include_bytes!("../../../../resources/media-controls.js")[..]
             },
+            Resource::DebuggerJS => &include_bytes!("../../../../resources/debugger.js")[..],
         })
     }
 
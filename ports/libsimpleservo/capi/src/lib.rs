@@ -15,7 +15,10 @@ use backtrace::Backtrace;
 #[cfg(not(target_os = "windows"))]
 use env_logger;
 use simpleservo::{self, gl_glue, ServoGlue, SERVO};
-use simpleservo::{Coordinates, EventLoopWaker, HostTrait, InitOptions, VRInitOptions};
+use simpleservo::{
+    BrowserHandle, Coordinates, DeviceFeatures, EventLoopWaker, HostTrait, InitOptions,
+    VRInitOptions,
+};
 use std::ffi::{CStr, CString};
 #[cfg(target_os = "windows")]
 use std::mem;
@@ -182,16 +185,17 @@ fn do_redirect_stdout_stderr() -> Result<(), ()> {
     Ok(())
 }
 
-fn call<F>(f: F)
+fn call<T, F>(f: F) -> T
 where
-    F: Fn(&mut ServoGlue) -> Result<(), &'static str>,
+    F: FnOnce(&mut ServoGlue) -> Result<T, &'static str>,
 {
-    if let Err(e) = SERVO.with(|s| match s.borrow_mut().as_mut() {
+    match SERVO.with(|s| match s.borrow_mut().as_mut() {
         Some(ref mut s) => (f)(s),
         None => Err("Servo not available in this thread"),
     }) {
-        panic!(e);
-    };
+        Ok(r) => r,
+        Err(e) => panic!(e),
+    }
 }
 
 /// Callback used by Servo internals
@@ -199,16 +203,18 @@ where
 pub struct CHostCallbacks {
     pub flush: extern "C" fn(),
     pub make_current: extern "C" fn(),
-    pub on_alert: extern "C" fn(message: *const c_char),
-    pub on_load_started: extern "C" fn(),
-    pub on_load_ended: extern "C" fn(),
-    pub on_title_changed: extern "C" fn(title: *const c_char),
-    pub on_allow_navigation: extern "C" fn(url: *const c_char) -> bool,
-    pub on_url_changed: extern "C" fn(url: *const c_char),
-    pub on_history_changed: extern "C" fn(can_go_back: bool, can_go_forward: bool),
+    pub on_alert: extern "C" fn(browser: u32, message: *const c_char),
+    pub on_load_started: extern "C" fn(browser: u32),
+    pub on_load_ended: extern "C" fn(browser: u32),
+    pub on_title_changed: extern "C" fn(browser: u32, title: *const c_char),
+    pub on_allow_navigation: extern "C" fn(browser: u32, url: *const c_char) -> bool,
+    pub on_url_changed: extern "C" fn(browser: u32, url: *const c_char),
+    pub on_history_changed: extern "C" fn(browser: u32, can_go_back: bool, can_go_forward: bool),
+    pub on_browser_created: extern "C" fn(browser: u32),
+    pub on_browser_closed: extern "C" fn(browser: u32),
     pub on_animating_changed: extern "C" fn(animating: bool),
     pub on_shutdown_complete: extern "C" fn(),
-    pub on_ime_state_changed: extern "C" fn(show: bool),
+    pub on_ime_state_changed: extern "C" fn(browser: u32, show: bool),
     pub get_clipboard_contents: extern "C" fn() -> *const c_char,
     pub set_clipboard_contents: extern "C" fn(contents: *const c_char),
 }
@@ -303,6 +309,7 @@ unsafe fn init(
         url,
         coordinates,
         density: opts.density,
+        device_features: DeviceFeatures::default(),
         vr_init: if opts.vr_pointer.is_null() {
             VRInitOptions::None
         } else {
@@ -399,28 +406,54 @@ pub extern "C" fn perform_updates() {
 }
 
 #[no_mangle]
-pub extern "C" fn load_uri(url: *const c_char) {
+pub extern "C" fn new_browser(url: *const c_char) -> u32 {
+    catch_any_panic(|| {
+        debug!("new_browser");
+        let url = unsafe { CStr::from_ptr(url) };
+        let url = url.to_str().expect("Can't read string");
+        call(|s| s.new_browser(url))
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn select_browser(browser: u32) {
+    catch_any_panic(|| {
+        debug!("select_browser");
+        call(|s| s.select_browser(browser));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn close_browser(browser: u32) {
+    catch_any_panic(|| {
+        debug!("close_browser");
+        call(|s| s.close_browser(browser));
+    });
+}
+
+#[no_mangle]
+pub extern "C" fn load_uri(browser: u32, url: *const c_char) {
     catch_any_panic(|| {
         debug!("load_url");
         let url = unsafe { CStr::from_ptr(url) };
         let url = url.to_str().expect("Can't read string");
-        call(|s| s.load_uri(url));
+        call(|s| s.load_uri(browser, url));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn reload() {
+pub extern "C" fn reload(browser: u32) {
     catch_any_panic(|| {
         debug!("reload");
-        call(|s| s.reload());
+        call(|s| s.reload(browser));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn stop() {
+pub extern "C" fn stop(browser: u32) {
     catch_any_panic(|| {
         debug!("stop");
-        call(|s| s.stop());
+        call(|s| s.stop(browser));
     });
 }
 
@@ -433,106 +466,106 @@ pub extern "C" fn refresh() {
 }
 
 #[no_mangle]
-pub extern "C" fn go_back() {
+pub extern "C" fn go_back(browser: u32) {
     catch_any_panic(|| {
         debug!("go_back");
-        call(|s| s.go_back());
+        call(|s| s.go_back(browser));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn go_forward() {
+pub extern "C" fn go_forward(browser: u32) {
     catch_any_panic(|| {
         debug!("go_forward");
-        call(|s| s.go_forward());
+        call(|s| s.go_forward(browser));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn scroll_start(dx: i32, dy: i32, x: i32, y: i32) {
+pub extern "C" fn scroll_start(browser: u32, dx: i32, dy: i32, x: i32, y: i32) {
     catch_any_panic(|| {
         debug!("scroll_start");
-        call(|s| s.scroll_start(dx as f32, dy as f32, x, y));
+        call(|s| s.scroll_start(browser, dx as f32, dy as f32, x, y));
     })
 }
 
 #[no_mangle]
-pub extern "C" fn scroll_end(dx: i32, dy: i32, x: i32, y: i32) {
+pub extern "C" fn scroll_end(browser: u32, dx: i32, dy: i32, x: i32, y: i32) {
     catch_any_panic(|| {
         debug!("scroll_end");
-        call(|s| s.scroll_end(dx as f32, dy as f32, x, y));
+        call(|s| s.scroll_end(browser, dx as f32, dy as f32, x, y));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn scroll(dx: i32, dy: i32, x: i32, y: i32) {
+pub extern "C" fn scroll(browser: u32, dx: i32, dy: i32, x: i32, y: i32) {
     catch_any_panic(|| {
         debug!("scroll");
-        call(|s| s.scroll(dx as f32, dy as f32, x, y));
+        call(|s| s.scroll(browser, dx as f32, dy as f32, x, y));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn touch_down(x: f32, y: f32, pointer_id: i32) {
+pub extern "C" fn touch_down(browser: u32, x: f32, y: f32, pointer_id: i32) {
     catch_any_panic(|| {
         debug!("touch down");
-        call(|s| s.touch_down(x, y, pointer_id));
+        call(|s| s.touch_down(browser, x, y, pointer_id));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn touch_up(x: f32, y: f32, pointer_id: i32) {
+pub extern "C" fn touch_up(browser: u32, x: f32, y: f32, pointer_id: i32) {
     catch_any_panic(|| {
         debug!("touch up");
-        call(|s| s.touch_up(x, y, pointer_id));
+        call(|s| s.touch_up(browser, x, y, pointer_id));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn touch_move(x: f32, y: f32, pointer_id: i32) {
+pub extern "C" fn touch_move(browser: u32, x: f32, y: f32, pointer_id: i32) {
     catch_any_panic(|| {
         debug!("touch move");
-        call(|s| s.touch_move(x, y, pointer_id));
+        call(|s| s.touch_move(browser, x, y, pointer_id));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn touch_cancel(x: f32, y: f32, pointer_id: i32) {
+pub extern "C" fn touch_cancel(browser: u32, x: f32, y: f32, pointer_id: i32) {
     catch_any_panic(|| {
         debug!("touch cancel");
-        call(|s| s.touch_cancel(x, y, pointer_id));
+        call(|s| s.touch_cancel(browser, x, y, pointer_id));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn pinchzoom_start(factor: f32, x: i32, y: i32) {
+pub extern "C" fn pinchzoom_start(browser: u32, factor: f32, x: i32, y: i32) {
     catch_any_panic(|| {
         debug!("pinchzoom_start");
-        call(|s| s.pinchzoom_start(factor, x as u32, y as u32));
+        call(|s| s.pinchzoom_start(browser, factor, x as u32, y as u32));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn pinchzoom(factor: f32, x: i32, y: i32) {
+pub extern "C" fn pinchzoom(browser: u32, factor: f32, x: i32, y: i32) {
     catch_any_panic(|| {
         debug!("pinchzoom");
-        call(|s| s.pinchzoom(factor, x as u32, y as u32));
+        call(|s| s.pinchzoom(browser, factor, x as u32, y as u32));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn pinchzoom_end(factor: f32, x: i32, y: i32) {
+pub extern "C" fn pinchzoom_end(browser: u32, factor: f32, x: i32, y: i32) {
     catch_any_panic(|| {
         debug!("pinchzoom_end");
-        call(|s| s.pinchzoom_end(factor, x as u32, y as u32));
+        call(|s| s.pinchzoom_end(browser, factor, x as u32, y as u32));
     });
 }
 
 #[no_mangle]
-pub extern "C" fn click(x: i32, y: i32) {
+pub extern "C" fn click(browser: u32, x: i32, y: i32) {
     catch_any_panic(|| {
         debug!("click");
-        call(|s| s.click(x as f32, y as f32));
+        call(|s| s.click(browser, x as f32, y as f32));
     });
 }
 
@@ -572,43 +605,53 @@ impl HostTrait for HostCallbacks {
         (self.0.make_current)();
     }
 
-    fn on_alert(&self, message: String) {
+    fn on_alert(&self, browser: BrowserHandle, message: String) {
         debug!("on_alert");
         let message = CString::new(message).expect("Can't create string");
-        (self.0.on_alert)(message.as_ptr());
+        (self.0.on_alert)(browser, message.as_ptr());
     }
 
-    fn on_load_started(&self) {
+    fn on_load_started(&self, browser: BrowserHandle) {
         debug!("on_load_ended");
-        (self.0.on_load_started)();
+        (self.0.on_load_started)(browser);
     }
 
-    fn on_load_ended(&self) {
+    fn on_load_ended(&self, browser: BrowserHandle) {
         debug!("on_load_ended");
-        (self.0.on_load_ended)();
+        (self.0.on_load_ended)(browser);
     }
 
-    fn on_title_changed(&self, title: String) {
+    fn on_title_changed(&self, browser: BrowserHandle, title: String) {
         debug!("on_title_changed");
         let title = CString::new(title).expect("Can't create string");
-        (self.0.on_title_changed)(title.as_ptr());
+        (self.0.on_title_changed)(browser, title.as_ptr());
     }
 
-    fn on_allow_navigation(&self, url: String) -> bool {
+    fn on_allow_navigation(&self, browser: BrowserHandle, url: String) -> bool {
         debug!("on_allow_navigation");
         let url = CString::new(url).expect("Can't create string");
-        (self.0.on_allow_navigation)(url.as_ptr())
+        (self.0.on_allow_navigation)(browser, url.as_ptr())
     }
 
-    fn on_url_changed(&self, url: String) {
+    fn on_url_changed(&self, browser: BrowserHandle, url: String) {
         debug!("on_url_changed");
         let url = CString::new(url).expect("Can't create string");
-        (self.0.on_url_changed)(url.as_ptr());
+        (self.0.on_url_changed)(browser, url.as_ptr());
     }
 
-    fn on_history_changed(&self, can_go_back: bool, can_go_forward: bool) {
+    fn on_history_changed(&self, browser: BrowserHandle, can_go_back: bool, can_go_forward: bool) {
         debug!("on_history_changed");
-        (self.0.on_history_changed)(can_go_back, can_go_forward);
+        (self.0.on_history_changed)(browser, can_go_back, can_go_forward);
+    }
+
+    fn on_browser_created(&self, browser: BrowserHandle) {
+        debug!("on_browser_created");
+        (self.0.on_browser_created)(browser);
+    }
+
+    fn on_browser_closed(&self, browser: BrowserHandle) {
+        debug!("on_browser_closed");
+        (self.0.on_browser_closed)(browser);
     }
 
     fn on_animating_changed(&self, animating: bool) {
@@ -621,9 +664,9 @@ impl HostTrait for HostCallbacks {
         (self.0.on_shutdown_complete)();
     }
 
-    fn on_ime_state_changed(&self, show: bool) {
+    fn on_ime_state_changed(&self, browser: BrowserHandle, show: bool) {
         debug!("on_ime_state_changed");
-        (self.0.on_ime_state_changed)(show);
+        (self.0.on_ime_state_changed)(browser, show);
     }
 
     fn get_clipboard_contents(&self) -> Option<String> {
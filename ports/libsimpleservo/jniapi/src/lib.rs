@@ -14,7 +14,10 @@ use jni::{errors, JNIEnv, JavaVM};
 use libc::{dup2, pipe, read};
 use log::Level;
 use simpleservo::{self, gl_glue, ServoGlue, SERVO};
-use simpleservo::{Coordinates, EventLoopWaker, HostTrait, InitOptions, VRInitOptions};
+use simpleservo::{
+    BrowserHandle, Coordinates, DeviceFeatures, EventLoopWaker, HostTrait, InitOptions,
+    PointerCapabilities, VRInitOptions,
+};
 use std::os::raw::{c_char, c_int, c_void};
 use std::ptr::{null, null_mut};
 use std::sync::Arc;
@@ -25,18 +28,22 @@ struct HostCallbacks {
     jvm: JavaVM,
 }
 
-fn call<F>(env: &JNIEnv, f: F)
+fn call<T, F>(env: &JNIEnv, f: F) -> Option<T>
 where
-    F: Fn(&mut ServoGlue) -> Result<(), &str>,
+    F: FnOnce(&mut ServoGlue) -> Result<T, &str>,
 {
     SERVO.with(|s| {
-        if let Err(error) = match s.borrow_mut().as_mut() {
+        match match s.borrow_mut().as_mut() {
             Some(ref mut s) => (f)(s),
             None => Err("Servo not available in this thread"),
         } {
-            throw(env, error);
+            Ok(r) => Some(r),
+            Err(error) => {
+                throw(env, error);
+                None
+            },
         }
-    });
+    })
 }
 
 #[no_mangle]
@@ -149,12 +156,56 @@ pub fn Java_org_mozilla_servoview_JNIServo_performUpdates(env: JNIEnv, _class: J
 }
 
 #[no_mangle]
-pub fn Java_org_mozilla_servoview_JNIServo_loadUri(env: JNIEnv, _class: JClass, url: JString) {
+pub fn Java_org_mozilla_servoview_JNIServo_newBrowser(
+    env: JNIEnv,
+    _class: JClass,
+    url: JString,
+) -> jint {
+    debug!("newBrowser");
+    match env.get_string(url) {
+        Ok(url) => {
+            let url: String = url.into();
+            call(&env, |s| s.new_browser(&url)).map_or(-1, |browser| browser as jint)
+        },
+        Err(_) => {
+            throw(&env, "Failed to convert Java string");
+            -1
+        },
+    }
+}
+
+#[no_mangle]
+pub fn Java_org_mozilla_servoview_JNIServo_selectBrowser(
+    env: JNIEnv,
+    _class: JClass,
+    browser: jint,
+) {
+    debug!("selectBrowser");
+    call(&env, |s| s.select_browser(browser as BrowserHandle));
+}
+
+#[no_mangle]
+pub fn Java_org_mozilla_servoview_JNIServo_closeBrowser(
+    env: JNIEnv,
+    _class: JClass,
+    browser: jint,
+) {
+    debug!("closeBrowser");
+    call(&env, |s| s.close_browser(browser as BrowserHandle));
+}
+
+#[no_mangle]
+pub fn Java_org_mozilla_servoview_JNIServo_loadUri(
+    env: JNIEnv,
+    _class: JClass,
+    browser: jint,
+    url: JString,
+) {
     debug!("loadUri");
     match env.get_string(url) {
         Ok(url) => {
             let url: String = url.into();
-            call(&env, |s| s.load_uri(&url));
+            call(&env, |s| s.load_uri(browser as BrowserHandle, &url));
         },
         Err(_) => {
             throw(&env, "Failed to convert Java string");
@@ -163,15 +214,15 @@ pub fn Java_org_mozilla_servoview_JNIServo_loadUri(env: JNIEnv, _class: JClass,
 }
 
 #[no_mangle]
-pub fn Java_org_mozilla_servoview_JNIServo_reload(env: JNIEnv, _class: JClass) {
+pub fn Java_org_mozilla_servoview_JNIServo_reload(env: JNIEnv, _class: JClass, browser: jint) {
     debug!("reload");
-    call(&env, |s| s.reload());
+    call(&env, |s| s.reload(browser as BrowserHandle));
 }
 
 #[no_mangle]
-pub fn Java_org_mozilla_servoview_JNIServo_stop(env: JNIEnv, _class: JClass) {
+pub fn Java_org_mozilla_servoview_JNIServo_stop(env: JNIEnv, _class: JClass, browser: jint) {
     debug!("stop");
-    call(&env, |s| s.stop());
+    call(&env, |s| s.stop(browser as BrowserHandle));
 }
 
 #[no_mangle]
@@ -181,21 +232,22 @@ pub fn Java_org_mozilla_servoview_JNIServo_refresh(env: JNIEnv, _class: JClass)
 }
 
 #[no_mangle]
-pub fn Java_org_mozilla_servoview_JNIServo_goBack(env: JNIEnv, _class: JClass) {
+pub fn Java_org_mozilla_servoview_JNIServo_goBack(env: JNIEnv, _class: JClass, browser: jint) {
     debug!("goBack");
-    call(&env, |s| s.go_back());
+    call(&env, |s| s.go_back(browser as BrowserHandle));
 }
 
 #[no_mangle]
-pub fn Java_org_mozilla_servoview_JNIServo_goForward(env: JNIEnv, _class: JClass) {
+pub fn Java_org_mozilla_servoview_JNIServo_goForward(env: JNIEnv, _class: JClass, browser: jint) {
     debug!("goForward");
-    call(&env, |s| s.go_forward());
+    call(&env, |s| s.go_forward(browser as BrowserHandle));
 }
 
 #[no_mangle]
 pub fn Java_org_mozilla_servoview_JNIServo_scrollStart(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     dx: jint,
     dy: jint,
     x: jint,
@@ -203,7 +255,13 @@ pub fn Java_org_mozilla_servoview_JNIServo_scrollStart(
 ) {
     debug!("scrollStart");
     call(&env, |s| {
-        s.scroll_start(dx as f32, dy as f32, x as i32, y as i32)
+        s.scroll_start(
+            browser as BrowserHandle,
+            dx as f32,
+            dy as f32,
+            x as i32,
+            y as i32,
+        )
     });
 }
 
@@ -211,6 +269,7 @@ pub fn Java_org_mozilla_servoview_JNIServo_scrollStart(
 pub fn Java_org_mozilla_servoview_JNIServo_scrollEnd(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     dx: jint,
     dy: jint,
     x: jint,
@@ -218,7 +277,13 @@ pub fn Java_org_mozilla_servoview_JNIServo_scrollEnd(
 ) {
     debug!("scrollEnd");
     call(&env, |s| {
-        s.scroll_end(dx as f32, dy as f32, x as i32, y as i32)
+        s.scroll_end(
+            browser as BrowserHandle,
+            dx as f32,
+            dy as f32,
+            x as i32,
+            y as i32,
+        )
     });
 }
 
@@ -226,74 +291,96 @@ pub fn Java_org_mozilla_servoview_JNIServo_scrollEnd(
 pub fn Java_org_mozilla_servoview_JNIServo_scroll(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     dx: jint,
     dy: jint,
     x: jint,
     y: jint,
 ) {
     debug!("scroll");
-    call(&env, |s| s.scroll(dx as f32, dy as f32, x as i32, y as i32));
+    call(&env, |s| {
+        s.scroll(
+            browser as BrowserHandle,
+            dx as f32,
+            dy as f32,
+            x as i32,
+            y as i32,
+        )
+    });
 }
 
 #[no_mangle]
 pub fn Java_org_mozilla_servoview_JNIServo_touchDown(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     x: jfloat,
     y: jfloat,
     pointer_id: jint,
 ) {
     debug!("touchDown");
-    call(&env, |s| s.touch_down(x, y, pointer_id as i32));
+    call(&env, |s| {
+        s.touch_down(browser as BrowserHandle, x, y, pointer_id as i32)
+    });
 }
 
 #[no_mangle]
 pub fn Java_org_mozilla_servoview_JNIServo_touchUp(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     x: jfloat,
     y: jfloat,
     pointer_id: jint,
 ) {
     debug!("touchUp");
-    call(&env, |s| s.touch_up(x, y, pointer_id as i32));
+    call(&env, |s| {
+        s.touch_up(browser as BrowserHandle, x, y, pointer_id as i32)
+    });
 }
 
 #[no_mangle]
 pub fn Java_org_mozilla_servoview_JNIServo_touchMove(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     x: jfloat,
     y: jfloat,
     pointer_id: jint,
 ) {
     debug!("touchMove");
-    call(&env, |s| s.touch_move(x, y, pointer_id as i32));
+    call(&env, |s| {
+        s.touch_move(browser as BrowserHandle, x, y, pointer_id as i32)
+    });
 }
 
 #[no_mangle]
 pub fn Java_org_mozilla_servoview_JNIServo_touchCancel(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     x: jfloat,
     y: jfloat,
     pointer_id: jint,
 ) {
     debug!("touchCancel");
-    call(&env, |s| s.touch_cancel(x, y, pointer_id as i32));
+    call(&env, |s| {
+        s.touch_cancel(browser as BrowserHandle, x, y, pointer_id as i32)
+    });
 }
 
 #[no_mangle]
 pub fn Java_org_mozilla_servoview_JNIServo_pinchZoomStart(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     factor: jfloat,
     x: jint,
     y: jint,
 ) {
     debug!("pinchZoomStart");
     call(&env, |s| {
-        s.pinchzoom_start(factor as f32, x as u32, y as u32)
+        s.pinchzoom_start(browser as BrowserHandle, factor as f32, x as u32, y as u32)
     });
 }
 
@@ -301,30 +388,44 @@ pub fn Java_org_mozilla_servoview_JNIServo_pinchZoomStart(
 pub fn Java_org_mozilla_servoview_JNIServo_pinchZoom(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     factor: jfloat,
     x: jint,
     y: jint,
 ) {
     debug!("pinchZoom");
-    call(&env, |s| s.pinchzoom(factor as f32, x as u32, y as u32));
+    call(&env, |s| {
+        s.pinchzoom(browser as BrowserHandle, factor as f32, x as u32, y as u32)
+    });
 }
 
 #[no_mangle]
 pub fn Java_org_mozilla_servoview_JNIServo_pinchZoomEnd(
     env: JNIEnv,
     _: JClass,
+    browser: jint,
     factor: jfloat,
     x: jint,
     y: jint,
 ) {
     debug!("pinchZoomEnd");
-    call(&env, |s| s.pinchzoom_end(factor as f32, x as u32, y as u32));
+    call(&env, |s| {
+        s.pinchzoom_end(browser as BrowserHandle, factor as f32, x as u32, y as u32)
+    });
 }
 
 #[no_mangle]
-pub fn Java_org_mozilla_servoview_JNIServo_click(env: JNIEnv, _: JClass, x: jint, y: jint) {
+pub fn Java_org_mozilla_servoview_JNIServo_click(
+    env: JNIEnv,
+    _: JClass,
+    browser: jint,
+    x: jint,
+    y: jint,
+) {
     debug!("click");
-    call(&env, |s| s.click(x as f32, y as f32));
+    call(&env, |s| {
+        s.click(browser as BrowserHandle, x as f32, y as f32)
+    });
 }
 
 pub struct WakeupCallback {
@@ -376,7 +477,7 @@ impl HostTrait for HostCallbacks {
             .unwrap();
     }
 
-    fn on_alert(&self, message: String) {
+    fn on_alert(&self, browser: BrowserHandle, message: String) {
         debug!("on_alert");
         let env = self.jvm.get_env().unwrap();
         let s = match new_string(&env, &message) {
@@ -387,23 +488,25 @@ impl HostTrait for HostCallbacks {
         env.call_method(
             self.callbacks.as_obj(),
             "onAlert",
-            "(Ljava/lang/String;)V",
-            &[s],
+            "(ILjava/lang/String;)V",
+            &[JValue::Int(browser as jint), s],
         )
         .unwrap();
     }
 
-    fn on_load_started(&self) {
+    fn on_load_started(&self, browser: BrowserHandle) {
         debug!("on_load_started");
         let env = self.jvm.get_env().unwrap();
-        env.call_method(self.callbacks.as_obj(), "onLoadStarted", "()V", &[])
+        let browser = JValue::Int(browser as jint);
+        env.call_method(self.callbacks.as_obj(), "onLoadStarted", "(I)V", &[browser])
             .unwrap();
     }
 
-    fn on_load_ended(&self) {
+    fn on_load_ended(&self, browser: BrowserHandle) {
         debug!("on_load_ended");
         let env = self.jvm.get_env().unwrap();
-        env.call_method(self.callbacks.as_obj(), "onLoadEnded", "()V", &[])
+        let browser = JValue::Int(browser as jint);
+        env.call_method(self.callbacks.as_obj(), "onLoadEnded", "(I)V", &[browser])
             .unwrap();
     }
 
@@ -414,7 +517,7 @@ impl HostTrait for HostCallbacks {
             .unwrap();
     }
 
-    fn on_title_changed(&self, title: String) {
+    fn on_title_changed(&self, browser: BrowserHandle, title: String) {
         debug!("on_title_changed");
         let env = self.jvm.get_env().unwrap();
         let s = match new_string(&env, &title) {
@@ -425,13 +528,13 @@ impl HostTrait for HostCallbacks {
         env.call_method(
             self.callbacks.as_obj(),
             "onTitleChanged",
-            "(Ljava/lang/String;)V",
-            &[s],
+            "(ILjava/lang/String;)V",
+            &[JValue::Int(browser as jint), s],
         )
         .unwrap();
     }
 
-    fn on_allow_navigation(&self, url: String) -> bool {
+    fn on_allow_navigation(&self, browser: BrowserHandle, url: String) -> bool {
         debug!("on_allow_navigation");
         let env = self.jvm.get_env().unwrap();
         let s = match new_string(&env, &url) {
@@ -442,8 +545,8 @@ impl HostTrait for HostCallbacks {
         let allow = env.call_method(
             self.callbacks.as_obj(),
             "onAllowNavigation",
-            "(Ljava/lang/String;)Z",
-            &[s],
+            "(ILjava/lang/String;)Z",
+            &[JValue::Int(browser as jint), s],
         );
         match allow {
             Ok(allow) => return allow.z().unwrap(),
@@ -451,7 +554,7 @@ impl HostTrait for HostCallbacks {
         }
     }
 
-    fn on_url_changed(&self, url: String) {
+    fn on_url_changed(&self, browser: BrowserHandle, url: String) {
         debug!("on_url_changed");
         let env = self.jvm.get_env().unwrap();
         let s = match new_string(&env, &url) {
@@ -462,22 +565,49 @@ impl HostTrait for HostCallbacks {
         env.call_method(
             self.callbacks.as_obj(),
             "onUrlChanged",
-            "(Ljava/lang/String;)V",
-            &[s],
+            "(ILjava/lang/String;)V",
+            &[JValue::Int(browser as jint), s],
         )
         .unwrap();
     }
 
-    fn on_history_changed(&self, can_go_back: bool, can_go_forward: bool) {
+    fn on_history_changed(&self, browser: BrowserHandle, can_go_back: bool, can_go_forward: bool) {
         debug!("on_history_changed");
         let env = self.jvm.get_env().unwrap();
+        let browser = JValue::Int(browser as jint);
         let can_go_back = JValue::Bool(can_go_back as jboolean);
         let can_go_forward = JValue::Bool(can_go_forward as jboolean);
         env.call_method(
             self.callbacks.as_obj(),
             "onHistoryChanged",
-            "(ZZ)V",
-            &[can_go_back, can_go_forward],
+            "(IZZ)V",
+            &[browser, can_go_back, can_go_forward],
+        )
+        .unwrap();
+    }
+
+    fn on_browser_created(&self, browser: BrowserHandle) {
+        debug!("on_browser_created");
+        let env = self.jvm.get_env().unwrap();
+        let browser = JValue::Int(browser as jint);
+        env.call_method(
+            self.callbacks.as_obj(),
+            "onBrowserCreated",
+            "(I)V",
+            &[browser],
+        )
+        .unwrap();
+    }
+
+    fn on_browser_closed(&self, browser: BrowserHandle) {
+        debug!("on_browser_closed");
+        let env = self.jvm.get_env().unwrap();
+        let browser = JValue::Int(browser as jint);
+        env.call_method(
+            self.callbacks.as_obj(),
+            "onBrowserClosed",
+            "(I)V",
+            &[browser],
         )
         .unwrap();
     }
@@ -495,7 +625,7 @@ impl HostTrait for HostCallbacks {
         .unwrap();
     }
 
-    fn on_ime_state_changed(&self, _show: bool) {}
+    fn on_ime_state_changed(&self, _browser: BrowserHandle, _show: bool) {}
 
     fn get_clipboard_contents(&self) -> Option<String> {
         None
@@ -772,6 +902,12 @@ fn get_options(env: &JNIEnv, opts: JObject) -> Result<(InitOptions, bool, Option
         url,
         coordinates,
         density,
+        // Android devices are driven by touchscreens.
+        device_features: DeviceFeatures {
+            primary_pointer: PointerCapabilities::COARSE,
+            all_pointers: PointerCapabilities::COARSE,
+            ..DeviceFeatures::default()
+        },
         enable_subpixel_text_antialiasing,
         vr_init: if vr_pointer.is_null() {
             VRInitOptions::None
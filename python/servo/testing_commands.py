@@ -249,6 +249,7 @@ class MachCommands(CommandBase):
 
         self_contained_tests = [
             "background_hang_monitor",
+            "constellation",
             "gfx",
             "layout_2013",
             "msg",
@@ -257,6 +258,7 @@ class MachCommands(CommandBase):
             "selectors",
             "servo_config",
             "servo_remutex",
+            "servo_svg",
         ]
         if not packages:
             packages = set(os.listdir(path.join(self.context.topdir, "tests", "unit"))) - set(['.DS_Store'])
@@ -0,0 +1,309 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+// Runs in the privileged debugger global of a script thread, on top of
+// SpiderMonkey's Debugger API. The script thread talks to it through
+// `addDebuggee`, `removeDebuggee` and `handleRequest`; `servoPause` is
+// provided by the script thread and blocks until the client resumes.
+
+"use strict";
+
+const dbg = new Debugger();
+
+// The debuggee globals, mapped to the (JSON-serialized) id of their pipeline.
+const debuggees = new Map();
+
+// Every source seen so far, indexed by the id given to the client.
+const sources = [];
+const sourceIds = new Map();
+
+// The breakpoints set by the client, by source id and line.
+const breakpoints = new Map();
+
+// The youngest frame while paused, and the frames observed while stepping.
+let pausedFrame = null;
+let steppingFrames = [];
+
+function breakpointKey(sourceId, line) {
+  return sourceId + ":" + line;
+}
+
+function pipelineOf(script) {
+  const global = script.global;
+  return global ? debuggees.get(global) : undefined;
+}
+
+function registerSource(script) {
+  const source = script.source;
+  if (sourceIds.has(source)) {
+    return sourceIds.get(source);
+  }
+  const id = sources.length;
+  sources.push({ source, pipeline: pipelineOf(script) });
+  sourceIds.set(source, id);
+  return id;
+}
+
+function describeSource(id) {
+  const source = sources[id].source;
+  return {
+    id,
+    url: source.url || null,
+    introduction_type: source.introductionType || null,
+  };
+}
+
+function lineOf(frame) {
+  return frame.script.getOffsetLocation(frame.offset).lineNumber;
+}
+
+function describeFrame(frame, depth) {
+  const location = frame.script
+    ? frame.script.getOffsetLocation(frame.offset)
+    : { lineNumber: 0, columnNumber: 0 };
+  return {
+    depth,
+    kind: frame.type,
+    callee: frame.callee ? frame.callee.displayName || null : null,
+    source: frame.script ? registerSource(frame.script) : null,
+    line: location.lineNumber,
+    column: location.columnNumber,
+  };
+}
+
+// Describes a value from the debuggee the way `EvaluateJSReply` is serialized.
+// There are no object actors backed by debuggee objects yet, so objects are
+// described by their class instead of with a grip.
+function describeValue(value) {
+  switch (typeof value) {
+    case "undefined":
+      return "VoidValue";
+    case "boolean":
+      return { BooleanValue: value };
+    case "number":
+      return { NumberValue: value };
+    case "string":
+      return { StringValue: value };
+  }
+  if (value === null) {
+    return "NullValue";
+  }
+  if (value instanceof Debugger.Object) {
+    return { StringValue: "[object " + value.class + "]" };
+  }
+  return { StringValue: String(value) };
+}
+
+function clearStepping() {
+  for (const frame of steppingFrames) {
+    if (frame.live) {
+      frame.onStep = undefined;
+      frame.onPop = undefined;
+    }
+  }
+  steppingFrames = [];
+  dbg.onEnterFrame = undefined;
+}
+
+function pause(frame, why) {
+  const pipeline = frame.script ? pipelineOf(frame.script) : undefined;
+  if (pipeline === undefined || pausedFrame) {
+    return undefined;
+  }
+  clearStepping();
+  pausedFrame = frame;
+  const limit = servoPause(
+    pipeline,
+    JSON.stringify({ why, frame: describeFrame(frame, 0) }),
+  );
+  pausedFrame = null;
+  if (limit && frame.live) {
+    startStepping(frame, limit);
+  }
+  return undefined;
+}
+
+// Sets up the hooks for the resume limits of the remote debugging protocol:
+// "next" steps over calls, "step" steps into them and "finish" steps out of
+// the current frame.
+function startStepping(startFrame, limit) {
+  const startLine = lineOf(startFrame);
+
+  function onStep() {
+    if (this === startFrame && lineOf(this) === startLine) {
+      return undefined;
+    }
+    return pause(this, "resumeLimit");
+  }
+
+  function onPop() {
+    const older = this.older;
+    if (older && older.script) {
+      watch(older, onStep);
+    }
+    return undefined;
+  }
+
+  function watch(frame, stepHandler) {
+    frame.onStep = stepHandler;
+    frame.onPop = onPop;
+    steppingFrames.push(frame);
+  }
+
+  watch(startFrame, limit === "finish" ? undefined : onStep);
+  if (limit === "step") {
+    dbg.onEnterFrame = frame => pause(frame, "resumeLimit");
+  }
+}
+
+function setBreakpointOnScript(script, sourceId, line) {
+  let set = false;
+  for (const offset of script.getLineOffsets(line)) {
+    script.setBreakpoint(offset, {
+      hit: frame => pause(frame, "breakpoint"),
+      sourceId,
+      line,
+    });
+    set = true;
+  }
+  return set;
+}
+
+function scriptsOfSource(source) {
+  return dbg.findScripts().filter(script => script.source === source);
+}
+
+dbg.onNewScript = function (script) {
+  if (pipelineOf(script) === undefined) {
+    return;
+  }
+  const sourceId = registerSource(script);
+  const pending = [script];
+  while (pending.length) {
+    const current = pending.pop();
+    for (const breakpoint of breakpoints.values()) {
+      if (breakpoint.sourceId === sourceId) {
+        setBreakpointOnScript(current, sourceId, breakpoint.line);
+      }
+    }
+    pending.push(...current.getChildScripts());
+  }
+};
+
+dbg.onDebuggerStatement = frame => pause(frame, "debuggerStatement");
+
+function addDebuggee(global, pipeline) {
+  const debuggee = dbg.addDebuggee(global);
+  debuggees.set(debuggee, pipeline);
+  for (const script of dbg.findScripts({ global: debuggee })) {
+    registerSource(script);
+  }
+}
+
+function removeDebuggee(global) {
+  const debuggee = dbg.makeGlobalObjectReference(global);
+  debuggees.delete(debuggee);
+  dbg.removeDebuggee(debuggee);
+}
+
+// The largest number of lines after the requested one that are searched for
+// code to break on.
+const MAX_BREAKPOINT_SLIDE = 50;
+
+const requests = {
+  sources(pipeline) {
+    const ids = [];
+    sources.forEach(({ pipeline: owner }, id) => {
+      if (owner === pipeline) {
+        ids.push(describeSource(id));
+      }
+    });
+    return ids;
+  },
+
+  sourceText(id) {
+    const entry = sources[id];
+    return entry ? entry.source.text : null;
+  },
+
+  setBreakpoint(id, line) {
+    const entry = sources[id];
+    if (!entry) {
+      return null;
+    }
+    const scripts = scriptsOfSource(entry.source);
+    for (let actual = line; actual < line + MAX_BREAKPOINT_SLIDE; actual++) {
+      const key = breakpointKey(id, actual);
+      if (breakpoints.has(key)) {
+        return actual;
+      }
+      let set = false;
+      for (const script of scripts) {
+        set = setBreakpointOnScript(script, id, actual) || set;
+      }
+      if (set) {
+        breakpoints.set(key, { sourceId: id, line: actual });
+        return actual;
+      }
+    }
+    return null;
+  },
+
+  removeBreakpoint(id, line) {
+    const entry = sources[id];
+    if (!entry || !breakpoints.delete(breakpointKey(id, line))) {
+      return;
+    }
+    for (const script of scriptsOfSource(entry.source)) {
+      for (const offset of script.getLineOffsets(line)) {
+        for (const handler of script.getBreakpoints(offset)) {
+          if (handler.sourceId === id && handler.line === line) {
+            script.clearBreakpoint(handler, offset);
+          }
+        }
+      }
+    }
+  },
+
+  interrupt() {
+    if (!pausedFrame) {
+      dbg.onEnterFrame = frame => pause(frame, "interrupted");
+    }
+  },
+
+  frames() {
+    const frames = [];
+    for (let frame = pausedFrame; frame; frame = frame.older) {
+      frames.push(describeFrame(frame, frames.length));
+    }
+    return frames;
+  },
+
+  evaluate(depth, expression) {
+    let frame = pausedFrame;
+    for (let i = 0; frame && i < depth; i++) {
+      frame = frame.older;
+    }
+    if (!frame) {
+      return null;
+    }
+    const completion = frame.eval(expression);
+    if (!completion) {
+      return { threw: false, value: "VoidValue" };
+    }
+    if ("throw" in completion) {
+      return { threw: true, value: describeValue(completion.throw) };
+    }
+    return { threw: false, value: describeValue(completion.return) };
+  },
+};
+
+// Handles a request from the script thread. Both the request and its result
+// are JSON strings: the request is an array made of the name of the request
+// followed by its arguments.
+function handleRequest(request) {
+  const [name, ...args] = JSON.parse(request);
+  const result = requests[name](...args);
+  return JSON.stringify(result === undefined ? null : result);
+}
@@ -32,39 +32,45 @@ public class JNIServo {
 
     public native void resize(ServoCoordinates coords);
 
-    public native void reload();
+    public native int newBrowser(String uri);
 
-    public native void stop();
+    public native void selectBrowser(int browser);
+
+    public native void closeBrowser(int browser);
+
+    public native void reload(int browser);
+
+    public native void stop(int browser);
 
     public native void refresh();
 
-    public native void goBack();
+    public native void goBack(int browser);
 
-    public native void goForward();
+    public native void goForward(int browser);
 
-    public native void loadUri(String uri);
+    public native void loadUri(int browser, String uri);
 
-    public native void scrollStart(int dx, int dy, int x, int y);
+    public native void scrollStart(int browser, int dx, int dy, int x, int y);
 
-    public native void scroll(int dx, int dy, int x, int y);
+    public native void scroll(int browser, int dx, int dy, int x, int y);
 
-    public native void scrollEnd(int dx, int dy, int x, int y);
+    public native void scrollEnd(int browser, int dx, int dy, int x, int y);
 
-    public native void touchDown(float x, float y, int pointer_id);
+    public native void touchDown(int browser, float x, float y, int pointer_id);
 
-    public native void touchMove(float x, float y, int pointer_id);
+    public native void touchMove(int browser, float x, float y, int pointer_id);
 
-    public native void touchUp(float x, float y, int pointer_id);
+    public native void touchUp(int browser, float x, float y, int pointer_id);
 
-    public native void touchCancel(float x, float y, int pointer_id);
+    public native void touchCancel(int browser, float x, float y, int pointer_id);
 
-    public native void pinchZoomStart(float factor, int x, int y);
+    public native void pinchZoomStart(int browser, float factor, int x, int y);
 
-    public native void pinchZoom(float factor, int x, int y);
+    public native void pinchZoom(int browser, float factor, int x, int y);
 
-    public native void pinchZoomEnd(float factor, int x, int y);
+    public native void pinchZoomEnd(int browser, float factor, int x, int y);
 
-    public native void click(int x, int y);
+    public native void click(int browser, int x, int y);
 
     public static class ServoOptions {
       public String args;
@@ -93,19 +99,25 @@ public class JNIServo {
 
         void makeCurrent();
 
-        void onAlert(String message);
+        void onAlert(int browser, String message);
 
         void onAnimatingChanged(boolean animating);
 
-        void onLoadStarted();
+        void onLoadStarted(int browser);
+
+        void onLoadEnded(int browser);
+
+        void onTitleChanged(int browser, String title);
+
+        boolean onAllowNavigation(int browser, String url);
 
-        void onLoadEnded();
+        void onUrlChanged(int browser, String url);
 
-        void onTitleChanged(String title);
+        void onHistoryChanged(int browser, boolean canGoBack, boolean canGoForward);
 
-        void onUrlChanged(String url);
+        void onBrowserCreated(int browser);
 
-        void onHistoryChanged(boolean canGoBack, boolean canGoForward);
+        void onBrowserClosed(int browser);
 
         void onShutdownComplete();
     }
@@ -9,6 +9,7 @@ import android.app.Activity;
 import android.content.Context;
 import android.util.Log;
 
+import java.util.ArrayList;
 import java.util.concurrent.Callable;
 import java.util.concurrent.FutureTask;
 
@@ -24,6 +25,11 @@ public class Servo {
     private boolean mShutdownComplete;
     private boolean mSuspended;
     private Callbacks mServoCallbacks;
+    // The browser that is displayed, and that methods and Client
+    // callbacks refer to. -1 until the first browser is created.
+    private volatile int mBrowser = -1;
+    // Open browsers, in creation order. Only accessed from the GL thread.
+    private ArrayList<Integer> mBrowsers = new ArrayList<>();
 
     public Servo(
             ServoOptions options,
@@ -100,68 +106,99 @@ public class Servo {
         mRunCallback.inGLThread(() -> mJNI.refresh());
     }
 
+    // The created browser is selected once it exists, see
+    // Client.onBrowserCreated.
+    public void newBrowser(String uri) {
+        mRunCallback.inGLThread(() -> mJNI.newBrowser(uri));
+    }
+
+    public void selectBrowser(int browser) {
+        mBrowser = browser;
+        mRunCallback.inGLThread(() -> mJNI.selectBrowser(browser));
+    }
+
+    public void closeBrowser(int browser) {
+        mRunCallback.inGLThread(() -> {
+            mJNI.closeBrowser(browser);
+            removeBrowser(browser);
+        });
+    }
+
+    // Mirrors libsimpleservo: when the selected browser is closed, the
+    // most recently created remaining browser is selected.
+    private void removeBrowser(int browser) {
+        mBrowsers.remove(Integer.valueOf(browser));
+        if (browser == mBrowser) {
+            mBrowser = mBrowsers.isEmpty() ? -1 : mBrowsers.get(mBrowsers.size() - 1);
+        }
+    }
+
+    public int getSelectedBrowser() {
+        return mBrowser;
+    }
+
     public void reload() {
-        mRunCallback.inGLThread(() -> mJNI.reload());
+        mRunCallback.inGLThread(() -> mJNI.reload(mBrowser));
     }
 
     public void stop() {
-        mRunCallback.inGLThread(() -> mJNI.stop());
+        mRunCallback.inGLThread(() -> mJNI.stop(mBrowser));
     }
 
     public void goBack() {
-        mRunCallback.inGLThread(() -> mJNI.goBack());
+        mRunCallback.inGLThread(() -> mJNI.goBack(mBrowser));
     }
 
     public void goForward() {
-        mRunCallback.inGLThread(() -> mJNI.goForward());
+        mRunCallback.inGLThread(() -> mJNI.goForward(mBrowser));
     }
 
     public void loadUri(String uri) {
-        mRunCallback.inGLThread(() -> mJNI.loadUri(uri));
+        mRunCallback.inGLThread(() -> mJNI.loadUri(mBrowser, uri));
     }
 
     public void scrollStart(int dx, int dy, int x, int y) {
-        mRunCallback.inGLThread(() -> mJNI.scrollStart(dx, dy, x, y));
+        mRunCallback.inGLThread(() -> mJNI.scrollStart(mBrowser, dx, dy, x, y));
     }
 
     public void scroll(int dx, int dy, int x, int y) {
-        mRunCallback.inGLThread(() -> mJNI.scroll(dx, dy, x, y));
+        mRunCallback.inGLThread(() -> mJNI.scroll(mBrowser, dx, dy, x, y));
     }
 
     public void scrollEnd(int dx, int dy, int x, int y) {
-        mRunCallback.inGLThread(() -> mJNI.scrollEnd(dx, dy, x, y));
+        mRunCallback.inGLThread(() -> mJNI.scrollEnd(mBrowser, dx, dy, x, y));
     }
 
     public void touchDown(float x, float y, int pointerId) {
-        mRunCallback.inGLThread(() -> mJNI.touchDown(x, y, pointerId));
+        mRunCallback.inGLThread(() -> mJNI.touchDown(mBrowser, x, y, pointerId));
     }
 
     public void touchMove(float x, float y, int pointerId) {
-        mRunCallback.inGLThread(() -> mJNI.touchMove(x, y, pointerId));
+        mRunCallback.inGLThread(() -> mJNI.touchMove(mBrowser, x, y, pointerId));
     }
 
     public void touchUp(float x, float y, int pointerId) {
-        mRunCallback.inGLThread(() -> mJNI.touchUp(x, y, pointerId));
+        mRunCallback.inGLThread(() -> mJNI.touchUp(mBrowser, x, y, pointerId));
     }
 
     public void touchCancel(float x, float y, int pointerId) {
-        mRunCallback.inGLThread(() -> mJNI.touchCancel(x, y, pointerId));
+        mRunCallback.inGLThread(() -> mJNI.touchCancel(mBrowser, x, y, pointerId));
     }
 
     public void pinchZoomStart(float factor, int x, int y) {
-        mRunCallback.inGLThread(() -> mJNI.pinchZoomStart(factor, x, y));
+        mRunCallback.inGLThread(() -> mJNI.pinchZoomStart(mBrowser, factor, x, y));
     }
 
     public void pinchZoom(float factor, int x, int y) {
-        mRunCallback.inGLThread(() -> mJNI.pinchZoom(factor, x, y));
+        mRunCallback.inGLThread(() -> mJNI.pinchZoom(mBrowser, factor, x, y));
     }
 
     public void pinchZoomEnd(float factor, int x, int y) {
-        mRunCallback.inGLThread(() -> mJNI.pinchZoomEnd(factor, x, y));
+        mRunCallback.inGLThread(() -> mJNI.pinchZoomEnd(mBrowser, factor, x, y));
     }
 
     public void click(int x, int y) {
-        mRunCallback.inGLThread(() -> mJNI.click(x, y));
+        mRunCallback.inGLThread(() -> mJNI.click(mBrowser, x, y));
     }
 
     public void suspend(boolean suspended) {
@@ -184,6 +221,13 @@ public class Servo {
         void onHistoryChanged(boolean canGoBack, boolean canGoForward);
 
         void onRedrawing(boolean redrawing);
+
+        // Called for all browsers, including the ones opened by content.
+        // The created browser is now the selected one.
+        default void onBrowserCreated(int browser) {}
+
+        // A browser has been closed by content.
+        default void onBrowserClosed(int browser) {}
     }
 
     public interface RunCallback {
@@ -200,7 +244,7 @@ public class Servo {
         void makeCurrent();
     }
 
-    private class Callbacks implements JNIServo.Callbacks, Client {
+    private class Callbacks implements JNIServo.Callbacks {
 
         private GfxCallbacks mGfxCb;
         Client mClient;
@@ -230,8 +274,10 @@ public class Servo {
             mGfxCb.makeCurrent();
         }
 
-        public void onAlert(String message) {
-            mRunCallback.inUIThread(() -> mClient.onAlert(message));
+        public void onAlert(int browser, String message) {
+            if (browser == mBrowser) {
+                mRunCallback.inUIThread(() -> mClient.onAlert(message));
+            }
         }
 
         public void onShutdownComplete() {
@@ -242,28 +288,49 @@ public class Servo {
             mRunCallback.inGLThread(() -> mGfxCb.animationStateChanged(animating));
         }
 
-        public boolean onAllowNavigation(String url) {
-            return mClient.onAllowNavigation(url);
+        public boolean onAllowNavigation(int browser, String url) {
+            return browser != mBrowser || mClient.onAllowNavigation(url);
+        }
+
+        public void onLoadStarted(int browser) {
+            if (browser == mBrowser) {
+                mRunCallback.inUIThread(() -> mClient.onLoadStarted());
+            }
+        }
+
+        public void onLoadEnded(int browser) {
+            if (browser == mBrowser) {
+                mRunCallback.inUIThread(() -> mClient.onLoadEnded());
+            }
         }
 
-        public void onLoadStarted() {
-            mRunCallback.inUIThread(() -> mClient.onLoadStarted());
+        public void onTitleChanged(int browser, String title) {
+            if (browser == mBrowser) {
+                mRunCallback.inUIThread(() -> mClient.onTitleChanged(title));
+            }
         }
 
-        public void onLoadEnded() {
-            mRunCallback.inUIThread(() -> mClient.onLoadEnded());
+        public void onUrlChanged(int browser, String url) {
+            if (browser == mBrowser) {
+                mRunCallback.inUIThread(() -> mClient.onUrlChanged(url));
+            }
         }
 
-        public void onTitleChanged(String title) {
-            mRunCallback.inUIThread(() -> mClient.onTitleChanged(title));
+        public void onHistoryChanged(int browser, boolean canGoBack, boolean canGoForward) {
+            if (browser == mBrowser) {
+                mRunCallback.inUIThread(() -> mClient.onHistoryChanged(canGoBack, canGoForward));
+            }
         }
 
-        public void onUrlChanged(String url) {
-            mRunCallback.inUIThread(() -> mClient.onUrlChanged(url));
+        public void onBrowserCreated(int browser) {
+            mBrowsers.add(browser);
+            mBrowser = browser;
+            mRunCallback.inUIThread(() -> mClient.onBrowserCreated(browser));
         }
 
-        public void onHistoryChanged(boolean canGoBack, boolean canGoForward) {
-            mRunCallback.inUIThread(() -> mClient.onHistoryChanged(canGoBack, canGoForward));
+        public void onBrowserClosed(int browser) {
+            removeBrowser(browser);
+            mRunCallback.inUIThread(() -> mClient.onBrowserClosed(browser));
         }
 
         public void onRedrawing(boolean redrawing) {
@@ -3,26 +3,48 @@
 
 namespace winrt::servo {
 
-void on_load_started() { sServo->Delegate().OnServoLoadStarted(); }
+void on_load_started(uint32_t browser) {
+  if (sServo->IsSelected(browser)) {
+    sServo->Delegate().OnServoLoadStarted();
+  }
+}
 
-void on_load_ended() { sServo->Delegate().OnServoLoadEnded(); }
+void on_load_ended(uint32_t browser) {
+  if (sServo->IsSelected(browser)) {
+    sServo->Delegate().OnServoLoadEnded();
+  }
+}
 
-void on_history_changed(bool back, bool forward) {
-  sServo->Delegate().OnServoHistoryChanged(back, forward);
+void on_history_changed(uint32_t browser, bool back, bool forward) {
+  if (sServo->IsSelected(browser)) {
+    sServo->Delegate().OnServoHistoryChanged(back, forward);
+  }
 }
 
+void on_browser_created(uint32_t browser) {
+  sServo->OnBrowserCreated(browser);
+}
+
+void on_browser_closed(uint32_t browser) { sServo->OnBrowserClosed(browser); }
+
 void on_shutdown_complete() { sServo->Delegate().OnServoShutdownComplete(); }
 
-void on_alert(const char *message) {
-  sServo->Delegate().OnServoAlert(char2hstring(message));
+void on_alert(uint32_t browser, const char *message) {
+  if (sServo->IsSelected(browser)) {
+    sServo->Delegate().OnServoAlert(char2hstring(message));
+  }
 }
 
-void on_title_changed(const char *title) {
-  sServo->Delegate().OnServoTitleChanged(char2hstring(title));
+void on_title_changed(uint32_t browser, const char *title) {
+  if (sServo->IsSelected(browser)) {
+    sServo->Delegate().OnServoTitleChanged(char2hstring(title));
+  }
 }
 
-void on_url_changed(const char *url) {
-  sServo->Delegate().OnServoURLChanged(char2hstring(url));
+void on_url_changed(uint32_t browser, const char *url) {
+  if (sServo->IsSelected(browser)) {
+    sServo->Delegate().OnServoURLChanged(char2hstring(url));
+  }
 }
 
 void flush() { sServo->Delegate().Flush(); }
@@ -31,8 +53,9 @@ void make_current() { sServo->Delegate().MakeCurrent(); }
 
 void wakeup() { sServo->Delegate().WakeUp(); }
 
-bool on_allow_navigation(const char *url) {
-  return sServo->Delegate().OnServoAllowNavigation(char2hstring(url));
+bool on_allow_navigation(uint32_t browser, const char *url) {
+  return !sServo->IsSelected(browser) ||
+         sServo->Delegate().OnServoAllowNavigation(char2hstring(url));
 };
 
 void on_animating_changed(bool aAnimating) {
@@ -43,8 +66,10 @@ void on_panic(const char *backtrace) {
   throw hresult_error(E_FAIL, char2hstring(backtrace));
 }
 
-void on_ime_state_changed(bool aShow) {
-  sServo->Delegate().OnServoIMEStateChanged(aShow);
+void on_ime_state_changed(uint32_t browser, bool aShow) {
+  if (sServo->IsSelected(browser)) {
+    sServo->Delegate().OnServoIMEStateChanged(aShow);
+  }
 }
 
 void set_clipboard_contents(const char* content) {
@@ -102,6 +127,8 @@ Servo::Servo(hstring url, GLsizei width, GLsizei height, float dpi,
   c.on_title_changed = &on_title_changed;
   c.on_url_changed = &on_url_changed;
   c.on_history_changed = &on_history_changed;
+  c.on_browser_created = &on_browser_created;
+  c.on_browser_closed = &on_browser_closed;
   c.on_animating_changed = &on_animating_changed;
   c.on_shutdown_complete = &on_shutdown_complete;
   c.on_allow_navigation = &on_allow_navigation;
@@ -7,6 +7,8 @@
 #include "pch.h"
 #include "logs.h"
 #include <stdlib.h>
+#include <algorithm>
+#include <vector>
 
 namespace winrt::servo {
 
@@ -51,14 +53,41 @@ public:
   void DeInit() { capi::deinit(); }
   void RequestShutdown() { capi::request_shutdown(); }
   void SetBatchMode(bool mode) { capi::set_batch_mode(mode); }
-  void GoForward() { capi::go_forward(); }
-  void GoBack() { capi::go_back(); }
-  void Click(float x, float y) { capi::click((int32_t)x, (int32_t)y); }
-  void Reload() { capi::reload(); }
-  void Stop() { capi::stop(); }
-  void LoadUri(hstring uri) { capi::load_uri(*hstring2char(uri)); }
+  void GoForward() { capi::go_forward(mBrowser); }
+  void GoBack() { capi::go_back(mBrowser); }
+  void Click(float x, float y) {
+    capi::click(mBrowser, (int32_t)x, (int32_t)y);
+  }
+  void Reload() { capi::reload(mBrowser); }
+  void Stop() { capi::stop(mBrowser); }
+  void LoadUri(hstring uri) { capi::load_uri(mBrowser, *hstring2char(uri)); }
   void Scroll(float dx, float dy, float x, float y) {
-    capi::scroll((int32_t)dx, (int32_t)dy, (int32_t)x, (int32_t)y);
+    capi::scroll(mBrowser, (int32_t)dx, (int32_t)dy, (int32_t)x, (int32_t)y);
+  }
+  // The new browser gets selected once created.
+  void NewBrowser(hstring uri) { capi::new_browser(*hstring2char(uri)); }
+  void SelectBrowser(uint32_t browser) {
+    mBrowser = browser;
+    capi::select_browser(browser);
+  }
+  void CloseBrowser(uint32_t browser) {
+    capi::close_browser(browser);
+    OnBrowserClosed(browser);
+  }
+  // Only events of the selected browser reach the delegate.
+  bool IsSelected(uint32_t browser) { return browser == mBrowser; }
+  void OnBrowserCreated(uint32_t browser) {
+    mBrowsers.push_back(browser);
+    mBrowser = browser;
+  }
+  // Like libsimpleservo, select the most recently created remaining
+  // browser if the selected one got closed.
+  void OnBrowserClosed(uint32_t browser) {
+    mBrowsers.erase(std::remove(mBrowsers.begin(), mBrowsers.end(), browser),
+                    mBrowsers.end());
+    if (browser == mBrowser && !mBrowsers.empty()) {
+      mBrowser = mBrowsers.back();
+    }
   }
   void SetSize(GLsizei width, GLsizei height) {
     if (width != mWindowWidth || height != mWindowHeight) {
@@ -72,6 +101,8 @@ private:
   ServoDelegate &mDelegate;
   GLsizei mWindowWidth;
   GLsizei mWindowHeight;
+  uint32_t mBrowser = 0;
+  std::vector<uint32_t> mBrowsers;
 };
 
 // This is sad. We need a static pointer to Servo because we use function
@@ -0,0 +1,146 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use profile::chrome_trace::ChromeTrace;
+use profile_traits::time::{ProfilerCategory, ProfilerThread, TimerMetadata};
+use profile_traits::time::{TimerMetadataFrameType, TimerMetadataReflowType};
+use serde_json::Value;
+use std::{env, fs, process};
+
+fn thread(thread_id: u64, name: &str) -> ProfilerThread {
+    ProfilerThread {
+        process_id: process::id(),
+        thread_id: thread_id,
+        name: name.to_owned(),
+    }
+}
+
+/// Write a trace through `write`, and return its events once the trace has been closed.
+fn trace_events<F: FnOnce(&mut ChromeTrace)>(name: &str, write: F) -> Vec<Value> {
+    let path = env::temp_dir().join(format!("servo-{}-{}.json", name, process::id()));
+    write(&mut ChromeTrace::new(&path).unwrap());
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let trace: Value = serde_json::from_str(&contents).expect("The trace is valid JSON");
+    assert_eq!(trace["displayTimeUnit"], "ms");
+    trace["traceEvents"].as_array().unwrap().clone()
+}
+
+/// The start and duration of a span, in microseconds.
+fn span_times(span: &Value) -> (f64, f64) {
+    (span["ts"].as_f64().unwrap(), span["dur"].as_f64().unwrap())
+}
+
+fn events_with_phase<'a>(events: &'a [Value], phase: &str) -> Vec<&'a Value> {
+    events.iter().filter(|event| event["ph"] == phase).collect()
+}
+
+#[test]
+fn test_empty_trace() {
+    let events = trace_events("empty", |_| {});
+    assert!(events.is_empty());
+}
+
+#[test]
+fn test_metadata_events() {
+    let main = thread(1, "Main");
+    let events = trace_events("metadata", |trace| {
+        trace.write_one(&(ProfilerCategory::LayoutPerform, None), (0, 1000), &main);
+        trace.write_one(&(ProfilerCategory::LayoutMain, None), (2000, 3000), &main);
+        // A different thread with the same name gets its own track.
+        trace.write_one(
+            &(ProfilerCategory::LayoutPerform, None),
+            (0, 1000),
+            &thread(2, "Main"),
+        );
+    });
+
+    let metadata = events_with_phase(&events, "M");
+    assert_eq!(metadata.len(), 3);
+    assert_eq!(metadata[0]["name"], "process_name");
+    assert_eq!(metadata[0]["pid"], process::id());
+    assert_eq!(metadata[0]["args"]["name"], "Servo");
+    assert_eq!(metadata[1]["name"], "thread_name");
+    assert_eq!(metadata[1]["tid"], 1);
+    assert_eq!(metadata[1]["args"]["name"], "Main");
+    assert_eq!(metadata[2]["name"], "thread_name");
+    assert_eq!(metadata[2]["tid"], 2);
+    assert_eq!(metadata[2]["args"]["name"], "Main");
+
+    let spans = events_with_phase(&events, "X");
+    let tids: Vec<_> = spans.iter().map(|span| span["tid"].clone()).collect();
+    assert_eq!(tids, [1, 1, 2]);
+}
+
+#[test]
+fn test_counter_events() {
+    let events = trace_events("counter", |trace| {
+        let values = vec![("resident".to_owned(), 2048), ("explicit".to_owned(), 1024)];
+        trace.write_counter("Memory", 5000, &values);
+    });
+
+    assert_eq!(events_with_phase(&events, "M").len(), 1);
+    let counters = events_with_phase(&events, "C");
+    assert_eq!(counters.len(), 1);
+    assert_eq!(counters[0]["name"], "Memory");
+    assert_eq!(counters[0]["cat"], "memory");
+    assert_eq!(counters[0]["ts"], 5.);
+    assert_eq!(counters[0]["tid"], 0);
+    assert_eq!(counters[0]["args"]["resident"], 2048);
+    assert_eq!(counters[0]["args"]["explicit"], 1024);
+}
+
+#[test]
+fn test_nested_categories() {
+    let main = thread(1, "Main");
+    let meta = TimerMetadata {
+        url: "https://example.com/".to_owned(),
+        iframe: TimerMetadataFrameType::RootWindow,
+        incremental: TimerMetadataReflowType::Incremental,
+    };
+    let events = trace_events("nested", |trace| {
+        // Inner timings are reported before the ones they are nested in.
+        trace.write_one(
+            &(ProfilerCategory::LayoutStyleRecalc, None),
+            (2000, 5000),
+            &main,
+        );
+        trace.write_one(
+            &(ProfilerCategory::LayoutPerform, Some(meta)),
+            (1000, 8000),
+            &main,
+        );
+        trace.write_one(
+            &(ProfilerCategory::ScriptEvaluate, None),
+            (9000, 9500),
+            &main,
+        );
+    });
+
+    let spans = events_with_phase(&events, "X");
+    assert_eq!(spans.len(), 3);
+    let (inner, outer, script) = (spans[0], spans[1], spans[2]);
+
+    assert_eq!(inner["cat"], "layout");
+    assert_eq!(span_times(inner), (2., 3.));
+    assert!(inner.get("args").is_none());
+
+    assert_eq!(outer["cat"], "layout");
+    assert_eq!(span_times(outer), (1., 7.));
+    assert_eq!(outer["args"]["url"], "https://example.com/");
+    assert_eq!(outer["args"]["iframe"], false);
+    assert_eq!(outer["args"]["incremental"], true);
+
+    // The outer span encloses the inner one on the same track.
+    let end = |span| {
+        let (start, duration) = span_times(span);
+        start + duration
+    };
+    assert_eq!(inner["tid"], outer["tid"]);
+    assert!(span_times(outer).0 <= span_times(inner).0);
+    assert!(end(outer) >= end(inner));
+
+    assert_eq!(script["cat"], "script");
+    assert_eq!(script["dur"], 0.5);
+}
@@ -4,4 +4,5 @@
 
 #![cfg(test)]
 
+mod chrome_trace;
 mod time;
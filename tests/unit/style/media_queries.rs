@@ -0,0 +1,120 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at https://mozilla.org/MPL/2.0/. */
+
+use cssparser::{Parser, ParserInput};
+use euclid::{Scale, Size2D};
+use servo_url::ServoUrl;
+use style::context::QuirksMode;
+use style::media_queries::{Device, MediaList, MediaType};
+use style::parser::ParserContext;
+use style::stylesheets::{CssRuleType, Origin};
+use style_traits::media_features::{ColorSchemePreference, DeviceFeatures, PointerCapabilities};
+use style_traits::ParsingMode;
+
+fn device(width: f32, height: f32, dppx: f32, features: DeviceFeatures) -> Device {
+    Device::new(
+        MediaType::screen(),
+        Size2D::new(width, height),
+        Scale::new(dppx),
+        features,
+    )
+}
+
+fn matches(css: &str, device: &Device) -> bool {
+    let url = ServoUrl::parse("http://localhost").unwrap();
+    let context = ParserContext::new(
+        Origin::Author,
+        &url,
+        Some(CssRuleType::Media),
+        ParsingMode::DEFAULT,
+        QuirksMode::NoQuirks,
+        None,
+        None,
+    );
+    let mut input = ParserInput::new(css);
+    let media_list = MediaList::parse(&context, &mut Parser::new(&mut input));
+    media_list.evaluate(device, QuirksMode::NoQuirks)
+}
+
+#[test]
+fn test_size_features() {
+    let device = device(800., 600., 1.0, DeviceFeatures::default());
+    assert!(matches("(min-height: 500px)", &device));
+    assert!(!matches("(max-height: 500px)", &device));
+    assert!(matches("(orientation: landscape)", &device));
+    assert!(!matches("(orientation: portrait)", &device));
+    assert!(matches("(aspect-ratio: 4/3)", &device));
+    assert!(matches("(min-aspect-ratio: 1/1)", &device));
+
+    let square = device(600., 600., 1.0, DeviceFeatures::default());
+    assert!(matches("(orientation: portrait)", &square));
+}
+
+#[test]
+fn test_resolution_features() {
+    let device = device(800., 600., 2.0, DeviceFeatures::default());
+    assert!(matches("(resolution: 2dppx)", &device));
+    assert!(matches("(min-resolution: 192dpi)", &device));
+    assert!(!matches("(max-resolution: 1dppx)", &device));
+    assert!(matches("(-webkit-min-device-pixel-ratio: 1.5)", &device));
+    assert!(!matches("(-webkit-max-device-pixel-ratio: 1.5)", &device));
+    // Only the prefixed form is supported.
+    assert!(!matches("(min-device-pixel-ratio: 1.5)", &device));
+}
+
+#[test]
+fn test_user_preference_features() {
+    let default = device(800., 600., 1.0, DeviceFeatures::default());
+    assert!(matches("(prefers-color-scheme: no-preference)", &default));
+    assert!(!matches("(prefers-color-scheme)", &default));
+    assert!(matches("(prefers-reduced-motion: no-preference)", &default));
+    assert!(!matches("(prefers-reduced-motion)", &default));
+
+    let device = device(
+        800.,
+        600.,
+        1.0,
+        DeviceFeatures {
+            prefers_color_scheme: ColorSchemePreference::Dark,
+            prefers_reduced_motion: true,
+            ..DeviceFeatures::default()
+        },
+    );
+    assert!(matches("(prefers-color-scheme: dark)", &device));
+    assert!(!matches("(prefers-color-scheme: light)", &device));
+    assert!(matches("(prefers-color-scheme)", &device));
+    assert!(matches("(prefers-reduced-motion: reduce)", &device));
+}
+
+#[test]
+fn test_interaction_features() {
+    let desktop = device(800., 600., 1.0, DeviceFeatures::default());
+    assert!(matches("(hover: hover)", &desktop));
+    assert!(matches("(pointer: fine)", &desktop));
+    assert!(!matches("(pointer: coarse)", &desktop));
+
+    let touchscreen = device(
+        800.,
+        600.,
+        1.0,
+        DeviceFeatures {
+            primary_pointer: PointerCapabilities::COARSE,
+            all_pointers: PointerCapabilities::COARSE | PointerCapabilities::FINE,
+            ..DeviceFeatures::default()
+        },
+    );
+    assert!(matches("(hover: none)", &touchscreen));
+    assert!(matches("(pointer: coarse)", &touchscreen));
+    assert!(!matches("(pointer: fine)", &touchscreen));
+    assert!(matches("(any-pointer: fine)", &touchscreen));
+    assert!(matches("(any-hover: none)", &touchscreen));
+}
+
+#[test]
+fn test_color_features() {
+    let device = device(800., 600., 1.0, DeviceFeatures::default());
+    assert!(matches("(color)", &device));
+    assert!(matches("(min-color: 8)", &device));
+    assert!(!matches("(min-color: 10)", &device));
+}
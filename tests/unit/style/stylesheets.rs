@@ -10,7 +10,7 @@ use std::cell::RefCell;
 use style::context::QuirksMode;
 use style::error_reporting::{ContextualParseError, ParseErrorReporter};
 use style::media_queries::MediaList;
-use style::shared_lock::SharedRwLock;
+use style::shared_lock::{SharedRwLock, ToCssWithGuard};
 use style::stylesheets::Origin;
 use style::stylesheets::Stylesheet;
 
@@ -234,3 +234,39 @@ fn test_source_url() {
         assert_eq!(*url_opt, test.1);
     }
 }
+
+#[test]
+fn test_update_from_str() {
+    let url = ServoUrl::parse("about::test").unwrap();
+    let lock = SharedRwLock::new();
+    let media = Arc::new(lock.wrap(MediaList::empty()));
+    let stylesheet = Stylesheet::from_str(
+        "a { color: red; } b { color: blue; }",
+        url.clone(),
+        Origin::Author,
+        media,
+        lock,
+        None,
+        None,
+        QuirksMode::NoQuirks,
+        0,
+    );
+
+    Stylesheet::update_from_str(
+        &stylesheet,
+        "/*# sourceURL=edited */ p { color: green; }",
+        url,
+        None,
+        None,
+        0,
+    );
+
+    let guard = stylesheet.shared_lock.read();
+    let rules = stylesheet.contents.rules(&guard);
+    assert_eq!(rules.len(), 1);
+    assert_eq!(rules[0].to_css_string(&guard), "p { color: green; }");
+    assert_eq!(
+        *stylesheet.contents.source_url.read(),
+        Some("edited".to_string())
+    );
+}
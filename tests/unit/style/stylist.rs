@@ -19,6 +19,7 @@ use style::stylesheets::StyleRule;
 use style::stylist::needs_revalidation_for_testing;
 use style::stylist::{Rule, Stylist};
 use style::thread_state::{self, ThreadState};
+use style_traits::media_features::DeviceFeatures;
 
 /// Helper method to get some Rules from selector strings.
 /// Each sublist of the result contains the Rules for one StyleRule.
@@ -216,6 +217,7 @@ fn mock_stylist() -> Stylist {
         MediaType::screen(),
         Size2D::new(0f32, 0f32),
         Scale::new(1.0),
+        DeviceFeatures::default(),
     );
     Stylist::new(device, QuirksMode::NoQuirks)
 }
@@ -19,6 +19,7 @@ use style::values::generics::NonNegative;
 use style::values::specified::LengthPercentage;
 use style::values::specified::NoCalcLength::{self, ViewportPercentage};
 use style::values::specified::ViewportPercentageLength::Vw;
+use style_traits::media_features::DeviceFeatures;
 use style_traits::viewport::*;
 use style_traits::{ParsingMode, PinchZoomFactor};
 
@@ -115,6 +116,7 @@ fn empty_viewport_rule() {
         MediaType::screen(),
         Size2D::new(800., 600.),
         Scale::new(1.0),
+        DeviceFeatures::default(),
     );
 
     test_viewport_rule("@viewport {}", &device, |declarations, css| {
@@ -142,6 +144,7 @@ fn simple_viewport_rules() {
         MediaType::screen(),
         Size2D::new(800., 600.),
         Scale::new(1.0),
+        DeviceFeatures::default(),
     );
 
     test_viewport_rule(
@@ -310,6 +313,7 @@ fn cascading_within_viewport_rule() {
         MediaType::screen(),
         Size2D::new(800., 600.),
         Scale::new(1.0),
+        DeviceFeatures::default(),
     );
 
     // normal order of appearance
@@ -450,6 +454,7 @@ fn multiple_stylesheets_cascading() {
         MediaType::screen(),
         Size2D::new(800., 600.),
         Scale::new(1.0),
+        DeviceFeatures::default(),
     );
     let shared_lock = SharedRwLock::new();
     let stylesheets = vec![
@@ -539,7 +544,12 @@ fn constrain_viewport() {
     }
 
     let initial_viewport = Size2D::new(800., 600.);
-    let device = Device::new(MediaType::screen(), initial_viewport, Scale::new(1.0));
+    let device = Device::new(
+        MediaType::screen(),
+        initial_viewport,
+        Scale::new(1.0),
+        DeviceFeatures::default(),
+    );
     let mut input = ParserInput::new("");
     assert_eq!(
         ViewportConstraints::maybe_new(&device, from_css!(input), QuirksMode::NoQuirks),
@@ -597,7 +607,12 @@ fn constrain_viewport() {
     );
 
     let initial_viewport = Size2D::new(200., 150.);
-    let device = Device::new(MediaType::screen(), initial_viewport, Scale::new(1.0));
+    let device = Device::new(
+        MediaType::screen(),
+        initial_viewport,
+        Scale::new(1.0),
+        DeviceFeatures::default(),
+    );
     let mut input = ParserInput::new("width: 320px auto");
     assert_eq!(
         ViewportConstraints::maybe_new(&device, from_css!(input), QuirksMode::NoQuirks),
@@ -18,6 +18,7 @@ test_interfaces([
   "DOMQuad",
   "DOMRect",
   "DOMRectReadOnly",
+  "DOMStringList",
   "CustomEvent",
   "DedicatedWorkerGlobalScope",
   "DOMException",
@@ -32,8 +33,22 @@ test_interfaces([
   "FormData",
   "Headers",
   "History",
+  "IDBCursor",
+  "IDBCursorWithValue",
+  "IDBDatabase",
+  "IDBFactory",
+  "IDBIndex",
+  "IDBKeyRange",
+  "IDBObjectStore",
+  "IDBOpenDBRequest",
+  "IDBRequest",
+  "IDBTransaction",
+  "IDBVersionChangeEvent",
   "ImageData",
+  "MessageChannel",
   "MessageEvent",
+  "MessagePort",
+  "Path2D",
   "Performance",
   "PerformanceEntry",
   "PerformanceMark",
@@ -46,8 +61,10 @@ test_interfaces([
   "PromiseRejectionEvent",
   "Request",
   "Response",
+  "SecurityPolicyViolationEvent",
   "TextDecoder",
   "TextEncoder",
+  "TextMetrics",
   "URL",
   "URLSearchParams",
   "WebSocket",